@@ -0,0 +1,230 @@
+//! Access log of the HTTP requests
+//! Each request is logged as a single line of key=value pairs, either in the main log (target "access") or in a separate file
+
+// System modules:
+use std::fs::{File, OpenOptions};
+use std::io::Write;
+use std::sync::Mutex;
+
+// External modules:
+use iron::prelude::{Request, IronResult, Response};
+use iron::headers::ContentLength;
+use time::{precise_time_ns, strftime, now};
+
+// Internal modules:
+use configuration::Configuration;
+
+/// Log target of the access log lines in the main log
+pub const ACCESS_LOG_TARGET: &'static str = "access";
+
+/// Everything that is logged about one request
+#[derive(Debug, Clone, PartialEq)]
+pub struct AccessLogEntry {
+    pub method: String,
+    pub path: String,
+    pub query: Option<String>,
+    /// HTTP status code of the response, 500 if the handler returned an error
+    pub status: u16,
+    /// Size of the response body in bytes, None if it's not known
+    pub size: Option<u64>,
+    pub remote_addr: String,
+    /// Time to handle the request in milliseconds, including waiting for the lock of the SLURM status
+    pub duration_ms: f64
+}
+
+impl AccessLogEntry {
+    /// Create the entry for the result of a handler, start is the time when the handler was called (from precise_time_ns)
+    pub fn new(method: String, path: String, query: Option<String>, remote_addr: String, result: &IronResult<Response>, start: u64) -> AccessLogEntry {
+        let (status, size) = match *result {
+            Ok(ref res) => (res.status.map_or(200, |status| status.to_u16()), res.headers.get::<ContentLength>().map(|length| length.0)),
+            Err(_) => (500, None)
+        };
+
+        AccessLogEntry {
+            method: method,
+            path: path,
+            query: query,
+            status: status,
+            size: size,
+            remote_addr: remote_addr,
+            duration_ms: (precise_time_ns() - start) as f64 / 1000000.0
+        }
+    }
+
+    /// Returns the entry as a single line, missing values are written as "-"
+    /// Example: method=GET path=/api/status query=user=willi status=200 size=1234 remote=10.0.0.1:51234 duration_ms=0.412
+    pub fn to_line(&self) -> String {
+        format!("method={} path={} query={} status={} size={} remote={} duration_ms={:.3}",
+            self.method,
+            self.path,
+            self.query.as_ref().map_or("-", |query| &query[..]),
+            self.status,
+            self.size.map_or("-".to_string(), |size| size.to_string()),
+            self.remote_addr,
+            self.duration_ms)
+    }
+}
+
+/// Writes the access log entries to the configured destination
+pub struct AccessLog {
+    /// Access log file, None logs to the main log
+    file: Option<Mutex<File>>
+}
+
+impl AccessLog {
+    /// Create an access log that writes to the main log
+    pub fn new() -> AccessLog {
+        AccessLog { file: None }
+    }
+
+    /// Create the access log from the configuration, returns None if the access log is disabled
+    /// If the access log file can't be opened, the main log is used instead
+    pub fn from_config(config: &Configuration) -> Option<AccessLog> {
+        if !config.access_log {
+            return None;
+        }
+
+        match config.access_log_file {
+            Some(ref path) => match OpenOptions::new().create(true).append(true).open(path) {
+                Ok(file) => Some(AccessLog { file: Some(Mutex::new(file)) }),
+                Err(err) => {
+                    warn!("Could not open access log file '{}': {}, using the main log instead", path, err);
+                    Some(AccessLog::new())
+                }
+            },
+            None => Some(AccessLog::new())
+        }
+    }
+
+    /// Write one entry, lines in the access log file start with the current time
+    pub fn write(&self, entry: &AccessLogEntry) {
+        match self.file {
+            Some(ref file) => {
+                let line = format!("{} {}\n", strftime("%Y.%m.%d - %H:%M:%S", &now()).unwrap(), entry.to_line());
+                match file.lock() {
+                    Ok(mut file) => {
+                        if let Err(err) = file.write_all(line.as_bytes()) {
+                            error!("Could not write access log: {}", err);
+                        }
+                    },
+                    Err(err) => error!("Could not lock Mutex: {}", err)
+                }
+            },
+            None => info!(target: ACCESS_LOG_TARGET, "{}", entry.to_line())
+        }
+    }
+}
+
+/// Public function that calls the handler and logs the request, the time includes the whole handler
+pub fn log_request<F>(req: &mut Request, access_log: &AccessLog, handler: F) -> IronResult<Response>
+        where F: FnOnce(&mut Request) -> IronResult<Response> {
+    let start = precise_time_ns();
+    let method = format!("{}", req.method);
+    let path = format!("/{}", req.url.path.join("/"));
+    let query = req.url.query.clone();
+    let remote_addr = format!("{}", req.remote_addr);
+
+    let result = handler(req);
+    access_log.write(&AccessLogEntry::new(method, path, query, remote_addr, &result, start));
+
+    result
+}
+
+#[test]
+fn test_access_log_entry_to_line() {
+    let entry = AccessLogEntry {
+        method: "GET".to_string(),
+        path: "/api/status".to_string(),
+        query: Some("user=willi".to_string()),
+        status: 200,
+        size: Some(1234),
+        remote_addr: "10.0.0.1:51234".to_string(),
+        duration_ms: 0.4123
+    };
+    assert_eq!(entry.to_line(), "method=GET path=/api/status query=user=willi status=200 size=1234 remote=10.0.0.1:51234 duration_ms=0.412");
+
+    let entry = AccessLogEntry { query: None, size: None, status: 503, .. entry };
+    assert_eq!(entry.to_line(), "method=GET path=/api/status query=- status=503 size=- remote=10.0.0.1:51234 duration_ms=0.412");
+}
+
+#[test]
+fn test_access_log_from_config() {
+    assert!(AccessLog::from_config(&Configuration::default()).is_none());
+    assert!(AccessLog::from_config(&Configuration{ access_log: true, .. Configuration::default() }).unwrap().file.is_none());
+
+    // The directory does not exist, so the main log is used
+    let config = Configuration{ access_log: true, access_log_file: Some("/this/path/does/not/exist/access.log".to_string()), .. Configuration::default() };
+    assert!(AccessLog::from_config(&config).unwrap().file.is_none());
+}
+
+#[test]
+fn test_access_log_file() {
+    use std::env::temp_dir;
+    use std::io::Read;
+    use std::fs::remove_file;
+
+    let path = temp_dir().join(format!("slurm_inspector_access_{}.log", precise_time_ns()));
+    let config = Configuration{ access_log: true, access_log_file: Some(path.to_string_lossy().to_string()), .. Configuration::default() };
+    let access_log = AccessLog::from_config(&config).unwrap();
+    let entry = AccessLogEntry {
+        method: "GET".to_string(),
+        path: "/".to_string(),
+        query: None,
+        status: 304,
+        size: None,
+        remote_addr: "127.0.0.1:4000".to_string(),
+        duration_ms: 1.0
+    };
+
+    access_log.write(&entry);
+    access_log.write(&entry);
+    let mut content = String::new();
+    File::open(&path).unwrap().read_to_string(&mut content).unwrap();
+    remove_file(&path).unwrap();
+
+    assert_eq!(content.lines().count(), 2);
+    assert!(content.lines().all(|line| line.ends_with(" method=GET path=/ query=- status=304 size=- remote=127.0.0.1:4000 duration_ms=1.000")));
+}
+
+#[test]
+fn test_access_log_main_log() {
+    use std::sync::Arc;
+    use log::{set_logger, Log, LogRecord, LogMetadata, LogLevelFilter};
+    use iron::status;
+
+    // Collects the lines of the access log target
+    struct TestLogger {
+        lines: Arc<Mutex<Vec<String>>>
+    }
+
+    impl Log for TestLogger {
+        fn enabled(&self, metadata: &LogMetadata) -> bool {
+            metadata.target() == ACCESS_LOG_TARGET
+        }
+
+        fn log(&self, record: &LogRecord) {
+            if self.enabled(record.metadata()) {
+                self.lines.lock().unwrap().push(format!("{}", record.args()));
+            }
+        }
+    }
+
+    let lines = Arc::new(Mutex::new(Vec::new()));
+    let logger_lines = lines.clone();
+    set_logger(|max_log_level| {
+        max_log_level.set(LogLevelFilter::Info);
+        Box::new(TestLogger { lines: logger_lines })
+    }).unwrap();
+
+    let mut res = Response::new();
+    res.status = Some(status::ServiceUnavailable);
+    res.headers.set(ContentLength(42));
+    let entry = AccessLogEntry::new("GET".to_string(), "/health".to_string(), Some("probe=1".to_string()), "10.0.0.1:51234".to_string(),
+        &Ok(res), precise_time_ns());
+    AccessLog::new().write(&entry);
+
+    let lines = lines.lock().unwrap();
+    let prefix = "method=GET path=/health query=probe=1 status=503 size=42 remote=10.0.0.1:51234 duration_ms=";
+    let line = lines.iter().find(|line| line.starts_with(prefix)).unwrap();
+    assert!(line[prefix.len()..].parse::<f64>().unwrap() >= 0.0);
+}
@@ -0,0 +1,665 @@
+//! Admin actions that change the cluster from the web interface: cancelling a job with scancel, draining and resuming nodes with scontrol
+//! They are disabled unless --enable-admin-actions is given, and only run for a user that the reverse proxy in front has authenticated:
+//! the user name is taken from the header of --remote-user-header (X-Remote-User by default), which is only trusted with --trust-proxy
+//! A POST must come from a page of the inspector (Origin or Referer header) and carry the CSRF token of the user from the form of the
+//! confirmation page, so another site can't make the browser of an admin run an action. Every action is logged with the user, the action and the result
+
+// System modules:
+use std::collections::BTreeMap;
+use std::fmt;
+use std::sync::Mutex;
+
+// External modules:
+use rustc_serialize::json::{Json, ToJson};
+use rustc_serialize::hex::ToHex;
+use openssl::crypto::hash::Type;
+use openssl::crypto::hmac::hmac;
+use openssl::crypto::memcmp;
+use openssl::crypto::rand::rand_bytes;
+
+// Internal modules:
+use configuration::Configuration;
+use command_runner::{CommandConfig, CommandRunner, run_command};
+use sinfo_util::{PartitionNodeInfo, NodeState, DownReason};
+use slurm_status::PageOptions;
+use template::ADMIN_CONFIRM_TEMPLATE;
+
+/// Header with the name of the user the reverse proxy has authenticated, if --remote-user-header is not given
+pub const DEFAULT_REMOTE_USER_HEADER: &'static str = "X-Remote-User";
+
+/// Maximum length of the reason of a drain, longer reasons are refused
+pub const MAX_REASON_LENGTH: usize = 128;
+
+/// Name of the hidden field with the CSRF token in the form of the confirmation page
+pub const CSRF_TOKEN_FIELD: &'static str = "csrf_token";
+
+/// Length of the secret of the CSRF tokens in bytes
+const CSRF_SECRET_LENGTH: usize = 32;
+
+// The secret of the CSRF tokens, created at the first use. The tokens of the forms are valid until the inspector is restarted
+static CSRF_SECRET: Mutex<Vec<u8>> = Mutex::new(Vec::new());
+
+/// An action that can be triggered with a POST request to its route, a GET request shows the confirmation page
+#[derive(Debug, Clone, PartialEq)]
+pub enum AdminAction {
+    /// scancel <job id>, only for plain jobs of the local cluster
+    CancelJob(u32),
+    /// scontrol update NodeName=<node> State=DRAIN Reason=<reason>, the reason is a field of the form on the confirmation page
+    DrainNode { node: String, reason: String },
+    /// scontrol update NodeName=<node> State=RESUME
+    ResumeNode(String)
+}
+
+impl AdminAction {
+    /// Route of the action below the URL prefix, for example "job/42/cancel"
+    pub fn route(&self) -> String {
+        match *self {
+            AdminAction::CancelJob(job_id) => format!("job/{}/cancel", job_id),
+            AdminAction::DrainNode { ref node, .. } => format!("node/{}/drain", node),
+            AdminAction::ResumeNode(ref node) => format!("node/{}/resume", node)
+        }
+    }
+
+    /// The SLURM command and its arguments, each argument is passed on its own, so no shell is involved
+    pub fn command(&self) -> (CommandConfig, Vec<String>) {
+        match *self {
+            AdminAction::CancelJob(job_id) => (CommandConfig::new("scancel", &[]), vec![job_id.to_string()]),
+            AdminAction::DrainNode { ref node, ref reason } => (CommandConfig::new("scontrol", &[]),
+                vec!["update".to_string(), format!("NodeName={}", node), "State=DRAIN".to_string(), format!("Reason={}", reason)]),
+            AdminAction::ResumeNode(ref node) => (CommandConfig::new("scontrol", &[]),
+                vec!["update".to_string(), format!("NodeName={}", node), "State=RESUME".to_string()])
+        }
+    }
+
+    /// Name of the node of a drain or resume, None for the other actions
+    pub fn node(&self) -> Option<&str> {
+        match *self {
+            AdminAction::DrainNode { ref node, .. } | AdminAction::ResumeNode(ref node) => Some(node),
+            AdminAction::CancelJob(_) => None
+        }
+    }
+
+    /// Drains need a reason, it's taken from the form of the confirmation page
+    pub fn needs_reason(&self) -> bool {
+        match *self {
+            AdminAction::DrainNode { .. } => true,
+            _ => false
+        }
+    }
+
+    /// Returns the action with the fields of the submitted form (application/x-www-form-urlencoded), the reason of a drain is validated
+    pub fn with_form(self, reason: Option<&str>) -> Result<AdminAction, AdminError> {
+        match self {
+            AdminAction::DrainNode { node, .. } => parse_reason(reason.unwrap_or(""))
+                .map(|reason| AdminAction::DrainNode { node: node, reason: reason }),
+            action => Ok(action)
+        }
+    }
+
+    /// Title of the confirmation page and label of its button
+    pub fn question(&self) -> (String, &'static str) {
+        match *self {
+            AdminAction::CancelJob(job_id) => (format!("Cancel job {}?", job_id), "Cancel job"),
+            AdminAction::DrainNode { ref node, .. } => (format!("Drain node {}?", node), "Drain node"),
+            AdminAction::ResumeNode(ref node) => (format!("Resume node {}?", node), "Resume node")
+        }
+    }
+
+    /// Notice shown on the page after the action
+    pub fn notice(&self, success: bool) -> String {
+        match (self, success) {
+            (&AdminAction::CancelJob(job_id), true) => format!("Job {} has been cancelled", job_id),
+            (&AdminAction::CancelJob(job_id), false) => format!("Job {} could not be cancelled, see the log for details", job_id),
+            (&AdminAction::DrainNode { ref node, .. }, true) => format!("Node {} has been drained", node),
+            (&AdminAction::DrainNode { ref node, .. }, false) => format!("Node {} could not be drained, see the log for details", node),
+            (&AdminAction::ResumeNode(ref node), true) => format!("Node {} has been resumed", node),
+            (&AdminAction::ResumeNode(ref node), false) => format!("Node {} could not be resumed, see the log for details", node)
+        }
+    }
+}
+
+impl fmt::Display for AdminAction {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            AdminAction::CancelJob(job_id) => write!(f, "cancel job {}", job_id),
+            AdminAction::DrainNode { ref node, ref reason } => write!(f, "drain node {} (reason: {})", node, reason),
+            AdminAction::ResumeNode(ref node) => write!(f, "resume node {}", node)
+        }
+    }
+}
+
+/// Why an admin action is refused
+#[derive(Debug, Clone, PartialEq)]
+pub enum AdminError {
+    /// --enable-admin-actions is not set
+    Disabled,
+    /// No user name from the reverse proxy, or the header isn't trusted without --trust-proxy
+    NotAuthenticated,
+    /// The argument in the route is not valid, for example a job ID that is not a number
+    InvalidArgument(String),
+    /// The reason of a drain is missing, too long or contains characters that are not allowed
+    InvalidReason(String),
+    /// The POST doesn't come from a page of the inspector: the Origin (or Referer) header is missing or has another host
+    ForeignOrigin,
+    /// The CSRF token of the form is missing or not the one of the user
+    InvalidToken
+}
+
+impl fmt::Display for AdminError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            AdminError::Disabled => write!(f, "admin actions are disabled"),
+            AdminError::NotAuthenticated => write!(f, "admin actions need a user authenticated by the reverse proxy"),
+            AdminError::InvalidArgument(ref argument) => write!(f, "invalid argument: '{}'", argument),
+            AdminError::InvalidReason(ref reason) if reason.is_empty() => write!(f, "a reason is required"),
+            AdminError::InvalidReason(ref reason) => write!(f, "invalid reason: '{}' (at most {} characters, no '=', '\"' or control characters)",
+                reason, MAX_REASON_LENGTH),
+            AdminError::ForeignOrigin => write!(f, "admin actions can only be sent from the confirmation page of slurm_inspector"),
+            AdminError::InvalidToken => write!(f, "the form token is missing or invalid, reload the confirmation page")
+        }
+    }
+}
+
+/// Public helper function to parse a job ID for an action, only plain numbers are passed to the SLURM command
+pub fn parse_job_id(text: &str) -> Result<u32, AdminError> {
+    if text.is_empty() || !text.chars().all(|c| c.is_ascii_digit()) {
+        return Err(AdminError::InvalidArgument(text.to_string()));
+    }
+    text.parse().map_err(|_| AdminError::InvalidArgument(text.to_string()))
+}
+
+/// Public helper function to parse a node name for an action: letters, digits, '-', '_' and '.', starting with a letter or digit
+/// A single node only, no hostlist, so nothing but the name can get into the NodeName= argument
+pub fn parse_node_name(text: &str) -> Result<String, AdminError> {
+    let valid = text.len() <= 64
+        && text.chars().next().map_or(false, |c| c.is_ascii_alphanumeric())
+        && text.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_' || c == '.');
+    if valid { Ok(text.to_string()) } else { Err(AdminError::InvalidArgument(text.to_string())) }
+}
+
+/// Public helper function to check the reason of a drain, surrounding whitespace is removed
+/// scontrol reads "key=value" pairs, so '=' and quotes are refused, as well as control characters like line breaks
+pub fn parse_reason(text: &str) -> Result<String, AdminError> {
+    let reason = text.trim();
+    let valid = !reason.is_empty() && reason.chars().count() <= MAX_REASON_LENGTH
+        && !reason.chars().any(|c| c == '=' || c == '"' || c == '\'' || c.is_control());
+    if valid { Ok(reason.to_string()) } else { Err(AdminError::InvalidReason(reason.to_string())) }
+}
+
+// Private helper function that returns the argument between prefix and suffix of the route, None if the route doesn't match
+fn route_argument<'a>(route: &'a str, prefix: &str, suffix: &str) -> Option<&'a str> {
+    if route.len() > prefix.len() + suffix.len() && route.starts_with(prefix) && route.ends_with(suffix) {
+        Some(&route[prefix.len()..route.len() - suffix.len()])
+    } else {
+        None
+    }
+}
+
+/// Public helper function that returns the action of a route below the URL prefix, None if the route is not an admin action
+/// The argument is validated here, so an invalid one is an error and not another page. The reason of a drain is still empty.
+pub fn admin_route(route: &str) -> Option<Result<AdminAction, AdminError>> {
+    if let Some(job_id) = route_argument(route, "job/", "/cancel") {
+        Some(parse_job_id(job_id).map(AdminAction::CancelJob))
+    } else if let Some(node) = route_argument(route, "node/", "/drain") {
+        Some(parse_node_name(node).map(|node| AdminAction::DrainNode { node: node, reason: String::new() }))
+    } else if let Some(node) = route_argument(route, "node/", "/resume") {
+        Some(parse_node_name(node).map(AdminAction::ResumeNode))
+    } else {
+        None
+    }
+}
+
+/// Public helper function that returns the action offered in the node table for a node of the local cluster:
+/// resume for nodes that are down, drained or failing, drain for nodes that can run jobs, None for the other states
+pub fn node_action(node: &PartitionNodeInfo) -> Option<AdminAction> {
+    if !node.cluster.is_empty() || parse_node_name(&node.node).is_err() {
+        return None;
+    }
+    match node.node_state {
+        NodeState::Down | NodeState::Drained | NodeState::Draining | NodeState::Fail | NodeState::Failing => Some(AdminAction::ResumeNode(node.node.clone())),
+        NodeState::Idle | NodeState::Allocated | NodeState::Mixed | NodeState::Completing | NodeState::Reserved | NodeState::Planned =>
+            Some(AdminAction::DrainNode { node: node.node.clone(), reason: String::new() }),
+        NodeState::Maint | NodeState::PoweredDown | NodeState::Unknown => None
+    }
+}
+
+/// Public helper function that returns the header with the name of the authenticated user: --remote-user-header or X-Remote-User
+pub fn remote_user_header(config: &Configuration) -> &str {
+    config.remote_user_header.as_ref().map_or(DEFAULT_REMOTE_USER_HEADER, |name| &name[..])
+}
+
+/// Public helper function that checks if admin actions are allowed for the request, returns the name of the user
+/// header returns the value of a header of the request, the user is read from the header of remote_user_header
+pub fn authorize(config: &Configuration, header: &dyn Fn(&str) -> Option<String>) -> Result<String, AdminError> {
+    if !config.enable_admin_actions {
+        return Err(AdminError::Disabled);
+    }
+    if !config.trust_proxy {
+        return Err(AdminError::NotAuthenticated);
+    }
+    match header(remote_user_header(config)).as_ref().map(|user| user.trim()) {
+        Some(user) if !user.is_empty() => Ok(user.to_string()),
+        _ => Err(AdminError::NotAuthenticated)
+    }
+}
+
+/// Public helper function that returns the secret of the CSRF tokens of the running inspector, a random one is created at the first call
+pub fn csrf_secret() -> Vec<u8> {
+    let mut secret = CSRF_SECRET.lock().unwrap_or_else(|err| err.into_inner());
+    if secret.is_empty() {
+        *secret = rand_bytes(CSRF_SECRET_LENGTH);
+    }
+    secret.clone()
+}
+
+/// Public helper function that returns the CSRF token of the user: the HMAC-SHA256 of the user name with the secret, as hex
+/// Only the inspector knows the secret, so another site can't put a valid token into a form
+pub fn csrf_token(secret: &[u8], user: &str) -> String {
+    hmac(Type::SHA256, secret, user.as_bytes()).to_hex()
+}
+
+// Private helper function that returns the host (and port) of a URL, for example "example.com:8080" of "https://example.com:8080/slurm/"
+fn url_host(url: &str) -> Option<&str> {
+    let rest = &url[url.find("://")? + 3..];
+    let host = rest.split(|c| c == '/' || c == '?' || c == '#').next().unwrap_or("");
+    if host.is_empty() || host.contains('@') { None } else { Some(host) }
+}
+
+/// Public helper function that checks the POST of an admin action of the user, see authorize for header
+/// The Origin header (or the Referer, if a browser sends no Origin) must have the host of the request, the one of X-Forwarded-Host
+/// with --trust-proxy, and token (the field CSRF_TOKEN_FIELD of the form) must be the CSRF token of the user
+pub fn check_admin_post(config: &Configuration, secret: &[u8], user: &str, header: &dyn Fn(&str) -> Option<String>, token: Option<&str>)
+    -> Result<(), AdminError> {
+    let host = if config.trust_proxy { header("X-Forwarded-Host").or_else(|| header("Host")) } else { header("Host") };
+    let source = header("Origin").or_else(|| header("Referer"));
+    let same_origin = match (host, source.as_ref().and_then(|source| url_host(source.trim()))) {
+        (Some(host), Some(source_host)) => source_host.eq_ignore_ascii_case(host.trim()),
+        _ => false
+    };
+    if !same_origin {
+        return Err(AdminError::ForeignOrigin);
+    }
+
+    let expected = csrf_token(secret, user);
+    match token {
+        // The comparison takes the same time for every token, so the token can't be guessed byte by byte
+        Some(token) if token.len() == expected.len() && memcmp::eq(token.as_bytes(), expected.as_bytes()) => Ok(()),
+        _ => Err(AdminError::InvalidToken)
+    }
+}
+
+/// Public helper function that runs the action for the given user and logs an audit line, in test mode the action is only simulated
+pub fn run_admin_action(action: &AdminAction, user: &str, config: &Configuration, runner: &dyn CommandRunner) -> Result<(), String> {
+    if config.test_mode {
+        info!("Audit: user '{}': {}: simulated (test mode)", user, action);
+        return Ok(());
+    }
+
+    let (command, args) = action.command();
+    let args: Vec<&str> = args.iter().map(|arg| &arg[..]).collect();
+    let result = run_command(runner, &command, &args).map(|_| ());
+    match result {
+        Ok(()) => info!("Audit: user '{}': {}: done", user, action),
+        Err(ref err) => warn!("Audit: user '{}': {}: failed: {}", user, action, err)
+    }
+    result
+}
+
+/// A node state changed by a simulated admin action in test mode, it's applied to the test data after every update
+#[derive(Debug, Clone, PartialEq)]
+pub enum SimulatedNode {
+    /// Drained with this reason, like "sinfo --list-reasons" reports it
+    Drained(DownReason),
+    Resumed
+}
+
+/// Public helper function for the test mode: remembers the state a drain or resume would set, since: the current time (TIME_FORMAT)
+pub fn simulate_node_action(action: &AdminAction, user: &str, since: &str, simulated: &mut BTreeMap<String, SimulatedNode>) {
+    match *action {
+        AdminAction::DrainNode { ref node, ref reason } => {
+            simulated.insert(node.clone(), SimulatedNode::Drained(DownReason { cluster: String::new(), node: node.clone(), reason: reason.clone(),
+                set_by: Some(user.to_string()), since: Some(since.to_string()) }));
+        },
+        AdminAction::ResumeNode(ref node) => {
+            simulated.insert(node.clone(), SimulatedNode::Resumed);
+        },
+        AdminAction::CancelJob(_) => {}
+    }
+}
+
+/// Public helper function for the test mode: changes the states and the down reasons of the test data like the simulated actions
+/// Nodes with running jobs are draining, the other ones drained, resumed nodes are allocated or idle again
+pub fn apply_simulated_nodes(node_info: &mut [PartitionNodeInfo], down_reasons: &mut Vec<DownReason>, simulated: &BTreeMap<String, SimulatedNode>) {
+    for node in node_info.iter_mut().filter(|node| node.cluster.is_empty()) {
+        match simulated.get(&node.node) {
+            Some(&SimulatedNode::Drained(_)) => node.node_state = match node.node_state {
+                NodeState::Allocated | NodeState::Mixed | NodeState::Completing => NodeState::Draining,
+                _ => NodeState::Drained
+            },
+            Some(&SimulatedNode::Resumed) => node.node_state = if node.cpus_alloc.map_or(false, |alloc| alloc > 0) { NodeState::Allocated } else { NodeState::Idle },
+            None => {}
+        }
+    }
+
+    down_reasons.retain(|reason| !(reason.cluster.is_empty() && simulated.contains_key(&reason.node)));
+    down_reasons.extend(simulated.values().filter_map(|simulated| match *simulated {
+        SimulatedNode::Drained(ref reason) => Some(reason.clone()),
+        SimulatedNode::Resumed => None
+    }));
+}
+
+/// Public helper function to render the confirmation page of an action (GET on its route), the form sends the POST request
+pub fn confirmation_page(action: &AdminAction, csrf_token: &str, options: &PageOptions) -> Result<String, String> {
+    let (title, button) = action.question();
+    let mut context = BTreeMap::new();
+    context.insert("url_prefix".to_string(), options.url_prefix.to_json());
+    context.insert("custom_css".to_string(), options.custom_css.to_json());
+    context.insert("title".to_string(), title.to_json());
+    context.insert("button".to_string(), button.to_json());
+    context.insert("route".to_string(), action.route().to_json());
+    context.insert("needs_reason".to_string(), action.needs_reason().to_json());
+    context.insert("max_reason".to_string(), MAX_REASON_LENGTH.to_json());
+    context.insert("csrf_field".to_string(), CSRF_TOKEN_FIELD.to_json());
+    context.insert("csrf_token".to_string(), csrf_token.to_json());
+
+    options.templates.render(ADMIN_CONFIRM_TEMPLATE, &Json::Object(context))
+}
+
+/// The result of an action, shown at the top of the page after the redirect
+#[derive(Debug, Clone, PartialEq)]
+pub struct AdminNotice {
+    pub text: String,
+    pub success: bool
+}
+
+impl ToJson for AdminNotice {
+    fn to_json(&self) -> Json {
+        let mut result = BTreeMap::new();
+        result.insert("text".to_string(), self.text.to_json());
+        result.insert("success".to_string(), self.success.to_json());
+        Json::Object(result)
+    }
+}
+
+/// Public helper function that returns the query string of the redirect after an action, for example "done=job/42/cancel"
+pub fn notice_query(action: &AdminAction, success: bool) -> String {
+    format!("{}={}", if success { "done" } else { "failed" }, action.route())
+}
+
+/// Public helper function that returns the notice of the ?done= or ?failed= parameter of the redirect
+/// The route is parsed again, so the page only shows the fixed texts of valid actions and no text from the URL
+pub fn admin_notice(done: Option<&str>, failed: Option<&str>) -> Option<AdminNotice> {
+    let notice = |route: &str, success: bool| match admin_route(route) {
+        Some(Ok(action)) => Some(AdminNotice { text: action.notice(success), success: success }),
+        _ => None
+    };
+
+    done.and_then(|route| notice(route, true)).or_else(|| failed.and_then(|route| notice(route, false)))
+}
+
+#[test]
+fn test_parse_job_id() {
+    assert_eq!(parse_job_id("42"), Ok(42));
+    assert_eq!(parse_job_id("0042"), Ok(42));
+    assert_eq!(parse_job_id(""), Err(AdminError::InvalidArgument(String::new())));
+    assert_eq!(parse_job_id("42_3"), Err(AdminError::InvalidArgument("42_3".to_string())));
+    assert_eq!(parse_job_id("42;rm"), Err(AdminError::InvalidArgument("42;rm".to_string())));
+    assert_eq!(parse_job_id("-1"), Err(AdminError::InvalidArgument("-1".to_string())));
+    assert_eq!(parse_job_id("+1"), Err(AdminError::InvalidArgument("+1".to_string())));
+    assert_eq!(parse_job_id(" 1"), Err(AdminError::InvalidArgument(" 1".to_string())));
+    // Too large for a job ID
+    assert_eq!(parse_job_id("99999999999"), Err(AdminError::InvalidArgument("99999999999".to_string())));
+}
+
+#[test]
+fn test_admin_route() {
+    assert_eq!(admin_route("job/42/cancel"), Some(Ok(AdminAction::CancelJob(42))));
+    assert_eq!(admin_route("job/42%3Bls/cancel"), Some(Err(AdminError::InvalidArgument("42%3Bls".to_string()))));
+    assert_eq!(admin_route("job/42"), None);
+    assert_eq!(admin_route("job/42/cancel/now"), None);
+    assert_eq!(admin_route("user/job/cancel"), None);
+    assert_eq!(AdminAction::CancelJob(42).route(), "job/42/cancel");
+}
+
+#[test]
+fn test_authorize() {
+    let enabled = Configuration{ enable_admin_actions: true, trust_proxy: true, .. Configuration::default() };
+    let header = |user: &'static str| move |name: &str| if name == "X-Remote-User" { Some(user.to_string()) } else { None };
+
+    assert_eq!(authorize(&enabled, &header("admin")), Ok("admin".to_string()));
+    assert_eq!(authorize(&enabled, &|_name: &str| None), Err(AdminError::NotAuthenticated));
+    assert_eq!(authorize(&enabled, &header(" ")), Err(AdminError::NotAuthenticated));
+    // The header could come from anybody without a reverse proxy
+    assert_eq!(authorize(&Configuration{ trust_proxy: false, .. enabled.clone() }, &header("admin")), Err(AdminError::NotAuthenticated));
+    assert_eq!(authorize(&Configuration{ enable_admin_actions: false, .. enabled.clone() }, &header("admin")), Err(AdminError::Disabled));
+
+    // With --remote-user-header only that header counts
+    let sso = Configuration{ remote_user_header: Some("X-Forwarded-User".to_string()), .. enabled };
+    assert_eq!(remote_user_header(&sso), "X-Forwarded-User");
+    assert_eq!(authorize(&sso, &header("admin")), Err(AdminError::NotAuthenticated));
+    assert_eq!(authorize(&sso, &|name: &str| if name == "X-Forwarded-User" { Some("admin".to_string()) } else { None }), Ok("admin".to_string()));
+}
+
+#[test]
+fn test_run_admin_action() {
+    use command_runner::TestRunner;
+
+    let config = Configuration{ enable_admin_actions: true, trust_proxy: true, .. Configuration::default() };
+    let runner = TestRunner::new(Ok(String::new()));
+    assert_eq!(run_admin_action(&AdminAction::CancelJob(42), "admin", &config, &runner), Ok(()));
+    let calls = runner.calls.lock().unwrap();
+    assert_eq!(calls.len(), 1);
+    assert_eq!((&calls[0].0[..], &calls[0].1[..]), ("scancel", &["42".to_string()][..]));
+
+    let runner = TestRunner::new(Err("Invalid job id specified".to_string()));
+    assert!(run_admin_action(&AdminAction::CancelJob(42), "admin", &config, &runner).is_err());
+
+    // Only simulated in test mode
+    let runner = TestRunner::new(Err("must not be called".to_string()));
+    assert_eq!(run_admin_action(&AdminAction::CancelJob(42), "admin", &Configuration{ test_mode: true, .. config }, &runner), Ok(()));
+    assert!(runner.calls.lock().unwrap().is_empty());
+}
+
+#[test]
+fn test_admin_notice() {
+    let action = AdminAction::CancelJob(42);
+
+    assert_eq!(notice_query(&action, true), "done=job/42/cancel");
+    assert_eq!(admin_notice(Some("job/42/cancel"), None), Some(AdminNotice{ text: "Job 42 has been cancelled".to_string(), success: true }));
+    assert_eq!(admin_notice(None, Some("job/42/cancel")).map(|notice| notice.success), Some(false));
+    // No text from the URL on the page
+    assert_eq!(admin_notice(Some("job/<b>/cancel"), None), None);
+    assert_eq!(admin_notice(Some("Hello"), None), None);
+    assert_eq!(admin_notice(None, None), None);
+}
+
+#[test]
+fn test_parse_node_name() {
+    assert_eq!(parse_node_name("node01"), Ok("node01".to_string()));
+    assert_eq!(parse_node_name("gpu-a_1.cluster"), Ok("gpu-a_1.cluster".to_string()));
+    assert_eq!(parse_node_name(""), Err(AdminError::InvalidArgument(String::new())));
+    // No hostlists, options or other arguments
+    assert_eq!(parse_node_name("node[01-04]"), Err(AdminError::InvalidArgument("node[01-04]".to_string())));
+    assert_eq!(parse_node_name("-node01"), Err(AdminError::InvalidArgument("-node01".to_string())));
+    assert_eq!(parse_node_name("node01 State=DOWN"), Err(AdminError::InvalidArgument("node01 State=DOWN".to_string())));
+    assert!(parse_node_name(&"n".repeat(65)).is_err());
+}
+
+#[test]
+fn test_parse_reason() {
+    assert_eq!(parse_reason("  bad DIMM, ticket #42 "), Ok("bad DIMM, ticket #42".to_string()));
+    assert_eq!(parse_reason(" "), Err(AdminError::InvalidReason(String::new())));
+    assert_eq!(parse_reason("a Weight=0"), Err(AdminError::InvalidReason("a Weight=0".to_string())));
+    assert!(parse_reason("\"quoted\"").is_err());
+    assert!(parse_reason("two\nlines").is_err());
+    assert!(parse_reason(&"x".repeat(MAX_REASON_LENGTH)).is_ok());
+    assert!(parse_reason(&"x".repeat(MAX_REASON_LENGTH + 1)).is_err());
+    assert_eq!(AdminError::InvalidReason(String::new()).to_string(), "a reason is required");
+}
+
+#[test]
+fn test_admin_route_nodes() {
+    let drain = AdminAction::DrainNode { node: "node01".to_string(), reason: String::new() };
+
+    assert_eq!(admin_route("node/node01/drain"), Some(Ok(drain.clone())));
+    assert_eq!(admin_route("node/node01/resume"), Some(Ok(AdminAction::ResumeNode("node01".to_string()))));
+    assert_eq!(admin_route("node/node%5B1-2%5D/resume"), Some(Err(AdminError::InvalidArgument("node%5B1-2%5D".to_string()))));
+    assert_eq!(admin_route("node//drain"), None);
+    assert_eq!(admin_route("node/node01"), None);
+    assert_eq!(drain.route(), "node/node01/drain");
+
+    // The reason comes from the form
+    assert!(drain.needs_reason());
+    assert!(!AdminAction::ResumeNode("node01".to_string()).needs_reason());
+    assert_eq!(drain.clone().with_form(Some(" maintenance ")),
+        Ok(AdminAction::DrainNode { node: "node01".to_string(), reason: "maintenance".to_string() }));
+    assert_eq!(drain.clone().with_form(None), Err(AdminError::InvalidReason(String::new())));
+    assert_eq!(AdminAction::CancelJob(42).with_form(None), Ok(AdminAction::CancelJob(42)));
+}
+
+#[test]
+fn test_node_commands() {
+    use command_runner::TestRunner;
+
+    let drain = AdminAction::DrainNode { node: "node01".to_string(), reason: "bad DIMM".to_string() };
+    let config = Configuration{ enable_admin_actions: true, trust_proxy: true, .. Configuration::default() };
+    let runner = TestRunner::new(Ok(String::new()));
+    assert_eq!(run_admin_action(&drain, "admin", &config, &runner), Ok(()));
+    assert_eq!(run_admin_action(&AdminAction::ResumeNode("node01".to_string()), "admin", &config, &runner), Ok(()));
+
+    // The reason is a single argument, no quoting needed
+    let calls = runner.calls.lock().unwrap();
+    assert_eq!((&calls[0].0[..], &calls[0].1[..]), ("scontrol",
+        &["update".to_string(), "NodeName=node01".to_string(), "State=DRAIN".to_string(), "Reason=bad DIMM".to_string()][..]));
+    assert_eq!((&calls[1].0[..], &calls[1].1[..]), ("scontrol",
+        &["update".to_string(), "NodeName=node01".to_string(), "State=RESUME".to_string()][..]));
+    assert_eq!(drain.to_string(), "drain node node01 (reason: bad DIMM)");
+    assert_eq!(admin_notice(Some("node/node01/drain"), None).map(|notice| notice.text), Some("Node node01 has been drained".to_string()));
+}
+
+#[test]
+fn test_node_action() {
+    use sinfo_util::get_partition_node_info_test;
+
+    let node = get_partition_node_info_test()[0].clone();
+    let with_state = |state: NodeState| PartitionNodeInfo { node_state: state, .. node.clone() };
+
+    assert_eq!(node_action(&with_state(NodeState::Idle)), Some(AdminAction::DrainNode { node: node.node.clone(), reason: String::new() }));
+    assert_eq!(node_action(&with_state(NodeState::Drained)), Some(AdminAction::ResumeNode(node.node.clone())));
+    assert_eq!(node_action(&with_state(NodeState::Down)), Some(AdminAction::ResumeNode(node.node.clone())));
+    assert_eq!(node_action(&with_state(NodeState::PoweredDown)), None);
+    // Only nodes of the local cluster
+    assert_eq!(node_action(&PartitionNodeInfo { cluster: "other".to_string(), .. with_state(NodeState::Idle) }), None);
+}
+
+#[test]
+fn test_apply_simulated_nodes() {
+    use sinfo_util::{get_partition_node_info_test, get_down_reasons_test};
+
+    let mut node_info = get_partition_node_info_test();
+    let mut down_reasons = get_down_reasons_test();
+    let drained = node_info.iter().find(|node| node.node_state == NodeState::Idle).unwrap().node.clone();
+    let resumed = down_reasons[0].node.clone();
+
+    let mut simulated = BTreeMap::new();
+    simulate_node_action(&AdminAction::DrainNode { node: drained.clone(), reason: "bad DIMM".to_string() }, "admin", "2000-01-01T12:00:00", &mut simulated);
+    simulate_node_action(&AdminAction::ResumeNode(resumed.clone()), "admin", "2000-01-01T12:00:00", &mut simulated);
+    simulate_node_action(&AdminAction::CancelJob(42), "admin", "2000-01-01T12:00:00", &mut simulated);
+    assert_eq!(simulated.len(), 2);
+
+    apply_simulated_nodes(&mut node_info, &mut down_reasons, &simulated);
+    assert!(node_info.iter().filter(|node| node.node == drained).all(|node| node.node_state == NodeState::Drained));
+    assert!(node_info.iter().filter(|node| node.node == resumed).all(|node| node.node_state == NodeState::Idle || node.node_state == NodeState::Allocated));
+    assert!(!down_reasons.iter().any(|reason| reason.node == resumed));
+    let reason = down_reasons.iter().find(|reason| reason.node == drained).unwrap();
+    assert_eq!((&reason.reason[..], reason.set_by.as_ref().map(|user| &user[..])), ("bad DIMM", Some("admin")));
+}
+
+#[test]
+fn test_confirmation_page() {
+    let options = PageOptions { url_prefix: "/slurm".to_string(), .. PageOptions::default() };
+
+    let html = confirmation_page(&AdminAction::DrainNode { node: "node01".to_string(), reason: String::new() }, "0123abcd", &options).unwrap();
+    assert!(html.contains("<h1>Drain node node01?</h1>"));
+    assert!(html.contains("action=\"/slurm/node/node01/drain\""));
+    assert!(html.contains("name=\"reason\""));
+    // The token is sent together with the reason
+    assert!(html.contains("<input type=\"hidden\" name=\"csrf_token\" value=\"0123abcd\">"));
+
+    let html = confirmation_page(&AdminAction::ResumeNode("node01".to_string()), "0123abcd", &options).unwrap();
+    assert!(html.contains("action=\"/slurm/node/node01/resume\""));
+    assert!(html.contains("<input type=\"hidden\" name=\"csrf_token\" value=\"0123abcd\">"));
+
+    let html = confirmation_page(&AdminAction::CancelJob(42), "0123abcd", &options).unwrap();
+    assert!(html.contains("action=\"/slurm/job/42/cancel\""));
+    assert!(html.contains("<input type=\"hidden\" name=\"csrf_token\" value=\"0123abcd\">"));
+    assert!(!html.contains("name=\"reason\""));
+}
+
+#[test]
+fn test_csrf_token() {
+    // Test vector of RFC 4231 (test case 2)
+    assert_eq!(csrf_token(b"Jefe", "what do ya want for nothing?"), "5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843");
+    assert_ne!(csrf_token(b"secret", "admin"), csrf_token(b"secret", "admin2"));
+    assert_ne!(csrf_token(b"secret", "admin"), csrf_token(b"other secret", "admin"));
+
+    // The same secret for the whole run of the inspector
+    assert_eq!(csrf_secret().len(), CSRF_SECRET_LENGTH);
+    assert_eq!(csrf_secret(), csrf_secret());
+}
+
+#[test]
+fn test_check_admin_post_origin() {
+    let config = Configuration{ enable_admin_actions: true, trust_proxy: true, .. Configuration::default() };
+    let token = csrf_token(b"secret", "admin");
+    let check = |headers: &[(&str, &str)]| {
+        let headers: Vec<(String, String)> = headers.iter().map(|&(name, value)| (name.to_string(), value.to_string())).collect();
+        check_admin_post(&config, b"secret", "admin", &|name: &str| headers.iter().find(|header| header.0 == name).map(|header| header.1.clone()),
+            Some(&token))
+    };
+
+    assert_eq!(check(&[("Host", "cluster.example.com"), ("Origin", "https://cluster.example.com")]), Ok(()));
+    assert_eq!(check(&[("Host", "cluster.example.com:8080"), ("Origin", "http://Cluster.Example.com:8080")]), Ok(()));
+    // Browsers that send no Origin send the Referer of the confirmation page
+    assert_eq!(check(&[("Host", "cluster.example.com"), ("Referer", "https://cluster.example.com/slurm/job/42/cancel")]), Ok(()));
+    // Behind the reverse proxy the host of the proxy counts
+    assert_eq!(check(&[("Host", "localhost:4545"), ("X-Forwarded-Host", "cluster.example.com"), ("Origin", "https://cluster.example.com")]), Ok(()));
+
+    // A form of another site, or a request without any origin
+    assert_eq!(check(&[("Host", "cluster.example.com"), ("Origin", "https://evil.example.com")]), Err(AdminError::ForeignOrigin));
+    assert_eq!(check(&[("Host", "cluster.example.com"), ("Origin", "https://cluster.example.com.evil.example.com")]), Err(AdminError::ForeignOrigin));
+    assert_eq!(check(&[("Host", "cluster.example.com"), ("Origin", "https://cluster.example.com@evil.example.com")]), Err(AdminError::ForeignOrigin));
+    assert_eq!(check(&[("Host", "cluster.example.com"), ("Origin", "null")]), Err(AdminError::ForeignOrigin));
+    assert_eq!(check(&[("Host", "cluster.example.com"), ("Origin", "https://evil.example.com"), ("Referer", "https://cluster.example.com/")]),
+        Err(AdminError::ForeignOrigin));
+    assert_eq!(check(&[("Host", "cluster.example.com"), ("Referer", "https://evil.example.com/cluster.example.com")]), Err(AdminError::ForeignOrigin));
+    assert_eq!(check(&[("Host", "cluster.example.com")]), Err(AdminError::ForeignOrigin));
+    assert_eq!(check(&[("Origin", "https://cluster.example.com")]), Err(AdminError::ForeignOrigin));
+    // X-Forwarded-Host could be sent by anybody without a reverse proxy
+    let direct = Configuration{ trust_proxy: false, .. config.clone() };
+    assert_eq!(check_admin_post(&direct, b"secret", "admin", &|name: &str| match name {
+        "Host" => Some("localhost:4545".to_string()),
+        "X-Forwarded-Host" | "Origin" => Some("https://evil.example.com".to_string()),
+        _ => None
+    }, Some(&token)), Err(AdminError::ForeignOrigin));
+}
+
+#[test]
+fn test_check_admin_post_token() {
+    let config = Configuration{ enable_admin_actions: true, trust_proxy: true, .. Configuration::default() };
+    let header = |name: &str| match name {
+        "Host" => Some("cluster.example.com".to_string()),
+        "Origin" => Some("https://cluster.example.com".to_string()),
+        _ => None
+    };
+    let token = csrf_token(b"secret", "admin");
+
+    assert_eq!(check_admin_post(&config, b"secret", "admin", &header, Some(&token)), Ok(()));
+    assert_eq!(check_admin_post(&config, b"secret", "admin", &header, None), Err(AdminError::InvalidToken));
+    assert_eq!(check_admin_post(&config, b"secret", "admin", &header, Some("")), Err(AdminError::InvalidToken));
+    assert_eq!(check_admin_post(&config, b"secret", "admin", &header, Some(&token[1..])), Err(AdminError::InvalidToken));
+    assert_eq!(check_admin_post(&config, b"secret", "admin", &header, Some(&token.to_uppercase())), Err(AdminError::InvalidToken));
+    // The token of another user or of an earlier run of the inspector
+    assert_eq!(check_admin_post(&config, b"secret", "admin2", &header, Some(&token)), Err(AdminError::InvalidToken));
+    assert_eq!(check_admin_post(&config, b"new secret", "admin", &header, Some(&token)), Err(AdminError::InvalidToken));
+}
@@ -0,0 +1,140 @@
+//! Anonymization of the SLURM status for public dashboards
+//! Replaces user names, user ids and job names before the status is rendered
+
+// System modules:
+use std::collections::{BTreeMap, BTreeSet};
+
+// Internal modules:
+use slurm_status::SlurmStatus;
+use sinfo_util::DownReason;
+
+/// Public helper function that replaces all user identities in the status
+/// User names are mapped to pseudonyms (user-001, user-002, ...) in alphabetical order of the real names,
+/// so the same set of users always gets the same pseudonyms and the page doesn't shuffle names on refresh.
+/// User ids are removed and job names are replaced by "job-<id>"
+pub fn anonymize_status(status: &mut SlurmStatus) {
+    let mut user_names: BTreeSet<String> = BTreeSet::new();
+
+    for job in &status.job_info {
+        user_names.insert(job.user_name.clone());
+    }
+    for job in &status.finished_job_info {
+        user_names.insert(job.user_name.clone());
+    }
+    for share in &status.share_info {
+        if let Some(ref user) = share.user {
+            user_names.insert(user.clone());
+        }
+    }
+    for reservation in &status.reservations {
+        user_names.extend(reservation.users.iter().cloned());
+    }
+
+    let pseudonyms: BTreeMap<String, String> = user_names.into_iter().enumerate()
+        .map(|(index, user_name)| (user_name, format!("user-{:03}", index + 1)))
+        .collect();
+
+    for job in status.job_info.iter_mut() {
+        job.user_name = pseudonyms[&job.user_name].clone();
+        job.user_id = None;
+        job.cancelled_by = None;
+        job.job_name = job.job_id.as_ref().map_or("job-?".to_string(), |job_id| format!("job-{}", job_id));
+    }
+
+    for job in status.finished_job_info.iter_mut() {
+        job.user_name = pseudonyms[&job.user_name].clone();
+        job.job_name = format!("job-{}", job.job_id);
+    }
+
+    for share in status.share_info.iter_mut() {
+        share.user = share.user.as_ref().map(|user| pseudonyms[user].clone());
+    }
+
+    for reservation in status.reservations.iter_mut() {
+        reservation.users = reservation.users.iter().map(|user| pseudonyms[user].clone()).collect();
+    }
+
+    anonymize_down_reasons(&mut status.down_reasons);
+}
+
+/// Public helper function that removes who set the reasons of the down nodes, usually an administrator or slurm itself
+/// The reasons are updated with the nodes, so this is also called after an update of only the node information
+pub fn anonymize_down_reasons(down_reasons: &mut [DownReason]) {
+    for reason in down_reasons.iter_mut() {
+        reason.set_by = None;
+    }
+}
+
+#[cfg(test)]
+fn test_status() -> SlurmStatus {
+    use squeue_util::get_job_info_test;
+    use sacct_util::get_finished_job_info_test;
+    use sshare_util::get_share_info_test;
+    use scontrol_util::get_reservation_info_test;
+    use sinfo_util::get_down_reasons_test;
+
+    let mut status = SlurmStatus::new();
+    status.job_info = get_job_info_test();
+    status.finished_job_info = get_finished_job_info_test();
+    status.share_info = get_share_info_test();
+    status.reservations = get_reservation_info_test();
+    status.down_reasons = get_down_reasons_test();
+    status
+}
+
+#[test]
+fn test_anonymize_status_pseudonyms() {
+    let mut status = test_status();
+    anonymize_status(&mut status);
+
+    assert_eq!(status.job_info[0].user_name, "user-002");
+    assert_eq!(status.job_info[0].user_id, None);
+    assert_eq!(status.job_info[0].job_name, "job-1");
+    assert_eq!(status.job_info[1].user_name, "user-003");
+    assert_eq!(status.finished_job_info[0].user_name, "user-002");
+    assert_eq!(status.finished_job_info[0].job_name, "job-101");
+    // "root" is sorted before the other users in the share information
+    assert_eq!(status.share_info[1].user, Some("user-001".to_string()));
+    assert_eq!(status.share_info[0].user, None);
+    assert_eq!(status.reservations[1].users, vec!["user-001"]);
+    assert_eq!(status.reservations[2].users, vec!["user-002", "user-003"]);
+    assert!(status.down_reasons.iter().all(|reason| reason.set_by.is_none()));
+}
+
+#[test]
+fn test_anonymize_status_deterministic() {
+    let mut status1 = test_status();
+    let mut status2 = test_status();
+    status2.job_info.reverse();
+
+    anonymize_status(&mut status1);
+    anonymize_status(&mut status2);
+
+    assert_eq!(status1.job_info[0].user_name, status2.job_info[11].user_name);
+    assert_eq!(status1.job_info[11].user_name, status2.job_info[0].user_name);
+}
+
+#[test]
+fn test_anonymize_status_no_leak() {
+    use slurm_status::{PageOptions, status_to_html, status_to_json};
+    use status_filter::StatusFilter;
+
+    let mut status = test_status();
+    anonymize_status(&mut status);
+
+    let options = PageOptions{ show_shares: true, .. PageOptions::default() };
+    let html = status_to_html(&status, &options).unwrap();
+    let json = status_to_json(&status, &StatusFilter::new());
+
+    for output in vec![html, json] {
+        for user in vec!["user01", "user02", "user03", "user04", "user05"] {
+            assert!(!output.contains(user));
+        }
+        for job_name in vec!["small_test", "batch"] {
+            assert!(!output.contains(job_name));
+        }
+        for user_id in vec!["1000", "1001", "1002", "1003", "1004"] {
+            assert!(!output.contains(user_id));
+        }
+    }
+}
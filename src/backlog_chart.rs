@@ -0,0 +1,264 @@
+//! Chart of the pending jobs per partition over the history (the scheduler backlog), shown on the status page below the summary
+//! The chart is plain SVG built from strings, so the page still works without JavaScript
+
+// System modules:
+use std::collections::{BTreeMap, BTreeSet};
+
+// Internal modules:
+use history::History;
+use display_time::DisplayTime;
+use template::escape_html;
+
+/// Size of the plot area in pixels, the chart always has the same size, independent of the history
+pub const PLOT_WIDTH: f64 = 540.0;
+pub const PLOT_HEIGHT: f64 = 160.0;
+
+/// Space around the plot area for the labels of the axes
+const MARGIN_LEFT: f64 = 50.0;
+const MARGIN_TOP: f64 = 10.0;
+const MARGIN_RIGHT: f64 = 20.0;
+const AXIS_LABEL_HEIGHT: f64 = 30.0;
+
+/// The legend is below the plot with LEGEND_COLUMNS partitions per row
+const LEGEND_ROW_HEIGHT: f64 = 18.0;
+const LEGEND_COLUMNS: usize = 4;
+
+/// Colors of the lines, repeated if there are more partitions
+const LINE_COLORS: [&'static str; 8] = ["#1f77b4", "#ff7f0e", "#2ca02c", "#d62728", "#9467bd", "#8c564b", "#e377c2", "#17becf"];
+
+/// Number of pending jobs per partition at one time (seconds since the epoch), None if the update of the jobs failed
+pub type BacklogSample = (i64, Option<BTreeMap<String, u32>>);
+
+/// Public helper function that returns the backlog of each history entry, oldest first
+pub fn backlog_samples(history: &History) -> Vec<BacklogSample> {
+    history.entries().iter().map(|entry| (entry.timestamp, entry.pending_by_partition.clone())).collect()
+}
+
+/// Public helper function that maps the value linearly from min..max to start..end (also if end is smaller than start)
+/// If min and max are the same, the value is mapped to start
+pub fn scale(value: f64, min: f64, max: f64, start: f64, end: f64) -> f64 {
+    if max <= min {
+        start
+    } else {
+        start + (value - min) / (max - min) * (end - start)
+    }
+}
+
+/// Public helper function that rounds the largest value up to the maximum of the y axis: 1, 2, 5, 10, 20, 50, 100, ...
+pub fn axis_maximum(max: u32) -> u64 {
+    let mut step = 1u64;
+
+    loop {
+        for factor in &[1, 2, 5] {
+            if factor * step >= max as u64 {
+                return factor * step;
+            }
+        }
+        step *= 10;
+    }
+}
+
+// Private helper function that returns the path of one partition, the line is interrupted at the failed updates
+// A point between two gaps is drawn as a dot (a line of length 0 with round ends)
+fn partition_path(samples: &[BacklogSample], partition: &str, x: &dyn Fn(i64) -> f64, y: &dyn Fn(u32) -> f64) -> String {
+    let mut result = String::new();
+    let mut segment_length = 0;
+
+    for &(timestamp, ref counts) in samples {
+        match *counts {
+            Some(ref counts) => {
+                let count = counts.get(partition).cloned().unwrap_or(0);
+                let command = if segment_length == 0 { 'M' } else { 'L' };
+                if !result.is_empty() {
+                    result.push(' ');
+                }
+                result.push_str(&format!("{}{:.1},{:.1}", command, x(timestamp), y(count)));
+                segment_length += 1;
+            },
+            None => {
+                if segment_length == 1 {
+                    result.push_str(" l0,0");
+                }
+                segment_length = 0;
+            }
+        }
+    }
+    if segment_length == 1 {
+        result.push_str(" l0,0");
+    }
+
+    result
+}
+
+/// Public helper function that returns the SVG line chart of the pending jobs per partition
+/// Returns None if there are less than two samples or if there never were pending jobs, then there is nothing to show
+pub fn backlog_chart(samples: &[BacklogSample], display_time: &DisplayTime) -> Option<String> {
+    let partitions: BTreeSet<&String> = samples.iter().filter_map(|&(_, ref counts)| counts.as_ref()).flat_map(|counts| counts.keys()).collect();
+    if samples.len() < 2 || partitions.is_empty() {
+        return None;
+    }
+
+    let first = samples[0].0;
+    let last = samples[samples.len() - 1].0;
+    let max_count = samples.iter().filter_map(|&(_, ref counts)| counts.as_ref()).flat_map(|counts| counts.values()).cloned().max().unwrap_or(0);
+    let y_max = axis_maximum(max_count);
+
+    let plot_bottom = MARGIN_TOP + PLOT_HEIGHT;
+    let plot_right = MARGIN_LEFT + PLOT_WIDTH;
+    let x = |timestamp: i64| scale(timestamp as f64, first as f64, last as f64, MARGIN_LEFT, plot_right);
+    let y = |count: u32| scale(count as f64, 0.0, y_max as f64, plot_bottom, MARGIN_TOP);
+    let legend_rows = (partitions.len() + LEGEND_COLUMNS - 1) / LEGEND_COLUMNS;
+    let width = plot_right + MARGIN_RIGHT;
+    let height = plot_bottom + AXIS_LABEL_HEIGHT + legend_rows as f64 * LEGEND_ROW_HEIGHT;
+
+    let mut result = format!("<svg xmlns=\"http://www.w3.org/2000/svg\" class=\"backlog_chart\" width=\"{0}\" height=\"{1}\" viewBox=\"0 0 {0} {1}\" \
+        font-family=\"sans-serif\" font-size=\"11\">\n", width, height);
+
+    // Axes with the labels: the number of pending jobs on the y axis, the time of the first and the last sample on the x axis
+    result.push_str(&format!("<line x1=\"{0}\" y1=\"{1}\" x2=\"{0}\" y2=\"{2}\" stroke=\"#888888\"/>\n", MARGIN_LEFT, MARGIN_TOP, plot_bottom));
+    result.push_str(&format!("<line x1=\"{0}\" y1=\"{1}\" x2=\"{2}\" y2=\"{1}\" stroke=\"#888888\"/>\n", MARGIN_LEFT, plot_bottom, plot_right));
+    result.push_str(&format!("<line x1=\"{0}\" y1=\"{1}\" x2=\"{2}\" y2=\"{1}\" stroke=\"#dddddd\"/>\n", MARGIN_LEFT, MARGIN_TOP, plot_right));
+    result.push_str(&format!("<text x=\"{}\" y=\"{}\" text-anchor=\"end\">0</text>\n", MARGIN_LEFT - 5.0, plot_bottom + 4.0));
+    result.push_str(&format!("<text x=\"{}\" y=\"{}\" text-anchor=\"end\">{}</text>\n", MARGIN_LEFT - 5.0, MARGIN_TOP + 4.0, y_max));
+    result.push_str(&format!("<text x=\"{}\" y=\"{}\" text-anchor=\"start\">{}</text>\n", MARGIN_LEFT, plot_bottom + 16.0,
+        escape_html(&display_time.format(first))));
+    result.push_str(&format!("<text x=\"{}\" y=\"{}\" text-anchor=\"end\">{}</text>\n", plot_right, plot_bottom + 16.0,
+        escape_html(&display_time.format(last))));
+
+    for (index, partition) in partitions.iter().enumerate() {
+        let color = LINE_COLORS[index % LINE_COLORS.len()];
+        result.push_str(&format!("<path d=\"{}\" fill=\"none\" stroke=\"{}\" stroke-width=\"2\" stroke-linecap=\"round\" stroke-linejoin=\"round\"/>\n",
+            partition_path(samples, partition, &x, &y), color));
+
+        let legend_x = MARGIN_LEFT + (index % LEGEND_COLUMNS) as f64 * (PLOT_WIDTH / LEGEND_COLUMNS as f64);
+        let legend_y = plot_bottom + AXIS_LABEL_HEIGHT + (index / LEGEND_COLUMNS) as f64 * LEGEND_ROW_HEIGHT;
+        result.push_str(&format!("<rect x=\"{}\" y=\"{}\" width=\"10\" height=\"10\" fill=\"{}\"/>\n", legend_x, legend_y - 9.0, color));
+        result.push_str(&format!("<text x=\"{}\" y=\"{}\">{}</text>\n", legend_x + 14.0, legend_y, escape_html(partition)));
+    }
+    result.push_str("</svg>\n");
+
+    Some(result)
+}
+
+#[cfg(test)]
+fn test_sample(timestamp: i64, counts: &[(&str, u32)]) -> BacklogSample {
+    (timestamp, Some(counts.iter().map(|&(partition, count)| (partition.to_string(), count)).collect()))
+}
+
+// Private helper function that checks that the tags of the XML are nested correctly, that the attributes are quoted
+// and that there is no unescaped "<" or "&" in the text, enough to check the generated SVG
+#[cfg(test)]
+fn is_well_formed_xml(xml: &str) -> bool {
+    let mut open_tags: Vec<&str> = Vec::new();
+    let mut rest = xml;
+
+    while let Some(start) = rest.find('<') {
+        let text = &rest[..start];
+        if text.split('&').skip(1).any(|entity| !["amp;", "lt;", "gt;", "quot;"].iter().any(|name| entity.starts_with(name))) {
+            return false;
+        }
+        let end = match rest[start..].find('>') {
+            Some(end) => start + end,
+            None => return false
+        };
+        let tag = &rest[start + 1..end];
+        if tag.contains('<') || tag.matches('"').count() % 2 != 0 {
+            return false;
+        }
+
+        if tag.starts_with('/') {
+            if open_tags.pop() != Some(&tag[1..]) {
+                return false;
+            }
+        } else if !tag.ends_with('/') {
+            open_tags.push(tag.split_whitespace().next().unwrap_or(""));
+        }
+        rest = &rest[end + 1..];
+    }
+
+    open_tags.is_empty() && !rest.contains('>')
+}
+
+#[test]
+fn test_scale() {
+    assert_eq!(scale(0.0, 0.0, 10.0, 50.0, 590.0), 50.0);
+    assert_eq!(scale(10.0, 0.0, 10.0, 50.0, 590.0), 590.0);
+    assert_eq!(scale(5.0, 0.0, 10.0, 50.0, 590.0), 320.0);
+    // The y axis goes up
+    assert_eq!(scale(0.0, 0.0, 20.0, 170.0, 10.0), 170.0);
+    assert_eq!(scale(5.0, 0.0, 20.0, 170.0, 10.0), 130.0);
+    assert_eq!(scale(20.0, 0.0, 20.0, 170.0, 10.0), 10.0);
+    assert_eq!(scale(7.0, 7.0, 7.0, 50.0, 590.0), 50.0);
+}
+
+#[test]
+fn test_axis_maximum() {
+    assert_eq!(axis_maximum(0), 1);
+    assert_eq!(axis_maximum(1), 1);
+    assert_eq!(axis_maximum(3), 5);
+    assert_eq!(axis_maximum(10), 10);
+    assert_eq!(axis_maximum(11), 20);
+    assert_eq!(axis_maximum(420), 500);
+    assert_eq!(axis_maximum(501), 1000);
+    assert_eq!(axis_maximum(u32::max_value()), 5000000000);
+}
+
+#[test]
+fn test_partition_path() {
+    let samples = vec![test_sample(0, &[("batch", 0)]), test_sample(60, &[("batch", 10)]), (120, None), test_sample(180, &[]),
+        (240, None), test_sample(300, &[("batch", 5)]), test_sample(360, &[("batch", 20)])];
+    let x = |timestamp: i64| scale(timestamp as f64, 0.0, 360.0, 0.0, 360.0);
+    let y = |count: u32| scale(count as f64, 0.0, 20.0, 100.0, 0.0);
+
+    // Interrupted at the failed updates, a missing partition has no pending jobs
+    assert_eq!(partition_path(&samples, "batch", &x, &y), "M0.0,100.0 L60.0,50.0 M180.0,100.0 l0,0 M300.0,75.0 L360.0,0.0");
+}
+
+#[test]
+fn test_backlog_chart() {
+    let utc = DisplayTime { timezone: ::display_time::DisplayTimezone::Utc, .. DisplayTime::default() };
+    let samples = vec![test_sample(1450432800, &[("batch", 4), ("<gpu>", 1)]), (1450432860, None), test_sample(1450432920, &[("batch", 12)])];
+    let chart = backlog_chart(&samples, &utc).unwrap();
+
+    assert!(is_well_formed_xml(&chart));
+    assert!(chart.starts_with("<svg xmlns=\"http://www.w3.org/2000/svg\" class=\"backlog_chart\" width=\"610\" height=\"218\""));
+    // Axis labels and legend
+    assert!(chart.contains(">20</text>"));
+    assert!(chart.contains(">2015.12.18 - 10:00</text>"));
+    assert!(chart.contains(">2015.12.18 - 10:02</text>"));
+    assert!(chart.contains(">&lt;gpu&gt;</text>"));
+    assert!(chart.contains(">batch</text>"));
+    // Both points are between gaps
+    assert!(chart.contains("<path d=\"M50.0,138.0 l0,0 M590.0,74.0 l0,0\""));
+    assert!(chart.contains("<path d=\"M50.0,162.0 l0,0 M590.0,170.0 l0,0\""));
+}
+
+#[test]
+fn test_backlog_chart_legend_rows() {
+    let partitions = ["p1", "p2", "p3", "p4", "p5"];
+    let counts: Vec<(&str, u32)> = partitions.iter().map(|&partition| (partition, 1)).collect();
+    let chart = backlog_chart(&[test_sample(0, &counts), test_sample(60, &counts)], &DisplayTime::default()).unwrap();
+
+    assert!(is_well_formed_xml(&chart));
+    assert_eq!(chart.matches("<path ").count(), 5);
+    // Two rows of the legend
+    assert!(chart.contains("height=\"236\""));
+}
+
+#[test]
+fn test_backlog_chart_nothing_to_show() {
+    let display_time = DisplayTime::default();
+
+    assert_eq!(backlog_chart(&[], &display_time), None);
+    assert_eq!(backlog_chart(&[test_sample(0, &[("batch", 3)])], &display_time), None);
+    assert_eq!(backlog_chart(&[test_sample(0, &[]), test_sample(60, &[]), (120, None)], &display_time), None);
+}
+
+#[test]
+fn test_is_well_formed_xml() {
+    assert!(is_well_formed_xml("<svg a=\"1\"><g><text>a &amp; b</text></g><rect/></svg>"));
+    assert!(!is_well_formed_xml("<svg><g></svg></g>"));
+    assert!(!is_well_formed_xml("<svg><text>a & b</text></svg>"));
+    assert!(!is_well_formed_xml("<svg a=\"1><text>a</text></svg>"));
+    assert!(!is_well_formed_xml("<svg>"));
+}
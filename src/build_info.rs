@@ -0,0 +1,97 @@
+//! Version and commit of the running binary and since when it runs, shown in the page footer and at /api/version
+//! The commit is embedded at build time from the environment variable SLURM_INSPECTOR_COMMIT, for example:
+//! SLURM_INSPECTOR_COMMIT=$(git rev-parse --short HEAD) cargo build --release
+
+// System modules:
+use std::collections::BTreeMap;
+
+// External modules:
+use rustc_serialize::json::{Json, ToJson};
+
+// Internal modules:
+use configuration::Configuration;
+use time_limit::format_duration_short;
+
+/// Version of the crate (Cargo.toml)
+pub const VERSION: &'static str = env!("CARGO_PKG_VERSION");
+
+/// Shown if the binary was built without SLURM_INSPECTOR_COMMIT
+pub const UNKNOWN_COMMIT: &'static str = "unknown";
+
+/// Version, commit and start time of the running process
+#[derive(Debug, Clone, PartialEq)]
+pub struct BuildInfo {
+    pub version: String,
+    pub commit: String,
+    /// Start of the process in seconds since the epoch, None in the --once mode and in tests
+    pub started: Option<i64>
+}
+
+impl BuildInfo {
+    /// Create the build information of this binary, the start time is set at startup
+    pub fn new() -> BuildInfo {
+        BuildInfo {
+            version: VERSION.to_string(),
+            commit: option_env!("SLURM_INSPECTOR_COMMIT").unwrap_or(UNKNOWN_COMMIT).to_string(),
+            started: None
+        }
+    }
+
+    /// Returns the number of seconds since the start of the process, None if the start time is not known
+    pub fn uptime(&self, now: i64) -> Option<u64> {
+        self.started.map(|started| if now > started { (now - started) as u64 } else { 0 })
+    }
+
+    /// Returns the text of the page footer, for example "slurm_inspector v0.1.0 (commit abc123), up 3d 4h 12m"
+    pub fn to_text(&self, now: i64) -> String {
+        match self.uptime(now) {
+            Some(uptime) => format!("slurm_inspector v{} (commit {}), up {}", self.version, self.commit, format_duration_short(uptime)),
+            None => format!("slurm_inspector v{} (commit {})", self.version, self.commit)
+        }
+    }
+}
+
+/// Public helper function that returns the build information, the uptime and the update settings as JSON (/api/version)
+pub fn version_to_json(build_info: &BuildInfo, config: &Configuration, now: i64) -> String {
+    let uptime = build_info.uptime(now);
+    let mut result = BTreeMap::new();
+
+    result.insert("version".to_string(), build_info.version.to_json());
+    result.insert("commit".to_string(), build_info.commit.to_json());
+    result.insert("started".to_string(), build_info.started.to_json());
+    result.insert("uptime_seconds".to_string(), uptime.to_json());
+    result.insert("uptime".to_string(), uptime.map(format_duration_short).to_json());
+    result.insert("interval".to_string(), config.interval.to_json());
+    result.insert("sinfo_interval".to_string(), config.sinfo_interval.to_json());
+    result.insert("squeue_interval".to_string(), config.squeue_interval.to_json());
+    result.insert("test_mode".to_string(), config.test_mode.to_json());
+
+    Json::Object(result).to_string()
+}
+
+#[test]
+fn test_build_info_text() {
+    let build_info = BuildInfo{ version: "0.1.0".to_string(), commit: "abc123".to_string(), started: Some(1000) };
+
+    assert_eq!(build_info.uptime(1000 + 3 * 86400 + 4 * 3600), Some(273600));
+    assert_eq!(build_info.to_text(1000 + 3 * 86400 + 4 * 3600 + 12 * 60), "slurm_inspector v0.1.0 (commit abc123), up 3d 4h 12m");
+    // A clock that goes backwards doesn't give a negative uptime
+    assert_eq!(build_info.uptime(900), Some(0));
+    assert_eq!(BuildInfo{ started: None, .. build_info }.to_text(2000), "slurm_inspector v0.1.0 (commit abc123)");
+    assert_eq!(BuildInfo::new().version, VERSION);
+}
+
+#[test]
+fn test_version_to_json() {
+    let build_info = BuildInfo{ version: "0.1.0".to_string(), commit: "abc123".to_string(), started: Some(1000) };
+    let config = Configuration{ interval: 120, sinfo_interval: 300, test_mode: true, .. Configuration::default() };
+    let json = Json::from_str(&version_to_json(&build_info, &config, 1090)).unwrap();
+
+    assert_eq!(json.find("version").and_then(|value| value.as_string()), Some("0.1.0"));
+    assert_eq!(json.find("commit").and_then(|value| value.as_string()), Some("abc123"));
+    assert_eq!(json.find("uptime_seconds").and_then(|value| value.as_u64()), Some(90));
+    assert_eq!(json.find("uptime").and_then(|value| value.as_string()), Some("1m"));
+    assert_eq!(json.find("interval").and_then(|value| value.as_u64()), Some(120));
+    assert_eq!(json.find("sinfo_interval").and_then(|value| value.as_u64()), Some(300));
+    assert_eq!(json.find("test_mode").and_then(|value| value.as_boolean()), Some(true));
+}
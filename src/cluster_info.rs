@@ -0,0 +1,115 @@
+//! Name of the cluster and version of SLURM, detected once at startup
+//! Both are shown on the web page, in the JSON responses and in the Prometheus metrics,
+//! so several instances of slurm_inspector can be told apart
+
+// Internal modules:
+use command_runner::{CommandConfig, CommandRunner, SLURM_ENVIRONMENT, run_command};
+use configuration::Configuration;
+use placeholder::is_placeholder;
+
+/// Shown if the cluster name or the SLURM version can't be detected
+pub const UNKNOWN: &'static str = "unknown";
+
+/// Public helper function to parse the output of "squeue --version", for example "slurm 20.11.8" or "slurm-wlm 21.08.5"
+/// Returns None if there is no version number in the output
+pub fn parse_slurm_version(output: &str) -> Option<String> {
+    let is_version = |word: &&str| word.starts_with(|c: char| c.is_digit(10)) && word.contains('.') &&
+        word.chars().all(|c| c.is_alphanumeric() || c == '.' || c == '-' || c == '_');
+
+    output.lines().next()
+        .and_then(|line| line.split_whitespace().find(is_version))
+        .map(|version| version.to_string())
+}
+
+/// Public helper function to parse the cluster name from the output of "scontrol show config"
+/// The line looks like "ClusterName             = cluster1", None if it's missing or empty
+pub fn parse_cluster_name(output: &str) -> Option<String> {
+    output.lines()
+        .filter_map(|line| {
+            let mut parts = line.splitn(2, '=');
+            match (parts.next(), parts.next()) {
+                (Some(key), Some(value)) if key.trim() == "ClusterName" => Some(value.trim()),
+                _ => None
+            }
+        })
+        .find(|value| !value.is_empty() && !is_placeholder(value))
+        .map(|value| value.to_string())
+}
+
+/// Public helper function to detect the version of SLURM with "squeue --version", "unknown" if that fails
+pub fn detect_slurm_version(runner: &dyn CommandRunner, config: &Configuration) -> String {
+    if config.test_mode {
+        return UNKNOWN.to_string();
+    }
+
+    match run_command(runner, &CommandConfig::new(&config.squeue_path, &[]), &["--version"]) {
+        Ok(output) => parse_slurm_version(&output).unwrap_or_else(|| {
+            warn!("Could not find the SLURM version in '{}'", output.trim());
+            UNKNOWN.to_string()
+        }),
+        Err(_) => UNKNOWN.to_string()
+    }
+}
+
+/// Public helper function to detect the cluster name with "scontrol show config"
+/// Falls back to the host name (of the remote host with --remote-host) and then to "unknown"
+pub fn detect_cluster_name(runner: &dyn CommandRunner, config: &Configuration) -> String {
+    if config.test_mode {
+        return "test".to_string();
+    }
+
+    if let Some(name) = run_command(runner, &CommandConfig::new("scontrol", &[]), &["show", "config"]).ok().and_then(|output| parse_cluster_name(&output)) {
+        return name;
+    }
+
+    match runner.run("hostname", &[], SLURM_ENVIRONMENT) {
+        Ok(ref output) if !output.trim().is_empty() => output.trim().to_string(),
+        _ => {
+            warn!("Could not detect the cluster name, use --cluster-name to set it");
+            UNKNOWN.to_string()
+        }
+    }
+}
+
+#[test]
+fn test_parse_slurm_version() {
+    assert_eq!(parse_slurm_version("slurm 20.11.8\n"), Some("20.11.8".to_string()));
+    assert_eq!(parse_slurm_version("slurm-wlm 21.08.5"), Some("21.08.5".to_string()));
+    assert_eq!(parse_slurm_version("slurm 23.02.0-0rc1\n"), Some("23.02.0-0rc1".to_string()));
+    assert_eq!(parse_slurm_version("slurm 17.11.2-bull.1.0"), Some("17.11.2-bull.1.0".to_string()));
+    assert_eq!(parse_slurm_version("20.11.8"), Some("20.11.8".to_string()));
+    assert_eq!(parse_slurm_version("slurm 20"), None);
+    assert_eq!(parse_slurm_version("squeue: command not found"), None);
+    assert_eq!(parse_slurm_version(""), None);
+}
+
+#[test]
+fn test_parse_cluster_name() {
+    let output = "Configuration data as of 2015-12-20T12:00:00\nAccountingStorageType   = accounting_storage/slurmdbd\n\
+        ClusterName             = cluster1\nControlMachine          = head01\n";
+    assert_eq!(parse_cluster_name(output), Some("cluster1".to_string()));
+    assert_eq!(parse_cluster_name("ClusterName = (null)\n"), None);
+    assert_eq!(parse_cluster_name("ClusterName =\n"), None);
+    assert_eq!(parse_cluster_name("ControlMachine = head01\n"), None);
+}
+
+#[test]
+fn test_detect_cluster_info() {
+    use command_runner::TestRunner;
+
+    let config = Configuration::default();
+    let runner = TestRunner::new(Ok("slurm 20.11.8\n".to_string()));
+    assert_eq!(detect_slurm_version(&runner, &config), "20.11.8");
+    assert_eq!(runner.calls.lock().unwrap()[0].1, vec!["--version"]);
+    // No ClusterName in the output, so the host name is used
+    assert_eq!(detect_cluster_name(&runner, &config), "slurm 20.11.8");
+    assert_eq!(runner.calls.lock().unwrap()[2].0, "hostname");
+
+    let runner = TestRunner::new(Err("No such file or directory".to_string()));
+    assert_eq!(detect_slurm_version(&runner, &config), UNKNOWN);
+    assert_eq!(detect_cluster_name(&runner, &config), UNKNOWN);
+
+    let config = Configuration{ test_mode: true, .. Configuration::default() };
+    assert_eq!(detect_cluster_name(&runner, &config), "test");
+    assert_eq!(detect_slurm_version(&runner, &config), UNKNOWN);
+}
@@ -0,0 +1,412 @@
+//! Column registry of the job and the node table: the id of each column, its header and how the cell is filled
+//! The columns that are shown and their order can be chosen with --job-columns / --node-columns or ?columns= / ?node_columns=,
+//! the HTML page and the CSV export use the same columns
+
+// System modules:
+use std::collections::BTreeMap;
+
+// External modules:
+use rustc_serialize::json::{Json, ToJson};
+
+// Internal modules:
+use status_page::{JobRow, NodeRow, Link};
+use sinfo_util::MEMORY_WARNING_PERCENT;
+
+/// One cell of a table row, the markup is in the "table_cell" template
+#[derive(Debug, Clone, PartialEq)]
+pub struct Cell {
+    /// Shown after the links, the whole content if there are no links
+    pub text: String,
+    /// Links to other rows, separated by separator
+    pub links: Vec<Link>,
+    pub separator: String,
+    /// Another page that the text links to, relative to the URL prefix, for example "partition/esd"
+    pub page: Option<String>,
+    /// HTML id, CSS class, tooltip and sort key (data-sort attribute) of the cell
+    pub id: Option<String>,
+    pub class: Option<String>,
+    pub title: Option<String>,
+    pub sort: Option<String>,
+    /// Percentage (0 - 100) shown as bar in front of the text, for example the memory in use
+    pub meter: Option<u32>,
+    /// Number of rows of a group, only set in the first row of the group
+    pub rowspan: Option<usize>,
+    /// The cell of an earlier row spans this row, so there is no cell in this row
+    pub covered: bool,
+    /// The complete value without markup, for the CSV export
+    pub value: String
+}
+
+impl Cell {
+    /// Create a cell that only contains the given text
+    pub fn new(text: &str) -> Cell {
+        Cell {
+            text: text.to_string(),
+            links: Vec::new(),
+            separator: String::new(),
+            page: None,
+            id: None,
+            class: None,
+            title: None,
+            sort: None,
+            meter: None,
+            rowspan: None,
+            covered: false,
+            value: text.to_string()
+        }
+    }
+}
+
+impl ToJson for Cell {
+    fn to_json(&self) -> Json {
+        let mut result = BTreeMap::new();
+        result.insert("text".to_string(), self.text.to_json());
+        result.insert("links".to_string(), self.links.to_json());
+        result.insert("separator".to_string(), self.separator.to_json());
+        result.insert("page".to_string(), self.page.to_json());
+        result.insert("id".to_string(), self.id.to_json());
+        result.insert("class".to_string(), self.class.to_json());
+        result.insert("title".to_string(), self.title.to_json());
+        result.insert("sort".to_string(), self.sort.to_json());
+        // As text, handlebars treats 0 as false and would hide the bar of 0%
+        result.insert("meter".to_string(), self.meter.map(|percent| percent.to_string()).to_json());
+        result.insert("rowspan".to_string(), self.rowspan.to_json());
+        result.insert("covered".to_string(), self.covered.to_json());
+        Json::Object(result)
+    }
+}
+
+/// A column of a table: id (used in the options), header and the function that fills the cell from a row
+pub struct Column<R> {
+    pub id: &'static str,
+    pub header: &'static str,
+    pub cell: fn(&R) -> Cell
+}
+
+/// Public helper function that returns all columns of the job table in the default order
+pub fn job_columns() -> Vec<Column<JobRow>> {
+    vec![
+        Column { id: "cluster", header: "Cluster", cell: |row| Cell::new(&row.cluster) },
+        Column { id: "executing_host", header: "Executing host", cell: |row| Cell::new(&row.executing_host) },
+        Column { id: "minimum_cpu", header: "Min CPU", cell: |row| Cell::new(&row.minimum_cpu) },
+        Column { id: "num_cpu", header: "Num CPU", cell: |row| Cell::new(&row.num_cpu) },
+        Column { id: "num_nodes", header: "Num nodes", cell: |row| Cell::new(&row.num_nodes) },
+        Column { id: "gpus", header: "GPUs", cell: |row| Cell::new(&row.gpus) },
+        Column { id: "job_array_id", header: "Job array ID", cell: |row| Cell::new(&row.job_array_id) },
+        Column { id: "num_sockets", header: "Number of Sockets", cell: |row| Cell::new(&row.num_sockets) },
+        Column { id: "job_id", header: "Job ID", cell: |row| Cell {
+            class: if row.is_step { Some("job_step_id".to_string()) } else { None },
+            .. Cell::new(&row.job_id)
+        } },
+        Column { id: "num_cores", header: "Number of Cores", cell: |row| Cell::new(&row.num_cores) },
+        Column { id: "job_name", header: "Job name", cell: |row| Cell::new(&row.job_name) },
+        Column { id: "num_threads", header: "Number of threads", cell: |row| Cell::new(&row.num_threads) },
+        Column { id: "job_array_index", header: "Job array index", cell: |row| Cell::new(&row.job_array_index) },
+        Column { id: "run_time", header: "Run time", cell: |row| Cell::new(&row.run_time) },
+        Column { id: "remaining", header: "Remaining", cell: |row| Cell {
+            class: row.remaining_class.clone(),
+            sort: if row.remaining_sort.is_empty() { None } else { Some(row.remaining_sort.clone()) },
+            .. Cell::new(&row.remaining)
+        } },
+        // Only the first ranges are links, the full hostlist is the tooltip and the value
+        Column { id: "nodes", header: "List of nodes", cell: |row| Cell {
+            links: row.nodes.clone(),
+            separator: ",".to_string(),
+            title: if row.more_ranges > 0 { Some(row.hostlist.clone()) } else { None },
+            value: row.hostlist.clone(),
+            .. Cell::new(&if row.more_ranges > 0 { format!(" (+{} more ranges)", row.more_ranges) } else { String::new() })
+        } },
+        // The rounded priority is shown, the exact one is the tooltip (with the factors of sprio), the sort key and the value
+        Column { id: "priority", header: "Priority", cell: |row| Cell {
+            title: match (row.priority_raw.as_ref(), row.priority_factors.as_ref()) {
+                (Some(raw), Some(factors)) => Some(format!("{}\n{}", raw, factors)),
+                (raw, factors) => raw.or(factors).cloned()
+            },
+            sort: row.priority_raw.clone(),
+            value: row.priority_raw.clone().unwrap_or_else(|| row.priority.clone()),
+            .. Cell::new(&row.priority)
+        } },
+        Column { id: "state_reason", header: "State reason", cell: |row| Cell { title: row.qos_limits.clone(), .. Cell::new(&row.state_reason) } },
+        // Dependencies on a job that failed are red, "singleton" follows the links
+        Column { id: "depends_on", header: "Depends on", cell: |row| Cell {
+            links: row.depends_on.clone(),
+            separator: ",".to_string(),
+            value: row.dependencies.clone(),
+            .. Cell::new(&match (row.depends_on.is_empty(), row.singleton) {
+                (true, _) => row.dependencies.clone(),
+                (false, true) => ",singleton".to_string(),
+                (false, false) => String::new()
+            })
+        } },
+        Column { id: "start_time", header: "Start time", cell: |row| Cell::new(&row.start_time) },
+        Column { id: "waiting_since", header: "Waiting since", cell: |row| Cell::new(&row.waiting_since) },
+        Column { id: "job_state", header: "Job state", cell: |row| Cell { class: row.state_class.clone(), .. Cell::new(&row.job_state) } },
+        Column { id: "user_name", header: "User name", cell: |row| Cell::new(&row.user_name) },
+        Column { id: "user_id", header: "User ID", cell: |row| Cell::new(&row.user_id) }
+    ]
+}
+
+/// Public helper function that returns all columns of the node table in the default order
+pub fn node_columns() -> Vec<Column<NodeRow>> {
+    vec![
+        Column { id: "cluster", header: "Cluster", cell: |row| Cell::new(&row.cluster) },
+        // The nodes are sorted by partition, the first row of each partition spans the whole group
+        Column { id: "partition", header: "Partition", cell: |row| Cell {
+            page: Some(format!("partition/{}", row.partition)),
+            class: Some("partition_group".to_string()),
+            rowspan: row.partition_rows,
+            covered: row.partition_rows.is_none(),
+            .. Cell::new(&row.partition)
+        } },
+        Column { id: "availability", header: "Availability", cell: |row| if row.available {
+            Cell::new("Up")
+        } else {
+            Cell { id: Some("partition_down".to_string()), .. Cell::new("Down") }
+        } },
+        Column { id: "hostname", header: "Hostname", cell: |row| Cell::new(&row.hostname) },
+        Column { id: "node", header: "Node", cell: |row| match row.job_anchor {
+            Some(ref anchor) => Cell { links: vec![Link { anchor: anchor.clone(), text: row.node.clone(), class: None }], value: row.node.clone(), .. Cell::new("") },
+            None => Cell::new(&row.node)
+        } },
+        Column { id: "error", header: "Error", cell: |row| Cell::new(&row.error) },
+        Column { id: "cpu_load", header: "CPU load", cell: |row| Cell {
+            class: row.load_class.clone(),
+            title: match row.load_class.as_ref().map(|class| &class[..]) {
+                Some("load_oversubscribed") => Some("More processes want to run than there are cores".to_string()),
+                Some("load_idle") => Some("Allocated, but almost no load: the job may hang".to_string()),
+                _ => None
+            },
+            value: row.cpu_load.clone(),
+            .. Cell::new(&row.relative_load)
+        } },
+        Column { id: "state", header: "Node state", cell: |row| Cell {
+            title: match (row.flags.is_empty(), row.reason.as_ref()) {
+                (false, Some(reason)) => Some(format!("{} - {}", row.flag_descriptions, reason)),
+                (false, None) => Some(row.flag_descriptions.clone()),
+                (true, reason) => reason.cloned()
+            },
+            .. Cell::new(&format!("{}{}", row.state, row.flags))
+        } },
+        Column { id: "sockets", header: "Node sockets", cell: |row| Cell::new(&row.sockets) },
+        Column { id: "cores", header: "Node cores", cell: |row| Cell::new(&row.cores) },
+        Column { id: "threads", header: "Node threads", cell: |row| Cell::new(&row.threads) },
+        Column { id: "cpus", header: "CPUs (A/I/O/T)", cell: |row| Cell::new(&row.cpus) },
+        Column { id: "utilization", header: "Utilization", cell: |row| if row.load_warning {
+            Cell {
+                class: Some("load_warning".to_string()),
+                title: Some(format!("CPU load {} doesn't match the allocation", row.cpu_load)),
+                .. Cell::new(&row.utilization)
+            }
+        } else {
+            Cell::new(&row.utilization)
+        } },
+        Column { id: "memory", header: "Memory used/total", cell: |row| Cell {
+            class: if row.memory_warning { Some("memory_warning".to_string()) } else { None },
+            title: if row.memory_warning { Some(format!("More than {}% of the memory is in use", MEMORY_WARNING_PERCENT)) } else { None },
+            sort: row.memory_percent.map(|percent| percent.to_string()),
+            meter: row.memory_percent,
+            .. Cell::new(&row.memory)
+        } },
+        Column { id: "gpus", header: "GPUs", cell: |row| Cell::new(&row.gpus) },
+        Column { id: "jobs", header: "Jobs", cell: |row| Cell {
+            links: row.jobs.clone(),
+            separator: ", ".to_string(),
+            value: if row.jobs.is_empty() { "-".to_string() } else { row.jobs.iter().map(|link| &link.text[..]).collect::<Vec<&str>>().join(", ") },
+            .. Cell::new(if row.jobs.is_empty() { "-" } else { "" })
+        } }
+    ]
+}
+
+/// Public helper function that returns the ids of the columns as comma separated list, for the error messages
+pub fn column_ids<R>(columns: &[Column<R>]) -> String {
+    columns.iter().map(|column| column.id).collect::<Vec<&str>>().join(", ")
+}
+
+/// Public helper function to parse a comma separated list of column ids
+/// Returns the known ids in the given order (each one only once) and the unknown ids
+pub fn parse_columns<R>(value: &str, columns: &[Column<R>]) -> (Vec<String>, Vec<String>) {
+    let mut selected: Vec<String> = Vec::new();
+    let mut unknown: Vec<String> = Vec::new();
+
+    for id in value.split(',').map(|id| id.trim()).filter(|id| !id.is_empty()) {
+        if !columns.iter().any(|column| column.id == id) {
+            unknown.push(id.to_string());
+        } else if !selected.iter().any(|other| other == id) {
+            selected.push(id.to_string());
+        }
+    }
+
+    (selected, unknown)
+}
+
+/// Public helper function that returns the selected columns in the selected order, all columns if the selection is empty
+pub fn selected_columns<'a, R>(columns: &'a [Column<R>], ids: &[String]) -> Vec<&'a Column<R>> {
+    if ids.is_empty() {
+        return columns.iter().collect();
+    }
+
+    ids.iter().filter_map(|id| columns.iter().find(|column| column.id == &id[..])).collect()
+}
+
+/// Public helper function that fills the cells of the selected columns from a row
+pub fn row_cells<R>(row: &R, columns: &[&Column<R>]) -> Vec<Cell> {
+    columns.iter().map(|column| (column.cell)(row)).collect()
+}
+
+#[cfg(test)]
+fn cell_texts<R>(columns: &[Column<R>], row: &R) -> Vec<(&'static str, &'static str, String, String)> {
+    columns.iter().map(|column| {
+        let cell = (column.cell)(row);
+        let links: Vec<&str> = cell.links.iter().map(|link| &link.text[..]).collect();
+        (column.id, column.header, format!("{}{}", links.join(&cell.separator), cell.text), cell.value)
+    }).collect()
+}
+
+#[test]
+fn test_job_columns() {
+    use squeue_util::get_job_info_test;
+    use status_page::job_row;
+
+    let mut jobs = get_job_info_test();
+    jobs[1].cluster = "cluster1".to_string();
+    let row = job_row(&jobs[1], None);
+    let text = |text: &str| text.to_string();
+
+    // Each column: id, header, text on the page and value in the CSV export
+    assert_eq!(cell_texts(&job_columns(), &row), vec![
+        ("cluster", "Cluster", text("cluster1"), text("cluster1")),
+        ("executing_host", "Executing host", text("node01"), text("node01")),
+        ("minimum_cpu", "Min CPU", text("1"), text("1")),
+        ("num_cpu", "Num CPU", text("2"), text("2")),
+        ("num_nodes", "Num nodes", text("2"), text("2")),
+        ("gpus", "GPUs", text(""), text("")),
+        ("job_array_id", "Job array ID", text("-"), text("-")),
+        ("num_sockets", "Number of Sockets", text("-"), text("-")),
+        ("job_id", "Job ID", text("2"), text("2")),
+        ("num_cores", "Number of Cores", text("-"), text("-")),
+        ("job_name", "Job name", text("small_test02"), text("small_test02")),
+        ("num_threads", "Number of threads", text("-"), text("-")),
+        ("job_array_index", "Job array index", text("-"), text("-")),
+        ("run_time", "Run time", text("1:15"), text("1:15")),
+        ("remaining", "Remaining", text("-"), text("-")),
+        ("nodes", "List of nodes", text("node[01-02]"), text("node[01-02]")),
+        ("priority", "Priority", text("0.9"), text("0.9")),
+        ("state_reason", "State reason", text("None"), text("None")),
+        ("depends_on", "Depends on", text("-"), text("-")),
+        ("start_time", "Start time", text("2000-01-01T09:00:00"), text("2000-01-01T09:00:00")),
+        ("waiting_since", "Waiting since", text("-"), text("-")),
+        ("job_state", "Job state", text("CANCELLED"), text("CANCELLED")),
+        ("user_name", "User name", text("user02"), text("user02")),
+        ("user_id", "User ID", text("1001"), text("1001"))
+    ]);
+}
+
+#[test]
+fn test_job_columns_markup() {
+    use squeue_util::{get_job_info_test, JobState};
+    use status_page::job_row;
+
+    let mut job = get_job_info_test().remove(0);
+    job.job_state = JobState::Failed;
+    job.run_time = Some("57:30".to_string());
+    job.time_limit = Some("1:00:00".to_string());
+    job.list_of_nodes = (1..30).filter(|n| n % 2 == 1).map(|n| format!("node{:02}", n)).collect();
+    let row = job_row(&job, None);
+    let cell = |id: &str| (job_columns().into_iter().find(|column| column.id == id).unwrap().cell)(&row);
+
+    assert_eq!(cell("job_state").class, Some("job_failed".to_string()));
+    assert_eq!(cell("job_id").class, None);
+    // The remaining time is only shown for running jobs
+    assert_eq!((cell("remaining").class, cell("remaining").sort), (None, None));
+    let nodes = cell("nodes");
+    // Only the first ranges are links, the tooltip and the CSV value are the full hostlist
+    assert_eq!(&nodes.links[0].text[..], "node[01,03,05,07,09,11,13,15]");
+    assert_eq!(&nodes.text[..], " (+7 more ranges)");
+    assert_eq!(nodes.title, Some("node[01,03,05,07,09,11,13,15,17,19,21,23,25,27,29]".to_string()));
+    assert_eq!(nodes.title.as_ref(), Some(&nodes.value));
+
+    // The priority is rounded, the exact value is the tooltip, the sort key and the CSV value
+    job.priority = Some(0.99998474074527);
+    let mut row = job_row(&job, None);
+    let priority = (job_columns().into_iter().find(|column| column.id == "priority").unwrap().cell)(&row);
+    assert_eq!((&priority.text[..], &priority.value[..]), ("0.999985", "0.99998474074527"));
+    assert_eq!((priority.title, priority.sort), (Some("0.99998474074527".to_string()), Some("0.99998474074527".to_string())));
+    row.priority_factors = Some("Age: 1000".to_string());
+    let priority = (job_columns().into_iter().find(|column| column.id == "priority").unwrap().cell)(&row);
+    assert_eq!(priority.title, Some("0.99998474074527\nAge: 1000".to_string()));
+}
+
+#[test]
+fn test_node_columns() {
+    use sinfo_util::get_partition_node_info_test;
+    use squeue_util::get_job_info_test;
+    use status_page::node_rows;
+
+    let mut nodes = get_partition_node_info_test();
+    nodes[2].reason = Some("Not responding".to_string());
+    let rows = node_rows(&nodes.iter().collect::<Vec<_>>(), &get_job_info_test());
+    let text = |text: &str| text.to_string();
+
+    assert_eq!(cell_texts(&node_columns(), &rows[2]), vec![
+        ("cluster", "Cluster", text(""), text("")),
+        ("partition", "Partition", text("esd"), text("esd")),
+        ("availability", "Availability", text("Down"), text("Down")),
+        ("hostname", "Hostname", text("node03"), text("node03")),
+        ("node", "Node", text("node03"), text("node03")),
+        ("error", "Error", text("down"), text("down")),
+        ("cpu_load", "CPU load", text("-"), text("-")),
+        ("state", "Node state", text("unknown-"), text("unknown-")),
+        ("sockets", "Node sockets", text("-"), text("-")),
+        ("cores", "Node cores", text("-"), text("-")),
+        ("threads", "Node threads", text("-"), text("-")),
+        ("cpus", "CPUs (A/I/O/T)", text("-"), text("-")),
+        ("utilization", "Utilization", text("-"), text("-")),
+        ("memory", "Memory used/total", text("- / 3.9 GB"), text("- / 3.9 GB")),
+        ("gpus", "GPUs", text(""), text("")),
+        ("jobs", "Jobs", text("6"), text("6"))
+    ]);
+
+    let cell = |row: &NodeRow, id: &str| (node_columns().into_iter().find(|column| column.id == id).unwrap().cell)(row);
+    // The first row of the partition spans all rows, the other rows have no partition cell
+    assert_eq!((cell(&rows[0], "partition").rowspan, cell(&rows[0], "partition").covered), (Some(12), false));
+    assert_eq!(cell(&rows[0], "partition").page, Some("partition/esd".to_string()));
+    assert_eq!((cell(&rows[1], "partition").rowspan, cell(&rows[1], "partition").covered), (None, true));
+    assert_eq!(cell(&rows[2], "availability").id, Some("partition_down".to_string()));
+    assert_eq!(cell(&rows[0], "availability").id, None);
+    assert_eq!(cell(&rows[2], "node").links[0].anchor, "job-6");
+    assert_eq!(cell(&rows[2], "state").title, Some("planned by backfill - Not responding".to_string()));
+    assert_eq!(cell(&rows[11], "state").title, Some("not responding".to_string()));
+    assert_eq!(cell(&rows[3], "utilization").class, Some("load_warning".to_string()));
+    assert_eq!(cell(&rows[6], "jobs").text, "-");
+    // node05 uses 95% of its memory
+    let memory = cell(&rows[4], "memory");
+    assert_eq!((&memory.text[..], memory.class, memory.meter, memory.sort), ("3.7 GB / 3.9 GB (95%)", Some("memory_warning".to_string()), Some(95), Some("95".to_string())));
+    assert_eq!(memory.title, Some("More than 90% of the memory is in use".to_string()));
+    assert_eq!((cell(&rows[0], "memory").class, cell(&rows[0], "memory").meter), (None, Some(13)));
+}
+
+#[test]
+fn test_parse_columns() {
+    let columns = job_columns();
+
+    assert_eq!(parse_columns("job_id, user_name,job_state", &columns), (vec![text("job_id"), text("user_name"), text("job_state")], vec![]));
+    assert_eq!(parse_columns("user_name,foo,user_name,,bar", &columns), (vec![text("user_name")], vec![text("foo"), text("bar")]));
+    assert_eq!(parse_columns("", &columns), (vec![], vec![]));
+    assert_eq!(parse_columns("jobs", &columns).1, vec![text("jobs")]);
+    assert!(parse_columns("jobs", &node_columns()).1.is_empty());
+    assert!(column_ids(&columns).starts_with("cluster, executing_host, minimum_cpu,"));
+
+    fn text(text: &str) -> String {
+        text.to_string()
+    }
+}
+
+#[test]
+fn test_selected_columns() {
+    let columns = job_columns();
+    let ids = |selected: Vec<&Column<JobRow>>| selected.iter().map(|column| column.id).collect::<Vec<&str>>();
+
+    assert_eq!(selected_columns(&columns, &[]).len(), columns.len());
+    assert_eq!(ids(selected_columns(&columns, &["user_name".to_string(), "job_id".to_string()])), vec!["user_name", "job_id"]);
+    assert_eq!(ids(selected_columns(&columns, &["user_name".to_string(), "unknown".to_string()])), vec!["user_name"]);
+}
@@ -0,0 +1,241 @@
+//! Record mode and replay mode of the SLURM commands
+//! With --record-dir the output of every command is written to a file, for example "squeue-20151218T100000.txt",
+//! so the exact output of a cluster can be attached to a bug report. With --replay-dir the commands are not called,
+//! instead the newest recorded file of each command is read and goes through the same parsers
+
+// System modules:
+use std::path::{Path, PathBuf};
+use std::fs::{File, read_dir, remove_file};
+use std::io::{self, Read, Write};
+
+// External modules:
+use time::{now, strftime};
+
+// Internal modules:
+use command_runner::CommandRunner;
+
+/// Format of the time stamp in the file names, sorting the names sorts the files by time
+pub const RECORD_TIME_FORMAT: &'static str = "%Y%m%dT%H%M%S";
+
+/// Extension of the recorded files
+pub const RECORD_EXTENSION: &'static str = "txt";
+
+/// Public helper function that returns the name of the recordings of a command without the time stamp
+/// It's the name of the program followed by the words of the arguments, the format strings and short options are left out:
+/// "squeue -h -o '%i %j'" is "squeue", "squeue -M cluster2 -h" is "squeue-cluster2", "scontrol show partition" is "scontrol-show-partition"
+/// and "sinfo --list-reasons -h" is "sinfo-list-reasons"
+pub fn command_key(program: &str, args: &[String]) -> String {
+    let program = Path::new(program).file_name().and_then(|name| name.to_str()).unwrap_or(program);
+    let is_word = |arg: &str| !arg.is_empty() && !arg.starts_with('-') && arg.chars().all(|c| c.is_alphanumeric() || c == '_' || c == '.' || c == '-');
+    let words = args.iter()
+        .map(|arg| if arg.starts_with("--") { &arg[2..] } else { &arg[..] })
+        .filter(|arg| is_word(arg));
+
+    Some(program).into_iter().chain(words).collect::<Vec<&str>>().join("-")
+}
+
+/// Public helper function that returns the file name of a recording
+pub fn record_file_name(key: &str, timestamp: &str) -> String {
+    format!("{}-{}.{}", key, timestamp, RECORD_EXTENSION)
+}
+
+/// Public helper function that returns the time stamp of a recording of the given command, None for all other files
+/// A recording of "squeue" is not mistaken for one of "squeue-cluster2", since the time stamp has a fixed format
+pub fn record_timestamp<'a>(file_name: &'a str, key: &str) -> Option<&'a str> {
+    let suffix = format!(".{}", RECORD_EXTENSION);
+    if !file_name.starts_with(key) || !file_name.ends_with(&suffix) || file_name.len() < key.len() + 1 + suffix.len() {
+        return None;
+    }
+
+    let rest = &file_name[key.len()..file_name.len() - suffix.len()];
+    let is_timestamp = |value: &str| value.len() == 15 && value.chars().enumerate().all(|(i, c)| if i == 8 { c == 'T' } else { c.is_digit(10) });
+    if rest.starts_with('-') && is_timestamp(&rest[1..]) {
+        Some(&rest[1..])
+    } else {
+        None
+    }
+}
+
+// Private helper function that returns the recordings of the given command, sorted from the oldest to the newest
+fn recordings(dir: &Path, key: &str) -> io::Result<Vec<PathBuf>> {
+    let mut result = Vec::new();
+
+    for entry in read_dir(dir)? {
+        let path = entry?.path();
+        let timestamp = path.file_name().and_then(|name| name.to_str()).and_then(|name| record_timestamp(name, key)).map(|timestamp| timestamp.to_string());
+        if let Some(timestamp) = timestamp {
+            result.push((timestamp, path));
+        }
+    }
+    result.sort();
+
+    Ok(result.into_iter().map(|(_, path)| path).collect())
+}
+
+/// Public helper function that writes the output of a command to a new recording
+/// Afterwards only the newest "keep" recordings of that command are kept, 0 keeps all of them
+pub fn write_recording(dir: &Path, key: &str, timestamp: &str, output: &str, keep: usize) -> io::Result<PathBuf> {
+    let path = dir.join(record_file_name(key, timestamp));
+    File::create(&path)?.write_all(output.as_bytes())?;
+
+    if keep > 0 {
+        let recordings = recordings(dir, key)?;
+        if recordings.len() > keep {
+            for old in &recordings[..recordings.len() - keep] {
+                remove_file(old)?;
+            }
+        }
+    }
+
+    Ok(path)
+}
+
+/// Public helper function that reads the newest recording of the given command
+pub fn read_newest_recording(dir: &Path, key: &str) -> Result<String, String> {
+    let recordings = recordings(dir, key).map_err(|err| format!("could not read replay directory '{}': {}", dir.display(), err))?;
+    let path = recordings.last().ok_or(format!("no recording of '{}' in '{}'", key, dir.display()))?;
+    let mut output = String::new();
+
+    File::open(path).and_then(|mut file| file.read_to_string(&mut output))
+        .map_err(|err| format!("could not read recording '{}': {}", path.display(), err))?;
+    debug!("Replay '{}'", path.display());
+    Ok(output)
+}
+
+/// Runs the commands and writes their output to the record directory, failed commands are not recorded
+/// A recording that can't be written is logged, the output is used anyway
+pub struct RecordingRunner<R: CommandRunner> {
+    /// Directory of the recordings
+    pub dir: PathBuf,
+    /// Number of recordings that are kept per command, 0 keeps all of them
+    pub keep: usize,
+    /// Runs the commands
+    pub inner: R
+}
+
+impl<R: CommandRunner> RecordingRunner<R> {
+    /// Create a new runner that records into the given directory
+    pub fn new(dir: &str, keep: usize, inner: R) -> RecordingRunner<R> {
+        RecordingRunner {
+            dir: PathBuf::from(dir),
+            keep: keep,
+            inner: inner
+        }
+    }
+}
+
+impl<R: CommandRunner> CommandRunner for RecordingRunner<R> {
+    fn run(&self, program: &str, args: &[String], environment: &[(&str, &str)]) -> Result<String, String> {
+        let output = self.inner.run(program, args, environment)?;
+        let timestamp = strftime(RECORD_TIME_FORMAT, &now()).unwrap();
+
+        if let Err(err) = write_recording(&self.dir, &command_key(program, args), &timestamp, &output, self.keep) {
+            warn!("Could not record the output of '{}' in '{}': {}", program, self.dir.display(), err);
+        }
+        Ok(output)
+    }
+}
+
+/// Doesn't call the commands, returns the newest recording of each command from the replay directory instead
+pub struct ReplayRunner {
+    /// Directory of the recordings
+    pub dir: PathBuf
+}
+
+impl ReplayRunner {
+    /// Create a new runner that replays the recordings of the given directory
+    pub fn new(dir: &str) -> ReplayRunner {
+        ReplayRunner {
+            dir: PathBuf::from(dir)
+        }
+    }
+}
+
+impl CommandRunner for ReplayRunner {
+    fn run(&self, program: &str, args: &[String], _environment: &[(&str, &str)]) -> Result<String, String> {
+        read_newest_recording(&self.dir, &command_key(program, args))
+    }
+}
+
+#[cfg(test)]
+fn temp_record_dir(name: &str) -> PathBuf {
+    use std::env::temp_dir;
+    use std::fs::create_dir;
+    use time::precise_time_ns;
+
+    let dir = temp_dir().join(format!("slurm_inspector_{}_{}", name, precise_time_ns()));
+    create_dir(&dir).unwrap();
+    dir
+}
+
+#[test]
+fn test_command_key() {
+    let args = |args: &[&str]| args.iter().map(|arg| arg.to_string()).collect::<Vec<String>>();
+
+    assert_eq!(command_key("squeue", &args(&["-h", "-o", "%B %c %C"])), "squeue");
+    assert_eq!(command_key("/opt/slurm/bin/squeue", &args(&["-M", "cluster2", "-h", "-o", "%B %c %C"])), "squeue-cluster2");
+    assert_eq!(command_key("squeue", &args(&["--version"])), "squeue-version");
+    assert_eq!(command_key("scontrol", &args(&["show", "partition"])), "scontrol-show-partition");
+    assert_eq!(command_key("sinfo", &args(&["-M", "cluster-a", "--list-reasons", "-h", "-o", "%n|%E|%U|%H"])), "sinfo-cluster-a-list-reasons");
+    assert_eq!(command_key("sacct", &args(&["-n", "-P", "-o", "JobID,JobName", "--starttime=now-24hours"])), "sacct");
+    assert_eq!(command_key("sdiag", &[]), "sdiag");
+}
+
+#[test]
+fn test_record_timestamp() {
+    assert_eq!(record_file_name("squeue", "20151218T100000"), "squeue-20151218T100000.txt");
+    assert_eq!(record_timestamp("squeue-20151218T100000.txt", "squeue"), Some("20151218T100000"));
+    assert_eq!(record_timestamp("squeue-cluster2-20151218T100000.txt", "squeue"), None);
+    assert_eq!(record_timestamp("squeue-cluster2-20151218T100000.txt", "squeue-cluster2"), Some("20151218T100000"));
+    assert_eq!(record_timestamp("squeue-20151218T100000.log", "squeue"), None);
+    assert_eq!(record_timestamp("squeue-2015121810000.txt", "squeue"), None);
+    assert_eq!(record_timestamp("squeue.txt", "squeue"), None);
+    assert_eq!(record_timestamp("sinfo-20151218T100000.txt", "squeue"), None);
+}
+
+#[test]
+fn test_write_recording() {
+    use std::fs::remove_dir_all;
+
+    let dir = temp_record_dir("record");
+    for (i, timestamp) in ["20151218T100000", "20151218T100100", "20151218T100200"].iter().enumerate() {
+        write_recording(&dir, "squeue", timestamp, &format!("output {}\n", i), 2).unwrap();
+    }
+    write_recording(&dir, "sinfo", "20151218T090000", "sinfo output\n", 2).unwrap();
+
+    // Only the two newest recordings of squeue are kept, the other commands are not affected
+    assert!(!dir.join("squeue-20151218T100000.txt").exists());
+    assert!(dir.join("squeue-20151218T100100.txt").exists());
+    assert_eq!(read_newest_recording(&dir, "squeue"), Ok("output 2\n".to_string()));
+    assert_eq!(read_newest_recording(&dir, "sinfo"), Ok("sinfo output\n".to_string()));
+    assert!(read_newest_recording(&dir, "sdiag").unwrap_err().starts_with("no recording of 'sdiag'"));
+    remove_dir_all(&dir).unwrap();
+
+    assert!(read_newest_recording(&dir, "squeue").unwrap_err().starts_with("could not read replay directory"));
+}
+
+#[test]
+fn test_record_and_replay() {
+    use std::fs::remove_dir_all;
+    use command_runner::{CommandConfig, TestRunner, run_command};
+    use squeue_util::get_job_info;
+    use inspector_metrics::SkippedWarnings;
+
+    let dir = temp_record_dir("replay");
+    let dir_name = dir.to_str().unwrap();
+    let squeue = CommandConfig::new("squeue", &[]);
+    let line = "node01 1 2 1 N/A * 42 * sim * N/A 1:00 node01 0.9 None 2015-12-18T09:00:00 RUNNING willi 1000 batch N/A 2015-12-18T08:30:00 1-00:00:00 (null)\n";
+
+    let runner = RecordingRunner::new(dir_name, 0, TestRunner::new(Ok(line.to_string())));
+    assert_eq!(run_command(&runner, &squeue, &["-h"]), Ok(line.to_string()));
+    let runner = RecordingRunner::new(dir_name, 0, TestRunner::new(Err("squeue: error".to_string())));
+    assert!(run_command(&runner, &CommandConfig::new("sinfo", &[]), &["-h"]).is_err());
+
+    // The recording goes through the squeue parser, the failed sinfo call has not been recorded
+    let runner = ReplayRunner::new(dir_name);
+    let jobs = get_job_info(&runner, &squeue, &[], None, &mut SkippedWarnings::new()).unwrap();
+    assert_eq!(jobs.items.len(), 1);
+    assert_eq!(jobs.items[0].job_name, "sim");
+    assert!(run_command(&runner, &CommandConfig::new("sinfo", &[]), &["-h"]).is_err());
+    remove_dir_all(&dir).unwrap();
+}
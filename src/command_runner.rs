@@ -0,0 +1,410 @@
+//! Runs the external SLURM commands, locally or on a remote host via ssh
+//! The path and extra arguments of each command are configurable, the runner can be replaced in tests
+//! All commands run with the C locale, so their output doesn't depend on the language of the server
+//! A command that keeps failing with the same error is logged once, followed by a short "still failing" line per attempt
+
+// System modules:
+use std::process::Command;
+use std::path::Path;
+use std::ffi::OsStr;
+use std::env;
+use std::fs;
+use std::sync::Mutex;
+use std::collections::BTreeMap;
+
+// External modules:
+use time::precise_time_ns;
+
+/// Environment of every SLURM command: the C locale and the ISO 8601 time format that the parsers expect
+/// On a server with LANG=de_DE squeue would print localized words and numbers with a decimal comma otherwise
+pub const SLURM_ENVIRONMENT: &'static [(&'static str, &'static str)] = &[
+    ("LC_ALL", "C"),
+    ("LANG", "C"),
+    ("SLURM_TIME_FORMAT", "standard")
+];
+
+/// Path and extra arguments of an external SLURM command
+#[derive(Debug, Clone, PartialEq)]
+pub struct CommandConfig {
+    /// Name (searched in PATH) or full path of the executable
+    pub path: String,
+    /// Arguments that are passed before the arguments slurm_inspector needs, for example "-M cluster2"
+    pub extra_args: Vec<String>
+}
+
+impl CommandConfig {
+    /// Create a new command configuration
+    pub fn new(path: &str, extra_args: &[String]) -> CommandConfig {
+        CommandConfig {
+            path: path.to_string(),
+            extra_args: extra_args.to_vec()
+        }
+    }
+
+    /// All arguments for a call: the extra arguments followed by the given ones
+    pub fn build_args(&self, args: &[&str]) -> Vec<String> {
+        self.extra_args.iter().cloned().chain(args.iter().map(|arg| arg.to_string())).collect()
+    }
+}
+
+/// Executes a program with the given environment variables and returns its standard output
+/// The node and the job commands of an update run in two threads, so a runner must be usable from both
+pub trait CommandRunner: Sync {
+    fn run(&self, program: &str, args: &[String], environment: &[(&str, &str)]) -> Result<String, String>;
+}
+
+/// Public helper function to check if an inherited environment variable could change the locale of a command
+/// LC_ALL and LANG are not included, since SLURM_ENVIRONMENT sets them
+pub fn is_locale_variable(name: &str) -> bool {
+    name == "LANGUAGE" || (name.starts_with("LC_") && name != "LC_ALL")
+}
+
+/// Public helper function to build the child process for a command, used for all SLURM commands
+/// If an environment is given, the inherited locale variables are removed before it is set
+pub fn build_command<S: AsRef<OsStr>>(program: &str, args: &[S], environment: &[(&str, &str)]) -> Command {
+    let mut command = Command::new(program);
+    command.args(args);
+
+    if !environment.is_empty() {
+        for (name, _) in env::vars_os() {
+            if name.to_str().map_or(false, is_locale_variable) {
+                command.env_remove(&name);
+            }
+        }
+    }
+
+    for &(name, value) in environment {
+        command.env(name, value);
+    }
+
+    command
+}
+
+impl<R: CommandRunner + ?Sized> CommandRunner for Box<R> {
+    fn run(&self, program: &str, args: &[String], environment: &[(&str, &str)]) -> Result<String, String> {
+        (**self).run(program, args, environment)
+    }
+}
+
+/// Executes the program as a child process
+pub struct SystemRunner;
+
+impl CommandRunner for SystemRunner {
+    fn run(&self, program: &str, args: &[String], environment: &[(&str, &str)]) -> Result<String, String> {
+        match build_command(program, args, environment).output() {
+            Result::Ok(val) => {
+                if val.status.success() {
+                    Ok(String::from_utf8_lossy(&val.stdout).to_string())
+                } else {
+                    Err(format!("{} ({})", String::from_utf8_lossy(&val.stderr).trim(), val.status))
+                }
+            },
+            Result::Err(err) => Err(format!("{}", err))
+        }
+    }
+}
+
+/// Executes the program on a remote host via ssh, for example on the login node of the cluster
+pub struct SshRunner<R: CommandRunner> {
+    /// The remote host, for example "user@login01"
+    pub host: String,
+    /// Extra arguments for ssh, for example "-p 2222"
+    pub ssh_args: Vec<String>,
+    /// Runs the ssh command itself
+    pub inner: R
+}
+
+impl<R: CommandRunner> SshRunner<R> {
+    /// Create a new runner for the given remote host
+    pub fn new(host: &str, ssh_args: &[String], inner: R) -> SshRunner<R> {
+        SshRunner {
+            host: host.to_string(),
+            ssh_args: ssh_args.to_vec(),
+            inner: inner
+        }
+    }
+
+    /// All arguments for ssh, the remote command line is passed as a single argument
+    /// The environment is set on the remote host with "env", BatchMode makes ssh fail instead of waiting for a password
+    pub fn build_ssh_args(&self, program: &str, args: &[String], environment: &[(&str, &str)]) -> Vec<String> {
+        let mut remote_command: Vec<String> = Vec::new();
+        if !environment.is_empty() {
+            remote_command.push("env".to_string());
+            remote_command.extend(environment.iter().map(|&(name, value)| shell_quote(&format!("{}={}", name, value))));
+        }
+        remote_command.extend(Some(program.to_string()).iter().chain(args.iter()).map(|arg| shell_quote(arg)));
+        let mut result: Vec<String> = vec!["-o".to_string(), "BatchMode=yes".to_string()];
+
+        result.extend(self.ssh_args.iter().cloned());
+        result.push(self.host.clone());
+        result.push(remote_command.join(" "));
+        result
+    }
+}
+
+impl<R: CommandRunner> CommandRunner for SshRunner<R> {
+    fn run(&self, program: &str, args: &[String], environment: &[(&str, &str)]) -> Result<String, String> {
+        // The locale of ssh itself doesn't matter
+        self.inner.run("ssh", &self.build_ssh_args(program, args, environment), &[]).map_err(|err| format!("ssh {}: {}", self.host, err))
+    }
+}
+
+/// Public helper function to quote an argument for the remote shell
+/// The remote shell splits the command line at whitespace and expands special characters, so everything else is put in single quotes
+pub fn shell_quote(arg: &str) -> String {
+    let is_safe = |c: char| c.is_alphanumeric() || "-_./=:,@+".contains(c);
+
+    if arg.len() > 0 && arg.chars().all(is_safe) {
+        arg.to_string()
+    } else {
+        format!("'{}'", arg.replace("'", "'\\''"))
+    }
+}
+
+/// How a failed command is logged
+#[derive(Debug, Clone, PartialEq)]
+pub enum FailureMessage {
+    /// First failure or a different error than last time: the error is logged
+    New,
+    /// Same error as last time, with the number of failed attempts in a row
+    Repeated(u32)
+}
+
+/// Last error and number of failed attempts in a row of each command line, so the same error isn't logged over and over
+#[derive(Debug, Clone, PartialEq)]
+pub struct FailureLog {
+    failures: BTreeMap<String, (String, u32)>
+}
+
+impl FailureLog {
+    /// Create an empty failure log
+    pub const fn new() -> FailureLog {
+        FailureLog {
+            failures: BTreeMap::new()
+        }
+    }
+
+    /// Record a failure of the command line, returns whether the error is new or repeated
+    pub fn failure(&mut self, command: &str, error: &str) -> FailureMessage {
+        let entry = self.failures.entry(command.to_string()).or_insert((String::new(), 0));
+        entry.1 += 1;
+        if entry.1 > 1 && entry.0 == error {
+            FailureMessage::Repeated(entry.1)
+        } else {
+            entry.0 = error.to_string();
+            FailureMessage::New
+        }
+    }
+
+    /// Record a successful call of the command line, returns the number of failed attempts before, None if it didn't fail
+    pub fn success(&mut self, command: &str) -> Option<u32> {
+        self.failures.remove(command).map(|(_, attempts)| attempts)
+    }
+}
+
+// The SLURM commands of the status updates run in the status thread and the thread of the node commands
+static FAILURE_LOG: Mutex<FailureLog> = Mutex::new(FailureLog::new());
+
+// Private helper function to access the failure log, a poisoned lock only means that a command thread panicked, the log is still usable
+fn with_failure_log<T, F: FnOnce(&mut FailureLog) -> T>(f: F) -> T {
+    let mut log = FAILURE_LOG.lock().unwrap_or_else(|err| err.into_inner());
+    f(&mut log)
+}
+
+/// Public helper function to run a configured command with the given arguments and SLURM_ENVIRONMENT
+/// Errors are logged and returned, so they can be shown on the web page, a repeated error is logged as "still failing"
+pub fn run_command(runner: &dyn CommandRunner, command: &CommandConfig, args: &[&str]) -> Result<String, String> {
+    let args = command.build_args(args);
+    let command_line = format!("{} {}", command.path, args.join(" "));
+
+    let start = precise_time_ns();
+    let result = runner.run(&command.path, &args, SLURM_ENVIRONMENT);
+    debug!("'{}' took {:.1} ms", command_line, (precise_time_ns() - start) as f64 / 1000000.0);
+
+    match result {
+        Ok(output) => {
+            if let Some(attempts) = with_failure_log(|log| log.success(&command_line)) {
+                info!("'{}' works again after {} failed attempts", command.path, attempts);
+            }
+            Ok(output)
+        }
+        Err(err) => {
+            let message = format!("Could not execute '{}': {}", command.path, err);
+            match with_failure_log(|log| log.failure(&command_line, &message)) {
+                FailureMessage::New => error!("{}", message),
+                FailureMessage::Repeated(attempts) => warn!("'{}' still failing, {} attempts", command.path, attempts)
+            }
+            Err(message)
+        }
+    }
+}
+
+/// Public helper function to split extra arguments given on the command line at whitespace
+pub fn split_args(args: &str) -> Vec<String> {
+    args.split_whitespace().map(|arg| arg.to_string()).collect()
+}
+
+// Private helper function to check if the file exists and is executable
+#[cfg(unix)]
+fn is_executable(path: &Path) -> bool {
+    use std::os::unix::fs::PermissionsExt;
+
+    fs::metadata(path).map(|metadata| metadata.is_file() && metadata.permissions().mode() & 0o111 != 0).unwrap_or(false)
+}
+
+#[cfg(not(unix))]
+fn is_executable(path: &Path) -> bool {
+    fs::metadata(path).map(|metadata| metadata.is_file()).unwrap_or(false)
+}
+
+/// Public helper function to check that the command can be executed
+/// Names without a path separator are searched in PATH, like Command does
+pub fn check_executable(path: &str) -> Result<(), String> {
+    if path.contains('/') {
+        if is_executable(Path::new(path)) {
+            Ok(())
+        } else {
+            Err(format!("'{}' does not exist or is not executable", path))
+        }
+    } else {
+        let found = env::var_os("PATH")
+            .map(|paths| env::split_paths(&paths).any(|dir| is_executable(&dir.join(path))))
+            .unwrap_or(false);
+
+        if found {
+            Ok(())
+        } else {
+            Err(format!("'{}' was not found in PATH", path))
+        }
+    }
+}
+
+#[cfg(test)]
+pub struct TestRunner {
+    pub output: Result<String, String>,
+    pub calls: Mutex<Vec<(String, Vec<String>, Vec<(String, String)>)>>
+}
+
+#[cfg(test)]
+impl TestRunner {
+    pub fn new(output: Result<String, String>) -> TestRunner {
+        TestRunner { output: output, calls: Mutex::new(Vec::new()) }
+    }
+}
+
+#[cfg(test)]
+impl CommandRunner for TestRunner {
+    fn run(&self, program: &str, args: &[String], environment: &[(&str, &str)]) -> Result<String, String> {
+        let environment = environment.iter().map(|&(name, value)| (name.to_string(), value.to_string())).collect();
+        self.calls.lock().unwrap().push((program.to_string(), args.to_vec(), environment));
+        self.output.clone()
+    }
+}
+
+#[test]
+fn test_build_args() {
+    let command = CommandConfig::new("/opt/slurm/current/bin/squeue", &split_args("-M cluster2"));
+    assert_eq!(command.build_args(&["-h", "-o", "%B %c"]), vec!["-M", "cluster2", "-h", "-o", "%B %c"]);
+
+    let command = CommandConfig::new("squeue", &[]);
+    assert_eq!(command.build_args(&["-h"]), vec!["-h"]);
+}
+
+#[test]
+fn test_split_args() {
+    assert_eq!(split_args(""), Vec::<String>::new());
+    assert_eq!(split_args("  -M   cluster2 "), vec!["-M", "cluster2"]);
+}
+
+#[test]
+fn test_run_command() {
+    let runner = TestRunner::new(Ok("output".to_string()));
+    let command = CommandConfig::new("/opt/slurm/bin/sinfo", &split_args("-M cluster2"));
+
+    assert_eq!(run_command(&runner, &command, &["-h"]), Ok("output".to_string()));
+    let calls = runner.calls.lock().unwrap();
+    assert_eq!(calls.len(), 1);
+    assert_eq!(calls[0].0, "/opt/slurm/bin/sinfo");
+    assert_eq!(calls[0].1, vec!["-M", "cluster2", "-h"]);
+}
+
+#[test]
+fn test_run_command_environment() {
+    let runner = TestRunner::new(Ok("output".to_string()));
+    run_command(&runner, &CommandConfig::new("squeue", &[]), &["-h"]).unwrap();
+
+    let calls = runner.calls.lock().unwrap();
+    let environment: Vec<(&str, &str)> = calls[0].2.iter().map(|&(ref name, ref value)| (&name[..], &value[..])).collect();
+    assert_eq!(environment, vec![("LC_ALL", "C"), ("LANG", "C"), ("SLURM_TIME_FORMAT", "standard")]);
+}
+
+#[test]
+fn test_is_locale_variable() {
+    assert!(is_locale_variable("LC_NUMERIC"));
+    assert!(is_locale_variable("LC_TIME"));
+    assert!(is_locale_variable("LANGUAGE"));
+    assert!(!is_locale_variable("LC_ALL"));
+    assert!(!is_locale_variable("LANG"));
+    assert!(!is_locale_variable("PATH"));
+}
+
+#[test]
+fn test_run_command_error() {
+    let runner = TestRunner::new(Err("No such file or directory".to_string()));
+    assert_eq!(run_command(&runner, &CommandConfig::new("sinfo", &[]), &["-h"]), Err("Could not execute 'sinfo': No such file or directory".to_string()));
+}
+
+#[test]
+fn test_failure_log() {
+    let mut log = FailureLog::new();
+
+    assert_eq!(log.success("sinfo -h"), None);
+    assert_eq!(log.failure("sinfo -h", "Connection refused"), FailureMessage::New);
+    assert_eq!(log.failure("sinfo -h", "Connection refused"), FailureMessage::Repeated(2));
+    assert_eq!(log.failure("sinfo -h", "Connection refused"), FailureMessage::Repeated(3));
+    // Other commands are counted on their own
+    assert_eq!(log.failure("squeue -h", "Connection refused"), FailureMessage::New);
+    // A different error is logged again, but still counted
+    assert_eq!(log.failure("sinfo -h", "Connection timed out"), FailureMessage::New);
+    assert_eq!(log.failure("sinfo -h", "Connection timed out"), FailureMessage::Repeated(5));
+    assert_eq!(log.success("sinfo -h"), Some(5));
+    assert_eq!(log.failure("sinfo -h", "Connection timed out"), FailureMessage::New);
+}
+
+#[test]
+fn test_shell_quote() {
+    assert_eq!(shell_quote("squeue"), "squeue");
+    assert_eq!(shell_quote("/opt/slurm/bin/squeue"), "/opt/slurm/bin/squeue");
+    assert_eq!(shell_quote("-h"), "-h");
+    assert_eq!(shell_quote("%R %a %n"), "'%R %a %n'");
+    assert_eq!(shell_quote("it's"), "'it'\\''s'");
+    assert_eq!(shell_quote(""), "''");
+    assert_eq!(shell_quote("$HOME;rm"), "'$HOME;rm'");
+}
+
+#[test]
+fn test_ssh_runner() {
+    let runner = SshRunner::new("user@login01", &split_args("-p 2222"), TestRunner::new(Ok("output".to_string())));
+    let command = CommandConfig::new("squeue", &split_args("-M cluster2"));
+
+    assert_eq!(run_command(&runner, &command, &["-h", "-o", "%B %c %C"]), Ok("output".to_string()));
+    assert_eq!(*runner.inner.calls.lock().unwrap(), vec![("ssh".to_string(), vec![
+        "-o".to_string(), "BatchMode=yes".to_string(), "-p".to_string(), "2222".to_string(), "user@login01".to_string(),
+        "env LC_ALL=C LANG=C SLURM_TIME_FORMAT=standard squeue -M cluster2 -h -o '%B %c %C'".to_string()
+    ], vec![])]);
+}
+
+#[test]
+fn test_ssh_runner_error() {
+    let runner = SshRunner::new("user@login01", &[], TestRunner::new(Err("Connection refused (exit code: 255)".to_string())));
+
+    assert_eq!(run_command(&runner, &CommandConfig::new("sinfo", &[]), &["-h"]),
+        Err("Could not execute 'sinfo': ssh user@login01: Connection refused (exit code: 255)".to_string()));
+}
+
+#[test]
+fn test_check_executable() {
+    assert!(check_executable("/this/file/does/not/exist").is_err());
+    assert!(check_executable("this_command_does_not_exist_anywhere").is_err());
+    assert!(check_executable(&env::temp_dir().to_string_lossy()).is_err());
+}
@@ -0,0 +1,170 @@
+//! Compression of HTTP response bodies
+//! Selects gzip or deflate based on the Accept-Encoding header and compresses pages via flate2
+
+// System modules:
+use std::io::Write;
+
+// External modules:
+use flate2::Compression;
+use flate2::write::{GzEncoder, ZlibEncoder};
+use iron::headers::{Encoding, QualityItem};
+
+/// Pages smaller than this (in bytes) are always sent uncompressed
+pub const MIN_COMPRESS_SIZE: usize = 1024;
+
+/// Content encodings supported by slurm_inspector
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PageEncoding {
+    /// gzip format, preferred if the client accepts it
+    Gzip,
+    /// zlib format (HTTP "deflate"), used as fallback
+    Deflate
+}
+
+/// A rendered page together with its compressed variants
+/// This is created once per update, so requests don't have to compress the page again
+#[derive(Debug, Clone, PartialEq)]
+pub struct CompressedPage {
+    /// The uncompressed page
+    pub plain: String,
+    /// gzip compressed page, None if the page is too small or compression failed
+    pub gzip: Option<Vec<u8>>,
+    /// deflate compressed page, None if the page is too small or compression failed
+    pub deflate: Option<Vec<u8>>
+}
+
+impl CompressedPage {
+    /// Create a new CompressedPage, compress the page if it's bigger than MIN_COMPRESS_SIZE
+    pub fn new(page: String) -> CompressedPage {
+        let (gzip, deflate) = if page.len() < MIN_COMPRESS_SIZE {
+            (None, None)
+        } else {
+            (compress(page.as_bytes(), PageEncoding::Gzip), compress(page.as_bytes(), PageEncoding::Deflate))
+        };
+
+        CompressedPage {
+            plain: page,
+            gzip: gzip,
+            deflate: deflate
+        }
+    }
+
+    /// Returns the compressed page for the given encoding if available
+    pub fn encoded(&self, encoding: PageEncoding) -> Option<&Vec<u8>> {
+        match encoding {
+            PageEncoding::Gzip => self.gzip.as_ref(),
+            PageEncoding::Deflate => self.deflate.as_ref()
+        }
+    }
+}
+
+/// Public helper function to choose the best encoding from the Accept-Encoding header values
+/// gzip is preferred over deflate, encodings with a quality of zero are ignored
+pub fn choose_encoding(accepted: &[QualityItem<Encoding>]) -> Option<PageEncoding> {
+    let is_accepted = |wanted: &Encoding| accepted.iter().any(|qi| qi.item == *wanted && qi.quality.0 > 0);
+
+    if is_accepted(&Encoding::Gzip) {
+        Some(PageEncoding::Gzip)
+    } else if is_accepted(&Encoding::Deflate) {
+        Some(PageEncoding::Deflate)
+    } else {
+        None
+    }
+}
+
+/// Public helper function to compress the given data with the given encoding
+/// On error returns None, the caller should send the data uncompressed instead
+pub fn compress(data: &[u8], encoding: PageEncoding) -> Option<Vec<u8>> {
+    let result = match encoding {
+        PageEncoding::Gzip => {
+            let mut encoder = GzEncoder::new(Vec::new(), Compression::Default);
+            encoder.write_all(data).and_then(|_| encoder.finish())
+        },
+        PageEncoding::Deflate => {
+            let mut encoder = ZlibEncoder::new(Vec::new(), Compression::Default);
+            encoder.write_all(data).and_then(|_| encoder.finish())
+        }
+    };
+
+    match result {
+        Result::Ok(compressed) => Some(compressed),
+        Result::Err(err) => {
+            error!("Could not compress page: {}", err);
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+use std::io::Read;
+#[cfg(test)]
+use flate2::read::{GzDecoder, ZlibDecoder};
+#[cfg(test)]
+use iron::headers::Quality;
+#[cfg(test)]
+use slurm_status::{SlurmStatus, PageOptions, status_to_html};
+#[cfg(test)]
+use sinfo_util::get_partition_node_info_test;
+#[cfg(test)]
+use squeue_util::get_job_info_test;
+
+#[cfg(test)]
+fn quality_item(encoding: Encoding, quality: u16) -> QualityItem<Encoding> {
+    QualityItem{ item: encoding, quality: Quality(quality) }
+}
+
+#[cfg(test)]
+fn test_page() -> String {
+    let mut status = SlurmStatus::new();
+    status.node_info = get_partition_node_info_test();
+    status.job_info = get_job_info_test();
+    status.last_update = "2015.12.20 - 12:00".to_string();
+
+    status_to_html(&status, &PageOptions{ page_refresh: 60, .. PageOptions::default() }).unwrap()
+}
+
+#[test]
+fn test_choose_encoding_none() {
+    assert_eq!(choose_encoding(&[]), None);
+    assert_eq!(choose_encoding(&[quality_item(Encoding::Identity, 1000)]), None);
+}
+
+#[test]
+fn test_choose_encoding_gzip() {
+    assert_eq!(choose_encoding(&[quality_item(Encoding::Gzip, 1000)]), Some(PageEncoding::Gzip));
+    assert_eq!(choose_encoding(&[quality_item(Encoding::Deflate, 1000), quality_item(Encoding::Gzip, 1000)]), Some(PageEncoding::Gzip));
+}
+
+#[test]
+fn test_choose_encoding_deflate() {
+    assert_eq!(choose_encoding(&[quality_item(Encoding::Deflate, 1000)]), Some(PageEncoding::Deflate));
+    assert_eq!(choose_encoding(&[quality_item(Encoding::Gzip, 0), quality_item(Encoding::Deflate, 500)]), Some(PageEncoding::Deflate));
+}
+
+#[test]
+fn test_compressed_page_small() {
+    let page = CompressedPage::new("<html></html>".to_string());
+    assert_eq!(page.plain, "<html></html>");
+    assert_eq!(page.encoded(PageEncoding::Gzip), None);
+    assert_eq!(page.encoded(PageEncoding::Deflate), None);
+}
+
+#[test]
+fn test_compressed_page_gzip() {
+    let page = CompressedPage::new(test_page());
+    let mut decoder = GzDecoder::new(&page.encoded(PageEncoding::Gzip).unwrap()[..]).unwrap();
+    let mut result = String::new();
+    decoder.read_to_string(&mut result).unwrap();
+
+    assert_eq!(result, test_page());
+}
+
+#[test]
+fn test_compressed_page_deflate() {
+    let page = CompressedPage::new(test_page());
+    let mut decoder = ZlibDecoder::new(&page.encoded(PageEncoding::Deflate).unwrap()[..]);
+    let mut result = String::new();
+    decoder.read_to_string(&mut result).unwrap();
+
+    assert_eq!(result, test_page());
+}
@@ -0,0 +1,259 @@
+//! The configuration in effect as JSON (/api/config) and as table of the page (with --debug-endpoints)
+//! Secrets are never shown: the webhook URL (which often contains a token) and the SMTP credentials are replaced by REDACTED.
+//! All values come from the defaults and the command line, the cluster name and the SLURM version are detected at startup
+
+// System modules:
+use std::collections::BTreeMap;
+
+// External modules:
+use rustc_serialize::json::{Json, ToJson};
+
+// Internal modules:
+use configuration::{Configuration, PartitionSelection};
+
+/// Shown instead of a secret that is set, a secret that isn't set is null
+pub const REDACTED: &'static str = "(redacted)";
+
+/// One setting of the configuration table on the page
+#[derive(Debug, Clone, PartialEq)]
+pub struct ConfigEntry {
+    pub name: String,
+    pub value: String
+}
+
+impl ToJson for ConfigEntry {
+    fn to_json(&self) -> Json {
+        let mut result = BTreeMap::new();
+        result.insert("name".to_string(), self.name.to_json());
+        result.insert("value".to_string(), self.value.to_json());
+        Json::Object(result)
+    }
+}
+
+// Private helper function that hides the value of a secret, but still tells if it's set
+fn redact(secret: &Option<String>) -> Json {
+    secret.as_ref().map(|_| REDACTED.to_string()).to_json()
+}
+
+// Private helper function for the enums of the configuration, the lower case name is the value of the command line option
+fn enum_name<T: ::std::fmt::Debug>(value: &T) -> Json {
+    format!("{:?}", value).to_lowercase().to_json()
+}
+
+/// Public helper function that returns the configuration without secrets as JSON object, the keys are the field names
+/// The templates are left out, template_dir tells where they come from
+pub fn config_json(config: &Configuration) -> Json {
+    let mut result = BTreeMap::new();
+    let mut insert = |name: &str, value: Json| { result.insert(name.to_string(), value); };
+
+    insert("port", config.port.to_json());
+    insert("interval", config.interval.to_json());
+    insert("sinfo_interval", config.sinfo_interval.to_json());
+    insert("squeue_interval", config.squeue_interval.to_json());
+    insert("test_mode", config.test_mode.to_json());
+    insert("log_level", config.log_level.to_json());
+    insert("log_target", enum_name(&config.log_target));
+    insert("log_format", enum_name(&config.log_format));
+    insert("log_dir", config.log_dir.to_json());
+    insert("log_rotate_size", config.log_rotate_size.to_json());
+    insert("log_keep", config.log_keep.to_json());
+    insert("page_refresh", config.page_refresh.to_json());
+    insert("custom_css", config.custom_css.to_json());
+    insert("banner_file", config.banner_file.to_json());
+    insert("finished_hours", config.finished_hours.to_json());
+    insert("recently_finished_minutes", config.recently_finished_minutes.to_json());
+    insert("enable_sshare", config.enable_sshare.to_json());
+    insert("enable_sprio", config.enable_sprio.to_json());
+    insert("anonymize", config.anonymize.to_json());
+    insert("users", config.users.to_json());
+    insert("history_size", config.history_size.to_json());
+    insert("history_db", config.history_db.to_json());
+    insert("history_retention", config.history_retention.to_json());
+    insert("cluster_name", config.cluster_name.to_json());
+    insert("slurm_version", config.slurm_version.to_json());
+    insert("webhook_url", redact(&config.webhook_url));
+    insert("webhook_cooldown", config.webhook_cooldown.to_json());
+    insert("email", config.email.as_ref().map_or(Json::Null, |email| {
+        let mut email_json = BTreeMap::new();
+        email_json.insert("smtp_server".to_string(), email.smtp_server.to_json());
+        email_json.insert("smtp_port".to_string(), email.smtp_port.to_json());
+        email_json.insert("from".to_string(), email.from.to_json());
+        email_json.insert("to".to_string(), email.to.to_json());
+        email_json.insert("smtp_user".to_string(), redact(&email.smtp_user));
+        email_json.insert("smtp_password_file".to_string(), redact(&email.smtp_password_file));
+        email_json.insert("node_threshold".to_string(), email.node_threshold.to_json());
+        email_json.insert("failing_minutes".to_string(), email.failing_minutes.to_json());
+        email_json.insert("cooldown".to_string(), email.cooldown.to_json());
+        Json::Object(email_json)
+    }));
+    insert("squeue_path", config.squeue_path.to_json());
+    insert("squeue_args", config.squeue_args.to_json());
+    insert("squeue_states", config.squeue_states.to_json());
+    insert("sinfo_path", config.sinfo_path.to_json());
+    insert("sinfo_args", config.sinfo_args.to_json());
+    insert("remote_host", config.remote_host.to_json());
+    insert("remote_ssh_args", config.remote_ssh_args.to_json());
+    insert("clusters", config.clusters.to_json());
+    insert("once", config.once.to_json());
+    insert("format", enum_name(&config.format));
+    insert("partitions", match config.partitions {
+        PartitionSelection::All => "all".to_json(),
+        PartitionSelection::Exclude(ref partitions) => {
+            let mut selection = BTreeMap::new();
+            selection.insert("exclude".to_string(), partitions.to_json());
+            Json::Object(selection)
+        },
+        PartitionSelection::Include(ref partitions) => {
+            let mut selection = BTreeMap::new();
+            selection.insert("include".to_string(), partitions.to_json());
+            Json::Object(selection)
+        }
+    });
+    insert("access_log", config.access_log.to_json());
+    insert("access_log_file", config.access_log_file.to_json());
+    insert("show_job_steps", config.show_job_steps.to_json());
+    insert("enable_partition_limits", config.enable_partition_limits.to_json());
+    insert("enable_licenses", config.enable_licenses.to_json());
+    insert("enable_qos", config.enable_qos.to_json());
+    insert("enable_sreport", config.enable_sreport.to_json());
+    insert("sreport_interval", config.sreport_interval.to_json());
+    insert("pressure_thresholds", config.pressure_thresholds.to_text().to_json());
+    insert("page_limit", config.page_limit.to_json());
+    insert("grid_width", config.grid_width.to_json());
+    insert("template_dir", config.template_dir.to_json());
+    insert("refresh_spacing", config.refresh_spacing.to_json());
+    insert("url_prefix", config.url_prefix.to_json());
+    insert("trust_proxy", config.trust_proxy.to_json());
+    insert("remote_user_header", config.remote_user_header.to_json());
+    insert("enable_admin_actions", config.enable_admin_actions.to_json());
+    insert("debug_endpoints", config.debug_endpoints.to_json());
+    insert("cors_allow_origin", config.cors_allow_origin.to_json());
+    insert("allowed_ips", config.allowed_ips.iter().map(|network| network.to_string()).collect::<Vec<String>>().to_json());
+    insert("listen_socket", config.listen_socket.to_json());
+    insert("socket_mode", format!("0{:o}", config.socket_mode).to_json());
+    insert("tls_cert", config.tls_cert.to_json());
+    insert("tls_key", config.tls_key.to_json());
+    insert("frame_options", enum_name(&config.frame_options));
+    insert("http_threads", config.http_threads.to_json());
+    insert("max_requests", config.max_requests.to_json());
+    insert("http_read_timeout", config.http_read_timeout.to_json());
+    insert("http_write_timeout", config.http_write_timeout.to_json());
+    insert("record_dir", config.record_dir.to_json());
+    insert("record_keep", config.record_keep.to_json());
+    insert("replay_dir", config.replay_dir.to_json());
+    insert("prom_textfile", config.prom_textfile.to_json());
+    insert("timezone", config.display_time.timezone.to_string().to_json());
+    insert("date_format", config.display_time.date_format.to_json());
+    insert("test_data", config.test_data.as_ref().map_or(Json::Null, |test_data| {
+        let mut test_data_json = BTreeMap::new();
+        test_data_json.insert("nodes".to_string(), test_data.nodes.to_json());
+        test_data_json.insert("partitions".to_string(), test_data.partitions.to_json());
+        test_data_json.insert("jobs".to_string(), test_data.jobs.to_json());
+        test_data_json.insert("seed".to_string(), test_data.seed.to_json());
+        Json::Object(test_data_json)
+    }));
+    insert("job_columns", config.job_columns.to_json());
+    insert("node_columns", config.node_columns.to_json());
+    insert("default_sort", config.default_sort.as_str().to_json());
+
+    Json::Object(result)
+}
+
+/// Public helper function that returns the answer of /api/config
+pub fn config_to_json(config: &Configuration) -> String {
+    config_json(config).to_string()
+}
+
+/// Public helper function that returns the settings for the table on the page, sorted by name
+/// Strings are shown without quotes, unset values as "-" and everything else as JSON
+pub fn config_entries(config: &Configuration) -> Vec<ConfigEntry> {
+    match config_json(config) {
+        Json::Object(settings) => settings.into_iter().map(|(name, value)| ConfigEntry {
+            name: name,
+            value: match value {
+                Json::String(text) => text,
+                Json::Null => "-".to_string(),
+                other => other.to_string()
+            }
+        }).collect(),
+        _ => Vec::new()
+    }
+}
+
+#[cfg(test)]
+fn config_with_secrets() -> Configuration {
+    use email_notify::EmailConfig;
+
+    Configuration {
+        webhook_url: Some("https://hooks.example.com/services/T0001/SECRET_TOKEN".to_string()),
+        email: Some(EmailConfig {
+            smtp_server: "mail.example.com".to_string(),
+            smtp_port: 587,
+            from: "slurm@example.com".to_string(),
+            to: vec!["admins@example.com".to_string()],
+            smtp_user: Some("secret_user".to_string()),
+            smtp_password_file: Some("/etc/slurm_inspector/secret_password".to_string()),
+            node_threshold: 1,
+            failing_minutes: 15,
+            cooldown: 3600
+        }),
+        .. Configuration::default()
+    }
+}
+
+#[test]
+fn test_config_to_json() {
+    use configuration::LogTarget;
+    use security::parse_ip_network;
+
+    let config = Configuration {
+        interval: 120,
+        log_target: LogTarget::Stdout,
+        partitions: PartitionSelection::Exclude(vec!["debug".to_string()]),
+        allowed_ips: vec![parse_ip_network("10.0.0.0/8").unwrap()],
+        .. Configuration::default()
+    };
+    let json = Json::from_str(&config_to_json(&config)).unwrap();
+    assert_eq!(json.find("interval").and_then(|value| value.as_u64()), Some(120));
+    assert_eq!(json.find("log_target").and_then(|value| value.as_string()), Some("stdout"));
+    assert_eq!(json.find("frame_options").and_then(|value| value.as_string()), Some("sameorigin"));
+    assert_eq!(json.find("socket_mode").and_then(|value| value.as_string()), Some("0660"));
+    assert_eq!(json.find_path(&["partitions", "exclude"]), Some(&vec!["debug".to_string()].to_json()));
+    assert_eq!(json.find("allowed_ips"), Some(&vec!["10.0.0.0/8".to_string()].to_json()));
+    assert_eq!(json.find("webhook_url"), Some(&Json::Null));
+    assert_eq!(json.find("email"), Some(&Json::Null));
+    assert!(json.find("templates").is_none());
+}
+
+#[test]
+fn test_config_to_json_redacts_secrets() {
+    let config = config_with_secrets();
+    let text = config_to_json(&config);
+    for secret in &["SECRET_TOKEN", "hooks.example.com", "secret_user", "secret_password"] {
+        assert!(!text.contains(secret), "{} leaked: {}", secret, text);
+    }
+
+    let json = Json::from_str(&text).unwrap();
+    assert_eq!(json.find("webhook_url").and_then(|value| value.as_string()), Some(REDACTED));
+    assert_eq!(json.find_path(&["email", "smtp_user"]).and_then(|value| value.as_string()), Some(REDACTED));
+    assert_eq!(json.find_path(&["email", "smtp_password_file"]).and_then(|value| value.as_string()), Some(REDACTED));
+    // The other email settings are shown
+    assert_eq!(json.find_path(&["email", "smtp_server"]).and_then(|value| value.as_string()), Some("mail.example.com"));
+
+    // The table of the page is built from the same JSON
+    let entries = config_entries(&config);
+    assert!(entries.iter().all(|entry| !entry.value.contains("SECRET_TOKEN") && !entry.value.contains("secret")));
+    assert_eq!(entries.iter().find(|entry| entry.name == "webhook_url").map(|entry| &entry.value[..]), Some(REDACTED));
+}
+
+#[test]
+fn test_config_entries() {
+    let entries = config_entries(&Configuration::default());
+    let value = |name: &str| entries.iter().find(|entry| entry.name == name).map(|entry| entry.value.clone());
+    assert_eq!(value("log_level"), Some("info".to_string()));
+    assert_eq!(value("port"), Some("4545".to_string()));
+    assert_eq!(value("log_dir"), Some("-".to_string()));
+    assert_eq!(value("users"), Some("[]".to_string()));
+    assert_eq!(entries[0].name, "access_log");
+    assert!(entries.windows(2).all(|pair| pair[0].name < pair[1].name));
+}
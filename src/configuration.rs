@@ -1,9 +1,94 @@
 //! Handles the configuration for slurm_inspector
 //! Parses command line arguments via clap and sets default values
 
+// System modules:
+use std::fmt;
+use std::error::Error;
+use std::str::FromStr;
+use std::sync::Arc;
+
 // External modules:
 use clap::App;
 
+// Internal modules:
+use history::DEFAULT_HISTORY_SIZE;
+use history_db::DEFAULT_HISTORY_RETENTION;
+use recent_jobs::DEFAULT_RECENTLY_FINISHED_MINUTES;
+use pagination::DEFAULT_PAGE_LIMIT;
+use occupancy_grid::DEFAULT_GRID_WIDTH;
+use template::{Templates, builtin_templates};
+use refresh::DEFAULT_REFRESH_SPACING;
+use request_limit::{DEFAULT_HTTP_THREADS, DEFAULT_MAX_REQUESTS, DEFAULT_HTTP_READ_TIMEOUT, DEFAULT_HTTP_WRITE_TIMEOUT};
+use cluster_info::UNKNOWN;
+use command_runner::split_args;
+use synthetic_data::SyntheticData;
+use columns::{Column, job_columns, node_columns, column_ids, parse_columns};
+use job_sort::{JobSort, parse_job_sort};
+use display_time::{DisplayTime, DEFAULT_DATE_FORMAT, parse_display_timezone, check_date_format};
+use email_notify::{EmailConfig, DEFAULT_SMTP_PORT, DEFAULT_EMAIL_NODE_THRESHOLD, DEFAULT_EMAIL_FAILING_MINUTES, DEFAULT_EMAIL_COOLDOWN};
+use security::{IpNetwork, FrameOptions, parse_ip_network, parse_frame_options};
+use squeue_util::{JobState, DEFAULT_SQUEUE_STATES};
+use status_filter::parse_job_states;
+use unix_socket::DEFAULT_SOCKET_MODE;
+use sreport_util::DEFAULT_SREPORT_INTERVAL;
+use scheduler_pressure::{PressureThresholds, PRESSURE_INDICATORS, parse_pressure_thresholds};
+use cors::normalize_origin;
+
+/// Default size in bytes at which the log file is rotated: 10 MB
+pub const DEFAULT_LOG_ROTATE_SIZE: u64 = 10 * 1024 * 1024;
+
+/// Default number of rotated log files that are kept
+pub const DEFAULT_LOG_KEEP: usize = 7;
+
+/// Output format of the --once mode
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum OutputFormat {
+    Html,
+    Json,
+    Text
+}
+
+/// Where the log messages are written to
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum LogTarget {
+    /// Log file in the log directory (flexi_logger)
+    File,
+    /// Standard output, for example for docker or journald
+    Stdout,
+    /// Standard error
+    Stderr
+}
+
+/// Format of the log lines
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum LogFormat {
+    /// Time stamp, level, target and message as text
+    Detailed,
+    /// One JSON object per line, for log aggregators like Loki
+    Json
+}
+
+/// Which partitions are shown, set with --exclude-partitions or --include-partitions
+#[derive(Debug, Clone, PartialEq)]
+pub enum PartitionSelection {
+    /// Show all partitions
+    All,
+    /// Hide these partitions
+    Exclude(Vec<String>),
+    /// Show only these partitions
+    Include(Vec<String>)
+}
+
+impl PartitionSelection {
+    /// Returns true if the given partition is shown
+    pub fn is_visible(&self, partition: &str) -> bool {
+        match *self {
+            PartitionSelection::All => true,
+            PartitionSelection::Exclude(ref partitions) => !partitions.iter().any(|name| name == partition),
+            PartitionSelection::Include(ref partitions) => partitions.iter().any(|name| name == partition)
+        }
+    }
+}
 
 /// slurm_inspector configuration (from command line arguments)
 #[derive(Debug, Clone, PartialEq)]
@@ -12,15 +97,850 @@ pub struct Configuration {
     pub port: u16,
     /// Update time intervall for SLURM status thread in seconds, default: 60 sec.
     pub interval: u64,
+    /// Update time interval of the node information (sinfo) in seconds, default: same as interval
+    pub sinfo_interval: u64,
+    /// Update time interval of the job information (squeue) in seconds, default: same as interval
+    pub squeue_interval: u64,
     /// If test mode is enabled, create some test data and do not call external commands ("sinfo", "squeue")
     pub test_mode: bool,
     /// Set the log level for flexi_logger: error, info or debug
-    pub log_level: String
+    pub log_level: String,
+    /// Where the log messages are written to, default: file
+    pub log_target: LogTarget,
+    /// Format of the log lines, default: detailed
+    pub log_format: LogFormat,
+    /// Directory of the log file, default: the current directory
+    pub log_dir: Option<String>,
+    /// The log file is rotated when it gets bigger than this size in bytes, 0 disables the rotation, default: 10 MB
+    pub log_rotate_size: u64,
+    /// Number of rotated log files that are kept, default: 7
+    pub log_keep: usize,
+    /// Auto refresh time interval for the web page in seconds, 0 disables auto refresh, default: same as interval
+    pub page_refresh: u64,
+    /// Path to a custom stylesheet that is served in addition to the default one
+    pub custom_css: Option<String>,
+    /// Text file with a banner for the top of the page, for example about a maintenance. It's read on every update
+    pub banner_file: Option<String>,
+    /// Show jobs that finished in the last hours (from sacct), default: 24 hours
+    pub finished_hours: u64,
+    /// Jobs that disappeared from squeue are shown for this number of minutes with their last state, 0 disables it, default: 10 minutes
+    pub recently_finished_minutes: u64,
+    /// Retrieve and show fair-share information (from sshare), needs SLURM accounting
+    pub enable_sshare: bool,
+    /// Retrieve the priority factors of the pending jobs (from sprio), shown as tooltip of the priority and at /api/priorities
+    pub enable_sprio: bool,
+    /// Hide user names, user ids and job names, for example for public dashboards
+    pub anonymize: bool,
+    /// Only show the jobs of these users (team view), empty shows all users, see StatusFilter::for_team
+    pub users: Vec<String>,
+    /// Number of updates kept in the history, default: 60
+    pub history_size: usize,
+    /// Write the history to this SQLite database, /api/history reads from it, see history_db
+    pub history_db: Option<String>,
+    /// Number of days the snapshots are kept in the history database, default: 30
+    pub history_retention: u64,
+    /// Name of the cluster, shown on the page, in the JSON responses, metrics and webhook notifications
+    /// Empty if not given, it's detected at startup then (ClusterName of scontrol show config or the host name)
+    pub cluster_name: String,
+    /// Version of SLURM, detected at startup with squeue --version, default: "unknown"
+    pub slurm_version: String,
+    /// URL that gets a HTTP POST when a node goes down or a partition becomes unavailable
+    pub webhook_url: Option<String>,
+    /// The same webhook notification is only sent once within this time in seconds, default: 600 sec.
+    pub webhook_cooldown: u64,
+    /// Settings of the email notifications, None if they are disabled (no --smtp-server)
+    pub email: Option<EmailConfig>,
+    /// Name or path of the squeue executable, default: "squeue"
+    pub squeue_path: String,
+    /// Extra arguments for squeue, for example "-M cluster2"
+    pub squeue_args: Vec<String>,
+    /// Job states listed by squeue (--states), None for the default of squeue (only pending and running jobs), default: "all"
+    pub squeue_states: Option<String>,
+    /// Name or path of the sinfo executable, default: "sinfo"
+    pub sinfo_path: String,
+    /// Extra arguments for sinfo, for example "-M cluster2"
+    pub sinfo_args: Vec<String>,
+    /// Run the SLURM commands on this host via ssh, for example "user@login01"
+    pub remote_host: Option<String>,
+    /// Extra arguments for ssh, for example "-p 2222"
+    pub remote_ssh_args: Vec<String>,
+    /// Monitor these clusters, squeue and sinfo are called once per cluster with "-M", default: only the local cluster
+    pub clusters: Vec<String>,
+    /// Print the SLURM status once and exit, without starting the web server
+    pub once: bool,
+    /// Output format of the --once mode, default: text
+    pub format: OutputFormat,
+    /// Partitions that are shown, hidden partitions are removed right after each update, default: all
+    pub partitions: PartitionSelection,
+    /// Log every HTTP request (method, path, status, size, remote address and duration)
+    pub access_log: bool,
+    /// Write the access log to this file instead of the main log
+    pub access_log_file: Option<String>,
+    /// Show the job steps (for example "1234.batch") below their job, otherwise they are removed after each update
+    pub show_job_steps: bool,
+    /// Retrieve and show the partition limits (from scontrol show partition), some sites restrict scontrol
+    pub enable_partition_limits: bool,
+    /// Retrieve and show the licenses of the cluster (from scontrol show licenses)
+    pub enable_licenses: bool,
+    /// Retrieve and show the QOS limits (from sacctmgr show qos), off by default since not every site has sacctmgr
+    pub enable_qos: bool,
+    /// Retrieve and show the CPU minutes of this month by account and user (from sreport), needs SLURM accounting
+    pub enable_sreport: bool,
+    /// Time between two sreport calls in seconds, default: 1 hour
+    pub sreport_interval: u64,
+    /// Thresholds at which the scheduler pressure indicators of the dashboard turn amber or red
+    pub pressure_thresholds: PressureThresholds,
+    /// Maximum number of rows of the job table per page, 0 shows all rows, default: 500
+    pub page_limit: usize,
+    /// Number of nodes per row of the occupancy grid, 0 hides the grid, default: 32
+    pub grid_width: usize,
+    /// Directory with user-provided templates (*.hbs) that replace or extend the built-in templates
+    pub template_dir: Option<String>,
+    /// Templates of the HTML page, the built-in ones until the template directory is loaded at startup
+    pub templates: Arc<Templates>,
+    /// Minimum time between two refreshes with POST /refresh in seconds, default: 10 sec.
+    pub refresh_spacing: u64,
+    /// Path under which a reverse proxy serves slurm_inspector, for example "/slurm", empty if it's served at the root
+    /// Always starts with a slash and never ends with one, see normalize_url_prefix
+    pub url_prefix: String,
+    /// Use the X-Forwarded-Prefix header of the reverse proxy instead of url_prefix, only if the proxy sets it
+    /// Also needed for the admin actions, the user name is taken from the header of remote_user_header (X-Remote-User by default)
+    pub trust_proxy: bool,
+    /// Header with the user name of the request (for example X-Remote-User of an SSO proxy), the jobs of that user are highlighted
+    /// The admin actions take the user from it as well, without it from X-Remote-User
+    pub remote_user_header: Option<String>,
+    /// Allow admin actions like cancelling a job from the web interface, see admin_actions
+    pub enable_admin_actions: bool,
+    /// Serve the raw output of the last sinfo and squeue calls at /debug/raw/sinfo and /debug/raw/squeue, see raw_output,
+    /// and the recent errors at /debug/errors, see error_log
+    pub debug_endpoints: bool,
+    /// Only clients from these networks get an answer (with trust_proxy the address of X-Forwarded-For), empty allows every client
+    pub allowed_ips: Vec<IpNetwork>,
+    /// Origins (normalized, see cors) whose pages may read the JSON API, "*" allows every origin, empty turns CORS off
+    pub cors_allow_origin: Vec<String>,
+    /// Listen on this unix domain socket instead of the TCP port, for a reverse proxy on the same host
+    pub listen_socket: Option<String>,
+    /// Permissions of the socket file, default: 0o660 (owner and group)
+    pub socket_mode: u32,
+    /// Certificate (PEM) for HTTPS, always set together with tls_key, default: plain HTTP
+    pub tls_cert: Option<String>,
+    /// Private key (PEM) of the certificate
+    pub tls_key: Option<String>,
+    /// Value of the X-Frame-Options header, default: SAMEORIGIN
+    pub frame_options: FrameOptions,
+    /// Number of threads of the web server, default: 32
+    pub http_threads: usize,
+    /// Maximum number of requests that are handled at the same time, more get "503 Service Unavailable", 0 means no limit, default: 16
+    pub max_requests: usize,
+    /// Read timeout of the connections of the web server in seconds, 0 means no timeout, default: 30
+    pub http_read_timeout: u64,
+    /// Write timeout of the connections of the web server in seconds, 0 means no timeout, default: 30
+    pub http_write_timeout: u64,
+    /// Write the output of every SLURM command to a file in this directory, for bug reports
+    pub record_dir: Option<String>,
+    /// Number of recorded files that are kept per command, 0 keeps all of them, default: 0
+    pub record_keep: usize,
+    /// Don't call the SLURM commands, read the newest recorded files from this directory instead
+    pub replay_dir: Option<String>,
+    /// Write the metrics of /metrics to this file after each successful update, for the textfile collector of node_exporter
+    pub prom_textfile: Option<String>,
+    /// Time zone and format of the timestamps on the page, default: local time of the server, %Y.%m.%d - %H:%M
+    pub display_time: DisplayTime,
+    /// Size of the synthetic cluster in the test mode, None uses the fixed test data
+    pub test_data: Option<SyntheticData>,
+    /// Ids of the columns of the job table in the shown order, empty shows all columns, see columns::job_columns
+    pub job_columns: Vec<String>,
+    /// Ids of the columns of the node table in the shown order, empty shows all columns, see columns::node_columns
+    pub node_columns: Vec<String>,
+    /// Order of the job table, ?sort= overrides it for a single request, see job_sort
+    pub default_sort: JobSort
+}
+
+impl Default for Configuration {
+    fn default() -> Configuration {
+        Configuration {
+            port: 4545,
+            interval: 60,
+            sinfo_interval: 60,
+            squeue_interval: 60,
+            test_mode: false,
+            log_level: "info".to_string(),
+            log_target: LogTarget::File,
+            log_format: LogFormat::Detailed,
+            log_dir: None,
+            log_rotate_size: DEFAULT_LOG_ROTATE_SIZE,
+            log_keep: DEFAULT_LOG_KEEP,
+            page_refresh: 60,
+            custom_css: None,
+            banner_file: None,
+            finished_hours: 24,
+            recently_finished_minutes: DEFAULT_RECENTLY_FINISHED_MINUTES,
+            enable_sshare: false,
+            enable_sprio: false,
+            anonymize: false,
+            users: Vec::new(),
+            history_size: DEFAULT_HISTORY_SIZE,
+            history_db: None,
+            history_retention: DEFAULT_HISTORY_RETENTION,
+            cluster_name: String::new(),
+            slurm_version: UNKNOWN.to_string(),
+            webhook_url: None,
+            webhook_cooldown: 600,
+            email: None,
+            squeue_path: "squeue".to_string(),
+            squeue_args: Vec::new(),
+            squeue_states: Some(DEFAULT_SQUEUE_STATES.to_string()),
+            sinfo_path: "sinfo".to_string(),
+            sinfo_args: Vec::new(),
+            remote_host: None,
+            remote_ssh_args: Vec::new(),
+            clusters: Vec::new(),
+            once: false,
+            format: OutputFormat::Text,
+            partitions: PartitionSelection::All,
+            access_log: false,
+            access_log_file: None,
+            show_job_steps: false,
+            enable_partition_limits: false,
+            enable_licenses: false,
+            enable_qos: false,
+            enable_sreport: false,
+            sreport_interval: DEFAULT_SREPORT_INTERVAL,
+            pressure_thresholds: PressureThresholds::default(),
+            page_limit: DEFAULT_PAGE_LIMIT,
+            grid_width: DEFAULT_GRID_WIDTH,
+            template_dir: None,
+            templates: Arc::new(builtin_templates()),
+            refresh_spacing: DEFAULT_REFRESH_SPACING,
+            url_prefix: String::new(),
+            trust_proxy: false,
+            remote_user_header: None,
+            enable_admin_actions: false,
+            debug_endpoints: false,
+            allowed_ips: Vec::new(),
+            cors_allow_origin: Vec::new(),
+            listen_socket: None,
+            socket_mode: DEFAULT_SOCKET_MODE,
+            tls_cert: None,
+            tls_key: None,
+            frame_options: FrameOptions::SameOrigin,
+            http_threads: DEFAULT_HTTP_THREADS,
+            max_requests: DEFAULT_MAX_REQUESTS,
+            http_read_timeout: DEFAULT_HTTP_READ_TIMEOUT,
+            http_write_timeout: DEFAULT_HTTP_WRITE_TIMEOUT,
+            record_dir: None,
+            record_keep: 0,
+            replay_dir: None,
+            prom_textfile: None,
+            display_time: DisplayTime::default(),
+            test_data: None,
+            job_columns: Vec::new(),
+            node_columns: Vec::new(),
+            default_sort: JobSort::State
+        }
+    }
+}
+
+/// Smallest allowed auto refresh interval for the web page in seconds
+pub const MIN_PAGE_REFRESH: u64 = 5;
+
+/// Largest allowed auto refresh interval for the web page in seconds
+pub const MAX_PAGE_REFRESH: u64 = 3600;
+
+/// Public helper function to clamp the page refresh interval to sensible values
+/// Zero or negative values disable the auto refresh, everything else is clamped to MIN_PAGE_REFRESH..MAX_PAGE_REFRESH
+pub fn clamp_page_refresh(page_refresh: i64) -> u64 {
+    if page_refresh <= 0 {
+        0
+    } else if (page_refresh as u64) < MIN_PAGE_REFRESH {
+        MIN_PAGE_REFRESH
+    } else if (page_refresh as u64) > MAX_PAGE_REFRESH {
+        MAX_PAGE_REFRESH
+    } else {
+        page_refresh as u64
+    }
+}
+
+/// Public helper function to parse a page refresh interval given by the user
+/// Numbers too big for i64 are clamped as well, everything else that is not a number returns None
+pub fn parse_page_refresh(value: &str) -> Option<u64> {
+    let value = value.trim();
+
+    match value.parse::<i64>() {
+        Ok(page_refresh) => Some(clamp_page_refresh(page_refresh)),
+        Err(_) => {
+            let is_number = |digits: &str| digits.len() > 0 && digits.chars().all(|c| c.is_digit(10));
+
+            if is_number(value) {
+                Some(MAX_PAGE_REFRESH)
+            } else if value.starts_with('-') && is_number(&value[1..]) {
+                Some(0)
+            } else {
+                None
+            }
+        }
+    }
+}
+
+/// Public helper function to normalize the URL prefix given by the user or the reverse proxy:
+/// "slurm/" and "/slurm" both become "/slurm", "/" and "" become "" (served at the root)
+pub fn normalize_url_prefix(value: &str) -> String {
+    let parts: Vec<&str> = value.trim().split('/').filter(|part| !part.is_empty()).collect();
+
+    if parts.is_empty() {
+        String::new()
+    } else {
+        format!("/{}", parts.join("/"))
+    }
+}
+
+#[test]
+fn test_normalize_url_prefix() {
+    assert_eq!(normalize_url_prefix("/slurm"), "/slurm");
+    assert_eq!(normalize_url_prefix("slurm"), "/slurm");
+    assert_eq!(normalize_url_prefix("/slurm/"), "/slurm");
+    assert_eq!(normalize_url_prefix(" //hpc//slurm/ "), "/hpc/slurm");
+    assert_eq!(normalize_url_prefix("/"), "");
+    assert_eq!(normalize_url_prefix(""), "");
+}
+
+/// Invalid command line arguments
+#[derive(Debug, Clone, PartialEq)]
+pub enum ConfigError {
+    /// The port is not a number between 1 and 65535
+    InvalidPort(String),
+    /// The update interval is not a number or zero
+    InvalidInterval(String),
+    /// The log level is not one of error, info or debug
+    InvalidLogLevel(String),
+    /// Some other option is not a valid number: (option, value)
+    InvalidNumber(String, String),
+    /// The output format is not one of html, json or text
+    InvalidFormat(String),
+    /// The log target is not one of file, stdout or stderr
+    InvalidLogTarget(String),
+    /// The log format is not one of detailed or json
+    InvalidLogFormat(String),
+    /// Some option is not a valid size like 10M: (option, value)
+    InvalidSize(String, String),
+    /// The number of kept log files is not a number or zero
+    InvalidLogKeep(String),
+    /// Two options that can't be used together: (option, option)
+    ConflictingOptions(String, String),
+    /// Unknown column of the job or node table: (option, column, valid columns)
+    InvalidColumn(String, String, String),
+    /// The time zone is not local, UTC or an offset like +02:00
+    InvalidTimezone(String),
+    /// The date format is empty or contains an unknown conversion specification
+    InvalidDateFormat(String),
+    /// An option that only works together with another one: (option, needed option)
+    MissingOption(String, String),
+    /// An entry of --allowed-ips is not an address or a network like 10.0.0.0/8
+    InvalidNetwork(String),
+    /// The frame options are not deny, sameorigin or allow
+    InvalidFrameOptions(String),
+    /// A state of --squeue-states is not a job state
+    InvalidSqueueStates(String),
+    /// The socket mode is not an octal mode like 660
+    InvalidSocketMode(String),
+    /// An entry of --pressure-thresholds is not indicator=amber:red or indicator=none
+    InvalidPressureThreshold(String),
+    /// An entry of --cors-allow-origin is not "*" or an origin like https://dashboard.example.com
+    InvalidOrigin(String),
+    /// The order of --default-sort is unknown
+    InvalidSort(String)
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            ConfigError::InvalidPort(ref value) => write!(f, "invalid port '{}', must be a number between 1 and 65535", value),
+            ConfigError::InvalidInterval(ref value) => write!(f, "invalid interval '{}', must be a number greater than 0", value),
+            ConfigError::InvalidLogLevel(ref value) => write!(f, "invalid log level '{}', must be error, info or debug", value),
+            ConfigError::InvalidNumber(ref option, ref value) => write!(f, "invalid value '{}' for --{}, must be a number", value, option),
+            ConfigError::InvalidFormat(ref value) => write!(f, "invalid format '{}', must be html, json or text", value),
+            ConfigError::InvalidLogTarget(ref value) => write!(f, "invalid log target '{}', must be file, stdout or stderr", value),
+            ConfigError::InvalidLogFormat(ref value) => write!(f, "invalid log format '{}', must be detailed or json", value),
+            ConfigError::InvalidSize(ref option, ref value) => write!(f, "invalid size '{}' for --{}, must be a number with an optional unit K, M or G", value, option),
+            ConfigError::InvalidLogKeep(ref value) => write!(f, "invalid value '{}' for --log-keep, must be a number greater than 0", value),
+            ConfigError::ConflictingOptions(ref first, ref second) => write!(f, "--{} and --{} can't be used together", first, second),
+            ConfigError::InvalidColumn(ref option, ref value, ref valid) => write!(f, "invalid column '{}' for --{}, valid columns are: {}", value, option, valid),
+            ConfigError::InvalidTimezone(ref value) => write!(f, "invalid time zone '{}', must be local, UTC or an offset like +02:00", value),
+            ConfigError::InvalidDateFormat(ref value) => write!(f, "invalid date format '{}', see the conversion specifications of strftime", value),
+            ConfigError::MissingOption(ref option, ref needed) => write!(f, "--{} needs --{}", option, needed),
+            ConfigError::InvalidNetwork(ref value) => write!(f, "invalid network '{}' for --allowed-ips, must be an address or a network like 10.0.0.0/8", value),
+            ConfigError::InvalidFrameOptions(ref value) => write!(f, "invalid frame options '{}', must be deny, sameorigin or allow", value),
+            ConfigError::InvalidSqueueStates(ref value) => write!(f, "invalid job state '{}' for --squeue-states, must be all, default or job states like pending,running", value),
+            ConfigError::InvalidSocketMode(ref value) => write!(f, "invalid socket mode '{}', must be an octal mode like 660", value),
+            ConfigError::InvalidPressureThreshold(ref value) => write!(f, "invalid threshold '{}' for --pressure-thresholds, must be indicator=amber:red (amber <= red) or indicator=none, the indicators are: {}", value, PRESSURE_INDICATORS.join(", ")),
+            ConfigError::InvalidOrigin(ref value) => write!(f, "invalid origin '{}' for --cors-allow-origin, must be * or scheme://host[:port] like https://dashboard.example.com", value),
+            ConfigError::InvalidSort(ref value) => write!(f, "invalid order '{}' for --default-sort, must be state, priority, run_time, job_id, user or squeue", value)
+        }
+    }
+}
+
+impl Error for ConfigError {
+    fn description(&self) -> &str {
+        "invalid command line argument"
+    }
+}
+
+/// Public helper function to validate the port, None returns the default port 4545
+pub fn parse_port(value: Option<&str>) -> Result<u16, ConfigError> {
+    match value {
+        None => Ok(4545),
+        Some(value) => match value.trim().parse::<u16>() {
+            Ok(port) if port > 0 => Ok(port),
+            _ => Err(ConfigError::InvalidPort(value.to_string()))
+        }
+    }
+}
+
+/// Public helper function to validate the update interval, None returns the default interval of 60 sec.
+pub fn parse_interval(value: Option<&str>) -> Result<u64, ConfigError> {
+    match value {
+        None => Ok(60),
+        Some(value) => match value.trim().parse::<u64>() {
+            Ok(interval) if interval > 0 => Ok(interval),
+            _ => Err(ConfigError::InvalidInterval(value.to_string()))
+        }
+    }
+}
+
+/// Public helper function to validate the update interval of the node or the job information, None returns the given update interval
+pub fn parse_table_interval(value: Option<&str>, interval: u64) -> Result<u64, ConfigError> {
+    match value {
+        None => Ok(interval),
+        Some(_) => parse_interval(value)
+    }
+}
+
+/// Public helper function to validate the log level, None returns the default level "info"
+pub fn parse_log_level(value: Option<&str>) -> Result<String, ConfigError> {
+    match value {
+        None => Ok("info".to_string()),
+        Some("error") | Some("info") | Some("debug") => Ok(value.unwrap().to_string()),
+        Some(value) => Err(ConfigError::InvalidLogLevel(value.to_string()))
+    }
+}
+
+/// Public helper function to validate the log target, None returns the default target file
+pub fn parse_log_target(value: Option<&str>) -> Result<LogTarget, ConfigError> {
+    match value {
+        None | Some("file") => Ok(LogTarget::File),
+        Some("stdout") => Ok(LogTarget::Stdout),
+        Some("stderr") => Ok(LogTarget::Stderr),
+        Some(value) => Err(ConfigError::InvalidLogTarget(value.to_string()))
+    }
+}
+
+/// Public helper function to validate the log format, None returns the default format detailed
+pub fn parse_log_format(value: Option<&str>) -> Result<LogFormat, ConfigError> {
+    match value {
+        None | Some("detailed") => Ok(LogFormat::Detailed),
+        Some("json") => Ok(LogFormat::Json),
+        Some(value) => Err(ConfigError::InvalidLogFormat(value.to_string()))
+    }
+}
+
+/// Public helper function to parse a size in bytes with an optional unit, for example "512K", "10M" or "1G"
+/// None returns the default size
+pub fn parse_size(option: &str, value: Option<&str>, default: u64) -> Result<u64, ConfigError> {
+    let value = match value {
+        None => return Ok(default),
+        Some(value) => value.trim()
+    };
+    let (number, factor) = match value.chars().last().map(|unit| unit.to_ascii_uppercase()) {
+        Some('K') => (&value[..value.len() - 1], 1024),
+        Some('M') => (&value[..value.len() - 1], 1024 * 1024),
+        Some('G') => (&value[..value.len() - 1], 1024 * 1024 * 1024),
+        _ => (value, 1)
+    };
+
+    number.parse::<u64>().ok()
+        .and_then(|number| number.checked_mul(factor))
+        .ok_or(ConfigError::InvalidSize(option.to_string(), value.to_string()))
+}
+
+/// Public helper function to validate the number of kept log files, None returns the default DEFAULT_LOG_KEEP
+pub fn parse_log_keep(value: Option<&str>) -> Result<usize, ConfigError> {
+    match value {
+        None => Ok(DEFAULT_LOG_KEEP),
+        Some(value) => match value.trim().parse::<usize>() {
+            Ok(keep) if keep > 0 => Ok(keep),
+            _ => Err(ConfigError::InvalidLogKeep(value.to_string()))
+        }
+    }
+}
+
+/// Public helper function to validate the output format, None returns the default format text
+pub fn parse_format(value: Option<&str>) -> Result<OutputFormat, ConfigError> {
+    match value {
+        None | Some("text") => Ok(OutputFormat::Text),
+        Some("html") => Ok(OutputFormat::Html),
+        Some("json") => Ok(OutputFormat::Json),
+        Some(value) => Err(ConfigError::InvalidFormat(value.to_string()))
+    }
+}
+
+/// Public helper function to validate a numeric option, None returns the given default value
+pub fn parse_number<T: FromStr>(option: &str, value: Option<&str>, default: T) -> Result<T, ConfigError> {
+    match value {
+        None => Ok(default),
+        Some(value) => value.trim().parse::<T>().map_err(|_| ConfigError::InvalidNumber(option.to_string(), value.to_string()))
+    }
+}
+
+// Private helper function to split a comma separated list of names (clusters, partitions), empty entries are ignored
+fn split_names(names: &str) -> Vec<String> {
+    names.split(',').map(|name| name.trim()).filter(|name| !name.is_empty()).map(|name| name.to_string()).collect()
+}
+
+/// Public helper function to parse the --exclude-partitions and --include-partitions options
+/// Returns an error if both are given
+pub fn parse_partition_selection(exclude: Option<&str>, include: Option<&str>) -> Result<PartitionSelection, ConfigError> {
+    match (exclude, include) {
+        (Some(_), Some(_)) => Err(ConfigError::ConflictingOptions("exclude-partitions".to_string(), "include-partitions".to_string())),
+        (Some(exclude), None) => Ok(PartitionSelection::Exclude(split_names(exclude))),
+        (None, Some(include)) => Ok(PartitionSelection::Include(split_names(include))),
+        (None, None) => Ok(PartitionSelection::All)
+    }
+}
+
+/// Public helper function to parse the --display-timezone and --date-format options, the options that are not given get their default value
+pub fn parse_display_time(timezone: Option<&str>, date_format: Option<&str>) -> Result<DisplayTime, ConfigError> {
+    let timezone = match timezone {
+        Some(value) => parse_display_timezone(value).ok_or(ConfigError::InvalidTimezone(value.to_string()))?,
+        None => DisplayTime::default().timezone
+    };
+    let date_format = date_format.unwrap_or(DEFAULT_DATE_FORMAT);
+    if !check_date_format(date_format) {
+        return Err(ConfigError::InvalidDateFormat(date_format.to_string()));
+    }
+
+    Ok(DisplayTime { timezone: timezone, date_format: date_format.to_string() })
+}
+
+/// Public helper function to parse the size of the synthetic test data, None if none of the options is given
+/// The options that are not given get their default value
+pub fn parse_test_data(nodes: Option<&str>, partitions: Option<&str>, jobs: Option<&str>, seed: Option<&str>) -> Result<Option<SyntheticData>, ConfigError> {
+    if nodes.is_none() && partitions.is_none() && jobs.is_none() && seed.is_none() {
+        return Ok(None);
+    }
+
+    let default = SyntheticData::default();
+    Ok(Some(SyntheticData {
+        nodes: parse_number("test-nodes", nodes, default.nodes)?,
+        partitions: parse_number("test-partitions", partitions, default.partitions)?,
+        jobs: parse_number("test-jobs", jobs, default.jobs)?,
+        seed: parse_number("test-seed", seed, default.seed)?
+    }))
+}
+
+#[test]
+fn test_parse_test_data() {
+    assert_eq!(parse_test_data(None, None, None, None), Ok(None));
+    assert_eq!(parse_test_data(None, None, Some("10000"), None), Ok(Some(SyntheticData{ jobs: 10000, .. SyntheticData::default() })));
+    assert_eq!(parse_test_data(Some("500"), Some("8"), Some("0"), Some("7")), Ok(Some(SyntheticData{ nodes: 500, partitions: 8, jobs: 0, seed: 7 })));
+    assert_eq!(parse_test_data(Some("many"), None, None, None), Err(ConfigError::InvalidNumber("test-nodes".to_string(), "many".to_string())));
+}
+
+/// Public helper function to parse the --job-columns and --node-columns options, None shows all columns
+/// Returns an error for the first unknown column, the message lists the valid columns
+pub fn parse_column_selection<R>(option: &str, value: Option<&str>, columns: &[Column<R>]) -> Result<Vec<String>, ConfigError> {
+    let (selected, unknown) = parse_columns(value.unwrap_or(""), columns);
+
+    match unknown.into_iter().next() {
+        Some(column) => Err(ConfigError::InvalidColumn(option.to_string(), column, column_ids(columns))),
+        None => Ok(selected)
+    }
+}
+
+#[test]
+fn test_parse_column_selection() {
+    assert_eq!(parse_column_selection("job-columns", None, &job_columns()), Ok(Vec::new()));
+    assert_eq!(parse_column_selection("job-columns", Some("job_id,user_name"), &job_columns()), Ok(vec!["job_id".to_string(), "user_name".to_string()]));
+    assert_eq!(parse_column_selection("node-columns", Some("node,job_id"), &node_columns()),
+        Err(ConfigError::InvalidColumn("node-columns".to_string(), "job_id".to_string(), column_ids(&node_columns()))));
+    assert!(format!("{}", parse_column_selection("node-columns", Some("load"), &node_columns()).unwrap_err())
+        .starts_with("invalid column 'load' for --node-columns, valid columns are: cluster, partition, availability,"));
+}
+
+/// Public helper function to parse the comma separated list of --users, without the option the jobs of all users are shown
+/// The anonymized user names can't be matched against the list, so both options together are refused
+pub fn parse_users(value: Option<&str>, anonymize: bool) -> Result<Vec<String>, ConfigError> {
+    let mut users: Vec<String> = Vec::new();
+    for user in value.unwrap_or("").split(',').map(|user| user.trim()).filter(|user| !user.is_empty()) {
+        if !users.iter().any(|known| known == user) {
+            users.push(user.to_string());
+        }
+    }
+
+    if anonymize && !users.is_empty() {
+        return Err(ConfigError::ConflictingOptions("users".to_string(), "anonymize".to_string()));
+    }
+    Ok(users)
+}
+
+#[test]
+fn test_parse_users() {
+    assert_eq!(parse_users(None, false), Ok(Vec::new()));
+    assert_eq!(parse_users(None, true), Ok(Vec::new()));
+    assert_eq!(parse_users(Some("willi, user01,,willi"), false), Ok(vec!["willi".to_string(), "user01".to_string()]));
+    assert_eq!(parse_users(Some(""), true), Ok(Vec::new()));
+    assert_eq!(parse_users(Some("willi"), true), Err(ConfigError::ConflictingOptions("users".to_string(), "anonymize".to_string())));
+}
+
+/// Public helper function to parse the comma separated list of --allowed-ips, without the option every client is allowed
+pub fn parse_allowed_ips(value: Option<&str>) -> Result<Vec<IpNetwork>, ConfigError> {
+    match value {
+        Some(value) => split_names(value).iter()
+            .map(|network| parse_ip_network(network).ok_or(ConfigError::InvalidNetwork(network.to_string())))
+            .collect(),
+        None => Ok(Vec::new())
+    }
+}
+
+/// Public helper function to parse the comma separated list of --cors-allow-origin, without the option CORS is off
+pub fn parse_cors_allow_origin(value: Option<&str>) -> Result<Vec<String>, ConfigError> {
+    match value {
+        Some(value) => split_names(value).iter()
+            .map(|origin| normalize_origin(origin).ok_or(ConfigError::InvalidOrigin(origin.to_string())))
+            .collect(),
+        None => Ok(Vec::new())
+    }
+}
+
+#[test]
+fn test_parse_cors_allow_origin() {
+    assert_eq!(parse_cors_allow_origin(None), Ok(Vec::new()));
+    assert_eq!(parse_cors_allow_origin(Some("*")), Ok(vec!["*".to_string()]));
+    assert_eq!(parse_cors_allow_origin(Some("https://Dashboard.example.com/, http://localhost:8080")),
+        Ok(vec!["https://dashboard.example.com".to_string(), "http://localhost:8080".to_string()]));
+    assert_eq!(parse_cors_allow_origin(Some("https://dashboard.example.com,dashboard.example.com")),
+        Err(ConfigError::InvalidOrigin("dashboard.example.com".to_string())));
+    assert_eq!(parse_cors_allow_origin(Some("https://example.com/app")), Err(ConfigError::InvalidOrigin("https://example.com/app".to_string())));
+}
+
+/// Public helper function to parse --squeue-states: "all", "default" (squeue is called without --states)
+/// or a comma separated list of job states, which is passed to squeue as it is. Without the option squeue lists all jobs
+pub fn parse_squeue_states(value: Option<&str>) -> Result<Option<String>, ConfigError> {
+    let value = match value {
+        Some(value) => value.trim().to_lowercase(),
+        None => return Ok(Some(DEFAULT_SQUEUE_STATES.to_string()))
+    };
+
+    match &value[..] {
+        "all" => Ok(Some(value)),
+        "default" => Ok(None),
+        _ => {
+            let (states, unknown_states) = parse_job_states(&value);
+            if let Some(state) = unknown_states.first() {
+                Err(ConfigError::InvalidSqueueStates(state.to_string()))
+            } else if states.is_empty() || states.contains(&JobState::Unknown) {
+                Err(ConfigError::InvalidSqueueStates(value.clone()))
+            } else {
+                Ok(Some(split_names(&value).join(",")))
+            }
+        }
+    }
+}
+
+#[test]
+fn test_parse_squeue_states() {
+    assert_eq!(parse_squeue_states(None), Ok(Some("all".to_string())));
+    assert_eq!(parse_squeue_states(Some("ALL")), Ok(Some("all".to_string())));
+    assert_eq!(parse_squeue_states(Some("default")), Ok(None));
+    assert_eq!(parse_squeue_states(Some("pending, running,cd")), Ok(Some("pending,running,cd".to_string())));
+    assert_eq!(parse_squeue_states(Some("pending,sleeping")), Err(ConfigError::InvalidSqueueStates("sleeping".to_string())));
+    assert_eq!(parse_squeue_states(Some(",")), Err(ConfigError::InvalidSqueueStates(",".to_string())));
+    assert_eq!(parse_squeue_states(Some("unknown")), Err(ConfigError::InvalidSqueueStates("unknown".to_string())));
+}
+
+#[test]
+fn test_parse_allowed_ips() {
+    assert_eq!(parse_allowed_ips(None), Ok(Vec::new()));
+    assert_eq!(parse_allowed_ips(Some("10.0.0.0/8, 2001:db8::/32,192.168.1.5")), Ok(vec![
+        IpNetwork { address: "10.0.0.0".parse().unwrap(), prefix_len: 8 },
+        IpNetwork { address: "2001:db8::".parse().unwrap(), prefix_len: 32 },
+        IpNetwork { address: "192.168.1.5".parse().unwrap(), prefix_len: 32 }]));
+    assert_eq!(parse_allowed_ips(Some("10.0.0.0/8,10.0.0.0/40")), Err(ConfigError::InvalidNetwork("10.0.0.0/40".to_string())));
+    assert_eq!(format!("{}", ConfigError::InvalidNetwork("login01".to_string())),
+        "invalid network 'login01' for --allowed-ips, must be an address or a network like 10.0.0.0/8");
+}
+
+/// Public helper function to parse the --listen-socket and --socket-mode options, returns the path of the socket and its mode
+/// The socket replaces the TCP port, so an explicit --port together with the socket is refused instead of silently ignored
+pub fn parse_listen_socket(port_given: bool, socket: Option<&str>, mode: Option<&str>) -> Result<(Option<String>, u32), ConfigError> {
+    match (socket, mode) {
+        (Some(_), _) if port_given => Err(ConfigError::ConflictingOptions("port".to_string(), "listen-socket".to_string())),
+        (None, Some(_)) => Err(ConfigError::MissingOption("socket-mode".to_string(), "listen-socket".to_string())),
+        (None, None) => Ok((None, DEFAULT_SOCKET_MODE)),
+        (Some(path), mode) => {
+            let mode = match mode {
+                Some(value) => match u32::from_str_radix(value.trim_start_matches("0o"), 8) {
+                    Ok(mode) if mode <= 0o777 => mode,
+                    _ => return Err(ConfigError::InvalidSocketMode(value.to_string()))
+                },
+                None => DEFAULT_SOCKET_MODE
+            };
+            Ok((Some(path.to_string()), mode))
+        }
+    }
+}
+
+#[test]
+fn test_parse_listen_socket() {
+    assert_eq!(parse_listen_socket(false, None, None), Ok((None, 0o660)));
+    assert_eq!(parse_listen_socket(true, None, None), Ok((None, 0o660)));
+    assert_eq!(parse_listen_socket(false, Some("/run/slurm_inspector.sock"), None), Ok((Some("/run/slurm_inspector.sock".to_string()), 0o660)));
+    assert_eq!(parse_listen_socket(false, Some("inspector.sock"), Some("600")), Ok((Some("inspector.sock".to_string()), 0o600)));
+    assert_eq!(parse_listen_socket(false, Some("inspector.sock"), Some("0o666")), Ok((Some("inspector.sock".to_string()), 0o666)));
+    assert_eq!(parse_listen_socket(false, Some("inspector.sock"), Some("0660")), Ok((Some("inspector.sock".to_string()), 0o660)));
+    assert_eq!(parse_listen_socket(false, Some("inspector.sock"), Some("880")), Err(ConfigError::InvalidSocketMode("880".to_string())));
+    assert_eq!(parse_listen_socket(false, Some("inspector.sock"), Some("7777")), Err(ConfigError::InvalidSocketMode("7777".to_string())));
+    assert_eq!(parse_listen_socket(true, Some("inspector.sock"), None), Err(ConfigError::ConflictingOptions("port".to_string(), "listen-socket".to_string())));
+    assert_eq!(parse_listen_socket(false, None, Some("600")), Err(ConfigError::MissingOption("socket-mode".to_string(), "listen-socket".to_string())));
+}
+
+/// Public helper function to check the --tls-cert and --tls-key options, returns the paths of the certificate and the key
+/// Both are needed for HTTPS, the files themselves are checked by tls::check_tls_files when the server starts
+pub fn parse_tls(cert: Option<&str>, key: Option<&str>, listen_socket: bool) -> Result<(Option<String>, Option<String>), ConfigError> {
+    match (cert, key) {
+        (Some(_), Some(_)) if listen_socket => Err(ConfigError::ConflictingOptions("listen-socket".to_string(), "tls-cert".to_string())),
+        (Some(cert), Some(key)) => Ok((Some(cert.to_string()), Some(key.to_string()))),
+        (Some(_), None) => Err(ConfigError::MissingOption("tls-cert".to_string(), "tls-key".to_string())),
+        (None, Some(_)) => Err(ConfigError::MissingOption("tls-key".to_string(), "tls-cert".to_string())),
+        (None, None) => Ok((None, None))
+    }
+}
+
+#[test]
+fn test_parse_tls() {
+    assert_eq!(parse_tls(None, None, false), Ok((None, None)));
+    assert_eq!(parse_tls(None, None, true), Ok((None, None)));
+    assert_eq!(parse_tls(Some("cert.pem"), Some("key.pem"), false), Ok((Some("cert.pem".to_string()), Some("key.pem".to_string()))));
+    assert_eq!(parse_tls(Some("cert.pem"), None, false), Err(ConfigError::MissingOption("tls-cert".to_string(), "tls-key".to_string())));
+    assert_eq!(parse_tls(None, Some("key.pem"), false), Err(ConfigError::MissingOption("tls-key".to_string(), "tls-cert".to_string())));
+    // The reverse proxy on the socket does the HTTPS
+    assert_eq!(parse_tls(Some("cert.pem"), Some("key.pem"), true), Err(ConfigError::ConflictingOptions("listen-socket".to_string(), "tls-cert".to_string())));
+}
+
+/// Public helper function to parse the --history-retention option, which only works together with --history-db
+/// Zero would delete every snapshot right after it has been written, so it's not allowed
+pub fn parse_history_retention(history_db: bool, value: Option<&str>) -> Result<u64, ConfigError> {
+    if value.is_some() && !history_db {
+        return Err(ConfigError::MissingOption("history-retention".to_string(), "history-db".to_string()));
+    }
+    match parse_number("history-retention", value, DEFAULT_HISTORY_RETENTION)? {
+        0 => Err(ConfigError::InvalidNumber("history-retention".to_string(), "0".to_string())),
+        days => Ok(days)
+    }
+}
+
+#[test]
+fn test_parse_history_retention() {
+    assert_eq!(parse_history_retention(false, None), Ok(30));
+    assert_eq!(parse_history_retention(true, None), Ok(30));
+    assert_eq!(parse_history_retention(true, Some("365")), Ok(365));
+    assert_eq!(parse_history_retention(true, Some("0")), Err(ConfigError::InvalidNumber("history-retention".to_string(), "0".to_string())));
+    assert_eq!(parse_history_retention(true, Some("1y")), Err(ConfigError::InvalidNumber("history-retention".to_string(), "1y".to_string())));
+    assert_eq!(parse_history_retention(false, Some("7")), Err(ConfigError::MissingOption("history-retention".to_string(), "history-db".to_string())));
+}
+
+/// Public helper function to parse the --sreport-interval option, which only works together with --enable-sreport
+pub fn parse_sreport_interval(enable_sreport: bool, value: Option<&str>) -> Result<u64, ConfigError> {
+    if value.is_some() && !enable_sreport {
+        return Err(ConfigError::MissingOption("sreport-interval".to_string(), "enable-sreport".to_string()));
+    }
+    match parse_number("sreport-interval", value, DEFAULT_SREPORT_INTERVAL)? {
+        0 => Err(ConfigError::InvalidNumber("sreport-interval".to_string(), "0".to_string())),
+        seconds => Ok(seconds)
+    }
+}
+
+#[test]
+fn test_parse_sreport_interval() {
+    assert_eq!(parse_sreport_interval(false, None), Ok(3600));
+    assert_eq!(parse_sreport_interval(true, Some("86400")), Ok(86400));
+    assert_eq!(parse_sreport_interval(true, Some("0")), Err(ConfigError::InvalidNumber("sreport-interval".to_string(), "0".to_string())));
+    assert_eq!(parse_sreport_interval(true, Some("1h")), Err(ConfigError::InvalidNumber("sreport-interval".to_string(), "1h".to_string())));
+    assert_eq!(parse_sreport_interval(false, Some("600")), Err(ConfigError::MissingOption("sreport-interval".to_string(), "enable-sreport".to_string())));
+}
+
+/// Public helper function to parse --pressure-thresholds, without the option the default thresholds are used
+pub fn parse_pressure_threshold_option(value: Option<&str>) -> Result<PressureThresholds, ConfigError> {
+    match value {
+        Some(value) => parse_pressure_thresholds(value).map_err(ConfigError::InvalidPressureThreshold),
+        None => Ok(PressureThresholds::default())
+    }
+}
+
+#[test]
+fn test_parse_pressure_threshold_option() {
+    assert_eq!(parse_pressure_threshold_option(None), Ok(PressureThresholds::default()));
+    assert_eq!(parse_pressure_threshold_option(Some("pending_users=10:20")).map(|thresholds| thresholds.to_text()),
+        Ok("pending_users=10:20,oldest_pending=24:72,pending_ratio=2:5".to_string()));
+    assert_eq!(parse_pressure_threshold_option(Some("pending_ratio=high")), Err(ConfigError::InvalidPressureThreshold("pending_ratio=high".to_string())));
+}
+
+/// Public helper function to check the options of the record and the replay mode
+/// Replaying can't be combined with recording (it would record the recordings) or with the test mode (which doesn't call the commands)
+pub fn check_replay_options(record: bool, replay: bool, test_mode: bool) -> Result<(), ConfigError> {
+    match (record, replay, test_mode) {
+        (true, true, _) => Err(ConfigError::ConflictingOptions("record-dir".to_string(), "replay-dir".to_string())),
+        (_, true, true) => Err(ConfigError::ConflictingOptions("replay-dir".to_string(), "test".to_string())),
+        _ => Ok(())
+    }
+}
+
+/// Public helper function to parse the email options, --smtp-server enables the notifications and needs --email-from and --email-to
+/// Returns None without --smtp-server, the other email options are not allowed then. The numbers get their default values
+pub fn parse_email_config(smtp_server: Option<&str>, from: Option<&str>, to: Option<&str>, smtp_user: Option<&str>, smtp_password_file: Option<&str>)
+    -> Result<Option<EmailConfig>, ConfigError> {
+    let missing = |option: &str, needed: &str| Err(ConfigError::MissingOption(option.to_string(), needed.to_string()));
+    let to = to.map(split_names).unwrap_or(Vec::new());
+
+    let smtp_server = match smtp_server {
+        Some(server) => server,
+        None => {
+            let given = [("email-from", from.is_some()), ("email-to", !to.is_empty()), ("smtp-user", smtp_user.is_some()),
+                ("smtp-password-file", smtp_password_file.is_some())];
+            return match given.iter().find(|&&(_, is_given)| is_given) {
+                Some(&(option, _)) => missing(option, "smtp-server"),
+                None => Ok(None)
+            };
+        }
+    };
+    let from = match from {
+        Some(from) => from,
+        None => return missing("smtp-server", "email-from")
+    };
+    if to.is_empty() {
+        return missing("smtp-server", "email-to");
+    }
+    match (smtp_user, smtp_password_file) {
+        (Some(_), None) => return missing("smtp-user", "smtp-password-file"),
+        (None, Some(_)) => return missing("smtp-password-file", "smtp-user"),
+        _ => {}
+    }
+
+    Ok(Some(EmailConfig {
+        smtp_server: smtp_server.to_string(),
+        smtp_port: DEFAULT_SMTP_PORT,
+        from: from.to_string(),
+        to: to,
+        smtp_user: smtp_user.map(|user| user.to_string()),
+        smtp_password_file: smtp_password_file.map(|path| path.to_string()),
+        node_threshold: DEFAULT_EMAIL_NODE_THRESHOLD,
+        failing_minutes: DEFAULT_EMAIL_FAILING_MINUTES,
+        cooldown: DEFAULT_EMAIL_COOLDOWN
+    }))
 }
 
 /// This will parse the command line arguments and create a new configuration object
-/// If the arguments are missing or there is a parse error, then the default values are used
-pub fn setup_configuration() -> Configuration {
+/// Missing arguments get their default values, invalid arguments return an error
+pub fn setup_configuration() -> Result<Configuration, ConfigError> {
     let matches = App::new("slurm_inspector")
         .version("0.1")
         .author("Willi Kappler")
@@ -28,30 +948,496 @@ pub fn setup_configuration() -> Configuration {
         .args_from_usage(
             "-p --port=[PORT] 'Sets the port for the web GUI (default: 4545)'
              -i --interval=[INTERVAL] 'Sets the update interval (in sec.) for the web page (default: 60 sec.)'
+             --sinfo-interval=[SINFO_INTERVAL] 'Sets the update interval (in sec.) of the node information (default: same as interval)'
+             --squeue-interval=[SQUEUE_INTERVAL] 'Sets the update interval (in sec.) of the job information (default: same as interval)'
              --test 'create test values, does not call sinfo or squeue'
-             --loglevel=[LOGLEVEL] 'specify log level: error, info or debug'"
+             --test-nodes=[TEST_NODES] 'Number of nodes of the synthetic test data (enables --test, default: 100)'
+             --test-partitions=[TEST_PARTITIONS] 'Number of partitions of the synthetic test data (enables --test, default: 4)'
+             --test-jobs=[TEST_JOBS] 'Number of jobs of the synthetic test data (enables --test, default: 1000)'
+             --test-seed=[TEST_SEED] 'Seed of the synthetic test data, the same seed gives the same data (enables --test, default: 1)'
+             --loglevel=[LOGLEVEL] 'specify log level: error, info or debug'
+             --log-target=[LOG_TARGET] 'Where to write the log: file, stdout or stderr (default: file)'
+             --log-format=[LOG_FORMAT] 'Format of the log lines: detailed or json (one JSON object per line, default: detailed)'
+             --log-dir=[LOG_DIR] 'Directory of the log file (default: current directory)'
+             --log-rotate-size=[LOG_ROTATE_SIZE] 'Rotate the log file when it gets bigger than this size, for example 512K, 10M, 0 disables it (default: 10M)'
+             --log-keep=[LOG_KEEP] 'Number of rotated log files that are kept (default: 7)'
+             --page-refresh=[PAGE_REFRESH] 'Sets the auto refresh interval (in sec.) for the web page, 0 disables it (default: same as interval)'
+             --custom-css=[CUSTOM_CSS] 'Path to a custom stylesheet that is linked after the default one'
+             --banner-file=[BANNER_FILE] 'Text file with a banner shown at the top of the page, re-read on every update, missing or empty: no banner'
+             --finished-hours=[FINISHED_HOURS] 'Show jobs that finished in the last hours (default: 24 hours)'
+             --recently-finished-minutes=[RECENTLY_FINISHED_MINUTES] 'Show jobs that disappeared from squeue for this time (in min., 0 disables it, default: 10 min.)'
+             --enable-sshare 'retrieve and show fair-share information, needs SLURM accounting'
+             --enable-sprio 'retrieve and show the priority factors of the pending jobs'
+             --anonymize 'hide user names, user ids and job names'
+             --users=[USERS] 'Comma separated list of users, only their jobs are shown (default: all users)'
+             --history-size=[HISTORY_SIZE] 'Number of updates kept in the history (default: 60)'
+             --history-db=[HISTORY_DB] 'Write the history to this SQLite database, so it survives restarts'
+             --history-retention=[HISTORY_RETENTION] 'Number of days the history is kept in --history-db (default: 30 days)'
+             --cluster-name=[CLUSTER_NAME] 'Name of the cluster shown on the page and in webhook notifications (default: detected)'
+             --webhook-url=[WEBHOOK_URL] 'URL that is notified via HTTP POST when a node goes down or a partition becomes unavailable'
+             --webhook-cooldown=[WEBHOOK_COOLDOWN] 'Send the same webhook notification only once within this time (in sec., default: 600 sec.)'
+             --smtp-server=[SMTP_SERVER] 'SMTP server for email notifications when a partition goes down, many nodes fail or the updates keep failing'
+             --smtp-port=[SMTP_PORT] 'Port of the SMTP server (default: 25)'
+             --smtp-user=[SMTP_USER] 'User name for the SMTP server (AUTH PLAIN, only over STARTTLS), needs --smtp-password-file'
+             --smtp-password-file=[SMTP_PASSWORD_FILE] 'File with the password of --smtp-user'
+             --email-from=[EMAIL_FROM] 'Sender address of the email notifications'
+             --email-to=[EMAIL_TO] 'Comma separated list of the recipients of the email notifications'
+             --email-node-threshold=[EMAIL_NODE_THRESHOLD] 'Send an email if more nodes go down or fail within one update (default: 5)'
+             --email-failing-minutes=[EMAIL_FAILING_MINUTES] 'Send an email if the updates have been failing for longer (in min., default: 30 min.)'
+             --email-cooldown=[EMAIL_COOLDOWN] 'Send at most one email per kind of notification within this time (in sec., default: 3600 sec.)'
+             --squeue-path=[SQUEUE_PATH] 'Name or path of the squeue executable (default: squeue)'
+             --squeue-args=[SQUEUE_ARGS] 'Extra arguments for squeue, for example \"-M cluster2\"'
+             --squeue-states=[SQUEUE_STATES] 'Job states listed by squeue: all, a comma separated list like pending,running,completed or default for the default of squeue (default: all)'
+             --sinfo-path=[SINFO_PATH] 'Name or path of the sinfo executable (default: sinfo)'
+             --sinfo-args=[SINFO_ARGS] 'Extra arguments for sinfo, for example \"-M cluster2\"'
+             --remote-host=[REMOTE_HOST] 'Run the SLURM commands on this host via ssh, for example user@login01'
+             --remote-ssh-args=[REMOTE_SSH_ARGS] 'Extra arguments for ssh, for example \"-p 2222\"'
+             --clusters=[CLUSTERS] 'Comma separated list of clusters to monitor, for example cluster1,cluster2'
+             --once 'print the SLURM status once and exit, does not start the web server'
+             --format=[FORMAT] 'Output format for --once: html, json or text (default: text)'
+             --exclude-partitions=[EXCLUDE_PARTITIONS] 'Comma separated list of partitions that are hidden, for example maintenance,cloud'
+             --include-partitions=[INCLUDE_PARTITIONS] 'Comma separated list of partitions that are shown, all others are hidden'
+             --access-log 'log every HTTP request in the main log (target: access)'
+             --access-log-file=[ACCESS_LOG_FILE] 'Write the access log to this file instead of the main log, enables --access-log'
+             --show-job-steps 'show the job steps below their job instead of hiding them'
+             --enable-partition-limits 'retrieve and show the partition limits like the max. time, needs scontrol'
+             --enable-licenses 'retrieve and show the licenses and how many of them are used, needs scontrol'
+             --enable-qos 'retrieve and show the QOS limits and explain the QOS reasons of pending jobs, needs sacctmgr'
+             --enable-sreport 'retrieve and show the CPU minutes of this month by account and user, needs sreport and SLURM accounting'
+             --sreport-interval=[SREPORT_INTERVAL] 'Sets the time (in sec.) between two sreport calls (default: 3600 sec.)'
+             --pressure-thresholds=[PRESSURE_THRESHOLDS] 'Comma separated list of indicator=amber:red at which the scheduler pressure turns amber or red (default: oldest_pending=24:72,pending_ratio=2:5)'
+             --page-limit=[PAGE_LIMIT] 'Maximum number of rows of the job table per page, 0 shows all rows (default: 500)'
+             --grid-width=[GRID_WIDTH] 'Number of nodes per row of the occupancy grid, 0 hides the grid (default: 32)'
+             --template-dir=[TEMPLATE_DIR] 'Directory with templates (*.hbs) that replace the built-in templates of the web page'
+             --refresh-spacing=[REFRESH_SPACING] 'Minimum time (in sec.) between two refreshes with POST /refresh (default: 10 sec.)'
+             --url-prefix=[URL_PREFIX] 'Path under which a reverse proxy serves the web page, for example /slurm'
+             --remote-user-header=[REMOTE_USER_HEADER] 'Header with the user name of the request, for example X-Remote-User, the jobs of that user are highlighted and it is the user of the admin actions (default: X-Remote-User)'
+             --trust-proxy 'use the X-Forwarded-Prefix header of the reverse proxy as URL prefix and the header of --remote-user-header for admin actions'
+             --enable-admin-actions 'allow users authenticated by the reverse proxy to cancel jobs and to drain or resume nodes from the web interface, needs --trust-proxy'
+             --debug-endpoints 'serve the raw output of the last sinfo and squeue calls at /debug/raw/sinfo and /debug/raw/squeue and the recent errors at /debug/errors'
+             --allowed-ips=[ALLOWED_IPS] 'Comma separated list of addresses and networks that may access the web server, for example 10.0.0.0/8,2001:db8::/32'
+             --cors-allow-origin=[CORS_ALLOW_ORIGIN] 'Comma separated list of origins whose pages may read the JSON API (/api/...), for example https://dashboard.example.com, or * for every origin'
+             --listen-socket=[LISTEN_SOCKET] 'Listen on this unix domain socket instead of the TCP port, for a reverse proxy on the same host'
+             --socket-mode=[SOCKET_MODE] 'Permissions of the socket file as octal mode (default: 660)'
+             --tls-cert=[TLS_CERT] 'Certificate file (PEM) for HTTPS, needs --tls-key (default: plain HTTP)'
+             --tls-key=[TLS_KEY] 'Private key file (PEM) of the certificate for HTTPS'
+             --frame-options=[FRAME_OPTIONS] 'Who may show the page in a frame: deny, sameorigin or allow (default: sameorigin)'
+             --http-threads=[HTTP_THREADS] 'Number of threads of the web server (default: 32)'
+             --max-requests=[MAX_REQUESTS] 'Maximum number of requests handled at the same time, more get 503, 0 disables the limit (default: 16)'
+             --http-read-timeout=[HTTP_READ_TIMEOUT] 'Seconds a client has to send its request, 0 disables the timeout (default: 30)'
+             --http-write-timeout=[HTTP_WRITE_TIMEOUT] 'Seconds a client has to read the answer, 0 disables the timeout (default: 30)'
+             --record-dir=[RECORD_DIR] 'Write the output of every SLURM command to a file in this directory'
+             --record-keep=[RECORD_KEEP] 'Number of recorded files that are kept per command, 0 keeps all (default: 0)'
+             --replay-dir=[REPLAY_DIR] 'Read the newest recorded files from this directory instead of calling the SLURM commands'
+             --prom-textfile=[PROM_TEXTFILE] 'Write the metrics to this file after each successful update, for the textfile collector of node_exporter'
+             --display-timezone=[DISPLAY_TIMEZONE] 'Time zone of the timestamps on the page: local, UTC or an offset like +02:00 (default: local)'
+             --date-format=[DATE_FORMAT] 'Format of the timestamps on the page in strftime syntax (default: %Y.%m.%d - %H:%M)'
+             --job-columns=[JOB_COLUMNS] 'Comma separated list of the columns of the job table in the shown order, for example job_id,user_name,job_state'
+             --node-columns=[NODE_COLUMNS] 'Comma separated list of the columns of the node table in the shown order, for example node,state,cpus'
+             --default-sort=[DEFAULT_SORT] 'Order of the job table: state (running jobs by run time, then pending jobs by priority), priority, run_time, job_id, user or squeue (default: state)'"
         )
         .get_matches();
 
-        let port = value_t!(matches.value_of("PORT"), u16).unwrap_or(4545);
-        let interval = value_t!(matches.value_of("INTERVAL"), u64).unwrap_or(60);
-        let test_mode = matches.is_present("test");
-        let log_level = match matches.value_of("LOGLEVEL") {
-            Some("error") => "error",
-            Some("info") => "info",
-            Some("debug") => "debug",
-            _ => "info"
+        let port = parse_port(matches.value_of("PORT"))?;
+        let interval = parse_interval(matches.value_of("INTERVAL"))?;
+        let sinfo_interval = parse_table_interval(matches.value_of("SINFO_INTERVAL"), interval)?;
+        let squeue_interval = parse_table_interval(matches.value_of("SQUEUE_INTERVAL"), interval)?;
+        let test_data = parse_test_data(matches.value_of("TEST_NODES"), matches.value_of("TEST_PARTITIONS"), matches.value_of("TEST_JOBS"),
+            matches.value_of("TEST_SEED"))?;
+        let test_mode = matches.is_present("test") || test_data.is_some();
+        let log_level = parse_log_level(matches.value_of("LOGLEVEL"))?;
+        let log_target = parse_log_target(matches.value_of("LOG_TARGET"))?;
+        let log_format = parse_log_format(matches.value_of("LOG_FORMAT"))?;
+        let log_dir = matches.value_of("LOG_DIR").map(|path| path.to_string());
+        let log_rotate_size = parse_size("log-rotate-size", matches.value_of("LOG_ROTATE_SIZE"), DEFAULT_LOG_ROTATE_SIZE)?;
+        let log_keep = parse_log_keep(matches.value_of("LOG_KEEP"))?;
+        let finished_hours = parse_number("finished-hours", matches.value_of("FINISHED_HOURS"), 24)?;
+        let recently_finished_minutes = parse_number("recently-finished-minutes", matches.value_of("RECENTLY_FINISHED_MINUTES"),
+            DEFAULT_RECENTLY_FINISHED_MINUTES)?;
+        let enable_sshare = matches.is_present("enable-sshare");
+        let enable_sprio = matches.is_present("enable-sprio");
+        let anonymize = matches.is_present("anonymize");
+        let users = parse_users(matches.value_of("USERS"), anonymize)?;
+        let history_size = parse_number("history-size", matches.value_of("HISTORY_SIZE"), DEFAULT_HISTORY_SIZE)?;
+        let history_db = matches.value_of("HISTORY_DB").map(|path| path.to_string());
+        let history_retention = parse_history_retention(history_db.is_some(), matches.value_of("HISTORY_RETENTION"))?;
+        let cluster_name = matches.value_of("CLUSTER_NAME").unwrap_or("");
+        let webhook_url = matches.value_of("WEBHOOK_URL").map(|url| url.to_string());
+        let webhook_cooldown = parse_number("webhook-cooldown", matches.value_of("WEBHOOK_COOLDOWN"), 600)?;
+        let email = match parse_email_config(matches.value_of("SMTP_SERVER"), matches.value_of("EMAIL_FROM"), matches.value_of("EMAIL_TO"),
+            matches.value_of("SMTP_USER"), matches.value_of("SMTP_PASSWORD_FILE"))? {
+            Some(email) => Some(EmailConfig {
+                smtp_port: parse_number("smtp-port", matches.value_of("SMTP_PORT"), DEFAULT_SMTP_PORT)?,
+                node_threshold: parse_number("email-node-threshold", matches.value_of("EMAIL_NODE_THRESHOLD"), DEFAULT_EMAIL_NODE_THRESHOLD)?,
+                failing_minutes: parse_number("email-failing-minutes", matches.value_of("EMAIL_FAILING_MINUTES"), DEFAULT_EMAIL_FAILING_MINUTES)?,
+                cooldown: parse_number("email-cooldown", matches.value_of("EMAIL_COOLDOWN"), DEFAULT_EMAIL_COOLDOWN)?,
+                .. email
+            }),
+            None => None
+        };
+        let custom_css = matches.value_of("CUSTOM_CSS").map(|path| path.to_string());
+        let banner_file = matches.value_of("BANNER_FILE").map(|path| path.to_string());
+        let squeue_path = matches.value_of("SQUEUE_PATH").unwrap_or("squeue");
+        let squeue_args = matches.value_of("SQUEUE_ARGS").map(split_args).unwrap_or(Vec::new());
+        let squeue_states = parse_squeue_states(matches.value_of("SQUEUE_STATES"))?;
+        let sinfo_path = matches.value_of("SINFO_PATH").unwrap_or("sinfo");
+        let sinfo_args = matches.value_of("SINFO_ARGS").map(split_args).unwrap_or(Vec::new());
+        let remote_host = matches.value_of("REMOTE_HOST").map(|host| host.to_string());
+        let remote_ssh_args = matches.value_of("REMOTE_SSH_ARGS").map(split_args).unwrap_or(Vec::new());
+        let clusters = matches.value_of("CLUSTERS").map(split_names).unwrap_or(Vec::new());
+        let once = matches.is_present("once");
+        let format = parse_format(matches.value_of("FORMAT"))?;
+        let access_log_file = matches.value_of("ACCESS_LOG_FILE").map(|path| path.to_string());
+        let access_log = matches.is_present("access-log") || access_log_file.is_some();
+        let show_job_steps = matches.is_present("show-job-steps");
+        let enable_partition_limits = matches.is_present("enable-partition-limits");
+        let enable_licenses = matches.is_present("enable-licenses");
+        let enable_qos = matches.is_present("enable-qos");
+        let enable_sreport = matches.is_present("enable-sreport");
+        let sreport_interval = parse_sreport_interval(enable_sreport, matches.value_of("SREPORT_INTERVAL"))?;
+        let pressure_thresholds = parse_pressure_threshold_option(matches.value_of("PRESSURE_THRESHOLDS"))?;
+        let page_limit = parse_number("page-limit", matches.value_of("PAGE_LIMIT"), DEFAULT_PAGE_LIMIT)?;
+        let grid_width = parse_number("grid-width", matches.value_of("GRID_WIDTH"), DEFAULT_GRID_WIDTH)?;
+        let template_dir = matches.value_of("TEMPLATE_DIR").map(|path| path.to_string());
+        let refresh_spacing = parse_number("refresh-spacing", matches.value_of("REFRESH_SPACING"), DEFAULT_REFRESH_SPACING)?;
+        let url_prefix = normalize_url_prefix(matches.value_of("URL_PREFIX").unwrap_or(""));
+        let trust_proxy = matches.is_present("trust-proxy");
+        let remote_user_header = matches.value_of("REMOTE_USER_HEADER").map(|name| name.to_string());
+        let enable_admin_actions = matches.is_present("enable-admin-actions");
+        let debug_endpoints = matches.is_present("debug-endpoints");
+        let allowed_ips = parse_allowed_ips(matches.value_of("ALLOWED_IPS"))?;
+        let cors_allow_origin = parse_cors_allow_origin(matches.value_of("CORS_ALLOW_ORIGIN"))?;
+        let (listen_socket, socket_mode) = parse_listen_socket(matches.value_of("PORT").is_some(), matches.value_of("LISTEN_SOCKET"),
+            matches.value_of("SOCKET_MODE"))?;
+        let (tls_cert, tls_key) = parse_tls(matches.value_of("TLS_CERT"), matches.value_of("TLS_KEY"), listen_socket.is_some())?;
+        let frame_options = match matches.value_of("FRAME_OPTIONS") {
+            Some(value) => parse_frame_options(value).ok_or(ConfigError::InvalidFrameOptions(value.to_string()))?,
+            None => FrameOptions::SameOrigin
+        };
+        let http_threads = parse_number("http-threads", matches.value_of("HTTP_THREADS"), DEFAULT_HTTP_THREADS)?;
+        if http_threads == 0 {
+            return Err(ConfigError::InvalidNumber("http-threads".to_string(), "0".to_string()));
+        }
+        let max_requests = parse_number("max-requests", matches.value_of("MAX_REQUESTS"), DEFAULT_MAX_REQUESTS)?;
+        let http_read_timeout = parse_number("http-read-timeout", matches.value_of("HTTP_READ_TIMEOUT"), DEFAULT_HTTP_READ_TIMEOUT)?;
+        let http_write_timeout = parse_number("http-write-timeout", matches.value_of("HTTP_WRITE_TIMEOUT"), DEFAULT_HTTP_WRITE_TIMEOUT)?;
+        let record_dir = matches.value_of("RECORD_DIR").map(|path| path.to_string());
+        let record_keep = parse_number("record-keep", matches.value_of("RECORD_KEEP"), 0)?;
+        let replay_dir = matches.value_of("REPLAY_DIR").map(|path| path.to_string());
+        check_replay_options(record_dir.is_some(), replay_dir.is_some(), test_mode)?;
+        let prom_textfile = matches.value_of("PROM_TEXTFILE").map(|path| path.to_string());
+        let display_time = parse_display_time(matches.value_of("DISPLAY_TIMEZONE"), matches.value_of("DATE_FORMAT"))?;
+        let job_columns = parse_column_selection("job-columns", matches.value_of("JOB_COLUMNS"), &job_columns())?;
+        let node_columns = parse_column_selection("node-columns", matches.value_of("NODE_COLUMNS"), &node_columns())?;
+        let default_sort = match matches.value_of("DEFAULT_SORT") {
+            Some(value) => parse_job_sort(value).ok_or(ConfigError::InvalidSort(value.to_string()))?,
+            None => JobSort::State
+        };
+        let partitions = parse_partition_selection(matches.value_of("EXCLUDE_PARTITIONS"), matches.value_of("INCLUDE_PARTITIONS"))?;
+        let page_refresh = match matches.value_of("PAGE_REFRESH") {
+            Some(value) => parse_page_refresh(value).ok_or(ConfigError::InvalidNumber("page-refresh".to_string(), value.to_string()))?,
+            None => clamp_page_refresh(interval as i64)
         };
 
-        Configuration {
+        Ok(Configuration {
             port: port,
             interval: interval,
+            sinfo_interval: sinfo_interval,
+            squeue_interval: squeue_interval,
             test_mode: test_mode,
-            log_level: log_level.to_string()
-        }
+            log_level: log_level,
+            log_target: log_target,
+            log_format: log_format,
+            log_dir: log_dir,
+            log_rotate_size: log_rotate_size,
+            log_keep: log_keep,
+            page_refresh: page_refresh,
+            custom_css: custom_css,
+            banner_file: banner_file,
+            finished_hours: finished_hours,
+            recently_finished_minutes: recently_finished_minutes,
+            enable_sshare: enable_sshare,
+            enable_sprio: enable_sprio,
+            anonymize: anonymize,
+            users: users,
+            history_size: history_size,
+            history_db: history_db,
+            history_retention: history_retention,
+            cluster_name: cluster_name.to_string(),
+            slurm_version: UNKNOWN.to_string(),
+            webhook_url: webhook_url,
+            webhook_cooldown: webhook_cooldown,
+            email: email,
+            squeue_path: squeue_path.to_string(),
+            squeue_args: squeue_args,
+            squeue_states: squeue_states,
+            sinfo_path: sinfo_path.to_string(),
+            sinfo_args: sinfo_args,
+            remote_host: remote_host,
+            remote_ssh_args: remote_ssh_args,
+            clusters: clusters,
+            once: once,
+            format: format,
+            partitions: partitions,
+            access_log: access_log,
+            access_log_file: access_log_file,
+            show_job_steps: show_job_steps,
+            enable_partition_limits: enable_partition_limits,
+            enable_licenses: enable_licenses,
+            enable_qos: enable_qos,
+            enable_sreport: enable_sreport,
+            sreport_interval: sreport_interval,
+            pressure_thresholds: pressure_thresholds,
+            page_limit: page_limit,
+            grid_width: grid_width,
+            template_dir: template_dir,
+            templates: Arc::new(builtin_templates()),
+            refresh_spacing: refresh_spacing,
+            url_prefix: url_prefix,
+            trust_proxy: trust_proxy,
+            remote_user_header: remote_user_header,
+            enable_admin_actions: enable_admin_actions,
+            debug_endpoints: debug_endpoints,
+            allowed_ips: allowed_ips,
+            cors_allow_origin: cors_allow_origin,
+            listen_socket: listen_socket,
+            socket_mode: socket_mode,
+            tls_cert: tls_cert,
+            tls_key: tls_key,
+            frame_options: frame_options,
+            http_threads: http_threads,
+            max_requests: max_requests,
+            http_read_timeout: http_read_timeout,
+            http_write_timeout: http_write_timeout,
+            record_dir: record_dir,
+            record_keep: record_keep,
+            replay_dir: replay_dir,
+            prom_textfile: prom_textfile,
+            display_time: display_time,
+            test_data: test_data,
+            job_columns: job_columns,
+            node_columns: node_columns,
+            default_sort: default_sort
+        })
 }
 
 #[test]
 fn test_setup_configuration() {
-    assert_eq!(setup_configuration(), Configuration{ port: 4545, interval: 60, test_mode: false, log_level: "info".to_string() });
+    assert_eq!(setup_configuration(), Ok(Configuration{ port: 4545, interval: 60, sinfo_interval: 60, squeue_interval: 60, test_mode: false, log_level: "info".to_string(),
+        log_target: LogTarget::File, log_format: LogFormat::Detailed, log_dir: None, log_rotate_size: 10485760, log_keep: 7, page_refresh: 60, custom_css: None, banner_file: None, finished_hours: 24, recently_finished_minutes: 10, enable_sshare: false, enable_sprio: false, anonymize: false, users: Vec::new(), history_size: 60,
+        history_db: None, history_retention: 30,
+        cluster_name: String::new(), slurm_version: "unknown".to_string(), webhook_url: None, webhook_cooldown: 600, email: None,
+        squeue_path: "squeue".to_string(), squeue_args: Vec::new(), squeue_states: Some("all".to_string()), sinfo_path: "sinfo".to_string(), sinfo_args: Vec::new(),
+        remote_host: None, remote_ssh_args: Vec::new(), clusters: Vec::new(), once: false, format: OutputFormat::Text,
+        partitions: PartitionSelection::All, access_log: false, access_log_file: None, show_job_steps: false,
+        enable_partition_limits: false, enable_licenses: false, enable_qos: false, enable_sreport: false, sreport_interval: 3600, pressure_thresholds: PressureThresholds::default(), page_limit: 500, grid_width: 32, template_dir: None, templates: Arc::new(builtin_templates()),
+        refresh_spacing: 10, url_prefix: String::new(), trust_proxy: false, remote_user_header: None, enable_admin_actions: false, debug_endpoints: false,
+        allowed_ips: Vec::new(), cors_allow_origin: Vec::new(), listen_socket: None, socket_mode: 0o660, tls_cert: None, tls_key: None, frame_options: FrameOptions::SameOrigin, http_threads: 32, max_requests: 16, http_read_timeout: 30, http_write_timeout: 30, record_dir: None, record_keep: 0, replay_dir: None, prom_textfile: None,
+        display_time: DisplayTime::default(),
+        test_data: None, job_columns: Vec::new(), node_columns: Vec::new(), default_sort: JobSort::State }));
+    assert_eq!(setup_configuration(), Ok(Configuration::default()));
+}
+
+#[test]
+fn test_parse_format() {
+    assert_eq!(parse_format(None), Ok(OutputFormat::Text));
+    assert_eq!(parse_format(Some("json")), Ok(OutputFormat::Json));
+    assert_eq!(parse_format(Some("html")), Ok(OutputFormat::Html));
+    assert_eq!(parse_format(Some("xml")), Err(ConfigError::InvalidFormat("xml".to_string())));
+}
+
+#[test]
+fn test_split_names() {
+    assert_eq!(split_names(""), Vec::<String>::new());
+    assert_eq!(split_names("cluster1, cluster2,,"), vec!["cluster1", "cluster2"]);
+}
+
+#[test]
+fn test_parse_partition_selection() {
+    assert_eq!(parse_partition_selection(None, None), Ok(PartitionSelection::All));
+    assert_eq!(parse_partition_selection(Some("maintenance, cloud"), None),
+        Ok(PartitionSelection::Exclude(vec!["maintenance".to_string(), "cloud".to_string()])));
+    assert_eq!(parse_partition_selection(None, Some("batch")), Ok(PartitionSelection::Include(vec!["batch".to_string()])));
+    assert_eq!(parse_partition_selection(Some("cloud"), Some("batch")),
+        Err(ConfigError::ConflictingOptions("exclude-partitions".to_string(), "include-partitions".to_string())));
+    assert_eq!(format!("{}", ConfigError::ConflictingOptions("exclude-partitions".to_string(), "include-partitions".to_string())),
+        "--exclude-partitions and --include-partitions can't be used together");
+}
+
+#[test]
+fn test_parse_email_config() {
+    let missing = |option: &str, needed: &str| Err(ConfigError::MissingOption(option.to_string(), needed.to_string()));
+
+    assert_eq!(parse_email_config(None, None, None, None, None), Ok(None));
+    let email = parse_email_config(Some("mail.example.com"), Some("slurm@example.com"), Some("admin1@example.com, admin2@example.com"), None, None)
+        .unwrap().unwrap();
+    assert_eq!(email.to, vec!["admin1@example.com", "admin2@example.com"]);
+    assert_eq!((email.smtp_port, email.node_threshold, email.failing_minutes, email.cooldown), (25, 5, 30, 3600));
+    assert_eq!(email.smtp_user, None);
+
+    assert_eq!(parse_email_config(None, None, Some("admin@example.com"), None, None), missing("email-to", "smtp-server"));
+    assert_eq!(parse_email_config(Some("mail.example.com"), None, Some("admin@example.com"), None, None), missing("smtp-server", "email-from"));
+    assert_eq!(parse_email_config(Some("mail.example.com"), Some("slurm@example.com"), Some(" , "), None, None), missing("smtp-server", "email-to"));
+    assert_eq!(parse_email_config(Some("mail.example.com"), Some("slurm@example.com"), Some("admin@example.com"), Some("slurm"), None),
+        missing("smtp-user", "smtp-password-file"));
+    assert_eq!(parse_email_config(Some("mail.example.com"), Some("slurm@example.com"), Some("admin@example.com"), None, Some("/etc/slurm_inspector.pw")),
+        missing("smtp-password-file", "smtp-user"));
+}
+
+#[test]
+fn test_parse_display_time() {
+    use display_time::DisplayTimezone;
+
+    assert_eq!(parse_display_time(None, None), Ok(DisplayTime::default()));
+    assert_eq!(parse_display_time(Some("UTC"), Some("%d.%m.%Y %H:%M")),
+        Ok(DisplayTime { timezone: DisplayTimezone::Utc, date_format: "%d.%m.%Y %H:%M".to_string() }));
+    assert_eq!(parse_display_time(Some("+02:00"), None).map(|display_time| display_time.timezone), Ok(DisplayTimezone::Offset(7200)));
+    assert_eq!(parse_display_time(Some("Mars"), None), Err(ConfigError::InvalidTimezone("Mars".to_string())));
+    assert_eq!(parse_display_time(None, Some("%Y %Q")), Err(ConfigError::InvalidDateFormat("%Y %Q".to_string())));
+}
+
+#[test]
+fn test_check_replay_options() {
+    assert_eq!(check_replay_options(false, false, false), Ok(()));
+    assert_eq!(check_replay_options(true, false, false), Ok(()));
+    assert_eq!(check_replay_options(false, true, false), Ok(()));
+    assert_eq!(check_replay_options(true, true, false), Err(ConfigError::ConflictingOptions("record-dir".to_string(), "replay-dir".to_string())));
+    assert_eq!(check_replay_options(false, true, true), Err(ConfigError::ConflictingOptions("replay-dir".to_string(), "test".to_string())));
+}
+
+#[test]
+fn test_partition_selection_is_visible() {
+    let exclude = PartitionSelection::Exclude(vec!["maintenance".to_string(), "cloud".to_string()]);
+    let include = PartitionSelection::Include(vec!["batch".to_string()]);
+
+    assert!(PartitionSelection::All.is_visible("maintenance"));
+    assert!(!exclude.is_visible("cloud"));
+    assert!(exclude.is_visible("batch"));
+    assert!(include.is_visible("batch"));
+    assert!(!include.is_visible("cloud"));
+    assert!(!PartitionSelection::Include(Vec::new()).is_visible("batch"));
+}
+
+#[test]
+fn test_clamp_page_refresh_disabled() {
+    assert_eq!(clamp_page_refresh(0), 0);
+    assert_eq!(clamp_page_refresh(-1), 0);
+    assert_eq!(clamp_page_refresh(-3600), 0);
+}
+
+#[test]
+fn test_clamp_page_refresh_min() {
+    assert_eq!(clamp_page_refresh(1), MIN_PAGE_REFRESH);
+    assert_eq!(clamp_page_refresh(MIN_PAGE_REFRESH as i64), MIN_PAGE_REFRESH);
+}
+
+#[test]
+fn test_clamp_page_refresh_max() {
+    assert_eq!(clamp_page_refresh(MAX_PAGE_REFRESH as i64), MAX_PAGE_REFRESH);
+    assert_eq!(clamp_page_refresh(1000000), MAX_PAGE_REFRESH);
+}
+
+#[test]
+fn test_clamp_page_refresh_valid() {
+    assert_eq!(clamp_page_refresh(30), 30);
+    assert_eq!(clamp_page_refresh(60), 60);
+}
+
+#[test]
+fn test_parse_page_refresh() {
+    assert_eq!(parse_page_refresh("30"), Some(30));
+    assert_eq!(parse_page_refresh(" 30 "), Some(30));
+    assert_eq!(parse_page_refresh("0"), Some(0));
+    assert_eq!(parse_page_refresh("-5"), Some(0));
+    assert_eq!(parse_page_refresh("99999999999999999999999"), Some(MAX_PAGE_REFRESH));
+    assert_eq!(parse_page_refresh("-99999999999999999999999"), Some(0));
+}
+
+#[test]
+fn test_parse_page_refresh_invalid() {
+    assert_eq!(parse_page_refresh(""), None);
+    assert_eq!(parse_page_refresh("-"), None);
+    assert_eq!(parse_page_refresh("banana"), None);
+    assert_eq!(parse_page_refresh("1.5"), None);
+}
+
+#[test]
+fn test_parse_port() {
+    assert_eq!(parse_port(None), Ok(4545));
+    assert_eq!(parse_port(Some("8080")), Ok(8080));
+    assert_eq!(parse_port(Some("banana")), Err(ConfigError::InvalidPort("banana".to_string())));
+    assert_eq!(parse_port(Some("0")), Err(ConfigError::InvalidPort("0".to_string())));
+    assert_eq!(parse_port(Some("65536")), Err(ConfigError::InvalidPort("65536".to_string())));
+}
+
+#[test]
+fn test_parse_interval() {
+    assert_eq!(parse_interval(None), Ok(60));
+    assert_eq!(parse_interval(Some("120")), Ok(120));
+    assert_eq!(parse_interval(Some("0")), Err(ConfigError::InvalidInterval("0".to_string())));
+    assert_eq!(parse_interval(Some("-1")), Err(ConfigError::InvalidInterval("-1".to_string())));
+    assert_eq!(parse_interval(Some("soon")), Err(ConfigError::InvalidInterval("soon".to_string())));
+}
+
+#[test]
+fn test_parse_table_interval() {
+    assert_eq!(parse_table_interval(None, 120), Ok(120));
+    assert_eq!(parse_table_interval(Some("10"), 120), Ok(10));
+    assert_eq!(parse_table_interval(Some("0"), 120), Err(ConfigError::InvalidInterval("0".to_string())));
+}
+
+#[test]
+fn test_parse_log_level() {
+    assert_eq!(parse_log_level(None), Ok("info".to_string()));
+    assert_eq!(parse_log_level(Some("debug")), Ok("debug".to_string()));
+    assert_eq!(parse_log_level(Some("error")), Ok("error".to_string()));
+    assert_eq!(parse_log_level(Some("verbose")), Err(ConfigError::InvalidLogLevel("verbose".to_string())));
+}
+
+#[test]
+fn test_parse_size() {
+    assert_eq!(parse_size("log-rotate-size", None, 42), Ok(42));
+    assert_eq!(parse_size("log-rotate-size", Some("1000"), 42), Ok(1000));
+    assert_eq!(parse_size("log-rotate-size", Some("512K"), 42), Ok(524288));
+    assert_eq!(parse_size("log-rotate-size", Some("10m"), 42), Ok(10485760));
+    assert_eq!(parse_size("log-rotate-size", Some("1G"), 42), Ok(1073741824));
+    assert_eq!(parse_size("log-rotate-size", Some("0"), 42), Ok(0));
+    for value in vec!["", "M", "ten", "-1M", "10MB", "99999999999999999G"] {
+        assert_eq!(parse_size("log-rotate-size", Some(value), 42), Err(ConfigError::InvalidSize("log-rotate-size".to_string(), value.to_string())));
+    }
+}
+
+#[test]
+fn test_parse_log_keep() {
+    assert_eq!(parse_log_keep(None), Ok(7));
+    assert_eq!(parse_log_keep(Some("3")), Ok(3));
+    assert_eq!(parse_log_keep(Some("0")), Err(ConfigError::InvalidLogKeep("0".to_string())));
+    assert_eq!(parse_log_keep(Some("all")), Err(ConfigError::InvalidLogKeep("all".to_string())));
+}
+
+#[test]
+fn test_parse_log_target() {
+    assert_eq!(parse_log_target(None), Ok(LogTarget::File));
+    assert_eq!(parse_log_target(Some("file")), Ok(LogTarget::File));
+    assert_eq!(parse_log_target(Some("stdout")), Ok(LogTarget::Stdout));
+    assert_eq!(parse_log_target(Some("stderr")), Ok(LogTarget::Stderr));
+    assert_eq!(parse_log_target(Some("syslog")), Err(ConfigError::InvalidLogTarget("syslog".to_string())));
+    assert_eq!(format!("{}", ConfigError::InvalidLogTarget("syslog".to_string())), "invalid log target 'syslog', must be file, stdout or stderr");
+}
+
+#[test]
+fn test_parse_log_format() {
+    assert_eq!(parse_log_format(None), Ok(LogFormat::Detailed));
+    assert_eq!(parse_log_format(Some("detailed")), Ok(LogFormat::Detailed));
+    assert_eq!(parse_log_format(Some("json")), Ok(LogFormat::Json));
+    assert_eq!(parse_log_format(Some("logfmt")), Err(ConfigError::InvalidLogFormat("logfmt".to_string())));
+}
+
+#[test]
+fn test_parse_number() {
+    assert_eq!(parse_number("finished-hours", None, 24), Ok(24));
+    assert_eq!(parse_number("finished-hours", Some("48"), 24), Ok(48));
+    assert_eq!(parse_number::<u64>("finished-hours", Some("two"), 24), Err(ConfigError::InvalidNumber("finished-hours".to_string(), "two".to_string())));
+    assert_eq!(format!("{}", ConfigError::InvalidNumber("finished-hours".to_string(), "two".to_string())),
+        "invalid value 'two' for --finished-hours, must be a number");
 }
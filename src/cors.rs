@@ -0,0 +1,196 @@
+//! Cross-origin requests to the JSON API (--cors-allow-origin), for a frontend that is served from another host
+//! Only the paths under /api/ get the CORS headers, the HTML pages never do. The origins are compared after normalizing
+//! them (lower case, no trailing slash, no default port), the answer echoes the origin of the request like browsers expect it.
+//! OPTIONS requests to /api/ (the preflight of the browser) are answered with "204 No Content".
+
+// External modules:
+use iron::prelude::Response;
+use iron::status;
+
+/// Allows every origin, the answer is "*" instead of the origin of the request
+pub const ANY_ORIGIN: &'static str = "*";
+
+/// The methods of the JSON API, all of them only read
+const ALLOW_METHODS: &'static str = "GET, HEAD, OPTIONS";
+
+/// The request headers a frontend may send: content negotiation and conditional requests with the ETag
+const ALLOW_HEADERS: &'static str = "Accept, If-None-Match";
+
+/// Browsers may cache the answer of a preflight request for 10 minutes
+const PREFLIGHT_MAX_AGE: &'static str = "600";
+
+/// Public helper function that tells if the route (the path without URL prefix) belongs to the JSON API
+pub fn is_api_route(route: &str) -> bool {
+    route.starts_with("api/")
+}
+
+/// Public helper function that normalizes an origin like "https://Dashboard.example.com:443/" to "https://dashboard.example.com"
+/// Returns None if it's not "*" or scheme://host with an optional port (a path, a query or another scheme than http or https)
+pub fn normalize_origin(origin: &str) -> Option<String> {
+    let origin = origin.trim();
+    if origin == ANY_ORIGIN {
+        return Some(origin.to_string());
+    }
+
+    let origin = origin.trim_end_matches('/').to_lowercase();
+    let (scheme, host) = match origin.find("://") {
+        Some(index) => (&origin[..index], &origin[index + 3..]),
+        None => return None
+    };
+    let default_port = match scheme {
+        "http" => ":80",
+        "https" => ":443",
+        _ => return None
+    };
+    if host.is_empty() || host.contains(|c: char| c == '/' || c == '?' || c == '#' || c == '@' || c.is_whitespace()) {
+        return None;
+    }
+
+    let host = if host.ends_with(default_port) { &host[..host.len() - default_port.len()] } else { host };
+    Some(format!("{}://{}", scheme, host))
+}
+
+/// Public helper function that returns the value of the Access-Control-Allow-Origin header for the Origin header of a request
+/// None if CORS is off (no allowed origins), the request has no Origin header or its origin is not allowed
+pub fn allowed_origin(allowed: &[String], origin: Option<&str>) -> Option<String> {
+    if allowed.iter().any(|allowed| allowed == ANY_ORIGIN) {
+        return Some(ANY_ORIGIN.to_string());
+    }
+
+    origin.and_then(|origin| normalize_origin(origin)
+        .filter(|normalized| normalized != ANY_ORIGIN && allowed.contains(normalized))
+        .map(|_| origin.trim().to_string()))
+}
+
+/// Public helper function that adds the CORS headers to a response of the API if the origin is allowed
+/// The answer depends on the Origin header, so caches are told with "Vary: Origin" unless every origin is allowed
+pub fn add_cors_headers(res: &mut Response, allowed: &[String], origin: Option<&str>) {
+    let allow_origin = match allowed_origin(allowed, origin) {
+        Some(allow_origin) => allow_origin,
+        None => return
+    };
+
+    if allow_origin != ANY_ORIGIN {
+        let vary = match res.headers.get_raw("Vary").and_then(|values| values.first()).map(|value| String::from_utf8_lossy(value).into_owned()) {
+            Some(vary) => format!("{}, Origin", vary),
+            None => "Origin".to_string()
+        };
+        res.headers.set_raw("Vary", vec![vary.into_bytes()]);
+    }
+    res.headers.set_raw("Access-Control-Allow-Origin", vec![allow_origin.into_bytes()]);
+    res.headers.set_raw("Access-Control-Expose-Headers", vec![b"ETag".to_vec()]);
+}
+
+/// Public helper function that answers an OPTIONS request to the API with "204 No Content"
+/// The CORS headers are only added for an allowed origin, otherwise the browser refuses the request that follows
+pub fn preflight_response(allowed: &[String], origin: Option<&str>) -> Response {
+    let mut res = Response::new();
+
+    res.status = Some(status::NoContent);
+    res.headers.set_raw("Allow", vec![ALLOW_METHODS.as_bytes().to_vec()]);
+    if allowed_origin(allowed, origin).is_some() {
+        res.headers.set_raw("Access-Control-Allow-Methods", vec![ALLOW_METHODS.as_bytes().to_vec()]);
+        res.headers.set_raw("Access-Control-Allow-Headers", vec![ALLOW_HEADERS.as_bytes().to_vec()]);
+        res.headers.set_raw("Access-Control-Max-Age", vec![PREFLIGHT_MAX_AGE.as_bytes().to_vec()]);
+        add_cors_headers(&mut res, allowed, origin);
+    }
+
+    res
+}
+
+#[test]
+fn test_is_api_route() {
+    assert!(is_api_route("api/status"));
+    assert!(is_api_route("api/partitions/esd"));
+    // The HTML pages and the other files never get the CORS headers
+    assert!(!is_api_route(""));
+    assert!(!is_api_route("status.txt"));
+    assert!(!is_api_route("partition/esd"));
+    assert!(!is_api_route("user/api/status"));
+    assert!(!is_api_route("apistatus"));
+}
+
+#[test]
+fn test_normalize_origin() {
+    assert_eq!(normalize_origin("https://dashboard.example.com"), Some("https://dashboard.example.com".to_string()));
+    assert_eq!(normalize_origin(" HTTPS://Dashboard.Example.com/ "), Some("https://dashboard.example.com".to_string()));
+    assert_eq!(normalize_origin("https://dashboard.example.com:443"), Some("https://dashboard.example.com".to_string()));
+    assert_eq!(normalize_origin("http://localhost:80/"), Some("http://localhost".to_string()));
+    assert_eq!(normalize_origin("http://localhost:8080"), Some("http://localhost:8080".to_string()));
+    // The default port of the other scheme is kept
+    assert_eq!(normalize_origin("https://localhost:80"), Some("https://localhost:80".to_string()));
+    assert_eq!(normalize_origin("*"), Some("*".to_string()));
+
+    assert_eq!(normalize_origin("dashboard.example.com"), None);
+    assert_eq!(normalize_origin("ftp://dashboard.example.com"), None);
+    assert_eq!(normalize_origin("https://dashboard.example.com/app"), None);
+    assert_eq!(normalize_origin("https://user@dashboard.example.com"), None);
+    assert_eq!(normalize_origin("https://"), None);
+    assert_eq!(normalize_origin(""), None);
+}
+
+#[test]
+fn test_allowed_origin() {
+    let allowed = vec!["https://dashboard.example.com".to_string(), "http://localhost:8080".to_string()];
+
+    // The origin of the request is echoed as it is
+    assert_eq!(allowed_origin(&allowed, Some("https://dashboard.example.com")), Some("https://dashboard.example.com".to_string()));
+    assert_eq!(allowed_origin(&allowed, Some("https://Dashboard.example.com/")), Some("https://Dashboard.example.com/".to_string()));
+    assert_eq!(allowed_origin(&allowed, Some("http://localhost:8080")), Some("http://localhost:8080".to_string()));
+    // The scheme and the port must match
+    assert_eq!(allowed_origin(&allowed, Some("http://dashboard.example.com")), None);
+    assert_eq!(allowed_origin(&allowed, Some("http://localhost:8081")), None);
+    assert_eq!(allowed_origin(&allowed, Some("https://evil.example.com")), None);
+    assert_eq!(allowed_origin(&allowed, Some("null")), None);
+    assert_eq!(allowed_origin(&allowed, Some("*")), None);
+    assert_eq!(allowed_origin(&allowed, None), None);
+
+    // Off without allowed origins
+    assert_eq!(allowed_origin(&[], Some("https://dashboard.example.com")), None);
+
+    let any = vec![ANY_ORIGIN.to_string()];
+    assert_eq!(allowed_origin(&any, Some("https://evil.example.com")), Some("*".to_string()));
+    assert_eq!(allowed_origin(&any, None), Some("*".to_string()));
+}
+
+#[test]
+fn test_add_cors_headers() {
+    let allowed = vec!["https://dashboard.example.com".to_string()];
+
+    let mut res = Response::new();
+    res.headers.set_raw("Vary", vec![b"Accept-Encoding".to_vec()]);
+    add_cors_headers(&mut res, &allowed, Some("https://dashboard.example.com"));
+    assert_eq!(res.headers.get_raw("Access-Control-Allow-Origin"), Some(&[b"https://dashboard.example.com".to_vec()][..]));
+    assert_eq!(res.headers.get_raw("Vary"), Some(&[b"Accept-Encoding, Origin".to_vec()][..]));
+    assert_eq!(res.headers.get_raw("Access-Control-Expose-Headers"), Some(&[b"ETag".to_vec()][..]));
+
+    let mut res = Response::new();
+    add_cors_headers(&mut res, &allowed, Some("https://evil.example.com"));
+    assert!(res.headers.get_raw("Access-Control-Allow-Origin").is_none());
+    assert!(res.headers.get_raw("Vary").is_none());
+
+    let mut res = Response::new();
+    add_cors_headers(&mut res, &[ANY_ORIGIN.to_string()], Some("https://evil.example.com"));
+    assert_eq!(res.headers.get_raw("Access-Control-Allow-Origin"), Some(&[b"*".to_vec()][..]));
+    assert!(res.headers.get_raw("Vary").is_none());
+}
+
+#[test]
+fn test_preflight_response() {
+    let allowed = vec!["https://dashboard.example.com".to_string()];
+
+    let res = preflight_response(&allowed, Some("https://dashboard.example.com"));
+    assert_eq!(res.status, Some(status::NoContent));
+    assert_eq!(res.headers.get_raw("Access-Control-Allow-Origin"), Some(&[b"https://dashboard.example.com".to_vec()][..]));
+    assert_eq!(res.headers.get_raw("Access-Control-Allow-Methods"), Some(&[b"GET, HEAD, OPTIONS".to_vec()][..]));
+    assert_eq!(res.headers.get_raw("Access-Control-Allow-Headers"), Some(&[b"Accept, If-None-Match".to_vec()][..]));
+    assert_eq!(res.headers.get_raw("Access-Control-Max-Age"), Some(&[b"600".to_vec()][..]));
+
+    // Not allowed or CORS off: still answered, but without the CORS headers
+    for res in vec![preflight_response(&allowed, Some("https://evil.example.com")), preflight_response(&[], Some("https://dashboard.example.com"))] {
+        assert_eq!(res.status, Some(status::NoContent));
+        assert_eq!(res.headers.get_raw("Allow"), Some(&[b"GET, HEAD, OPTIONS".to_vec()][..]));
+        assert!(res.headers.get_raw("Access-Control-Allow-Origin").is_none());
+        assert!(res.headers.get_raw("Access-Control-Allow-Methods").is_none());
+    }
+}
@@ -0,0 +1,141 @@
+//! Exports the current jobs and nodes as CSV (RFC 4180), for example for spreadsheets
+//! Each line ends with CRLF, missing values are written as "-" like on the web page
+
+// Internal modules:
+use squeue_util::JobInfo;
+use sinfo_util::PartitionNodeInfo;
+use status_filter::StatusFilter;
+use status_page::{JobRow, job_row, node_rows};
+use columns::{Column, job_columns, node_columns, selected_columns, row_cells};
+
+/// Public helper function to quote a single CSV field if necessary
+/// Fields containing a comma, a double quote or a line break are put in double quotes, double quotes are doubled
+pub fn csv_field(field: &str) -> String {
+    if field.contains(',') || field.contains('"') || field.contains('\r') || field.contains('\n') {
+        format!("\"{}\"", field.replace("\"", "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+// Private helper function to join the fields of one line
+fn csv_line(fields: &[String]) -> String {
+    let quoted: Vec<String> = fields.iter().map(|field| csv_field(field)).collect();
+    format!("{}\r\n", quoted.join(","))
+}
+
+// Private helper function to write the header line and one line per row with the values of the selected columns
+fn rows_to_csv<R>(rows: &[R], columns: &[Column<R>], selection: &[String]) -> String {
+    let columns = selected_columns(columns, selection);
+    let header: Vec<String> = columns.iter().map(|column| column.header.to_string()).collect();
+    let mut result = csv_line(&header);
+
+    for row in rows {
+        let fields: Vec<String> = row_cells(row, &columns).into_iter().map(|cell| cell.value).collect();
+        result.push_str(&csv_line(&fields));
+    }
+
+    result
+}
+
+/// Public helper function to write the jobs that pass the filter as CSV with a header line
+/// The columns are the same as in the job table of the web page, an empty selection writes all columns
+pub fn jobs_to_csv(job_info: &[JobInfo], filter: &StatusFilter, selection: &[String]) -> String {
+    let rows: Vec<JobRow> = job_info.iter().filter(|job| filter.job_matches(job)).map(|job| job_row(job, None)).collect();
+    rows_to_csv(&rows, &job_columns(), selection)
+}
+
+/// Public helper function to write the nodes that pass the filter as CSV with a header line
+/// The columns are the same as in the node table of the web page, an empty selection writes all columns
+pub fn nodes_to_csv(node_info: &[PartitionNodeInfo], job_info: &[JobInfo], filter: &StatusFilter, selection: &[String]) -> String {
+    let nodes: Vec<&PartitionNodeInfo> = node_info.iter().filter(|node| filter.node_matches(node)).collect();
+    rows_to_csv(&node_rows(&nodes, job_info), &node_columns(), selection)
+}
+
+/// Public helper function to build the file name of an export, including the time of the last update
+/// Characters that are problematic in file names are removed, for example "2000.01.01 - 09:00" becomes "jobs_2000.01.01-09-00.csv"
+pub fn export_file_name(name: &str, last_update: &str) -> String {
+    let timestamp: String = last_update.chars()
+        .filter(|c| !c.is_whitespace())
+        .map(|c| if c.is_alphanumeric() || c == '.' || c == '-' { c } else { '-' })
+        .collect();
+
+    if timestamp.is_empty() {
+        format!("{}.csv", name)
+    } else {
+        format!("{}_{}.csv", name, timestamp)
+    }
+}
+
+#[test]
+fn test_csv_field() {
+    assert_eq!(csv_field("small_test01"), "small_test01");
+    assert_eq!(csv_field(""), "");
+    assert_eq!(csv_field("test, run 2"), "\"test, run 2\"");
+    assert_eq!(csv_field("say \"hello\""), "\"say \"\"hello\"\"\"");
+    assert_eq!(csv_field("two\nlines"), "\"two\nlines\"");
+}
+
+#[test]
+fn test_jobs_to_csv() {
+    use squeue_util::get_job_info_test;
+
+    let mut jobs = get_job_info_test();
+    jobs[0].job_name = "sim, run \"A\"".to_string();
+    let csv = jobs_to_csv(&jobs, &StatusFilter::new(), &[]);
+    let lines: Vec<&str> = csv.split("\r\n").collect();
+
+    // Header, one line per job and the empty string after the last CRLF
+    assert_eq!(lines.len(), jobs.len() + 2);
+    assert!(lines[0].starts_with("Cluster,Executing host,Min CPU,Num CPU,"));
+    assert_eq!(lines[1], ",node01,1,2,1,2 (a100),-,-,1,-,\"sim, run \"\"A\"\"\",-,-,1:00,0:01:00 (50%),node01,0.9,None,-,2000-01-01T09:00:00,-,RUNNING,user01,1000");
+    assert_eq!(lines[2], ",node01,1,2,2,,-,-,2,-,small_test02,-,-,1:15,-,node[01-02],0.9,None,-,2000-01-01T09:00:00,-,CANCELLED,user02,1001");
+    assert_eq!(lines[jobs.len() + 1], "");
+}
+
+#[test]
+fn test_jobs_to_csv_filter() {
+    use squeue_util::{get_job_info_test, JobState};
+
+    let filter = StatusFilter{ user: Some("user02".to_string()), job_states: vec![JobState::Pending], .. StatusFilter::new() };
+    let csv = jobs_to_csv(&get_job_info_test(), &filter, &[]);
+
+    assert_eq!(csv.lines().count(), 2);
+    assert!(csv.contains(",8,-,small_test08,"));
+}
+
+#[test]
+fn test_jobs_to_csv_columns() {
+    use squeue_util::get_job_info_test;
+
+    let selection = vec!["job_id".to_string(), "user_name".to_string(), "nodes".to_string()];
+    let csv = jobs_to_csv(&get_job_info_test(), &StatusFilter::new(), &selection);
+    let lines: Vec<&str> = csv.lines().collect();
+
+    assert_eq!(lines[0], "Job ID,User name,List of nodes");
+    assert_eq!(lines[2], "2,user02,node[01-02]");
+}
+
+#[test]
+fn test_nodes_to_csv() {
+    use sinfo_util::get_partition_node_info_test;
+    use squeue_util::get_job_info_test;
+
+    let csv = nodes_to_csv(&get_partition_node_info_test(), &get_job_info_test(), &StatusFilter::new(), &[]);
+    let lines: Vec<&str> = csv.lines().collect();
+
+    assert_eq!(lines.len(), 13);
+    assert_eq!(lines[0], "Cluster,Partition,Availability,Hostname,Node,Error,CPU load,Node state,Node sockets,Node cores,Node threads,CPUs (A/I/O/T),Utilization,Memory used/total,GPUs,Jobs");
+    assert_eq!(lines[3], ",esd,Down,node03,node03,down,-,unknown-,-,-,-,-,-,- / 3.9 GB,,6");
+    assert_eq!(lines[5], ",esd,Up,node05,node05,none,0,allocated,1,1,1,1/0/0/1,100%,3.7 GB / 3.9 GB (95%),,6");
+    assert_eq!(lines[12], ",esd,Up,node12,node12,none,0,unknown*,1,1,1,0/0/1/1,0%,-,,-");
+
+    let csv = nodes_to_csv(&get_partition_node_info_test(), &[], &StatusFilter::new(), &["state".to_string(), "node".to_string()]);
+    assert_eq!(csv.lines().nth(12), Some("unknown*,node12"));
+}
+
+#[test]
+fn test_export_file_name() {
+    assert_eq!(export_file_name("jobs", "2000.01.01 - 09:00"), "jobs_2000.01.01-09-00.csv");
+    assert_eq!(export_file_name("nodes", ""), "nodes.csv");
+}
@@ -0,0 +1,198 @@
+//! Time zone and format of the timestamps shown on the page (--display-timezone and --date-format)
+//! The times of the updates are formatted from seconds since the epoch, the times printed by SLURM (start times, reservations, ...)
+//! are in the local time of the server and are converted. Calculations like the age of the data always use seconds since the epoch,
+//! never the formatted strings.
+
+// System modules:
+use std::fmt;
+
+// External modules:
+use time::{Tm, Timespec, at, at_utc, strftime, strptime};
+
+// Internal modules:
+use wait_times::TIME_FORMAT;
+
+/// Format of the shown timestamps, default of --date-format
+pub const DEFAULT_DATE_FORMAT: &'static str = "%Y.%m.%d - %H:%M";
+
+/// Largest accepted offset of a fixed time zone in seconds (UTC+14:00)
+const MAX_OFFSET: i32 = 14 * 3600;
+
+/// The time zone of the shown timestamps
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DisplayTimezone {
+    /// The local time zone of the server, SLURM prints its times in this zone
+    Local,
+    Utc,
+    /// Fixed offset east of UTC in seconds, for example 7200 for UTC+02:00
+    Offset(i32)
+}
+
+impl fmt::Display for DisplayTimezone {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            DisplayTimezone::Local => write!(f, "server time"),
+            DisplayTimezone::Utc | DisplayTimezone::Offset(0) => write!(f, "UTC"),
+            DisplayTimezone::Offset(offset) => {
+                let minutes = offset.abs() / 60;
+                write!(f, "UTC{}{:02}:{:02}", if offset < 0 { '-' } else { '+' }, minutes / 60, minutes % 60)
+            }
+        }
+    }
+}
+
+/// Public helper function to parse the time zone: "local", "UTC" or a fixed offset like "+02:00", "-0530", "+2" or "UTC+2"
+/// Returns None for anything else and for offsets of more than 14 hours
+pub fn parse_display_timezone(text: &str) -> Option<DisplayTimezone> {
+    let text = text.trim();
+    if text.eq_ignore_ascii_case("local") {
+        return Some(DisplayTimezone::Local);
+    }
+    if text.eq_ignore_ascii_case("utc") || text == "Z" {
+        return Some(DisplayTimezone::Utc);
+    }
+
+    let offset = if text.len() > 3 && text[..3].eq_ignore_ascii_case("utc") { &text[3..] } else { text };
+    let sign = match offset.chars().next() {
+        Some('+') => 1,
+        Some('-') => -1,
+        _ => return None
+    };
+    let digits: String = offset[1..].chars().filter(|&c| c != ':').collect();
+    if digits.is_empty() || digits.len() > 4 || !digits.chars().all(|c| c.is_ascii_digit()) || offset[1..].starts_with(':') {
+        return None;
+    }
+    let (hours, minutes): (i32, i32) = if digits.len() <= 2 {
+        (digits.parse().ok()?, 0)
+    } else {
+        let split = digits.len() - 2;
+        (digits[..split].parse().ok()?, digits[split..].parse().ok()?)
+    };
+    let seconds = hours * 3600 + minutes * 60;
+    if minutes >= 60 || seconds > MAX_OFFSET {
+        return None;
+    }
+
+    Some(DisplayTimezone::Offset(sign * seconds))
+}
+
+/// Public helper function that checks a date format (strftime syntax), invalid formats are rejected at startup
+pub fn check_date_format(format: &str) -> bool {
+    !format.trim().is_empty() && strftime(format, &at_utc(Timespec::new(0, 0))).is_ok()
+}
+
+/// Time zone and format of the shown timestamps
+#[derive(Debug, Clone, PartialEq)]
+pub struct DisplayTime {
+    pub timezone: DisplayTimezone,
+    /// strftime format, checked with check_date_format
+    pub date_format: String
+}
+
+impl Default for DisplayTime {
+    fn default() -> DisplayTime {
+        DisplayTime {
+            timezone: DisplayTimezone::Local,
+            date_format: DEFAULT_DATE_FORMAT.to_string()
+        }
+    }
+}
+
+impl DisplayTime {
+    // Private helper function that returns the broken-down time of the instant in the time zone
+    fn tm_at(&self, seconds: i64) -> Tm {
+        match self.timezone {
+            DisplayTimezone::Local => at(Timespec::new(seconds, 0)),
+            DisplayTimezone::Utc => at_utc(Timespec::new(seconds, 0)),
+            DisplayTimezone::Offset(offset) => Tm { tm_utcoff: offset, .. at_utc(Timespec::new(seconds + offset as i64, 0)) }
+        }
+    }
+
+    /// Formats the instant (seconds since the epoch), for example the time of the last update
+    pub fn format(&self, seconds: i64) -> String {
+        strftime(&self.date_format, &self.tm_at(seconds)).unwrap_or_default()
+    }
+
+    /// Formats a time printed by SLURM (format: %Y-%m-%dT%H:%M:%S, local time of the server), other texts are returned as they are
+    pub fn format_slurm_time(&self, text: &str) -> String {
+        let naive = match strptime(text, TIME_FORMAT) {
+            Ok(tm) => tm.to_timespec().sec,
+            Err(_) => return text.to_string()
+        };
+
+        match self.timezone {
+            // Already in the right time zone, only the format changes
+            DisplayTimezone::Local => strftime(&self.date_format, &at_utc(Timespec::new(naive, 0))).unwrap_or_default(),
+            _ => self.format(naive - at(Timespec::new(naive, 0)).tm_utcoff as i64)
+        }
+    }
+
+    /// Label of the time zone shown next to the timestamps, for example "UTC+02:00"
+    pub fn label(&self) -> String {
+        self.timezone.to_string()
+    }
+}
+
+#[test]
+fn test_parse_display_timezone() {
+    assert_eq!(parse_display_timezone("local"), Some(DisplayTimezone::Local));
+    assert_eq!(parse_display_timezone("UTC"), Some(DisplayTimezone::Utc));
+    assert_eq!(parse_display_timezone("utc"), Some(DisplayTimezone::Utc));
+    assert_eq!(parse_display_timezone("+02:00"), Some(DisplayTimezone::Offset(7200)));
+    assert_eq!(parse_display_timezone("-0530"), Some(DisplayTimezone::Offset(-19800)));
+    assert_eq!(parse_display_timezone("+2"), Some(DisplayTimezone::Offset(7200)));
+    assert_eq!(parse_display_timezone("UTC-10"), Some(DisplayTimezone::Offset(-36000)));
+    assert_eq!(parse_display_timezone("+14:00"), Some(DisplayTimezone::Offset(50400)));
+    assert_eq!(parse_display_timezone("+15:00"), None);
+    assert_eq!(parse_display_timezone("+02:60"), None);
+    assert_eq!(parse_display_timezone("+:20"), None);
+    assert_eq!(parse_display_timezone("2"), None);
+    assert_eq!(parse_display_timezone("Europe/Berlin"), None);
+    assert_eq!(parse_display_timezone(""), None);
+}
+
+#[test]
+fn test_timezone_label() {
+    assert_eq!(DisplayTimezone::Utc.to_string(), "UTC");
+    assert_eq!(DisplayTimezone::Offset(0).to_string(), "UTC");
+    assert_eq!(DisplayTimezone::Offset(7200).to_string(), "UTC+02:00");
+    assert_eq!(DisplayTimezone::Offset(-19800).to_string(), "UTC-05:30");
+    assert_eq!(DisplayTimezone::Local.to_string(), "server time");
+}
+
+#[test]
+fn test_check_date_format() {
+    assert!(check_date_format(DEFAULT_DATE_FORMAT));
+    assert!(check_date_format("%d.%m.%Y %H:%M:%S"));
+    assert!(!check_date_format("%Y-%m-%d %Q"));
+    assert!(!check_date_format(""));
+}
+
+#[test]
+fn test_display_time_format() {
+    // 2015-12-18 10:00:00 UTC
+    let instant = 1450432800;
+    let utc = DisplayTime { timezone: DisplayTimezone::Utc, .. DisplayTime::default() };
+
+    assert_eq!(utc.format(instant), "2015.12.18 - 10:00");
+    assert_eq!(DisplayTime { timezone: DisplayTimezone::Offset(-19800), .. utc.clone() }.format(instant), "2015.12.18 - 04:30");
+    assert_eq!(DisplayTime { timezone: DisplayTimezone::Offset(50400), .. utc.clone() }.format(instant), "2015.12.19 - 00:00");
+    assert_eq!(DisplayTime { date_format: "%d/%m/%Y %H:%M:%S %z".to_string(), timezone: DisplayTimezone::Offset(3600) }.format(instant),
+        "18/12/2015 11:00:00 +0100");
+    assert_eq!(DisplayTime { timezone: DisplayTimezone::Local, .. utc }.format(instant),
+        strftime(DEFAULT_DATE_FORMAT, &at(Timespec::new(instant, 0))).unwrap());
+}
+
+#[test]
+fn test_format_slurm_time() {
+    let local = DisplayTime::default();
+    assert_eq!(local.format_slurm_time("2015-12-18T10:00:00"), "2015.12.18 - 10:00");
+    assert_eq!(local.format_slurm_time("N/A"), "N/A");
+    assert_eq!(local.format_slurm_time("-"), "-");
+
+    // Converted from the local time of the server
+    let naive = 1450432800;
+    let utc = DisplayTime { timezone: DisplayTimezone::Utc, date_format: "%Y-%m-%d %H:%M".to_string() };
+    let expected = strftime("%Y-%m-%d %H:%M", &at_utc(Timespec::new(naive - at(Timespec::new(naive, 0)).tm_utcoff as i64, 0))).unwrap();
+    assert_eq!(utc.format_slurm_time("2015-12-18T10:00:00"), expected);
+}
@@ -0,0 +1,673 @@
+//! Email notifications via SMTP when a partition goes down, when many nodes fail within one update or when the updates keep failing
+//! For sites without a webhook receiver. The SLURM status thread only checks the triggers and queues the messages,
+//! a separate mail thread talks to the mail server, so a slow or unreachable server never delays the updates
+//! The connection is switched to TLS with STARTTLS if the server offers it, the password of --smtp-user is only sent over TLS
+
+// System modules:
+use std::collections::BTreeMap;
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::mem;
+use std::fs::File;
+use std::net::TcpStream;
+use std::sync::mpsc::{Sender, channel};
+use std::thread;
+use std::time::Duration;
+
+// External modules:
+use time::now;
+use rustc_serialize::base64::{ToBase64, STANDARD};
+use openssl::ssl::{Ssl, SslContext, SslMethod, SslStream, SSL_VERIFY_PEER};
+
+// Internal modules:
+use sinfo_util::{NodeState, PartitionAvailability};
+use node_events::{NodeEvent, NodeEventKind, PartitionEvent};
+use error_log::{ErrorCategory, record_error};
+
+/// Default port of the SMTP server, default of --smtp-port
+pub const DEFAULT_SMTP_PORT: u16 = 25;
+
+/// A message is sent if more nodes than this go down or fail within one update, default of --email-node-threshold
+pub const DEFAULT_EMAIL_NODE_THRESHOLD: usize = 5;
+
+/// A message is sent if the updates have been failing for longer than this (in minutes), default of --email-failing-minutes
+pub const DEFAULT_EMAIL_FAILING_MINUTES: u64 = 30;
+
+/// At most one message per trigger is sent within this time (in sec.), default of --email-cooldown
+pub const DEFAULT_EMAIL_COOLDOWN: u64 = 3600;
+
+/// Timeout of the connection to the SMTP server in seconds
+const SMTP_TIMEOUT: u64 = 30;
+
+/// Settings of the email notifications, given on the command line
+#[derive(Debug, Clone, PartialEq)]
+pub struct EmailConfig {
+    pub smtp_server: String,
+    pub smtp_port: u16,
+    pub from: String,
+    /// At least one recipient
+    pub to: Vec<String>,
+    /// User name for SMTP AUTH PLAIN, None sends without authentication. Only sent after STARTTLS
+    pub smtp_user: Option<String>,
+    /// File with the password of smtp_user, it's read before each message, so the password doesn't show up in the process list
+    pub smtp_password_file: Option<String>,
+    pub node_threshold: usize,
+    pub failing_minutes: u64,
+    /// Cooldown in seconds
+    pub cooldown: u64
+}
+
+/// The subject and text of a notification
+#[derive(Debug, Clone, PartialEq)]
+pub struct EmailMessage {
+    pub subject: String,
+    pub body: String
+}
+
+/// Sends a message to the recipients, can be replaced in tests
+pub trait Mailer {
+    fn send(&self, message: &EmailMessage) -> Result<(), String>;
+}
+
+/// What triggered a notification, at most one message per trigger is sent within the cooldown
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum EmailTrigger {
+    PartitionDown,
+    NodesFailed,
+    UpdatesFailing
+}
+
+// Private helper function that returns the prefix of the subject, with the name of the cluster if there is one
+fn subject_prefix(cluster: &str) -> String {
+    if cluster.is_empty() {
+        "[slurm_inspector]".to_string()
+    } else {
+        format!("[slurm_inspector {}]", cluster)
+    }
+}
+
+/// Public helper function that returns the message about the partitions that went down, None if no partition went down
+pub fn partition_down_message(cluster: &str, events: &[PartitionEvent]) -> Option<EmailMessage> {
+    let down: Vec<&PartitionEvent> = events.iter().filter(|event| event.to == PartitionAvailability::Down).collect();
+    let subject = match down.len() {
+        0 => return None,
+        1 => format!("{} Partition {} is down", subject_prefix(cluster), down[0].partition),
+        count => format!("{} {} partitions are down", subject_prefix(cluster), count)
+    };
+    let body = down.iter()
+        .map(|event| format!("{}: partition {} changed from {} to {}\n", event.time, event.partition, event.from, event.to))
+        .collect();
+
+    Some(EmailMessage { subject: subject, body: body })
+}
+
+/// Public helper function that returns the message about the nodes that went down or failed within one update
+/// None if there are not more than threshold of them
+pub fn nodes_failed_message(cluster: &str, events: &[NodeEvent], threshold: usize) -> Option<EmailMessage> {
+    let failed: Vec<String> = events.iter().filter_map(|event| match event.kind {
+        NodeEventKind::StateChanged { from, to, .. } if to == NodeState::Down || to == NodeState::Fail => {
+            Some(format!("{}: node {} changed from {} to {}\n", event.time, event.node, from, to))
+        },
+        _ => None
+    }).collect();
+
+    if failed.len() > threshold {
+        Some(EmailMessage {
+            subject: format!("{} {} nodes went down or failed", subject_prefix(cluster), failed.len()),
+            body: failed.concat()
+        })
+    } else {
+        None
+    }
+}
+
+/// Public helper function that returns the message about the updates that have been failing for the given number of minutes
+pub fn updates_failing_message(cluster: &str, minutes: i64, errors: &[String]) -> EmailMessage {
+    let mut body = format!("The updates of the SLURM status have been failing for {} minutes, the page shows old data.\n\nLast errors:\n", minutes);
+    for error in errors {
+        body.push_str(&format!("{}\n", error));
+    }
+
+    EmailMessage {
+        subject: format!("{} Updates failing for {} minutes", subject_prefix(cluster), minutes),
+        body: body
+    }
+}
+
+/// Checks the triggers after each update and queues the messages for the mail thread
+pub struct EmailNotifier {
+    cluster: String,
+    node_threshold: usize,
+    /// In seconds
+    failing_time: i64,
+    /// Cooldown in seconds
+    cooldown: i64,
+    /// Time of the last message of each trigger
+    last_sent: BTreeMap<EmailTrigger, i64>,
+    /// Time of the first failed update since the last update without errors
+    failing_since: Option<i64>,
+    queue: Sender<EmailMessage>
+}
+
+impl EmailNotifier {
+    /// Create a new notifier that queues the messages in the given channel, see start_mail_thread
+    pub fn new(config: &EmailConfig, cluster: &str, queue: Sender<EmailMessage>) -> EmailNotifier {
+        EmailNotifier {
+            cluster: cluster.to_string(),
+            node_threshold: config.node_threshold,
+            failing_time: (config.failing_minutes * 60) as i64,
+            cooldown: config.cooldown as i64,
+            last_sent: BTreeMap::new(),
+            failing_since: None,
+            queue: queue
+        }
+    }
+
+    // Private helper function that queues the message unless a message of the same trigger has been queued within the cooldown
+    fn queue(&mut self, trigger: EmailTrigger, message: EmailMessage, now: i64) {
+        if let Some(last_time) = self.last_sent.get(&trigger) {
+            if now - last_time < self.cooldown {
+                debug!("Skip email notification within cooldown: {:?}", trigger);
+                return;
+            }
+        }
+
+        self.last_sent.insert(trigger, now);
+        if self.queue.send(message).is_err() {
+            error!("Could not queue email notification, the mail thread has stopped");
+        }
+    }
+
+    /// Checks the node and partition changes of an update
+    pub fn check_events(&mut self, node_events: &[NodeEvent], partition_events: &[PartitionEvent], now: i64) {
+        if let Some(message) = partition_down_message(&self.cluster, partition_events) {
+            self.queue(EmailTrigger::PartitionDown, message, now);
+        }
+        if let Some(message) = nodes_failed_message(&self.cluster, node_events, self.node_threshold) {
+            self.queue(EmailTrigger::NodesFailed, message, now);
+        }
+    }
+
+    /// Checks the errors of an update, a message is sent once the updates have been failing for longer than --email-failing-minutes
+    pub fn check_errors(&mut self, update_errors: &[String], now: i64) {
+        if update_errors.is_empty() {
+            self.failing_since = None;
+            return;
+        }
+
+        let failing_since = *self.failing_since.get_or_insert(now);
+        if now - failing_since > self.failing_time {
+            let message = updates_failing_message(&self.cluster, (now - failing_since) / 60, update_errors);
+            self.queue(EmailTrigger::UpdatesFailing, message, now);
+        }
+    }
+}
+
+/// Connection to the SMTP server that can be switched to TLS, the tests replace it with fixed replies
+pub trait SmtpConnection: BufRead + Write {
+    /// Switches to TLS after the server has accepted STARTTLS, server is the name in the certificate
+    fn start_tls(&mut self, server: &str) -> Result<(), String>;
+}
+
+// Private helper function that reads a reply of the SMTP server (several lines like "250-..." and "250 ...")
+// and checks that the code is in the same class (2xx, 3xx) as the expected one, returns the lines of the reply
+fn expect_reply<R: BufRead>(reader: &mut R, expected: u32) -> Result<Vec<String>, String> {
+    let mut lines = Vec::new();
+    loop {
+        let mut line = String::new();
+        if reader.read_line(&mut line).map_err(|err| format!("{}", err))? == 0 {
+            return Err("connection closed by the SMTP server".to_string());
+        }
+        let code = line.get(..3).and_then(|code| code.parse::<u32>().ok()).ok_or(format!("invalid reply: {}", line.trim()))?;
+        lines.push(line.trim().to_string());
+        if line.as_bytes().get(3) == Some(&b'-') {
+            continue;
+        }
+
+        return if code / 100 == expected / 100 {
+            Ok(lines)
+        } else {
+            Err(format!("unexpected reply: {}", line.trim()))
+        };
+    }
+}
+
+// Private helper function that sends one command to the SMTP server and checks the reply
+fn smtp_command<C: BufRead + Write>(connection: &mut C, command: &str, expected: u32) -> Result<Vec<String>, String> {
+    connection.write_all(format!("{}\r\n", command).as_bytes()).and_then(|_| connection.flush()).map_err(|err| format!("{}", err))?;
+    expect_reply(connection, expected)
+}
+
+// Private helper function that checks if the reply to EHLO lists STARTTLS, the first line is the greeting
+fn offers_starttls(ehlo_reply: &[String]) -> bool {
+    ehlo_reply.iter().skip(1).any(|line| line.get(4..).map_or(false, |extension| extension.trim().eq_ignore_ascii_case("STARTTLS")))
+}
+
+/// Public helper function that returns the message with its header as sent after the DATA command, with CRLF line endings
+/// A line that starts with a dot gets a second one (dot-stuffing), the final "." line is not included
+pub fn format_email(from: &str, to: &[String], message: &EmailMessage, date: &str) -> String {
+    let mut result = format!("From: {}\r\nTo: {}\r\nSubject: {}\r\nDate: {}\r\nMIME-Version: 1.0\r\n\
+        Content-Type: text/plain; charset=utf-8\r\nContent-Transfer-Encoding: 8bit\r\n\r\n", from, to.join(", "), message.subject, date);
+
+    for line in message.body.lines() {
+        if line.starts_with('.') {
+            result.push('.');
+        }
+        result.push_str(line);
+        result.push_str("\r\n");
+    }
+
+    result
+}
+
+/// Public helper function that runs the SMTP dialog to send the message, password is the one of smtp_user
+/// If the server offers STARTTLS the connection is switched to TLS right after EHLO. Without TLS the message is only sent
+/// without authentication, with smtp_user the session is ended before the credentials are sent
+pub fn smtp_session<C: SmtpConnection>(connection: &mut C, config: &EmailConfig, password: Option<&str>,
+    message: &EmailMessage, date: &str) -> Result<(), String> {
+    expect_reply(connection, 220)?;
+    let ehlo_reply = smtp_command(connection, "EHLO slurm_inspector", 250)?;
+    if offers_starttls(&ehlo_reply) {
+        smtp_command(connection, "STARTTLS", 220)?;
+        connection.start_tls(&config.smtp_server)?;
+        // Everything the server said before TLS is forgotten (RFC 3207), so it's asked again
+        smtp_command(connection, "EHLO slurm_inspector", 250)?;
+    } else if config.smtp_user.is_some() {
+        let _ = smtp_command(connection, "QUIT", 221);
+        return Err("the SMTP server doesn't offer STARTTLS, the password of --smtp-user is only sent over TLS".to_string());
+    }
+    if let (Some(user), Some(password)) = (config.smtp_user.as_ref(), password) {
+        let credentials = format!("\0{}\0{}", user, password).as_bytes().to_base64(STANDARD);
+        smtp_command(connection, &format!("AUTH PLAIN {}", credentials), 235)?;
+    }
+    smtp_command(connection, &format!("MAIL FROM:<{}>", config.from), 250)?;
+    for to in &config.to {
+        smtp_command(connection, &format!("RCPT TO:<{}>", to), 250)?;
+    }
+    smtp_command(connection, "DATA", 354)?;
+    smtp_command(connection, &format!("{}.", format_email(&config.from, &config.to, message, date)), 250)?;
+    // The message has been accepted, a failed QUIT doesn't matter
+    let _ = smtp_command(connection, "QUIT", 221);
+
+    Ok(())
+}
+
+// The TCP connection to the SMTP server, after STARTTLS wrapped in TLS
+enum SmtpStream {
+    Plain(TcpStream),
+    Tls(SslStream<TcpStream>),
+    // Only while the connection is switched
+    Closed
+}
+
+impl Read for SmtpStream {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        match *self {
+            SmtpStream::Plain(ref mut stream) => stream.read(buf),
+            SmtpStream::Tls(ref mut stream) => stream.read(buf),
+            SmtpStream::Closed => Err(io::Error::new(io::ErrorKind::NotConnected, "the connection has been closed"))
+        }
+    }
+}
+
+impl Write for SmtpStream {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match *self {
+            SmtpStream::Plain(ref mut stream) => stream.write(buf),
+            SmtpStream::Tls(ref mut stream) => stream.write(buf),
+            SmtpStream::Closed => Err(io::Error::new(io::ErrorKind::NotConnected, "the connection has been closed"))
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match *self {
+            SmtpStream::Plain(ref mut stream) => stream.flush(),
+            SmtpStream::Tls(ref mut stream) => stream.flush(),
+            SmtpStream::Closed => Ok(())
+        }
+    }
+}
+
+// The connection of SmtpMailer, the reader buffers the replies of the server
+struct SmtpTcpConnection {
+    reader: BufReader<SmtpStream>
+}
+
+impl Read for SmtpTcpConnection {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.reader.read(buf)
+    }
+}
+
+impl BufRead for SmtpTcpConnection {
+    fn fill_buf(&mut self) -> io::Result<&[u8]> {
+        self.reader.fill_buf()
+    }
+
+    fn consume(&mut self, amount: usize) {
+        self.reader.consume(amount)
+    }
+}
+
+impl Write for SmtpTcpConnection {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.reader.get_mut().write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.reader.get_mut().flush()
+    }
+}
+
+impl SmtpConnection for SmtpTcpConnection {
+    fn start_tls(&mut self, server: &str) -> Result<(), String> {
+        // Anything after the reply to STARTTLS was sent before TLS and could have been injected on the way
+        if !self.reader.buffer().is_empty() {
+            return Err("the SMTP server sent more than the reply to STARTTLS".to_string());
+        }
+        let stream = match mem::replace(self.reader.get_mut(), SmtpStream::Closed) {
+            SmtpStream::Plain(stream) => stream,
+            _ => return Err("the connection already uses TLS".to_string())
+        };
+        // The certificate must be signed by one of the certificate authorities of the system
+        let mut context = SslContext::new(SslMethod::Sslv23).map_err(|err| format!("{}", err))?;
+        context.set_default_verify_paths().map_err(|err| format!("{}", err))?;
+        context.set_verify(SSL_VERIFY_PEER, None);
+        let ssl = Ssl::new(&context).map_err(|err| format!("{}", err))?;
+        ssl.set_hostname(server).map_err(|err| format!("{}", err))?;
+        let stream = SslStream::connect(ssl, stream).map_err(|err| format!("TLS handshake with the SMTP server failed: {}", err))?;
+        *self.reader.get_mut() = SmtpStream::Tls(stream);
+        Ok(())
+    }
+}
+
+/// Sends the messages to the configured SMTP server
+pub struct SmtpMailer {
+    pub config: EmailConfig
+}
+
+impl Mailer for SmtpMailer {
+    fn send(&self, message: &EmailMessage) -> Result<(), String> {
+        let password = match self.config.smtp_password_file {
+            Some(ref path) => {
+                let mut password = String::new();
+                File::open(path).and_then(|mut file| file.read_to_string(&mut password))
+                    .map_err(|err| format!("could not read the password file '{}': {}", path, err))?;
+                Some(password.trim().to_string())
+            },
+            None => None
+        };
+
+        let stream = TcpStream::connect((&self.config.smtp_server[..], self.config.smtp_port)).map_err(|err| format!("{}", err))?;
+        stream.set_read_timeout(Some(Duration::new(SMTP_TIMEOUT, 0))).map_err(|err| format!("{}", err))?;
+        stream.set_write_timeout(Some(Duration::new(SMTP_TIMEOUT, 0))).map_err(|err| format!("{}", err))?;
+        let mut connection = SmtpTcpConnection { reader: BufReader::new(SmtpStream::Plain(stream)) };
+        let date = format!("{}", now().rfc822z());
+
+        smtp_session(&mut connection, &self.config, password.as_ref().map(|password| &password[..]), message, &date)
+    }
+}
+
+/// Public function that starts the mail thread, which sends the queued messages one after the other
+/// Returns the queue for the EmailNotifier, failed messages are logged and dropped
+pub fn start_mail_thread(mailer: Box<dyn Mailer + Send>) -> Sender<EmailMessage> {
+    let (sender, receiver) = channel::<EmailMessage>();
+
+    thread::spawn(move || {
+        for message in receiver.iter() {
+            match mailer.send(&message) {
+                Ok(_) => info!("Email notification sent: {}", message.subject),
+                Err(err) => {
+                    error!("Could not send email notification '{}': {}", message.subject, err);
+                    record_error(ErrorCategory::Email, &format!("{}: {}", message.subject, err));
+                }
+            }
+        }
+    });
+
+    sender
+}
+
+#[cfg(test)]
+fn test_email_config() -> EmailConfig {
+    EmailConfig {
+        smtp_server: "localhost".to_string(),
+        smtp_port: DEFAULT_SMTP_PORT,
+        from: "slurm@example.com".to_string(),
+        to: vec!["admin1@example.com".to_string(), "admin2@example.com".to_string()],
+        smtp_user: None,
+        smtp_password_file: None,
+        node_threshold: 2,
+        failing_minutes: 30,
+        cooldown: 3600
+    }
+}
+
+#[cfg(test)]
+fn test_node_event(node: &str, to: NodeState) -> NodeEvent {
+    use sinfo_util::ErrorCause;
+
+    NodeEvent {
+        time: "2000.01.01 - 03:00".to_string(),
+        node: node.to_string(),
+        kind: NodeEventKind::StateChanged { from: NodeState::Idle, to: to, reason: ErrorCause::None }
+    }
+}
+
+#[cfg(test)]
+fn test_partition_event(partition: &str, to: PartitionAvailability) -> PartitionEvent {
+    PartitionEvent { time: "2000.01.01 - 03:00".to_string(), partition: partition.to_string(), from: PartitionAvailability::Up, to: to }
+}
+
+#[test]
+fn test_partition_down_message() {
+    assert_eq!(partition_down_message("cluster1", &[test_partition_event("esd", PartitionAvailability::Up)]), None);
+    assert_eq!(partition_down_message("cluster1", &[test_partition_event("esd", PartitionAvailability::Down)]), Some(EmailMessage {
+        subject: "[slurm_inspector cluster1] Partition esd is down".to_string(),
+        body: "2000.01.01 - 03:00: partition esd changed from up to down\n".to_string()
+    }));
+
+    let message = partition_down_message("", &[test_partition_event("esd", PartitionAvailability::Down), test_partition_event("gpu", PartitionAvailability::Down)]);
+    assert_eq!(message.map(|message| message.subject), Some("[slurm_inspector] 2 partitions are down".to_string()));
+}
+
+#[test]
+fn test_nodes_failed_message() {
+    let events = vec![test_node_event("node01", NodeState::Down), test_node_event("node02", NodeState::Fail),
+        test_node_event("node03", NodeState::Drained), test_node_event("node04", NodeState::Allocated)];
+    assert_eq!(nodes_failed_message("cluster1", &events, 2), None);
+
+    let events: Vec<NodeEvent> = events.into_iter().chain(Some(test_node_event("node05", NodeState::Down))).collect();
+    assert_eq!(nodes_failed_message("cluster1", &events, 2), Some(EmailMessage {
+        subject: "[slurm_inspector cluster1] 3 nodes went down or failed".to_string(),
+        body: "2000.01.01 - 03:00: node node01 changed from idle to down\n2000.01.01 - 03:00: node node02 changed from idle to fail\n\
+            2000.01.01 - 03:00: node node05 changed from idle to down\n".to_string()
+    }));
+}
+
+#[test]
+fn test_email_notifier_cooldown() {
+    let (sender, receiver) = channel();
+    let mut notifier = EmailNotifier::new(&test_email_config(), "cluster1", sender);
+    let down = [test_partition_event("esd", PartitionAvailability::Down)];
+
+    notifier.check_events(&[], &down, 0);
+    // Within the cooldown of this trigger
+    notifier.check_events(&[], &[test_partition_event("gpu", PartitionAvailability::Down)], 600);
+    assert_eq!(receiver.try_iter().count(), 1);
+
+    // A different trigger has its own cooldown
+    let failed: Vec<NodeEvent> = ["node01", "node02", "node03"].iter().map(|node| test_node_event(node, NodeState::Down)).collect();
+    notifier.check_events(&failed, &[], 900);
+    assert_eq!(receiver.try_iter().map(|message| message.subject).collect::<Vec<String>>(),
+        vec!["[slurm_inspector cluster1] 3 nodes went down or failed".to_string()]);
+
+    notifier.check_events(&[], &down, 3600);
+    assert_eq!(receiver.try_iter().count(), 1);
+}
+
+#[test]
+fn test_email_notifier_updates_failing() {
+    let (sender, receiver) = channel();
+    let mut notifier = EmailNotifier::new(&test_email_config(), "cluster1", sender);
+    let errors = vec!["Could not execute 'sinfo': timeout".to_string()];
+
+    notifier.check_errors(&errors, 1000);
+    notifier.check_errors(&errors, 1000 + 1800);
+    assert_eq!(receiver.try_iter().count(), 0);
+
+    // Failing for longer than 30 minutes
+    notifier.check_errors(&errors, 1000 + 1860);
+    let messages: Vec<EmailMessage> = receiver.try_iter().collect();
+    assert_eq!(messages, vec![updates_failing_message("cluster1", 31, &errors)]);
+    assert_eq!(messages[0].subject, "[slurm_inspector cluster1] Updates failing for 31 minutes");
+    assert!(messages[0].body.ends_with("Last errors:\nCould not execute 'sinfo': timeout\n"));
+
+    // A successful update starts over, the cooldown still applies
+    notifier.check_errors(&[], 3000);
+    notifier.check_errors(&errors, 3060);
+    notifier.check_errors(&errors, 6000);
+    assert_eq!(receiver.try_iter().count(), 0);
+    notifier.check_errors(&errors, 1000 + 1860 + 3600);
+    assert_eq!(receiver.try_iter().count(), 1);
+}
+
+#[test]
+fn test_format_email() {
+    let message = EmailMessage { subject: "[slurm_inspector] Partition esd is down".to_string(), body: "first line\n.hidden\nlast line\n".to_string() };
+    let config = test_email_config();
+
+    assert_eq!(format_email(&config.from, &config.to, &message, "Sat, 01 Jan 2000 03:00:00 +0000"),
+        "From: slurm@example.com\r\nTo: admin1@example.com, admin2@example.com\r\nSubject: [slurm_inspector] Partition esd is down\r\n\
+        Date: Sat, 01 Jan 2000 03:00:00 +0000\r\nMIME-Version: 1.0\r\nContent-Type: text/plain; charset=utf-8\r\n\
+        Content-Transfer-Encoding: 8bit\r\n\r\nfirst line\r\n..hidden\r\nlast line\r\n");
+}
+
+// Connection of the tests with the replies of the server, STARTTLS is recorded as "<TLS>" in the written commands
+#[cfg(test)]
+struct TestConnection {
+    replies: ::std::io::Cursor<Vec<u8>>,
+    written: Vec<u8>
+}
+
+#[cfg(test)]
+impl TestConnection {
+    fn new(replies: &str) -> TestConnection {
+        TestConnection { replies: ::std::io::Cursor::new(replies.as_bytes().to_vec()), written: Vec::new() }
+    }
+
+    fn written(&self) -> String {
+        String::from_utf8(self.written.clone()).unwrap()
+    }
+}
+
+#[cfg(test)]
+impl Read for TestConnection {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.replies.read(buf)
+    }
+}
+
+#[cfg(test)]
+impl BufRead for TestConnection {
+    fn fill_buf(&mut self) -> io::Result<&[u8]> {
+        self.replies.fill_buf()
+    }
+
+    fn consume(&mut self, amount: usize) {
+        self.replies.consume(amount)
+    }
+}
+
+#[cfg(test)]
+impl Write for TestConnection {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.written.write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+impl SmtpConnection for TestConnection {
+    fn start_tls(&mut self, server: &str) -> Result<(), String> {
+        assert_eq!(server, "localhost");
+        self.written.extend_from_slice(b"<TLS>\r\n");
+        Ok(())
+    }
+}
+
+#[test]
+fn test_smtp_session() {
+    let config = EmailConfig { smtp_user: Some("slurm".to_string()), .. test_email_config() };
+    let message = EmailMessage { subject: "Test".to_string(), body: "Text\n".to_string() };
+    let mut connection = TestConnection::new("220 mail.example.com ESMTP\r\n250-mail.example.com\r\n250 STARTTLS\r\n220 Ready to start TLS\r\n\
+        250-mail.example.com\r\n250 AUTH PLAIN\r\n235 OK\r\n250 OK\r\n250 OK\r\n251 OK\r\n354 Go ahead\r\n250 Queued\r\n221 Bye\r\n");
+
+    assert_eq!(smtp_session(&mut connection, &config, Some("secret"), &message, "date"), Ok(()));
+    let written = connection.written();
+    let commands: Vec<&str> = written.split("\r\n").collect();
+    // The credentials are only sent after TLS has been started and the server has been greeted again
+    assert_eq!(&commands[..8], &["EHLO slurm_inspector", "STARTTLS", "<TLS>", "EHLO slurm_inspector", "AUTH PLAIN AHNsdXJtAHNlY3JldA==",
+        "MAIL FROM:<slurm@example.com>", "RCPT TO:<admin1@example.com>", "RCPT TO:<admin2@example.com>"]);
+    assert!(written.contains("\r\nDATA\r\nFrom: slurm@example.com\r\n"));
+    assert!(written.ends_with("\r\n\r\nText\r\n.\r\nQUIT\r\n"));
+
+    // Without STARTTLS a message without authentication is sent in plain text
+    let mut connection = TestConnection::new("220 mail.example.com\r\n250 OK\r\n250 OK\r\n250 OK\r\n250 OK\r\n354 Go ahead\r\n250 Queued\r\n221 Bye\r\n");
+    assert_eq!(smtp_session(&mut connection, &test_email_config(), None, &message, "date"), Ok(()));
+    assert!(connection.written().starts_with("EHLO slurm_inspector\r\nMAIL FROM:<slurm@example.com>\r\n"));
+
+    // A rejected recipient stops the session
+    let mut connection = TestConnection::new("220 mail.example.com\r\n250 OK\r\n250 OK\r\n550 No such user\r\n");
+    assert_eq!(smtp_session(&mut connection, &test_email_config(), None, &message, "date"),
+        Err("unexpected reply: 550 No such user".to_string()));
+    assert!(!connection.written().contains("DATA"));
+}
+
+#[test]
+fn test_smtp_session_without_tls() {
+    let config = EmailConfig { smtp_user: Some("slurm".to_string()), .. test_email_config() };
+    let message = EmailMessage { subject: "Test".to_string(), body: "Text\n".to_string() };
+
+    // The password is never sent over a connection without TLS
+    let mut connection = TestConnection::new("220 mail.example.com\r\n250-mail.example.com\r\n250 AUTH PLAIN\r\n221 Bye\r\n");
+    assert_eq!(smtp_session(&mut connection, &config, Some("secret"), &message, "date"),
+        Err("the SMTP server doesn't offer STARTTLS, the password of --smtp-user is only sent over TLS".to_string()));
+    assert_eq!(connection.written(), "EHLO slurm_inspector\r\nQUIT\r\n");
+
+    // The same if the server offers STARTTLS but then refuses it
+    let mut connection = TestConnection::new("220 mail.example.com\r\n250-mail.example.com\r\n250 starttls\r\n454 TLS not available\r\n");
+    assert_eq!(smtp_session(&mut connection, &config, Some("secret"), &message, "date"),
+        Err("unexpected reply: 454 TLS not available".to_string()));
+    assert_eq!(connection.written(), "EHLO slurm_inspector\r\nSTARTTLS\r\n");
+}
+
+#[test]
+fn test_mail_thread() {
+    use std::sync::{Arc, Mutex};
+
+    struct TestMailer {
+        sent: Arc<Mutex<Vec<EmailMessage>>>
+    }
+
+    impl Mailer for TestMailer {
+        fn send(&self, message: &EmailMessage) -> Result<(), String> {
+            self.sent.lock().unwrap().push(message.clone());
+            if message.subject == "fail" { Err("connection refused".to_string()) } else { Ok(()) }
+        }
+    }
+
+    let sent = Arc::new(Mutex::new(Vec::new()));
+    let queue = start_mail_thread(Box::new(TestMailer { sent: sent.clone() }));
+    let message = |subject: &str| EmailMessage { subject: subject.to_string(), body: String::new() };
+
+    // A failed message doesn't stop the thread
+    queue.send(message("fail")).unwrap();
+    queue.send(message("second")).unwrap();
+    while sent.lock().unwrap().len() < 2 {
+        thread::sleep(Duration::from_millis(10));
+    }
+    assert_eq!(*sent.lock().unwrap(), vec![message("fail"), message("second")]);
+}
@@ -0,0 +1,214 @@
+//! The recent warnings and errors of the inspector, kept in memory and shown at /debug/errors (with --debug-endpoints)
+//! Failed updates, lines that could not be parsed, caught panics of the handlers and failed notifications are recorded
+//! together with the time. Only the last ERROR_LOG_SIZE entries are kept, the oldest ones are dropped first
+
+// System modules:
+use std::collections::{BTreeMap, VecDeque};
+use std::fmt;
+use std::sync::Mutex;
+
+// External modules:
+use rustc_serialize::json::{Json, ToJson};
+use time::get_time;
+
+// Internal modules:
+use slurm_status::PageOptions;
+use display_time::DisplayTime;
+use template::RECENT_ERRORS_TEMPLATE;
+
+/// Maximum number of entries of the error log
+pub const ERROR_LOG_SIZE: usize = 200;
+
+/// Where an entry of the error log comes from
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ErrorCategory {
+    /// A SLURM command of the status update failed
+    Update,
+    /// The output of a SLURM command had lines that could not be parsed
+    SkippedLines,
+    /// A request handler panicked
+    Handler,
+    /// A request was answered with "500 Internal Server Error"
+    Http,
+    Webhook,
+    Email
+}
+
+impl ErrorCategory {
+    /// The name in the JSON and on the page
+    pub fn as_str(&self) -> &'static str {
+        match *self {
+            ErrorCategory::Update => "update",
+            ErrorCategory::SkippedLines => "skipped_lines",
+            ErrorCategory::Handler => "handler",
+            ErrorCategory::Http => "http",
+            ErrorCategory::Webhook => "webhook",
+            ErrorCategory::Email => "email"
+        }
+    }
+}
+
+impl fmt::Display for ErrorCategory {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// One warning or error with the time in seconds since the epoch
+#[derive(Debug, Clone, PartialEq)]
+pub struct ErrorEntry {
+    pub time: i64,
+    pub category: ErrorCategory,
+    pub message: String
+}
+
+impl ToJson for ErrorEntry {
+    fn to_json(&self) -> Json {
+        let mut result = BTreeMap::new();
+        result.insert("time".to_string(), self.time.to_json());
+        result.insert("category".to_string(), self.category.as_str().to_json());
+        result.insert("message".to_string(), self.message.to_json());
+        Json::Object(result)
+    }
+}
+
+// The entries and the number of entries that were dropped because the log was full
+struct ErrorEntries {
+    entries: VecDeque<ErrorEntry>,
+    dropped: u64
+}
+
+/// Ring buffer of the recent errors, shared by the status thread, the mail thread and the HTTP threads
+pub struct ErrorRecorder {
+    capacity: usize,
+    entries: Mutex<ErrorEntries>
+}
+
+impl ErrorRecorder {
+    /// Create an empty error log that keeps at most capacity entries
+    pub const fn new(capacity: usize) -> ErrorRecorder {
+        ErrorRecorder {
+            capacity: capacity,
+            entries: Mutex::new(ErrorEntries{ entries: VecDeque::new(), dropped: 0 })
+        }
+    }
+
+    // Private helper function to access the entries, a poisoned lock only means that a thread panicked while recording
+    fn with_entries<T, F: FnOnce(&mut ErrorEntries) -> T>(&self, f: F) -> T {
+        let mut entries = self.entries.lock().unwrap_or_else(|err| err.into_inner());
+        f(&mut entries)
+    }
+
+    /// Record an error, the oldest entry is dropped if the log is full
+    pub fn record(&self, time: i64, category: ErrorCategory, message: &str) {
+        let capacity = self.capacity;
+        self.with_entries(|log| {
+            while log.entries.len() >= capacity.max(1) {
+                log.entries.pop_front();
+                log.dropped += 1;
+            }
+            log.entries.push_back(ErrorEntry{ time: time, category: category, message: message.to_string() });
+        })
+    }
+
+    /// The recorded entries, the newest first
+    pub fn entries(&self) -> Vec<ErrorEntry> {
+        self.with_entries(|log| log.entries.iter().rev().cloned().collect())
+    }
+
+    /// Number of entries that were dropped to make room for newer ones
+    pub fn dropped(&self) -> u64 {
+        self.with_entries(|log| log.dropped)
+    }
+
+    /// The entries (newest first) and the number of dropped entries as JSON, the answer of /debug/errors.json
+    pub fn to_json(&self) -> Json {
+        let mut result = BTreeMap::new();
+        result.insert("capacity".to_string(), self.capacity.to_json());
+        result.insert("dropped".to_string(), self.dropped().to_json());
+        result.insert("errors".to_string(), self.entries().to_json());
+        Json::Object(result)
+    }
+}
+
+// The error log of the running inspector
+static ERRORS: ErrorRecorder = ErrorRecorder::new(ERROR_LOG_SIZE);
+
+/// Public helper function that returns the error log of the running inspector
+pub fn error_recorder() -> &'static ErrorRecorder {
+    &ERRORS
+}
+
+/// Public helper function that records an error with the current time in the error log of the running inspector
+pub fn record_error(category: ErrorCategory, message: &str) {
+    ERRORS.record(get_time().sec, category, message)
+}
+
+/// Public helper function to render the page of /debug/errors, the times are shown like the time of the last update
+pub fn recent_errors_page(recorder: &ErrorRecorder, options: &PageOptions, display_time: &DisplayTime) -> Result<String, String> {
+    let errors: Vec<Json> = recorder.entries().into_iter().map(|entry| {
+        let mut row = BTreeMap::new();
+        row.insert("time".to_string(), display_time.format(entry.time).to_json());
+        row.insert("category".to_string(), entry.category.as_str().to_json());
+        row.insert("message".to_string(), entry.message.to_json());
+        Json::Object(row)
+    }).collect();
+
+    let mut context = BTreeMap::new();
+    context.insert("url_prefix".to_string(), options.url_prefix.to_json());
+    context.insert("custom_css".to_string(), options.custom_css.to_json());
+    context.insert("capacity".to_string(), recorder.capacity.to_json());
+    context.insert("dropped".to_string(), recorder.dropped().to_json());
+    context.insert("errors".to_string(), Json::Array(errors));
+
+    options.templates.render(RECENT_ERRORS_TEMPLATE, &Json::Object(context))
+}
+
+#[test]
+fn test_error_recorder_eviction() {
+    let recorder = ErrorRecorder::new(3);
+    assert!(recorder.entries().is_empty());
+
+    for index in 0..5 {
+        recorder.record(1000 + index, ErrorCategory::Update, &format!("error {}", index));
+    }
+    let entries = recorder.entries();
+    assert_eq!(entries.iter().map(|entry| &entry.message[..]).collect::<Vec<&str>>(), vec!["error 4", "error 3", "error 2"]);
+    assert_eq!(entries[0].time, 1004);
+    assert_eq!(recorder.dropped(), 2);
+
+    let json = recorder.to_json();
+    assert_eq!(json.find("capacity").and_then(|value| value.as_u64()), Some(3));
+    assert_eq!(json.find("dropped").and_then(|value| value.as_u64()), Some(2));
+    assert_eq!(json.find("errors").and_then(|value| value.as_array()).map(|errors| errors.len()), Some(3));
+    assert_eq!(entries[0].to_json().find("category").and_then(|value| value.as_string()), Some("update"));
+}
+
+#[test]
+fn test_error_recorder_concurrent() {
+    use std::sync::Arc;
+    use std::thread;
+
+    let recorder = Arc::new(ErrorRecorder::new(50));
+    let threads: Vec<_> = (0..8).map(|thread_index| {
+        let recorder = recorder.clone();
+        thread::spawn(move || {
+            for index in 0..20 {
+                recorder.record(index, ErrorCategory::Handler, &format!("thread {} error {}", thread_index, index));
+            }
+        })
+    }).collect();
+    for thread in threads {
+        thread.join().unwrap();
+    }
+
+    // Every append is either kept or counted as dropped
+    assert_eq!(recorder.entries().len(), 50);
+    assert_eq!(recorder.dropped(), 8 * 20 - 50);
+    // The last append of each thread is newer than the ones it made before
+    for thread_index in 0..8 {
+        let prefix = format!("thread {} ", thread_index);
+        let indices: Vec<i64> = recorder.entries().iter().filter(|entry| entry.message.starts_with(&prefix)).map(|entry| entry.time).collect();
+        assert!(indices.windows(2).all(|pair| pair[0] > pair[1]));
+    }
+}
@@ -0,0 +1,221 @@
+//! Parses generic resources (GRES) like GPUs from the output of sinfo (%G) and squeue (%b)
+//! Examples: "gpu:a100:4(S:0-1)", "gpu:2,mps:100", "gres:gpu:a100:2" or "gres/gpu=2" (newer squeue versions)
+
+// System modules:
+use std::collections::BTreeMap;
+use std::fmt;
+
+// External modules:
+use rustc_serialize::json::{Json, ToJson};
+
+// Internal modules:
+use placeholder::is_placeholder;
+
+/// Name of the generic resource for GPUs
+pub const GPU_NAME: &'static str = "gpu";
+
+/// One generic resource of a node or a job, for example 4 GPUs of type a100
+#[derive(Debug, Clone, PartialEq)]
+pub struct GresInfo {
+    /// Name of the resource, for example "gpu" or "mps"
+    pub name: String,
+    /// Type of the resource, for example "a100", None if it was not given
+    pub type_: Option<String>,
+    /// Number of resources, 1 if it was not given
+    pub count: u64
+}
+
+impl GresInfo {
+    /// Returns true if the resource is a GPU
+    pub fn is_gpu(&self) -> bool {
+        self.name == GPU_NAME
+    }
+}
+
+impl fmt::Display for GresInfo {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.type_ {
+            Some(ref type_) => write!(f, "{}:{}:{}", self.name, type_, self.count),
+            None => write!(f, "{}:{}", self.name, self.count)
+        }
+    }
+}
+
+impl ToJson for GresInfo {
+    fn to_json(&self) -> Json {
+        let mut result = BTreeMap::new();
+        result.insert("name".to_string(), self.name.to_json());
+        result.insert("type".to_string(), self.type_.to_json());
+        result.insert("count".to_string(), self.count.to_json());
+        Json::Object(result)
+    }
+}
+
+// Private helper function to split the GRES string at commas that are not inside parentheses
+// The socket affinity can contain commas, for example "gpu:2(S:0,1)"
+fn split_top_level(gres: &str) -> Vec<&str> {
+    let mut result = Vec::new();
+    let mut depth = 0;
+    let mut start = 0;
+
+    for (index, c) in gres.char_indices() {
+        match c {
+            '(' => depth += 1,
+            ')' => depth -= 1,
+            ',' if depth == 0 => {
+                result.push(&gres[start..index]);
+                start = index + 1;
+            },
+            _ => {}
+        }
+    }
+    result.push(&gres[start..]);
+
+    result
+}
+
+// Private helper function to parse a count with an optional suffix, for example "4", "100" or "2K"
+// SLURM uses binary multipliers for the suffixes, None if the count is not a number or doesn't fit into an u64
+fn parse_count(count: &str) -> Option<u64> {
+    let (number, multiplier) = match count.chars().last() {
+        Some('K') | Some('k') => (&count[..count.len() - 1], 1024),
+        Some('M') | Some('m') => (&count[..count.len() - 1], 1024 * 1024),
+        Some('G') | Some('g') => (&count[..count.len() - 1], 1024 * 1024 * 1024),
+        _ => (count, 1)
+    };
+
+    number.parse::<u64>().ok().and_then(|number| number.checked_mul(multiplier))
+}
+
+// Private helper function to parse a single GRES entry, for example "gpu:a100:4(S:0-1)"
+// Returns None if the entry is empty or the count is not a number
+fn parse_gres_entry(entry: &str) -> Option<GresInfo> {
+    // Remove the socket affinity or the indices, for example "(S:0-1)" or "(IDX:0-3)"
+    let entry = entry.split('(').next().unwrap_or("").trim();
+    // squeue prints the tres-per-node with a prefix, for example "gres:gpu:2" or "gres/gpu=2"
+    let entry = if entry.starts_with("gres:") || entry.starts_with("gres/") { &entry[5..] } else { entry };
+    let parts: Vec<&str> = entry.split(|c| c == ':' || c == '=').collect();
+
+    let (name, type_, count) = match parts.len() {
+        1 => (parts[0], None, None),
+        // The second part is either the count or the type
+        2 => match parse_count(parts[1]) {
+            Some(count) => (parts[0], None, Some(count)),
+            None => (parts[0], Some(parts[1]), None)
+        },
+        3 => (parts[0], Some(parts[1]), Some(parse_count(parts[2])?)),
+        _ => return None
+    };
+
+    if name.is_empty() || type_.map_or(false, |type_| type_.is_empty()) {
+        return None;
+    }
+
+    Some(GresInfo {
+        name: name.to_string(),
+        type_: type_.map(|type_| type_.to_string()),
+        count: count.unwrap_or(1)
+    })
+}
+
+/// Public helper function to parse the generic resources of a node (sinfo %G) or a job (squeue %b)
+/// Placeholders like "(null)" or "N/A" give an empty list, invalid entries are skipped
+pub fn parse_gres(gres: &str) -> Vec<GresInfo> {
+    if is_placeholder(gres) {
+        return Vec::new();
+    }
+
+    split_top_level(gres).into_iter().filter_map(|entry| {
+        let result = parse_gres_entry(entry);
+        if result.is_none() {
+            debug!("invalid gres entry: '{}'", entry);
+        }
+        result
+    }).collect()
+}
+
+/// Public helper function to get the number of GPUs of a node or a job
+pub fn gpu_count(gres: &[GresInfo]) -> u64 {
+    gres.iter().filter(|gres| gres.is_gpu()).fold(0, |total: u64, gres| total.saturating_add(gres.count))
+}
+
+/// Public helper function to describe the GPUs, for example "4 (a100)" or "2 (a100), 2 (v100)"
+/// Returns an empty string if there are no GPUs
+pub fn gpus_to_text(gres: &[GresInfo]) -> String {
+    let gpus: Vec<String> = gres.iter().filter(|gres| gres.is_gpu()).map(|gres| match gres.type_ {
+        Some(ref type_) => format!("{} ({})", gres.count, type_),
+        None => gres.count.to_string()
+    }).collect();
+
+    gpus.join(", ")
+}
+
+#[test]
+fn test_parse_gres_type_and_affinity() {
+    assert_eq!(parse_gres("gpu:a100:4(S:0-1)"), vec![GresInfo{ name: "gpu".to_string(), type_: Some("a100".to_string()), count: 4 }]);
+    assert_eq!(parse_gres("gpu:a100:4(IDX:0-3)"), vec![GresInfo{ name: "gpu".to_string(), type_: Some("a100".to_string()), count: 4 }]);
+    assert_eq!(parse_gres("gpu:a100:4"), parse_gres("gpu:a100:4(S:0-1)"));
+}
+
+#[test]
+fn test_parse_gres_missing_type_or_count() {
+    assert_eq!(parse_gres("gpu:2"), vec![GresInfo{ name: "gpu".to_string(), type_: None, count: 2 }]);
+    assert_eq!(parse_gres("gpu"), vec![GresInfo{ name: "gpu".to_string(), type_: None, count: 1 }]);
+    assert_eq!(parse_gres("gpu:k80"), vec![GresInfo{ name: "gpu".to_string(), type_: Some("k80".to_string()), count: 1 }]);
+}
+
+#[test]
+fn test_parse_gres_multiple() {
+    let gres = parse_gres("gpu:tesla:2(S:0,1),gpu:k80:2(S:1),mps:100");
+    assert_eq!(gres.len(), 3);
+    assert_eq!(gres[0], GresInfo{ name: "gpu".to_string(), type_: Some("tesla".to_string()), count: 2 });
+    assert_eq!(gres[1], GresInfo{ name: "gpu".to_string(), type_: Some("k80".to_string()), count: 2 });
+    assert_eq!(gres[2], GresInfo{ name: "mps".to_string(), type_: None, count: 100 });
+    assert_eq!(gpu_count(&gres), 4);
+}
+
+#[test]
+fn test_parse_gres_squeue() {
+    let gpus = vec![GresInfo{ name: "gpu".to_string(), type_: Some("a100".to_string()), count: 2 }];
+    assert_eq!(parse_gres("gres:gpu:a100:2"), gpus);
+    assert_eq!(parse_gres("gres/gpu:a100=2"), gpus);
+    assert_eq!(parse_gres("gres/gpu=2"), vec![GresInfo{ name: "gpu".to_string(), type_: None, count: 2 }]);
+}
+
+#[test]
+fn test_parse_gres_suffix() {
+    assert_eq!(parse_gres("bandwidth:lustre:4K"), vec![GresInfo{ name: "bandwidth".to_string(), type_: Some("lustre".to_string()), count: 4096 }]);
+    assert_eq!(parse_gres("mps:1M")[0].count, 1024 * 1024);
+}
+
+#[test]
+fn test_parse_gres_invalid() {
+    assert!(parse_gres("(null)").is_empty());
+    assert!(parse_gres("N/A").is_empty());
+    assert!(parse_gres("").is_empty());
+    assert!(parse_gres("gpu:a100:x").is_empty());
+    assert!(parse_gres("gpu:a:b:c").is_empty());
+    assert!(parse_gres(":2").is_empty());
+    // A count that doesn't fit into an u64 is invalid, it must not overflow
+    assert_eq!(parse_count("99999999999G"), None);
+    assert_eq!(parse_count("18446744073709551615"), Some(u64::MAX));
+    assert!(parse_gres("gpu:a100:99999999999G").is_empty());
+    assert_eq!(parse_gres("gpu:99999999999G"), vec![GresInfo{ name: "gpu".to_string(), type_: Some("99999999999G".to_string()), count: 1 }]);
+    assert_eq!(gpu_count(&parse_gres("gpu:18446744073709551615,gpu:2")), u64::MAX);
+    // Invalid entries are skipped, the others are kept
+    assert_eq!(parse_gres("gpu:a100:x,gpu:2"), vec![GresInfo{ name: "gpu".to_string(), type_: None, count: 2 }]);
+}
+
+#[test]
+fn test_gpus_to_text() {
+    assert_eq!(gpus_to_text(&parse_gres("gpu:a100:2,gpu:v100:2,mps:100")), "2 (a100), 2 (v100)");
+    assert_eq!(gpus_to_text(&parse_gres("gpu:4")), "4");
+    assert_eq!(gpus_to_text(&parse_gres("mps:100")), "");
+    assert_eq!(gpu_count(&[]), 0);
+}
+
+#[test]
+fn test_gres_display() {
+    assert_eq!(parse_gres("gpu:a100:4(S:0-1)")[0].to_string(), "gpu:a100:4");
+    assert_eq!(parse_gres("gpu")[0].to_string(), "gpu:1");
+}
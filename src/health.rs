@@ -0,0 +1,136 @@
+//! Health check for load balancers, reverse proxies and systemd
+//! Only looks at the time of the last successful update, so it's cheap and doesn't render anything
+
+// System modules:
+use std::collections::BTreeMap;
+
+// External modules:
+use rustc_serialize::json::{Json, ToJson};
+
+// Internal modules:
+use slurm_status::SlurmStatus;
+
+/// The status is healthy if the last successful update is at most this many update intervals old
+pub const MAX_MISSED_UPDATES: u64 = 3;
+
+/// Health of the SLURM status thread
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum HealthState {
+    /// No update has finished yet
+    Starting,
+    /// The last update succeeded recently
+    Healthy,
+    /// The updates failed or are too old
+    Unhealthy
+}
+
+impl ToJson for HealthState {
+    fn to_json(&self) -> Json {
+        Json::String(match *self {
+            HealthState::Starting => "starting",
+            HealthState::Healthy => "healthy",
+            HealthState::Unhealthy => "unhealthy"
+        }.to_string())
+    }
+}
+
+/// Body of the /health response
+#[derive(Debug, Clone, PartialEq)]
+pub struct HealthReport {
+    pub status: HealthState,
+    /// Seconds since the last successful update, None if there was none yet
+    pub last_update_age_seconds: Option<i64>,
+    /// Number of updates that failed since the last successful one
+    pub consecutive_update_failures: u32,
+    /// Name of the cluster, so several instances can be told apart
+    pub cluster_name: String
+}
+
+impl HealthReport {
+    /// Returns true if the service can handle requests, only then /health returns 200
+    pub fn is_healthy(&self) -> bool {
+        self.status == HealthState::Healthy
+    }
+}
+
+impl ToJson for HealthReport {
+    fn to_json(&self) -> Json {
+        let mut result = BTreeMap::new();
+        result.insert("status".to_string(), self.status.to_json());
+        result.insert("last_update_age_seconds".to_string(), self.last_update_age_seconds.to_json());
+        result.insert("consecutive_update_failures".to_string(), self.consecutive_update_failures.to_json());
+        result.insert("cluster_name".to_string(), self.cluster_name.to_json());
+        Json::Object(result)
+    }
+}
+
+/// Public helper function that returns the body of /health if the status can't be read
+/// It has the same fields as a HealthReport, so monitoring can parse it, the values that are not known are null
+pub fn unknown_health_json() -> Json {
+    let mut result = BTreeMap::new();
+    result.insert("status".to_string(), HealthState::Unhealthy.to_json());
+    result.insert("last_update_age_seconds".to_string(), Json::Null);
+    result.insert("consecutive_update_failures".to_string(), Json::Null);
+    result.insert("cluster_name".to_string(), Json::Null);
+    Json::Object(result)
+}
+
+/// Public helper function to check the health of the status with the given update interval (in sec.) at the given time (seconds since the epoch)
+pub fn health_report(status: &SlurmStatus, interval: u64, now: i64) -> HealthReport {
+    let age = status.last_success.map(|last_success| now - last_success);
+    let state = match age {
+        None if status.consecutive_failures == 0 => HealthState::Starting,
+        Some(age) if age <= (interval * MAX_MISSED_UPDATES) as i64 => HealthState::Healthy,
+        _ => HealthState::Unhealthy
+    };
+
+    HealthReport {
+        status: state,
+        last_update_age_seconds: age,
+        consecutive_update_failures: status.consecutive_failures,
+        cluster_name: status.cluster_name.clone()
+    }
+}
+
+#[test]
+fn test_health_report_starting() {
+    let report = health_report(&SlurmStatus::new(), 60, 1000);
+
+    assert_eq!(report, HealthReport{ status: HealthState::Starting, last_update_age_seconds: None, consecutive_update_failures: 0,
+        cluster_name: String::new() });
+    assert!(!report.is_healthy());
+    assert_eq!(report.to_json().to_string(), "{\"cluster_name\":\"\",\"consecutive_update_failures\":0,\"last_update_age_seconds\":null,\"status\":\"starting\"}");
+}
+
+#[test]
+fn test_health_report_healthy() {
+    let mut status = SlurmStatus::new();
+    status.last_success = Some(1000);
+
+    let report = health_report(&status, 60, 1180);
+    assert_eq!(report, HealthReport{ status: HealthState::Healthy, last_update_age_seconds: Some(180), consecutive_update_failures: 0,
+        cluster_name: String::new() });
+    assert!(report.is_healthy());
+
+    // A single failed update is fine, as long as the last successful one is recent enough
+    status.consecutive_failures = 1;
+    assert!(health_report(&status, 60, 1070).is_healthy());
+}
+
+#[test]
+fn test_health_report_unhealthy() {
+    let mut status = SlurmStatus::new();
+    status.last_success = Some(1000);
+    status.consecutive_failures = 4;
+    status.cluster_name = "cluster1".to_string();
+
+    let report = health_report(&status, 60, 1181);
+    assert_eq!(report, HealthReport{ status: HealthState::Unhealthy, last_update_age_seconds: Some(181), consecutive_update_failures: 4,
+        cluster_name: "cluster1".to_string() });
+    assert!(!report.is_healthy());
+    assert_eq!(report.to_json().to_string(), "{\"cluster_name\":\"cluster1\",\"consecutive_update_failures\":4,\"last_update_age_seconds\":181,\"status\":\"unhealthy\"}");
+
+    // The first update failed
+    let status = SlurmStatus{ consecutive_failures: 1, .. SlurmStatus::new() };
+    assert_eq!(health_report(&status, 60, 1000).status, HealthState::Unhealthy);
+}
@@ -0,0 +1,249 @@
+//! Bounded history of the SLURM status
+//! Only the number of nodes and jobs per state is kept for each update, not full copies of the status
+
+// System modules:
+use std::collections::{BTreeMap, VecDeque};
+
+// External modules:
+use rustc_serialize::json::{Json, ToJson};
+
+// Internal modules:
+use sinfo_util::PartitionNodeInfo;
+use squeue_util::{JobInfo, JobState};
+
+/// Default number of entries kept in the history
+pub const DEFAULT_HISTORY_SIZE: usize = 60;
+
+/// Number of nodes and jobs per state at a given time
+#[derive(Debug, Clone, PartialEq)]
+pub struct HistoryEntry {
+    /// Seconds since the epoch
+    pub timestamp: i64,
+    /// Number of nodes per node state
+    pub node_states: BTreeMap<String, u32>,
+    /// Number of jobs per job state
+    pub job_states: BTreeMap<String, u32>,
+    /// Number of pending jobs per partition, None if the update of the jobs failed (a gap in the backlog chart)
+    /// A job that is pending in several partitions is counted in each of them
+    pub pending_by_partition: Option<BTreeMap<String, u32>>
+}
+
+impl HistoryEntry {
+    /// Count the nodes and jobs per state
+    pub fn new(timestamp: i64, node_info: &[PartitionNodeInfo], job_info: &[JobInfo]) -> HistoryEntry {
+        let mut node_states = BTreeMap::new();
+        let mut job_states = BTreeMap::new();
+        let mut pending_by_partition = BTreeMap::new();
+
+        for node in node_info {
+            *node_states.entry(format!("{:?}", node.node_state)).or_insert(0) += 1;
+        }
+
+        for job in job_info {
+            *job_states.entry(format!("{:?}", job.job_state)).or_insert(0) += 1;
+            if job.job_state == JobState::Pending {
+                for partition in job.partition.split(',').filter(|partition| !partition.is_empty()) {
+                    *pending_by_partition.entry(partition.to_string()).or_insert(0) += 1;
+                }
+            }
+        }
+
+        HistoryEntry {
+            timestamp: timestamp,
+            node_states: node_states,
+            job_states: job_states,
+            pending_by_partition: Some(pending_by_partition)
+        }
+    }
+
+    /// Number of pending jobs
+    pub fn queue_length(&self) -> u32 {
+        self.job_states.get(&format!("{:?}", JobState::Pending)).cloned().unwrap_or(0)
+    }
+}
+
+impl ToJson for HistoryEntry {
+    fn to_json(&self) -> Json {
+        let mut result = BTreeMap::new();
+        result.insert("timestamp".to_string(), self.timestamp.to_json());
+        result.insert("node_states".to_string(), self.node_states.to_json());
+        result.insert("job_states".to_string(), self.job_states.to_json());
+        result.insert("pending_by_partition".to_string(), self.pending_by_partition.to_json());
+        Json::Object(result)
+    }
+}
+
+/// Minimum, maximum and average queue length over a time window
+#[derive(Debug, Clone, PartialEq)]
+pub struct QueueSummary {
+    pub min: u32,
+    pub max: u32,
+    pub avg: f64,
+    /// Number of history entries in the time window
+    pub samples: usize
+}
+
+/// Ring buffer of the last history entries, the oldest entry is dropped when it's full
+#[derive(Debug, Clone, PartialEq)]
+pub struct History {
+    max_size: usize,
+    entries: VecDeque<HistoryEntry>
+}
+
+impl History {
+    /// Create an empty history that keeps at most max_size entries
+    pub fn new(max_size: usize) -> History {
+        History {
+            max_size: max_size,
+            entries: VecDeque::new()
+        }
+    }
+
+    /// Add a new entry and drop the oldest ones if there are too many
+    pub fn push(&mut self, entry: HistoryEntry) {
+        self.entries.push_back(entry);
+
+        while self.entries.len() > self.max_size {
+            self.entries.pop_front();
+        }
+    }
+
+    /// All entries, oldest first
+    pub fn entries(&self) -> &VecDeque<HistoryEntry> {
+        &self.entries
+    }
+
+    /// The entries between from and to (seconds since the epoch, both included), oldest first
+    /// A missing limit means no limit in this direction
+    pub fn range(&self, from: Option<i64>, to: Option<i64>) -> Vec<HistoryEntry> {
+        self.entries.iter()
+            .filter(|entry| from.map_or(true, |from| entry.timestamp >= from) && to.map_or(true, |to| entry.timestamp <= to))
+            .cloned()
+            .collect()
+    }
+
+    /// Summary of the queue length in the last window seconds, counted from the newest entry
+    /// Returns None if the history is empty
+    pub fn queue_summary(&self, window: i64) -> Option<QueueSummary> {
+        let newest = match self.entries.back() {
+            Some(entry) => entry.timestamp,
+            None => return None
+        };
+
+        let lengths: Vec<u32> = self.entries.iter()
+            .filter(|entry| entry.timestamp > newest - window)
+            .map(|entry| entry.queue_length())
+            .collect();
+        let sum = lengths.iter().fold(0, |sum, length| sum + *length as u64);
+
+        Some(QueueSummary {
+            min: lengths.iter().cloned().min().unwrap_or(0),
+            max: lengths.iter().cloned().max().unwrap_or(0),
+            avg: sum as f64 / lengths.len() as f64,
+            samples: lengths.len()
+        })
+    }
+}
+
+impl ToJson for History {
+    fn to_json(&self) -> Json {
+        Json::Array(self.entries.iter().map(|entry| entry.to_json()).collect())
+    }
+}
+
+#[cfg(test)]
+fn test_entry(timestamp: i64, pending: u32) -> HistoryEntry {
+    let mut entry = HistoryEntry::new(timestamp, &[], &[]);
+    entry.job_states.insert("Pending".to_string(), pending);
+    entry
+}
+
+#[test]
+fn test_history_entry_counts() {
+    use sinfo_util::get_partition_node_info_test;
+    use squeue_util::get_job_info_test;
+
+    let entry = HistoryEntry::new(100, &get_partition_node_info_test(), &get_job_info_test());
+
+    assert_eq!(entry.timestamp, 100);
+    assert_eq!(entry.node_states.get("Idle"), Some(&3));
+    assert_eq!(entry.node_states.get("Down"), None);
+    assert_eq!(entry.node_states.values().fold(0, |sum, count| sum + count), 12);
+    assert_eq!(entry.job_states.get("Running"), Some(&1));
+    assert_eq!(entry.job_states.get("Unknown"), Some(&2));
+    assert_eq!(entry.queue_length(), 1);
+}
+
+#[test]
+fn test_history_entry_pending_by_partition() {
+    use squeue_util::get_job_info_test;
+
+    let mut jobs = get_job_info_test();
+    jobs[0].job_state = JobState::Pending;
+    jobs[0].partition = "batch,gpu".to_string();
+    let entry = HistoryEntry::new(100, &[], &jobs);
+    let pending: Vec<(String, u32)> = entry.pending_by_partition.clone().unwrap().into_iter().collect();
+
+    // Counted in both partitions
+    assert_eq!(pending, vec![("batch".to_string(), 1), ("esd".to_string(), 1), ("gpu".to_string(), 1)]);
+    assert_eq!(entry.queue_length(), 2);
+}
+
+#[test]
+fn test_history_eviction() {
+    let mut history = History::new(3);
+
+    for timestamp in 0..5 {
+        history.push(test_entry(timestamp, 0));
+    }
+
+    let timestamps: Vec<i64> = history.entries().iter().map(|entry| entry.timestamp).collect();
+    assert_eq!(timestamps, vec![2, 3, 4]);
+}
+
+#[test]
+fn test_history_size_zero() {
+    let mut history = History::new(0);
+    history.push(test_entry(0, 0));
+
+    assert_eq!(history.entries().len(), 0);
+    assert_eq!(history.queue_summary(3600), None);
+}
+
+#[test]
+fn test_history_queue_summary() {
+    let mut history = History::new(10);
+    history.push(test_entry(0, 100));
+    history.push(test_entry(3600, 2));
+    history.push(test_entry(4800, 6));
+    history.push(test_entry(6000, 4));
+
+    // The first entry is older than one hour, counted from the newest entry
+    assert_eq!(history.queue_summary(3600), Some(QueueSummary{ min: 2, max: 6, avg: 4.0, samples: 3 }));
+    assert_eq!(history.queue_summary(1), Some(QueueSummary{ min: 4, max: 4, avg: 4.0, samples: 1 }));
+}
+
+#[test]
+fn test_history_range() {
+    let mut history = History::new(10);
+    for timestamp in 1..5 {
+        history.push(test_entry(timestamp * 60, 1));
+    }
+
+    let timestamps = |entries: Vec<HistoryEntry>| entries.iter().map(|entry| entry.timestamp).collect::<Vec<i64>>();
+    assert_eq!(timestamps(history.range(None, None)), vec![60, 120, 180, 240]);
+    assert_eq!(timestamps(history.range(Some(120), Some(180))), vec![120, 180]);
+    assert_eq!(timestamps(history.range(Some(121), None)), vec![180, 240]);
+    assert_eq!(timestamps(history.range(None, Some(59))), Vec::<i64>::new());
+}
+
+#[test]
+fn test_history_to_json() {
+    let mut history = History::new(10);
+    history.push(test_entry(60, 3));
+
+    let json = history.to_json();
+    assert_eq!(json.as_array().map(|entries| entries.len()), Some(1));
+    assert_eq!(json[0].find_path(&["job_states", "Pending"]).and_then(|val| val.as_u64()), Some(3));
+    assert_eq!(json[0].find("timestamp").and_then(|val| val.as_i64()), Some(60));
+}
@@ -0,0 +1,282 @@
+//! Persistent history in a SQLite database (--history-db)
+//! The in-memory history is lost when slurm_inspector restarts, for example during a maintenance. With --history-db
+//! the summary of every update (the same as a HistoryEntry) is written to the database by the status thread,
+//! /api/history then reads from it. Snapshots older than --history-retention days are deleted after each write.
+
+// System modules:
+use std::collections::BTreeMap;
+use std::time::Duration;
+
+// External modules:
+use rusqlite::{Connection, Result, NO_PARAMS};
+
+// Internal modules:
+use history::HistoryEntry;
+
+/// Default number of days the snapshots are kept in the database
+pub const DEFAULT_HISTORY_RETENTION: u64 = 30;
+
+/// How long a query waits for the status thread to finish its write
+const BUSY_TIMEOUT: u64 = 5;
+
+/// One row per snapshot, pending_known is 0 if the update of the jobs failed (pending_by_partition is None)
+/// The counts have one row per state or partition, kind is "node_state", "job_state" or "pending"
+const SCHEMA: &'static str = "
+    CREATE TABLE IF NOT EXISTS snapshots (
+        timestamp INTEGER PRIMARY KEY,
+        pending_known INTEGER NOT NULL
+    );
+    CREATE TABLE IF NOT EXISTS counts (
+        timestamp INTEGER NOT NULL,
+        kind TEXT NOT NULL,
+        name TEXT NOT NULL,
+        count INTEGER NOT NULL,
+        PRIMARY KEY (timestamp, kind, name)
+    );";
+
+/// Connection to the history database
+pub struct HistoryDb {
+    connection: Connection,
+    /// Timestamp of the last snapshot written by this connection, the same snapshot is not written twice
+    last_stored: Option<i64>
+}
+
+impl HistoryDb {
+    /// Opens the database and creates the tables if they don't exist yet
+    pub fn open(path: &str) -> Result<HistoryDb> {
+        let connection = Connection::open(path)?;
+        connection.busy_timeout(Duration::from_secs(BUSY_TIMEOUT))?;
+        connection.execute_batch(SCHEMA)?;
+
+        Ok(HistoryDb {
+            connection: connection,
+            last_stored: None
+        })
+    }
+
+    /// Writes the snapshot, an existing snapshot with the same timestamp is replaced
+    pub fn insert(&mut self, entry: &HistoryEntry) -> Result<()> {
+        let transaction = self.connection.transaction()?;
+        transaction.execute("DELETE FROM counts WHERE timestamp = ?1", &[&entry.timestamp])?;
+        transaction.execute("INSERT OR REPLACE INTO snapshots (timestamp, pending_known) VALUES (?1, ?2)",
+            &[&entry.timestamp, &(entry.pending_by_partition.is_some() as i64)])?;
+
+        {
+            let mut insert_count = transaction.prepare("INSERT INTO counts (timestamp, kind, name, count) VALUES (?1, ?2, ?3, ?4)")?;
+            let empty = BTreeMap::new();
+            let kinds = [("node_state", &entry.node_states), ("job_state", &entry.job_states),
+                ("pending", entry.pending_by_partition.as_ref().unwrap_or(&empty))];
+            for &(kind, counts) in &kinds {
+                for (name, count) in counts.iter() {
+                    insert_count.execute(params![entry.timestamp, kind, name, *count as i64])?;
+                }
+            }
+        }
+
+        transaction.commit()
+    }
+
+    /// Deletes the snapshots older than the given time (seconds since the epoch), returns the number of deleted snapshots
+    pub fn prune(&mut self, oldest: i64) -> Result<usize> {
+        let transaction = self.connection.transaction()?;
+        transaction.execute("DELETE FROM counts WHERE timestamp < ?1", &[&oldest])?;
+        let deleted = transaction.execute("DELETE FROM snapshots WHERE timestamp < ?1", &[&oldest])?;
+        transaction.commit()?;
+
+        Ok(deleted)
+    }
+
+    /// The snapshots between from and to (seconds since the epoch, both included), oldest first
+    /// A missing limit means no limit in this direction
+    pub fn range(&self, from: Option<i64>, to: Option<i64>) -> Result<Vec<HistoryEntry>> {
+        let from = from.unwrap_or(i64::min_value());
+        let to = to.unwrap_or(i64::max_value());
+        let mut entries = BTreeMap::new();
+
+        let mut snapshots = self.connection.prepare("SELECT timestamp, pending_known FROM snapshots WHERE timestamp BETWEEN ?1 AND ?2")?;
+        let rows = snapshots.query_map(&[&from, &to], |row| Ok((row.get::<_, i64>(0)?, row.get::<_, i64>(1)? != 0)))?;
+        for row in rows {
+            let (timestamp, pending_known) = row?;
+            entries.insert(timestamp, HistoryEntry {
+                timestamp: timestamp,
+                node_states: BTreeMap::new(),
+                job_states: BTreeMap::new(),
+                pending_by_partition: if pending_known { Some(BTreeMap::new()) } else { None }
+            });
+        }
+
+        let mut counts = self.connection.prepare("SELECT timestamp, kind, name, count FROM counts WHERE timestamp BETWEEN ?1 AND ?2")?;
+        let rows = counts.query_map(&[&from, &to], |row| Ok((row.get::<_, i64>(0)?, row.get::<_, String>(1)?, row.get::<_, String>(2)?,
+            row.get::<_, i64>(3)?)))?;
+        for row in rows {
+            let (timestamp, kind, name, count) = row?;
+            // Counts without snapshot can only come from a write of another program, they are ignored
+            if let Some(entry) = entries.get_mut(&timestamp) {
+                let counts = match &kind[..] {
+                    "node_state" => Some(&mut entry.node_states),
+                    "job_state" => Some(&mut entry.job_states),
+                    "pending" => entry.pending_by_partition.as_mut(),
+                    _ => None
+                };
+                if let Some(counts) = counts {
+                    counts.insert(name, count as u32);
+                }
+            }
+        }
+
+        Ok(entries.into_iter().map(|(_, entry)| entry).collect())
+    }
+
+    /// Number of snapshots in the database
+    pub fn len(&self) -> Result<usize> {
+        self.connection.query_row("SELECT COUNT(*) FROM snapshots", NO_PARAMS, |row| row.get::<_, i64>(0)).map(|count| count as usize)
+    }
+
+    /// Writes the newest snapshot after an update and deletes the ones older than oldest (seconds since the epoch)
+    /// A snapshot that has already been written is skipped, errors are logged and the update goes on
+    pub fn store(&mut self, entry: &HistoryEntry, oldest: i64) {
+        if self.last_stored == Some(entry.timestamp) {
+            return;
+        }
+
+        match self.insert(entry).and_then(|_| self.prune(oldest)) {
+            Ok(deleted) => {
+                self.last_stored = Some(entry.timestamp);
+                if deleted > 0 {
+                    debug!("deleted {} old snapshots from the history database", deleted);
+                }
+            },
+            Err(err) => warn!("Could not write the history to the database: {}", err)
+        }
+    }
+}
+
+#[cfg(test)]
+fn temp_db_path(name: &str) -> String {
+    use std::env::temp_dir;
+    use time::precise_time_ns;
+
+    temp_dir().join(format!("slurm_inspector_{}_{}.sqlite", name, precise_time_ns())).to_string_lossy().to_string()
+}
+
+#[cfg(test)]
+fn test_snapshot(timestamp: i64, pending: Option<u32>) -> HistoryEntry {
+    let mut node_states = BTreeMap::new();
+    node_states.insert("Idle".to_string(), 3);
+    node_states.insert("Allocated".to_string(), 2);
+    let mut job_states = BTreeMap::new();
+    job_states.insert("Running".to_string(), 4);
+    job_states.insert("Pending".to_string(), pending.unwrap_or(0));
+
+    HistoryEntry {
+        timestamp: timestamp,
+        node_states: node_states,
+        job_states: job_states,
+        pending_by_partition: pending.map(|pending| {
+            let mut partitions = BTreeMap::new();
+            partitions.insert("esd".to_string(), pending);
+            partitions.insert("gpu".to_string(), 1);
+            partitions
+        })
+    }
+}
+
+#[test]
+fn test_history_db_schema() {
+    use std::fs::remove_file;
+
+    let path = temp_db_path("schema");
+    let mut db = HistoryDb::open(&path).unwrap();
+    assert_eq!(db.len().unwrap(), 0);
+    db.insert(&test_snapshot(60, Some(2))).unwrap();
+
+    // Opening an existing database keeps its snapshots
+    let db = HistoryDb::open(&path).unwrap();
+    assert_eq!(db.len().unwrap(), 1);
+    let tables: Vec<String> = db.connection.prepare("SELECT name FROM sqlite_master WHERE type = 'table' ORDER BY name").unwrap()
+        .query_map(NO_PARAMS, |row| row.get(0)).unwrap().map(|name| name.unwrap()).collect();
+    assert_eq!(tables, vec!["counts", "snapshots"]);
+
+    remove_file(&path).unwrap();
+}
+
+#[test]
+fn test_history_db_insert() {
+    use std::fs::remove_file;
+
+    let path = temp_db_path("insert");
+    let mut db = HistoryDb::open(&path).unwrap();
+    db.insert(&test_snapshot(60, Some(2))).unwrap();
+    // The update of the jobs failed
+    db.insert(&test_snapshot(120, None)).unwrap();
+    assert_eq!(db.range(None, None).unwrap(), vec![test_snapshot(60, Some(2)), test_snapshot(120, None)]);
+
+    // The same timestamp replaces the snapshot, no counts are left over
+    let mut replaced = test_snapshot(60, Some(5));
+    replaced.node_states.remove("Allocated");
+    db.insert(&replaced).unwrap();
+    assert_eq!(db.len().unwrap(), 2);
+    assert_eq!(db.range(None, Some(60)).unwrap(), vec![replaced]);
+
+    remove_file(&path).unwrap();
+}
+
+#[test]
+fn test_history_db_range() {
+    use std::fs::remove_file;
+
+    let path = temp_db_path("range");
+    let mut db = HistoryDb::open(&path).unwrap();
+    // Inserted out of order, returned oldest first
+    for &timestamp in &[180, 60, 240, 120] {
+        db.insert(&test_snapshot(timestamp, Some(timestamp as u32 / 60))).unwrap();
+    }
+
+    let timestamps = |entries: Vec<HistoryEntry>| entries.iter().map(|entry| entry.timestamp).collect::<Vec<i64>>();
+    assert_eq!(timestamps(db.range(None, None).unwrap()), vec![60, 120, 180, 240]);
+    assert_eq!(timestamps(db.range(Some(120), Some(180)).unwrap()), vec![120, 180]);
+    assert_eq!(timestamps(db.range(Some(121), None).unwrap()), vec![180, 240]);
+    assert_eq!(timestamps(db.range(None, Some(119)).unwrap()), vec![60]);
+    assert_eq!(timestamps(db.range(Some(300), None).unwrap()), Vec::<i64>::new());
+    assert_eq!(db.range(Some(180), Some(180)).unwrap(), vec![test_snapshot(180, Some(3))]);
+
+    remove_file(&path).unwrap();
+}
+
+#[test]
+fn test_history_db_prune() {
+    use std::fs::remove_file;
+
+    let path = temp_db_path("prune");
+    let mut db = HistoryDb::open(&path).unwrap();
+    for timestamp in 1..6 {
+        db.insert(&test_snapshot(timestamp * 60, Some(1))).unwrap();
+    }
+
+    assert_eq!(db.prune(180).unwrap(), 2);
+    assert_eq!(db.prune(180).unwrap(), 0);
+    assert_eq!(db.range(None, None).unwrap().iter().map(|entry| entry.timestamp).collect::<Vec<i64>>(), vec![180, 240, 300]);
+    let counts: i64 = db.connection.query_row("SELECT COUNT(*) FROM counts WHERE timestamp < 180", NO_PARAMS, |row| row.get(0)).unwrap();
+    assert_eq!(counts, 0);
+
+    remove_file(&path).unwrap();
+}
+
+#[test]
+fn test_history_db_store() {
+    use std::fs::remove_file;
+
+    let path = temp_db_path("store");
+    let mut db = HistoryDb::open(&path).unwrap();
+    db.store(&test_snapshot(60, Some(1)), 0);
+    db.store(&test_snapshot(120, Some(2)), 0);
+    // Already written, the counts in the database are kept
+    db.store(&test_snapshot(120, Some(7)), 0);
+    assert_eq!(db.range(None, None).unwrap(), vec![test_snapshot(60, Some(1)), test_snapshot(120, Some(2))]);
+
+    // Older snapshots are deleted after the write
+    db.store(&test_snapshot(180, Some(3)), 120);
+    assert_eq!(db.range(None, None).unwrap(), vec![test_snapshot(120, Some(2)), test_snapshot(180, Some(3))]);
+
+    remove_file(&path).unwrap();
+}
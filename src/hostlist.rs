@@ -0,0 +1,288 @@
+//! Expands SLURM hostlists like "node[01-03,05]" into single node names and compresses them again
+//! squeue and sacct print the nodes of a job in this compressed form, the web page uses it for long node lists
+
+// System modules:
+use std::collections::BTreeSet;
+
+/// Ranges with more nodes than this are not expanded, to protect against bogus input
+pub const MAX_RANGE_SIZE: u64 = 100000;
+
+// Private helper function to split the hostlist at commas that are not inside brackets
+fn split_top_level(hostlist: &str) -> Vec<&str> {
+    let mut result = Vec::new();
+    let mut depth = 0;
+    let mut start = 0;
+
+    for (index, c) in hostlist.char_indices() {
+        match c {
+            '[' => depth += 1,
+            ']' => depth -= 1,
+            ',' if depth == 0 => {
+                result.push(&hostlist[start..index]);
+                start = index + 1;
+            },
+            _ => {}
+        }
+    }
+    result.push(&hostlist[start..]);
+
+    result
+}
+
+// Private helper function to expand the content of a bracket, for example "01-03,05"
+// Numbers keep the zero padding of the start of the range, returns None if the content is not valid
+fn expand_ranges(ranges: &str) -> Option<Vec<String>> {
+    let mut result = Vec::new();
+
+    for range in ranges.split(',') {
+        let mut bounds = range.splitn(2, '-');
+        let start = bounds.next().unwrap_or("");
+        let end = bounds.next().unwrap_or(start);
+        let width = start.len();
+
+        if start.is_empty() || !start.chars().all(|c| c.is_digit(10)) || !end.chars().all(|c| c.is_digit(10)) {
+            return None;
+        }
+
+        let first = start.parse::<u64>().ok()?;
+        let last = end.parse::<u64>().ok()?;
+
+        if last < first || last - first >= MAX_RANGE_SIZE {
+            return None;
+        }
+
+        for number in first..(last + 1) {
+            result.push(format!("{:01$}", number, width));
+        }
+    }
+
+    Some(result)
+}
+
+// Private helper function to expand a single entry, which can contain several brackets, for example "rack[1-2]-node[1-2]"
+fn expand_entry(entry: &str) -> Vec<String> {
+    let open = match entry.find('[') {
+        Some(open) => open,
+        None => return vec![entry.to_string()]
+    };
+    let close = match entry[open..].find(']') {
+        Some(close) => open + close,
+        None => return vec![entry.to_string()]
+    };
+
+    match expand_ranges(&entry[(open + 1)..close]) {
+        Some(numbers) => {
+            let rest = expand_entry(&entry[(close + 1)..]);
+            let mut result = Vec::new();
+
+            for number in &numbers {
+                for suffix in &rest {
+                    result.push(format!("{}{}{}", &entry[..open], number, suffix));
+                }
+            }
+
+            result
+        },
+        None => vec![entry.to_string()]
+    }
+}
+
+/// Public helper function to expand a hostlist into single node names, in the given order
+/// Invalid entries are kept as they are, empty entries are skipped
+pub fn expand_hostlist(hostlist: &str) -> Vec<String> {
+    split_top_level(hostlist.trim()).into_iter()
+        .filter(|entry| !entry.is_empty())
+        .flat_map(|entry| expand_entry(entry))
+        .collect()
+}
+
+#[test]
+fn test_expand_hostlist_plain() {
+    assert_eq!(expand_hostlist(""), Vec::<String>::new());
+    assert_eq!(expand_hostlist("node01"), vec!["node01"]);
+    assert_eq!(expand_hostlist("node01,node02"), vec!["node01", "node02"]);
+}
+
+#[test]
+fn test_expand_hostlist_ranges() {
+    assert_eq!(expand_hostlist("node[01-03,05]"), vec!["node01", "node02", "node03", "node05"]);
+    assert_eq!(expand_hostlist("node[8-10]"), vec!["node8", "node9", "node10"]);
+    assert_eq!(expand_hostlist("gpu[1-2],node[001-002],login"), vec!["gpu1", "gpu2", "node001", "node002", "login"]);
+}
+
+#[test]
+fn test_expand_hostlist_multiple_brackets() {
+    assert_eq!(expand_hostlist("rack[1-2]-node[1-2]"), vec!["rack1-node1", "rack1-node2", "rack2-node1", "rack2-node2"]);
+}
+
+#[test]
+fn test_expand_hostlist_invalid() {
+    assert_eq!(expand_hostlist("node[01-"), vec!["node[01-"]);
+    assert_eq!(expand_hostlist("node[a-b]"), vec!["node[a-b]"]);
+    assert_eq!(expand_hostlist("node[5-1]"), vec!["node[5-1]"]);
+    assert_eq!(expand_hostlist("node[1-99999999]"), vec!["node[1-99999999]"]);
+}
+
+// Private helper function to split a node name into the prefix and the number at the end, for example "node01" -> ("node", "01")
+// Returns None if the name doesn't end with a number
+fn split_node_number(node: &str) -> Option<(&str, &str)> {
+    let prefix = node.trim_end_matches(|c: char| c.is_digit(10));
+
+    // Too many digits for u64
+    if prefix.len() == node.len() || node.len() - prefix.len() > 18 {
+        None
+    } else {
+        Some((prefix, &node[prefix.len()..]))
+    }
+}
+
+// A group of nodes in a compressed hostlist: the same prefix and the same zero padding
+// The width is the number of digits of zero padded numbers, 0 for numbers without padding and None for nodes without number
+#[derive(Debug, Clone, PartialEq)]
+struct HostlistGroup {
+    prefix: String,
+    width: Option<usize>,
+    numbers: BTreeSet<u64>
+}
+
+// Private helper function to group the nodes, the groups keep the order of their first node
+// Numbers without leading zero join the zero padded group with the same number of digits, for example "node10" and "node09"
+fn group_nodes(nodes: &[String]) -> Vec<HostlistGroup> {
+    let is_padded = |digits: &str| digits.len() > 1 && digits.starts_with('0');
+    let padded: BTreeSet<(&str, usize)> = nodes.iter()
+        .filter_map(|node| split_node_number(node))
+        .filter(|&(_, digits)| is_padded(digits))
+        .map(|(prefix, digits)| (prefix, digits.len()))
+        .collect();
+    let mut result: Vec<HostlistGroup> = Vec::new();
+
+    for node in nodes {
+        let (prefix, width, number) = match split_node_number(node) {
+            Some((prefix, digits)) => {
+                let width = if padded.contains(&(prefix, digits.len())) { digits.len() } else { 0 };
+                (prefix, Some(width), digits.parse::<u64>().ok())
+            },
+            None => (&node[..], None, None)
+        };
+
+        match result.iter().position(|group| group.prefix == prefix && group.width == width) {
+            Some(index) => { result[index].numbers.extend(number); },
+            None => result.push(HostlistGroup{ prefix: prefix.to_string(), width: width, numbers: number.into_iter().collect() })
+        }
+    }
+
+    result
+}
+
+// Private helper function to split the sorted numbers into ranges of consecutive numbers
+fn number_ranges(numbers: &BTreeSet<u64>) -> Vec<(u64, u64)> {
+    let mut result: Vec<(u64, u64)> = Vec::new();
+
+    for &number in numbers {
+        match result.last_mut() {
+            Some(range) if range.1 + 1 == number => range.1 = number,
+            _ => result.push((number, number))
+        }
+    }
+
+    result
+}
+
+// Private helper function to format a group with the given ranges, for example "node[01-03,05]" or "gpu7"
+fn format_group(group: &HostlistGroup, ranges: &[(u64, u64)]) -> String {
+    let width = group.width.unwrap_or(0);
+    let formatted: Vec<String> = ranges.iter().map(|&(first, last)| {
+        if first == last {
+            format!("{:01$}", first, width)
+        } else {
+            format!("{:02$}-{:02$}", first, last, width)
+        }
+    }).collect();
+
+    if group.width.is_none() {
+        group.prefix.clone()
+    } else if ranges.len() == 1 && ranges[0].0 == ranges[0].1 {
+        format!("{}{}", group.prefix, formatted[0])
+    } else {
+        format!("{}[{}]", group.prefix, formatted.join(","))
+    }
+}
+
+/// Public helper function to compress the node names into hostlist entries, for example "node[01-03]" and "gpu7"
+/// Only the first max_ranges ranges (or names without number) are included, the number of the left out ranges is returned too
+/// Nodes with the same prefix and number of digits are combined, so the zero padding is kept, duplicates are removed
+pub fn compress_hostlist_entries(nodes: &[String], max_ranges: usize) -> (Vec<String>, usize) {
+    let mut entries = Vec::new();
+    let mut remaining = max_ranges;
+    let mut left_out = 0;
+
+    for group in group_nodes(nodes) {
+        let ranges = if group.width.is_none() { vec![(0, 0)] } else { number_ranges(&group.numbers) };
+        let shown = ranges.len().min(remaining);
+
+        if shown > 0 {
+            entries.push(format_group(&group, &ranges[..shown]));
+        }
+        remaining -= shown;
+        left_out += ranges.len() - shown;
+    }
+
+    (entries, left_out)
+}
+
+/// Public helper function to compress the node names into a hostlist, the inverse of expand_hostlist
+/// For example ["node01", "node02", "node03", "gpu7"] becomes "node[01-03],gpu7"
+pub fn compress_hostlist(nodes: &[String]) -> String {
+    compress_hostlist_entries(nodes, usize::max_value()).0.join(",")
+}
+
+#[test]
+fn test_compress_hostlist() {
+    let nodes = |names: &[&str]| -> Vec<String> { names.iter().map(|name| name.to_string()).collect() };
+
+    assert_eq!(compress_hostlist(&[]), "");
+    assert_eq!(compress_hostlist(&nodes(&["node01"])), "node01");
+    assert_eq!(compress_hostlist(&nodes(&["node01", "node02", "node03", "gpu7"])), "node[01-03],gpu7");
+    assert_eq!(compress_hostlist(&nodes(&["node03", "node01", "node02", "node01"])), "node[01-03]");
+    assert_eq!(compress_hostlist(&nodes(&["login", "node1", "login"])), "login,node1");
+}
+
+#[test]
+fn test_compress_hostlist_padding() {
+    let nodes = |names: &[&str]| -> Vec<String> { names.iter().map(|name| name.to_string()).collect() };
+
+    assert_eq!(compress_hostlist(&nodes(&["node001", "node002", "node010"])), "node[001-002,010]");
+    assert_eq!(compress_hostlist(&nodes(&["node08", "node09", "node10"])), "node[08-10]");
+    // Zero padded and not padded numbers are different groups, otherwise the names would change
+    assert_eq!(compress_hostlist(&nodes(&["node9", "node10", "node09"])), "node9,node[09-10]");
+    assert_eq!(compress_hostlist(&nodes(&["node9", "node10", "node11"])), "node[9-11]");
+    assert_eq!(compress_hostlist(&nodes(&["node0", "node1", "node00"])), "node[0-1],node00");
+}
+
+#[test]
+fn test_compress_hostlist_non_contiguous() {
+    let nodes = expand_hostlist("node[01-03,05,07-08]");
+
+    assert_eq!(compress_hostlist(&nodes), "node[01-03,05,07-08]");
+    assert_eq!(compress_hostlist(&expand_hostlist("rack[1-2]-node[1-2]")), "rack1-node[1-2],rack2-node[1-2]");
+}
+
+#[test]
+fn test_compress_hostlist_round_trip() {
+    for hostlist in &["node[01-03],gpu7", "node[001-256]", "gpu[1-4,6],node[08-12],login01,login", "node[1-3,10-12]", "a[0-9],b[00-99]"] {
+        let nodes = expand_hostlist(hostlist);
+        assert_eq!(compress_hostlist(&nodes), *hostlist);
+        assert_eq!(expand_hostlist(&compress_hostlist(&nodes)), nodes);
+    }
+}
+
+#[test]
+fn test_compress_hostlist_entries_max_ranges() {
+    let nodes = expand_hostlist("node[001-256,300,302],gpu[1-2],login");
+
+    assert_eq!(compress_hostlist_entries(&nodes, 1), (vec!["node[001-256]".to_string()], 4));
+    assert_eq!(compress_hostlist_entries(&nodes, 2), (vec!["node[001-256,300]".to_string()], 3));
+    assert_eq!(compress_hostlist_entries(&nodes, 4), (vec!["node[001-256,300,302]".to_string(), "gpu[1-2]".to_string()], 1));
+    assert_eq!(compress_hostlist_entries(&nodes, 10).1, 0);
+    assert_eq!(compress_hostlist_entries(&nodes, 0), (Vec::new(), 5));
+}
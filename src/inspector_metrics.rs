@@ -0,0 +1,347 @@
+//! Metrics about slurm_inspector itself: how long sinfo and squeue take and how many lines could be parsed
+//! Helps to tell whether SLURM is slow or the parser drops lines
+
+// System modules:
+use std::collections::BTreeMap;
+
+// External modules:
+use rustc_serialize::json::{Json, ToJson};
+use time::get_time;
+
+/// Number of characters of a skipped line that are logged
+pub const SKIPPED_LINE_SAMPLE: usize = 120;
+
+/// A skipped line of a command is logged at most once in this number of seconds, the others are counted
+pub const SKIPPED_WARNING_INTERVAL: i64 = 300;
+
+/// Result of parsing the output of a SLURM command
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParseResult<T> {
+    /// The parsed items, one per valid line
+    pub items: Vec<T>,
+    /// Number of lines that could not be parsed
+    pub skipped: usize,
+    /// Number of lines, without empty lines and the "CLUSTER:" header lines of "-M"
+    pub total: usize
+}
+
+impl<T> ParseResult<T> {
+    /// Create an empty ParseResult
+    pub fn new() -> ParseResult<T> {
+        ParseResult {
+            items: Vec::new(),
+            skipped: 0,
+            total: 0
+        }
+    }
+
+    /// Move the items of the other result into this one and add up the counts
+    pub fn append(&mut self, other: &mut ParseResult<T>) {
+        self.items.append(&mut other.items);
+        self.skipped += other.skipped;
+        self.total += other.total;
+    }
+}
+
+/// Public helper function to check if a line of the output counts for the parse statistics
+/// Empty lines and the header lines of "-M" (for example "CLUSTER: cluster2") don't count
+pub fn is_data_line(line: &str) -> bool {
+    let line = line.trim();
+    !line.is_empty() && !line.starts_with("CLUSTER:")
+}
+
+/// Public helper function that returns the first SKIPPED_LINE_SAMPLE characters of a line for the log, "..." marks a cut line
+pub fn line_sample(line: &str) -> String {
+    let line = line.trim();
+    match line.char_indices().nth(SKIPPED_LINE_SAMPLE) {
+        Some((index, _)) => format!("{}...", &line[..index]),
+        None => line.to_string()
+    }
+}
+
+/// Time of the last warning about a skipped line of a command and the number of skipped lines since then
+/// A new SLURM version that changes the output would otherwise log every line of every update
+#[derive(Debug, Clone, PartialEq)]
+pub struct SkippedWarnings {
+    /// Time of the last warning, None before the first one
+    last_warning: Option<i64>,
+    /// Number of skipped lines since the last warning that were not logged
+    suppressed: u32
+}
+
+impl SkippedWarnings {
+    /// Create the warnings of a command that hasn't skipped a line yet
+    pub fn new() -> SkippedWarnings {
+        SkippedWarnings {
+            last_warning: None,
+            suppressed: 0
+        }
+    }
+
+    /// Record a skipped line at the given time, returns the number of lines that were not logged before if this one should be logged,
+    /// None if the last warning is less than SKIPPED_WARNING_INTERVAL seconds ago
+    pub fn skipped(&mut self, time: i64) -> Option<u32> {
+        if let Some(last_warning) = self.last_warning {
+            if time - last_warning < SKIPPED_WARNING_INTERVAL {
+                self.suppressed += 1;
+                return None;
+            }
+        }
+        let result = self.suppressed;
+        self.last_warning = Some(time);
+        self.suppressed = 0;
+        Some(result)
+    }
+
+    /// Log a line of the command that could not be parsed, with the expected and the actual number of fields and the start of the line
+    pub fn warn_skipped_line(&mut self, command: &str, expected: &str, actual: usize, line: &str) {
+        match self.skipped(get_time().sec) {
+            Some(0) => warn!("Skipped a line of {}: expected {} fields, got {}: '{}'", command, expected, actual, line_sample(line)),
+            Some(suppressed) => warn!("Skipped a line of {}: expected {} fields, got {}: '{}' ({} more lines were skipped since the last warning)",
+                command, expected, actual, line_sample(line), suppressed),
+            None => debug!("Skipped a line of {}: expected {} fields, got {}", command, expected, actual)
+        }
+    }
+}
+
+/// Metrics of one SLURM command, the last call and the totals since the start
+#[derive(Debug, Clone, PartialEq)]
+pub struct CommandMetrics {
+    /// Wall time of the last call in milliseconds
+    pub last_duration_ms: f64,
+    /// Number of parsed lines of the last successful call
+    pub last_parsed: usize,
+    /// Number of skipped lines of the last successful call
+    pub last_skipped: usize,
+    /// Number of calls
+    pub calls: u64,
+    /// Number of calls that failed
+    pub failures: u64,
+    /// Wall time of all calls in milliseconds
+    pub total_duration_ms: f64,
+    /// Number of parsed lines of all calls
+    pub total_parsed: u64,
+    /// Number of skipped lines of all calls
+    pub total_skipped: u64,
+    /// Rate limit of the warnings about skipped lines, the parser gets it with the output
+    pub skipped_warnings: SkippedWarnings
+}
+
+impl CommandMetrics {
+    /// Create empty command metrics
+    pub fn new() -> CommandMetrics {
+        CommandMetrics {
+            last_duration_ms: 0.0,
+            last_parsed: 0,
+            last_skipped: 0,
+            calls: 0,
+            failures: 0,
+            total_duration_ms: 0.0,
+            total_parsed: 0,
+            total_skipped: 0,
+            skipped_warnings: SkippedWarnings::new()
+        }
+    }
+
+    /// Record a successful call
+    pub fn record_success<T>(&mut self, duration_ms: f64, result: &ParseResult<T>) {
+        self.record_call(duration_ms);
+        self.last_parsed = result.items.len();
+        self.last_skipped = result.skipped;
+        self.total_parsed += result.items.len() as u64;
+        self.total_skipped += result.skipped as u64;
+    }
+
+    /// Record a call that failed, the parse counts of the last successful call are kept
+    pub fn record_failure(&mut self, duration_ms: f64) {
+        self.record_call(duration_ms);
+        self.failures += 1;
+    }
+
+    // Private helper function to count the call and its duration
+    fn record_call(&mut self, duration_ms: f64) {
+        self.last_duration_ms = duration_ms;
+        self.calls += 1;
+        self.total_duration_ms += duration_ms;
+    }
+
+    /// Average wall time of the calls in milliseconds, 0 if there were no calls
+    pub fn average_duration_ms(&self) -> f64 {
+        if self.calls == 0 { 0.0 } else { self.total_duration_ms / self.calls as f64 }
+    }
+}
+
+impl ToJson for CommandMetrics {
+    fn to_json(&self) -> Json {
+        let mut result = BTreeMap::new();
+        result.insert("last_duration_ms".to_string(), self.last_duration_ms.to_json());
+        result.insert("last_parsed".to_string(), self.last_parsed.to_json());
+        result.insert("last_skipped".to_string(), self.last_skipped.to_json());
+        result.insert("calls".to_string(), self.calls.to_json());
+        result.insert("failures".to_string(), self.failures.to_json());
+        result.insert("total_duration_ms".to_string(), self.total_duration_ms.to_json());
+        result.insert("total_parsed".to_string(), self.total_parsed.to_json());
+        result.insert("total_skipped".to_string(), self.total_skipped.to_json());
+        Json::Object(result)
+    }
+}
+
+/// Metrics of all SLURM commands that are parsed
+#[derive(Debug, Clone, PartialEq)]
+pub struct InspectorMetrics {
+    pub sinfo: CommandMetrics,
+    pub squeue: CommandMetrics
+}
+
+impl InspectorMetrics {
+    /// Create empty metrics
+    pub fn new() -> InspectorMetrics {
+        InspectorMetrics {
+            sinfo: CommandMetrics::new(),
+            squeue: CommandMetrics::new()
+        }
+    }
+
+    /// Returns the name and the metrics of each command
+    pub fn commands(&self) -> Vec<(&'static str, &CommandMetrics)> {
+        vec![("sinfo", &self.sinfo), ("squeue", &self.squeue)]
+    }
+
+    /// Returns true if no command has been called yet, for example in test mode
+    pub fn is_empty(&self) -> bool {
+        self.commands().iter().all(|&(_, metrics)| metrics.calls == 0)
+    }
+
+    /// Number of lines of all commands that could not be parsed in their last successful call
+    pub fn last_skipped(&self) -> usize {
+        self.commands().iter().map(|&(_, metrics)| metrics.last_skipped).sum()
+    }
+}
+
+impl ToJson for InspectorMetrics {
+    fn to_json(&self) -> Json {
+        let mut result = BTreeMap::new();
+        for (name, metrics) in self.commands() {
+            result.insert(name.to_string(), metrics.to_json());
+        }
+        Json::Object(result)
+    }
+}
+
+/// Public helper function to escape a label value of the Prometheus text format
+pub fn escape_label(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('"', "\\\"").replace('\n', "\\n")
+}
+
+/// Public helper function to write the metrics in the Prometheus text format, used by /metrics
+/// Every metric has the label "cluster", and slurm_inspector_info has the SLURM version as label
+pub fn metrics_to_prometheus(metrics: &InspectorMetrics, cluster_name: &str, slurm_version: &str) -> String {
+    let mut result = String::new();
+    let cluster = escape_label(cluster_name);
+
+    result.push_str("# HELP slurm_inspector_info Name of the cluster and version of SLURM\n");
+    result.push_str("# TYPE slurm_inspector_info gauge\n");
+    result.push_str(&format!("slurm_inspector_info{{cluster=\"{}\",slurm_version=\"{}\"}} 1\n", cluster, escape_label(slurm_version)));
+
+    let values: Vec<(&str, &str, &str, fn(&CommandMetrics) -> String)> = vec![
+        ("slurm_inspector_command_duration_seconds", "gauge", "Wall time of the last call of the SLURM command",
+            |m: &CommandMetrics| format!("{}", m.last_duration_ms / 1000.0)),
+        ("slurm_inspector_command_duration_seconds_total", "counter", "Wall time of all calls of the SLURM command",
+            |m: &CommandMetrics| format!("{}", m.total_duration_ms / 1000.0)),
+        ("slurm_inspector_command_calls_total", "counter", "Number of calls of the SLURM command",
+            |m: &CommandMetrics| m.calls.to_string()),
+        ("slurm_inspector_command_failures_total", "counter", "Number of calls of the SLURM command that failed",
+            |m: &CommandMetrics| m.failures.to_string()),
+        ("slurm_inspector_parsed_lines", "gauge", "Number of parsed lines of the last successful call",
+            |m: &CommandMetrics| m.last_parsed.to_string()),
+        ("slurm_inspector_skipped_lines", "gauge", "Number of skipped lines of the last successful call",
+            |m: &CommandMetrics| m.last_skipped.to_string()),
+        ("slurm_inspector_parsed_lines_total", "counter", "Number of parsed lines of all calls",
+            |m: &CommandMetrics| m.total_parsed.to_string()),
+        ("slurm_inspector_skipped_lines_total", "counter", "Number of skipped lines of all calls",
+            |m: &CommandMetrics| m.total_skipped.to_string())
+    ];
+
+    for &(name, kind, help, value) in &values {
+        result.push_str(&format!("# HELP {} {}\n", name, help));
+        result.push_str(&format!("# TYPE {} {}\n", name, kind));
+        for (command, command_metrics) in metrics.commands() {
+            result.push_str(&format!("{}{{cluster=\"{}\",command=\"{}\"}} {}\n", name, cluster, command, value(command_metrics)));
+        }
+    }
+
+    result
+}
+
+#[test]
+fn test_is_data_line() {
+    assert!(is_data_line("esd up node01 node01 none 0.22 idle 2 2 2"));
+    assert!(is_data_line("1 2 3"));
+    assert!(!is_data_line("   "));
+    assert!(!is_data_line("CLUSTER: cluster2"));
+}
+
+#[test]
+fn test_line_sample() {
+    assert_eq!(line_sample("  1 2 3  "), "1 2 3");
+    let line = "ä".repeat(200);
+    assert_eq!(line_sample(&line), format!("{}...", "ä".repeat(SKIPPED_LINE_SAMPLE)));
+    assert_eq!(line_sample(&line[..2 * SKIPPED_LINE_SAMPLE]), "ä".repeat(SKIPPED_LINE_SAMPLE));
+}
+
+#[test]
+fn test_skipped_warnings() {
+    let mut metrics = InspectorMetrics::new();
+    assert_eq!(metrics.sinfo.skipped_warnings.skipped(1000), Some(0));
+    assert_eq!(metrics.sinfo.skipped_warnings.skipped(1001), None);
+    assert_eq!(metrics.sinfo.skipped_warnings.skipped(1000 + SKIPPED_WARNING_INTERVAL - 1), None);
+    // Each command has its own limit
+    assert_eq!(metrics.squeue.skipped_warnings.skipped(1001), Some(0));
+    // The next warning tells how many lines were not logged
+    assert_eq!(metrics.sinfo.skipped_warnings.skipped(1000 + SKIPPED_WARNING_INTERVAL), Some(2));
+    assert_eq!(metrics.sinfo.skipped_warnings.skipped(1000 + 2 * SKIPPED_WARNING_INTERVAL), Some(0));
+}
+
+#[test]
+fn test_command_metrics() {
+    let mut metrics = CommandMetrics::new();
+    let result = ParseResult{ items: vec![1, 2, 3], skipped: 1, total: 4 };
+
+    metrics.record_success(10.0, &result);
+    metrics.record_failure(30.0);
+    metrics.record_success(20.0, &ParseResult{ items: vec![1], skipped: 0, total: 1 });
+
+    assert_eq!(metrics.last_duration_ms, 20.0);
+    assert_eq!((metrics.last_parsed, metrics.last_skipped), (1, 0));
+    assert_eq!((metrics.calls, metrics.failures), (3, 1));
+    assert_eq!((metrics.total_parsed, metrics.total_skipped), (4, 1));
+    assert_eq!(metrics.average_duration_ms(), 20.0);
+    assert_eq!(CommandMetrics::new().average_duration_ms(), 0.0);
+}
+
+#[test]
+fn test_parse_result_append() {
+    let mut result = ParseResult{ items: vec![1, 2], skipped: 1, total: 3 };
+    result.append(&mut ParseResult{ items: vec![3], skipped: 2, total: 3 });
+
+    assert_eq!(result, ParseResult{ items: vec![1, 2, 3], skipped: 3, total: 6 });
+}
+
+#[test]
+fn test_metrics_to_prometheus() {
+    let mut metrics = InspectorMetrics::new();
+    assert!(metrics.is_empty());
+    metrics.squeue.record_success(1500.0, &ParseResult{ items: vec![1, 2], skipped: 1, total: 3 });
+    assert!(!metrics.is_empty());
+    assert_eq!(metrics.last_skipped(), 1);
+
+    let text = metrics_to_prometheus(&metrics, "cluster1", "20.11.8");
+    assert!(text.contains("slurm_inspector_info{cluster=\"cluster1\",slurm_version=\"20.11.8\"} 1\n"));
+    assert!(text.contains("# TYPE slurm_inspector_command_duration_seconds gauge\n"));
+    assert!(text.contains("slurm_inspector_command_duration_seconds{cluster=\"cluster1\",command=\"squeue\"} 1.5\n"));
+    assert!(text.contains("slurm_inspector_command_calls_total{cluster=\"cluster1\",command=\"sinfo\"} 0\n"));
+    assert!(text.contains("slurm_inspector_skipped_lines_total{cluster=\"cluster1\",command=\"squeue\"} 1\n"));
+    assert_eq!(text.lines().count(), 3 + 8 * 4);
+
+    assert_eq!(escape_label("a\"b\\c\nd"), "a\\\"b\\\\c\\nd");
+}
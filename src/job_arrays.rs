@@ -0,0 +1,247 @@
+//! Groups the tasks of job arrays, so a job array with many tasks is shown as one summary row
+//! squeue prints one line per running array task (and one line for all pending tasks, for example "1234_[5-99]")
+
+// System modules:
+use std::collections::BTreeMap;
+
+// External modules:
+use rustc_serialize::json::{Json, ToJson};
+
+// Internal modules:
+use squeue_util::JobInfo;
+#[cfg(test)]
+use squeue_util::JobState;
+
+/// Summary of the tasks of a job array
+#[derive(Debug, Clone, PartialEq)]
+pub struct JobArraySummary {
+    /// Name of the cluster, empty if only the local cluster is monitored
+    pub cluster: String,
+    /// ID of the job array (squeue %F)
+    pub array_id: u32,
+    /// Job name and user of the first task
+    pub job_name: String,
+    pub user_name: String,
+    /// Number of tasks in each job state, job steps are not counted
+    pub state_counts: BTreeMap<String, u32>,
+    /// Shortest and longest run time of the tasks, None if no task has a run time
+    pub min_run_time: Option<String>,
+    pub max_run_time: Option<String>,
+    /// The tasks (and their job steps) in the original order
+    pub tasks: Vec<JobInfo>
+}
+
+impl JobArraySummary {
+    /// Number of tasks without the job steps
+    pub fn num_tasks(&self) -> u32 {
+        self.state_counts.values().sum()
+    }
+}
+
+impl ToJson for JobArraySummary {
+    fn to_json(&self) -> Json {
+        let mut result = BTreeMap::new();
+        result.insert("cluster".to_string(), self.cluster.to_json());
+        result.insert("array_id".to_string(), self.array_id.to_json());
+        result.insert("job_name".to_string(), self.job_name.to_json());
+        result.insert("user_name".to_string(), self.user_name.to_json());
+        result.insert("state_counts".to_string(), self.state_counts.to_json());
+        result.insert("min_run_time".to_string(), self.min_run_time.to_json());
+        result.insert("max_run_time".to_string(), self.max_run_time.to_json());
+        result.insert("tasks".to_string(), self.tasks.to_json());
+        Json::Object(result)
+    }
+}
+
+/// A row of the job table: either a single job or a job array with its tasks
+#[derive(Debug, Clone, PartialEq)]
+pub enum JobGroup {
+    Single(JobInfo),
+    Array(JobArraySummary)
+}
+
+impl ToJson for JobGroup {
+    fn to_json(&self) -> Json {
+        let mut result = BTreeMap::new();
+        match *self {
+            JobGroup::Single(ref job) => result.insert("job".to_string(), job.to_json()),
+            JobGroup::Array(ref summary) => result.insert("array".to_string(), summary.to_json())
+        };
+        Json::Object(result)
+    }
+}
+
+// Private helper function to check if the job is a job step
+fn is_step(job: &JobInfo) -> bool {
+    job.job_id.as_ref().map_or(false, |job_id| job_id.is_step())
+}
+
+/// Public helper function to convert the run time printed by squeue into seconds
+/// The format is "[days-][hours:]minutes:seconds", for example "2:46" or "1-02:03:04"
+pub fn run_time_to_seconds(run_time: &str) -> Option<u64> {
+    let (days, time) = match run_time.find('-') {
+        Some(index) => (run_time[..index].parse::<u64>().ok()?, &run_time[index + 1..]),
+        None => (0, run_time)
+    };
+
+    let parts = time.split(':').map(|part| part.parse::<u64>().ok()).collect::<Option<Vec<u64>>>()?;
+    let seconds = match parts.len() {
+        2 => parts[0] * 60 + parts[1],
+        3 => parts[0] * 3600 + parts[1] * 60 + parts[2],
+        _ => return None
+    };
+
+    Some(days * 86400 + seconds)
+}
+
+// Private helper function to build the summary of the tasks of one job array
+fn summarize_array(cluster: &str, array_id: u32, tasks: Vec<JobInfo>) -> JobArraySummary {
+    let mut state_counts = BTreeMap::new();
+    let mut min_run_time: Option<(u64, String)> = None;
+    let mut max_run_time: Option<(u64, String)> = None;
+
+    for task in tasks.iter().filter(|task| !is_step(task)) {
+        *state_counts.entry(task.job_state.to_string()).or_insert(0) += 1;
+
+        if let Some(ref run_time) = task.run_time {
+            if let Some(seconds) = run_time_to_seconds(run_time) {
+                if min_run_time.as_ref().map_or(true, |&(min, _)| seconds < min) {
+                    min_run_time = Some((seconds, run_time.clone()));
+                }
+                if max_run_time.as_ref().map_or(true, |&(max, _)| seconds > max) {
+                    max_run_time = Some((seconds, run_time.clone()));
+                }
+            }
+        }
+    }
+
+    JobArraySummary {
+        cluster: cluster.to_string(),
+        array_id: array_id,
+        job_name: tasks[0].job_name.clone(),
+        user_name: tasks[0].user_name.clone(),
+        state_counts: state_counts,
+        min_run_time: min_run_time.map(|(_, run_time)| run_time),
+        max_run_time: max_run_time.map(|(_, run_time)| run_time),
+        tasks: tasks
+    }
+}
+
+/// Public helper function to group the jobs that share the same job array ID (and cluster)
+/// A group is only built for at least two tasks and takes the position of its first task, otherwise the order is kept
+/// Job steps are kept with the tasks of their array but don't count as tasks
+pub fn group_job_arrays(job_info: Vec<JobInfo>) -> Vec<JobGroup> {
+    let mut tasks: BTreeMap<(String, u32), Vec<JobInfo>> = BTreeMap::new();
+    let mut order: Vec<Result<(String, u32), JobInfo>> = Vec::new();
+
+    for job in job_info {
+        match job.job_array_id {
+            Some(array_id) => {
+                let key = (job.cluster.clone(), array_id);
+                if !tasks.contains_key(&key) {
+                    order.push(Ok(key.clone()));
+                }
+                tasks.entry(key).or_insert_with(Vec::new).push(job);
+            },
+            None => order.push(Err(job))
+        }
+    }
+
+    let mut result = Vec::new();
+
+    for entry in order {
+        match entry {
+            Ok(key) => {
+                let array_tasks = tasks.remove(&key).unwrap_or_else(Vec::new);
+                if array_tasks.iter().filter(|task| !is_step(task)).count() >= 2 {
+                    result.push(JobGroup::Array(summarize_array(&key.0, key.1, array_tasks)));
+                } else {
+                    result.extend(array_tasks.into_iter().map(JobGroup::Single));
+                }
+            },
+            Err(job) => result.push(JobGroup::Single(job))
+        }
+    }
+
+    result
+}
+
+#[cfg(test)]
+fn test_job(job_id: &str, array_id: Option<u32>, state: JobState, run_time: &str) -> JobInfo {
+    JobInfo {
+        job_array_id: array_id,
+        job_name: "sim".to_string(),
+        run_time: Some(run_time.to_string()),
+        .. ::squeue_util::test_job(job_id, state)
+    }
+}
+
+#[test]
+fn test_run_time_to_seconds() {
+    assert_eq!(run_time_to_seconds("0:00"), Some(0));
+    assert_eq!(run_time_to_seconds("2:46"), Some(166));
+    assert_eq!(run_time_to_seconds("1:02:03"), Some(3723));
+    assert_eq!(run_time_to_seconds("1-02:03:04"), Some(93784));
+    assert_eq!(run_time_to_seconds("INVALID"), None);
+    assert_eq!(run_time_to_seconds("1:2:3:4"), None);
+}
+
+#[test]
+fn test_group_job_arrays_mixed() {
+    let jobs = vec![
+        test_job("10", None, JobState::Running, "1:00"),
+        test_job("20_1", Some(20), JobState::Running, "1:02:03"),
+        test_job("30", None, JobState::Pending, "0:00"),
+        test_job("20_2", Some(20), JobState::Running, "9:59"),
+        test_job("20_2.batch", Some(20), JobState::Running, "1-00:00:00"),
+        test_job("20_[3-99]", Some(20), JobState::Pending, "0:00")
+    ];
+
+    let groups = group_job_arrays(jobs.clone());
+    assert_eq!(groups.len(), 3);
+    assert_eq!(groups[0], JobGroup::Single(jobs[0].clone()));
+    assert_eq!(groups[2], JobGroup::Single(jobs[2].clone()));
+
+    match groups[1] {
+        JobGroup::Array(ref summary) => {
+            assert_eq!(summary.array_id, 20);
+            assert_eq!(summary.job_name, "sim");
+            assert_eq!(summary.num_tasks(), 3);
+            assert_eq!(summary.state_counts.get("RUNNING"), Some(&2));
+            assert_eq!(summary.state_counts.get("PENDING"), Some(&1));
+            // The run time of the job step doesn't count
+            assert_eq!(summary.min_run_time, Some("0:00".to_string()));
+            assert_eq!(summary.max_run_time, Some("1:02:03".to_string()));
+            assert_eq!(summary.tasks.len(), 4);
+        },
+        _ => panic!("job array not grouped")
+    }
+}
+
+#[test]
+fn test_group_job_arrays_no_arrays() {
+    let jobs = vec![test_job("10", None, JobState::Running, "1:00"), test_job("11", None, JobState::Pending, "0:00"),
+        test_job("12_1", Some(12), JobState::Running, "1:00")];
+    let groups = group_job_arrays(jobs.clone());
+
+    // A single task is not grouped
+    assert_eq!(groups, jobs.into_iter().map(JobGroup::Single).collect::<Vec<JobGroup>>());
+    assert!(group_job_arrays(Vec::new()).is_empty());
+}
+
+#[test]
+fn test_group_job_arrays_clusters() {
+    let mut other_cluster = test_job("20_2", Some(20), JobState::Running, "1:00");
+    other_cluster.cluster = "cluster2".to_string();
+    let groups = group_job_arrays(vec![test_job("20_1", Some(20), JobState::Running, "1:00"), other_cluster, test_job("20_3", Some(20), JobState::Running, "1:00")]);
+
+    assert_eq!(groups.len(), 2);
+    match groups[0] {
+        JobGroup::Array(ref summary) => assert_eq!((summary.cluster.as_ref(), summary.num_tasks()), ("", 2)),
+        _ => panic!("job array not grouped")
+    }
+    match groups[1] {
+        JobGroup::Single(ref job) => assert_eq!(job.cluster, "cluster2"),
+        _ => panic!("task of the other cluster grouped")
+    }
+}
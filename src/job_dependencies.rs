@@ -0,0 +1,333 @@
+//! Dependencies of jobs (squeue %d), for example "afterok:12345,afterany:12346"
+//! A pending job with the state reason "Dependency" waits for the jobs it depends on. If one of them ended in a way that
+//! can never satisfy the dependency (afterok on a failed job), the job and every job that waits for it are stuck
+
+// System modules:
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+
+// External modules:
+use rustc_serialize::json::{Json, ToJson};
+
+// Internal modules:
+use squeue_util::{JobInfo, JobId, JobState, str_to_job_id};
+use placeholder::is_placeholder;
+
+/// Kind of a dependency, see "sbatch --dependency"
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum DependencyKind {
+    /// The other job has started
+    After,
+    /// The other job has ended, in any way
+    AfterAny,
+    /// The other job has completed successfully
+    AfterOk,
+    /// The other job has failed
+    AfterNotOk,
+    /// The task with the same index of the other job array has completed successfully
+    AfterCorr,
+    /// No other job with the same name and user is running
+    Singleton
+}
+
+impl DependencyKind {
+    /// The name used by SLURM, for example "afterok"
+    pub fn name(&self) -> &'static str {
+        match *self {
+            DependencyKind::After => "after",
+            DependencyKind::AfterAny => "afterany",
+            DependencyKind::AfterOk => "afterok",
+            DependencyKind::AfterNotOk => "afternotok",
+            DependencyKind::AfterCorr => "aftercorr",
+            DependencyKind::Singleton => "singleton"
+        }
+    }
+}
+
+impl ToJson for DependencyKind {
+    fn to_json(&self) -> Json {
+        Json::String(self.name().to_string())
+    }
+}
+
+/// One dependency of a job
+#[derive(Debug, Clone, PartialEq)]
+pub struct Dependency {
+    pub kind: DependencyKind,
+    /// The job that is waited for, None for singleton
+    pub job_id: Option<JobId>
+}
+
+impl fmt::Display for Dependency {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.job_id {
+            Some(ref job_id) => write!(f, "{}:{}", self.kind.name(), job_id),
+            None => write!(f, "{}", self.kind.name())
+        }
+    }
+}
+
+impl ToJson for Dependency {
+    fn to_json(&self) -> Json {
+        let mut result = BTreeMap::new();
+        result.insert("kind".to_string(), self.kind.to_json());
+        result.insert("job_id".to_string(), self.job_id.to_json());
+        Json::Object(result)
+    }
+}
+
+// Private helper function to convert the name of a dependency kind, unknown kinds are None
+fn str_to_dependency_kind(kind: &str) -> Option<DependencyKind> {
+    match &*kind.to_lowercase() {
+        "after" => Some(DependencyKind::After),
+        "afterany" => Some(DependencyKind::AfterAny),
+        "afterok" => Some(DependencyKind::AfterOk),
+        "afternotok" => Some(DependencyKind::AfterNotOk),
+        "aftercorr" => Some(DependencyKind::AfterCorr),
+        "singleton" => Some(DependencyKind::Singleton),
+        _ => None
+    }
+}
+
+/// Public helper function to parse the dependencies printed by squeue (%d), for example "afterok:12345,afterany:12346"
+/// Clauses are separated by "," (all must be satisfied) or "?" (one must be satisfied), a clause can list several jobs
+/// ("afterok:1:2"). The state that newer versions of SLURM append ("(unfulfilled)") and the delay of "after" ("+10")
+/// are left out, unknown kinds are skipped. "(null)" and other placeholders are no dependencies
+pub fn parse_dependencies(dependencies: &str) -> Vec<Dependency> {
+    let mut result = Vec::new();
+    if is_placeholder(dependencies) {
+        return result;
+    }
+
+    for clause in dependencies.split(|c| c == ',' || c == '?').filter(|clause| !clause.is_empty()) {
+        let mut parts = clause.split(':');
+        let kind = match parts.next().and_then(str_to_dependency_kind) {
+            Some(kind) => kind,
+            None => {
+                debug!("unknown dependency: {}", clause);
+                continue
+            }
+        };
+
+        if kind == DependencyKind::Singleton {
+            result.push(Dependency { kind: kind, job_id: None });
+            continue
+        }
+
+        for job_id in parts {
+            let job_id = job_id.split(|c| c == '(' || c == '+').next().unwrap_or("");
+            match str_to_job_id(job_id) {
+                Some(job_id) => result.push(Dependency { kind: kind, job_id: Some(job_id) }),
+                None => debug!("invalid job id in dependency: {}", clause)
+            }
+        }
+    }
+
+    result
+}
+
+#[test]
+fn test_parse_dependencies_single() {
+    let dependency = |kind: DependencyKind, job_id: u32| Dependency { kind: kind, job_id: Some(JobId::new(job_id)) };
+
+    assert_eq!(parse_dependencies("afterok:12345"), vec![dependency(DependencyKind::AfterOk, 12345)]);
+    assert_eq!(parse_dependencies("afternotok:12345"), vec![dependency(DependencyKind::AfterNotOk, 12345)]);
+    assert_eq!(parse_dependencies("afterany:12345"), vec![dependency(DependencyKind::AfterAny, 12345)]);
+    assert_eq!(parse_dependencies("aftercorr:12345"), vec![dependency(DependencyKind::AfterCorr, 12345)]);
+    assert_eq!(parse_dependencies("after:12345"), vec![dependency(DependencyKind::After, 12345)]);
+    assert_eq!(parse_dependencies("AfterOK:12345"), vec![dependency(DependencyKind::AfterOk, 12345)]);
+    assert_eq!(parse_dependencies("singleton"), vec![Dependency { kind: DependencyKind::Singleton, job_id: None }]);
+}
+
+#[test]
+fn test_parse_dependencies_multiple() {
+    let dependency = |kind: DependencyKind, job_id: u32| Dependency { kind: kind, job_id: Some(JobId::new(job_id)) };
+
+    assert_eq!(parse_dependencies("afterok:12345,afterany:12346"),
+        vec![dependency(DependencyKind::AfterOk, 12345), dependency(DependencyKind::AfterAny, 12346)]);
+    assert_eq!(parse_dependencies("afterok:1:2?afternotok:3"),
+        vec![dependency(DependencyKind::AfterOk, 1), dependency(DependencyKind::AfterOk, 2), dependency(DependencyKind::AfterNotOk, 3)]);
+    assert_eq!(parse_dependencies("singleton,afterok:7").len(), 2);
+}
+
+#[test]
+fn test_parse_dependencies_state_and_delay() {
+    // Newer versions append the state of the dependency, "after" can have a delay in minutes
+    assert_eq!(parse_dependencies("afterok:12345(unfulfilled),afterany:12346_*(failed)"), vec![
+        Dependency { kind: DependencyKind::AfterOk, job_id: Some(JobId::new(12345)) },
+        Dependency { kind: DependencyKind::AfterAny, job_id: Some(JobId { id: 12346, array_index: Some("*".to_string()), step: None }) }
+    ]);
+    assert_eq!(parse_dependencies("after:12345+10"), vec![Dependency { kind: DependencyKind::After, job_id: Some(JobId::new(12345)) }]);
+}
+
+#[test]
+fn test_parse_dependencies_none_and_invalid() {
+    assert!(parse_dependencies("(null)").is_empty());
+    assert!(parse_dependencies("N/A").is_empty());
+    assert!(parse_dependencies("").is_empty());
+    assert!(parse_dependencies("expand:12345").is_empty());
+    assert!(parse_dependencies("afterok:").is_empty());
+    assert!(parse_dependencies("afterok:abc").is_empty());
+    assert_eq!(parse_dependencies("afterok:abc,afterok:1,,").len(), 1);
+}
+
+#[test]
+fn test_dependency_display() {
+    let dependencies = parse_dependencies("afterok:12345_7,singleton");
+    assert_eq!(dependencies.iter().map(|dependency| dependency.to_string()).collect::<Vec<String>>(), vec!["afterok:12345_7", "singleton"]);
+    assert_eq!(dependencies[0].to_json().to_string(), "{\"job_id\":\"12345_7\",\"kind\":\"afterok\"}");
+}
+
+/// Public helper function that returns true if a dependency of the given kind can never be satisfied by a job in the given state,
+/// for example afterok on a failed job
+pub fn never_satisfied(kind: DependencyKind, state: JobState) -> bool {
+    match kind {
+        DependencyKind::AfterOk | DependencyKind::AfterCorr => match state {
+            JobState::BootFail | JobState::Cancelled | JobState::Deadline | JobState::Failed | JobState::NodeFail |
+            JobState::OutOfMemory | JobState::Timeout => true,
+            _ => false
+        },
+        DependencyKind::AfterNotOk => state == JobState::Completed,
+        DependencyKind::After | DependencyKind::AfterAny | DependencyKind::Singleton => false
+    }
+}
+
+/// The jobs by cluster and job id and the pending jobs whose chain of dependencies is stuck
+pub struct DependencyGraph<'a> {
+    jobs: BTreeMap<(String, JobId), &'a JobInfo>,
+    stuck: BTreeSet<(String, JobId)>
+}
+
+impl<'a> DependencyGraph<'a> {
+    /// Walks the dependencies of all pending jobs: a job is stuck if one of its dependencies can never be satisfied
+    /// or if it waits for a pending job that is stuck. Jobs that are not in the list (they ended long ago) are not followed
+    pub fn new(job_info: &'a [JobInfo]) -> DependencyGraph<'a> {
+        let jobs: BTreeMap<(String, JobId), &JobInfo> = job_info.iter()
+            .filter_map(|job| job.job_id.as_ref().map(|job_id| ((job.cluster.clone(), job_id.clone()), job))).collect();
+        let mut graph = DependencyGraph { jobs: jobs, stuck: BTreeSet::new() };
+        let mut done: BTreeSet<(String, JobId)> = BTreeSet::new();
+
+        let keys: Vec<(String, JobId)> = graph.jobs.keys().cloned().collect();
+        for key in keys {
+            graph.visit(&key, &mut done, &mut BTreeSet::new());
+        }
+
+        graph
+    }
+
+    // Private helper function that decides if the job is stuck, depth first. Jobs on the current path (a cycle) count as not stuck
+    fn visit(&mut self, key: &(String, JobId), done: &mut BTreeSet<(String, JobId)>, path: &mut BTreeSet<(String, JobId)>) -> bool {
+        if done.contains(key) {
+            return self.stuck.contains(key);
+        }
+        let job = match self.jobs.get(key) {
+            Some(job) if job.job_state == JobState::Pending && !path.contains(key) => *job,
+            _ => return false
+        };
+
+        path.insert(key.clone());
+        let mut stuck = false;
+        for dependency in &job.dependencies {
+            let target = match dependency.job_id {
+                Some(ref job_id) => (key.0.clone(), job_id.clone()),
+                None => continue
+            };
+            let target_state = match self.jobs.get(&target) {
+                Some(target_job) => target_job.job_state,
+                None => continue
+            };
+            if never_satisfied(dependency.kind, target_state) || self.visit(&target, done, path) {
+                stuck = true;
+                break;
+            }
+        }
+        path.remove(key);
+
+        done.insert(key.clone());
+        if stuck {
+            self.stuck.insert(key.clone());
+        }
+        stuck
+    }
+
+    /// True if the job is pending and its chain of dependencies is stuck
+    pub fn is_stuck(&self, cluster: &str, job_id: &JobId) -> bool {
+        self.stuck.contains(&(cluster.to_string(), job_id.clone()))
+    }
+
+    /// True if the dependency of a job of the given cluster can never be satisfied: the job it waits for has failed (for afterok)
+    /// or is stuck itself
+    pub fn is_dependency_stuck(&self, cluster: &str, dependency: &Dependency) -> bool {
+        let job_id = match dependency.job_id {
+            Some(ref job_id) => job_id,
+            None => return false
+        };
+
+        match self.jobs.get(&(cluster.to_string(), job_id.clone())) {
+            Some(job) => never_satisfied(dependency.kind, job.job_state) || self.is_stuck(cluster, job_id),
+            None => false
+        }
+    }
+}
+
+#[cfg(test)]
+fn dependent_job(job_id: &str, state: JobState, dependencies: &str) -> JobInfo {
+    JobInfo { dependencies: parse_dependencies(dependencies), .. ::squeue_util::test_job(job_id, state) }
+}
+
+#[test]
+fn test_never_satisfied() {
+    assert!(never_satisfied(DependencyKind::AfterOk, JobState::Failed));
+    assert!(never_satisfied(DependencyKind::AfterOk, JobState::Cancelled));
+    assert!(never_satisfied(DependencyKind::AfterCorr, JobState::Timeout));
+    assert!(!never_satisfied(DependencyKind::AfterOk, JobState::Running));
+    assert!(!never_satisfied(DependencyKind::AfterOk, JobState::Completed));
+    assert!(never_satisfied(DependencyKind::AfterNotOk, JobState::Completed));
+    assert!(!never_satisfied(DependencyKind::AfterNotOk, JobState::Failed));
+    assert!(!never_satisfied(DependencyKind::AfterAny, JobState::Failed));
+    assert!(!never_satisfied(DependencyKind::After, JobState::Failed));
+}
+
+#[test]
+fn test_dependency_graph_chain() {
+    // 1 failed <- 2 <- 3 <- 4 (pending chain), 5 waits for any end of 1, 6 waits for the running job 7
+    let jobs = vec![
+        dependent_job("1", JobState::Failed, "(null)"),
+        dependent_job("2", JobState::Pending, "afterok:1"),
+        dependent_job("3", JobState::Pending, "afterok:2"),
+        dependent_job("4", JobState::Pending, "afterany:3"),
+        dependent_job("5", JobState::Pending, "afterany:1"),
+        dependent_job("6", JobState::Pending, "afterok:7,afterok:99"),
+        dependent_job("7", JobState::Running, "(null)")
+    ];
+    let graph = DependencyGraph::new(&jobs);
+    let stuck: Vec<u32> = jobs.iter().filter(|job| graph.is_stuck("", job.job_id.as_ref().unwrap())).map(|job| job.job_id.as_ref().unwrap().id)
+        .collect();
+    assert_eq!(stuck, vec![2, 3, 4]);
+
+    assert!(graph.is_dependency_stuck("", &jobs[1].dependencies[0]));
+    // The target is stuck, not failed
+    assert!(graph.is_dependency_stuck("", &jobs[3].dependencies[0]));
+    assert!(!graph.is_dependency_stuck("", &jobs[4].dependencies[0]));
+    // Running or unknown targets
+    assert!(!graph.is_dependency_stuck("", &jobs[5].dependencies[0]));
+    assert!(!graph.is_dependency_stuck("", &jobs[5].dependencies[1]));
+    // The same job id on another cluster
+    assert!(!graph.is_dependency_stuck("cluster2", &jobs[1].dependencies[0]));
+}
+
+#[test]
+fn test_dependency_graph_cycle() {
+    // A cycle never ends the walk, but isn't reported as stuck either
+    let jobs = vec![
+        dependent_job("1", JobState::Pending, "afterok:2"),
+        dependent_job("2", JobState::Pending, "afterok:1"),
+        dependent_job("3", JobState::Pending, "afterok:1,afternotok:4"),
+        dependent_job("4", JobState::Completed, "(null)")
+    ];
+    let graph = DependencyGraph::new(&jobs);
+    assert!(!graph.is_stuck("", &JobId::new(1)));
+    assert!(!graph.is_stuck("", &JobId::new(2)));
+    assert!(graph.is_stuck("", &JobId::new(3)));
+    assert!(!graph.is_stuck("", &JobId::new(4)));
+}
@@ -0,0 +1,202 @@
+//! Order of the job table on the page: --default-sort or ?sort= of the request, which overrides the default
+//! By default the running jobs come first, the longest running one at the top, then the pending jobs with the highest priority
+//! first, then the other jobs. Job steps stay below their job, the JSON API and the text report keep the order of squeue
+
+// System modules:
+use std::cmp::Ordering;
+use std::collections::BTreeMap;
+
+// Internal modules:
+use squeue_util::{JobInfo, JobState};
+use job_arrays::run_time_to_seconds;
+
+/// Significant digits of the priority in the job table, the exact value is shown as tooltip
+pub const PRIORITY_DIGITS: usize = 6;
+
+/// Order of the job table
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum JobSort {
+    /// Running jobs by run time, then pending jobs by priority, then the rest (the default)
+    State,
+    /// Highest priority first, jobs without priority last
+    Priority,
+    /// Longest run time first
+    RunTime,
+    /// Ascending job id
+    JobId,
+    /// User name, then job id
+    User,
+    /// The order of the squeue output
+    Squeue
+}
+
+impl JobSort {
+    /// Name in --default-sort and ?sort=
+    pub fn as_str(&self) -> &'static str {
+        match *self {
+            JobSort::State => "state",
+            JobSort::Priority => "priority",
+            JobSort::RunTime => "run_time",
+            JobSort::JobId => "job_id",
+            JobSort::User => "user",
+            JobSort::Squeue => "squeue"
+        }
+    }
+}
+
+impl Default for JobSort {
+    fn default() -> JobSort {
+        JobSort::State
+    }
+}
+
+/// Public helper function to parse --default-sort and ?sort=: state, priority, run_time, job_id, user or squeue, case insensitive
+pub fn parse_job_sort(text: &str) -> Option<JobSort> {
+    match &text.trim().to_lowercase()[..] {
+        "state" => Some(JobSort::State),
+        "priority" => Some(JobSort::Priority),
+        "run_time" => Some(JobSort::RunTime),
+        "job_id" => Some(JobSort::JobId),
+        "user" => Some(JobSort::User),
+        "squeue" => Some(JobSort::Squeue),
+        _ => None
+    }
+}
+
+// Private helper function that compares two optional values, the larger one first and None last
+fn descending<T: PartialOrd>(a: Option<T>, b: Option<T>) -> Ordering {
+    match (a, b) {
+        (Some(a), Some(b)) => b.partial_cmp(&a).unwrap_or(Ordering::Equal),
+        (Some(_), None) => Ordering::Less,
+        (None, Some(_)) => Ordering::Greater,
+        (None, None) => Ordering::Equal
+    }
+}
+
+// Private helper function for the default order: running before pending before the rest
+fn state_rank(job: &JobInfo) -> u8 {
+    match job.job_state {
+        JobState::Running => 0,
+        JobState::Pending => 1,
+        _ => 2
+    }
+}
+
+// Private helper function that compares two jobs, equal jobs keep the order of squeue
+fn compare_jobs(a: &JobInfo, b: &JobInfo, sort: JobSort) -> Ordering {
+    let run_time = |job: &JobInfo| job.run_time.as_ref().and_then(|run_time| run_time_to_seconds(run_time));
+    match sort {
+        JobSort::State => state_rank(a).cmp(&state_rank(b)).then_with(|| match a.job_state {
+            JobState::Running => descending(run_time(a), run_time(b)),
+            JobState::Pending => descending(a.priority, b.priority),
+            _ => Ordering::Equal
+        }),
+        JobSort::Priority => descending(a.priority, b.priority),
+        JobSort::RunTime => descending(run_time(a), run_time(b)),
+        JobSort::JobId => a.job_id.as_ref().map(|job_id| job_id.parent()).cmp(&b.job_id.as_ref().map(|job_id| job_id.parent())),
+        JobSort::User => a.user_name.cmp(&b.user_name)
+            .then_with(|| a.job_id.as_ref().map(|job_id| job_id.parent()).cmp(&b.job_id.as_ref().map(|job_id| job_id.parent()))),
+        JobSort::Squeue => Ordering::Equal
+    }
+}
+
+/// Public helper function that sorts the jobs of the job table, the sort is stable
+/// A job step is sorted like its job, so it stays below it (see squeue_util::group_job_steps)
+pub fn sort_jobs(jobs: &mut Vec<JobInfo>, sort: JobSort) {
+    if sort == JobSort::Squeue {
+        return;
+    }
+
+    let parents: BTreeMap<(String, String), usize> = jobs.iter().enumerate()
+        .filter_map(|(index, job)| job.job_id.as_ref().filter(|job_id| !job_id.is_step()).map(|job_id| ((job.cluster.clone(), job_id.to_string()), index)))
+        .collect();
+    // The index of the job that decides the position: the job itself or the job of a step
+    let keys: Vec<usize> = jobs.iter().enumerate().map(|(index, job)| match job.job_id {
+        Some(ref job_id) if job_id.is_step() => parents.get(&(job.cluster.clone(), job_id.parent().to_string())).cloned().unwrap_or(index),
+        _ => index
+    }).collect();
+
+    let mut order: Vec<usize> = (0..jobs.len()).collect();
+    order.sort_by(|&a, &b| compare_jobs(&jobs[keys[a]], &jobs[keys[b]], sort));
+
+    let mut slots: Vec<Option<JobInfo>> = jobs.drain(..).map(Some).collect();
+    jobs.extend(order.into_iter().filter_map(|index| slots[index].take()));
+}
+
+/// Public helper function that formats a priority with PRIORITY_DIGITS significant digits, for example 0.99998474074527 as "0.999985"
+/// and 0.5 as "0.5". Whole numbers (the priority of SLURM without normalization) are shown completely
+pub fn format_priority(priority: f64) -> String {
+    if !priority.is_finite() || priority.fract() == 0.0 {
+        return format!("{}", priority);
+    }
+
+    let magnitude = priority.abs().log10().floor() as i32;
+    let decimals = (PRIORITY_DIGITS as i32 - 1 - magnitude).max(0) as usize;
+    let text = format!("{:.*}", decimals, priority);
+    // Trailing zeros don't add information: 0.5000 is shown as 0.5
+    if text.contains('.') {
+        text.trim_end_matches('0').trim_end_matches('.').to_string()
+    } else {
+        text
+    }
+}
+
+#[cfg(test)]
+fn test_job(job_id: &str, job_state: JobState, run_time: &str, priority: Option<f64>, user_name: &str) -> JobInfo {
+    JobInfo {
+        run_time: Some(run_time.to_string()),
+        priority: priority,
+        user_name: user_name.to_string(),
+        .. ::squeue_util::test_job(job_id, job_state)
+    }
+}
+
+#[test]
+fn test_parse_job_sort() {
+    for sort in &[JobSort::State, JobSort::Priority, JobSort::RunTime, JobSort::JobId, JobSort::User, JobSort::Squeue] {
+        assert_eq!(parse_job_sort(sort.as_str()), Some(*sort));
+    }
+    assert_eq!(parse_job_sort(" Priority "), Some(JobSort::Priority));
+    assert_eq!(parse_job_sort("size"), None);
+    assert_eq!(parse_job_sort(""), None);
+    assert_eq!(JobSort::default(), JobSort::State);
+}
+
+#[test]
+fn test_sort_jobs() {
+    let jobs = vec![
+        test_job("10", JobState::Pending, "0:00", Some(0.2), "user02"),
+        test_job("11", JobState::Running, "5:00", Some(0.9), "user01"),
+        test_job("11.batch", JobState::Running, "5:00", None, "user01"),
+        test_job("12", JobState::Pending, "0:00", Some(0.99998474074527), "user01"),
+        test_job("13", JobState::Completed, "1:00:00", Some(0.5), "user03"),
+        test_job("14", JobState::Running, "1-00:00:00", Some(0.1), "user02"),
+        test_job("15", JobState::Pending, "0:00", None, "user01")
+    ];
+    let order = |sort: JobSort| {
+        let mut jobs = jobs.clone();
+        sort_jobs(&mut jobs, sort);
+        jobs.iter().map(|job| job.job_id.as_ref().unwrap().to_string()).collect::<Vec<String>>()
+    };
+
+    // The step stays below its job
+    assert_eq!(order(JobSort::State), vec!["14", "11", "11.batch", "12", "10", "15", "13"]);
+    assert_eq!(order(JobSort::Priority), vec!["12", "11", "11.batch", "13", "10", "14", "15"]);
+    assert_eq!(order(JobSort::RunTime), vec!["14", "13", "11", "11.batch", "10", "12", "15"]);
+    assert_eq!(order(JobSort::JobId), vec!["10", "11", "11.batch", "12", "13", "14", "15"]);
+    assert_eq!(order(JobSort::User), vec!["11", "11.batch", "12", "15", "10", "14", "13"]);
+    assert_eq!(order(JobSort::Squeue), vec!["10", "11", "11.batch", "12", "13", "14", "15"]);
+}
+
+#[test]
+fn test_format_priority() {
+    assert_eq!(format_priority(0.99998474074527), "0.999985");
+    assert_eq!(format_priority(0.12345678), "0.123457");
+    assert_eq!(format_priority(0.00012346), "0.00012346");
+    assert_eq!(format_priority(0.5), "0.5");
+    assert_eq!(format_priority(1234.56789), "1234.57");
+    assert_eq!(format_priority(0.0), "0");
+    // Whole numbers and large values keep all their digits
+    assert_eq!(format_priority(4294901758.0), "4294901758");
+    assert_eq!(format_priority(123456.789), "123457");
+}
@@ -0,0 +1,294 @@
+//! Sets up the logger according to the configuration
+//! Log files are rotated by size and only the last few are kept, stderr uses flexi_logger and stdout a minimal logger
+//! With --log-format json every target writes one JSON object per line instead of the detailed text format
+
+// System modules:
+use std::io::{self, Write};
+use std::fs::{File, OpenOptions, rename, remove_file};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+// External modules:
+use log::{set_logger, Log, LogRecord, LogMetadata, LogLevel, LogLevelFilter};
+use flexi_logger::{detailed_format, init, LogConfig};
+use time::{strftime, now};
+use rustc_serialize::json::Json;
+
+// Internal modules:
+use configuration::{Configuration, LogTarget, LogFormat};
+
+/// Format of the time stamp of the detailed format
+const DETAILED_TIME_FORMAT: &'static str = "%Y.%m.%d - %H:%M:%S";
+
+/// Format of the time stamp of the JSON format (ISO 8601 with time zone offset)
+const JSON_TIME_FORMAT: &'static str = "%Y-%m-%dT%H:%M:%S%z";
+
+/// Minimal logger that writes one line per message to stdout or stderr
+pub struct ConsoleLogger {
+    target: LogTarget,
+    format: LogFormat,
+    level: LogLevelFilter
+}
+
+impl Log for ConsoleLogger {
+    fn enabled(&self, metadata: &LogMetadata) -> bool {
+        metadata.level() <= self.level
+    }
+
+    fn log(&self, record: &LogRecord) {
+        if !self.enabled(record.metadata()) {
+            return;
+        }
+
+        let line = format_record(self.format, record);
+        // Errors can't be logged here, so they are ignored
+        let _ = match self.target {
+            LogTarget::Stdout => io::stdout().write_all(line.as_bytes()),
+            _ => io::stderr().write_all(line.as_bytes())
+        };
+    }
+}
+
+/// Log file that is rotated when it gets bigger than max_size
+/// The rotated files get a number, "slurm_inspector.log.1" is the newest one, only the last keep files are kept
+pub struct RotatingFile {
+    path: PathBuf,
+    max_size: u64,
+    keep: usize,
+    file: File,
+    size: u64
+}
+
+impl RotatingFile {
+    /// Open (or create) the log file, new lines are appended. A max_size of 0 disables the rotation
+    pub fn open(path: &Path, max_size: u64, keep: usize) -> io::Result<RotatingFile> {
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+        let size = file.metadata()?.len();
+
+        Ok(RotatingFile {
+            path: path.to_path_buf(),
+            max_size: max_size,
+            keep: keep,
+            file: file,
+            size: size
+        })
+    }
+
+    /// Returns the path of the rotated file with the given number, for example "slurm_inspector.log.1"
+    pub fn rotated_path(&self, number: usize) -> PathBuf {
+        let mut path = self.path.as_os_str().to_os_string();
+        path.push(format!(".{}", number));
+        PathBuf::from(path)
+    }
+
+    /// Write a line, the file is rotated before if the line doesn't fit anymore
+    pub fn write_line(&mut self, line: &str) -> io::Result<()> {
+        if self.max_size > 0 && self.size > 0 && self.size + line.len() as u64 > self.max_size {
+            self.rotate()?;
+        }
+
+        self.file.write_all(line.as_bytes())?;
+        self.size += line.len() as u64;
+
+        Ok(())
+    }
+
+    // Private helper function to rotate the files: the oldest one is removed, the others get the next number
+    // and the current file becomes number 1
+    fn rotate(&mut self) -> io::Result<()> {
+        let oldest = self.rotated_path(self.keep);
+        if self.keep > 0 && oldest.exists() {
+            remove_file(&oldest)?;
+        }
+
+        for number in (1..self.keep).rev() {
+            let path = self.rotated_path(number);
+            if path.exists() {
+                rename(&path, &self.rotated_path(number + 1))?;
+            }
+        }
+
+        if self.keep > 0 {
+            rename(&self.path, &self.rotated_path(1))?;
+        } else {
+            remove_file(&self.path)?;
+        }
+
+        self.file = OpenOptions::new().create(true).append(true).open(&self.path)?;
+        self.size = 0;
+
+        Ok(())
+    }
+}
+
+/// Logger that writes one line per message to a rotating log file
+pub struct FileLogger {
+    file: Mutex<RotatingFile>,
+    format: LogFormat,
+    level: LogLevelFilter
+}
+
+impl Log for FileLogger {
+    fn enabled(&self, metadata: &LogMetadata) -> bool {
+        metadata.level() <= self.level
+    }
+
+    fn log(&self, record: &LogRecord) {
+        if !self.enabled(record.metadata()) {
+            return;
+        }
+
+        let line = format_record(self.format, record);
+        // Errors can't be logged here, so they are ignored
+        if let Ok(mut file) = self.file.lock() {
+            let _ = file.write_line(&line);
+        }
+    }
+}
+
+/// Public helper function to format one line of the console logger and the log file
+pub fn format_line(timestamp: &str, level: LogLevel, target: &str, message: &str) -> String {
+    format!("{} {} [{}] {}\n", timestamp, level, target, message)
+}
+
+/// Public helper function to format one log message as JSON object (without line break), for example
+/// {"ts":"2015-12-20T12:00:00+0100","level":"WARN","target":"slurm_util::slurm_status","msg":"sinfo failed"}
+/// Quotes, backslashes, line breaks and other control characters in the message are escaped, so each message stays on one line
+pub fn format_json(timestamp: &str, level: LogLevel, target: &str, message: &str) -> String {
+    let string = |text: &str| Json::String(text.to_string()).to_string();
+
+    format!("{{\"ts\":{},\"level\":{},\"target\":{},\"msg\":{}}}", string(timestamp), string(&level.to_string()), string(target), string(message))
+}
+
+/// Public helper function to format a log record for flexi_logger (stderr) as JSON object, flexi_logger adds the line break
+pub fn json_format(record: &LogRecord) -> String {
+    format_json(&strftime(JSON_TIME_FORMAT, &now()).unwrap(), record.level(), record.target(), &format!("{}", record.args()))
+}
+
+// Private helper function to format one line of the console logger and the log file in the configured format
+fn format_record(format: LogFormat, record: &LogRecord) -> String {
+    let message = format!("{}", record.args());
+
+    match format {
+        LogFormat::Detailed => format_line(&strftime(DETAILED_TIME_FORMAT, &now()).unwrap(), record.level(), record.target(), &message),
+        LogFormat::Json => format!("{}\n", format_json(&strftime(JSON_TIME_FORMAT, &now()).unwrap(), record.level(), record.target(), &message))
+    }
+}
+
+/// Public helper function to convert the log level of the configuration (error, info or debug) to a filter
+pub fn log_level_filter(log_level: &str) -> LogLevelFilter {
+    match log_level {
+        "error" => LogLevelFilter::Error,
+        "debug" => LogLevelFilter::Debug,
+        _ => LogLevelFilter::Info
+    }
+}
+
+/// Public helper function to get the path of the log file in the configured log directory
+pub fn log_file_path(config: &Configuration) -> PathBuf {
+    Path::new(config.log_dir.as_ref().map_or(".", |dir| &dir[..])).join("slurm_inspector.log")
+}
+
+// Private helper function to install the rotating file logger
+fn init_file_logger(config: &Configuration) -> Result<(), String> {
+    let path = log_file_path(config);
+    let file = RotatingFile::open(&path, config.log_rotate_size, config.log_keep)
+        .map_err(|err| format!("could not open log file '{}': {}", path.display(), err))?;
+    let level = log_level_filter(&config.log_level);
+
+    set_logger(|max_log_level| {
+        max_log_level.set(level);
+        Box::new(FileLogger { file: Mutex::new(file), format: config.log_format, level: level })
+    }).map_err(|err| format!("{}", err))
+}
+
+// Private helper function to install the console logger
+fn init_console_logger(config: &Configuration, target: LogTarget) -> Result<(), String> {
+    let level = log_level_filter(&config.log_level);
+
+    set_logger(|max_log_level| {
+        max_log_level.set(level);
+        Box::new(ConsoleLogger { target: target, format: config.log_format, level: level })
+    }).map_err(|err| format!("{}", err))
+}
+
+/// Public function to set up the logger, returns an error if no logger could be set up
+/// If flexi_logger can't log to stderr, the console logger is used instead
+pub fn init_logging(config: &Configuration) -> Result<(), String> {
+    match config.log_target {
+        LogTarget::File => init_file_logger(config),
+        LogTarget::Stderr => {
+            let format = match config.log_format {
+                LogFormat::Detailed => detailed_format,
+                LogFormat::Json => json_format
+            };
+            match init(LogConfig { log_to_file: false, format: format, .. LogConfig::new() }, Some(config.log_level.clone())) {
+                Ok(()) => Ok(()),
+                Err(err) => {
+                    writeln!(io::stderr(), "slurm_inspector: flexi_logger initialization failed: {}, using a minimal logger", err).unwrap();
+                    init_console_logger(config, LogTarget::Stderr)
+                }
+            }
+        },
+        LogTarget::Stdout => init_console_logger(config, LogTarget::Stdout)
+    }
+}
+
+#[test]
+fn test_format_line() {
+    assert_eq!(format_line("2015.12.20 - 12:00:00", LogLevel::Warn, "slurm_util::slurm_status", "sinfo failed"),
+        "2015.12.20 - 12:00:00 WARN [slurm_util::slurm_status] sinfo failed\n");
+}
+
+#[test]
+fn test_format_json() {
+    assert_eq!(format_json("2015-12-20T12:00:00+0100", LogLevel::Warn, "slurm_util::slurm_status", "sinfo failed"),
+        "{\"ts\":\"2015-12-20T12:00:00+0100\",\"level\":\"WARN\",\"target\":\"slurm_util::slurm_status\",\"msg\":\"sinfo failed\"}");
+    assert_eq!(format_json("t", LogLevel::Error, "main", "could not parse \"42 x\" in C:\\slurm"),
+        "{\"ts\":\"t\",\"level\":\"ERROR\",\"target\":\"main\",\"msg\":\"could not parse \\\"42 x\\\" in C:\\\\slurm\"}");
+    // Non-ASCII characters are kept, line breaks and tabs are escaped so the message stays on one line
+    assert_eq!(format_json("t", LogLevel::Info, "main", "user J\u{fc}rgen: \u{2713}"),
+        "{\"ts\":\"t\",\"level\":\"INFO\",\"target\":\"main\",\"msg\":\"user J\u{fc}rgen: \u{2713}\"}");
+    let json = format_json("t", LogLevel::Debug, "main", "squeue output:\nline 1\r\n\tline 2");
+    assert_eq!(json, "{\"ts\":\"t\",\"level\":\"DEBUG\",\"target\":\"main\",\"msg\":\"squeue output:\\nline 1\\r\\n\\tline 2\"}");
+    assert!(!json.contains('\n'));
+
+    // The message can be read back
+    let parsed = Json::from_str(&format_json("t", LogLevel::Info, "main", "a \"b\"\nc")).unwrap();
+    assert_eq!(parsed.find("msg").and_then(|msg| msg.as_string()), Some("a \"b\"\nc"));
+}
+
+#[test]
+fn test_log_level_filter() {
+    assert_eq!(log_level_filter("error"), LogLevelFilter::Error);
+    assert_eq!(log_level_filter("info"), LogLevelFilter::Info);
+    assert_eq!(log_level_filter("debug"), LogLevelFilter::Debug);
+}
+
+#[test]
+fn test_log_file_path() {
+    assert_eq!(log_file_path(&Configuration::default()), Path::new("./slurm_inspector.log"));
+    let config = Configuration{ log_dir: Some("/var/log/slurm_inspector".to_string()), .. Configuration::default() };
+    assert_eq!(log_file_path(&config), Path::new("/var/log/slurm_inspector/slurm_inspector.log"));
+}
+
+#[test]
+fn test_rotating_file_keep() {
+    use std::env::temp_dir;
+    use std::fs::{create_dir, remove_dir_all};
+    use time::precise_time_ns;
+
+    let dir = temp_dir().join(format!("slurm_inspector_log_{}", precise_time_ns()));
+    create_dir(&dir).unwrap();
+    let mut file = RotatingFile::open(&dir.join("slurm_inspector.log"), 10, 2).unwrap();
+
+    for _ in 0..5 {
+        file.write_line("0123456789").unwrap();
+    }
+
+    assert!(file.rotated_path(1).exists());
+    assert!(file.rotated_path(2).exists());
+    assert!(!file.rotated_path(3).exists());
+    assert_eq!(file.size, 10);
+    remove_dir_all(&dir).unwrap();
+}
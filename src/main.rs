@@ -4,47 +4,239 @@
 
 // External crates:
 extern crate iron;
+extern crate time;
 #[macro_use] extern crate log;
-extern crate flexi_logger;
 
 // Internal crates:
 extern crate slurm_util;
 
 // System modules:
 use std::net::{SocketAddrV4, Ipv4Addr};
-use std::sync::{Arc, Mutex};
+use std::process;
+use std::io::{self, Write};
+use std::sync::Arc;
+use std::fs::{create_dir_all, remove_file};
+use std::path::PathBuf;
 
 // External modules:
 use iron::prelude::{Iron, Request};
-use flexi_logger::{detailed_format,init,LogConfig};
+use iron::Protocol;
+use time::get_time;
 
 // Internal modules:
-use slurm_util::configuration::setup_configuration;
-use slurm_util::request_handler::handle_request;
-use slurm_util::slurm_status::{SlurmStatus, check_slurm_status};
+use slurm_util::configuration::{Configuration, OutputFormat, LogTarget, setup_configuration};
+use slurm_util::request_handler::{handle_request, guard_request, limit_request, secure_request};
+use slurm_util::slurm_status::{SlurmStatus, PageOptions, check_slurm_status, update_slurm_status, create_runner,
+    status_to_html, status_to_json, status_to_text};
+use slurm_util::status_filter::StatusFilter;
+use slurm_util::static_files::read_custom_css;
+use slurm_util::history::History;
+use slurm_util::command_runner::check_executable;
+use slurm_util::access_log::{AccessLog, log_request};
+use slurm_util::logging::init_logging;
+use slurm_util::template::page_templates;
+use slurm_util::refresh::RefreshControl;
+use slurm_util::status_stream::Subscribers;
+use slurm_util::cluster_info::{detect_cluster_name, detect_slurm_version};
+use slurm_util::request_limit::{RequestLimiter, http_timeouts};
+use slurm_util::build_info::BuildInfo;
+use slurm_util::prom_textfile::temp_path;
+use slurm_util::unix_socket::{bind_socket, remove_on_signal, listen_socket};
+use slurm_util::tls::check_tls_files;
+use slurm_util::shared_status::SharedStatus;
+
+// Fetch the SLURM status once and print it to stdout, without starting the web server
+// Returns the exit code of the program: 0 on success, 1 if the page can't be rendered, 2 if a SLURM command failed
+fn run_once(config: &Configuration) -> i32 {
+    let mut status = SlurmStatus::new();
+    update_slurm_status(&mut status, config, &*create_runner(config), None);
+
+    match config.format {
+        OutputFormat::Html => match status_to_html(&status, &PageOptions::new(config)) {
+            Ok(html) => print!("{}", html),
+            Err(err) => {
+                writeln!(io::stderr(), "slurm_inspector: could not render the page: {}", err).unwrap();
+                return 1;
+            }
+        },
+        OutputFormat::Json => println!("{}", status_to_json(&status, &StatusFilter::for_team(&config.users))),
+        OutputFormat::Text => print!("{}", status_to_text(&status, &StatusFilter::for_team(&config.users)))
+    }
+
+    for err in &status.update_errors {
+        writeln!(io::stderr(), "slurm_inspector: {}", err).unwrap();
+    }
+
+    if status.update_errors.is_empty() { 0 } else { 2 }
+}
 
 fn main() {
-    // Parse command line arguments
-    let config = setup_configuration();
+    // The uptime on the page and at /api/version counts from here
+    let started = get_time().sec;
+
+    // Parse command line arguments, the logger is not set up yet so errors go to stderr
+    let mut config = match setup_configuration() {
+        Ok(config) => config,
+        Err(err) => {
+            writeln!(io::stderr(), "slurm_inspector: {}", err).unwrap();
+            process::exit(1);
+        }
+    };
+
+    // Without a log file there is nothing to look at later, but logging to stdout or stderr is optional
+    if let Err(err) = init_logging(&config) {
+        if config.log_target == LogTarget::File {
+            panic!("Logger initialization failed with the following error: {}", err);
+        }
+        writeln!(io::stderr(), "slurm_inspector: logger initialization failed: {}, continuing without log", err).unwrap();
+    }
+
+    let build_info = BuildInfo::new();
+    info!("slurm_inspector v{} (commit {})", build_info.version, build_info.commit);
+    info!("configuration: port: {}, interval: {} (sinfo: {}, squeue: {}), test mode: {}, log level: {}, log target: {:?}, log format: {:?}, page refresh: {}", config.port, config.interval, config.sinfo_interval, config.squeue_interval, config.test_mode, config.log_level, config.log_target, config.log_format, config.page_refresh);
 
-    init(LogConfig { log_to_file: true, format: detailed_format, .. LogConfig::new() }, Some(config.log_level.clone()))
-         .unwrap_or_else(|e| { panic!("Logger initialization failed with the following error: {}", e) });
+    // Load the user-provided templates, the built-in templates are used if they can't be loaded
+    if config.template_dir.is_some() {
+        config.templates = Arc::new(page_templates(&config.template_dir));
+    }
 
-    info!("configuration: port: {}, interval: {}, test mode: {}, log level: {}", config.port, config.interval, config.test_mode, config.log_level);
+    // Check the custom stylesheet early, so a wrong path shows up in the log right away
+    if let Some(ref path) = config.custom_css {
+        read_custom_css(path);
+    }
+
+    // Check the SLURM commands early, so a wrong path shows up in the log right away
+    // In remote mode the commands are on the remote host, so only ssh can be checked, in replay mode no command is called
+    if !config.test_mode && config.replay_dir.is_none() {
+        if config.remote_host.is_some() {
+            if let Err(err) = check_executable("ssh") {
+                warn!("ssh can't be executed: {}", err);
+            }
+        } else {
+            for path in &[&config.squeue_path, &config.sinfo_path] {
+                if let Err(err) = check_executable(path) {
+                    warn!("SLURM command can't be executed: {}", err);
+                }
+            }
+        }
+    }
+
+    // Create the record directory early, so a wrong path shows up in the log right away
+    if let Some(ref dir) = config.record_dir {
+        match create_dir_all(dir) {
+            Ok(()) => info!("recording the output of the SLURM commands in '{}'", dir),
+            Err(err) => warn!("Could not create the record directory '{}': {}", dir, err)
+        }
+    }
+    if let Some(ref path) = config.history_db {
+        info!("saving the history in '{}' for {} days", path, config.history_retention);
+    }
+    if let Some(ref dir) = config.replay_dir {
+        info!("replaying the output of the SLURM commands from '{}'", dir);
+    }
+    // A temporary file can be left over if slurm_inspector was killed while writing the metrics
+    if let Some(ref path) = config.prom_textfile {
+        info!("writing the metrics to '{}' after each update", path);
+        let _ = remove_file(temp_path(path));
+    }
+
+    // The cluster name and the SLURM version don't change while running, so they are detected only once
+    {
+        let runner = create_runner(&config);
+        if config.cluster_name.is_empty() {
+            config.cluster_name = detect_cluster_name(&*runner, &config);
+        }
+        config.slurm_version = detect_slurm_version(&*runner, &config);
+        info!("cluster name: {}, SLURM version: {}", config.cluster_name, config.slurm_version);
+    }
+
+    if config.once {
+        process::exit(run_once(&config));
+    }
 
     // Create empty SlurmStatus object
-    let initial_slurm_status = SlurmStatus{ node_info: Vec::new(), job_info: Vec::new(), last_update: String::new() };
+    let mut initial_slurm_status = SlurmStatus::new();
+    initial_slurm_status.history = History::new(config.history_size);
+    initial_slurm_status.build_info = BuildInfo { started: Some(started), .. build_info };
 
     // Iron-persistence can't be used here since own thread can't access private filed "data" of struct "State"
-    let local_slurm_status = Arc::new(Mutex::new(initial_slurm_status));
+    // Requests render from a snapshot of the status, the background thread swaps in a new one after each update
+    let local_slurm_status = Arc::new(SharedStatus::new(initial_slurm_status));
+
+    // POST /refresh wakes up the background thread through this
+    let refresh = Arc::new(RefreshControl::new(config.refresh_spacing));
+
+    // The background thread sends every new status to the open /events streams
+    let subscribers = Arc::new(Subscribers::new());
 
     // Start the background thread and read in real values
-    check_slurm_status(&local_slurm_status, config.clone());
+    check_slurm_status(&local_slurm_status, &refresh, &subscribers, config.clone());
 
     // Need to clone this since each client request will be handled by iron in a separate thread
     let shared_slurm_status = local_slurm_status.clone();
+    let handler_config = config.clone();
+    let access_log = AccessLog::from_config(&config);
+    let limiter = RequestLimiter::new(config.max_requests);
+    info!("web server threads: {}, max. requests at the same time: {}", config.http_threads, config.max_requests);
+    if config.max_requests >= config.http_threads {
+        warn!("--max-requests should be lower than --http-threads, otherwise no thread is left to reject requests");
+    }
+    if !config.allowed_ips.is_empty() {
+        info!("allowed clients: {}", config.allowed_ips.iter().map(|network| network.to_string()).collect::<Vec<String>>().join(", "));
+    }
+    if config.enable_admin_actions && !config.trust_proxy {
+        warn!("--enable-admin-actions needs --trust-proxy, without an authenticated user all admin actions are refused");
+    }
+
+    // Rejected requests are logged in the access log as well
+    let handler = move |req: &mut Request| {
+        let handler = |req: &mut Request| secure_request(req, &handler_config, |req| limit_request(&limiter, ||
+            guard_request(req, |req| handle_request(req, &shared_slurm_status, &handler_config, &refresh, &subscribers))));
+        match access_log {
+            Some(ref access_log) => log_request(req, access_log, handler),
+            None => handler(req)
+        }
+    };
 
     // Run iron web framework and wait for the user to load the page
-    Iron::new( move |req: &mut Request| { handle_request(req, &shared_slurm_status) }
-).http(SocketAddrV4::new(Ipv4Addr::new(0, 0, 0, 0), config.port)).unwrap();
+    match config.listen_socket {
+        Some(ref path) => {
+            let (listener, socket_file) = match bind_socket(path, config.socket_mode) {
+                Ok(socket) => socket,
+                Err(err) => {
+                    error!("Could not listen on the socket '{}': {}", path, err);
+                    writeln!(io::stderr(), "slurm_inspector: could not listen on the socket '{}': {}", path, err).unwrap();
+                    process::exit(1);
+                }
+            };
+            info!("listening on the socket '{}' (mode {:o})", path, config.socket_mode);
+            remove_on_signal(socket_file);
+            listen_socket(handler, listener, config.http_threads, http_timeouts(config.http_read_timeout, config.http_write_timeout)).unwrap();
+        },
+        None => {
+            // Check the certificate and the key here, Iron's own error doesn't say what's wrong with them
+            let (scheme, protocol) = match (config.tls_cert.as_ref(), config.tls_key.as_ref()) {
+                (Some(cert), Some(key)) => {
+                    if let Err(err) = check_tls_files(cert, key) {
+                        error!("Can't use HTTPS: {}", err);
+                        writeln!(io::stderr(), "slurm_inspector: can't use HTTPS: {}", err).unwrap();
+                        process::exit(1);
+                    }
+                    ("https", Protocol::Https { certificate: PathBuf::from(cert), key: PathBuf::from(key) })
+                },
+                _ => ("http", Protocol::Http)
+            };
+            let timeouts = http_timeouts(config.http_read_timeout, config.http_write_timeout);
+            match Iron::new(handler).listen_with(SocketAddrV4::new(Ipv4Addr::new(0, 0, 0, 0), config.port), config.http_threads, protocol,
+                Some(timeouts)) {
+                // Dropping the guard waits for the server threads, so main doesn't return
+                Ok(listening) => info!("listening on {}://{}", scheme, listening.socket),
+                Err(err) => {
+                    error!("Could not start the web server on port {}: {}", config.port, err);
+                    writeln!(io::stderr(), "slurm_inspector: could not start the web server on port {}: {}", config.port, err).unwrap();
+                    process::exit(1);
+                }
+            }
+        }
+    }
 }
@@ -0,0 +1,107 @@
+//! Natural ordering of strings like node names
+//! Runs of digits are compared as numbers, so "node2" comes before "node10"
+
+// System modules:
+use std::cmp::Ordering;
+
+// Private helper function to split a string into runs of digits and runs of other characters
+fn split_chunks(text: &str) -> Vec<&str> {
+    let mut result: Vec<&str> = Vec::new();
+    let mut start = 0;
+    let mut last_is_digit = None;
+
+    for (index, character) in text.char_indices() {
+        let is_digit = character.is_digit(10);
+        if last_is_digit.map_or(false, |last| last != is_digit) {
+            result.push(&text[start..index]);
+            start = index;
+        }
+        last_is_digit = Some(is_digit);
+    }
+
+    if start < text.len() {
+        result.push(&text[start..]);
+    }
+
+    result
+}
+
+// Private helper function to compare two runs of digits by their numeric value
+// No parsing is done, so arbitrary long numbers work. On equal values the shorter run comes first ("1" < "01")
+fn compare_digits(left: &str, right: &str) -> Ordering {
+    let left_value = &left[left.find(|c| c != '0').unwrap_or(left.len())..];
+    let right_value = &right[right.find(|c| c != '0').unwrap_or(right.len())..];
+
+    match left_value.len().cmp(&right_value.len()) {
+        Ordering::Equal => match left_value.cmp(right_value) {
+            Ordering::Equal => left.len().cmp(&right.len()),
+            ordering => ordering
+        },
+        ordering => ordering
+    }
+}
+
+/// Public helper function to compare two strings in natural order
+/// Runs of digits are compared as numbers, everything else character by character
+pub fn natural_cmp(left: &str, right: &str) -> Ordering {
+    let left_chunks = split_chunks(left);
+    let right_chunks = split_chunks(right);
+
+    for (left_chunk, right_chunk) in left_chunks.iter().zip(right_chunks.iter()) {
+        let left_is_digit = left_chunk.chars().next().map_or(false, |c| c.is_digit(10));
+        let right_is_digit = right_chunk.chars().next().map_or(false, |c| c.is_digit(10));
+
+        let ordering = if left_is_digit && right_is_digit {
+            compare_digits(left_chunk, right_chunk)
+        } else {
+            left_chunk.cmp(right_chunk)
+        };
+
+        if ordering != Ordering::Equal {
+            return ordering;
+        }
+    }
+
+    left_chunks.len().cmp(&right_chunks.len())
+}
+
+#[test]
+fn test_split_chunks() {
+    assert_eq!(split_chunks(""), Vec::<&str>::new());
+    assert_eq!(split_chunks("node"), vec!["node"]);
+    assert_eq!(split_chunks("gpu-a10b2"), vec!["gpu-a", "10", "b", "2"]);
+    assert_eq!(split_chunks("42"), vec!["42"]);
+}
+
+#[test]
+fn test_natural_cmp_numbers() {
+    assert_eq!(natural_cmp("node2", "node10"), Ordering::Less);
+    assert_eq!(natural_cmp("node10", "node2"), Ordering::Greater);
+    assert_eq!(natural_cmp("gpu-a1", "gpu-a10"), Ordering::Less);
+    assert_eq!(natural_cmp("gpu-a9", "gpu-a10"), Ordering::Less);
+    assert_eq!(natural_cmp("node10", "node10"), Ordering::Equal);
+}
+
+#[test]
+fn test_natural_cmp_digit_width() {
+    assert_eq!(natural_cmp("node02", "node10"), Ordering::Less);
+    assert_eq!(natural_cmp("node002", "node10"), Ordering::Less);
+    assert_eq!(natural_cmp("node1", "node01"), Ordering::Less);
+    assert_eq!(natural_cmp("node01", "node1"), Ordering::Greater);
+    assert_eq!(natural_cmp("node01a", "node1b"), Ordering::Greater);
+}
+
+#[test]
+fn test_natural_cmp_prefixes() {
+    assert_eq!(natural_cmp("node", "node1"), Ordering::Less);
+    assert_eq!(natural_cmp("gpu1", "node1"), Ordering::Less);
+    assert_eq!(natural_cmp("1node", "node"), Ordering::Less);
+    assert_eq!(natural_cmp("", "node"), Ordering::Less);
+}
+
+#[test]
+fn test_natural_cmp_sort() {
+    let mut nodes = vec!["node10", "node2", "gpu-a10", "node1", "gpu-a1", "gpu-a2"];
+    nodes.sort_by(|a, b| natural_cmp(a, b));
+    assert_eq!(nodes, vec!["gpu-a1", "gpu-a2", "gpu-a10", "node1", "node2", "node10"]);
+}
@@ -0,0 +1,299 @@
+//! Detects node state changes between two updates of the SLURM status
+//! The changes are logged and the most recent ones are shown on the web page
+
+// System modules:
+use std::collections::{BTreeMap, VecDeque};
+use std::fmt;
+
+// External modules:
+use rustc_serialize::json::{Json, ToJson};
+
+// Internal modules:
+use sinfo_util::{PartitionNodeInfo, PartitionAvailability, NodeState, ErrorCause};
+
+/// Number of node events kept in the SLURM status
+pub const MAX_NODE_EVENTS: usize = 50;
+
+/// What happened to a node between two updates
+#[derive(Debug, Clone, PartialEq)]
+pub enum NodeEventKind {
+    /// The node state has changed, reason is the error cause reported by sinfo
+    StateChanged { from: NodeState, to: NodeState, reason: ErrorCause },
+    /// The node is new in the output of sinfo
+    Appeared(NodeState),
+    /// The node is no longer in the output of sinfo
+    Disappeared(NodeState)
+}
+
+/// A change of a single node
+#[derive(Debug, Clone, PartialEq)]
+pub struct NodeEvent {
+    /// Time of the update that detected the change, format: %Y.%m.%d - %H:%M
+    pub time: String,
+    pub node: String,
+    pub kind: NodeEventKind
+}
+
+impl NodeEvent {
+    /// Returns true for changes that need the attention of an admin
+    pub fn is_alarming(&self) -> bool {
+        match self.kind {
+            NodeEventKind::StateChanged { to, .. } => is_bad_state(to),
+            NodeEventKind::Appeared(_) => false,
+            NodeEventKind::Disappeared(_) => true
+        }
+    }
+}
+
+impl fmt::Display for NodeEvent {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.kind {
+            NodeEventKind::StateChanged { from, to, reason } => write!(f, "{}: {} -> {}, reason: {}", self.node, from, to, reason),
+            NodeEventKind::Appeared(state) => write!(f, "{}: appeared ({})", self.node, state),
+            NodeEventKind::Disappeared(state) => write!(f, "{}: disappeared (was {})", self.node, state)
+        }
+    }
+}
+
+impl ToJson for NodeEvent {
+    fn to_json(&self) -> Json {
+        let mut result = BTreeMap::new();
+        result.insert("time".to_string(), self.time.to_json());
+        result.insert("node".to_string(), self.node.to_json());
+        match self.kind {
+            NodeEventKind::StateChanged { from, to, reason } => {
+                result.insert("event".to_string(), "StateChanged".to_json());
+                result.insert("from".to_string(), from.to_json());
+                result.insert("to".to_string(), to.to_json());
+                result.insert("reason".to_string(), reason.to_json());
+            },
+            NodeEventKind::Appeared(state) => {
+                result.insert("event".to_string(), "Appeared".to_json());
+                result.insert("to".to_string(), state.to_json());
+            },
+            NodeEventKind::Disappeared(state) => {
+                result.insert("event".to_string(), "Disappeared".to_json());
+                result.insert("from".to_string(), state.to_json());
+            }
+        }
+        Json::Object(result)
+    }
+}
+
+/// Availability change of a partition between two updates
+#[derive(Debug, Clone, PartialEq)]
+pub struct PartitionEvent {
+    /// Time of the update that detected the change, format: %Y.%m.%d - %H:%M
+    pub time: String,
+    pub partition: String,
+    pub from: PartitionAvailability,
+    pub to: PartitionAvailability
+}
+
+// Private helper function, states where the node can't run jobs because something is wrong
+fn is_bad_state(state: NodeState) -> bool {
+    match state {
+        NodeState::Down | NodeState::Drained | NodeState::Draining | NodeState::Fail | NodeState::Failing | NodeState::Unknown => true,
+        _ => false
+    }
+}
+
+// Private helper function to prefix the name with the cluster, if there is one
+// Nodes and partitions on different clusters can have the same name
+fn name_in_cluster(cluster: &str, name: &str) -> String {
+    if cluster.is_empty() {
+        name.to_string()
+    } else {
+        format!("{}/{}", cluster, name)
+    }
+}
+
+// Private helper function to index the nodes by name
+// A node can be in several partitions, but it has the same state in all of them
+fn nodes_by_name(node_info: &[PartitionNodeInfo]) -> BTreeMap<String, &PartitionNodeInfo> {
+    node_info.iter().map(|node| (name_in_cluster(&node.cluster, &node.node), node)).collect()
+}
+
+/// Public helper function to compare the nodes of two updates and return all changes, sorted by node name
+pub fn diff_node_info(previous: &[PartitionNodeInfo], current: &[PartitionNodeInfo], time: &str) -> Vec<NodeEvent> {
+    let previous_nodes = nodes_by_name(previous);
+    let current_nodes = nodes_by_name(current);
+    let mut result = Vec::new();
+
+    for (name, node) in &current_nodes {
+        let kind = match previous_nodes.get(name) {
+            Some(old) if old.node_state != node.node_state => {
+                NodeEventKind::StateChanged { from: old.node_state, to: node.node_state, reason: node.error }
+            },
+            Some(_) => continue,
+            None => NodeEventKind::Appeared(node.node_state)
+        };
+        result.push(NodeEvent { time: time.to_string(), node: name.to_string(), kind: kind });
+    }
+
+    for (name, old) in &previous_nodes {
+        if !current_nodes.contains_key(name) {
+            result.push(NodeEvent { time: time.to_string(), node: name.to_string(), kind: NodeEventKind::Disappeared(old.node_state) });
+        }
+    }
+
+    result.sort_by(|a, b| a.node.cmp(&b.node));
+    result
+}
+
+/// Public helper function to compare the partition availability of two updates, sorted by partition name
+/// Partitions that are only in one of the updates are ignored
+pub fn diff_partition_availability(previous: &[PartitionNodeInfo], current: &[PartitionNodeInfo], time: &str) -> Vec<PartitionEvent> {
+    let previous_partitions: BTreeMap<String, PartitionAvailability> = previous.iter().map(|node| (name_in_cluster(&node.cluster, &node.partition), node.availability)).collect();
+    let current_partitions: BTreeMap<String, PartitionAvailability> = current.iter().map(|node| (name_in_cluster(&node.cluster, &node.partition), node.availability)).collect();
+
+    current_partitions.iter()
+        .filter_map(|(partition, availability)| {
+            previous_partitions.get(partition)
+                .and_then(|old| if old != availability { Some(*old) } else { None })
+                .map(|old| PartitionEvent { time: time.to_string(), partition: partition.to_string(), from: old, to: *availability })
+        })
+        .collect()
+}
+
+/// Public helper function to log the events and add them to the list of recent events
+/// The list is bounded by MAX_NODE_EVENTS, the oldest events are dropped first
+pub fn record_node_events(events: &mut VecDeque<NodeEvent>, new_events: Vec<NodeEvent>) {
+    for event in new_events {
+        if event.is_alarming() {
+            warn!("{}", event);
+        } else {
+            info!("{}", event);
+        }
+
+        events.push_back(event);
+    }
+
+    while events.len() > MAX_NODE_EVENTS {
+        events.pop_front();
+    }
+}
+
+#[cfg(test)]
+fn test_node(node: &str, node_state: NodeState, error: ErrorCause) -> PartitionNodeInfo {
+    PartitionNodeInfo {
+        cluster: String::new(),
+        partition: "esd".to_string(),
+        availability: PartitionAvailability::Up,
+        hostname: Some(node.to_string()),
+        node: node.to_string(),
+        error: error,
+        cpu_load: None,
+        node_state: node_state,
+        node_flags: Vec::new(),
+        node_sockets: None,
+        node_cores: None,
+        node_threads: None,
+        cpus_alloc: None,
+        cpus_idle: None,
+        cpus_other: None,
+        cpus_total: None,
+        gres: Vec::new(),
+        memory_total: None,
+        memory_free: None,
+        reason: None
+    }
+}
+
+#[test]
+fn test_diff_node_info_unchanged() {
+    let nodes = vec![test_node("node01", NodeState::Idle, ErrorCause::None)];
+    assert_eq!(diff_node_info(&nodes, &nodes, "t"), vec![]);
+}
+
+#[test]
+fn test_diff_node_info_state_changed() {
+    let previous = vec![
+        test_node("node07", NodeState::Idle, ErrorCause::None),
+        test_node("node08", NodeState::Idle, ErrorCause::None)
+    ];
+    let current = vec![
+        test_node("node07", NodeState::Down, ErrorCause::Down),
+        test_node("node08", NodeState::Idle, ErrorCause::None)
+    ];
+    let events = diff_node_info(&previous, &current, "2000.01.01 - 03:00");
+
+    assert_eq!(events, vec![NodeEvent {
+        time: "2000.01.01 - 03:00".to_string(),
+        node: "node07".to_string(),
+        kind: NodeEventKind::StateChanged { from: NodeState::Idle, to: NodeState::Down, reason: ErrorCause::Down }
+    }]);
+    assert_eq!(events[0].to_string(), "node07: idle -> down, reason: down");
+    assert!(events[0].is_alarming());
+}
+
+#[test]
+fn test_diff_node_info_appeared_and_disappeared() {
+    let previous = vec![
+        test_node("node01", NodeState::Idle, ErrorCause::None),
+        test_node("node02", NodeState::Allocated, ErrorCause::None)
+    ];
+    let current = vec![
+        test_node("node01", NodeState::Idle, ErrorCause::None),
+        test_node("node03", NodeState::Idle, ErrorCause::None)
+    ];
+    let events = diff_node_info(&previous, &current, "t");
+
+    assert_eq!(events.len(), 2);
+    assert_eq!(events[0].kind, NodeEventKind::Disappeared(NodeState::Allocated));
+    assert_eq!(events[0].to_string(), "node02: disappeared (was allocated)");
+    assert_eq!(events[1].kind, NodeEventKind::Appeared(NodeState::Idle));
+    assert!(!events[1].is_alarming());
+}
+
+#[test]
+fn test_diff_node_info_multiple_partitions() {
+    let mut other_partition = test_node("node01", NodeState::Idle, ErrorCause::None);
+    other_partition.partition = "gpu".to_string();
+    let previous = vec![test_node("node01", NodeState::Idle, ErrorCause::None), other_partition.clone()];
+    other_partition.node_state = NodeState::Mixed;
+    let current = vec![test_node("node01", NodeState::Mixed, ErrorCause::None), other_partition];
+
+    assert_eq!(diff_node_info(&previous, &current, "t").len(), 1);
+}
+
+#[test]
+fn test_diff_node_info_clusters() {
+    let mut other_cluster = test_node("node01", NodeState::Idle, ErrorCause::None);
+    other_cluster.cluster = "cluster2".to_string();
+    let previous = vec![test_node("node01", NodeState::Idle, ErrorCause::None), other_cluster.clone()];
+    other_cluster.node_state = NodeState::Down;
+    let current = vec![test_node("node01", NodeState::Idle, ErrorCause::None), other_cluster];
+    let events = diff_node_info(&previous, &current, "t");
+
+    assert_eq!(events.len(), 1);
+    assert_eq!(events[0].node, "cluster2/node01");
+}
+
+#[test]
+fn test_diff_partition_availability() {
+    let previous = vec![test_node("node01", NodeState::Idle, ErrorCause::None)];
+    let mut current = previous.clone();
+    assert_eq!(diff_partition_availability(&previous, &current, "t"), vec![]);
+
+    current[0].availability = PartitionAvailability::Down;
+    assert_eq!(diff_partition_availability(&previous, &current, "t"), vec![PartitionEvent {
+        time: "t".to_string(),
+        partition: "esd".to_string(),
+        from: PartitionAvailability::Up,
+        to: PartitionAvailability::Down
+    }]);
+}
+
+#[test]
+fn test_record_node_events_bounded() {
+    let mut events = VecDeque::new();
+
+    for index in 0..(MAX_NODE_EVENTS + 5) {
+        let event = NodeEvent { time: "t".to_string(), node: format!("node{}", index), kind: NodeEventKind::Appeared(NodeState::Idle) };
+        record_node_events(&mut events, vec![event]);
+    }
+
+    assert_eq!(events.len(), MAX_NODE_EVENTS);
+    assert_eq!(events.front().map(|event| &event.node[..]), Some("node5"));
+}
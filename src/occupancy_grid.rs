@@ -0,0 +1,141 @@
+//! Occupancy grid of the status page: one small colored cell per node, grouped by partition, above the node table
+//! The color comes from the node state, a node with running jobs gets a border. The grid is plain HTML (divs with classes),
+//! the details of a node are in the title attribute of its cell
+
+// System modules:
+use std::collections::BTreeMap;
+
+// External modules:
+use rustc_serialize::json::{Json, ToJson};
+
+// Internal modules:
+use sinfo_util::PartitionNodeInfo;
+use squeue_util::{JobInfo, JobState};
+use slurm_status::jobs_by_node;
+
+/// Default number of nodes in one row of the grid (--grid-width)
+pub const DEFAULT_GRID_WIDTH: usize = 32;
+
+/// One node of the grid
+#[derive(Debug, Clone, PartialEq)]
+pub struct GridCell {
+    pub node: String,
+    /// CSS class of the node state, for example "grid_idle" or "grid_down"
+    pub state_class: String,
+    /// At least one job is running on the node
+    pub busy: bool,
+    /// Tooltip with node name, state, load, allocated CPUs and the ids of the running jobs
+    pub title: String
+}
+
+impl ToJson for GridCell {
+    fn to_json(&self) -> Json {
+        let mut result = BTreeMap::new();
+        result.insert("node".to_string(), self.node.to_json());
+        result.insert("state_class".to_string(), self.state_class.to_json());
+        result.insert("busy".to_string(), self.busy.to_json());
+        result.insert("title".to_string(), self.title.to_json());
+        Json::Object(result)
+    }
+}
+
+/// The nodes of one partition, split into rows of at most --grid-width nodes
+#[derive(Debug, Clone, PartialEq)]
+pub struct GridPartition {
+    pub cluster: String,
+    pub partition: String,
+    pub rows: Vec<Vec<GridCell>>
+}
+
+impl ToJson for GridPartition {
+    fn to_json(&self) -> Json {
+        let mut result = BTreeMap::new();
+        result.insert("cluster".to_string(), self.cluster.to_json());
+        result.insert("partition".to_string(), self.partition.to_json());
+        result.insert("rows".to_string(), self.rows.to_json());
+        Json::Object(result)
+    }
+}
+
+/// Public helper function that returns the CSS class of the node state, for example "grid_powered_down" for NodeState::PoweredDown
+pub fn grid_state_class(node: &PartitionNodeInfo) -> String {
+    format!("grid_{}", node.node_state.as_slurm_str())
+}
+
+/// Public helper function that builds the grid of the given nodes (already filtered and sorted by cluster and partition)
+/// Only running jobs mark a node as busy, a width of 0 disables the grid
+pub fn occupancy_grid(node_info: &[&PartitionNodeInfo], job_info: &[JobInfo], width: usize) -> Vec<GridPartition> {
+    if width == 0 {
+        return Vec::new();
+    }
+
+    let running: Vec<JobInfo> = job_info.iter().filter(|job| job.job_state == JobState::Running).cloned().collect();
+    let node_jobs = jobs_by_node(&running);
+    let mut result: Vec<GridPartition> = Vec::new();
+    let mut cells: Vec<GridCell> = Vec::new();
+
+    for (index, node) in node_info.iter().enumerate() {
+        let job_ids: Vec<String> = node_jobs.get(&(node.cluster.clone(), node.node.clone()))
+            .map_or(Vec::new(), |job_ids| job_ids.iter().map(|job_id| job_id.to_string()).collect());
+        let cpus = match (node.cpus_alloc, node.cpus_total) {
+            (Some(alloc), Some(total)) => format!(", CPUs {}/{}", alloc, total),
+            _ => String::new()
+        };
+        let jobs = if job_ids.is_empty() { "no jobs".to_string() } else { format!("jobs {}", job_ids.join(", ")) };
+
+        cells.push(GridCell {
+            node: node.node.clone(),
+            state_class: grid_state_class(node),
+            busy: !job_ids.is_empty(),
+            title: format!("{} ({}): load {}{}, {}", node.node, node.node_state,
+                node.cpu_load.map_or("-".to_string(), |load| load.to_string()), cpus, jobs)
+        });
+
+        let last_of_partition = node_info.get(index + 1).map_or(true, |next| next.cluster != node.cluster || next.partition != node.partition);
+        if last_of_partition {
+            result.push(GridPartition {
+                cluster: node.cluster.clone(),
+                partition: node.partition.clone(),
+                rows: cells.chunks(width).map(|row| row.to_vec()).collect()
+            });
+            cells.clear();
+        }
+    }
+
+    result
+}
+
+#[test]
+fn test_occupancy_grid() {
+    use sinfo_util::get_partition_node_info_test;
+    use squeue_util::get_job_info_test;
+
+    let mut nodes = get_partition_node_info_test();
+    let mut gpu = nodes[0].clone();
+    gpu.partition = "gpu".to_string();
+    nodes.push(gpu);
+    let nodes: Vec<&PartitionNodeInfo> = nodes.iter().collect();
+    let jobs = get_job_info_test();
+
+    let grid = occupancy_grid(&nodes, &jobs, 5);
+    assert_eq!(grid.iter().map(|partition| (&partition.partition[..], partition.rows.iter().map(|row| row.len()).collect::<Vec<usize>>()))
+        .collect::<Vec<_>>(), vec![("esd", vec![5, 5, 2]), ("gpu", vec![1])]);
+
+    let cell = |name: &str| grid[0].rows.iter().flat_map(|row| row.iter()).find(|cell| cell.node == name).unwrap().clone();
+    assert_eq!(cell("node01").state_class, "grid_idle");
+    assert_eq!(cell("node07").state_class, "grid_drained");
+    assert_eq!(cell("node01").title, "node01 (idle): load 0.22, CPUs 0/8, jobs 1");
+    assert_eq!(cell("node03").title, "node03 (unknown): load -, no jobs");
+    assert!(cell("node01").busy);
+    assert!(!cell("node04").busy);
+    // A node in two partitions shows up in both
+    assert_eq!(grid[1].rows[0][0].node, "node01");
+
+    // Pending and finished jobs don't count
+    let pending: Vec<JobInfo> = jobs.iter().map(|job| JobInfo{ job_state: JobState::Pending, .. job.clone() }).collect();
+    assert!(occupancy_grid(&nodes, &pending, 5).iter().flat_map(|partition| partition.rows.iter().flat_map(|row| row.iter())).all(|cell| !cell.busy));
+
+    assert_eq!(occupancy_grid(&nodes, &jobs, 100)[0].rows.len(), 1);
+    assert!(occupancy_grid(&nodes, &jobs, 0).is_empty());
+    assert!(occupancy_grid(&[], &jobs, 5).is_empty());
+}
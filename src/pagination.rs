@@ -0,0 +1,217 @@
+//! Splits long job tables into pages, set via the query parameters ?limit= and ?page=
+//! With many thousand pending jobs the page would otherwise get too big for the browser
+
+// System modules:
+use std::collections::BTreeMap;
+
+// External modules:
+use rustc_serialize::json::{Json, ToJson};
+
+/// Default number of rows per page, can be changed with --page-limit
+pub const DEFAULT_PAGE_LIMIT: usize = 500;
+
+/// Requested page of a table
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Pagination {
+    /// Number of rows per page, 0 shows all rows
+    pub limit: usize,
+    /// Number of the page, starting at 1
+    pub page: usize
+}
+
+/// The rows of a table that are shown on the requested page
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PageRange {
+    /// Number of the page (starting at 1), a page beyond the end is the last page
+    pub page: usize,
+    /// Number of pages, at least 1
+    pub pages: usize,
+    /// Index of the first row and index after the last row, start == end if there are no rows
+    pub start: usize,
+    pub end: usize,
+    /// Number of rows of the whole table
+    pub total: usize
+}
+
+impl Pagination {
+    /// Create the pagination for the given limit (0 for all rows) and page (starting at 1)
+    pub fn new(limit: usize, page: usize) -> Pagination {
+        Pagination {
+            limit: limit,
+            page: page
+        }
+    }
+
+    /// Pagination that shows all rows on one page
+    pub fn unlimited() -> Pagination {
+        Pagination::new(0, 1)
+    }
+
+    /// Returns the rows of a table with total rows that are shown on the requested page
+    /// A page beyond the end returns the last page and page 0 the first one
+    pub fn range(&self, total: usize) -> PageRange {
+        if self.limit == 0 {
+            return PageRange { page: 1, pages: 1, start: 0, end: total, total: total };
+        }
+
+        let pages = if total == 0 { 1 } else { (total + self.limit - 1) / self.limit };
+        let page = if self.page == 0 { 1 } else if self.page > pages { pages } else { self.page };
+        let start = (page - 1) * self.limit;
+        let end = if start + self.limit > total { total } else { start + self.limit };
+
+        PageRange { page: page, pages: pages, start: start, end: end, total: total }
+    }
+
+    /// Returns the rows of the given table that are shown on the requested page
+    pub fn apply<'a, T>(&self, rows: &'a [T]) -> &'a [T] {
+        let range = self.range(rows.len());
+        &rows[range.start..range.end]
+    }
+}
+
+impl Default for Pagination {
+    fn default() -> Pagination {
+        Pagination::new(DEFAULT_PAGE_LIMIT, 1)
+    }
+}
+
+impl PageRange {
+    /// Returns true if the table doesn't fit on one page
+    pub fn is_paged(&self) -> bool {
+        self.pages > 1
+    }
+}
+
+impl ToJson for PageRange {
+    fn to_json(&self) -> Json {
+        let mut result = BTreeMap::new();
+        result.insert("page".to_string(), self.page.to_json());
+        result.insert("pages".to_string(), self.pages.to_json());
+        result.insert("start".to_string(), self.start.to_json());
+        result.insert("end".to_string(), self.end.to_json());
+        result.insert("total".to_string(), self.total.to_json());
+        Json::Object(result)
+    }
+}
+
+/// Public helper function to parse the limit and page query values, missing or invalid values get the defaults
+pub fn parse_pagination(limit: Option<&str>, page: Option<&str>, default_limit: usize) -> Pagination {
+    Pagination::new(
+        limit.and_then(|limit| limit.trim().parse::<usize>().ok()).unwrap_or(default_limit),
+        page.and_then(|page| page.trim().parse::<usize>().ok()).unwrap_or(1))
+}
+
+/// Public helper function to replace (or add) a parameter of a query string, the other parameters are kept
+pub fn query_with_param(query: &str, name: &str, value: &str) -> String {
+    let prefix = format!("{}=", name);
+    let mut params: Vec<String> = query.split('&')
+        .filter(|param| !param.is_empty() && *param != name && !param.starts_with(&prefix))
+        .map(|param| param.to_string())
+        .collect();
+    params.push(format!("{}={}", name, value));
+
+    format!("?{}", params.join("&"))
+}
+
+/// Public helper function to replace (or add) the page parameter of a query string, the other parameters are kept
+pub fn query_with_page(query: &str, page: usize) -> String {
+    query_with_param(query, "page", &page.to_string())
+}
+
+/// Navigation above a table with "Previous" and "Next" links
+#[derive(Debug, Clone, PartialEq)]
+pub struct PageLinks {
+    /// Query strings of the previous and the next page, None on the first or the last page
+    pub previous: Option<String>,
+    pub next: Option<String>,
+    /// Number of the first and the last row that are shown (starting at 1) and number of all rows
+    pub first_row: usize,
+    pub last_row: usize,
+    pub total: usize
+}
+
+impl ToJson for PageLinks {
+    fn to_json(&self) -> Json {
+        let mut result = BTreeMap::new();
+        result.insert("previous".to_string(), self.previous.to_json());
+        result.insert("next".to_string(), self.next.to_json());
+        result.insert("first_row".to_string(), self.first_row.to_json());
+        result.insert("last_row".to_string(), self.last_row.to_json());
+        result.insert("total".to_string(), self.total.to_json());
+        Json::Object(result)
+    }
+}
+
+/// Public helper function to build the navigation above a table, the other parameters of the query are kept in the links
+/// Returns None if the table fits on one page
+pub fn page_links(query: &str, range: &PageRange) -> Option<PageLinks> {
+    if !range.is_paged() {
+        return None;
+    }
+
+    Some(PageLinks {
+        previous: if range.page > 1 { Some(query_with_page(query, range.page - 1)) } else { None },
+        next: if range.page < range.pages { Some(query_with_page(query, range.page + 1)) } else { None },
+        first_row: range.start + 1,
+        last_row: range.end,
+        total: range.total
+    })
+}
+
+#[test]
+fn test_pagination_range() {
+    assert_eq!(Pagination::new(10, 1).range(25), PageRange{ page: 1, pages: 3, start: 0, end: 10, total: 25 });
+    assert_eq!(Pagination::new(10, 3).range(25), PageRange{ page: 3, pages: 3, start: 20, end: 25, total: 25 });
+    assert_eq!(Pagination::new(10, 2).range(20), PageRange{ page: 2, pages: 2, start: 10, end: 20, total: 20 });
+}
+
+#[test]
+fn test_pagination_range_edge_cases() {
+    // Beyond the end is the last page, page 0 is the first page
+    assert_eq!(Pagination::new(10, 99).range(25), PageRange{ page: 3, pages: 3, start: 20, end: 25, total: 25 });
+    assert_eq!(Pagination::new(10, 0).range(25), Pagination::new(10, 1).range(25));
+    // Limit 0 shows all rows
+    assert_eq!(Pagination::new(0, 5).range(25), PageRange{ page: 1, pages: 1, start: 0, end: 25, total: 25 });
+    // An empty table has one empty page
+    assert_eq!(Pagination::new(10, 2).range(0), PageRange{ page: 1, pages: 1, start: 0, end: 0, total: 0 });
+}
+
+#[test]
+fn test_pagination_apply() {
+    let rows: Vec<u32> = (0..25).collect();
+    assert_eq!(Pagination::new(10, 2).apply(&rows), &rows[10..20]);
+    assert_eq!(Pagination::unlimited().apply(&rows).len(), 25);
+}
+
+#[test]
+fn test_parse_pagination() {
+    assert_eq!(parse_pagination(Some("50"), Some("3"), 500), Pagination::new(50, 3));
+    assert_eq!(parse_pagination(None, None, 500), Pagination::new(500, 1));
+    assert_eq!(parse_pagination(Some("all"), Some("-1"), 500), Pagination::new(500, 1));
+    assert_eq!(parse_pagination(Some("0"), None, 500), Pagination::unlimited());
+}
+
+#[test]
+fn test_query_with_page() {
+    assert_eq!(query_with_page("", 2), "?page=2");
+    assert_eq!(query_with_page("user=willi&page=1&limit=50", 2), "?user=willi&limit=50&page=2");
+    assert_eq!(query_with_page("pages=7&page", 3), "?pages=7&page=3");
+}
+
+#[test]
+fn test_query_with_param() {
+    assert_eq!(query_with_param("", "all_jobs", "1"), "?all_jobs=1");
+    assert_eq!(query_with_param("all_jobs=1&user=willi", "all_jobs", "0"), "?user=willi&all_jobs=0");
+    assert_eq!(query_with_param("all_jobs_x=1", "all_jobs", "1"), "?all_jobs_x=1&all_jobs=1");
+}
+
+#[test]
+fn test_page_links() {
+    assert_eq!(page_links("", &Pagination::new(10, 1).range(10)), None);
+    assert_eq!(page_links("limit=10", &Pagination::new(10, 1).range(25)),
+        Some(PageLinks{ previous: None, next: Some("?limit=10&page=2".to_string()), first_row: 1, last_row: 10, total: 25 }));
+    assert_eq!(page_links("limit=10&page=2", &Pagination::new(10, 2).range(25)),
+        Some(PageLinks{ previous: Some("?limit=10&page=1".to_string()), next: Some("?limit=10&page=3".to_string()), first_row: 11, last_row: 20, total: 25 }));
+    assert_eq!(page_links("limit=10&page=3", &Pagination::new(10, 3).range(25)),
+        Some(PageLinks{ previous: Some("?limit=10&page=2".to_string()), next: None, first_row: 21, last_row: 25, total: 25 }));
+}
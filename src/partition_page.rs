@@ -0,0 +1,91 @@
+//! Page of a single partition (/partition/<name>): the nodes, the jobs and the limits of the partition with its own summary numbers
+//! The tables are rendered like on the status page, /api/partitions/<name> returns the status of the partition as JSON
+
+// External modules:
+use rustc_serialize::json::ToJson;
+
+// Internal modules:
+use slurm_status::{SlurmStatus, PageOptions, PartitionSummary, partition_summaries, render_filtered_status, filtered_status_to_json};
+use status_filter::StatusFilter;
+use template::PARTITION_TEMPLATE;
+
+// Private helper function that returns the filter for the nodes and jobs of the given partition, the other parameters of the query are kept
+fn partition_filter(filter: &StatusFilter, name: &str) -> StatusFilter {
+    StatusFilter { partition: Some(name.to_string()), .. filter.clone() }
+}
+
+/// Public helper function that returns the summary of the partition: nodes, node states, GPUs, limits and wait times
+/// Returns None if no node is in the partition, with several clusters the partition of the first cluster is returned
+pub fn partition_summary(status: &SlurmStatus, name: &str) -> Option<PartitionSummary> {
+    partition_summaries(status, &partition_filter(&StatusFilter::new(), name)).into_iter().next()
+}
+
+/// Public helper function to render the page of a partition, None if the partition doesn't exist
+pub fn partition_to_html(status: &SlurmStatus, options: &PageOptions, name: &str) -> Option<Result<String, String>> {
+    partition_summary(status, name)
+        .map(|summary| render_filtered_status(status, options, partition_filter(&options.filter, name), PARTITION_TEMPLATE, summary.to_json()))
+}
+
+/// Public helper function that returns the status of a partition as JSON (/api/partitions/<name>), like /api/status with the summary
+/// of the partition, None if the partition doesn't exist
+pub fn partition_to_json(status: &SlurmStatus, filter: &StatusFilter, name: &str) -> Option<String> {
+    partition_summary(status, name).map(|summary| filtered_status_to_json(status, &partition_filter(filter, name), summary.to_json()))
+}
+
+#[cfg(test)]
+fn test_status() -> SlurmStatus {
+    use configuration::Configuration;
+    use command_runner::TestRunner;
+    use slurm_status::update_slurm_status;
+
+    let config = Configuration{ test_mode: true, enable_partition_limits: true, .. Configuration::default() };
+    let mut status = SlurmStatus::new();
+    update_slurm_status(&mut status, &config, &TestRunner::new(Err("must not be called".to_string())), None);
+    status
+}
+
+#[test]
+fn test_partition_summary() {
+    let status = test_status();
+
+    let summary = partition_summary(&status, "esd").unwrap();
+    assert_eq!(summary.name, "esd");
+    assert_eq!(summary.nodes as usize, status.node_info.len());
+    assert!(summary.limits.is_some());
+
+    assert_eq!(partition_summary(&status, "nopartition"), None);
+}
+
+#[test]
+fn test_partition_to_html() {
+    let mut status = test_status();
+    status.node_info[0].partition = "gpu".to_string();
+    let options = PageOptions::default();
+
+    let html = partition_to_html(&status, &options, "esd").unwrap().unwrap();
+    assert!(html.contains("<h1>Partition esd</h1>"));
+    assert!(html.contains("<td class=\"partition_name\"><a href=\"/partition/esd\">esd</a></td>"));
+    assert!(html.contains(&format!("<td rowspan=\"{}\" class=\"partition_group\"><a href=\"/partition/esd\">esd</a></td>", status.node_info.len() - 1)));
+    assert!(!html.contains("/partition/gpu"));
+    assert!(html.contains("<h3>Job information"));
+
+    let html = partition_to_html(&status, &options, "gpu").unwrap().unwrap();
+    assert!(html.contains("<td rowspan=\"1\" class=\"partition_group\"><a href=\"/partition/gpu\">gpu</a></td>"));
+    assert!(!html.contains("/partition/esd"));
+
+    assert_eq!(partition_to_html(&status, &options, "nopartition"), None);
+}
+
+#[test]
+fn test_partition_to_json() {
+    use rustc_serialize::json::Json;
+
+    let status = test_status();
+
+    let json = Json::from_str(&partition_to_json(&status, &StatusFilter::new(), "esd").unwrap()).unwrap();
+    assert_eq!(json.find_path(&["summary", "name"]).and_then(|value| value.as_string()), Some("esd"));
+    assert!(json.find_path(&["summary", "limits"]).map_or(false, |value| value.is_object()));
+    assert_eq!(json.find("node_info").and_then(|value| value.as_array()).map(|nodes| nodes.len()), Some(status.node_info.len()));
+
+    assert_eq!(partition_to_json(&status, &StatusFilter::new(), "nopartition"), None);
+}
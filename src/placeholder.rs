@@ -0,0 +1,37 @@
+//! Placeholders that squeue and sinfo print for values that are not available
+//! Depending on the field and the SLURM version this is "(null)", "N/A", "*" or "-"
+
+/// All placeholders for missing values
+pub const PLACEHOLDERS: [&'static str; 4] = ["(null)", "N/A", "*", "-"];
+
+/// Public helper function to check if a field value is a placeholder for a missing value
+pub fn is_placeholder(value: &str) -> bool {
+    PLACEHOLDERS.contains(&value)
+}
+
+/// Public helper function to convert a field value into a string, placeholders become None
+pub fn optional_string(value: &str) -> Option<String> {
+    if is_placeholder(value) {
+        None
+    } else {
+        Some(value.to_string())
+    }
+}
+
+#[test]
+fn test_is_placeholder() {
+    for placeholder in &PLACEHOLDERS {
+        assert!(is_placeholder(placeholder));
+    }
+    assert!(!is_placeholder("null"));
+    assert!(!is_placeholder("node01"));
+    assert!(!is_placeholder(""));
+}
+
+#[test]
+fn test_optional_string() {
+    assert_eq!(optional_string("node01"), Some("node01".to_string()));
+    assert_eq!(optional_string("2000-01-01T09:00:00"), Some("2000-01-01T09:00:00".to_string()));
+    assert_eq!(optional_string("(null)"), None);
+    assert_eq!(optional_string("N/A"), None);
+}
@@ -0,0 +1,92 @@
+//! Output of the metrics for the textfile collector of node_exporter (--prom-textfile)
+//! For sites that can't open another port for scraping: the metrics of /metrics are written to a file after each successful update
+//! The file is replaced atomically, so node_exporter never reads half of it
+
+// System modules:
+use std::fs::{File, rename, remove_file};
+use std::io::{self, Write};
+
+// Internal modules:
+use slurm_status::{SlurmStatus, status_to_prometheus};
+
+/// Public helper function that returns the name of the temporary file next to the given file
+/// node_exporter only reads files ending with ".prom", so it ignores the temporary file
+pub fn temp_path(path: &str) -> String {
+    format!("{}.tmp", path)
+}
+
+/// Public helper function that replaces the file with the given content: the content is written to a temporary file
+/// in the same directory, which is then renamed, so readers see either the old or the new file
+/// If something fails the temporary file is removed again
+pub fn write_atomically(path: &str, content: &str) -> io::Result<()> {
+    let temp = temp_path(path);
+    let result = File::create(&temp)
+        .and_then(|mut file| file.write_all(content.as_bytes()).and_then(|_| file.sync_all()))
+        .and_then(|_| rename(&temp, path));
+
+    if result.is_err() {
+        let _ = remove_file(&temp);
+    }
+    result
+}
+
+/// Public helper function that returns the metrics of the file: the same as /metrics and the time of the write
+/// now is the current time in seconds since the epoch
+pub fn textfile_metrics(status: &SlurmStatus, now: i64) -> String {
+    let mut result = status_to_prometheus(status);
+
+    result.push_str("# HELP slurm_inspector_last_write Time of the last write of this file in seconds since the epoch\n");
+    result.push_str("# TYPE slurm_inspector_last_write gauge\n");
+    result.push_str(&format!("slurm_inspector_last_write {}\n", now));
+
+    result
+}
+
+/// Public helper function that writes the metrics to the file after an update, errors are logged and the update goes on
+pub fn write_prom_textfile(path: &str, status: &SlurmStatus, now: i64) {
+    if let Err(err) = write_atomically(path, &textfile_metrics(status, now)) {
+        warn!("Could not write the metrics to '{}': {}", path, err);
+    }
+}
+
+#[test]
+fn test_write_atomically() {
+    use std::env::temp_dir;
+    use std::io::Read;
+    use std::path::Path;
+    use time::precise_time_ns;
+
+    let path = temp_dir().join(format!("slurm_inspector_{}.prom", precise_time_ns())).to_string_lossy().to_string();
+    let read = |path: &str| {
+        let mut content = String::new();
+        File::open(path).unwrap().read_to_string(&mut content).unwrap();
+        content
+    };
+
+    write_atomically(&path, "first 1\n").unwrap();
+    assert_eq!(read(&path), "first 1\n");
+    write_atomically(&path, "second 2\n").unwrap();
+    assert_eq!(read(&path), "second 2\n");
+    assert!(!Path::new(&temp_path(&path)).exists());
+    remove_file(&path).unwrap();
+
+    // The directory does not exist, nothing is left behind
+    let missing = "/this/path/does/not/exist/slurm.prom";
+    assert!(write_atomically(missing, "first 1\n").is_err());
+    assert!(!Path::new(&temp_path(missing)).exists());
+}
+
+#[test]
+fn test_textfile_metrics() {
+    use inspector_metrics::metrics_to_prometheus;
+
+    let mut status = SlurmStatus::new();
+    status.cluster_name = "cluster1".to_string();
+    status.slurm_version = "17.11.2".to_string();
+
+    let metrics = textfile_metrics(&status, 1500000000);
+    assert!(metrics.starts_with(&metrics_to_prometheus(&status.metrics, "cluster1", "17.11.2")));
+    assert!(metrics.contains("slurm_inspector_pending_ratio{cluster=\"cluster1\"} 0\n"));
+    assert!(metrics.contains("# TYPE slurm_inspector_last_write gauge\n"));
+    assert!(metrics.ends_with("\nslurm_inspector_last_write 1500000000\n"));
+}
@@ -0,0 +1,156 @@
+//! Raw output of the sinfo and squeue calls of the last update for the debug endpoints (--debug-endpoints)
+//! When the parsed tables look wrong, /debug/raw/sinfo and /debug/raw/squeue show what the commands really printed,
+//! without shell access to the server. The output is only kept with --debug-endpoints and at most MAX_RAW_OUTPUT_SIZE bytes of it
+
+// System modules:
+use std::sync::Mutex;
+
+// Internal modules:
+use command_runner::{CommandRunner, shell_quote};
+use display_time::DisplayTime;
+
+/// Maximum number of bytes of output that is kept per command (5 MB), the rest is cut off with a notice
+pub const MAX_RAW_OUTPUT_SIZE: usize = 5 * 1024 * 1024;
+
+/// One call of a command, with a list of clusters sinfo and squeue are called once per cluster
+#[derive(Debug, Clone, PartialEq)]
+pub struct RawCall {
+    /// The command line, for example "sinfo -h -o '%R %a'"
+    pub command_line: String,
+    /// "0" if the command succeeded, otherwise the error, which contains the exit status and stderr of the command
+    pub exit_status: String,
+    /// The exact stdout of the command, empty if it failed
+    pub stdout: String,
+    /// Number of bytes of stdout that have been cut off
+    pub truncated: usize
+}
+
+/// The calls of a command during the last update
+#[derive(Debug, Clone, PartialEq)]
+pub struct RawOutput {
+    /// Time of the capture in seconds since the epoch
+    pub captured_at: i64,
+    pub calls: Vec<RawCall>
+}
+
+/// Public helper function that cuts the text off after at most max_size bytes (at a character boundary)
+/// Returns the kept text and the number of bytes that have been cut off
+pub fn truncate_output(text: &str, max_size: usize) -> (String, usize) {
+    if text.len() <= max_size {
+        return (text.to_string(), 0);
+    }
+
+    let mut end = max_size;
+    while !text.is_char_boundary(end) {
+        end -= 1;
+    }
+    (text[..end].to_string(), text.len() - end)
+}
+
+impl RawOutput {
+    /// The plain text of the debug endpoint: for each call a header with the command line, the capture time and the exit status,
+    /// followed by the exact stdout
+    pub fn to_text(&self, display_time: &DisplayTime) -> String {
+        let mut result = String::new();
+
+        for call in &self.calls {
+            result.push_str(&format!("# Command: {}\n", call.command_line));
+            result.push_str(&format!("# Captured: {} ({})\n", display_time.format(self.captured_at), display_time.label()));
+            result.push_str(&format!("# Exit status: {}\n", call.exit_status));
+            result.push_str(&call.stdout);
+            if call.truncated > 0 {
+                if !call.stdout.ends_with('\n') {
+                    result.push('\n');
+                }
+                result.push_str(&format!("# Truncated: {} more bytes are not shown (limit: {} bytes)\n", call.truncated, MAX_RAW_OUTPUT_SIZE));
+            }
+        }
+
+        result
+    }
+}
+
+/// Runs the commands with the inner runner and keeps their output, so the output of the last update can be shown
+/// The output of all calls together is kept up to MAX_RAW_OUTPUT_SIZE bytes
+pub struct CapturingRunner<'a> {
+    /// Runs the commands
+    pub inner: &'a dyn CommandRunner,
+    calls: Mutex<Vec<RawCall>>
+}
+
+impl<'a> CapturingRunner<'a> {
+    /// Create a new runner that captures the output of the inner runner
+    pub fn new(inner: &'a dyn CommandRunner) -> CapturingRunner<'a> {
+        CapturingRunner {
+            inner: inner,
+            calls: Mutex::new(Vec::new())
+        }
+    }
+
+    /// Returns the captured calls, captured_at is the current time in seconds since the epoch
+    pub fn into_output(self, captured_at: i64) -> RawOutput {
+        RawOutput {
+            captured_at: captured_at,
+            calls: self.calls.into_inner().unwrap_or_else(|err| err.into_inner())
+        }
+    }
+}
+
+impl<'a> CommandRunner for CapturingRunner<'a> {
+    fn run(&self, program: &str, args: &[String], environment: &[(&str, &str)]) -> Result<String, String> {
+        let result = self.inner.run(program, args, environment);
+        let command_line = Some(program.to_string()).iter().chain(args.iter()).map(|arg| shell_quote(arg)).collect::<Vec<String>>().join(" ");
+        let mut calls = self.calls.lock().unwrap_or_else(|err| err.into_inner());
+        let used: usize = calls.iter().map(|call| call.stdout.len()).sum();
+
+        let (exit_status, (stdout, truncated)) = match result {
+            Ok(ref output) => ("0".to_string(), truncate_output(output, MAX_RAW_OUTPUT_SIZE - used)),
+            Err(ref err) => (err.clone(), (String::new(), 0))
+        };
+        calls.push(RawCall { command_line: command_line, exit_status: exit_status, stdout: stdout, truncated: truncated });
+
+        result
+    }
+}
+
+#[test]
+fn test_truncate_output() {
+    assert_eq!(truncate_output("node01 idle\n", 100), ("node01 idle\n".to_string(), 0));
+    assert_eq!(truncate_output("node01 idle\n", 6), ("node01".to_string(), 6));
+    // Not in the middle of "ü" (two bytes)
+    assert_eq!(truncate_output("müller", 2), ("m".to_string(), 6));
+    assert_eq!(truncate_output("", 0), (String::new(), 0));
+}
+
+#[test]
+fn test_capturing_runner() {
+    use command_runner::TestRunner;
+    use display_time::DisplayTimezone;
+
+    let inner = TestRunner::new(Ok("esd up node01 node01 none 0.22 idle\n".to_string()));
+    let runner = CapturingRunner::new(&inner);
+    assert_eq!(runner.run("sinfo", &["-h".to_string(), "-o".to_string(), "%R %a".to_string()], &[]), Ok("esd up node01 node01 none 0.22 idle\n".to_string()));
+
+    let output = runner.into_output(1450432800);
+    assert_eq!(output.calls, vec![RawCall { command_line: "sinfo -h -o '%R %a'".to_string(), exit_status: "0".to_string(),
+        stdout: "esd up node01 node01 none 0.22 idle\n".to_string(), truncated: 0 }]);
+    let utc = DisplayTime { timezone: DisplayTimezone::Utc, .. DisplayTime::default() };
+    assert_eq!(output.to_text(&utc), "# Command: sinfo -h -o '%R %a'\n# Captured: 2015.12.18 - 10:00 (UTC)\n# Exit status: 0\n\
+        esd up node01 node01 none 0.22 idle\n");
+
+    let inner = TestRunner::new(Err("slurm_load_partitions: Unable to contact slurm controller (exit status: 1)".to_string()));
+    let runner = CapturingRunner::new(&inner);
+    assert!(runner.run("sinfo", &[], &[]).is_err());
+    let output = runner.into_output(1450432800);
+    assert_eq!(output.calls[0].exit_status, "slurm_load_partitions: Unable to contact slurm controller (exit status: 1)");
+    assert_eq!(output.calls[0].stdout, "");
+}
+
+#[test]
+fn test_raw_output_truncated() {
+    let output = RawOutput { captured_at: 1450432800, calls: vec![RawCall { command_line: "squeue".to_string(), exit_status: "0".to_string(),
+        stdout: "1 RUNNING".to_string(), truncated: 1024 }] };
+    let text = output.to_text(&DisplayTime::default());
+
+    assert!(text.ends_with("# Exit status: 0\n1 RUNNING\n# Truncated: 1024 more bytes are not shown (limit: 5242880 bytes)\n"));
+}
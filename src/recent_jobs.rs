@@ -0,0 +1,152 @@
+//! Jobs that disappeared from squeue between two updates
+//! A job that finishes between two polls would simply vanish from the page, so it's kept for a few minutes with its last known state
+//! until sacct lists it with the finished jobs
+
+// System modules:
+use std::collections::{BTreeMap, BTreeSet};
+
+// External modules:
+use rustc_serialize::json::{Json, ToJson};
+
+// Internal modules:
+use squeue_util::JobInfo;
+
+/// Number of minutes a disappeared job is kept, default of --recently-finished-minutes
+pub const DEFAULT_RECENTLY_FINISHED_MINUTES: u64 = 10;
+
+/// A job that was in the previous job list but not in the current one
+#[derive(Debug, Clone, PartialEq)]
+pub struct RecentlyFinishedJob {
+    /// The job as squeue printed it the last time, job_state is the last known state
+    pub job: JobInfo,
+    /// Time of the last update that still contained the job, in seconds since the epoch
+    pub last_seen: i64
+}
+
+impl ToJson for RecentlyFinishedJob {
+    fn to_json(&self) -> Json {
+        let mut result = BTreeMap::new();
+        result.insert("job".to_string(), self.job.to_json());
+        result.insert("last_state".to_string(), self.job.job_state.to_json());
+        result.insert("last_seen".to_string(), self.last_seen.to_json());
+        Json::Object(result)
+    }
+}
+
+/// The recently disappeared jobs and the time of the last job update, the jobs of the previous update were seen at that time
+#[derive(Debug, Clone, PartialEq)]
+pub struct RecentlyFinished {
+    /// Oldest first
+    pub jobs: Vec<RecentlyFinishedJob>,
+    last_update: Option<i64>
+}
+
+// Private helper function that returns the key of a job: the cluster and the job ID, None for jobs without a job ID
+fn job_key(job: &JobInfo) -> Option<(String, String)> {
+    job.job_id.as_ref().map(|job_id| (job.cluster.clone(), job_id.to_string()))
+}
+
+impl RecentlyFinished {
+    /// Create an empty list, nothing is compared before the first update
+    pub fn new() -> RecentlyFinished {
+        RecentlyFinished {
+            jobs: Vec::new(),
+            last_update: None
+        }
+    }
+
+    /// Compares the job lists of the previous and the current update at the given time: jobs that disappeared are added,
+    /// jobs that are back in the queue (for example requeued) are removed, and jobs older than the retention time expire
+    pub fn update(&mut self, previous: &[JobInfo], current: &[JobInfo], now: i64, retention_minutes: u64) {
+        let current_keys: BTreeSet<(String, String)> = current.iter().filter_map(job_key).collect();
+        self.jobs.retain(|recent| job_key(&recent.job).map_or(false, |key| !current_keys.contains(&key)));
+
+        if let Some(last_seen) = self.last_update {
+            let known_keys: BTreeSet<(String, String)> = self.jobs.iter().filter_map(|recent| job_key(&recent.job)).collect();
+            let disappeared = previous.iter()
+                .filter(|job| job_key(job).map_or(false, |key| !current_keys.contains(&key) && !known_keys.contains(&key)))
+                .map(|job| RecentlyFinishedJob { job: job.clone(), last_seen: last_seen });
+            self.jobs.extend(disappeared);
+        }
+
+        self.last_update = Some(now);
+        self.expire(now, retention_minutes);
+    }
+
+    /// Removes the jobs that have been seen last more than the retention time ago, also called if the job update failed
+    pub fn expire(&mut self, now: i64, retention_minutes: u64) {
+        let retention = (retention_minutes * 60) as i64;
+        self.jobs.retain(|recent| now - recent.last_seen < retention);
+    }
+}
+
+#[test]
+fn test_recently_finished_disappeared() {
+    use squeue_util::{JobState, test_job};
+    use update_schedule::{Clock, TestClock};
+
+    let clock = TestClock::new(1000);
+    let mut recent = RecentlyFinished::new();
+
+    // Nothing to compare on the first update
+    recent.update(&[], &[test_job("1", JobState::Running), test_job("2", JobState::Running)], clock.now(), 10);
+    assert!(recent.jobs.is_empty());
+
+    clock.sleep(60);
+    recent.update(&[test_job("1", JobState::Running), test_job("2", JobState::Running)], &[test_job("2", JobState::Running), test_job("3", JobState::Running)], clock.now(), 10);
+    assert_eq!(recent.jobs.len(), 1);
+    assert_eq!(recent.jobs[0].job, test_job("1", JobState::Running));
+    // Seen last at the previous update
+    assert_eq!(recent.jobs[0].last_seen, 1000);
+
+    // Already known, not added twice
+    clock.sleep(60);
+    recent.update(&[test_job("1", JobState::Running), test_job("2", JobState::Running)], &[test_job("2", JobState::Running)], clock.now(), 10);
+    assert_eq!(recent.jobs.len(), 1);
+
+    // Back in the queue (requeued)
+    clock.sleep(60);
+    recent.update(&[test_job("2", JobState::Running)], &[test_job("1", JobState::Running), test_job("2", JobState::Running)], clock.now(), 10);
+    assert!(recent.jobs.is_empty());
+}
+
+#[test]
+fn test_recently_finished_expiry() {
+    use squeue_util::{JobState, test_job};
+    use update_schedule::{Clock, TestClock};
+
+    let clock = TestClock::new(1000);
+    let mut recent = RecentlyFinished::new();
+    recent.update(&[], &[test_job("1", JobState::Running), test_job("2", JobState::Running)], clock.now(), 10);
+    clock.sleep(60);
+    recent.update(&[test_job("1", JobState::Running), test_job("2", JobState::Running)], &[test_job("2", JobState::Running)], clock.now(), 10);
+    clock.sleep(120);
+    recent.update(&[test_job("2", JobState::Running)], &[], clock.now(), 10);
+    assert_eq!(recent.jobs.iter().map(|recent| recent.last_seen).collect::<Vec<i64>>(), vec![1000, 1060]);
+
+    // Kept for less than 10 minutes after the last time it was seen
+    clock.time.set(1000 + 599);
+    recent.expire(clock.now(), 10);
+    assert_eq!(recent.jobs.len(), 2);
+    clock.time.set(1000 + 600);
+    recent.expire(clock.now(), 10);
+    assert_eq!(recent.jobs.len(), 1);
+    assert_eq!(recent.jobs[0].job, test_job("2", JobState::Running));
+
+    // 0 minutes: not kept at all
+    clock.sleep(60);
+    recent.update(&[test_job("3", JobState::Running)], &[], clock.now(), 0);
+    assert!(recent.jobs.is_empty());
+}
+
+#[test]
+fn test_recently_finished_without_job_id() {
+    use squeue_util::{JobState, test_job};
+
+    let mut recent = RecentlyFinished::new();
+    let no_id = JobInfo { job_id: None, .. test_job("1", JobState::Running) };
+
+    recent.update(&[], &[no_id.clone()], 1000, 10);
+    recent.update(&[no_id], &[], 1060, 10);
+    assert!(recent.jobs.is_empty());
+}
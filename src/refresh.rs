@@ -0,0 +1,198 @@
+//! On-demand refresh of the SLURM status with POST /refresh
+//! The web handler wakes up the SLURM status thread, which then fetches everything right away
+//! instead of sleeping until the next update is due
+
+// System modules:
+use std::sync::{Mutex, MutexGuard, Condvar};
+use std::time::{Duration, Instant};
+use std::fmt;
+
+// External modules:
+use time::get_time;
+
+// Internal modules:
+use update_schedule::Clock;
+
+/// Default minimum time between two refreshes in seconds, so a reload button can't flood slurmctld
+pub const DEFAULT_REFRESH_SPACING: u64 = 10;
+
+/// Reasons why a refresh is refused
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum RefreshError {
+    /// Another refresh has been requested and is not finished yet
+    Running,
+    /// The last refresh started less than the minimum spacing ago, contains the number of seconds to wait
+    TooSoon(u64)
+}
+
+impl fmt::Display for RefreshError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            RefreshError::Running => write!(f, "a refresh is already running"),
+            RefreshError::TooSoon(seconds) => write!(f, "the last refresh was too recent, try again in {} sec.", seconds)
+        }
+    }
+}
+
+// Private state of the refresh, protected by the Mutex of RefreshControl
+#[derive(Debug)]
+struct RefreshState {
+    /// A refresh has been requested, the SLURM status thread has not started it yet
+    requested: bool,
+    /// The SLURM status thread is fetching the status for a refresh
+    running: bool,
+    /// Number of finished refreshes
+    finished: u64,
+    /// Time of the last refresh request in seconds since the epoch
+    last_refresh: Option<i64>
+}
+
+/// Shared between the web handlers, which request refreshes, and the SLURM status thread, which sleeps on it
+/// A single condition variable signals both a new request and a finished refresh
+#[derive(Debug)]
+pub struct RefreshControl {
+    /// Minimum time between two refreshes in seconds
+    min_spacing: u64,
+    state: Mutex<RefreshState>,
+    changed: Condvar
+}
+
+impl RefreshControl {
+    /// Create a RefreshControl that allows one refresh within the given number of seconds
+    pub fn new(min_spacing: u64) -> RefreshControl {
+        RefreshControl {
+            min_spacing: min_spacing,
+            state: Mutex::new(RefreshState{ requested: false, running: false, finished: 0, last_refresh: None }),
+            changed: Condvar::new()
+        }
+    }
+
+    // Private helper function to lock the state, a panic of another thread doesn't leave the state inconsistent
+    fn lock<'a>(&'a self) -> MutexGuard<'a, RefreshState> {
+        self.state.lock().unwrap_or_else(|err| err.into_inner())
+    }
+
+    /// Request a refresh at the given time (seconds since the epoch) and wake up the SLURM status thread
+    /// Returns the number of the refresh that is needed for wait_finished
+    pub fn request(&self, now: i64) -> Result<u64, RefreshError> {
+        let mut state = self.lock();
+
+        if state.requested || state.running {
+            return Err(RefreshError::Running);
+        }
+        if let Some(last_refresh) = state.last_refresh {
+            let next_refresh = last_refresh + self.min_spacing as i64;
+            if now < next_refresh {
+                return Err(RefreshError::TooSoon((next_refresh - now) as u64));
+            }
+        }
+
+        state.requested = true;
+        state.last_refresh = Some(now);
+        self.changed.notify_all();
+        Ok(state.finished + 1)
+    }
+
+    /// Wait until the refresh with the given number has finished, returns false if that took longer than the timeout
+    pub fn wait_finished(&self, refresh: u64, timeout: Duration) -> bool {
+        let deadline = Instant::now() + timeout;
+        let mut state = self.lock();
+
+        while state.finished < refresh {
+            let now = Instant::now();
+            if now >= deadline {
+                return false;
+            }
+            state = match self.changed.wait_timeout(state, deadline - now) {
+                Ok((state, _)) => state,
+                Err(err) => err.into_inner().0
+            };
+        }
+        true
+    }
+
+    /// Called by the SLURM status thread after each update, finishes the running refresh (if there is one)
+    pub fn finish(&self) {
+        let mut state = self.lock();
+
+        if state.running {
+            state.running = false;
+            state.finished += 1;
+            self.changed.notify_all();
+        }
+    }
+}
+
+impl Clock for RefreshControl {
+    fn now(&self) -> i64 {
+        get_time().sec
+    }
+
+    /// Sleeps until the time is over or a refresh is requested, a refresh that is already requested doesn't wait at all
+    fn sleep(&self, seconds: u64) -> bool {
+        let deadline = Instant::now() + Duration::new(seconds, 0);
+        let mut state = self.lock();
+
+        while !state.requested {
+            let now = Instant::now();
+            if now >= deadline {
+                return false;
+            }
+            state = match self.changed.wait_timeout(state, deadline - now) {
+                Ok((state, _)) => state,
+                Err(err) => err.into_inner().0
+            };
+        }
+        state.requested = false;
+        state.running = true;
+        true
+    }
+}
+
+#[test]
+fn test_refresh_request() {
+    let control = RefreshControl::new(10);
+
+    assert_eq!(control.request(1000), Ok(1));
+    // The status thread has not picked it up yet
+    assert_eq!(control.request(1020), Err(RefreshError::Running));
+    assert!(control.sleep(3600));
+    assert_eq!(control.request(1020), Err(RefreshError::Running));
+    control.finish();
+    assert!(control.wait_finished(1, Duration::new(0, 0)));
+
+    assert_eq!(control.request(1005), Err(RefreshError::TooSoon(5)));
+    assert_eq!(RefreshError::TooSoon(5).to_string(), "the last refresh was too recent, try again in 5 sec.");
+    assert_eq!(control.request(1010), Ok(2));
+    assert!(!control.wait_finished(2, Duration::from_millis(10)));
+}
+
+#[test]
+fn test_refresh_finish_without_request() {
+    let control = RefreshControl::new(0);
+
+    // Regular updates don't count as refresh
+    assert!(!control.sleep(0));
+    control.finish();
+    assert_eq!(control.request(1000), Ok(1));
+}
+
+#[test]
+fn test_refresh_interrupts_sleep() {
+    use std::sync::Arc;
+    use std::thread;
+
+    let control = Arc::new(RefreshControl::new(0));
+    let sleeper = control.clone();
+    let start = Instant::now();
+    let handle = thread::spawn(move || {
+        let interrupted = sleeper.sleep(3600);
+        sleeper.finish();
+        interrupted
+    });
+
+    let refresh = control.request(1000).unwrap();
+    assert!(control.wait_finished(refresh, Duration::new(10, 0)));
+    assert!(handle.join().unwrap());
+    assert!(start.elapsed() < Duration::new(10, 0));
+}
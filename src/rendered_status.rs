@@ -0,0 +1,124 @@
+//! The SLURM status rendered as HTML, JSON and plain text
+//! All three representations are rendered from the same snapshot once per update, so requests only have to send the bytes
+
+// System modules:
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+
+// Internal modules:
+use slurm_status::{SlurmStatus, PageOptions, status_to_html, status_to_json, status_to_text};
+use compression::CompressedPage;
+use configuration::OutputFormat;
+use status_filter::StatusFilter;
+
+/// The representations of one snapshot of the SLURM status, each one compressed if it's big enough
+#[derive(Debug, Clone, PartialEq)]
+pub struct RenderedStatus {
+    /// The HTML page with the default page options
+    pub html: CompressedPage,
+    /// The JSON API response, without filter
+    pub json: CompressedPage,
+    /// The plain text report, without filter
+    pub text: CompressedPage,
+    /// Hash of all representations, changes whenever one of them changes
+    pub etag: String,
+    /// Error of the HTML template, the HTML page is empty in that case and requests get an error
+    pub html_error: Option<String>
+}
+
+impl RenderedStatus {
+    /// Create an empty RenderedStatus object, used before the first update
+    pub fn new() -> RenderedStatus {
+        RenderedStatus::from_strings(String::new(), String::new(), String::new())
+    }
+
+    /// Render all representations from the given status
+    /// A template error is logged and kept, the JSON and text representations are still rendered
+    /// The JSON and text contain all jobs of the team of the page options (--users), including the finished ones
+    pub fn render(status: &SlurmStatus, options: &PageOptions) -> RenderedStatus {
+        let filter = StatusFilter::for_team(&options.filter.team);
+        let (html, html_error) = match status_to_html(status, options) {
+            Ok(html) => (html, None),
+            Err(err) => {
+                error!("Could not render the status page: {}", err);
+                (String::new(), Some(err))
+            }
+        };
+
+        RenderedStatus {
+            html_error: html_error,
+            .. RenderedStatus::from_strings(html, status_to_json(status, &filter), status_to_text(status, &filter))
+        }
+    }
+
+    // Private helper function to compress the representations and calculate the etag
+    fn from_strings(html: String, json: String, text: String) -> RenderedStatus {
+        let mut hasher = DefaultHasher::new();
+        html.hash(&mut hasher);
+        json.hash(&mut hasher);
+        text.hash(&mut hasher);
+
+        RenderedStatus {
+            html: CompressedPage::new(html),
+            json: CompressedPage::new(json),
+            text: CompressedPage::new(text),
+            etag: format!("{:016x}", hasher.finish()),
+            html_error: None
+        }
+    }
+
+    /// Returns the representation for the given format
+    pub fn get(&self, format: OutputFormat) -> &CompressedPage {
+        match format {
+            OutputFormat::Html => &self.html,
+            OutputFormat::Json => &self.json,
+            OutputFormat::Text => &self.text
+        }
+    }
+
+    /// Returns the entity tag of the given representation, including the quotes of the ETag header
+    /// Each representation gets its own tag, since they have different content
+    pub fn entity_tag(&self, format: OutputFormat) -> String {
+        let suffix = match format {
+            OutputFormat::Html => "html",
+            OutputFormat::Json => "json",
+            OutputFormat::Text => "text"
+        };
+
+        format!("\"{}-{}\"", self.etag, suffix)
+    }
+}
+
+#[test]
+fn test_rendered_status_same_snapshot() {
+    use sinfo_util::get_partition_node_info_test;
+    use squeue_util::get_job_info_test;
+
+    let mut status = SlurmStatus::new();
+    status.node_info = get_partition_node_info_test();
+    status.job_info = get_job_info_test();
+    status.last_update = "2000.01.01 - 09:00".to_string();
+    let rendered = RenderedStatus::render(&status, &PageOptions::default());
+
+    assert!(rendered.html.plain.contains("Last update: 2000.01.01 - 09:00"));
+    assert!(rendered.json.plain.contains("\"last_update\":\"2000.01.01 - 09:00\""));
+    assert!(rendered.text.plain.starts_with("Slurm Inspector, last update: 2000.01.01 - 09:00\n"));
+    assert_eq!(rendered.get(OutputFormat::Json), &rendered.json);
+}
+
+#[test]
+fn test_rendered_status_etag() {
+    let empty = RenderedStatus::new();
+    let mut status = SlurmStatus::new();
+    status.last_update = "2000.01.01 - 09:00".to_string();
+    let options = PageOptions::default();
+    let first = RenderedStatus::render(&status, &options);
+
+    assert_eq!(first.etag, RenderedStatus::render(&status, &options).etag);
+    assert!(first.etag != empty.etag);
+    assert_eq!(first.entity_tag(OutputFormat::Html), format!("\"{}-html\"", first.etag));
+    assert!(first.entity_tag(OutputFormat::Json) != first.entity_tag(OutputFormat::Text));
+
+    status.last_update = "2000.01.01 - 09:01".to_string();
+    assert!(RenderedStatus::render(&status, &options).etag != first.etag);
+}
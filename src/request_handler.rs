@@ -1,16 +1,91 @@
 //! The Iron web framework request handler and helper function
 
 // System modules:
-use std::sync::Mutex;
+use std::cmp::min;
+use std::time::Duration;
+use std::collections::BTreeMap;
+use std::io::Read;
+use std::net::IpAddr;
+use std::panic::{AssertUnwindSafe, catch_unwind};
 
 // External modules:
 use iron::prelude::{Request, IronResult, Response};
-use iron::headers::{Headers, ContentType};
-use iron::mime::{Mime, TopLevel, SubLevel};
+use iron::headers::{Headers, ContentType, ContentLength, ContentEncoding, Accept, AcceptEncoding, Encoding, CacheControl, CacheDirective, QualityItem,
+    Location};
+use iron::mime::{Mime, TopLevel, SubLevel, Attr, Value};
+use iron::method::Method;
 use iron::status;
+use time::{get_time, strftime, now};
+use rustc_serialize::json::{Json, ToJson};
 
 // Internal modules:
-use slurm_status::{status_to_html, SlurmStatus};
+use slurm_status::{SlurmStatus, PageOptions, status_to_html, status_to_prometheus, status_to_text, status_to_json, status_to_json_paged, shares_to_json, priorities_to_json, licenses_to_json, qos_to_json, usage_to_json, users_to_json, events_to_json,
+    partitions_to_json, summary_to_json, create_runner, update_shared_status, update_slurm_status_parts};
+use compression::{CompressedPage, PageEncoding, choose_encoding};
+use configuration::{Configuration, OutputFormat, parse_page_refresh, normalize_url_prefix};
+use rendered_status::RenderedStatus;
+use shared_status::SharedStatus;
+use static_files::{DEFAULT_CSS, FAVICON, ROBOTS_TXT, read_custom_css};
+use status_filter::{StatusFilter, parse_job_states, parse_node_states};
+use csv_export::{jobs_to_csv, nodes_to_csv, export_file_name};
+use health::{HealthReport, health_report, unknown_health_json};
+use pagination::parse_pagination;
+use refresh::{RefreshControl, RefreshError};
+use status_stream::{Subscribers, StatusEvent, EventStream, MAX_SUBSCRIBERS};
+use status_changes::changes_to_json;
+use request_limit::RequestLimiter;
+use columns::{Column, job_columns, node_columns, parse_columns};
+use build_info::version_to_json;
+use config_view::config_to_json;
+use user_page::{user_to_html, user_to_json};
+use partition_page::{partition_to_html, partition_to_json};
+use template::escape_html;
+use admin_actions::{AdminAction, AdminError, admin_route, authorize, remote_user_header, run_admin_action, notice_query, admin_notice,
+    confirmation_page, simulate_node_action, csrf_secret, csrf_token, check_admin_post, CSRF_TOKEN_FIELD};
+use update_schedule::DueUpdates;
+use wait_times::TIME_FORMAT;
+use security::{add_security_headers, client_ip, is_allowed};
+use cors::{add_cors_headers, is_api_route, preflight_response};
+use history::HistoryEntry;
+use history_db::HistoryDb;
+use job_sort::parse_job_sort;
+use error_log::{ErrorCategory, ErrorRecorder, error_recorder, recent_errors_page};
+
+/// Browsers may cache the default stylesheet for one day
+const DEFAULT_CSS_MAX_AGE: u32 = 86400;
+
+/// The custom stylesheet may change without restarting, so browsers only cache it for one hour
+const CUSTOM_CSS_MAX_AGE: u32 = 3600;
+
+/// The favicon and robots.txt are embedded in the binary, so browsers may cache them for one week
+const EMBEDDED_FILE_MAX_AGE: u32 = 604800;
+
+/// A POST /refresh waits at most this many seconds for the new status
+const REFRESH_TIMEOUT: u64 = 120;
+
+/// Maximum size of the form of an admin action in bytes, the rest of a larger body is ignored
+const MAX_FORM_SIZE: u64 = 4096;
+
+// Private helper function that returns the mime type of a text response, all text is sent as UTF-8
+// Without the charset some proxies and browsers guess Latin-1 and mangle non-ASCII node and user names
+fn utf8_mime(top_level: TopLevel, sub_level: SubLevel) -> Mime {
+    Mime(top_level, sub_level, vec![(Attr::Charset, Value::Utf8)])
+}
+
+// Private helper function to set the body of a response together with its Content-Length, which is also used by the access log
+fn set_body(res: &mut Response, body: Vec<u8>) {
+    res.headers.set(ContentLength(body.len() as u64));
+    res.body = Some(Box::new(body));
+}
+
+// Private helper function that answers a HEAD request: the headers of the GET response, but without its body
+// The Content-Length stays the length of the body, an empty body is sent since Iron would set it to 0 without one
+fn without_body(mut res: Response) -> Response {
+    if res.body.is_some() {
+        res.body = Some(Box::new(Vec::<u8>::new()));
+    }
+    res
+}
 
 /// Accepts a HTML string and returns a IronResult response with correct mime type
 fn string_to_response(page: &str) -> IronResult<Response> {
@@ -18,20 +93,1123 @@ fn string_to_response(page: &str) -> IronResult<Response> {
 
     res.status = Some(status::Ok);
     res.headers = Headers::new();
-    res.headers.set(ContentType(Mime(TopLevel::Text, SubLevel::Html, vec![])));
-    res.body = Some(Box::new(page.to_string()));
+    res.headers.set(ContentType(utf8_mime(TopLevel::Text, SubLevel::Html)));
+    set_body(&mut res, page.as_bytes().to_vec());
+
+    Ok(res)
+}
+
+/// Accepts a rendered page and returns a IronResult response with the given mime type, compressed if the client supports it
+fn page_to_response(page: &CompressedPage, mime: Mime, encoding: Option<PageEncoding>) -> IronResult<Response> {
+    let mut res = Response::new();
+
+    res.status = Some(status::Ok);
+    res.headers = Headers::new();
+    res.headers.set(ContentType(mime));
+
+    match encoding.and_then(|enc| page.encoded(enc).map(|body| (enc, body))) {
+        Some((enc, body)) => {
+            res.headers.set(ContentEncoding(vec![
+                match enc {
+                    PageEncoding::Gzip => Encoding::Gzip,
+                    PageEncoding::Deflate => Encoding::Deflate
+                }
+            ]));
+            res.headers.set_raw("Vary", vec![b"Accept-Encoding".to_vec()]);
+            set_body(&mut res, body.clone());
+        },
+        None => set_body(&mut res, page.plain.clone().into_bytes())
+    }
+
+    Ok(res)
+}
+
+/// Returns "500 Internal Server Error" if the templates couldn't render the page, the error is logged where it happened
+fn render_error_response() -> IronResult<Response> {
+    let mut res = string_to_response("<h1>Could not render the page!</h1>")?;
+    res.status = Some(status::InternalServerError);
+    Ok(res)
+}
+
+/// Returns the mime type of the given output format
+fn format_mime(format: OutputFormat) -> Mime {
+    match format {
+        OutputFormat::Html => utf8_mime(TopLevel::Text, SubLevel::Html),
+        OutputFormat::Json => utf8_mime(TopLevel::Application, SubLevel::Json),
+        OutputFormat::Text => utf8_mime(TopLevel::Text, SubLevel::Plain)
+    }
+}
+
+// Private helper function to check if one of the entity tags of the If-None-Match header matches the given tag
+// Weak tags ("W/...") are compared like strong tags, since the representation is sent as it is
+fn etag_matches(if_none_match: &[Vec<u8>], entity_tag: &str) -> bool {
+    if_none_match.iter()
+        .flat_map(|value| String::from_utf8_lossy(value).split(',').map(|tag| tag.trim().to_string()).collect::<Vec<String>>())
+        .any(|tag| tag == "*" || tag.trim_start_matches("W/") == entity_tag)
+}
+
+/// Returns the cached representation of the status in the given format, or "304 Not Modified" if the client already has it
+fn rendered_to_response(rendered: &RenderedStatus, format: OutputFormat, encoding: Option<PageEncoding>, if_none_match: &[Vec<u8>]) -> IronResult<Response> {
+    if format == OutputFormat::Html && rendered.html_error.is_some() {
+        return render_error_response();
+    }
+
+    let entity_tag = rendered.entity_tag(format);
+
+    let mut res = if etag_matches(if_none_match, &entity_tag) {
+        let mut res = Response::new();
+        res.status = Some(status::NotModified);
+        res.headers = Headers::new();
+        res
+    } else {
+        page_to_response(rendered.get(format), format_mime(format), encoding)?
+    };
+    res.headers.set_raw("ETag", vec![entity_tag.into_bytes()]);
+
+    Ok(res)
+}
+
+/// Accepts a plain text string and returns a IronResult response with correct mime type
+fn text_to_response(text: &str) -> IronResult<Response> {
+    let mut res = Response::new();
+
+    res.status = Some(status::Ok);
+    res.headers = Headers::new();
+    res.headers.set(ContentType(utf8_mime(TopLevel::Text, SubLevel::Plain)));
+    set_body(&mut res, text.as_bytes().to_vec());
+
+    Ok(res)
+}
+
+/// Accepts a JSON string and returns a IronResult response with correct mime type
+fn json_to_response(json: &str) -> IronResult<Response> {
+    let mut res = Response::new();
+
+    res.status = Some(status::Ok);
+    res.headers = Headers::new();
+    res.headers.set(ContentType(utf8_mime(TopLevel::Application, SubLevel::Json)));
+    set_body(&mut res, json.as_bytes().to_vec());
+
+    Ok(res)
+}
+
+/// Accepts the time of the last update after a refresh and returns "202 Accepted" with a small JSON body
+/// finished is false if the refresh took longer than REFRESH_TIMEOUT, the time is then still the one of the previous update
+fn refresh_to_response(last_update: &str, finished: bool) -> IronResult<Response> {
+    let mut result = BTreeMap::new();
+    result.insert("last_update".to_string(), last_update.to_json());
+    result.insert("finished".to_string(), finished.to_json());
+
+    let mut res = json_to_response(&Json::Object(result).to_string())?;
+    res.status = Some(status::Accepted);
+    res.headers.set(CacheControl(vec![CacheDirective::NoCache]));
 
     Ok(res)
 }
 
+/// Returns "429 Too Many Requests" if a refresh is refused, with Retry-After if the time to wait is known
+fn refresh_refused_response(err: &RefreshError) -> IronResult<Response> {
+    let mut result = BTreeMap::new();
+    result.insert("error".to_string(), err.to_string().to_json());
+
+    let mut res = json_to_response(&Json::Object(result).to_string())?;
+    res.status = Some(status::TooManyRequests);
+    if let RefreshError::TooSoon(seconds) = *err {
+        res.headers.set_raw("Retry-After", vec![seconds.to_string().into_bytes()]);
+    }
+
+    Ok(res)
+}
+
+/// Accepts an event stream and returns a IronResult response that is written until the client closes the connection
+/// There is no Content-Length, and proxies like nginx are asked not to buffer the events
+fn events_to_response(stream: EventStream) -> IronResult<Response> {
+    let mut res = Response::new();
+
+    res.status = Some(status::Ok);
+    res.headers = Headers::new();
+    res.headers.set(ContentType(Mime(TopLevel::Text, SubLevel::EventStream, vec![])));
+    res.headers.set(CacheControl(vec![CacheDirective::NoCache]));
+    res.headers.set_raw("X-Accel-Buffering", vec![b"no".to_vec()]);
+    res.body = Some(Box::new(stream));
+
+    Ok(res)
+}
+
+/// Returns "303 See Other" to the status page, so the "Refresh now" button of the page ends up on the new status
+fn redirect_to_status_page(url_prefix: &str) -> IronResult<Response> {
+    redirect_to(&format!("{}/", url_prefix))
+}
+
+// Private helper function that sends the browser to the given location with "303 See Other", so a reload doesn't repeat the POST
+fn redirect_to(location: &str) -> IronResult<Response> {
+    let mut res = Response::new();
+
+    res.status = Some(status::SeeOther);
+    res.headers = Headers::new();
+    res.headers.set(Location(location.to_string()));
+    set_body(&mut res, Vec::new());
+
+    Ok(res)
+}
+
+/// Returns the result of an admin action: browsers are sent back to the status page (below the given URL prefix) with a notice,
+/// other clients get "200 OK" or "500 Internal Server Error" with a small JSON body
+fn admin_action_response(action: &AdminAction, result: &Result<(), String>, redirect_prefix: Option<&str>) -> IronResult<Response> {
+    if let Some(url_prefix) = redirect_prefix {
+        return redirect_to(&format!("{}/?{}", url_prefix, notice_query(action, result.is_ok())));
+    }
+
+    let mut json = BTreeMap::new();
+    json.insert("action".to_string(), action.to_string().to_json());
+    json.insert("success".to_string(), result.is_ok().to_json());
+    json.insert("message".to_string(), action.notice(result.is_ok()).to_json());
+
+    let mut res = json_to_response(&Json::Object(json).to_string())?;
+    if result.is_err() {
+        res.status = Some(status::InternalServerError);
+    }
+    res.headers.set(CacheControl(vec![CacheDirective::NoCache]));
+
+    Ok(res)
+}
+
+/// Returns why an admin action was refused as JSON: "404 Not Found" if the admin actions are disabled,
+/// "403 Forbidden" without authenticated user and "400 Bad Request" for an invalid argument or reason
+fn admin_refused_response(err: &AdminError) -> IronResult<Response> {
+    let mut result = BTreeMap::new();
+    result.insert("error".to_string(), err.to_string().to_json());
+
+    let mut res = json_to_response(&Json::Object(result).to_string())?;
+    res.status = Some(match *err {
+        AdminError::Disabled => status::NotFound,
+        AdminError::NotAuthenticated | AdminError::ForeignOrigin | AdminError::InvalidToken => status::Forbidden,
+        AdminError::InvalidArgument(_) | AdminError::InvalidReason(_) => status::BadRequest
+    });
+
+    Ok(res)
+}
+
+/// Returns "404 Not Found" with the given message as HTML page or JSON, the message is escaped since it contains a name from the URL
+fn not_found_response(message: &str, format: OutputFormat) -> IronResult<Response> {
+    let mut res = match format {
+        OutputFormat::Json => {
+            let mut error = BTreeMap::new();
+            error.insert("error".to_string(), message.to_json());
+            json_to_response(&Json::Object(error).to_string())?
+        },
+        _ => string_to_response(&format!("<h1>{}</h1>", escape_html(message)))?
+    };
+    res.status = Some(status::NotFound);
+    Ok(res)
+}
+
+/// Returns "503 Service Unavailable" when too many requests are handled at the same time, clients may retry after a second
+fn too_many_requests_response() -> IronResult<Response> {
+    let mut res = text_to_response("Too many requests at the same time, try again later!\n")?;
+
+    res.status = Some(status::ServiceUnavailable);
+    res.headers.set_raw("Retry-After", vec![b"1".to_vec()]);
+
+    Ok(res)
+}
+
+/// Returns the error as JSON with the given status, for example "400 Bad Request"
+fn json_error_response(message: &str, code: status::Status) -> IronResult<Response> {
+    let mut result = BTreeMap::new();
+    result.insert("error".to_string(), message.to_json());
+
+    let mut res = json_to_response(&Json::Object(result).to_string())?;
+    res.status = Some(code);
+
+    Ok(res)
+}
+
+/// Returns "403 Forbidden" for clients that are not in the allow-list of --allowed-ips
+fn forbidden_response() -> IronResult<Response> {
+    let mut res = text_to_response("Access denied!\n")?;
+
+    res.status = Some(status::Forbidden);
+
+    Ok(res)
+}
+
+/// Accepts a health report and returns a IronResult response, "503 Service Unavailable" if the service is not healthy
+fn health_to_response(report: &HealthReport) -> IronResult<Response> {
+    health_json_to_response(&report.to_json(), report.is_healthy())
+}
+
+// Private helper function that returns the body of /health with 200 if the service is healthy and 503 otherwise
+fn health_json_to_response(body: &Json, healthy: bool) -> IronResult<Response> {
+    let mut res = Response::new();
+
+    res.status = Some(if healthy { status::Ok } else { status::ServiceUnavailable });
+    res.headers = Headers::new();
+    res.headers.set(ContentType(utf8_mime(TopLevel::Application, SubLevel::Json)));
+    res.headers.set(CacheControl(vec![CacheDirective::NoCache]));
+    set_body(&mut res, body.to_string().into_bytes());
+
+    Ok(res)
+}
+
+/// Accepts a CSV string and returns a IronResult response that browsers offer as download with the given file name
+fn csv_to_response(csv: &str, file_name: &str) -> IronResult<Response> {
+    let mut res = Response::new();
+
+    res.status = Some(status::Ok);
+    res.headers = Headers::new();
+    res.headers.set(ContentType(utf8_mime(TopLevel::Text, SubLevel::Ext("csv".to_string()))));
+    res.headers.set_raw("Content-Disposition", vec![format!("attachment; filename=\"{}\"", file_name).into_bytes()]);
+    set_body(&mut res, csv.as_bytes().to_vec());
+
+    Ok(res)
+}
+
+/// Accepts a stylesheet and returns a IronResult response with correct mime type and cache headers
+fn css_to_response(css: &str, max_age: u32) -> IronResult<Response> {
+    let mut res = Response::new();
+
+    res.status = Some(status::Ok);
+    res.headers = Headers::new();
+    res.headers.set(ContentType(utf8_mime(TopLevel::Text, SubLevel::Css)));
+    res.headers.set(CacheControl(vec![CacheDirective::Public, CacheDirective::MaxAge(max_age)]));
+    set_body(&mut res, css.as_bytes().to_vec());
+
+    Ok(res)
+}
+
+/// Accepts an embedded file and returns a IronResult response with the given mime type and cache headers for one week
+fn embedded_file_response(content: &[u8], mime: Mime) -> IronResult<Response> {
+    let mut res = Response::new();
+
+    res.status = Some(status::Ok);
+    res.headers = Headers::new();
+    res.headers.set(ContentType(mime));
+    res.headers.set(CacheControl(vec![CacheDirective::Public, CacheDirective::MaxAge(EMBEDDED_FILE_MAX_AGE)]));
+    set_body(&mut res, content.to_vec());
+
+    Ok(res)
+}
+
+/// Returns the custom stylesheet, or an empty stylesheet if it's not configured or can't be read
+fn custom_css_response(custom_css: &Option<String>) -> IronResult<Response> {
+    let css = custom_css.as_ref().and_then(|path| read_custom_css(path)).unwrap_or(String::new());
+
+    css_to_response(&css, CUSTOM_CSS_MAX_AGE)
+}
+
+#[test]
+fn test_css_to_response() {
+    let res = css_to_response(DEFAULT_CSS, DEFAULT_CSS_MAX_AGE).unwrap();
+    assert_eq!(res.status, Some(status::Ok));
+    assert_eq!(res.headers.get::<ContentType>(), Some(&ContentType(utf8_mime(TopLevel::Text, SubLevel::Css))));
+    assert_eq!(res.headers.get::<CacheControl>(), Some(&CacheControl(vec![CacheDirective::Public, CacheDirective::MaxAge(DEFAULT_CSS_MAX_AGE)])));
+}
+
+#[test]
+fn test_embedded_file_responses() {
+    let svg = Mime(TopLevel::Image, SubLevel::Ext("svg+xml".to_string()), vec![]);
+    let res = embedded_file_response(FAVICON, svg.clone()).unwrap();
+    assert_eq!(res.status, Some(status::Ok));
+    assert_eq!(res.headers.get::<ContentType>(), Some(&ContentType(svg)));
+    assert_eq!(res.headers.get::<CacheControl>(), Some(&CacheControl(vec![CacheDirective::Public, CacheDirective::MaxAge(EMBEDDED_FILE_MAX_AGE)])));
+    assert_eq!(res.headers.get::<ContentLength>(), Some(&ContentLength(FAVICON.len() as u64)));
+
+    let plain = Mime(TopLevel::Text, SubLevel::Plain, vec![(Attr::Charset, Value::Utf8)]);
+    let res = embedded_file_response(ROBOTS_TXT.as_bytes(), plain.clone()).unwrap();
+    assert_eq!(res.status, Some(status::Ok));
+    assert_eq!(res.headers.get::<ContentType>(), Some(&ContentType(plain)));
+    assert_eq!(res.headers.get::<CacheControl>(), Some(&CacheControl(vec![CacheDirective::Public, CacheDirective::MaxAge(EMBEDDED_FILE_MAX_AGE)])));
+    assert!(ROBOTS_TXT.contains("Disallow: /\n"));
+}
+
+#[test]
+fn test_custom_css_response_fallback() {
+    for custom_css in vec![None, Some("/this/file/does/not/exist.css".to_string())] {
+        let res = custom_css_response(&custom_css).unwrap();
+        assert_eq!(res.status, Some(status::Ok));
+        assert_eq!(res.headers.get::<ContentType>(), Some(&ContentType(utf8_mime(TopLevel::Text, SubLevel::Css))));
+    }
+}
+
+/// Public helper function to get the value of a parameter from the query string of a request
+/// Returns None if the parameter is not present
+pub fn get_query_param(query: Option<&str>, name: &str) -> Option<String> {
+    query.and_then(|query| {
+        query.split('&')
+            .map(|pair| {
+                let mut items = pair.splitn(2, '=');
+                (items.next().unwrap_or(""), items.next().unwrap_or(""))
+            })
+            .find(|&(key, _)| key == name)
+            .map(|(_, value)| decode_query_value(value))
+    })
+}
+
+// Private helper function that returns the value of an ASCII hex digit, 0 for any other byte
+fn hex_digit_value(digit: u8) -> u8 {
+    (digit as char).to_digit(16).unwrap_or(0) as u8
+}
+
+// Private helper function to decode a percent-encoded query value, '+' is decoded as space
+// Invalid escape sequences are kept as they are
+fn decode_query_value(value: &str) -> String {
+    let bytes = value.as_bytes();
+    let mut result: Vec<u8> = Vec::new();
+    let mut index = 0;
+
+    while index < bytes.len() {
+        match bytes[index] {
+            b'+' => result.push(b' '),
+            // Only two hex digits are decoded, the bytes after '%' can be the start of a multi-byte character or a sign
+            b'%' if index + 2 < bytes.len() && bytes[index + 1].is_ascii_hexdigit() && bytes[index + 2].is_ascii_hexdigit() => {
+                result.push(hex_digit_value(bytes[index + 1]) * 16 + hex_digit_value(bytes[index + 2]));
+                index += 2;
+            },
+            byte => result.push(byte)
+        }
+        index += 1;
+    }
+
+    String::from_utf8_lossy(&result).to_string()
+}
+
+/// Pages that only show the status of a single user or partition
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DetailPage {
+    User,
+    Partition
+}
+
+/// Paths of the detail pages as HTML page and as JSON, the name of the user or partition follows
+const DETAIL_ROUTES: [(&'static str, DetailPage, OutputFormat); 4] = [
+    ("user/", DetailPage::User, OutputFormat::Html),
+    ("api/users/", DetailPage::User, OutputFormat::Json),
+    ("partition/", DetailPage::Partition, OutputFormat::Html),
+    ("api/partitions/", DetailPage::Partition, OutputFormat::Json)
+];
+
+/// Public helper function that parses the path of a detail page (without URL prefix), for example "user/willi" or "api/partitions/gpu"
+/// Returns the page, the format and the decoded name, None for other paths and without name
+pub fn detail_route(path: &str) -> Option<(DetailPage, OutputFormat, String)> {
+    DETAIL_ROUTES.iter()
+        .find(|&&(prefix, _, _)| path.starts_with(prefix) && path.len() > prefix.len())
+        .map(|&(prefix, page, format)| (page, format, decode_path_segment(&path[prefix.len()..])))
+}
+
+#[test]
+fn test_detail_route() {
+    assert_eq!(detail_route("user/user01"), Some((DetailPage::User, OutputFormat::Html, "user01".to_string())));
+    assert_eq!(detail_route("api/users/j%C3%BCrgen"), Some((DetailPage::User, OutputFormat::Json, "jürgen".to_string())));
+    assert_eq!(detail_route("partition/gpu"), Some((DetailPage::Partition, OutputFormat::Html, "gpu".to_string())));
+    assert_eq!(detail_route("api/partitions/esd"), Some((DetailPage::Partition, OutputFormat::Json, "esd".to_string())));
+    assert_eq!(detail_route("api/partitions"), None);
+    assert_eq!(detail_route("user/"), None);
+    assert_eq!(detail_route("api/status"), None);
+}
+
+/// Public helper function to decode a percent-encoded segment of the request path, unlike in the query '+' is kept
+pub fn decode_path_segment(segment: &str) -> String {
+    decode_query_value(&segment.replace('+', "%2B"))
+}
+
+#[test]
+fn test_decode_path_segment() {
+    assert_eq!(decode_path_segment("user01"), "user01");
+    assert_eq!(decode_path_segment("j%C3%BCrgen"), "jürgen");
+    assert_eq!(decode_path_segment("a+b%20c"), "a+b c");
+    assert_eq!(decode_path_segment("%3Cb%3E"), "<b>");
+}
+
+#[test]
+fn test_get_query_param() {
+    assert_eq!(get_query_param(None, "refresh"), None);
+    assert_eq!(get_query_param(Some(""), "refresh"), None);
+    assert_eq!(get_query_param(Some("refresh=30"), "refresh"), Some("30".to_string()));
+    assert_eq!(get_query_param(Some("foo=1&refresh=0"), "refresh"), Some("0".to_string()));
+    assert_eq!(get_query_param(Some("refresh"), "refresh"), Some("".to_string()));
+    assert_eq!(get_query_param(Some("foo=1&bar=2"), "refresh"), None);
+}
+
+#[test]
+fn test_get_query_param_decode() {
+    assert_eq!(get_query_param(Some("user=willi%20k"), "user"), Some("willi k".to_string()));
+    assert_eq!(get_query_param(Some("user=willi+k"), "user"), Some("willi k".to_string()));
+    assert_eq!(get_query_param(Some("user=%C3%BC"), "user"), Some("\u{fc}".to_string()));
+    assert_eq!(get_query_param(Some("user=100%"), "user"), Some("100%".to_string()));
+    assert_eq!(get_query_param(Some("user=%zz1"), "user"), Some("%zz1".to_string()));
+    assert_eq!(get_query_param(Some("user=%3c%3E"), "user"), Some("<>".to_string()));
+    // Multi-byte characters and signs after '%' are not decoded
+    assert_eq!(get_query_param(Some("reason=%a\u{e9}"), "reason"), Some("%a\u{e9}".to_string()));
+    assert_eq!(get_query_param(Some("reason=%\u{e9}"), "reason"), Some("%\u{e9}".to_string()));
+    assert_eq!(get_query_param(Some("reason=%+1"), "reason"), Some("% 1".to_string()));
+    assert_eq!(get_query_param(Some("reason=a%4"), "reason"), Some("a%4".to_string()));
+}
+
+/// Public helper function that returns the URL prefix of the links on the page for this request
+/// The X-Forwarded-Prefix header of the reverse proxy is only used with --trust-proxy, otherwise it could be set by anyone
+pub fn request_url_prefix(forwarded_prefix: Option<&str>, config: &Configuration) -> String {
+    match forwarded_prefix {
+        Some(prefix) if config.trust_proxy => normalize_url_prefix(prefix),
+        _ => config.url_prefix.clone()
+    }
+}
+
+// Private helper function that reads the X-Forwarded-Prefix header and returns the URL prefix of the request
+fn get_url_prefix(req: &Request, config: &Configuration) -> String {
+    let forwarded_prefix = req.headers.get_raw("X-Forwarded-Prefix")
+        .and_then(|values| values.first())
+        .and_then(|value| String::from_utf8(value.clone()).ok());
+
+    request_url_prefix(forwarded_prefix.as_ref().map(|prefix| &prefix[..]), config)
+}
+
+/// Public helper function that removes the URL prefix from the path of the request (without leading '/', like "api/status")
+/// Proxies that pass the path unchanged and proxies that strip the prefix both work, "slurm" with the prefix "/slurm" is the main page
+pub fn route_path<'a>(path: &'a str, url_prefix: &str) -> &'a str {
+    // The prefix is normalized, so it's either empty or starts with '/'
+    let prefix = if url_prefix.starts_with('/') { &url_prefix[1..] } else { url_prefix };
+
+    if prefix.is_empty() || !path.starts_with(prefix) {
+        return path;
+    }
+    match &path[prefix.len()..] {
+        "" => "",
+        rest if rest.starts_with('/') => &rest[1..],
+        // Only whole path segments are removed: "slurmdb" doesn't start with the prefix "/slurm"
+        _ => path
+    }
+}
+
+#[test]
+fn test_route_path() {
+    assert_eq!(route_path("api/status", ""), "api/status");
+    assert_eq!(route_path("slurm/api/status", "/slurm"), "api/status");
+    assert_eq!(route_path("slurm/static/style.css", "/slurm"), "static/style.css");
+    assert_eq!(route_path("slurm", "/slurm"), "");
+    assert_eq!(route_path("slurm/", "/slurm"), "");
+    assert_eq!(route_path("tools/slurm/health", "/tools/slurm"), "health");
+    // The proxy has already removed the prefix
+    assert_eq!(route_path("api/status", "/slurm"), "api/status");
+    assert_eq!(route_path("", "/slurm"), "");
+    assert_eq!(route_path("slurmdb/api/status", "/slurm"), "slurmdb/api/status");
+}
+
+#[test]
+fn test_request_url_prefix() {
+    let config = Configuration{ url_prefix: "/slurm".to_string(), .. Configuration::default() };
+    assert_eq!(request_url_prefix(None, &config), "/slurm");
+    // The header is ignored without --trust-proxy
+    assert_eq!(request_url_prefix(Some("/other"), &config), "/slurm");
+
+    let config = Configuration{ trust_proxy: true, .. config };
+    assert_eq!(request_url_prefix(Some("/other/"), &config), "/other");
+    assert_eq!(request_url_prefix(Some(""), &config), "");
+    assert_eq!(request_url_prefix(None, &config), "/slurm");
+}
+
+/// Public helper function that returns the user whose jobs are highlighted on the page: ?me=<user> beats the header of --remote-user-header
+/// Empty values are ignored, and in anonymize mode nobody is highlighted, since it would reveal who owns which jobs
+pub fn request_page_user(me: Option<&str>, remote_user: Option<&str>, config: &Configuration) -> Option<String> {
+    if config.anonymize {
+        return None;
+    }
+
+    me.map(|user| user.trim()).filter(|user| !user.is_empty())
+        .or_else(|| remote_user.map(|user| user.trim()).filter(|user| !user.is_empty()))
+        .map(|user| user.to_string())
+}
+
+// Private helper function that returns the first value of a header of the request, if it's valid UTF-8
+fn header_value(req: &Request, name: &str) -> Option<String> {
+    req.headers.get_raw(name)
+        .and_then(|values| values.first())
+        .and_then(|value| String::from_utf8(value.clone()).ok())
+}
+
+// Private helper function that reads ?me= and the header of --remote-user-header
+fn get_page_user(req: &Request, query: Option<&str>, config: &Configuration) -> Option<String> {
+    let remote_user = config.remote_user_header.as_ref().and_then(|name| header_value(req, name));
+
+    request_page_user(get_query_param(query, "me").as_ref().map(|me| &me[..]), remote_user.as_ref().map(|user| &user[..]), config)
+}
+
+#[test]
+fn test_request_page_user() {
+    let config = Configuration::default();
+    assert_eq!(request_page_user(None, None, &config), None);
+    assert_eq!(request_page_user(Some("user01"), None, &config), Some("user01".to_string()));
+    assert_eq!(request_page_user(None, Some(" user02 "), &config), Some("user02".to_string()));
+    // The query beats the header, empty values are ignored
+    assert_eq!(request_page_user(Some("user01"), Some("user02"), &config), Some("user01".to_string()));
+    assert_eq!(request_page_user(Some(""), Some("user02"), &config), Some("user02".to_string()));
+    assert_eq!(request_page_user(Some(" "), Some(""), &config), None);
+
+    let config = Configuration{ anonymize: true, .. config };
+    assert_eq!(request_page_user(Some("user01"), Some("user02"), &config), None);
+}
+
+// Private helper function to build the filter from the query string of a request
+// Filtering by user is disabled in anonymize mode, since it would reveal who owns which jobs
+// "state" is the short form of "job_state"
+// ?all_jobs=1 shows the finished jobs and ?all_jobs=0 hides them, without it hide_finished is the default:
+// the web page hides them, the JSON API and the exports return everything
+fn get_status_filter(query: Option<&str>, config: &Configuration, hide_finished: bool) -> StatusFilter {
+    let non_empty = |value: String| if value.len() == 0 { None } else { Some(value) };
+    let job_state = get_query_param(query, "job_state").or_else(|| get_query_param(query, "state")).unwrap_or(String::new());
+    let (job_states, mut unknown_states) = parse_job_states(&job_state);
+    let (node_states, unknown_node_states) = parse_node_states(&get_query_param(query, "node_state").unwrap_or(String::new()));
+    unknown_states.extend(unknown_node_states);
+
+    StatusFilter {
+        user: if config.anonymize { None } else { get_query_param(query, "user").and_then(&non_empty) },
+        team: config.users.clone(),
+        cluster: get_query_param(query, "cluster").and_then(&non_empty),
+        partition: None,
+        job_states: job_states,
+        node_states: node_states,
+        unknown_states: unknown_states,
+        hide_finished: get_query_param(query, "all_jobs").map_or(hide_finished, |value| value == "0")
+    }
+}
+
+/// Public helper function to get the shown columns of a table from the query string (?columns= or ?node_columns=)
+/// Without the parameter the configured columns are shown, returns the known and the unknown column ids
+pub fn get_column_selection<R>(query: Option<&str>, name: &str, columns: &[Column<R>], configured: &[String]) -> (Vec<String>, Vec<String>) {
+    match get_query_param(query, name) {
+        Some(value) => parse_columns(&value, columns),
+        None => (configured.to_vec(), Vec::new())
+    }
+}
+
+#[test]
+fn test_get_column_selection() {
+    use columns::job_columns;
+
+    let configured = vec!["job_id".to_string()];
+    assert_eq!(get_column_selection(None, "columns", &job_columns(), &configured), (configured.clone(), Vec::new()));
+    assert_eq!(get_column_selection(Some("columns=user_name%2Cjob_state,size"), "columns", &job_columns(), &configured),
+        (vec!["user_name".to_string(), "job_state".to_string()], vec!["size".to_string()]));
+    // Only unknown columns show all columns
+    assert_eq!(get_column_selection(Some("columns=size"), "columns", &job_columns(), &configured), (Vec::new(), vec!["size".to_string()]));
+}
+
+/// Public function that calls the handler only if the limiter has a permit left, otherwise the request is rejected with 503
+/// The permit is held until the handler returns, the body of a /events stream is written afterwards and is limited by MAX_SUBSCRIBERS
+pub fn limit_request<F>(limiter: &RequestLimiter, handler: F) -> IronResult<Response>
+        where F: FnOnce() -> IronResult<Response> {
+    match limiter.try_acquire() {
+        Some(_permit) => handler(),
+        None => too_many_requests_response()
+    }
+}
+
+/// Public function that records the requests which fail in the error log (/debug/errors), the request line is "GET /path"
+/// A panic of the handler is caught and answered with "500 Internal Server Error" instead of dropping the connection
+pub fn guard_request<F>(req: &mut Request, handler: F) -> IronResult<Response>
+        where F: FnOnce(&mut Request) -> IronResult<Response> {
+    let request_line = format!("{} /{}", req.method, req.url.path.join("/"));
+    guard_response(&request_line, error_recorder(), || handler(req))
+}
+
+/// Public helper function that calls the handler and records a panic or a "500 Internal Server Error" in the given error log
+pub fn guard_response<F>(request_line: &str, recorder: &ErrorRecorder, handler: F) -> IronResult<Response>
+        where F: FnOnce() -> IronResult<Response> {
+    match catch_unwind(AssertUnwindSafe(handler)) {
+        Ok(Ok(res)) => {
+            if res.status == Some(status::InternalServerError) {
+                recorder.record(get_time().sec, ErrorCategory::Http, &format!("{}: 500 Internal Server Error", request_line));
+            }
+            Ok(res)
+        },
+        Ok(Err(err)) => {
+            recorder.record(get_time().sec, ErrorCategory::Http, &format!("{}: {}", request_line, err.error));
+            Err(err)
+        },
+        Err(panic) => {
+            let message = panic.downcast_ref::<&str>().map(|message| message.to_string())
+                .or_else(|| panic.downcast_ref::<String>().cloned())
+                .unwrap_or_else(|| "unknown panic".to_string());
+            error!("The handler of '{}' panicked: {}", request_line, message);
+            recorder.record(get_time().sec, ErrorCategory::Handler, &format!("{}: panicked: {}", request_line, message));
+            let mut res = text_to_response("Internal server error!\n")?;
+            res.status = Some(status::InternalServerError);
+            Ok(res)
+        }
+    }
+}
+
+/// Public helper function that rejects clients which are not in the allow-list of --allowed-ips and adds the security headers
+/// to every response, including the rejected ones. With --trust-proxy the address is taken from the X-Forwarded-For header
+pub fn secure_request<F>(req: &mut Request, config: &Configuration, handler: F) -> IronResult<Response>
+        where F: FnOnce(&mut Request) -> IronResult<Response> {
+    let forwarded_for = req.headers.get_raw("X-Forwarded-For")
+        .and_then(|values| values.last())
+        .and_then(|value| String::from_utf8(value.clone()).ok());
+    let address = client_ip(&req.remote_addr, forwarded_for.as_ref().map(|value| &value[..]), config.trust_proxy);
+    if address.is_none() {
+        info!("invalid X-Forwarded-For header from {}: {:?}", req.remote_addr, forwarded_for);
+    }
+
+    secure_response(address, config, || handler(req))
+}
+
+/// Public helper function that calls the handler if the client address is allowed and adds the security headers to the response
+/// None is an address that couldn't be determined, it's only allowed without --allowed-ips
+pub fn secure_response<F>(address: Option<IpAddr>, config: &Configuration, handler: F) -> IronResult<Response>
+        where F: FnOnce() -> IronResult<Response> {
+    let mut res = if is_allowed(&config.allowed_ips, address) {
+        handler()?
+    } else {
+        if let Some(address) = address {
+            info!("access denied for {}", address);
+        }
+        forbidden_response()?
+    };
+    add_security_headers(&mut res, config.frame_options);
+
+    Ok(res)
+}
+
+#[test]
+fn test_secure_response() {
+    use security::parse_ip_network;
+
+    let config = Configuration { allowed_ips: vec![parse_ip_network("10.0.0.0/8").unwrap(), parse_ip_network("2001:db8::/32").unwrap()],
+        .. Configuration::default() };
+
+    let res = secure_response(Some("10.1.2.3".parse().unwrap()), &config, || text_to_response("status")).unwrap();
+    assert_eq!(res.status, Some(status::Ok));
+    assert_eq!(res.headers.get_raw("X-Content-Type-Options"), Some(&[b"nosniff".to_vec()][..]));
+    assert_eq!(res.headers.get_raw("X-Frame-Options"), Some(&[b"SAMEORIGIN".to_vec()][..]));
+
+    let res = secure_response(Some("2001:db8::7".parse().unwrap()), &config, || text_to_response("status")).unwrap();
+    assert_eq!(res.status, Some(status::Ok));
+
+    // Rejected without calling the handler, but with the security headers
+    let res = secure_response(Some("203.0.113.7".parse().unwrap()), &config, || panic!("handler called")).unwrap();
+    assert_eq!(res.status, Some(status::Forbidden));
+    assert_eq!(res.headers.get_raw("X-Content-Type-Options"), Some(&[b"nosniff".to_vec()][..]));
+    assert!(res.headers.get_raw("Content-Security-Policy").is_some());
+    let res = secure_response(None, &config, || panic!("handler called")).unwrap();
+    assert_eq!(res.status, Some(status::Forbidden));
+
+    // Without --allowed-ips every client is allowed
+    let res = secure_response(None, &Configuration::default(), || text_to_response("status")).unwrap();
+    assert_eq!(res.status, Some(status::Ok));
+    assert!(res.headers.get_raw("Referrer-Policy").is_some());
+}
+
+#[test]
+fn test_limit_request() {
+    let limiter = RequestLimiter::new(1);
+
+    let res = limit_request(&limiter, || {
+        // A second request while the first one is being handled
+        let res = limit_request(&limiter, || text_to_response("inner")).unwrap();
+        assert_eq!(res.status, Some(status::ServiceUnavailable));
+        assert_eq!(res.headers.get_raw("Retry-After"), Some(&[b"1".to_vec()][..]));
+        text_to_response("outer")
+    }).unwrap();
+    assert_eq!(res.status, Some(status::Ok));
+    assert_eq!(limiter.in_flight(), 0);
+}
+
 /// Handles Iron requests and shows the slurm status as a HTML web page
 /// TODO: better error handling
-pub fn handle_request(req: &mut Request, shared_slurm_status: &Mutex<SlurmStatus>) -> IronResult<Response> {
+pub fn handle_request(req: &mut Request, shared_slurm_status: &SharedStatus, config: &Configuration, refresh: &RefreshControl,
+        subscribers: &Subscribers) -> IronResult<Response> {
     debug!("req: {:?}", req);
 
-    match shared_slurm_status.lock() {
+    if req.method == Method::Head {
+        req.method = Method::Get;
+        return handle_request(req, shared_slurm_status, config, refresh, subscribers).map(without_body);
+    }
+
+    let url_prefix = get_url_prefix(req, config);
+    let path = req.url.path.join("/");
+
+    let route = route_path(&path, &url_prefix);
+    // Only the JSON API may be read by the pages of other origins (--cors-allow-origin), the HTML pages never get the CORS headers
+    if is_api_route(route) {
+        let origin = req.headers.get_raw("Origin")
+            .and_then(|values| values.first())
+            .and_then(|value| String::from_utf8(value.clone()).ok());
+        let origin = origin.as_ref().map(|origin| &origin[..]);
+        if req.method == Method::Options {
+            return Ok(preflight_response(&config.cors_allow_origin, origin));
+        }
+
+        let mut res = route_request(req, shared_slurm_status, config, refresh, subscribers, route, &url_prefix)?;
+        add_cors_headers(&mut res, &config.cors_allow_origin, origin);
+        return Ok(res);
+    }
+
+    route_request(req, shared_slurm_status, config, refresh, subscribers, route, &url_prefix)
+}
+
+// Private helper function that calls the handler of the route (the path without URL prefix)
+fn route_request(req: &mut Request, shared_slurm_status: &SharedStatus, config: &Configuration, refresh: &RefreshControl,
+        subscribers: &Subscribers, route: &str, url_prefix: &str) -> IronResult<Response> {
+    if let Some(action) = admin_route(route) {
+        return handle_admin_action(req, shared_slurm_status, config, action, url_prefix);
+    }
+    if let Some((page, format, name)) = detail_route(route) {
+        return handle_detail_page(req, shared_slurm_status, config, page, &name, format);
+    }
+
+    match route {
+        "static/style.css" => css_to_response(DEFAULT_CSS, DEFAULT_CSS_MAX_AGE),
+        "static/custom.css" => custom_css_response(&config.custom_css),
+        "favicon.ico" => embedded_file_response(FAVICON, Mime(TopLevel::Image, SubLevel::Ext("svg+xml".to_string()), vec![])),
+        "robots.txt" => embedded_file_response(ROBOTS_TXT.as_bytes(), utf8_mime(TopLevel::Text, SubLevel::Plain)),
+        "api/status" => handle_status(req, shared_slurm_status, config, OutputFormat::Json),
+        "api/shares" => handle_api(req, shared_slurm_status, config, shares_to_json),
+        "api/priorities" => handle_api(req, shared_slurm_status, config, priorities_to_json),
+        "api/licenses" => handle_api(req, shared_slurm_status, config, licenses_to_json),
+        "api/qos" => handle_api(req, shared_slurm_status, config, qos_to_json),
+        "api/usage" => handle_api(req, shared_slurm_status, config, usage_to_json),
+        "api/users" => handle_api(req, shared_slurm_status, config, users_to_json),
+        "api/partitions" => handle_api(req, shared_slurm_status, config, partitions_to_json),
+        "api/history" => handle_history(req, shared_slurm_status, config),
+        "api/changes" => handle_changes(req, shared_slurm_status, config),
+        "api/events" => handle_api(req, shared_slurm_status, config, events_to_json),
+        "api/summary" => handle_api(req, shared_slurm_status, config, summary_to_json),
+        "api/version" => handle_version(shared_slurm_status, config),
+        "api/config" => json_to_response(&config_to_json(config)),
+        "health" => handle_health(shared_slurm_status, config),
+        "metrics" => handle_metrics(shared_slurm_status),
+        "debug/raw/sinfo" => handle_raw_output(shared_slurm_status, config, "sinfo"),
+        "debug/raw/squeue" => handle_raw_output(shared_slurm_status, config, "squeue"),
+        "debug/errors" => handle_recent_errors(error_recorder(), config, url_prefix, OutputFormat::Html),
+        "debug/errors.json" => handle_recent_errors(error_recorder(), config, url_prefix, OutputFormat::Json),
+        "refresh" => handle_refresh(req, shared_slurm_status, refresh, url_prefix),
+        "events" => handle_events(req, shared_slurm_status, config, subscribers),
+        "status.txt" => handle_status(req, shared_slurm_status, config, OutputFormat::Text),
+        "export/jobs.csv" => {
+            let (selection, _) = get_column_selection(req.url.query.as_ref().map(|q| &q[..]), "columns", &job_columns(), &config.job_columns);
+            handle_export(req, shared_slurm_status, config, "jobs", &selection,
+                |status, filter, selection| jobs_to_csv(&status.job_info, filter, selection))
+        },
+        "export/nodes.csv" => {
+            let (selection, _) = get_column_selection(req.url.query.as_ref().map(|q| &q[..]), "node_columns", &node_columns(), &config.node_columns);
+            handle_export(req, shared_slurm_status, config, "nodes", &selection,
+                |status, filter, selection| nodes_to_csv(&status.node_info, &status.job_info, filter, selection))
+        },
+        _ => handle_status_page(req, shared_slurm_status, config)
+    }
+}
+
+/// Returns (parts of) the slurm status as JSON, to_json does the actual conversion
+fn handle_api(req: &mut Request, shared_slurm_status: &SharedStatus, config: &Configuration,
+        to_json: fn(&SlurmStatus, &StatusFilter) -> String) -> IronResult<Response> {
+    let filter = get_status_filter(req.url.query.as_ref().map(|q| &q[..]), config, false);
+
+    match shared_slurm_status.snapshot() {
         Ok(status) => {
-            string_to_response(&status_to_html(&status))
+            json_to_response(&to_json(&status, &filter))
+        },
+        Err(err) => {
+            error!("Could not lock Mutex: {}", err);
+            json_to_response("{\"error\":\"Could not lock Mutex!\"}")
+        }
+    }
+}
+
+/// Returns the changes of the jobs and nodes since the snapshot of ?since=<token>, see status_changes
+fn handle_changes(req: &mut Request, shared_slurm_status: &SharedStatus, config: &Configuration) -> IronResult<Response> {
+    let since = get_query_param(req.url.query.as_ref().map(|q| &q[..]), "since");
+
+    match shared_slurm_status.snapshot() {
+        Ok(status) => json_to_response(&changes_to_json(&status, since.as_ref().map(|since| &since[..]), &StatusFilter::for_team(&config.users))),
+        Err(err) => {
+            error!("Could not lock Mutex: {}", err);
+            json_to_response("{\"error\":\"Could not lock Mutex!\"}")
+        }
+    }
+}
+
+/// Public helper function to read a time from the query string in seconds since the epoch, like ?from=1450432800
+/// Returns None if the parameter is missing and the value as error if it's not a number
+pub fn get_time_param(query: Option<&str>, name: &str) -> Result<Option<i64>, String> {
+    match get_query_param(query, name) {
+        Some(value) => value.trim().parse().map(Some).map_err(|_| value),
+        None => Ok(None)
+    }
+}
+
+#[test]
+fn test_get_time_param() {
+    assert_eq!(get_time_param(Some("from=1450432800&to=1450436400"), "from"), Ok(Some(1450432800)));
+    assert_eq!(get_time_param(Some("from=1450432800&to=1450436400"), "to"), Ok(Some(1450436400)));
+    assert_eq!(get_time_param(Some("from=1450432800"), "to"), Ok(None));
+    assert_eq!(get_time_param(None, "from"), Ok(None));
+    assert_eq!(get_time_param(Some("from=yesterday"), "from"), Err("yesterday".to_string()));
+}
+
+/// Returns the history as JSON: with --history-db from the database, otherwise the updates kept in memory
+/// ?from= and ?to= limit the time range (seconds since the epoch, both included), "400 Bad Request" if they are not numbers
+fn handle_history(req: &mut Request, shared_slurm_status: &SharedStatus, config: &Configuration) -> IronResult<Response> {
+    let query = req.url.query.as_ref().map(|q| &q[..]);
+    let (from, to) = match (get_time_param(query, "from"), get_time_param(query, "to")) {
+        (Ok(from), Ok(to)) => (from, to),
+        (Err(value), _) | (_, Err(value)) => {
+            return json_error_response(&format!("invalid time '{}', must be seconds since the epoch", value), status::BadRequest);
+        }
+    };
+
+    history_response(shared_slurm_status, config, from, to)
+}
+
+// Private helper function that reads the history in the time range and returns it as JSON
+fn history_response(shared_slurm_status: &SharedStatus, config: &Configuration, from: Option<i64>, to: Option<i64>) -> IronResult<Response> {
+    let entries: Vec<HistoryEntry> = match config.history_db {
+        Some(ref path) => match HistoryDb::open(path).and_then(|db| db.range(from, to)) {
+            Ok(entries) => entries,
+            Err(err) => {
+                error!("Could not read the history from the database '{}': {}", path, err);
+                return json_error_response("Could not read the history from the database!", status::InternalServerError);
+            }
+        },
+        None => match shared_slurm_status.snapshot() {
+            Ok(status) => status.history.range(from, to),
+            Err(err) => {
+                error!("Could not lock Mutex: {}", err);
+                return json_to_response("{\"error\":\"Could not lock Mutex!\"}");
+            }
+        }
+    };
+
+    json_to_response(&Json::Array(entries.iter().map(|entry| entry.to_json()).collect()).to_string())
+}
+
+#[test]
+fn test_history_response() {
+    use std::env::temp_dir;
+    use std::fs::remove_file;
+    use time::precise_time_ns;
+    use history::History;
+
+    let shared_slurm_status = SharedStatus::new(SlurmStatus::new());
+    shared_slurm_status.update(&mut |status| {
+        status.history = History::new(10);
+        status.history.push(HistoryEntry::new(60, &[], &[]));
+        status.history.push(HistoryEntry::new(120, &[], &[]));
+    }).unwrap();
+    let res = history_response(&shared_slurm_status, &Configuration::default(), Some(100), None).unwrap();
+    assert_eq!(res.status, Some(status::Ok));
+    assert_eq!(res.headers.get::<ContentLength>(), Some(&ContentLength(
+        Json::Array(vec![HistoryEntry::new(120, &[], &[]).to_json()]).to_string().len() as u64)));
+
+    // From the database, not from memory
+    let path = temp_dir().join(format!("slurm_inspector_api_history_{}.sqlite", precise_time_ns())).to_string_lossy().to_string();
+    let mut db = HistoryDb::open(&path).unwrap();
+    for timestamp in &[60, 3600, 7200] {
+        db.insert(&HistoryEntry::new(*timestamp, &[], &[])).unwrap();
+    }
+    let config = Configuration { history_db: Some(path.clone()), .. Configuration::default() };
+    let res = history_response(&shared_slurm_status, &config, Some(3600), None).unwrap();
+    assert_eq!(res.headers.get::<ContentLength>(), Some(&ContentLength(
+        Json::Array(vec![HistoryEntry::new(3600, &[], &[]).to_json(), HistoryEntry::new(7200, &[], &[]).to_json()]).to_string().len() as u64)));
+    remove_file(&path).unwrap();
+
+    let res = json_error_response("invalid time 'x', must be seconds since the epoch", status::BadRequest).unwrap();
+    assert_eq!(res.status, Some(status::BadRequest));
+}
+
+/// Streams the status as Server-Sent Events: the current status right away and then a new event after every update
+/// The events contain the summary of the status, with ?full=1 the whole status like /api/status
+fn handle_events(req: &mut Request, shared_slurm_status: &SharedStatus, config: &Configuration, subscribers: &Subscribers) -> IronResult<Response> {
+    let full = get_query_param(req.url.query.as_ref().map(|q| &q[..]), "full").map_or(false, |value| value == "1");
+
+    // Subscribe before the current status is read, so no update gets lost in between
+    let receiver = match subscribers.subscribe() {
+        Some(receiver) => receiver,
+        None => {
+            warn!("Too many event streams, the limit is {}", MAX_SUBSCRIBERS);
+            let mut res = text_to_response("Too many event streams!\n")?;
+            res.status = Some(status::ServiceUnavailable);
+            return Ok(res);
+        }
+    };
+
+    match shared_slurm_status.snapshot() {
+        Ok(status) => events_to_response(EventStream::new(receiver, full, StatusEvent::new(&status, &StatusFilter::for_team(&config.users)))),
+        Err(err) => {
+            error!("Could not lock Mutex: {}", err);
+            let mut res = text_to_response("Could not lock Mutex!\n")?;
+            res.status = Some(status::InternalServerError);
+            Ok(res)
+        }
+    }
+}
+
+// Browsers ask for text/html explicitly, curl and scripts get the JSON response
+fn accepts_html(accept: &[QualityItem<Mime>]) -> bool {
+    accept.iter().any(|item| item.item.0 == TopLevel::Text && item.item.1 == SubLevel::Html && item.quality.0 > 0)
+}
+
+// Private helper function that reads the form of a POST request (application/x-www-form-urlencoded), at most MAX_FORM_SIZE bytes
+fn read_form(req: &mut Request) -> String {
+    let mut form = String::new();
+    if let Err(err) = req.body.by_ref().take(MAX_FORM_SIZE).read_to_string(&mut form) {
+        warn!("Could not read the form of the request: {}", err);
+    }
+    form
+}
+
+/// Shows the confirmation page of an admin action (GET) or runs it (POST) for the user authenticated by the reverse proxy, see admin_actions
+/// The user is checked before the argument, so an unauthenticated request learns nothing about the arguments
+/// In test mode drained and resumed nodes are simulated in the test data, which is updated right away
+fn handle_admin_action(req: &mut Request, shared_slurm_status: &SharedStatus, config: &Configuration, action: Result<AdminAction, AdminError>,
+        url_prefix: &str) -> IronResult<Response> {
+    if req.method != Method::Post && req.method != Method::Get {
+        let mut res = string_to_response("<h1>Use POST for admin actions!</h1>")?;
+        res.status = Some(status::MethodNotAllowed);
+        res.headers.set_raw("Allow", vec![b"GET, POST".to_vec()]);
+        return Ok(res);
+    }
+
+    let header = |name: &str| header_value(req, name);
+    let checked = authorize(config, &header)
+        .and_then(|user| action.map(|action| (user, action)));
+    let (user, action) = match checked {
+        Ok(checked) => checked,
+        Err(err) => {
+            warn!("Admin action refused for '{}': {}", header(remote_user_header(config)).unwrap_or_default(), err);
+            return admin_refused_response(&err);
+        }
+    };
+
+    if req.method == Method::Get {
+        let options = PageOptions { url_prefix: url_prefix.to_string(), .. PageOptions::new(config) };
+        return html_page_response(confirmation_page(&action, &csrf_token(&csrf_secret(), &user), &options));
+    }
+
+    // Only the form of the confirmation page of the inspector runs the action, not a form of another site
+    let form = read_form(req);
+    let token = get_query_param(Some(&form), CSRF_TOKEN_FIELD);
+    if let Err(err) = check_admin_post(config, &csrf_secret(), &user, &|name: &str| header_value(req, name), token.as_ref().map(|token| &token[..])) {
+        warn!("Admin action refused for '{}': {}", user, err);
+        return admin_refused_response(&err);
+    }
+
+    let action = if action.needs_reason() {
+        match action.with_form(get_query_param(Some(&form), "reason").as_ref().map(|reason| &reason[..])) {
+            Ok(action) => action,
+            Err(err) => {
+                warn!("Admin action refused for '{}': {}", user, err);
+                return admin_refused_response(&err);
+            }
+        }
+    } else {
+        action
+    };
+
+    let result = run_admin_action(&action, &user, config, &*create_runner(config));
+    if config.test_mode && result.is_ok() && action.node().is_some() {
+        let since = strftime(TIME_FORMAT, &now()).unwrap();
+        update_shared_status(shared_slurm_status, &PageOptions::new(config), &mut |status| {
+            simulate_node_action(&action, &user, &since, &mut status.simulated_nodes);
+            update_slurm_status_parts(status, config, &*create_runner(config), None, DueUpdates{ node_info: true, job_info: false });
+        });
+    }
+    let html = req.headers.get::<Accept>().map_or(false, |accept| accepts_html(&accept.0));
+    admin_action_response(&action, &result, if html { Some(url_prefix) } else { None })
+}
+
+/// Requests an immediate update of the SLURM status (only with POST) and waits until it is finished
+fn handle_refresh(req: &mut Request, shared_slurm_status: &SharedStatus, refresh: &RefreshControl, url_prefix: &str) -> IronResult<Response> {
+    if req.method != Method::Post {
+        let mut res = string_to_response("<h1>Use POST to refresh the status!</h1>")?;
+        res.status = Some(status::MethodNotAllowed);
+        res.headers.set_raw("Allow", vec![b"POST".to_vec()]);
+        return Ok(res);
+    }
+
+    let html = req.headers.get::<Accept>().map_or(false, |accept| accepts_html(&accept.0));
+    refresh_status(shared_slurm_status, refresh, if html { Some(url_prefix) } else { None })
+}
+
+// Private helper function that does the actual refresh, browsers are sent back to the status page (below the given URL prefix) afterwards
+fn refresh_status(shared_slurm_status: &SharedStatus, refresh: &RefreshControl, redirect_prefix: Option<&str>) -> IronResult<Response> {
+    let number = match refresh.request(get_time().sec) {
+        Ok(number) => number,
+        Err(err) => {
+            info!("Refresh refused: {}", err);
+            return refresh_refused_response(&err);
+        }
+    };
+
+    let finished = refresh.wait_finished(number, Duration::new(REFRESH_TIMEOUT, 0));
+    if !finished {
+        warn!("Refresh did not finish within {} sec.", REFRESH_TIMEOUT);
+    }
+    if let Some(url_prefix) = redirect_prefix {
+        return redirect_to_status_page(url_prefix);
+    }
+
+    match shared_slurm_status.snapshot() {
+        Ok(status) => refresh_to_response(&status.last_update, finished),
+        Err(err) => {
+            error!("Could not lock Mutex: {}", err);
+            json_to_response("{\"error\":\"Could not lock Mutex!\"}")
+        }
+    }
+}
+
+/// Returns the health of the SLURM status thread, the lock is only held to read the time of the last update
+fn handle_health(shared_slurm_status: &SharedStatus, config: &Configuration) -> IronResult<Response> {
+    match shared_slurm_status.snapshot() {
+        Ok(status) => {
+            // Any update without errors counts, so the shorter interval is the one that matters
+            health_to_response(&health_report(&status, min(config.sinfo_interval, config.squeue_interval), get_time().sec))
+        },
+        Err(err) => {
+            error!("Could not lock Mutex: {}", err);
+            health_json_to_response(&unknown_health_json(), false)
+        }
+    }
+}
+
+/// Returns the version, commit and uptime of slurm_inspector and the update settings as JSON
+fn handle_version(shared_slurm_status: &SharedStatus, config: &Configuration) -> IronResult<Response> {
+    match shared_slurm_status.snapshot() {
+        Ok(status) => {
+            json_to_response(&version_to_json(&status.build_info, config, get_time().sec))
+        },
+        Err(err) => {
+            error!("Could not lock Mutex: {}", err);
+            json_to_response("{\"error\":\"Could not lock Mutex!\"}")
+        }
+    }
+}
+
+/// Shows the status of a single user or partition as HTML page or JSON
+/// "404 Not Found" if the user has no jobs or the partition doesn't exist
+fn handle_detail_page(req: &mut Request, shared_slurm_status: &SharedStatus, config: &Configuration, page: DetailPage, name: &str,
+        format: OutputFormat) -> IronResult<Response> {
+    let query = req.url.query.as_ref().map(|q| &q[..]);
+    let default_options = PageOptions::new(config);
+    let options = PageOptions{
+        page_refresh: get_query_param(query, "refresh").and_then(|value| parse_page_refresh(&value)).unwrap_or(default_options.page_refresh),
+        filter: get_status_filter(query, config, false),
+        url_prefix: get_url_prefix(req, config),
+        .. default_options
+    };
+
+    match shared_slurm_status.snapshot() {
+        Ok(status) => {
+            let response = match (page, format) {
+                (DetailPage::User, OutputFormat::Json) => user_to_json(&status, &options.filter, name).map(|json| json_to_response(&json)),
+                (DetailPage::User, _) => user_to_html(&status, &options, name).map(html_page_response),
+                (DetailPage::Partition, OutputFormat::Json) => partition_to_json(&status, &options.filter, name).map(|json| json_to_response(&json)),
+                (DetailPage::Partition, _) => partition_to_html(&status, &options, name).map(html_page_response)
+            };
+            response.unwrap_or_else(|| match page {
+                DetailPage::User => not_found_response(&format!("no jobs for user {}", name), format),
+                DetailPage::Partition => not_found_response(&format!("no partition {}", name), format)
+            })
         },
         Err(err) => {
             error!("Could not lock Mutex: {}", err);
@@ -39,3 +1217,590 @@ pub fn handle_request(req: &mut Request, shared_slurm_status: &Mutex<SlurmStatus
         }
     }
 }
+
+// Private helper function that returns a rendered detail page, or "500 Internal Server Error" if it couldn't be rendered
+fn html_page_response(html: Result<String, String>) -> IronResult<Response> {
+    match html {
+        Ok(html) => string_to_response(&html),
+        Err(err) => {
+            error!("Could not render the page: {}", err);
+            render_error_response()
+        }
+    }
+}
+
+/// Returns the metrics of the SLURM commands and the scheduler pressure in the Prometheus text format
+fn handle_metrics(shared_slurm_status: &SharedStatus) -> IronResult<Response> {
+    match shared_slurm_status.snapshot() {
+        Ok(status) => {
+            text_to_response(&status_to_prometheus(&status))
+        },
+        Err(err) => {
+            error!("Could not lock Mutex: {}", err);
+            let mut res = text_to_response("Could not lock Mutex!\n")?;
+            res.status = Some(status::InternalServerError);
+            Ok(res)
+        }
+    }
+}
+
+/// Returns the raw output of the last sinfo or squeue calls as plain text, "404 Not Found" without --debug-endpoints
+fn handle_raw_output(shared_slurm_status: &SharedStatus, config: &Configuration, command: &str) -> IronResult<Response> {
+    if !config.debug_endpoints {
+        let mut res = text_to_response("Not found, the debug endpoints are disabled (--debug-endpoints)\n")?;
+        res.status = Some(status::NotFound);
+        return Ok(res);
+    }
+    if config.anonymize {
+        let mut res = text_to_response("Not found, the raw output contains the real user names and is not kept with --anonymize\n")?;
+        res.status = Some(status::NotFound);
+        return Ok(res);
+    }
+
+    match shared_slurm_status.snapshot() {
+        Ok(status) => {
+            let raw_output = if command == "sinfo" { &status.raw_sinfo } else { &status.raw_squeue };
+            let mut res = match *raw_output {
+                Some(ref raw_output) => text_to_response(&raw_output.to_text(&config.display_time))?,
+                // Before the first update and in the test mode, where no commands are called
+                None => text_to_response(&format!("No output of {} has been captured yet\n", command))?
+            };
+            res.headers.set(CacheControl(vec![CacheDirective::NoCache]));
+            Ok(res)
+        },
+        Err(err) => {
+            error!("Could not lock Mutex: {}", err);
+            let mut res = text_to_response("Could not lock Mutex!\n")?;
+            res.status = Some(status::InternalServerError);
+            Ok(res)
+        }
+    }
+}
+
+/// Returns (parts of) the slurm status as CSV file with the selected columns, to_csv does the actual conversion
+/// Unknown columns are ignored, a CSV file has no place for a notice
+fn handle_export(req: &mut Request, shared_slurm_status: &SharedStatus, config: &Configuration, name: &str, selection: &[String],
+        to_csv: fn(&SlurmStatus, &StatusFilter, &[String]) -> String) -> IronResult<Response> {
+    let filter = get_status_filter(req.url.query.as_ref().map(|q| &q[..]), config, false);
+
+    match shared_slurm_status.snapshot() {
+        Ok(status) => {
+            csv_to_response(&to_csv(&status, &filter, selection), &export_file_name(name, &status.last_update))
+        },
+        Err(err) => {
+            error!("Could not lock Mutex: {}", err);
+            string_to_response("<h1>Could not lock Mutex!</h1>")
+        }
+    }
+}
+
+// Private helper function to choose the output format of the status page from the Accept header
+// The format with the highest quality wins, HTML is preferred if several formats have the same quality
+// Browsers always accept text/html, so other formats are only returned if they are explicitly asked for, for example with curl -H "Accept: application/json"
+fn negotiate_format(accept: &[QualityItem<Mime>]) -> OutputFormat {
+    let quality = |top_level: TopLevel, sub_level: SubLevel| accept.iter()
+        .filter(|item| item.item.0 == top_level && item.item.1 == sub_level)
+        .map(|item| item.quality.0)
+        .max()
+        .unwrap_or(0);
+    let candidates = [
+        (OutputFormat::Html, quality(TopLevel::Text, SubLevel::Html)),
+        (OutputFormat::Json, quality(TopLevel::Application, SubLevel::Json)),
+        (OutputFormat::Text, quality(TopLevel::Text, SubLevel::Plain))
+    ];
+
+    candidates.iter()
+        .fold((OutputFormat::Html, 0), |best, &candidate| if candidate.1 > best.1 { candidate } else { best })
+        .0
+}
+
+/// Shows the slurm status as HTML web page, JSON or plain text, depending on the Accept header
+fn handle_status_page(req: &mut Request, shared_slurm_status: &SharedStatus, config: &Configuration) -> IronResult<Response> {
+    let format = req.headers.get::<Accept>().map_or(OutputFormat::Html, |accept| negotiate_format(&accept.0));
+    let mut res = handle_status(req, shared_slurm_status, config, format)?;
+    res.headers.set_raw("Vary", vec![b"Accept, Accept-Encoding".to_vec()]);
+
+    Ok(res)
+}
+
+/// Shows the slurm status in the given format
+/// Without filter the representation rendered after the last update is sent, so HTML, JSON and plain text always show the same snapshot
+fn handle_status(req: &mut Request, shared_slurm_status: &SharedStatus, config: &Configuration, format: OutputFormat) -> IronResult<Response> {
+    let encoding = req.headers.get::<AcceptEncoding>().and_then(|accepted| choose_encoding(&accepted.0));
+    let if_none_match = req.headers.get_raw("If-None-Match").map_or(Vec::new(), |values| values.to_vec());
+    let query = req.url.query.as_ref().map(|q| &q[..]);
+    let page_refresh = get_query_param(query, "refresh").and_then(|value| parse_page_refresh(&value));
+    let filter = get_status_filter(query, config, format == OutputFormat::Html);
+    let all_jobs = get_query_param(query, "all_jobs").is_some();
+    let expand_arrays = get_query_param(query, "expand_arrays").map_or(false, |value| value == "1");
+    // An unknown order is ignored like an invalid refresh interval
+    let sort = get_query_param(query, "sort").and_then(|value| parse_job_sort(&value));
+    let limit = get_query_param(query, "limit");
+    let page = get_query_param(query, "page");
+    let paged = limit.is_some() || page.is_some();
+    let pagination = parse_pagination(limit.as_ref().map(|limit| &limit[..]), page.as_ref().map(|page| &page[..]), config.page_limit);
+    let url_prefix = get_url_prefix(req, config);
+    let (job_selection, unknown_job_columns) = get_column_selection(query, "columns", &job_columns(), &config.job_columns);
+    let (node_selection, unknown_node_columns) = get_column_selection(query, "node_columns", &node_columns(), &config.node_columns);
+    let custom_columns = get_query_param(query, "columns").is_some() || get_query_param(query, "node_columns").is_some();
+    let admin_notice = admin_notice(get_query_param(query, "done").as_ref().map(|done| &done[..]),
+        get_query_param(query, "failed").as_ref().map(|failed| &failed[..]));
+    let me = get_page_user(req, query, config);
+
+    match shared_slurm_status.snapshot() {
+        Ok(status) => {
+            let default_view = match format {
+                OutputFormat::Html => page_refresh.is_none() && !expand_arrays && sort.is_none() && !paged && !custom_columns && admin_notice.is_none()
+                    && url_prefix == config.url_prefix && !all_jobs && me.is_none(),
+                OutputFormat::Json => !paged && !all_jobs,
+                OutputFormat::Text => !all_jobs
+            };
+            if default_view && filter.is_empty() {
+                rendered_to_response(&status.rendered, format, encoding, &if_none_match)
+            } else {
+                // The user wants a different refresh interval, the flat job view, another order, another page, other columns, a filtered view,
+                // the finished jobs shown differently or the own jobs highlighted
+                // (or the proxy sent another URL prefix), so the cached representation can't be used
+                match format {
+                    OutputFormat::Html => {
+                        let default_options = PageOptions::new(config);
+                        let options = PageOptions{
+                            page_refresh: page_refresh.unwrap_or(default_options.page_refresh),
+                            filter: filter,
+                            expand_arrays: expand_arrays,
+                            sort: sort.unwrap_or(default_options.sort),
+                            pagination: pagination,
+                            query: query.unwrap_or("").to_string(),
+                            url_prefix: url_prefix,
+                            job_columns: job_selection,
+                            node_columns: node_selection,
+                            unknown_job_columns: unknown_job_columns,
+                            unknown_node_columns: unknown_node_columns,
+                            admin_notice: admin_notice,
+                            me: me,
+                            .. default_options
+                        };
+                        match status_to_html(&status, &options) {
+                            Ok(html) => page_to_response(&CompressedPage::new(html), format_mime(format), encoding),
+                            Err(err) => {
+                                error!("Could not render the status page: {}", err);
+                                render_error_response()
+                            }
+                        }
+                    },
+                    OutputFormat::Json if paged => json_to_response(&status_to_json_paged(&status, &filter, &pagination)),
+                    OutputFormat::Json => json_to_response(&status_to_json(&status, &filter)),
+                    OutputFormat::Text => text_to_response(&status_to_text(&status, &filter))
+                }
+            }
+        },
+        Err(err) => {
+            error!("Could not lock Mutex: {}", err);
+            match format {
+                OutputFormat::Html => string_to_response("<h1>Could not lock Mutex!</h1>"),
+                OutputFormat::Json => json_to_response("{\"error\":\"Could not lock Mutex!\"}"),
+                OutputFormat::Text => text_to_response("Could not lock Mutex!\n")
+            }
+        }
+    }
+}
+
+#[test]
+fn test_health_to_response() {
+    use health::HealthState;
+
+    let healthy = HealthReport{ status: HealthState::Healthy, last_update_age_seconds: Some(10), consecutive_update_failures: 0,
+        cluster_name: "cluster1".to_string() };
+    let res = health_to_response(&healthy).unwrap();
+    assert_eq!(res.status, Some(status::Ok));
+    assert_eq!(res.headers.get::<ContentType>(), Some(&ContentType(utf8_mime(TopLevel::Application, SubLevel::Json))));
+
+    for state in vec![HealthState::Starting, HealthState::Unhealthy] {
+        let report = HealthReport{ status: state, .. healthy.clone() };
+        assert_eq!(health_to_response(&report).unwrap().status, Some(status::ServiceUnavailable));
+    }
+}
+
+#[test]
+fn test_unknown_health_to_response() {
+    use health::HealthState;
+
+    // The body if the status can't be read has the same fields as the other answers, with null for the unknown values
+    let res = health_json_to_response(&unknown_health_json(), false).unwrap();
+    assert_eq!(res.status, Some(status::ServiceUnavailable));
+    assert_eq!(res.headers.get::<ContentType>(), Some(&ContentType(utf8_mime(TopLevel::Application, SubLevel::Json))));
+
+    let unknown = unknown_health_json();
+    let report = HealthReport{ status: HealthState::Unhealthy, last_update_age_seconds: Some(10), consecutive_update_failures: 3,
+        cluster_name: "cluster1".to_string() }.to_json();
+    assert_eq!(unknown.as_object().unwrap().keys().collect::<Vec<_>>(), report.as_object().unwrap().keys().collect::<Vec<_>>());
+    assert_eq!(unknown.find("status"), report.find("status"));
+    assert_eq!(unknown.find("last_update_age_seconds"), Some(&Json::Null));
+    assert_eq!(unknown.find("consecutive_update_failures"), Some(&Json::Null));
+}
+
+#[test]
+fn test_not_found_response() {
+    let res = not_found_response("no jobs for user <script>", OutputFormat::Html).unwrap();
+    assert_eq!(res.status, Some(status::NotFound));
+    assert_eq!(res.headers.get::<ContentLength>(), Some(&ContentLength("<h1>no jobs for user &lt;script&gt;</h1>".len() as u64)));
+
+    let res = not_found_response("no jobs for user nobody", OutputFormat::Json).unwrap();
+    assert_eq!(res.status, Some(status::NotFound));
+    assert_eq!(res.headers.get::<ContentLength>(), Some(&ContentLength("{\"error\":\"no jobs for user nobody\"}".len() as u64)));
+}
+
+#[test]
+fn test_refresh_responses() {
+    let res = refresh_to_response("2000.01.01 - 09:00", true).unwrap();
+    assert_eq!(res.status, Some(status::Accepted));
+    assert_eq!(res.headers.get::<ContentLength>(), Some(&ContentLength("{\"finished\":true,\"last_update\":\"2000.01.01 - 09:00\"}".len() as u64)));
+
+    let res = refresh_refused_response(&RefreshError::TooSoon(7)).unwrap();
+    assert_eq!(res.status, Some(status::TooManyRequests));
+    assert_eq!(res.headers.get_raw("Retry-After"), Some(&[b"7".to_vec()][..]));
+    let res = refresh_refused_response(&RefreshError::Running).unwrap();
+    assert_eq!(res.status, Some(status::TooManyRequests));
+    assert_eq!(res.headers.get_raw("Retry-After"), None);
+
+    let res = redirect_to_status_page("").unwrap();
+    assert_eq!(res.status, Some(status::SeeOther));
+    assert_eq!(res.headers.get::<Location>(), Some(&Location("/".to_string())));
+    let res = redirect_to_status_page("/slurm").unwrap();
+    assert_eq!(res.headers.get::<Location>(), Some(&Location("/slurm/".to_string())));
+}
+
+#[test]
+fn test_admin_action_responses() {
+    let action = AdminAction::CancelJob(42);
+
+    let res = admin_action_response(&action, &Ok(()), Some("/slurm")).unwrap();
+    assert_eq!(res.status, Some(status::SeeOther));
+    assert_eq!(res.headers.get::<Location>(), Some(&Location("/slurm/?done=job/42/cancel".to_string())));
+    let res = admin_action_response(&action, &Err("scancel failed".to_string()), Some("")).unwrap();
+    assert_eq!(res.headers.get::<Location>(), Some(&Location("/?failed=job/42/cancel".to_string())));
+
+    let res = admin_action_response(&action, &Ok(()), None).unwrap();
+    assert_eq!(res.status, Some(status::Ok));
+    assert_eq!(res.headers.get::<ContentLength>(),
+        Some(&ContentLength("{\"action\":\"cancel job 42\",\"message\":\"Job 42 has been cancelled\",\"success\":true}".len() as u64)));
+    let res = admin_action_response(&action, &Err("scancel failed".to_string()), None).unwrap();
+    assert_eq!(res.status, Some(status::InternalServerError));
+
+    assert_eq!(admin_refused_response(&AdminError::Disabled).unwrap().status, Some(status::NotFound));
+    assert_eq!(admin_refused_response(&AdminError::NotAuthenticated).unwrap().status, Some(status::Forbidden));
+    assert_eq!(admin_refused_response(&AdminError::ForeignOrigin).unwrap().status, Some(status::Forbidden));
+    assert_eq!(admin_refused_response(&AdminError::InvalidToken).unwrap().status, Some(status::Forbidden));
+    assert_eq!(admin_refused_response(&AdminError::InvalidArgument("4x".to_string())).unwrap().status, Some(status::BadRequest));
+    assert_eq!(admin_refused_response(&AdminError::InvalidReason(String::new())).unwrap().status, Some(status::BadRequest));
+}
+
+#[test]
+fn test_events_to_response() {
+    let subscribers = Subscribers::new();
+    let stream = EventStream::new(subscribers.subscribe().unwrap(), false, StatusEvent::new(&SlurmStatus::new(), &StatusFilter::new()));
+    let res = events_to_response(stream).unwrap();
+
+    assert_eq!(res.status, Some(status::Ok));
+    assert_eq!(res.headers.get::<ContentType>(), Some(&ContentType(Mime(TopLevel::Text, SubLevel::EventStream, vec![]))));
+    assert_eq!(res.headers.get::<ContentLength>(), None);
+    assert!(res.body.is_some());
+}
+
+#[test]
+fn test_accepts_html() {
+    use iron::headers::Quality;
+
+    let html = QualityItem{ item: Mime(TopLevel::Text, SubLevel::Html, vec![]), quality: Quality(1000) };
+    let no_html = QualityItem{ item: Mime(TopLevel::Text, SubLevel::Html, vec![]), quality: Quality(0) };
+    let any = QualityItem{ item: Mime(TopLevel::Star, SubLevel::Star, vec![]), quality: Quality(1000) };
+
+    assert!(accepts_html(&[any.clone(), html]));
+    assert!(!accepts_html(&[any]));
+    assert!(!accepts_html(&[no_html]));
+    assert!(!accepts_html(&[]));
+}
+
+#[test]
+fn test_refresh_status() {
+    use std::sync::Arc;
+    use std::thread;
+    use update_schedule::Clock;
+
+    let shared_slurm_status = Arc::new(SharedStatus::new(SlurmStatus::new()));
+    let refresh = Arc::new(RefreshControl::new(3600));
+
+    // Stands in for the SLURM status thread
+    let (thread_status, thread_refresh) = (shared_slurm_status.clone(), refresh.clone());
+    let handle = thread::spawn(move || {
+        assert!(thread_refresh.sleep(3600));
+        thread_status.update(&mut |status| status.last_update = "2000.01.01 - 09:00".to_string()).unwrap();
+        thread_refresh.finish();
+    });
+
+    let res = refresh_status(&shared_slurm_status, &refresh, None).unwrap();
+    handle.join().unwrap();
+    assert_eq!(res.status, Some(status::Accepted));
+    assert_eq!(res.headers.get::<ContentLength>(), Some(&ContentLength("{\"finished\":true,\"last_update\":\"2000.01.01 - 09:00\"}".len() as u64)));
+
+    // Within the minimum spacing the refresh is refused
+    assert_eq!(refresh_status(&shared_slurm_status, &refresh, Some("")).unwrap().status, Some(status::TooManyRequests));
+}
+
+#[test]
+fn test_handle_raw_output() {
+    use raw_output::{RawOutput, RawCall};
+
+    let shared_slurm_status = SharedStatus::new(SlurmStatus::new());
+    let enabled = Configuration { debug_endpoints: true, .. Configuration::default() };
+
+    // Disabled by default
+    let res = handle_raw_output(&shared_slurm_status, &Configuration::default(), "sinfo").unwrap();
+    assert_eq!(res.status, Some(status::NotFound));
+
+    let res = handle_raw_output(&shared_slurm_status, &enabled, "squeue").unwrap();
+    assert_eq!(res.status, Some(status::Ok));
+    assert_eq!(res.headers.get::<ContentLength>(), Some(&ContentLength("No output of squeue has been captured yet\n".len() as u64)));
+
+    let raw_output = RawOutput { captured_at: 1450432800, calls: vec![RawCall { command_line: "squeue -h".to_string(), exit_status: "0".to_string(),
+        stdout: "82 RUNNING willi\n".to_string(), truncated: 0 }] };
+    shared_slurm_status.update(&mut |status| status.raw_squeue = Some(raw_output.clone())).unwrap();
+    let res = handle_raw_output(&shared_slurm_status, &enabled, "squeue").unwrap();
+    assert_eq!(res.status, Some(status::Ok));
+    assert_eq!(res.headers.get::<ContentType>(), Some(&ContentType(Mime(TopLevel::Text, SubLevel::Plain, vec![(Attr::Charset, Value::Utf8)]))));
+    assert_eq!(res.headers.get::<ContentLength>(), Some(&ContentLength(raw_output.to_text(&enabled.display_time).len() as u64)));
+    assert_eq!(res.headers.get::<CacheControl>(), Some(&CacheControl(vec![CacheDirective::NoCache])));
+
+    // Still disabled, even if there is output
+    let res = handle_raw_output(&shared_slurm_status, &Configuration::default(), "squeue").unwrap();
+    assert_eq!(res.status, Some(status::NotFound));
+
+    // The real user names would be shown next to the pseudonyms of the page
+    let anonymized = Configuration { anonymize: true, .. enabled.clone() };
+    let res = handle_raw_output(&shared_slurm_status, &anonymized, "squeue").unwrap();
+    assert_eq!(res.status, Some(status::NotFound));
+    assert_eq!(res.headers.get::<ContentLength>(),
+        Some(&ContentLength("Not found, the raw output contains the real user names and is not kept with --anonymize\n".len() as u64)));
+}
+
+/// Returns the recent errors as HTML page or JSON, "404 Not Found" without --debug-endpoints
+fn handle_recent_errors(recorder: &ErrorRecorder, config: &Configuration, url_prefix: &str, format: OutputFormat) -> IronResult<Response> {
+    if !config.debug_endpoints {
+        let mut res = text_to_response("Not found, the debug endpoints are disabled (--debug-endpoints)\n")?;
+        res.status = Some(status::NotFound);
+        return Ok(res);
+    }
+
+    let mut res = match format {
+        OutputFormat::Json => json_to_response(&recorder.to_json().to_string())?,
+        _ => {
+            let options = PageOptions { url_prefix: url_prefix.to_string(), .. PageOptions::new(config) };
+            html_page_response(recent_errors_page(recorder, &options, &config.display_time))?
+        }
+    };
+    res.headers.set(CacheControl(vec![CacheDirective::NoCache]));
+    Ok(res)
+}
+
+#[test]
+fn test_handle_recent_errors() {
+    let recorder = ErrorRecorder::new(10);
+    recorder.record(1450432800, ErrorCategory::Update, "Could not execute 'sinfo': <not found>");
+    let enabled = Configuration { debug_endpoints: true, .. Configuration::default() };
+
+    // Disabled by default
+    for format in vec![OutputFormat::Html, OutputFormat::Json] {
+        let res = handle_recent_errors(&recorder, &Configuration::default(), "", format).unwrap();
+        assert_eq!(res.status, Some(status::NotFound));
+    }
+
+    let res = handle_recent_errors(&recorder, &enabled, "", OutputFormat::Json).unwrap();
+    assert_eq!(res.status, Some(status::Ok));
+    assert_eq!(res.headers.get::<ContentType>(), Some(&ContentType(Mime(TopLevel::Application, SubLevel::Json, vec![(Attr::Charset, Value::Utf8)]))));
+    assert_eq!(res.headers.get::<CacheControl>(), Some(&CacheControl(vec![CacheDirective::NoCache])));
+    assert_eq!(res.headers.get::<ContentLength>(), Some(&ContentLength(recorder.to_json().to_string().len() as u64)));
+
+    let html = recent_errors_page(&recorder, &PageOptions { url_prefix: "/slurm".to_string(), .. PageOptions::new(&enabled) },
+        &enabled.display_time).unwrap();
+    assert!(html.contains("<td>update</td><td>Could not execute 'sinfo': &lt;not found&gt;</td>"));
+    assert!(html.contains(&format!("<td>{}</td>", enabled.display_time.format(1450432800))));
+    assert!(html.contains("href=\"/slurm/debug/errors.json\""));
+    let res = handle_recent_errors(&recorder, &enabled, "/slurm", OutputFormat::Html).unwrap();
+    assert_eq!(res.status, Some(status::Ok));
+    assert_eq!(res.headers.get::<ContentLength>(), Some(&ContentLength(html.len() as u64)));
+
+    let html = recent_errors_page(&ErrorRecorder::new(10), &PageOptions::new(&enabled), &enabled.display_time).unwrap();
+    assert!(html.contains("No errors have been recorded"));
+}
+
+#[test]
+fn test_guard_response() {
+    let recorder = ErrorRecorder::new(10);
+
+    let res = guard_response("GET /", &recorder, || text_to_response("status")).unwrap();
+    assert_eq!(res.status, Some(status::Ok));
+    assert!(recorder.entries().is_empty());
+
+    let res = guard_response("GET /metrics", &recorder, || {
+        let mut res = text_to_response("Could not lock Mutex!\n")?;
+        res.status = Some(status::InternalServerError);
+        Ok(res)
+    }).unwrap();
+    assert_eq!(res.status, Some(status::InternalServerError));
+    assert_eq!(recorder.entries()[0].category, ErrorCategory::Http);
+    assert_eq!(recorder.entries()[0].message, "GET /metrics: 500 Internal Server Error");
+
+    // The panic is answered with 500 and recorded
+    let res = guard_response("GET /api/status", &recorder, || -> IronResult<Response> { panic!("index out of bounds") }).unwrap();
+    assert_eq!(res.status, Some(status::InternalServerError));
+    assert_eq!(recorder.entries()[0].category, ErrorCategory::Handler);
+    assert_eq!(recorder.entries()[0].message, "GET /api/status: panicked: index out of bounds");
+    assert_eq!(recorder.entries().len(), 2);
+}
+
+#[test]
+fn test_csv_to_response() {
+    let res = csv_to_response("Cluster\r\n", "jobs_2000.01.01-09-00.csv").unwrap();
+    assert_eq!(res.status, Some(status::Ok));
+    assert_eq!(res.headers.get::<ContentType>(), Some(&ContentType(Mime(TopLevel::Text, SubLevel::Ext("csv".to_string()), vec![(Attr::Charset, Value::Utf8)]))));
+    assert_eq!(res.headers.get_raw("Content-Disposition"), Some(&[b"attachment; filename=\"jobs_2000.01.01-09-00.csv\"".to_vec()][..]));
+}
+
+#[test]
+fn test_negotiate_format() {
+    use iron::headers::Quality;
+
+    let plain = QualityItem{ item: Mime(TopLevel::Text, SubLevel::Plain, vec![]), quality: Quality(1000) };
+    let html = QualityItem{ item: Mime(TopLevel::Text, SubLevel::Html, vec![]), quality: Quality(1000) };
+    let json = QualityItem{ item: Mime(TopLevel::Application, SubLevel::Json, vec![]), quality: Quality(900) };
+    let any = QualityItem{ item: Mime(TopLevel::Star, SubLevel::Star, vec![]), quality: Quality(800) };
+    let no_html = QualityItem{ item: Mime(TopLevel::Text, SubLevel::Html, vec![]), quality: Quality(0) };
+
+    assert_eq!(negotiate_format(&[plain.clone()]), OutputFormat::Text);
+    assert_eq!(negotiate_format(&[plain.clone(), any.clone()]), OutputFormat::Text);
+    assert_eq!(negotiate_format(&[html.clone(), plain.clone()]), OutputFormat::Html);
+    assert_eq!(negotiate_format(&[plain.clone(), no_html]), OutputFormat::Text);
+    assert_eq!(negotiate_format(&[json.clone()]), OutputFormat::Json);
+    assert_eq!(negotiate_format(&[plain, json.clone()]), OutputFormat::Text);
+    assert_eq!(negotiate_format(&[html, json]), OutputFormat::Html);
+    assert_eq!(negotiate_format(&[any]), OutputFormat::Html);
+    assert_eq!(negotiate_format(&[]), OutputFormat::Html);
+}
+
+#[test]
+fn test_etag_matches() {
+    assert!(etag_matches(&[b"\"abc-html\"".to_vec()], "\"abc-html\""));
+    assert!(etag_matches(&[b"\"old-html\", W/\"abc-html\"".to_vec()], "\"abc-html\""));
+    assert!(etag_matches(&[b"*".to_vec()], "\"abc-html\""));
+    assert!(!etag_matches(&[b"\"abc-json\"".to_vec()], "\"abc-html\""));
+    assert!(!etag_matches(&[], "\"abc-html\""));
+}
+
+#[test]
+fn test_rendered_to_response() {
+    use sinfo_util::get_partition_node_info_test;
+    use squeue_util::get_job_info_test;
+
+    let mut status = SlurmStatus::new();
+    status.node_info = get_partition_node_info_test();
+    status.job_info = get_job_info_test();
+    status.last_update = "2000.01.01 - 09:00".to_string();
+    let rendered = RenderedStatus::render(&status, &PageOptions::default());
+    let entity_tag = rendered.entity_tag(OutputFormat::Json);
+
+    let res = rendered_to_response(&rendered, OutputFormat::Json, None, &[]).unwrap();
+    assert_eq!(res.status, Some(status::Ok));
+    assert_eq!(res.headers.get::<ContentType>(), Some(&ContentType(utf8_mime(TopLevel::Application, SubLevel::Json))));
+    assert_eq!(res.headers.get_raw("ETag"), Some(&[entity_tag.clone().into_bytes()][..]));
+
+    let res = rendered_to_response(&rendered, OutputFormat::Html, Some(PageEncoding::Gzip), &[]).unwrap();
+    assert_eq!(res.headers.get::<ContentEncoding>(), Some(&ContentEncoding(vec![Encoding::Gzip])));
+
+    let res = rendered_to_response(&rendered, OutputFormat::Json, None, &[entity_tag.clone().into_bytes()]).unwrap();
+    assert_eq!(res.status, Some(status::NotModified));
+    assert_eq!(res.headers.get_raw("ETag"), Some(&[entity_tag.into_bytes()][..]));
+
+    let res = rendered_to_response(&rendered, OutputFormat::Text, None, &[rendered.entity_tag(OutputFormat::Json).into_bytes()]).unwrap();
+    assert_eq!(res.status, Some(status::Ok));
+}
+
+#[test]
+fn test_response_headers() {
+    use iron::response::ResponseBody;
+
+    // Multi-byte UTF-8: the Content-Length is the number of bytes, not of characters
+    let text = "Jörg on nöde01 – 💻";
+    let length = Some(&ContentLength(text.len() as u64));
+    assert!(text.len() > text.chars().count());
+
+    let res = string_to_response(text).unwrap();
+    assert_eq!(res.headers.get::<ContentType>(), Some(&ContentType(utf8_mime(TopLevel::Text, SubLevel::Html))));
+    assert_eq!(res.headers.get::<ContentLength>(), length);
+    let res = json_to_response(text).unwrap();
+    assert_eq!(res.headers.get::<ContentType>(), Some(&ContentType(utf8_mime(TopLevel::Application, SubLevel::Json))));
+    assert_eq!(res.headers.get::<ContentLength>(), length);
+    let res = text_to_response(text).unwrap();
+    assert_eq!(res.headers.get::<ContentType>(), Some(&ContentType(utf8_mime(TopLevel::Text, SubLevel::Plain))));
+    assert_eq!(res.headers.get::<ContentLength>(), length);
+    let res = csv_to_response(text, "jobs.csv").unwrap();
+    assert_eq!(res.headers.get::<ContentType>(), Some(&ContentType(utf8_mime(TopLevel::Text, SubLevel::Ext("csv".to_string())))));
+    assert_eq!(res.headers.get::<ContentLength>(), length);
+    let res = css_to_response(text, DEFAULT_CSS_MAX_AGE).unwrap();
+    assert_eq!(res.headers.get::<ContentType>(), Some(&ContentType(utf8_mime(TopLevel::Text, SubLevel::Css))));
+    assert_eq!(res.headers.get::<ContentLength>(), length);
+    assert_eq!(format_mime(OutputFormat::Html), utf8_mime(TopLevel::Text, SubLevel::Html));
+
+    // Compressed responses have the length of the compressed body
+    let page = CompressedPage::new(text.repeat(200));
+    let res = page_to_response(&page, format_mime(OutputFormat::Html), Some(PageEncoding::Gzip)).unwrap();
+    assert_eq!(res.headers.get::<ContentLength>(), Some(&ContentLength(page.gzip.as_ref().unwrap().len() as u64)));
+    let res = page_to_response(&page, format_mime(OutputFormat::Html), None).unwrap();
+    assert_eq!(res.headers.get::<ContentLength>(), Some(&ContentLength(page.plain.len() as u64)));
+
+    // HEAD: same headers, empty body
+    let res = without_body(string_to_response(text).unwrap());
+    assert_eq!(res.headers.get::<ContentLength>(), length);
+    let mut body = Vec::new();
+    res.body.unwrap().write_body(&mut ResponseBody::new(&mut body)).unwrap();
+    assert!(body.is_empty());
+}
+
+#[test]
+fn test_rendered_to_response_template_error() {
+    use std::sync::Arc;
+    use template::Templates;
+
+    let mut templates = Templates::new();
+    templates.register("status", "{{> missing}}").unwrap();
+    let rendered = RenderedStatus::render(&SlurmStatus::new(), &PageOptions{ templates: Arc::new(templates), .. PageOptions::default() });
+    assert_eq!(rendered.html_error, Some("template 'status': unknown partial 'missing'".to_string()));
+
+    // Only the HTML page fails, the other formats are still sent
+    let res = rendered_to_response(&rendered, OutputFormat::Html, None, &[]).unwrap();
+    assert_eq!(res.status, Some(status::InternalServerError));
+    let res = rendered_to_response(&rendered, OutputFormat::Json, None, &[]).unwrap();
+    assert_eq!(res.status, Some(status::Ok));
+}
+
+#[test]
+fn test_get_status_filter() {
+    use squeue_util::JobState;
+    use sinfo_util::NodeState;
+
+    let filter = get_status_filter(Some("user=willi&job_state=pending,Running&node_state=down,broken"), &Configuration::default(), false);
+    assert_eq!(filter.user, Some("willi".to_string()));
+    assert_eq!(filter.job_states, vec![JobState::Pending, JobState::Running]);
+    assert_eq!(filter.node_states, vec![NodeState::Down]);
+    assert_eq!(filter.unknown_states, vec!["broken".to_string()]);
+
+    let filter = get_status_filter(Some("state=pd&user=willi"), &Configuration{ anonymize: true, .. Configuration::default() }, false);
+    assert_eq!(filter, StatusFilter{ job_states: vec![JobState::Pending], .. StatusFilter::new() });
+
+    assert!(get_status_filter(None, &Configuration::default(), false).is_empty());
+
+    // The web page hides the finished jobs by default, the API shows them
+    assert!(get_status_filter(None, &Configuration::default(), true).hide_finished);
+    assert!(!get_status_filter(Some("all_jobs=1"), &Configuration::default(), true).hide_finished);
+    assert!(!get_status_filter(None, &Configuration::default(), false).hide_finished);
+    assert!(get_status_filter(Some("all_jobs=0"), &Configuration::default(), false).hide_finished);
+}
@@ -0,0 +1,183 @@
+//! Limits the number of requests that are handled at the same time
+//! Every request occupies a thread of the web server, so a misbehaving client with hundreds of connections
+//! gets "503 Service Unavailable" instead of tying up all threads and the memory of the host
+
+// System modules:
+use std::sync::{Mutex, MutexGuard};
+use std::time::Duration;
+
+// External modules:
+use iron::Timeouts;
+
+/// Default number of threads of the web server
+pub const DEFAULT_HTTP_THREADS: usize = 32;
+
+/// Default maximum number of requests that are handled at the same time
+/// Lower than the number of threads, so there are threads left for the /events streams and for the 503 responses
+pub const DEFAULT_MAX_REQUESTS: usize = 16;
+
+/// Default read timeout of the connections in seconds: a client that doesn't send its request in time is disconnected
+pub const DEFAULT_HTTP_READ_TIMEOUT: u64 = 30;
+
+/// Default write timeout of the connections in seconds: a client that doesn't read the answer (or an /events stream) in time is disconnected
+pub const DEFAULT_HTTP_WRITE_TIMEOUT: u64 = 30;
+
+/// Public helper function that returns the timeouts of the connections of the web server (--http-read-timeout and --http-write-timeout)
+/// in seconds, 0 means no timeout. The keep-alive timeout stays the default of Iron
+pub fn http_timeouts(read: u64, write: u64) -> Timeouts {
+    let timeout = |seconds: u64| if seconds == 0 { None } else { Some(Duration::from_secs(seconds)) };
+    Timeouts { read: timeout(read), write: timeout(write), .. Timeouts::default() }
+}
+
+// Private state of the limiter, protected by the Mutex of RequestLimiter
+#[derive(Debug)]
+struct LimiterState {
+    /// Number of requests that are being handled right now
+    in_flight: usize,
+    /// Number of requests that have been rejected since the last accepted one, to log only the start and the end of an overload
+    rejected: u64
+}
+
+/// Shared between the threads of the web server, each request has to get a permit first
+#[derive(Debug)]
+pub struct RequestLimiter {
+    /// Maximum number of requests at the same time, 0 means no limit
+    max_requests: usize,
+    state: Mutex<LimiterState>
+}
+
+/// Permit for one request, the request counts as finished when it's dropped
+#[derive(Debug)]
+pub struct RequestPermit<'a> {
+    limiter: &'a RequestLimiter
+}
+
+impl RequestLimiter {
+    /// Create a limiter for the given number of requests, 0 disables the limit
+    pub fn new(max_requests: usize) -> RequestLimiter {
+        RequestLimiter {
+            max_requests: max_requests,
+            state: Mutex::new(LimiterState {
+                in_flight: 0,
+                rejected: 0
+            })
+        }
+    }
+
+    // Private helper function to lock the state, a panic of another request handler doesn't leave it inconsistent
+    fn lock<'a>(&'a self) -> MutexGuard<'a, LimiterState> {
+        self.state.lock().unwrap_or_else(|err| err.into_inner())
+    }
+
+    /// Returns a permit if there are less than max_requests requests in flight, None if the request has to be rejected
+    pub fn try_acquire<'a>(&'a self) -> Option<RequestPermit<'a>> {
+        let mut state = self.lock();
+
+        if self.max_requests > 0 && state.in_flight >= self.max_requests {
+            if state.rejected == 0 {
+                warn!("{} requests are being handled, further requests are rejected with 503", state.in_flight);
+            }
+            state.rejected += 1;
+            return None;
+        }
+
+        if state.rejected > 0 {
+            info!("Requests are accepted again, {} requests have been rejected", state.rejected);
+            state.rejected = 0;
+        }
+        state.in_flight += 1;
+        Some(RequestPermit{ limiter: self })
+    }
+
+    /// Number of requests that are being handled right now
+    pub fn in_flight(&self) -> usize {
+        self.lock().in_flight
+    }
+}
+
+impl<'a> Drop for RequestPermit<'a> {
+    fn drop(&mut self) {
+        self.limiter.lock().in_flight -= 1;
+    }
+}
+
+#[test]
+fn test_request_limiter() {
+    let limiter = RequestLimiter::new(2);
+
+    let first = limiter.try_acquire();
+    let second = limiter.try_acquire();
+    assert!(first.is_some() && second.is_some());
+    assert_eq!(limiter.in_flight(), 2);
+    assert!(limiter.try_acquire().is_none());
+    assert!(limiter.try_acquire().is_none());
+    assert_eq!(limiter.lock().rejected, 2);
+
+    drop(first);
+    assert_eq!(limiter.in_flight(), 1);
+    assert!(limiter.try_acquire().is_some());
+    assert_eq!(limiter.lock().rejected, 0);
+    drop(second);
+    assert_eq!(limiter.in_flight(), 0);
+}
+
+#[test]
+fn test_request_limiter_unlimited() {
+    let limiter = RequestLimiter::new(0);
+    let permits: Vec<_> = (0..100).map(|_| limiter.try_acquire().unwrap()).collect();
+
+    assert_eq!(limiter.in_flight(), 100);
+    drop(permits);
+    assert_eq!(limiter.in_flight(), 0);
+}
+
+#[test]
+fn test_request_limiter_threads() {
+    use std::sync::{Arc, Barrier};
+    use std::sync::mpsc::channel;
+    use std::thread;
+
+    // Four simulated request handlers, the two that get a permit wait until the main thread releases them
+    let limiter = Arc::new(RequestLimiter::new(2));
+    let release = Arc::new(Barrier::new(3));
+    let (sender, receiver) = channel();
+
+    let handles: Vec<_> = (0..4).map(|_| {
+        let (limiter, release, sender) = (limiter.clone(), release.clone(), sender.clone());
+        thread::spawn(move || {
+            let accepted = match limiter.try_acquire() {
+                Some(_permit) => {
+                    release.wait();
+                    true
+                },
+                None => false
+            };
+            sender.send(accepted).unwrap();
+        })
+    }).collect();
+
+    // The rejected handlers finish first, the accepted ones are still waiting
+    assert_eq!(receiver.recv().unwrap(), false);
+    assert_eq!(receiver.recv().unwrap(), false);
+    assert_eq!(limiter.in_flight(), 2);
+
+    release.wait();
+    assert_eq!(receiver.recv().unwrap(), true);
+    assert_eq!(receiver.recv().unwrap(), true);
+    for handle in handles {
+        handle.join().unwrap();
+    }
+    assert_eq!(limiter.in_flight(), 0);
+    assert!(limiter.try_acquire().is_some());
+}
+
+#[test]
+fn test_http_timeouts() {
+    let timeouts = http_timeouts(DEFAULT_HTTP_READ_TIMEOUT, 5);
+    assert_eq!(timeouts.read, Some(Duration::from_secs(30)));
+    assert_eq!(timeouts.write, Some(Duration::from_secs(5)));
+    assert_eq!(timeouts.keep_alive, Timeouts::default().keep_alive);
+
+    let timeouts = http_timeouts(0, 0);
+    assert_eq!((timeouts.read, timeouts.write), (None, None));
+}
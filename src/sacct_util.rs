@@ -0,0 +1,288 @@
+//! Abstraction for the SLURM sacct command
+//! Runs sacct, parses output into data structure (FinishedJobInfo)
+
+// System modules:
+use std::collections::BTreeMap;
+use std::fmt;
+
+// External modules:
+use rustc_serialize::json::{Json, ToJson};
+
+// Internal modules:
+use squeue_util::{JobState, str_to_job_state};
+use command_runner::{CommandRunner, CommandConfig, run_command};
+
+/// Exit code of a job as reported by sacct: "exit status:signal"
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ExitCode {
+    /// The exit status of the job script
+    pub status: u32,
+    /// The signal that terminated the job, 0 if none
+    pub signal: u32
+}
+
+/// All the information about a SLURM job that has finished
+#[derive(Debug, Clone, PartialEq)]
+pub struct FinishedJobInfo {
+    pub job_id: String,
+    pub job_name: String,
+    pub user_name: String,
+    pub partition: String,
+    pub job_state: JobState,
+    pub exit_code: Option<ExitCode>,
+    pub elapsed: String,
+    pub end_time: String
+}
+
+impl ExitCode {
+    /// A guess why the job ended, None for a job that succeeded
+    /// This is only a heuristic: the same signal can come from different causes and a program can choose any exit status
+    pub fn hint(&self) -> Option<String> {
+        match (self.status, self.signal) {
+            (0, 0) => None,
+            (_, 9) => Some("killed by signal 9 (likely out of memory or scancel)".to_string()),
+            (_, 15) => Some("terminated by signal 15 (likely scancel or the time limit)".to_string()),
+            (_, 11) => Some("crashed with signal 11 (likely a segmentation fault)".to_string()),
+            (_, 6) => Some("aborted by signal 6 (likely a failed assertion or abort())".to_string()),
+            // SLURM reports 125 as signal of jobs that were killed by the out of memory handler
+            (_, 125) => Some("killed by signal 125 (likely out of memory)".to_string()),
+            (_, 0) => Some(exit_status_hint(self.status)),
+            (_, signal) => Some(format!("killed by signal {}", signal))
+        }
+    }
+}
+
+// Private helper function that guesses the cause of an exit status without signal
+// The shell of the job script returns 128 + signal if a command was killed by a signal
+fn exit_status_hint(status: u32) -> String {
+    match status {
+        126 => "exit code 126 (likely the command is not executable)".to_string(),
+        127 => "exit code 127 (likely command not found)".to_string(),
+        137 => "exit code 137 (likely a command killed by signal 9, out of memory or scancel)".to_string(),
+        status if status > 128 && status < 160 => format!("exit code {} (likely a command killed by signal {})", status, status - 128),
+        status => format!("exit code {}", status)
+    }
+}
+
+impl fmt::Display for ExitCode {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}:{}", self.status, self.signal)
+    }
+}
+
+impl ToJson for ExitCode {
+    fn to_json(&self) -> Json {
+        let mut result = BTreeMap::new();
+        result.insert("status".to_string(), self.status.to_json());
+        result.insert("signal".to_string(), self.signal.to_json());
+        result.insert("hint".to_string(), self.hint().to_json());
+        Json::Object(result)
+    }
+}
+
+impl ToJson for FinishedJobInfo {
+    fn to_json(&self) -> Json {
+        let mut result = BTreeMap::new();
+        result.insert("job_id".to_string(), self.job_id.to_json());
+        result.insert("job_name".to_string(), self.job_name.to_json());
+        result.insert("user_name".to_string(), self.user_name.to_json());
+        result.insert("partition".to_string(), self.partition.to_json());
+        result.insert("job_state".to_string(), self.job_state.to_json());
+        result.insert("exit_code".to_string(), self.exit_code.to_json());
+        result.insert("elapsed".to_string(), self.elapsed.to_json());
+        result.insert("end_time".to_string(), self.end_time.to_json());
+        Json::Object(result)
+    }
+}
+
+/// Public helper function to generate test data
+pub fn get_finished_job_info_test() -> Vec<FinishedJobInfo> {
+    let test_data = "
+        101|small_test01|user01|esd|COMPLETED|0:0|00:10:00|2000-01-01T08:10:00
+        101.batch|batch||esd|COMPLETED|0:0|00:10:00|2000-01-01T08:10:00
+        102|small_test02|user02|esd|FAILED|1:0|00:01:12|2000-01-01T08:20:00
+        103|small_test03|user03|esd|CANCELLED by 1002|0:15|01:00:00|2000-01-01T08:30:00
+        104|small_test04|user01|esd|TIMEOUT|0:1|02:00:00|2000-01-01T08:40:00
+        105|small_test05|user04|esd|NODE_FAIL|0:0|00:05:01|2000-01-01T08:50:00
+    ";
+
+    get_finished_job_info_util(test_data)
+}
+
+/// Public helper function to retrieve the list of jobs that finished in the last hours
+pub fn get_finished_job_info(runner: &dyn CommandRunner, hours: u64) -> Vec<FinishedJobInfo> {
+    get_finished_job_info_util(&call_sacct(runner, hours))
+}
+
+// Private helper function to parse the output of "sacct" and return a list of FinishedJobInfo
+fn get_finished_job_info_util(sacct_output: &str) -> Vec<FinishedJobInfo> {
+    let mut result: Vec<FinishedJobInfo> = Vec::new();
+
+    for line in sacct_output.lines() {
+        let items: Vec<&str> = line.trim().split('|').collect();
+
+        // Skip invalid line
+        if items.len() != 8 {
+            debug!("number of items in line: {}", items.len());
+            continue
+        }
+
+        // Skip job steps (1234.batch, 1234.0), they belong to the job itself
+        if items[0].contains('.') {
+            continue
+        }
+
+        result.push( FinishedJobInfo{
+                job_id: items[0].to_string(),
+                job_name: items[1].to_string(),
+                user_name: items[2].to_string(),
+                partition: items[3].to_string(),
+                // sacct adds the user id to cancelled jobs: "CANCELLED by 1000"
+                job_state: str_to_job_state(items[4].split_whitespace().next().unwrap_or("")),
+                exit_code: str_to_exit_code(items[5]),
+                elapsed: items[6].to_string(),
+                end_time: items[7].to_string()
+        })
+    }
+
+    result
+}
+
+#[test]
+fn test_get_finished_job_info_util_empty() {
+    assert_eq!(get_finished_job_info_util("").len(), 0);
+}
+
+#[test]
+fn test_get_finished_job_info_util_invalid() {
+    assert_eq!(get_finished_job_info_util("1|2|3|4").len(), 0);
+}
+
+#[test]
+fn test_get_finished_job_info_util_01() {
+    let input = "1234|small_test|willi|esd|COMPLETED|0:0|00:02:46|2015-11-12T09:54:18";
+    let output = vec![FinishedJobInfo{
+        job_id: "1234".to_string(),
+        job_name: "small_test".to_string(),
+        user_name: "willi".to_string(),
+        partition: "esd".to_string(),
+        job_state: JobState::Completed,
+        exit_code: Some(ExitCode{ status: 0, signal: 0 }),
+        elapsed: "00:02:46".to_string(),
+        end_time: "2015-11-12T09:54:18".to_string()
+    }];
+
+    assert_eq!(get_finished_job_info_util(input), output);
+}
+
+#[test]
+fn test_get_finished_job_info_util_02() {
+    let input = "1234|small_test|willi|esd|FAILED|1:0|00:02:46|2015-11-12T09:54:18\n1234.batch|batch||esd|FAILED|1:0|00:02:46|2015-11-12T09:54:18\n1235|job name|willi|esd|CANCELLED by 1000|0:15|00:00:10|2015-11-12T10:00:00";
+    let output = vec![
+        FinishedJobInfo{
+            job_id: "1234".to_string(),
+            job_name: "small_test".to_string(),
+            user_name: "willi".to_string(),
+            partition: "esd".to_string(),
+            job_state: JobState::Failed,
+            exit_code: Some(ExitCode{ status: 1, signal: 0 }),
+            elapsed: "00:02:46".to_string(),
+            end_time: "2015-11-12T09:54:18".to_string()
+        },
+        FinishedJobInfo{
+            job_id: "1235".to_string(),
+            job_name: "job name".to_string(),
+            user_name: "willi".to_string(),
+            partition: "esd".to_string(),
+            job_state: JobState::Cancelled,
+            exit_code: Some(ExitCode{ status: 0, signal: 15 }),
+            elapsed: "00:00:10".to_string(),
+            end_time: "2015-11-12T10:00:00".to_string()
+        }
+    ];
+
+    assert_eq!(get_finished_job_info_util(input), output);
+}
+
+/*
+    sacct -n -P -o JobID,JobName,User,Partition,State,ExitCode,Elapsed,End --starttime=now-24hours
+    -n: no header
+    -P: output is delimited by "|", no trailing "|"
+    JobID: Job id / job step id
+    JobName: Job name / job step name
+    User: User name (empty for job steps)
+    Partition: Partition the job ran in
+    State: Job state, "CANCELLED by <uid>" for cancelled jobs
+    ExitCode: "exit status:signal"
+    Elapsed: Run time of the job
+    End: End time of the job
+
+    Output looks like:
+    82|small_test|willi|esd|COMPLETED|0:0|00:02:46|2015-11-12T09:54:18
+    82.batch|batch||esd|COMPLETED|0:0|00:02:46|2015-11-12T09:54:18
+*/
+
+// Private helper function to execute the external "sacct" SLURM command and return its output into a string
+// On error returns an empty string. TODO: better error handling
+fn call_sacct(runner: &dyn CommandRunner, hours: u64) -> String {
+    let start_time = format!("--starttime=now-{}hours", hours);
+
+    // return empty string on error (run_command logs it), but continue with the program
+    run_command(runner, &CommandConfig::new("sacct", &[]), &["-n", "-P", "-o", "JobID,JobName,User,Partition,State,ExitCode,Elapsed,End", &start_time])
+        .unwrap_or(String::new())
+}
+
+// Private helper function to parse the exit code: "exit status:signal"
+fn str_to_exit_code(exit_code: &str) -> Option<ExitCode> {
+    let items: Vec<&str> = exit_code.split(':').collect();
+
+    if items.len() != 2 {
+        return None;
+    }
+
+    match (items[0].parse::<u32>(), items[1].parse::<u32>()) {
+        (Ok(status), Ok(signal)) => Some(ExitCode{ status: status, signal: signal }),
+        _ => None
+    }
+}
+
+#[test]
+fn test_str_to_exit_code_success() {
+    assert_eq!(str_to_exit_code("0:0"), Some(ExitCode{ status: 0, signal: 0 }));
+}
+
+#[test]
+fn test_str_to_exit_code_status() {
+    assert_eq!(str_to_exit_code("1:0"), Some(ExitCode{ status: 1, signal: 0 }));
+    assert_eq!(str_to_exit_code("127:0"), Some(ExitCode{ status: 127, signal: 0 }));
+}
+
+#[test]
+fn test_str_to_exit_code_signal() {
+    assert_eq!(str_to_exit_code("0:15"), Some(ExitCode{ status: 0, signal: 15 }));
+    assert_eq!(str_to_exit_code("0:9"), Some(ExitCode{ status: 0, signal: 9 }));
+}
+
+#[test]
+fn test_exit_code_hint() {
+    let hint = |exit_code: &str| str_to_exit_code(exit_code).and_then(|exit_code| exit_code.hint());
+
+    assert_eq!(hint("0:0"), None);
+    assert_eq!(hint("1:0"), Some("exit code 1".to_string()));
+    assert_eq!(hint("127:0"), Some("exit code 127 (likely command not found)".to_string()));
+    assert_eq!(hint("137:0"), Some("exit code 137 (likely a command killed by signal 9, out of memory or scancel)".to_string()));
+    assert_eq!(hint("139:0"), Some("exit code 139 (likely a command killed by signal 11)".to_string()));
+    assert_eq!(hint("0:9"), Some("killed by signal 9 (likely out of memory or scancel)".to_string()));
+    assert_eq!(hint("0:15"), Some("terminated by signal 15 (likely scancel or the time limit)".to_string()));
+    assert_eq!(hint("0:125"), Some("killed by signal 125 (likely out of memory)".to_string()));
+    assert_eq!(hint("0:2"), Some("killed by signal 2".to_string()));
+    assert_eq!(str_to_exit_code("0:9").map(|exit_code| exit_code.to_string()), Some("0:9".to_string()));
+}
+
+#[test]
+fn test_str_to_exit_code_invalid() {
+    assert_eq!(str_to_exit_code(""), None);
+    assert_eq!(str_to_exit_code("1"), None);
+    assert_eq!(str_to_exit_code("a:b"), None);
+    assert_eq!(str_to_exit_code("1:2:3"), None);
+}
@@ -0,0 +1,187 @@
+//! Abstraction for the SLURM sacctmgr command
+//! Runs "sacctmgr show qos", parses output into data structure (QosInfo) and explains the QOS state reasons of pending jobs
+//! with the limits of the QOS
+
+// System modules:
+use std::collections::BTreeMap;
+
+// External modules:
+use rustc_serialize::json::{Json, ToJson};
+
+// Internal modules:
+use command_runner::{CommandRunner, CommandConfig, run_command};
+use squeue_util::StateReason;
+
+/// Fields of "sacctmgr show qos", in this order
+const QOS_FORMAT: &'static str = "format=Name,Priority,MaxWall,MaxTRESPU,MaxJobsPU,MaxSubmitPU";
+
+/// Priority and per user limits of one QOS, a limit is None (or the TRES are empty) if the QOS doesn't set it
+#[derive(Debug, Clone, PartialEq)]
+pub struct QosInfo {
+    pub name: String,
+    pub priority: Option<u32>,
+    /// Maximum run time of a job, for example "2-00:00:00"
+    pub max_wall: Option<String>,
+    /// Maximum trackable resources of all running jobs of a user, for example [("cpu", "64"), ("gres/gpu", "4")]
+    pub max_tres_per_user: Vec<(String, String)>,
+    /// Maximum number of running jobs of a user
+    pub max_jobs_per_user: Option<u32>,
+    /// Maximum number of pending and running jobs of a user
+    pub max_submit_per_user: Option<u32>
+}
+
+impl QosInfo {
+    /// The limit of one trackable resource (for example "cpu" or "mem") per user
+    pub fn tres_limit(&self, tres: &str) -> Option<String> {
+        self.max_tres_per_user.iter().find(|&&(ref name, _)| name == tres).map(|&(_, ref value)| value.clone())
+    }
+
+    /// The limits of all generic resources per user, for example "gres/gpu=4", None if the QOS has none
+    pub fn gres_limit(&self) -> Option<String> {
+        let gres: Vec<String> = self.max_tres_per_user.iter().filter(|&&(ref name, _)| name.starts_with("gres/"))
+            .map(|&(ref name, ref value)| format!("{}={}", name, value)).collect();
+        if gres.is_empty() { None } else { Some(gres.join(",")) }
+    }
+
+    /// The limits of the trackable resources as text, for example "cpu=64,gres/gpu=4", empty if the QOS has none
+    pub fn tres_text(&self) -> String {
+        self.max_tres_per_user.iter().map(|&(ref name, ref value)| format!("{}={}", name, value)).collect::<Vec<String>>().join(",")
+    }
+}
+
+impl ToJson for QosInfo {
+    fn to_json(&self) -> Json {
+        let mut max_tres_per_user = BTreeMap::new();
+        for &(ref name, ref value) in &self.max_tres_per_user {
+            max_tres_per_user.insert(name.clone(), value.to_json());
+        }
+
+        let mut result = BTreeMap::new();
+        result.insert("name".to_string(), self.name.to_json());
+        result.insert("priority".to_string(), self.priority.to_json());
+        result.insert("max_wall".to_string(), self.max_wall.to_json());
+        result.insert("max_tres_per_user".to_string(), Json::Object(max_tres_per_user));
+        result.insert("max_jobs_per_user".to_string(), self.max_jobs_per_user.to_json());
+        result.insert("max_submit_per_user".to_string(), self.max_submit_per_user.to_json());
+        Json::Object(result)
+    }
+}
+
+/// Public helper function to generate test data, "debug" has no TRES limit and no submit limit
+pub fn get_qos_info_test() -> Vec<QosInfo> {
+    let test_data = "
+        normal|50|2-00:00:00|cpu=64,gres/gpu=4|50|100
+        long|10|7-00:00:00|cpu=16,mem=200G|10|20
+        debug|100|00:30:00||2|
+    ";
+
+    get_qos_info_util(test_data)
+}
+
+/// Public helper function to retrieve the QOS of the cluster
+/// Returns an error if sacctmgr could not be executed
+pub fn get_qos_info(runner: &dyn CommandRunner) -> Result<Vec<QosInfo>, String> {
+    run_command(runner, &CommandConfig::new("sacctmgr", &[]), &["-n", "-P", "show", "qos", QOS_FORMAT])
+        .map(|output| get_qos_info_util(&output))
+}
+
+// Private helper function to parse the output of "sacctmgr -n -P show qos" and return a list of QosInfo
+// Each QOS is one line with the fields separated by "|", empty fields are limits that are not set
+fn get_qos_info_util(sacctmgr_output: &str) -> Vec<QosInfo> {
+    let mut result: Vec<QosInfo> = Vec::new();
+
+    for line in sacctmgr_output.lines() {
+        let items: Vec<&str> = line.split('|').map(|item| item.trim()).collect();
+
+        // Skip invalid line
+        if items.len() != 6 || items[0].is_empty() {
+            debug!("number of items in line: {}", items.len());
+            continue
+        }
+
+        result.push( QosInfo{
+                name: items[0].to_string(),
+                priority: items[1].parse::<u32>().ok(),
+                max_wall: if items[2].is_empty() { None } else { Some(items[2].to_string()) },
+                max_tres_per_user: items[3].split(',').filter_map(|tres| {
+                    let mut parts = tres.splitn(2, '=');
+                    match (parts.next(), parts.next()) {
+                        (Some(name), Some(value)) if !name.is_empty() => Some((name.to_string(), value.to_string())),
+                        _ => None
+                    }
+                }).collect(),
+                max_jobs_per_user: items[4].parse::<u32>().ok(),
+                max_submit_per_user: items[5].parse::<u32>().ok()
+        })
+    }
+
+    result
+}
+
+#[test]
+fn test_get_qos_info_util() {
+    assert!(get_qos_info_util("").is_empty());
+
+    let qos = get_qos_info_test();
+    assert_eq!(qos.len(), 3);
+    assert_eq!(qos[0], QosInfo {
+        name: "normal".to_string(),
+        priority: Some(50),
+        max_wall: Some("2-00:00:00".to_string()),
+        max_tres_per_user: vec![("cpu".to_string(), "64".to_string()), ("gres/gpu".to_string(), "4".to_string())],
+        max_jobs_per_user: Some(50),
+        max_submit_per_user: Some(100)
+    });
+    assert_eq!(qos[2].max_tres_per_user, Vec::new());
+    assert_eq!(qos[2].max_submit_per_user, None);
+
+    // Wrong number of fields and lines without a name are skipped
+    assert!(get_qos_info_util("normal|50|2-00:00:00|cpu=64\n|10|||1|2\n").is_empty());
+}
+
+#[test]
+fn test_qos_info_limits() {
+    let qos = get_qos_info_test();
+    assert_eq!(qos[1].tres_limit("mem"), Some("200G".to_string()));
+    assert_eq!(qos[1].tres_limit("node"), None);
+    assert_eq!(qos[0].gres_limit(), Some("gres/gpu=4".to_string()));
+    assert_eq!(qos[1].gres_limit(), None);
+    assert_eq!(qos[0].tres_text(), "cpu=64,gres/gpu=4");
+    assert_eq!(qos[0].to_json().find_path(&["max_tres_per_user", "gres/gpu"]).and_then(|val| val.as_string()), Some("4"));
+}
+
+/// Public helper function that returns the QOS limits behind the state reason of a pending job as text for its tooltip,
+/// for example "Max. CPUs per user: normal 64, long 16". squeue doesn't tell the QOS of the job, so every QOS
+/// that sets the limit is listed. None for the other reasons or if no QOS sets the limit
+pub fn qos_limits_text(reason: StateReason, qos_info: &[QosInfo]) -> Option<String> {
+    let (label, limit): (&str, fn(&QosInfo) -> Option<String>) = match reason {
+        StateReason::QOSMaxCpuPerUserLimit => ("Max. CPUs per user", |qos| qos.tres_limit("cpu")),
+        StateReason::QOSMaxNodePerUserLimit => ("Max. nodes per user", |qos| qos.tres_limit("node")),
+        StateReason::QOSMaxMemoryPerUser => ("Max. memory per user", |qos| qos.tres_limit("mem")),
+        StateReason::QOSMaxGRESPerUser => ("Max. GRES per user", |qos| qos.gres_limit()),
+        StateReason::QOSMaxJobsPerUserLimit => ("Max. running jobs per user", |qos| qos.max_jobs_per_user.map(|jobs| jobs.to_string())),
+        StateReason::QOSMaxSubmitJobPerUserLimit => ("Max. submitted jobs per user", |qos| qos.max_submit_per_user.map(|jobs| jobs.to_string())),
+        StateReason::QOSMaxWallDurationPerJobLimit => ("Max. wall time", |qos| qos.max_wall.clone()),
+        _ => return None
+    };
+
+    let limits: Vec<String> = qos_info.iter().filter_map(|qos| limit(qos).map(|value| format!("{} {}", qos.name, value))).collect();
+    if limits.is_empty() { None } else { Some(format!("{}: {}", label, limits.join(", "))) }
+}
+
+#[test]
+fn test_qos_limits_text() {
+    let qos = get_qos_info_test();
+    assert_eq!(qos_limits_text(StateReason::QOSMaxCpuPerUserLimit, &qos), Some("Max. CPUs per user: normal 64, long 16".to_string()));
+    assert_eq!(qos_limits_text(StateReason::QOSMaxMemoryPerUser, &qos), Some("Max. memory per user: long 200G".to_string()));
+    assert_eq!(qos_limits_text(StateReason::QOSMaxGRESPerUser, &qos), Some("Max. GRES per user: normal gres/gpu=4".to_string()));
+    assert_eq!(qos_limits_text(StateReason::QOSMaxJobsPerUserLimit, &qos), Some("Max. running jobs per user: normal 50, long 10, debug 2".to_string()));
+    assert_eq!(qos_limits_text(StateReason::QOSMaxSubmitJobPerUserLimit, &qos), Some("Max. submitted jobs per user: normal 100, long 20".to_string()));
+    assert_eq!(qos_limits_text(StateReason::QOSMaxWallDurationPerJobLimit, &qos),
+        Some("Max. wall time: normal 2-00:00:00, long 7-00:00:00, debug 00:30:00".to_string()));
+
+    // No QOS sets the limit, other reasons and no QOS information
+    assert_eq!(qos_limits_text(StateReason::QOSMaxNodePerUserLimit, &qos), None);
+    assert_eq!(qos_limits_text(StateReason::Resources, &qos), None);
+    assert_eq!(qos_limits_text(StateReason::QOSMaxCpuPerUserLimit, &[]), None);
+}
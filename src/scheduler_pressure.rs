@@ -0,0 +1,211 @@
+//! Scheduler pressure: indicators of the pending jobs that show if the cluster keeps up with the submitted jobs
+//! The indicators are computed with the other headline numbers (see slurm_status::dashboard_summary) and shown as second row
+//! of the dashboard. An indicator turns amber or red when it reaches its threshold (--pressure-thresholds), /metrics has them as gauges
+
+// Internal modules:
+use slurm_status::DashboardSummary;
+use inspector_metrics::escape_label;
+
+/// Names of the indicators in --pressure-thresholds
+pub const PRESSURE_INDICATORS: [&'static str; 5] = ["pending_users", "oldest_pending", "pending_ratio", "pending_resources", "pending_priority"];
+
+/// How bad the value of an indicator is compared with its thresholds
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum PressureLevel {
+    Normal,
+    Amber,
+    Red
+}
+
+impl PressureLevel {
+    /// CSS class of the dashboard box, None if the box keeps its normal color
+    pub fn css_class(&self) -> Option<&'static str> {
+        match *self {
+            PressureLevel::Normal => None,
+            PressureLevel::Amber => Some("dashboard_amber"),
+            PressureLevel::Red => Some("dashboard_red")
+        }
+    }
+}
+
+/// The value of an indicator is amber from the first threshold on and red from the second threshold on
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Threshold {
+    pub amber: f64,
+    pub red: f64
+}
+
+/// The thresholds of all indicators, None: the indicator always has the normal color
+#[derive(Debug, Clone, PartialEq)]
+pub struct PressureThresholds {
+    pub pending_users: Option<Threshold>,
+    /// In hours
+    pub oldest_pending: Option<Threshold>,
+    pub pending_ratio: Option<Threshold>,
+    pub pending_resources: Option<Threshold>,
+    pub pending_priority: Option<Threshold>
+}
+
+impl Default for PressureThresholds {
+    /// The number of users and jobs depends too much on the size of the cluster, so only the wait time
+    /// (1 day / 3 days) and the ratio (2 / 5 pending jobs per running job) have a threshold by default
+    fn default() -> PressureThresholds {
+        PressureThresholds {
+            pending_users: None,
+            oldest_pending: Some(Threshold{ amber: 24.0, red: 72.0 }),
+            pending_ratio: Some(Threshold{ amber: 2.0, red: 5.0 }),
+            pending_resources: None,
+            pending_priority: None
+        }
+    }
+}
+
+impl PressureThresholds {
+    // Private helper function that returns the threshold with the given name of PRESSURE_INDICATORS
+    fn threshold_mut(&mut self, name: &str) -> Option<&mut Option<Threshold>> {
+        match name {
+            "pending_users" => Some(&mut self.pending_users),
+            "oldest_pending" => Some(&mut self.oldest_pending),
+            "pending_ratio" => Some(&mut self.pending_ratio),
+            "pending_resources" => Some(&mut self.pending_resources),
+            "pending_priority" => Some(&mut self.pending_priority),
+            _ => None
+        }
+    }
+
+    /// The thresholds in the format of --pressure-thresholds, for example "oldest_pending=24:72,pending_ratio=2:5"
+    pub fn to_text(&self) -> String {
+        let thresholds = [self.pending_users, self.oldest_pending, self.pending_ratio, self.pending_resources, self.pending_priority];
+        PRESSURE_INDICATORS.iter().zip(thresholds.iter())
+            .filter_map(|(name, threshold)| threshold.map(|threshold| format!("{}={}:{}", name, threshold.amber, threshold.red)))
+            .collect::<Vec<String>>().join(",")
+    }
+}
+
+/// Public helper function to parse --pressure-thresholds: a comma separated list of indicator=amber:red, for example
+/// "pending_users=20:50,oldest_pending=12:48", or indicator=none to turn the colors of the indicator off.
+/// The indicators that are not in the list keep the default thresholds. Returns the invalid entry as error
+pub fn parse_pressure_thresholds(value: &str) -> Result<PressureThresholds, String> {
+    let mut result = PressureThresholds::default();
+
+    for entry in value.split(',').map(|entry| entry.trim()).filter(|entry| !entry.is_empty()) {
+        let mut parts = entry.splitn(2, '=');
+        let name = parts.next().unwrap_or("").trim();
+        let value = parts.next().unwrap_or("").trim();
+        let threshold = if value == "none" {
+            None
+        } else {
+            let mut values = value.splitn(2, ':').map(|value| value.trim().parse::<f64>().ok());
+            match (values.next(), values.next()) {
+                (Some(Some(amber)), Some(Some(red))) if amber >= 0.0 && amber <= red => Some(Threshold{ amber: amber, red: red }),
+                _ => return Err(entry.to_string())
+            }
+        };
+        *result.threshold_mut(name).ok_or(entry.to_string())? = threshold;
+    }
+
+    Ok(result)
+}
+
+/// Public helper function that compares the value of an indicator with its thresholds
+pub fn pressure_level(value: f64, threshold: Option<Threshold>) -> PressureLevel {
+    match threshold {
+        Some(threshold) if value >= threshold.red => PressureLevel::Red,
+        Some(threshold) if value >= threshold.amber => PressureLevel::Amber,
+        _ => PressureLevel::Normal
+    }
+}
+
+/// Public helper function that returns the number of pending jobs per running job
+/// Without running jobs every pending job counts, so a cluster where nothing runs still shows the pressure
+pub fn pending_ratio(pending_jobs: u32, running_jobs: u32) -> f64 {
+    pending_jobs as f64 / running_jobs.max(1) as f64
+}
+
+/// Public helper function that returns the indicators as gauges in the Prometheus text format, appended to /metrics
+pub fn pressure_to_prometheus(summary: &DashboardSummary, cluster_name: &str) -> String {
+    let mut result = String::new();
+    let cluster = escape_label(cluster_name);
+    let values = [
+        ("slurm_inspector_pending_users", "Number of distinct users with pending jobs", "", summary.pending_users as f64),
+        ("slurm_inspector_oldest_pending_seconds", "Wait time of the pending job that waits longest", "", summary.oldest_pending.unwrap_or(0) as f64),
+        ("slurm_inspector_pending_ratio", "Number of pending jobs per running job", "", summary.pending_ratio()),
+        ("slurm_inspector_pending_jobs_by_reason", "Number of pending jobs waiting for resources or priority", ",reason=\"resources\"", summary.pending_resources as f64),
+        ("slurm_inspector_pending_jobs_by_reason", "", ",reason=\"priority\"", summary.pending_priority as f64)
+    ];
+
+    for &(name, help, labels, value) in &values {
+        if !help.is_empty() {
+            result.push_str(&format!("# HELP {} {}\n", name, help));
+            result.push_str(&format!("# TYPE {} gauge\n", name));
+        }
+        result.push_str(&format!("{}{{cluster=\"{}\"{}}} {}\n", name, cluster, labels, value));
+    }
+
+    result
+}
+
+#[test]
+fn test_pressure_level() {
+    let threshold = Some(Threshold{ amber: 2.0, red: 5.0 });
+    assert_eq!(pressure_level(0.0, threshold), PressureLevel::Normal);
+    assert_eq!(pressure_level(1.9, threshold), PressureLevel::Normal);
+    assert_eq!(pressure_level(2.0, threshold), PressureLevel::Amber);
+    assert_eq!(pressure_level(4.9, threshold), PressureLevel::Amber);
+    assert_eq!(pressure_level(5.0, threshold), PressureLevel::Red);
+    assert_eq!(pressure_level(1000.0, None), PressureLevel::Normal);
+    // Amber and red at the same value: red wins
+    assert_eq!(pressure_level(3.0, Some(Threshold{ amber: 3.0, red: 3.0 })), PressureLevel::Red);
+
+    assert_eq!(PressureLevel::Normal.css_class(), None);
+    assert_eq!(PressureLevel::Red.css_class(), Some("dashboard_red"));
+}
+
+#[test]
+fn test_pending_ratio() {
+    assert_eq!(pending_ratio(0, 0), 0.0);
+    assert_eq!(pending_ratio(10, 4), 2.5);
+    assert_eq!(pending_ratio(3, 0), 3.0);
+}
+
+#[test]
+fn test_parse_pressure_thresholds() {
+    assert_eq!(parse_pressure_thresholds(""), Ok(PressureThresholds::default()));
+    assert_eq!(PressureThresholds::default().to_text(), "oldest_pending=24:72,pending_ratio=2:5");
+
+    let thresholds = parse_pressure_thresholds("pending_users=20:50, oldest_pending=none,pending_priority=0.5:1.5").unwrap();
+    assert_eq!(thresholds.pending_users, Some(Threshold{ amber: 20.0, red: 50.0 }));
+    assert_eq!(thresholds.oldest_pending, None);
+    assert_eq!(thresholds.pending_ratio, Some(Threshold{ amber: 2.0, red: 5.0 }));
+    assert_eq!(thresholds.to_text(), "pending_users=20:50,pending_ratio=2:5,pending_priority=0.5:1.5");
+    // The text can be parsed again
+    assert_eq!(parse_pressure_thresholds(&thresholds.to_text()), Ok(PressureThresholds{ oldest_pending: Some(Threshold{ amber: 24.0, red: 72.0 }), .. thresholds }));
+
+    assert_eq!(parse_pressure_thresholds("queue_length=1:2"), Err("queue_length=1:2".to_string()));
+    assert_eq!(parse_pressure_thresholds("pending_users=20"), Err("pending_users=20".to_string()));
+    assert_eq!(parse_pressure_thresholds("pending_users=50:20"), Err("pending_users=50:20".to_string()));
+    assert_eq!(parse_pressure_thresholds("pending_users=-1:20"), Err("pending_users=-1:20".to_string()));
+    assert_eq!(parse_pressure_thresholds("pending_users"), Err("pending_users".to_string()));
+}
+
+#[test]
+fn test_pressure_to_prometheus() {
+    use slurm_status::{SlurmStatus, dashboard_summary};
+    use status_filter::StatusFilter;
+
+    let mut summary = dashboard_summary(&SlurmStatus::new(), &StatusFilter::new());
+    summary.pending_jobs = 6;
+    summary.running_jobs = 4;
+    summary.pending_users = 2;
+    summary.oldest_pending = Some(5400);
+    summary.pending_resources = 4;
+    summary.pending_priority = 1;
+
+    let text = pressure_to_prometheus(&summary, "cluster1");
+    assert!(text.contains("# TYPE slurm_inspector_pending_users gauge\nslurm_inspector_pending_users{cluster=\"cluster1\"} 2\n"));
+    assert!(text.contains("slurm_inspector_oldest_pending_seconds{cluster=\"cluster1\"} 5400\n"));
+    assert!(text.contains("slurm_inspector_pending_ratio{cluster=\"cluster1\"} 1.5\n"));
+    assert!(text.contains("slurm_inspector_pending_jobs_by_reason{cluster=\"cluster1\",reason=\"resources\"} 4\n"));
+    assert!(text.contains("slurm_inspector_pending_jobs_by_reason{cluster=\"cluster1\",reason=\"priority\"} 1\n"));
+    assert_eq!(text.matches("# TYPE slurm_inspector_pending_jobs_by_reason gauge").count(), 1);
+}
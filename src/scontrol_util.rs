@@ -0,0 +1,646 @@
+//! Abstraction for the SLURM scontrol command
+//! Runs "scontrol show reservations", "scontrol show partition" and "scontrol show licenses",
+//! parses output into data structures (ReservationInfo, PartitionLimits, LicenseInfo)
+
+// System modules:
+use std::collections::BTreeMap;
+use std::fmt;
+
+// External modules:
+use rustc_serialize::json::{Json, ToJson};
+use time::{strftime, at, get_time, Duration};
+
+// Internal modules:
+use command_runner::{CommandConfig, CommandRunner, run_command};
+use hostlist::expand_hostlist;
+use placeholder::{is_placeholder, optional_string};
+
+/// Reservations that start within this number of hours are highlighted
+pub const UPCOMING_RESERVATION_HOURS: i64 = 24;
+
+/// A reservation of nodes, for example for a maintenance
+#[derive(Debug, Clone, PartialEq)]
+pub struct ReservationInfo {
+    pub name: String,
+    /// Format: %Y-%m-%dT%H:%M:%S, None if scontrol printed something else
+    pub start_time: Option<String>,
+    pub end_time: Option<String>,
+    /// Single node names, scontrol prints them as compressed hostlist
+    pub nodes: Vec<String>,
+    /// Users and accounts that can use the reservation, empty if not restricted
+    pub users: Vec<String>,
+    pub accounts: Vec<String>,
+    /// For example MAINT, IGNORE_JOBS or DAILY
+    pub flags: Vec<String>,
+    /// ACTIVE or INACTIVE
+    pub state: Option<String>
+}
+
+impl ReservationInfo {
+    /// Create an empty reservation with the given name
+    pub fn new(name: &str) -> ReservationInfo {
+        ReservationInfo {
+            name: name.to_string(),
+            start_time: None,
+            end_time: None,
+            nodes: Vec::new(),
+            users: Vec::new(),
+            accounts: Vec::new(),
+            flags: Vec::new(),
+            state: None
+        }
+    }
+
+    /// Returns true if the reservation is active at the given time (format: %Y-%m-%dT%H:%M:%S)
+    pub fn is_active(&self, now: &str) -> bool {
+        match (self.start_time.as_ref(), self.end_time.as_ref()) {
+            (Some(start_time), Some(end_time)) => &start_time[..] <= now && now < &end_time[..],
+            _ => false
+        }
+    }
+
+    /// Returns true if the reservation starts after now, but not later than until (same format as is_active)
+    pub fn starts_within(&self, now: &str, until: &str) -> bool {
+        self.start_time.as_ref().map_or(false, |start_time| now < &start_time[..] && &start_time[..] <= until)
+    }
+
+    /// Returns true if it's a maintenance reservation
+    pub fn is_maintenance(&self) -> bool {
+        self.flags.iter().any(|flag| flag == "MAINT")
+    }
+}
+
+impl ToJson for ReservationInfo {
+    fn to_json(&self) -> Json {
+        let mut result = BTreeMap::new();
+        result.insert("name".to_string(), self.name.to_json());
+        result.insert("start_time".to_string(), self.start_time.to_json());
+        result.insert("end_time".to_string(), self.end_time.to_json());
+        result.insert("nodes".to_string(), self.nodes.to_json());
+        result.insert("users".to_string(), self.users.to_json());
+        result.insert("accounts".to_string(), self.accounts.to_json());
+        result.insert("flags".to_string(), self.flags.to_json());
+        result.insert("state".to_string(), self.state.to_json());
+        Json::Object(result)
+    }
+}
+
+/// A limit of a partition that can also be unlimited
+#[derive(Debug, Clone, PartialEq)]
+pub enum Limit<T> {
+    Unlimited,
+    Limited(T)
+}
+
+impl<T: fmt::Display> fmt::Display for Limit<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Limit::Unlimited => write!(f, "UNLIMITED"),
+            Limit::Limited(ref value) => write!(f, "{}", value)
+        }
+    }
+}
+
+impl<T: ToJson> ToJson for Limit<T> {
+    fn to_json(&self) -> Json {
+        match *self {
+            Limit::Unlimited => Json::String("UNLIMITED".to_string()),
+            Limit::Limited(ref value) => value.to_json()
+        }
+    }
+}
+
+/// Limits of a partition, None if the key is missing in the output of scontrol (older SLURM versions)
+#[derive(Debug, Clone, PartialEq)]
+pub struct PartitionLimits {
+    pub name: String,
+    /// Maximum run time of a job, for example "7-00:00:00"
+    pub max_time: Option<Limit<String>>,
+    /// Run time of a job that doesn't request one, None if there is no default ("NONE")
+    pub default_time: Option<Limit<String>>,
+    pub max_nodes: Option<Limit<u32>>,
+    pub max_cpus_per_node: Option<Limit<u32>>,
+    /// For example UP, DOWN, DRAIN or INACTIVE
+    pub state: Option<String>,
+    /// Groups that can use the partition, empty if all groups are allowed ("ALL")
+    pub allow_groups: Vec<String>
+}
+
+impl PartitionLimits {
+    /// Create partition limits with the given name where all keys are missing
+    pub fn new(name: &str) -> PartitionLimits {
+        PartitionLimits {
+            name: name.to_string(),
+            max_time: None,
+            default_time: None,
+            max_nodes: None,
+            max_cpus_per_node: None,
+            state: None,
+            allow_groups: Vec::new()
+        }
+    }
+}
+
+impl ToJson for PartitionLimits {
+    fn to_json(&self) -> Json {
+        let mut result = BTreeMap::new();
+        result.insert("name".to_string(), self.name.to_json());
+        result.insert("max_time".to_string(), self.max_time.to_json());
+        result.insert("default_time".to_string(), self.default_time.to_json());
+        result.insert("max_nodes".to_string(), self.max_nodes.to_json());
+        result.insert("max_cpus_per_node".to_string(), self.max_cpus_per_node.to_json());
+        result.insert("state".to_string(), self.state.to_json());
+        result.insert("allow_groups".to_string(), self.allow_groups.to_json());
+        Json::Object(result)
+    }
+}
+
+/// A license of the cluster, for example of a commercial application, None if the key is missing in the output of scontrol
+#[derive(Debug, Clone, PartialEq)]
+pub struct LicenseInfo {
+    pub name: String,
+    pub total: Option<u32>,
+    pub used: Option<u32>,
+    pub free: Option<u32>,
+    pub reserved: Option<u32>
+}
+
+impl LicenseInfo {
+    /// Create a license with the given name where all keys are missing
+    pub fn new(name: &str) -> LicenseInfo {
+        LicenseInfo {
+            name: name.to_string(),
+            total: None,
+            used: None,
+            free: None,
+            reserved: None
+        }
+    }
+
+    /// No license is free, jobs that need it pend with the reason "Licenses"
+    pub fn is_exhausted(&self) -> bool {
+        self.free == Some(0)
+    }
+}
+
+impl ToJson for LicenseInfo {
+    fn to_json(&self) -> Json {
+        let mut result = BTreeMap::new();
+        result.insert("name".to_string(), self.name.to_json());
+        result.insert("total".to_string(), self.total.to_json());
+        result.insert("used".to_string(), self.used.to_json());
+        result.insert("free".to_string(), self.free.to_json());
+        result.insert("reserved".to_string(), self.reserved.to_json());
+        Json::Object(result)
+    }
+}
+
+/// Public helper function to get the local time plus the given number of seconds in the format of the reservation times
+pub fn reservation_time(offset_seconds: i64) -> String {
+    strftime("%Y-%m-%dT%H:%M:%S", &at(get_time() + Duration::seconds(offset_seconds))).unwrap()
+}
+
+/// Public helper function to generate test data
+/// The times are relative to now, so there is always an active and an upcoming reservation
+pub fn get_reservation_info_test() -> Vec<ReservationInfo> {
+    let hour = 3600;
+    let test_data = format!("
+        ReservationName=course StartTime={} EndTime={} Duration=03:00:00
+           Nodes=node[05-06] NodeCnt=2 CoreCnt=2 Features=(null) PartitionName=esd Flags=
+           Users=(null) Accounts=teaching State=ACTIVE
+        ReservationName=maint StartTime={} EndTime={} Duration=08:00:00
+           Nodes=node[01-12] NodeCnt=12 CoreCnt=56 Features=(null) PartitionName=(null) Flags=MAINT,IGNORE_JOBS
+           Users=root Accounts=(null) State=INACTIVE
+        ReservationName=benchmark StartTime={} EndTime={} Duration=02:00:00
+           Nodes=node01 NodeCnt=1 CoreCnt=8 Features=(null) PartitionName=(null) Flags=DAILY
+           Users=user01,user02 Accounts=(null) State=INACTIVE
+    ",
+        reservation_time(-hour), reservation_time(2 * hour),
+        reservation_time(5 * hour), reservation_time(13 * hour),
+        reservation_time(72 * hour), reservation_time(74 * hour));
+
+    get_reservation_info_util(&test_data)
+}
+
+/// Public helper function to generate test data
+pub fn get_partition_limits_test() -> BTreeMap<String, PartitionLimits> {
+    let test_data = "
+        PartitionName=esd
+           AllowGroups=ALL AllowAccounts=ALL AllowQos=ALL
+           DefaultTime=01:00:00 MaxNodes=UNLIMITED MaxTime=7-00:00:00 MaxCPUsPerNode=UNLIMITED
+           Nodes=node[01-12] State=UP TotalCPUs=56 TotalNodes=12
+    ";
+
+    get_partition_limits_util(test_data)
+}
+
+/// Public helper function to generate test data, one of the licenses is used up
+pub fn get_license_info_test() -> Vec<LicenseInfo> {
+    let test_data = "
+        LicenseName=matlab Total=10 Used=3 Free=7 Reserved=0 Remote=no
+        LicenseName=ansys Total=4 Used=4 Free=0 Reserved=0 Remote=no
+        LicenseName=comsol@db Total=20 Used=5 Free=13 Reserved=2 Remote=yes
+    ";
+
+    get_license_info_util(test_data)
+}
+
+/// Public helper function to retrieve the current reservations
+/// Returns an error if scontrol could not be executed
+pub fn get_reservation_info(runner: &dyn CommandRunner, command: &CommandConfig) -> Result<Vec<ReservationInfo>, String> {
+    call_scontrol(runner, command, "reservations").map(|output| get_reservation_info_util(&output))
+}
+
+/// Public helper function to retrieve the limits of all partitions, the key is the partition name
+/// Returns an error if scontrol could not be executed
+pub fn get_partition_limits(runner: &dyn CommandRunner, command: &CommandConfig) -> Result<BTreeMap<String, PartitionLimits>, String> {
+    call_scontrol(runner, command, "partition").map(|output| get_partition_limits_util(&output))
+}
+
+/// Public helper function to retrieve the licenses of the cluster
+/// Returns an error if scontrol could not be executed
+pub fn get_license_info(runner: &dyn CommandRunner, command: &CommandConfig) -> Result<Vec<LicenseInfo>, String> {
+    call_scontrol(runner, command, "licenses").map(|output| get_license_info_util(&output))
+}
+
+// Private helper function to split the output into "key=value" pairs
+// Values can contain spaces (for example Comment=...), so words without "=" belong to the value before
+fn str_to_key_values(output: &str) -> Vec<(&str, String)> {
+    let mut result: Vec<(&str, String)> = Vec::new();
+
+    for word in output.split_whitespace() {
+        let mut items = word.splitn(2, '=');
+        match (items.next(), items.next()) {
+            (Some(key), Some(value)) if !key.is_empty() => result.push((key, value.to_string())),
+            _ => if let Some(last) = result.last_mut() {
+                last.1.push(' ');
+                last.1.push_str(word);
+            }
+        }
+    }
+
+    result
+}
+
+#[test]
+fn test_str_to_key_values() {
+    assert_eq!(str_to_key_values("ReservationName=maint Flags=\n   Comment=HPC course TRES=cpu=96"), vec![
+        ("ReservationName", "maint".to_string()),
+        ("Flags", "".to_string()),
+        ("Comment", "HPC course".to_string()),
+        ("TRES", "cpu=96".to_string())
+    ]);
+    assert!(str_to_key_values("No reservations in the system").is_empty());
+}
+
+// Private helper function to split the output into records, each one starts with the given key (for example "PartitionName")
+// Pairs before the first record are skipped
+fn str_to_records<'a>(output: &'a str, first_key: &str) -> Vec<Vec<(&'a str, String)>> {
+    let mut result: Vec<Vec<(&str, String)>> = Vec::new();
+
+    for (key, value) in str_to_key_values(output) {
+        if key == first_key {
+            result.push(vec![(key, value)]);
+        } else if let Some(record) = result.last_mut() {
+            record.push((key, value));
+        } else {
+            debug!("key before the first record: {}", key);
+        }
+    }
+
+    result
+}
+
+#[test]
+fn test_str_to_records() {
+    let records = str_to_records("Ignored=1\nPartitionName=a\n  State=UP\nPartitionName=b State=DOWN", "PartitionName");
+    assert_eq!(records, vec![
+        vec![("PartitionName", "a".to_string()), ("State", "UP".to_string())],
+        vec![("PartitionName", "b".to_string()), ("State", "DOWN".to_string())]
+    ]);
+}
+
+// Private helper function to split a comma separated list, placeholders and empty values give an empty list
+fn str_to_list(value: &str) -> Vec<String> {
+    if is_placeholder(value) {
+        Vec::new()
+    } else {
+        value.split(',').filter(|item| !item.is_empty()).map(|item| item.to_string()).collect()
+    }
+}
+
+#[test]
+fn test_str_to_list() {
+    assert_eq!(str_to_list("MAINT,IGNORE_JOBS"), vec!["MAINT", "IGNORE_JOBS"]);
+    assert_eq!(str_to_list("root"), vec!["root"]);
+    assert!(str_to_list("(null)").is_empty());
+    assert!(str_to_list("").is_empty());
+}
+
+// Private helper function to parse the output of "scontrol show reservations" and return a list of ReservationInfo
+// Each reservation starts with "ReservationName=" and spans several lines, unknown keys are ignored
+fn get_reservation_info_util(scontrol_output: &str) -> Vec<ReservationInfo> {
+    let mut result: Vec<ReservationInfo> = Vec::new();
+
+    for record in str_to_records(scontrol_output, "ReservationName") {
+        let mut reservation = ReservationInfo::new(&record[0].1);
+
+        for (key, value) in record {
+            match key {
+                "StartTime" => reservation.start_time = optional_string(&value),
+                "EndTime" => reservation.end_time = optional_string(&value),
+                "Nodes" => reservation.nodes = if is_placeholder(&value) { Vec::new() } else { expand_hostlist(&value) },
+                "Users" => reservation.users = str_to_list(&value),
+                "Accounts" => reservation.accounts = str_to_list(&value),
+                "Flags" => reservation.flags = str_to_list(&value),
+                "State" => reservation.state = optional_string(&value),
+                _ => {}
+            }
+        }
+
+        result.push(reservation);
+    }
+
+    result
+}
+
+#[test]
+fn test_get_reservation_info_util_empty() {
+    assert!(get_reservation_info_util("").is_empty());
+    assert!(get_reservation_info_util("No reservations in the system\n").is_empty());
+}
+
+#[test]
+fn test_get_reservation_info_util_fixture() {
+    let reservations = get_reservation_info_util(include_str!("../tests/fixtures/scontrol_reservations.txt"));
+    assert_eq!(reservations.len(), 3);
+
+    assert_eq!(reservations[0], ReservationInfo {
+        name: "maint_2016".to_string(),
+        start_time: Some("2016-01-04T08:00:00".to_string()),
+        end_time: Some("2016-01-04T20:00:00".to_string()),
+        nodes: expand_hostlist("node[01-12]"),
+        users: vec!["root".to_string()],
+        accounts: Vec::new(),
+        flags: vec!["MAINT".to_string(), "IGNORE_JOBS".to_string(), "SPEC_NODES".to_string(), "ALL_NODES".to_string()],
+        state: Some("INACTIVE".to_string())
+    });
+    assert!(reservations[0].is_maintenance());
+
+    // Empty flags and a comment with spaces at the end
+    assert_eq!(reservations[1].nodes, vec!["node05", "node06", "gpu01"]);
+    assert!(reservations[1].users.is_empty());
+    assert_eq!(reservations[1].accounts, vec!["teaching", "esd"]);
+    assert!(reservations[1].flags.is_empty());
+    assert_eq!(reservations[1].state, Some("ACTIVE".to_string()));
+
+    assert_eq!(reservations[2].users, vec!["user01", "user02"]);
+    assert_eq!(reservations[2].flags, vec!["DAILY", "REPLACE_DOWN"]);
+}
+
+#[test]
+fn test_reservation_timing() {
+    let reservations = get_reservation_info_util(include_str!("../tests/fixtures/scontrol_reservations.txt"));
+    let now = "2015-12-17T10:05:32";
+    let until = "2015-12-18T10:05:32";
+
+    assert!(!reservations[0].is_active(now));
+    assert!(!reservations[0].starts_within(now, until));
+    assert!(reservations[1].is_active(now));
+    assert!(!reservations[1].starts_within(now, until));
+    assert!(!reservations[2].is_active(now));
+    assert!(reservations[2].starts_within(now, until));
+    // The end time is not part of the reservation
+    assert!(!reservations[2].is_active("2015-12-18T08:00:00"));
+    assert!(!ReservationInfo::new("unknown").is_active(now));
+}
+
+#[test]
+fn test_get_reservation_info_test() {
+    let reservations = get_reservation_info_test();
+    let now = reservation_time(0);
+    let until = reservation_time(UPCOMING_RESERVATION_HOURS * 3600);
+
+    assert_eq!(reservations.len(), 3);
+    assert!(reservations[0].is_active(&now));
+    assert!(reservations[1].starts_within(&now, &until));
+    assert!(!reservations[2].is_active(&now) && !reservations[2].starts_within(&now, &until));
+}
+
+// Private helper function to parse a time limit, for example "7-00:00:00" or "UNLIMITED"
+// "NONE" (no default time) and placeholders give None
+fn str_to_time_limit(value: &str) -> Option<Limit<String>> {
+    match value {
+        "UNLIMITED" | "INFINITE" => Some(Limit::Unlimited),
+        "NONE" | "" => None,
+        _ => optional_string(value).map(Limit::Limited)
+    }
+}
+
+#[test]
+fn test_str_to_time_limit() {
+    assert_eq!(str_to_time_limit("7-00:00:00"), Some(Limit::Limited("7-00:00:00".to_string())));
+    assert_eq!(str_to_time_limit("UNLIMITED"), Some(Limit::Unlimited));
+    assert_eq!(str_to_time_limit("INFINITE"), Some(Limit::Unlimited));
+    assert_eq!(str_to_time_limit("NONE"), None);
+    assert_eq!(str_to_time_limit("N/A"), None);
+}
+
+// Private helper function to parse a number limit, for example "2" or "UNLIMITED"
+fn str_to_number_limit(value: &str) -> Option<Limit<u32>> {
+    match value {
+        "UNLIMITED" | "INFINITE" => Some(Limit::Unlimited),
+        _ => value.parse::<u32>().ok().map(Limit::Limited)
+    }
+}
+
+#[test]
+fn test_str_to_number_limit() {
+    assert_eq!(str_to_number_limit("32"), Some(Limit::Limited(32)));
+    assert_eq!(str_to_number_limit("UNLIMITED"), Some(Limit::Unlimited));
+    assert_eq!(str_to_number_limit("(null)"), None);
+}
+
+// Private helper function to parse the output of "scontrol show partition" and return the limits by partition name
+// Each partition starts with "PartitionName=" and spans several lines, unknown keys are ignored
+fn get_partition_limits_util(scontrol_output: &str) -> BTreeMap<String, PartitionLimits> {
+    let mut result = BTreeMap::new();
+
+    for record in str_to_records(scontrol_output, "PartitionName") {
+        let mut limits = PartitionLimits::new(&record[0].1);
+
+        for (key, value) in record {
+            match key {
+                "MaxTime" => limits.max_time = str_to_time_limit(&value),
+                "DefaultTime" => limits.default_time = str_to_time_limit(&value),
+                "MaxNodes" => limits.max_nodes = str_to_number_limit(&value),
+                "MaxCPUsPerNode" => limits.max_cpus_per_node = str_to_number_limit(&value),
+                "State" => limits.state = optional_string(&value),
+                "AllowGroups" => limits.allow_groups = if value == "ALL" { Vec::new() } else { str_to_list(&value) },
+                _ => {}
+            }
+        }
+
+        result.insert(limits.name.clone(), limits);
+    }
+
+    result
+}
+
+#[test]
+fn test_get_partition_limits_util_empty() {
+    assert!(get_partition_limits_util("").is_empty());
+    assert!(get_partition_limits_util("No partitions in the system").is_empty());
+}
+
+#[test]
+fn test_get_partition_limits_util_fixture() {
+    let limits = get_partition_limits_util(include_str!("../tests/fixtures/scontrol_partitions.txt"));
+    assert_eq!(limits.len(), 3);
+
+    assert_eq!(limits["esd"], PartitionLimits {
+        name: "esd".to_string(),
+        max_time: Some(Limit::Limited("7-00:00:00".to_string())),
+        default_time: Some(Limit::Limited("01:00:00".to_string())),
+        max_nodes: Some(Limit::Unlimited),
+        max_cpus_per_node: Some(Limit::Unlimited),
+        state: Some("UP".to_string()),
+        allow_groups: Vec::new()
+    });
+    assert_eq!(limits["gpu"], PartitionLimits {
+        name: "gpu".to_string(),
+        max_time: Some(Limit::Limited("2-00:00:00".to_string())),
+        default_time: None,
+        max_nodes: Some(Limit::Limited(2)),
+        max_cpus_per_node: Some(Limit::Limited(32)),
+        state: Some("DRAIN".to_string()),
+        allow_groups: vec!["gpu_users".to_string(), "admins".to_string()]
+    });
+    // Short output with missing keys
+    assert_eq!(limits["debug"].max_time, Some(Limit::Unlimited));
+    assert_eq!(limits["debug"].max_nodes, None);
+    assert_eq!(limits["debug"].default_time, None);
+}
+
+#[test]
+fn test_partition_limits_to_json() {
+    let limits = get_partition_limits_util(include_str!("../tests/fixtures/scontrol_partitions.txt"));
+    assert_eq!(limits["gpu"].to_json().to_string(), "{\"allow_groups\":[\"gpu_users\",\"admins\"],\"default_time\":null,\"max_cpus_per_node\":32,\
+        \"max_nodes\":2,\"max_time\":\"2-00:00:00\",\"name\":\"gpu\",\"state\":\"DRAIN\"}");
+    assert_eq!(limits["esd"].max_nodes.to_json().to_string(), "\"UNLIMITED\"");
+}
+
+// Private helper function to parse the output of "scontrol show licenses" and return a list of LicenseInfo
+// Each license is one line starting with "LicenseName=", unknown keys are ignored
+fn get_license_info_util(scontrol_output: &str) -> Vec<LicenseInfo> {
+    let mut result: Vec<LicenseInfo> = Vec::new();
+
+    for record in str_to_records(scontrol_output, "LicenseName") {
+        let mut license = LicenseInfo::new(&record[0].1);
+
+        for (key, value) in record {
+            match key {
+                "Total" => license.total = value.parse::<u32>().ok(),
+                "Used" => license.used = value.parse::<u32>().ok(),
+                "Free" => license.free = value.parse::<u32>().ok(),
+                "Reserved" => license.reserved = value.parse::<u32>().ok(),
+                _ => {}
+            }
+        }
+
+        result.push(license);
+    }
+
+    result
+}
+
+#[test]
+fn test_get_license_info_util_empty() {
+    assert!(get_license_info_util("").is_empty());
+    assert!(get_license_info_util("No licenses configured in Slurm.\n").is_empty());
+}
+
+#[test]
+fn test_get_license_info_util_single() {
+    assert_eq!(get_license_info_util("LicenseName=foo Total=10 Used=3 Free=7 Reserved=0 Remote=no"), vec![LicenseInfo {
+        name: "foo".to_string(),
+        total: Some(10),
+        used: Some(3),
+        free: Some(7),
+        reserved: Some(0)
+    }]);
+}
+
+#[test]
+fn test_get_license_info_util_multiple() {
+    // Older versions print no Reserved, newer ones more keys (LastConsumed, ...)
+    let licenses = get_license_info_util("LicenseName=matlab Total=10 Used=10 Free=0 Reserved=0 Remote=no\n\
+        LicenseName=ansys Total=4 Used=1 Free=3 Remote=no\n\
+        LicenseName=comsol@db Total=20 Used=5 Free=13 Reserved=2 Remote=yes LastConsumed=5 LastDeficit=0 LastUpdate=2023-01-01T10:00:00\n");
+    assert_eq!(licenses.iter().map(|license| &license.name[..]).collect::<Vec<&str>>(), vec!["matlab", "ansys", "comsol@db"]);
+    assert!(licenses[0].is_exhausted());
+    assert!(!licenses[1].is_exhausted());
+    assert_eq!(licenses[1].reserved, None);
+    assert_eq!((licenses[2].total, licenses[2].used, licenses[2].free, licenses[2].reserved), (Some(20), Some(5), Some(13), Some(2)));
+    // A missing Free is not the same as no free license
+    assert!(!LicenseInfo::new("unknown").is_exhausted());
+}
+
+#[test]
+fn test_get_license_info() {
+    use command_runner::TestRunner;
+
+    let runner = TestRunner::new(Ok("LicenseName=foo Total=10 Used=3 Free=7 Reserved=0 Remote=no".to_string()));
+    assert_eq!(get_license_info(&runner, &CommandConfig::new("scontrol", &[])).unwrap().len(), 1);
+    assert_eq!(runner.calls.lock().unwrap()[0].1, vec!["show", "licenses"]);
+    assert_eq!(get_license_info_test().len(), 3);
+    assert_eq!(get_license_info_test()[1].to_json().to_string(), "{\"free\":0,\"name\":\"ansys\",\"reserved\":0,\"total\":4,\"used\":4}");
+}
+
+/*
+    scontrol show reservations
+
+    Output looks like (see tests/fixtures/scontrol_reservations.txt for a complete example):
+    ReservationName=maint_2016 StartTime=2016-01-04T08:00:00 EndTime=2016-01-04T20:00:00 Duration=12:00:00
+       Nodes=node[01-12] NodeCnt=12 CoreCnt=96 Features=(null) PartitionName=(null) Flags=MAINT,IGNORE_JOBS,SPEC_NODES,ALL_NODES
+       TRES=cpu=96
+       Users=root Groups=(null) Accounts=(null) Licenses=(null) State=INACTIVE BurstBuffer=(null) Watts=n/a
+       MaxStartDelay=(null)
+
+    Without reservations: No reservations in the system
+
+    scontrol show partition
+
+    Output looks like (see tests/fixtures/scontrol_partitions.txt for a complete example):
+    PartitionName=esd
+       AllowGroups=ALL AllowAccounts=ALL AllowQos=ALL
+       AllocNodes=ALL Default=YES QoS=N/A
+       DefaultTime=01:00:00 DisableRootJobs=NO ExclusiveUser=NO GraceTime=0 Hidden=NO
+       MaxNodes=UNLIMITED MaxTime=7-00:00:00 MinNodes=0 LLN=NO MaxCPUsPerNode=UNLIMITED
+       ...
+       State=UP TotalCPUs=96 TotalNodes=12 SelectTypeParameters=NONE
+
+    scontrol show licenses
+
+    Output looks like (one line per license):
+    LicenseName=matlab Total=10 Used=3 Free=7 Reserved=0 Remote=no
+
+    Without licenses: No licenses configured in Slurm.
+*/
+
+// Private helper function to execute "scontrol show <entity>" and return its output as a string
+fn call_scontrol(runner: &dyn CommandRunner, command: &CommandConfig, entity: &str) -> Result<String, String> {
+    run_command(runner, command, &["show", entity])
+}
+
+#[test]
+fn test_get_reservation_info_error() {
+    use command_runner::TestRunner;
+
+    let runner = TestRunner::new(Err("not found".to_string()));
+    assert!(get_reservation_info(&runner, &CommandConfig::new("scontrol", &[])).is_err());
+
+    let runner = TestRunner::new(Ok("ReservationName=maint StartTime=2016-01-04T08:00:00".to_string()));
+    assert_eq!(get_reservation_info(&runner, &CommandConfig::new("scontrol", &[])).unwrap().len(), 1);
+    assert_eq!(runner.calls.lock().unwrap()[0].1, vec!["show", "reservations"]);
+}
@@ -0,0 +1,396 @@
+//! Abstraction for the SLURM sdiag command
+//! Runs sdiag, parses output into data structure (SchedulerStats)
+
+// System modules:
+use std::collections::BTreeMap;
+
+// External modules:
+use rustc_serialize::json::{Json, ToJson};
+
+// Internal modules:
+use command_runner::{CommandRunner, CommandConfig, run_command};
+
+/// Statistics of the main scheduler, times are in microseconds
+#[derive(Debug, Clone, PartialEq)]
+pub struct MainScheduleStats {
+    pub last_cycle: Option<u64>,
+    pub max_cycle: Option<u64>,
+    pub total_cycles: Option<u64>,
+    pub mean_cycle: Option<u64>,
+    pub mean_depth_cycle: Option<u64>,
+    pub cycles_per_minute: Option<u64>,
+    pub last_queue_length: Option<u64>
+}
+
+/// Statistics of the backfill scheduler, times are in microseconds
+#[derive(Debug, Clone, PartialEq)]
+pub struct BackfillStats {
+    pub total_backfilled_jobs: Option<u64>,
+    pub total_cycles: Option<u64>,
+    pub last_cycle: Option<u64>,
+    pub max_cycle: Option<u64>,
+    pub mean_cycle: Option<u64>,
+    pub last_depth_cycle: Option<u64>,
+    pub last_queue_length: Option<u64>
+}
+
+/// Scheduler health information from sdiag
+/// Sections that are missing in the sdiag output (older SLURM versions) are None
+#[derive(Debug, Clone, PartialEq)]
+pub struct SchedulerStats {
+    pub server_thread_count: Option<u64>,
+    pub agent_queue_size: Option<u64>,
+    pub jobs_submitted: Option<u64>,
+    pub jobs_started: Option<u64>,
+    pub jobs_completed: Option<u64>,
+    pub jobs_canceled: Option<u64>,
+    pub jobs_failed: Option<u64>,
+    pub main_schedule: Option<MainScheduleStats>,
+    pub backfill: Option<BackfillStats>,
+    /// Total number of RPCs received by slurmctld (sum over all message types)
+    pub rpc_count: Option<u64>
+}
+
+impl MainScheduleStats {
+    /// Create an empty MainScheduleStats object
+    pub fn new() -> MainScheduleStats {
+        MainScheduleStats {
+            last_cycle: None,
+            max_cycle: None,
+            total_cycles: None,
+            mean_cycle: None,
+            mean_depth_cycle: None,
+            cycles_per_minute: None,
+            last_queue_length: None
+        }
+    }
+}
+
+impl BackfillStats {
+    /// Create an empty BackfillStats object
+    pub fn new() -> BackfillStats {
+        BackfillStats {
+            total_backfilled_jobs: None,
+            total_cycles: None,
+            last_cycle: None,
+            max_cycle: None,
+            mean_cycle: None,
+            last_depth_cycle: None,
+            last_queue_length: None
+        }
+    }
+}
+
+impl SchedulerStats {
+    /// Create an empty SchedulerStats object
+    pub fn new() -> SchedulerStats {
+        SchedulerStats {
+            server_thread_count: None,
+            agent_queue_size: None,
+            jobs_submitted: None,
+            jobs_started: None,
+            jobs_completed: None,
+            jobs_canceled: None,
+            jobs_failed: None,
+            main_schedule: None,
+            backfill: None,
+            rpc_count: None
+        }
+    }
+}
+
+impl ToJson for MainScheduleStats {
+    fn to_json(&self) -> Json {
+        let mut result = BTreeMap::new();
+        result.insert("last_cycle".to_string(), self.last_cycle.to_json());
+        result.insert("max_cycle".to_string(), self.max_cycle.to_json());
+        result.insert("total_cycles".to_string(), self.total_cycles.to_json());
+        result.insert("mean_cycle".to_string(), self.mean_cycle.to_json());
+        result.insert("mean_depth_cycle".to_string(), self.mean_depth_cycle.to_json());
+        result.insert("cycles_per_minute".to_string(), self.cycles_per_minute.to_json());
+        result.insert("last_queue_length".to_string(), self.last_queue_length.to_json());
+        Json::Object(result)
+    }
+}
+
+impl ToJson for BackfillStats {
+    fn to_json(&self) -> Json {
+        let mut result = BTreeMap::new();
+        result.insert("total_backfilled_jobs".to_string(), self.total_backfilled_jobs.to_json());
+        result.insert("total_cycles".to_string(), self.total_cycles.to_json());
+        result.insert("last_cycle".to_string(), self.last_cycle.to_json());
+        result.insert("max_cycle".to_string(), self.max_cycle.to_json());
+        result.insert("mean_cycle".to_string(), self.mean_cycle.to_json());
+        result.insert("last_depth_cycle".to_string(), self.last_depth_cycle.to_json());
+        result.insert("last_queue_length".to_string(), self.last_queue_length.to_json());
+        Json::Object(result)
+    }
+}
+
+impl ToJson for SchedulerStats {
+    fn to_json(&self) -> Json {
+        let mut result = BTreeMap::new();
+        result.insert("server_thread_count".to_string(), self.server_thread_count.to_json());
+        result.insert("agent_queue_size".to_string(), self.agent_queue_size.to_json());
+        result.insert("jobs_submitted".to_string(), self.jobs_submitted.to_json());
+        result.insert("jobs_started".to_string(), self.jobs_started.to_json());
+        result.insert("jobs_completed".to_string(), self.jobs_completed.to_json());
+        result.insert("jobs_canceled".to_string(), self.jobs_canceled.to_json());
+        result.insert("jobs_failed".to_string(), self.jobs_failed.to_json());
+        result.insert("main_schedule".to_string(), self.main_schedule.to_json());
+        result.insert("backfill".to_string(), self.backfill.to_json());
+        result.insert("rpc_count".to_string(), self.rpc_count.to_json());
+        Json::Object(result)
+    }
+}
+
+/// Public helper function to generate test data
+pub fn get_scheduler_stats_test() -> SchedulerStats {
+    let test_data = "
+        Server thread count: 3
+        Agent queue size:    0
+
+        Jobs submitted: 12
+        Jobs started:   10
+        Jobs completed: 6
+        Jobs canceled:  1
+        Jobs failed:    1
+
+        Main schedule statistics (microseconds):
+            Last cycle:   1312
+            Max cycle:    48071
+            Total cycles: 80
+            Mean cycle:   2045
+            Mean depth cycle:  12
+            Cycles per minute: 1
+            Last queue length: 4
+
+        Backfilling stats
+            Total backfilled jobs (since last slurm start): 5
+            Total cycles: 37
+            Last cycle when: Sat Jan  1 09:00:00 2000
+            Last cycle: 10345
+            Max cycle:  387231
+            Mean cycle: 15120
+            Last depth cycle: 4
+            Last queue length: 4
+
+        Remote Procedure Call statistics by message type
+            REQUEST_PARTITION_INFO                  ( 2009) count:144    ave_time:172    total_time:24819
+            REQUEST_JOB_INFO                        ( 2003) count:143    ave_time:1093   total_time:157138
+    ";
+
+    get_scheduler_stats_util(test_data)
+}
+
+/// Public helper function to retrieve the current scheduler statistics
+pub fn get_scheduler_stats(runner: &dyn CommandRunner) -> SchedulerStats {
+    get_scheduler_stats_util(&call_sdiag(runner))
+}
+
+// The sections of the sdiag output that are of interest
+enum Section {
+    General,
+    MainSchedule,
+    Backfill,
+    RpcByMessageType,
+    Ignored
+}
+
+// Private helper function to parse the output of "sdiag" and return the SchedulerStats
+fn get_scheduler_stats_util(sdiag_output: &str) -> SchedulerStats {
+    let mut result = SchedulerStats::new();
+    let mut section = Section::General;
+
+    for line in sdiag_output.lines() {
+        let line = line.trim();
+
+        // Section headers
+        if line.starts_with("Main schedule statistics") {
+            section = Section::MainSchedule;
+            result.main_schedule = Some(MainScheduleStats::new());
+            continue
+        } else if line.starts_with("Backfilling stats") {
+            section = Section::Backfill;
+            result.backfill = Some(BackfillStats::new());
+            continue
+        } else if line.starts_with("Remote Procedure Call statistics by message type") {
+            section = Section::RpcByMessageType;
+            result.rpc_count = Some(0);
+            continue
+        } else if line.starts_with("Remote Procedure Call statistics") || line.starts_with("Pending RPC statistics") {
+            // Other RPC sections would count the same RPCs again
+            section = Section::Ignored;
+            continue
+        }
+
+        match section {
+            Section::RpcByMessageType => {
+                if let Some(count) = str_to_rpc_count(line) {
+                    result.rpc_count = result.rpc_count.map(|total| total + count);
+                }
+            },
+            Section::Ignored => {},
+            _ => {
+                let (key, value) = match str_to_key_value(line) {
+                    Some(key_value) => key_value,
+                    None => continue
+                };
+
+                match section {
+                    Section::General => match key {
+                        "Server thread count" => result.server_thread_count = value,
+                        "Agent queue size" => result.agent_queue_size = value,
+                        "Jobs submitted" => result.jobs_submitted = value,
+                        "Jobs started" => result.jobs_started = value,
+                        "Jobs completed" => result.jobs_completed = value,
+                        "Jobs canceled" => result.jobs_canceled = value,
+                        "Jobs failed" => result.jobs_failed = value,
+                        _ => {}
+                    },
+                    Section::MainSchedule => if let Some(ref mut stats) = result.main_schedule {
+                        match key {
+                            "Last cycle" => stats.last_cycle = value,
+                            "Max cycle" => stats.max_cycle = value,
+                            "Total cycles" => stats.total_cycles = value,
+                            "Mean cycle" => stats.mean_cycle = value,
+                            "Mean depth cycle" => stats.mean_depth_cycle = value,
+                            "Cycles per minute" => stats.cycles_per_minute = value,
+                            "Last queue length" => stats.last_queue_length = value,
+                            _ => {}
+                        }
+                    },
+                    Section::Backfill => if let Some(ref mut stats) = result.backfill {
+                        match key {
+                            "Total backfilled jobs (since last slurm start)" => stats.total_backfilled_jobs = value,
+                            "Total cycles" => stats.total_cycles = value,
+                            "Last cycle" => stats.last_cycle = value,
+                            "Max cycle" => stats.max_cycle = value,
+                            "Mean cycle" => stats.mean_cycle = value,
+                            "Last depth cycle" => stats.last_depth_cycle = value,
+                            "Last queue length" => stats.last_queue_length = value,
+                            _ => {}
+                        }
+                    },
+                    _ => {}
+                }
+            }
+        }
+    }
+
+    result
+}
+
+#[test]
+fn test_get_scheduler_stats_util_empty() {
+    assert_eq!(get_scheduler_stats_util(""), SchedulerStats::new());
+}
+
+#[test]
+fn test_get_scheduler_stats_util_fixture() {
+    let stats = get_scheduler_stats_util(include_str!("../tests/fixtures/sdiag.txt"));
+
+    assert_eq!(stats.server_thread_count, Some(3));
+    assert_eq!(stats.agent_queue_size, Some(0));
+    assert_eq!(stats.jobs_submitted, Some(125));
+    assert_eq!(stats.jobs_started, Some(118));
+    assert_eq!(stats.jobs_completed, Some(97));
+    assert_eq!(stats.jobs_canceled, Some(6));
+    assert_eq!(stats.jobs_failed, Some(2));
+    assert_eq!(stats.main_schedule, Some(MainScheduleStats{
+        last_cycle: Some(1312),
+        max_cycle: Some(48071),
+        total_cycles: Some(802),
+        mean_cycle: Some(2045),
+        mean_depth_cycle: Some(12),
+        cycles_per_minute: Some(1),
+        last_queue_length: Some(4)
+    }));
+    assert_eq!(stats.backfill, Some(BackfillStats{
+        total_backfilled_jobs: Some(57),
+        total_cycles: Some(372),
+        last_cycle: Some(10345),
+        max_cycle: Some(387231),
+        mean_cycle: Some(15120),
+        last_depth_cycle: Some(4),
+        last_queue_length: Some(4)
+    }));
+    assert_eq!(stats.rpc_count, Some(4535));
+}
+
+#[test]
+fn test_get_scheduler_stats_util_missing_sections() {
+    // Older SLURM versions don't have backfill and RPC statistics
+    let input = "Server thread count: 3\nAgent queue size:    0\nJobs submitted: 5\n\nMain schedule statistics (microseconds):\n\tLast cycle:   100\n\tMax cycle:    200\n";
+    let stats = get_scheduler_stats_util(input);
+
+    assert_eq!(stats.server_thread_count, Some(3));
+    assert_eq!(stats.jobs_submitted, Some(5));
+    assert_eq!(stats.jobs_started, None);
+    assert_eq!(stats.main_schedule.as_ref().and_then(|main| main.last_cycle), Some(100));
+    assert_eq!(stats.main_schedule.as_ref().and_then(|main| main.total_cycles), None);
+    assert_eq!(stats.backfill, None);
+    assert_eq!(stats.rpc_count, None);
+}
+
+/*
+    sdiag
+
+    Output looks like (see tests/fixtures/sdiag.txt for a complete example):
+    Server thread count: 3
+    Agent queue size:    0
+
+    Jobs submitted: 125
+    ...
+
+    Main schedule statistics (microseconds):
+        Last cycle:   1312
+        ...
+
+    Backfilling stats
+        Total backfilled jobs (since last slurm start): 57
+        ...
+
+    Remote Procedure Call statistics by message type
+        REQUEST_PARTITION_INFO                  ( 2009) count:1440   ave_time:172    total_time:248190
+        ...
+*/
+
+// Private helper function to execute the external "sdiag" SLURM command and return its output into a string
+// On error returns an empty string. TODO: better error handling
+fn call_sdiag(runner: &dyn CommandRunner) -> String {
+    // return empty string on error (run_command logs it), but continue with the program
+    run_command(runner, &CommandConfig::new("sdiag", &[]), &[]).unwrap_or(String::new())
+}
+
+// Private helper function to split a "key: value" line, only the first word of the value is parsed as number
+fn str_to_key_value(line: &str) -> Option<(&str, Option<u64>)> {
+    let mut items = line.splitn(2, ':');
+
+    match (items.next(), items.next()) {
+        (Some(key), Some(value)) => Some((key.trim(), value.split_whitespace().next().and_then(|val| val.parse::<u64>().ok()))),
+        _ => None
+    }
+}
+
+#[test]
+fn test_str_to_key_value() {
+    assert_eq!(str_to_key_value("Jobs submitted: 125"), Some(("Jobs submitted", Some(125))));
+    assert_eq!(str_to_key_value("Agent queue size:    0"), Some(("Agent queue size", Some(0))));
+    assert_eq!(str_to_key_value("Last cycle when: Thu Dec 17 10:05:01 2015"), Some(("Last cycle when", None)));
+    assert_eq!(str_to_key_value("Backfilling stats"), None);
+}
+
+// Private helper function to get the count of a RPC statistics line
+fn str_to_rpc_count(line: &str) -> Option<u64> {
+    line.split_whitespace()
+        .find(|item| item.starts_with("count:"))
+        .and_then(|item| item["count:".len()..].parse::<u64>().ok())
+}
+
+#[test]
+fn test_str_to_rpc_count() {
+    assert_eq!(str_to_rpc_count("REQUEST_JOB_INFO                        ( 2003) count:1437   ave_time:1093   total_time:1571385"), Some(1437));
+    assert_eq!(str_to_rpc_count("REQUEST_JOB_INFO"), None);
+    assert_eq!(str_to_rpc_count(""), None);
+}
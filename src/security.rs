@@ -0,0 +1,266 @@
+//! Security headers of the responses and the allow-list of client addresses (--allowed-ips, --frame-options)
+//! Every response gets headers that stop browsers from guessing content types, from embedding the page in foreign frames
+//! and from loading anything that slurm_inspector doesn't serve itself. With --allowed-ips only clients from the given
+//! networks get an answer, all others get "403 Forbidden".
+
+// System modules:
+use std::fmt;
+use std::net::{IpAddr, SocketAddr};
+
+// External modules:
+use iron::prelude::Response;
+
+/// The page has no scripts. The CSS is served from static/, templates of --template-dir may also have inline styles.
+/// The favicon is served from the same origin, the charts are inline SVG
+const CONTENT_SECURITY_POLICY: &'static str = "default-src 'none'; style-src 'self' 'unsafe-inline'; img-src 'self' data:; \
+    connect-src 'self'; form-action 'self'; base-uri 'none'";
+
+/// Who may show the page in a frame, default of --frame-options
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum FrameOptions {
+    /// Nobody
+    Deny,
+    /// Only pages of the same origin
+    SameOrigin,
+    /// Everybody, for example a dashboard on another host. No X-Frame-Options header is sent
+    Allow
+}
+
+impl FrameOptions {
+    /// Value of the frame-ancestors directive of the Content-Security-Policy, None for no restriction
+    fn frame_ancestors(&self) -> Option<&'static str> {
+        match *self {
+            FrameOptions::Deny => Some("'none'"),
+            FrameOptions::SameOrigin => Some("'self'"),
+            FrameOptions::Allow => None
+        }
+    }
+}
+
+/// Public helper function to parse the --frame-options option: "deny", "sameorigin" or "allow", case insensitive
+pub fn parse_frame_options(text: &str) -> Option<FrameOptions> {
+    match &text.trim().to_lowercase()[..] {
+        "deny" => Some(FrameOptions::Deny),
+        "sameorigin" => Some(FrameOptions::SameOrigin),
+        "allow" => Some(FrameOptions::Allow),
+        _ => None
+    }
+}
+
+/// Public helper function that adds the security headers to a response, existing headers with the same names are replaced
+pub fn add_security_headers(res: &mut Response, frame_options: FrameOptions) {
+    let mut policy = CONTENT_SECURITY_POLICY.to_string();
+
+    res.headers.set_raw("X-Content-Type-Options", vec![b"nosniff".to_vec()]);
+    res.headers.set_raw("Referrer-Policy", vec![b"same-origin".to_vec()]);
+    match frame_options {
+        FrameOptions::Deny => res.headers.set_raw("X-Frame-Options", vec![b"DENY".to_vec()]),
+        FrameOptions::SameOrigin => res.headers.set_raw("X-Frame-Options", vec![b"SAMEORIGIN".to_vec()]),
+        FrameOptions::Allow => res.headers.remove_raw("X-Frame-Options")
+    }
+    if let Some(ancestors) = frame_options.frame_ancestors() {
+        policy.push_str(&format!("; frame-ancestors {}", ancestors));
+    }
+    res.headers.set_raw("Content-Security-Policy", vec![policy.into_bytes()]);
+}
+
+/// A network in CIDR notation like 10.0.0.0/8 or 2001:db8::/32, for the allow-list of --allowed-ips
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct IpNetwork {
+    pub address: IpAddr,
+    /// Number of leading bits that must match, at most 32 for IPv4 and 128 for IPv6
+    pub prefix_len: u8
+}
+
+impl fmt::Display for IpNetwork {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}/{}", self.address, self.prefix_len)
+    }
+}
+
+/// Public helper function to parse a network like "10.0.0.0/8" or "2001:db8::/32"
+/// A single address like "192.168.1.5" is a network with only this address. Returns None for anything else
+pub fn parse_ip_network(text: &str) -> Option<IpNetwork> {
+    let text = text.trim();
+    let (address, prefix_len) = match text.find('/') {
+        Some(index) => (&text[..index], Some(&text[index + 1..])),
+        None => (text, None)
+    };
+    let address: IpAddr = address.parse().ok()?;
+    let max_len = match address {
+        IpAddr::V4(_) => 32,
+        IpAddr::V6(_) => 128
+    };
+    let prefix_len = match prefix_len {
+        Some(digits) if !digits.is_empty() && digits.chars().all(|c| c.is_ascii_digit()) => digits.parse().ok()?,
+        Some(_) => return None,
+        None => max_len
+    };
+    if prefix_len > max_len {
+        return None;
+    }
+
+    Some(IpNetwork { address: address, prefix_len: prefix_len })
+}
+
+// Private helper function that compares the first bits of two addresses (in network byte order)
+fn prefix_matches(network: &[u8], address: &[u8], prefix_len: u8) -> bool {
+    let full_bytes = (prefix_len / 8) as usize;
+    let rest_bits = prefix_len % 8;
+
+    if network[..full_bytes] != address[..full_bytes] {
+        return false;
+    }
+    if rest_bits == 0 {
+        return true;
+    }
+    let mask = 0xffu8 << (8 - rest_bits);
+    network[full_bytes] & mask == address[full_bytes] & mask
+}
+
+impl IpNetwork {
+    /// True if the address is in this network. IPv4 addresses mapped to IPv6 (::ffff:10.0.0.1) match IPv4 networks
+    pub fn contains(&self, address: &IpAddr) -> bool {
+        match (self.address, *address) {
+            (IpAddr::V4(network), IpAddr::V4(address)) => prefix_matches(&network.octets(), &address.octets(), self.prefix_len),
+            (IpAddr::V6(network), IpAddr::V6(address)) => prefix_matches(&network.octets(), &address.octets(), self.prefix_len),
+            (IpAddr::V4(network), IpAddr::V6(address)) => {
+                let segments = address.segments();
+                if segments[..5].iter().all(|&segment| segment == 0) && segments[5] == 0xffff {
+                    let octets = address.octets();
+                    prefix_matches(&network.octets(), &octets[12..], self.prefix_len)
+                } else {
+                    false
+                }
+            },
+            (IpAddr::V6(_), IpAddr::V4(_)) => false
+        }
+    }
+}
+
+/// Public helper function that returns the address of the client
+/// With trust_proxy it's the last address of the X-Forwarded-For header, which the reverse proxy has added.
+/// The addresses before it are sent by the client and could be forged. Without the header it's the address of the peer.
+/// Returns None if the header is given but its last entry is not an address, the request is rejected then
+pub fn client_ip(remote_addr: &SocketAddr, forwarded_for: Option<&str>, trust_proxy: bool) -> Option<IpAddr> {
+    match forwarded_for {
+        Some(forwarded_for) if trust_proxy => forwarded_for.rsplit(',').next().and_then(|address| address.trim().parse().ok()),
+        _ => Some(remote_addr.ip())
+    }
+}
+
+/// Public helper function that checks the address of the client against the allow-list, an empty list allows every client
+pub fn is_allowed(allowed_ips: &[IpNetwork], address: Option<IpAddr>) -> bool {
+    if allowed_ips.is_empty() {
+        return true;
+    }
+    match address {
+        Some(address) => allowed_ips.iter().any(|network| network.contains(&address)),
+        None => false
+    }
+}
+
+#[test]
+fn test_parse_frame_options() {
+    assert_eq!(parse_frame_options("DENY"), Some(FrameOptions::Deny));
+    assert_eq!(parse_frame_options("sameorigin"), Some(FrameOptions::SameOrigin));
+    assert_eq!(parse_frame_options(" allow "), Some(FrameOptions::Allow));
+    assert_eq!(parse_frame_options("ALLOW-FROM http://example.com"), None);
+}
+
+#[test]
+fn test_add_security_headers() {
+    let raw = |res: &Response, name: &str| res.headers.get_raw(name).map(|values| String::from_utf8(values[0].clone()).unwrap());
+
+    let mut res = Response::new();
+    add_security_headers(&mut res, FrameOptions::SameOrigin);
+    assert_eq!(raw(&res, "X-Content-Type-Options"), Some("nosniff".to_string()));
+    assert_eq!(raw(&res, "X-Frame-Options"), Some("SAMEORIGIN".to_string()));
+    assert_eq!(raw(&res, "Referrer-Policy"), Some("same-origin".to_string()));
+    assert_eq!(raw(&res, "Content-Security-Policy"), Some("default-src 'none'; style-src 'self' 'unsafe-inline'; img-src 'self' data:; \
+        connect-src 'self'; form-action 'self'; base-uri 'none'; frame-ancestors 'self'".to_string()));
+
+    add_security_headers(&mut res, FrameOptions::Deny);
+    assert_eq!(raw(&res, "X-Frame-Options"), Some("DENY".to_string()));
+    assert!(raw(&res, "Content-Security-Policy").unwrap().ends_with("; base-uri 'none'; frame-ancestors 'none'"));
+
+    add_security_headers(&mut res, FrameOptions::Allow);
+    assert_eq!(raw(&res, "X-Frame-Options"), None);
+    assert!(raw(&res, "Content-Security-Policy").unwrap().ends_with("; base-uri 'none'"));
+    assert_eq!(raw(&res, "X-Content-Type-Options"), Some("nosniff".to_string()));
+}
+
+#[test]
+fn test_parse_ip_network() {
+    assert_eq!(parse_ip_network("10.0.0.0/8"), Some(IpNetwork { address: "10.0.0.0".parse().unwrap(), prefix_len: 8 }));
+    assert_eq!(parse_ip_network(" 192.168.1.5 "), Some(IpNetwork { address: "192.168.1.5".parse().unwrap(), prefix_len: 32 }));
+    assert_eq!(parse_ip_network("2001:db8::/32"), Some(IpNetwork { address: "2001:db8::".parse().unwrap(), prefix_len: 32 }));
+    assert_eq!(parse_ip_network("::1"), Some(IpNetwork { address: "::1".parse().unwrap(), prefix_len: 128 }));
+    assert_eq!(parse_ip_network("10.0.0.0/33"), None);
+    assert_eq!(parse_ip_network("2001:db8::/129"), None);
+    assert_eq!(parse_ip_network("10.0.0.0/"), None);
+    assert_eq!(parse_ip_network("10.0.0.0/+8"), None);
+    assert_eq!(parse_ip_network("10.0.0/8"), None);
+    assert_eq!(parse_ip_network("login01"), None);
+    assert_eq!(format!("{}", parse_ip_network("2001:db8::/32").unwrap()), "2001:db8::/32");
+}
+
+#[test]
+fn test_ip_network_contains_v4() {
+    let contains = |network: &str, address: &str| parse_ip_network(network).unwrap().contains(&address.parse().unwrap());
+
+    assert!(contains("10.0.0.0/8", "10.1.2.3"));
+    assert!(!contains("10.0.0.0/8", "11.0.0.1"));
+    assert!(contains("192.168.4.0/22", "192.168.7.255"));
+    assert!(!contains("192.168.4.0/22", "192.168.8.0"));
+    assert!(contains("192.168.1.5", "192.168.1.5"));
+    assert!(!contains("192.168.1.5", "192.168.1.6"));
+    assert!(contains("0.0.0.0/0", "203.0.113.7"));
+    // Host bits of the network are ignored
+    assert!(contains("10.1.2.3/8", "10.200.0.1"));
+    // IPv4 mapped to IPv6
+    assert!(contains("10.0.0.0/8", "::ffff:10.1.2.3"));
+    assert!(!contains("10.0.0.0/8", "::ffff:11.1.2.3"));
+    assert!(!contains("0.0.0.0/0", "2001:db8::1"));
+}
+
+#[test]
+fn test_ip_network_contains_v6() {
+    let contains = |network: &str, address: &str| parse_ip_network(network).unwrap().contains(&address.parse().unwrap());
+
+    assert!(contains("2001:db8::/32", "2001:db8:1234::1"));
+    assert!(!contains("2001:db8::/32", "2001:db9::1"));
+    assert!(contains("fd00::/7", "fdff::1"));
+    assert!(!contains("fd00::/7", "fe00::1"));
+    assert!(contains("2001:db8::/29", "2001:dbf:ffff::1"));
+    assert!(!contains("2001:db8::/29", "2001:dc0::1"));
+    assert!(contains("::1", "::1"));
+    assert!(!contains("::1", "::2"));
+    assert!(contains("::/0", "2001:db8::1"));
+    assert!(!contains("::/0", "10.0.0.1"));
+}
+
+#[test]
+fn test_client_ip() {
+    let peer: SocketAddr = "10.0.0.5:51234".parse().unwrap();
+
+    assert_eq!(client_ip(&peer, None, false), Some("10.0.0.5".parse().unwrap()));
+    assert_eq!(client_ip(&peer, Some("192.168.1.7"), false), Some("10.0.0.5".parse().unwrap()));
+    assert_eq!(client_ip(&peer, None, true), Some("10.0.0.5".parse().unwrap()));
+    assert_eq!(client_ip(&peer, Some("192.168.1.7"), true), Some("192.168.1.7".parse().unwrap()));
+    // The first entry is sent by the client, only the last one is added by the proxy
+    assert_eq!(client_ip(&peer, Some("127.0.0.1, 2001:db8::7"), true), Some("2001:db8::7".parse().unwrap()));
+    assert_eq!(client_ip(&peer, Some("192.168.1.7, unknown"), true), None);
+}
+
+#[test]
+fn test_is_allowed() {
+    let allowed = vec![parse_ip_network("10.0.0.0/8").unwrap(), parse_ip_network("2001:db8::/32").unwrap()];
+
+    assert!(is_allowed(&[], None));
+    assert!(is_allowed(&[], Some("203.0.113.7".parse().unwrap())));
+    assert!(is_allowed(&allowed, Some("10.3.2.1".parse().unwrap())));
+    assert!(is_allowed(&allowed, Some("2001:db8::1".parse().unwrap())));
+    assert!(!is_allowed(&allowed, Some("203.0.113.7".parse().unwrap())));
+    assert!(!is_allowed(&allowed, None));
+}
@@ -0,0 +1,131 @@
+//! The SLURM status shared by the Iron response threads and the SLURM status thread
+//! Requests take a snapshot (an Arc of a complete SlurmStatus that never changes) and render from it without holding a lock,
+//! the status thread builds a complete new SlurmStatus and swaps it in. The lock is only held to clone or replace the Arc,
+//! so a slow render doesn't block the update and a request never sees half of an update
+
+// System modules:
+use std::sync::{Arc, Mutex};
+
+// Internal modules:
+use slurm_status::SlurmStatus;
+
+/// The current snapshot of the SLURM status, see the module documentation
+#[derive(Debug)]
+pub struct SharedStatus {
+    current: Mutex<Arc<SlurmStatus>>,
+    /// Held during a whole update, so two updates (the status thread and a simulated admin action) don't overwrite each other
+    writer: Mutex<()>
+}
+
+impl SharedStatus {
+    pub fn new(status: SlurmStatus) -> SharedStatus {
+        SharedStatus {
+            current: Mutex::new(Arc::new(status)),
+            writer: Mutex::new(())
+        }
+    }
+
+    /// The current status, it stays the same while the caller holds it, even if a new status is swapped in
+    /// Returns an error if the lock is poisoned
+    pub fn snapshot(&self) -> Result<Arc<SlurmStatus>, String> {
+        self.current.lock().map(|current| current.clone()).map_err(|err| err.to_string())
+    }
+
+    /// Replaces the current status with a new one, the requests that still hold the old snapshot keep it
+    pub fn replace(&self, status: SlurmStatus) -> Result<(), String> {
+        let status = Arc::new(status);
+        self.current.lock().map(|mut current| *current = status).map_err(|err| err.to_string())
+    }
+
+    /// Builds the next status from a copy of the current one and swaps it in, the lock of the snapshot is not held in between
+    /// Updates run one after the other, the next update starts from the result of the previous one
+    /// The update runs while the other updates wait, so it should only apply results that have been fetched before
+    pub fn update(&self, update: &mut dyn FnMut(&mut SlurmStatus)) -> Result<(), String> {
+        // The writer lock protects no data, a panic during an earlier update left the current status untouched
+        let _writer = self.writer.lock().unwrap_or_else(|err| err.into_inner());
+        let mut status = (*self.snapshot()?).clone();
+        update(&mut status);
+        self.replace(status)
+    }
+}
+
+#[test]
+fn test_shared_status_snapshot() {
+    let shared = SharedStatus::new(SlurmStatus::new());
+    let old = shared.snapshot().unwrap();
+    shared.update(&mut |status| status.last_update = "2000.01.01 - 09:00".to_string()).unwrap();
+
+    // The old snapshot is not changed by the update
+    assert_eq!(old.last_update, "");
+    assert_eq!(shared.snapshot().unwrap().last_update, "2000.01.01 - 09:00");
+
+    shared.replace(SlurmStatus::new()).unwrap();
+    assert_eq!(shared.snapshot().unwrap().last_update, "");
+}
+
+#[test]
+fn test_shared_status_no_torn_snapshot() {
+    use std::thread;
+    use squeue_util::get_job_info_test;
+
+    // Every update sets the time of the update and the jobs together, a reader must always see both of the same update
+    let shared = Arc::new(SharedStatus::new(SlurmStatus::new()));
+    let writer_shared = shared.clone();
+    let writer = thread::spawn(move || {
+        for update in 1..200 {
+            writer_shared.update(&mut |status| {
+                status.last_update = update.to_string();
+                status.job_info.clear();
+                thread::yield_now();
+                status.job_info = get_job_info_test().into_iter().cycle().take(update).collect();
+            }).unwrap();
+        }
+    });
+
+    let mut last_seen = 0;
+    loop {
+        let status = shared.snapshot().unwrap();
+        let update = status.last_update.parse::<usize>().unwrap_or(0);
+        assert_eq!(status.job_info.len(), update);
+        // Snapshots never go back to an older update
+        assert!(update >= last_seen);
+        last_seen = update;
+        if update == 199 {
+            break;
+        }
+    }
+    writer.join().unwrap();
+}
+
+#[test]
+fn test_shared_status_update_after_panic() {
+    use std::panic::{catch_unwind, AssertUnwindSafe};
+
+    let shared = SharedStatus::new(SlurmStatus::new());
+    let result = catch_unwind(AssertUnwindSafe(|| shared.update(&mut |_status| panic!("update failed"))));
+    assert!(result.is_err());
+
+    // The status of before the failed update is kept and the next update works
+    assert_eq!(shared.snapshot().unwrap().last_update, "");
+    shared.update(&mut |status| status.last_update = "2000.01.01 - 09:00".to_string()).unwrap();
+    assert_eq!(shared.snapshot().unwrap().last_update, "2000.01.01 - 09:00");
+}
+
+#[test]
+fn test_shared_status_concurrent_updates() {
+    use std::thread;
+
+    // Two writers at the same time, no update is lost
+    let shared = Arc::new(SharedStatus::new(SlurmStatus::new()));
+    let writers: Vec<thread::JoinHandle<()>> = (0..2).map(|_| {
+        let shared = shared.clone();
+        thread::spawn(move || for _ in 0..100 {
+            shared.update(&mut |status| status.update_errors.push("error".to_string())).unwrap();
+        })
+    }).collect();
+    for writer in writers {
+        writer.join().unwrap();
+    }
+
+    assert_eq!(shared.snapshot().unwrap().update_errors.len(), 200);
+}
@@ -2,10 +2,24 @@
 //! Runs sinfo, parses output into data structure (PartitionNodeInfo)
 
 // System modules:
-use std::process::Command;
+use std::collections::BTreeMap;
+use std::cmp::Ordering;
+use std::fmt;
+
+// External modules:
+use rustc_serialize::json::{Json, ToJson};
+
+// Internal modules:
+use natural_sort::natural_cmp;
+use command_runner::{CommandConfig, CommandRunner, run_command};
+use inspector_metrics::{ParseResult, SkippedWarnings, is_data_line};
+use placeholder::{is_placeholder, optional_string};
+use gres::{GresInfo, parse_gres};
+use hostlist::expand_hostlist;
+use synthetic_data::{SyntheticData, synthetic_sinfo_output};
 
 /// PartitionAvailability, can be "up" or "down"
-#[derive(Debug, PartialEq)]
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
 pub enum PartitionAvailability {
     /// SLURM Partition is up and available
     Up,
@@ -14,7 +28,7 @@ pub enum PartitionAvailability {
 }
 
 /// The ErrorCause why a SLURM partition is down
-#[derive(Debug, PartialEq)]
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
 pub enum ErrorCause {
     Down,
     Drained,
@@ -25,7 +39,7 @@ pub enum ErrorCause {
 }
 
 /// The state of an individual node
-#[derive(Debug, PartialEq)]
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
 pub enum NodeState {
     Allocated,
     Completing,
@@ -36,144 +50,782 @@ pub enum NodeState {
     Failing,
     Idle,
     Maint,
+    /// Some CPUs of the node are allocated, others are idle
+    Mixed,
+    /// The node is part of an advanced reservation
+    Reserved,
+    /// The node is powered off by the power saving mode
+    PoweredDown,
+    /// The node is planned by the backfill scheduler for a pending job
+    Planned,
     /// The node is in an unknown state
     Unknown
 }
 
+/// Flags that sinfo appends to the node state as a single character suffix
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum NodeFlag {
+    /// "*": the node is not responding
+    NotResponding,
+    /// "~": the node is powered off
+    PoweredOff,
+    /// "#": the node is powering up or being configured
+    PoweringUp,
+    /// "%": the node is powering down
+    PoweringDown,
+    /// "!": the node is pending power down
+    PendingPowerDown,
+    /// "$": the node is in a maintenance reservation
+    Maintenance,
+    /// "@": the node is pending reboot
+    PendingReboot,
+    /// "^": the node is rebooting
+    Rebooting,
+    /// "-": the node is planned by the backfill scheduler
+    PlannedByBackfill
+}
+
+impl NodeFlag {
+    /// The character sinfo uses for this flag
+    pub fn symbol(&self) -> char {
+        match *self {
+            NodeFlag::NotResponding => '*',
+            NodeFlag::PoweredOff => '~',
+            NodeFlag::PoweringUp => '#',
+            NodeFlag::PoweringDown => '%',
+            NodeFlag::PendingPowerDown => '!',
+            NodeFlag::Maintenance => '$',
+            NodeFlag::PendingReboot => '@',
+            NodeFlag::Rebooting => '^',
+            NodeFlag::PlannedByBackfill => '-'
+        }
+    }
+
+    /// Human readable description, shown as tooltip on the web page
+    pub fn description(&self) -> &'static str {
+        match *self {
+            NodeFlag::NotResponding => "not responding",
+            NodeFlag::PoweredOff => "powered off",
+            NodeFlag::PoweringUp => "powering up",
+            NodeFlag::PoweringDown => "powering down",
+            NodeFlag::PendingPowerDown => "pending power down",
+            NodeFlag::Maintenance => "maintenance reservation",
+            NodeFlag::PendingReboot => "pending reboot",
+            NodeFlag::Rebooting => "rebooting",
+            NodeFlag::PlannedByBackfill => "planned by backfill"
+        }
+    }
+}
+
+/// An idle node with a higher CPU load than this is shown with a warning color, something runs outside of SLURM
+pub const IDLE_LOAD_WARNING: f64 = 0.5;
+
+/// An allocated node with a lower CPU load per allocated CPU than this is shown with a warning color, the job likely hangs
+pub const ALLOCATED_LOAD_WARNING: f64 = 0.05;
+
+/// A node with a higher CPU load than this percentage of its cores is oversubscribed
+pub const OVERSUBSCRIBED_LOAD_PERCENT: f64 = 110.0;
+
+/// An allocated node with a lower CPU load than this percentage of its cores is suspiciously idle
+pub const IDLE_ALLOCATED_LOAD_PERCENT: f64 = 5.0;
+
+/// A node that uses more than this percentage of its memory is shown with a warning color
+pub const MEMORY_WARNING_PERCENT: f64 = 90.0;
+
+/// How the CPU load of a node compares to its number of cores
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum LoadLevel {
+    /// The load fits the cores
+    Normal,
+    /// More processes want to run than there are cores, above OVERSUBSCRIBED_LOAD_PERCENT
+    Oversubscribed,
+    /// The node is allocated, but almost nothing runs (below IDLE_ALLOCATED_LOAD_PERCENT), the job likely hangs
+    IdleAllocated
+}
+
+/// Public helper function to classify the CPU load of a node in percent of its cores
+pub fn classify_load(load_percent: f64, node_state: NodeState) -> LoadLevel {
+    if load_percent > OVERSUBSCRIBED_LOAD_PERCENT {
+        LoadLevel::Oversubscribed
+    } else if node_state == NodeState::Allocated && load_percent < IDLE_ALLOCATED_LOAD_PERCENT {
+        LoadLevel::IdleAllocated
+    } else {
+        LoadLevel::Normal
+    }
+}
+
 /// SLURM partition and node information
-#[derive(Debug, PartialEq)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct PartitionNodeInfo {
+    /// Name of the cluster, empty if only the local cluster is monitored
+    pub cluster: String,
     pub partition: String,
     pub availability: PartitionAvailability,
-    pub hostname: String,
+    /// None if sinfo printed a placeholder like "N/A"
+    pub hostname: Option<String>,
     pub node: String,
     pub error: ErrorCause,
     pub cpu_load: Option<f64>,
     pub node_state: NodeState,
+    /// Flags that were appended to the node state, for example "*" for not responding
+    pub node_flags: Vec<NodeFlag>,
     pub node_sockets: Option<u32>,
     pub node_cores: Option<u32>,
-    pub node_threads: Option<u32>
+    pub node_threads: Option<u32>,
+    /// Number of CPUs that are allocated, idle, in other states (for example down) and in total
+    /// None if sinfo printed "-", for example for a node that is down
+    pub cpus_alloc: Option<u32>,
+    pub cpus_idle: Option<u32>,
+    pub cpus_other: Option<u32>,
+    pub cpus_total: Option<u32>,
+    /// Generic resources of the node like GPUs, empty if sinfo printed "(null)"
+    pub gres: Vec<GresInfo>,
+    /// Memory of the node and free memory in MB (%m and %e), None if sinfo printed "N/A"
+    /// Some SLURM versions don't report the free memory, then only the total is known
+    pub memory_total: Option<u64>,
+    pub memory_free: Option<u64>,
+    /// Why the node is down or drained, set from the output of "sinfo --list-reasons" (see merge_down_reasons)
+    pub reason: Option<String>
+}
+
+impl PartitionNodeInfo {
+    /// Percentage of the CPUs that are allocated, None if the number of CPUs is not known
+    pub fn cpu_utilization(&self) -> Option<f64> {
+        match (self.cpus_alloc, self.cpus_total) {
+            (Some(alloc), Some(total)) if total > 0 => Some(alloc as f64 * 100.0 / total as f64),
+            _ => None
+        }
+    }
+
+    /// Number of cores (sockets * cores * threads), None if one of them is not known
+    pub fn core_count(&self) -> Option<u32> {
+        match (self.node_sockets, self.node_cores, self.node_threads) {
+            (Some(sockets), Some(cores), Some(threads)) => Some(sockets * cores * threads),
+            _ => None
+        }
+    }
+
+    /// CPU load in percent of the cores, None if the load or the number of cores is not known
+    pub fn load_percent(&self) -> Option<f64> {
+        match (self.cpu_load, self.core_count()) {
+            (Some(load), Some(cores)) if cores > 0 => Some(load * 100.0 / cores as f64),
+            _ => None
+        }
+    }
+
+    /// How the CPU load compares to the cores, None if the load or the number of cores is not known
+    pub fn load_level(&self) -> Option<LoadLevel> {
+        self.load_percent().map(|percent| classify_load(percent, self.node_state))
+    }
+
+    /// Memory in use in MB (total minus free), None if the total or the free memory is not known
+    /// The free memory comes from the OS, a node that reports more free memory than it has uses nothing
+    pub fn memory_used(&self) -> Option<u64> {
+        match (self.memory_total, self.memory_free) {
+            (Some(total), Some(free)) => Some(total.saturating_sub(free)),
+            _ => None
+        }
+    }
+
+    /// Percentage of the memory that is in use, None if the used or the total memory is not known
+    pub fn memory_percent(&self) -> Option<f64> {
+        match (self.memory_used(), self.memory_total) {
+            (Some(used), Some(total)) if total > 0 => Some(used as f64 * 100.0 / total as f64),
+            _ => None
+        }
+    }
+
+    /// Returns true if the node uses more than MEMORY_WARNING_PERCENT of its memory
+    pub fn has_memory_warning(&self) -> bool {
+        self.memory_percent().map_or(false, |percent| percent > MEMORY_WARNING_PERCENT)
+    }
+
+    /// Returns true if the CPU load doesn't match the allocation: an idle node with load or an allocated node without load
+    pub fn has_load_mismatch(&self) -> bool {
+        match (self.cpus_alloc, self.cpu_load) {
+            (Some(0), Some(load)) => load > IDLE_LOAD_WARNING,
+            (Some(alloc), Some(load)) => load / (alloc as f64) < ALLOCATED_LOAD_WARNING,
+            _ => false
+        }
+    }
+}
+
+impl PartitionAvailability {
+    /// The spelling of sinfo, str_to_availability parses it back
+    pub fn as_slurm_str(&self) -> &'static str {
+        match *self {
+            PartitionAvailability::Up => "up",
+            PartitionAvailability::Down => "down"
+        }
+    }
+}
+
+impl fmt::Display for PartitionAvailability {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.as_slurm_str())
+    }
+}
+
+impl ErrorCause {
+    /// The spelling of sinfo, str_to_error parses it back
+    pub fn as_slurm_str(&self) -> &'static str {
+        match *self {
+            ErrorCause::Down => "down",
+            ErrorCause::Drained => "drained",
+            ErrorCause::Draining => "draining",
+            ErrorCause::None => "none",
+            ErrorCause::Unknown => "unknown"
+        }
+    }
+}
+
+impl fmt::Display for ErrorCause {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.as_slurm_str())
+    }
+}
+
+impl NodeState {
+    /// The long form of the state as printed by sinfo (%T) without the flags, str_to_node_state parses it back
+    pub fn as_slurm_str(&self) -> &'static str {
+        match *self {
+            NodeState::Allocated => "allocated",
+            NodeState::Completing => "completing",
+            NodeState::Down => "down",
+            NodeState::Drained => "drained",
+            NodeState::Draining => "draining",
+            NodeState::Fail => "fail",
+            NodeState::Failing => "failing",
+            NodeState::Idle => "idle",
+            NodeState::Maint => "maint",
+            NodeState::Mixed => "mixed",
+            NodeState::Reserved => "reserved",
+            NodeState::PoweredDown => "powered_down",
+            NodeState::Planned => "planned",
+            NodeState::Unknown => "unknown"
+        }
+    }
+}
+
+impl fmt::Display for NodeState {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.as_slurm_str())
+    }
+}
+
+// The JSON keeps the variant names, the API and the stored history use them
+impl ToJson for PartitionAvailability {
+    fn to_json(&self) -> Json {
+        Json::String(format!("{:?}", self))
+    }
+}
+
+impl ToJson for ErrorCause {
+    fn to_json(&self) -> Json {
+        Json::String(format!("{:?}", self))
+    }
+}
+
+impl ToJson for NodeState {
+    fn to_json(&self) -> Json {
+        Json::String(format!("{:?}", self))
+    }
+}
+
+impl ToJson for NodeFlag {
+    fn to_json(&self) -> Json {
+        Json::String(format!("{:?}", self))
+    }
+}
+
+impl ToJson for PartitionNodeInfo {
+    fn to_json(&self) -> Json {
+        let mut result = BTreeMap::new();
+        result.insert("cluster".to_string(), self.cluster.to_json());
+        result.insert("partition".to_string(), self.partition.to_json());
+        result.insert("availability".to_string(), self.availability.to_json());
+        result.insert("hostname".to_string(), self.hostname.to_json());
+        result.insert("node".to_string(), self.node.to_json());
+        result.insert("error".to_string(), self.error.to_json());
+        result.insert("cpu_load".to_string(), self.cpu_load.to_json());
+        result.insert("node_state".to_string(), self.node_state.to_json());
+        result.insert("node_flags".to_string(), self.node_flags.to_json());
+        result.insert("node_sockets".to_string(), self.node_sockets.to_json());
+        result.insert("node_cores".to_string(), self.node_cores.to_json());
+        result.insert("node_threads".to_string(), self.node_threads.to_json());
+        result.insert("cpus_alloc".to_string(), self.cpus_alloc.to_json());
+        result.insert("cpus_idle".to_string(), self.cpus_idle.to_json());
+        result.insert("cpus_other".to_string(), self.cpus_other.to_json());
+        result.insert("cpus_total".to_string(), self.cpus_total.to_json());
+        result.insert("gres".to_string(), self.gres.to_json());
+        result.insert("memory_total".to_string(), self.memory_total.to_json());
+        result.insert("memory_free".to_string(), self.memory_free.to_json());
+        result.insert("memory_used".to_string(), self.memory_used().to_json());
+        result.insert("reason".to_string(), self.reason.to_json());
+        Json::Object(result)
+    }
+}
+
+/// Why a node is down, drained or failing, as reported by "sinfo --list-reasons"
+#[derive(Debug, Clone, PartialEq)]
+pub struct DownReason {
+    /// Name of the cluster, empty if only the local cluster is monitored
+    pub cluster: String,
+    pub node: String,
+    /// Free text set by the administrator or by slurmctld, for example "Not responding"
+    pub reason: String,
+    /// User name and user id of the one who set the reason, for example "root(0)"
+    pub set_by: Option<String>,
+    /// Format: %Y-%m-%dT%H:%M:%S, None if sinfo printed "Unknown" or a placeholder
+    pub since: Option<String>
+}
+
+impl ToJson for DownReason {
+    fn to_json(&self) -> Json {
+        let mut result = BTreeMap::new();
+        result.insert("cluster".to_string(), self.cluster.to_json());
+        result.insert("node".to_string(), self.node.to_json());
+        result.insert("reason".to_string(), self.reason.to_json());
+        result.insert("set_by".to_string(), self.set_by.to_json());
+        result.insert("since".to_string(), self.since.to_json());
+        Json::Object(result)
+    }
 }
 
 /// Public helper function to generate test data
 pub fn get_partition_node_info_test() -> Vec<PartitionNodeInfo> {
     let test_data = "
-        esd up node01 node01 none 0.22 idle 2 2 2
-        esd up node02 node02 none 0.0 idle 2 8 2
-        esd down node03 node03 down - - - - -
-        esd up node04 node04 none 0.71 idle 1 1 1
-        esd up node05 node05 none 0.0 alloc 1 1 1
-        esd up node06 node06 none 0.0 completing 1 1 1
-        esd up node07 node07 none 0.0 drained 1 1 1
-        esd up node08 node08 none 0.0 draining 1 1 1
-        esd up node09 node09 none 0.0 fail 1 1 1
-        esd up node10 node10 none 0.0 failing 1 1 1
-        esd up node11 node11 none 0.0 maint 1 1 1
-        esd up node12 node12 none 0.0 unknown 1 1 1
+        esd up node01 node01 none 0.22 idle 2 2 2 0/8/0/8 gpu:a100:4(S:0-1) 32000 28000
+        esd up node02 node02 none 0.0 idle 2 8 2 0/32/0/32 (null) 128000 120000
+        esd down node03 node03 down - - - - - - (null) 4000 N/A
+        esd up node04 node04 none 0.71 idle 1 1 1 0/1/0/1 (null) 4000 3000
+        esd up node05 node05 none 0.0 alloc 1 1 1 1/0/0/1 (null) 4000 200
+        esd up node06 node06 none 0.0 completing 1 1 1 1/0/0/1 (null) 4000 1000
+        esd up node07 node07 none 0.0 drained 1 1 1 0/0/1/1 (null) 4000 3900
+        esd up node08 node08 none 0.0 draining 1 1 1 0/0/1/1 (null) 4000 3900
+        esd up node09 node09 none 0.0 fail 1 1 1 0/0/1/1 (null) 4000 N/A
+        esd up node10 node10 none 0.0 failing 1 1 1 0/0/1/1 (null) 4000 N/A
+        esd up node11 node11 none 0.0 maint 1 1 1 0/0/1/1 (null) 4000 3900
+        esd up node12 node12 none 0.0 unknown* 1 1 1 0/0/1/1 (null) N/A N/A
     ";
 
-    get_pn_info_util(test_data)
+    get_pn_info_util(test_data, &mut SkippedWarnings::new()).items
+}
+
+/// Public helper function to generate test data, the reasons of the test nodes that are down, drained or failing
+pub fn get_down_reasons_test() -> Vec<DownReason> {
+    let test_data = "
+        node03|Not responding|slurm(202)|2015-12-18T08:15:00
+        node07|Bad DIMM, replaced on Monday|root(0)|2015-12-17T16:40:12
+        node08|Kernel update|root(0)|2015-12-18T09:30:00
+        node09|Epilog error|slurm(202)|Unknown
+        node10|Epilog error|slurm(202)|Unknown
+    ";
+
+    get_down_reasons_util(test_data)
+}
+
+/// Public helper function to get the nodes of a synthetic cluster of the given size (--test-nodes)
+pub fn get_partition_node_info_synthetic(data: &SyntheticData) -> Vec<PartitionNodeInfo> {
+    get_pn_info_util(&synthetic_sinfo_output(data), &mut SkippedWarnings::new()).items
 }
 
 /// Public helper function to retrieve a list of current SLURM partition and node status
-pub fn get_partition_node_info() -> Vec<PartitionNodeInfo> {
-    get_pn_info_util(&call_sinfo())
+/// With a list of clusters sinfo is called once per cluster (-M) and the results are merged
+/// Returns an error if sinfo could not be executed, otherwise the nodes and the number of lines that could not be parsed
+/// The lines that could not be parsed are logged, rate limited by warnings (see InspectorMetrics)
+pub fn get_partition_node_info(runner: &dyn CommandRunner, command: &CommandConfig, clusters: &[String], warnings: &mut SkippedWarnings)
+    -> Result<ParseResult<PartitionNodeInfo>, String> {
+    if clusters.is_empty() {
+        return call_sinfo(runner, command).map(|output| get_pn_info_util(&output, warnings));
+    }
+
+    let mut result = ParseResult::new();
+
+    for cluster in clusters {
+        let cluster_command = CommandConfig::new(&command.path, &command.build_args(&["-M", cluster]));
+        let mut node_info = get_pn_info_util(&call_sinfo(runner, &cluster_command)?, warnings);
+
+        for node in node_info.items.iter_mut() {
+            node.cluster = cluster.clone();
+        }
+        result.append(&mut node_info);
+    }
+
+    sort_partition_node_info(&mut result.items);
+
+    Ok(result)
+}
+
+/// Public helper function to retrieve why nodes are down, drained or failing
+/// With a list of clusters sinfo is called once per cluster (-M), like in get_partition_node_info
+/// Returns an error if sinfo could not be executed
+pub fn get_down_reasons(runner: &dyn CommandRunner, command: &CommandConfig, clusters: &[String]) -> Result<Vec<DownReason>, String> {
+    if clusters.is_empty() {
+        return call_sinfo_reasons(runner, command).map(|output| get_down_reasons_util(&output));
+    }
+
+    let mut result = Vec::new();
+
+    for cluster in clusters {
+        let cluster_command = CommandConfig::new(&command.path, &command.build_args(&["-M", cluster]));
+        for mut reason in get_down_reasons_util(&call_sinfo_reasons(runner, &cluster_command)?) {
+            reason.cluster = cluster.clone();
+            result.push(reason);
+        }
+    }
+
+    Ok(result)
+}
+
+// Private helper function to parse the output of "sinfo --list-reasons" and return a list of DownReason
+// The fields are separated by "|", the reason is free text, so the node is split off at the first "|" and the
+// user and time at the last two, everything in between is the reason (it can even contain "|" itself)
+// A compressed node list like "node[01-03]" gives one DownReason per node, invalid lines are skipped
+fn get_down_reasons_util(sinfo_output: &str) -> Vec<DownReason> {
+    let mut result = Vec::new();
+
+    for line in sinfo_output.lines().filter(|line| is_data_line(line)) {
+        let mut items = line.splitn(2, '|');
+        let (nodes, rest) = match (items.next(), items.next()) {
+            (Some(nodes), Some(rest)) => (nodes.trim(), rest),
+            _ => {
+                debug!("no reason in line: {}", line);
+                continue;
+            }
+        };
+        let items: Vec<&str> = rest.rsplitn(3, '|').collect();
+        if items.len() != 3 || nodes.is_empty() {
+            debug!("number of fields in reason line: {}", items.len() + 1);
+            continue;
+        }
+
+        for node in expand_hostlist(nodes) {
+            result.push(DownReason {
+                cluster: String::new(),
+                node: node,
+                reason: items[2].trim().to_string(),
+                set_by: optional_string(items[1].trim()),
+                since: optional_string(items[0].trim()).and_then(|since| if since == "Unknown" { None } else { Some(since) })
+            });
+        }
+    }
+
+    result
+}
+
+/// Public helper function that sets the reason of each node in all of its partitions, the other nodes have no reason
+/// The nodes are matched by cluster and node name
+pub fn merge_down_reasons(node_info: &mut [PartitionNodeInfo], reasons: &[DownReason]) {
+    let reasons: BTreeMap<(&str, &str), &str> = reasons.iter()
+        .map(|reason| ((&reason.cluster[..], &reason.node[..]), &reason.reason[..]))
+        .collect();
+
+    for node in node_info.iter_mut() {
+        node.reason = reasons.get(&(&node.cluster[..], &node.node[..])).map(|reason| reason.to_string());
+    }
+}
+
+#[test]
+fn test_get_down_reasons_util() {
+    let output = "
+        node03|Not responding|slurm(202)|2015-12-18T08:15:00
+        node[07-08]|Bad DIMM | replace it, ticket 4711|root(0)|2015-12-17T16:40:12
+
+        node09||root(0)|Unknown
+        node10|missing fields
+        CLUSTER: cluster2
+    ";
+    let reasons = get_down_reasons_util(output);
+
+    assert_eq!(reasons.len(), 4);
+    assert_eq!(reasons[0], DownReason{ cluster: String::new(), node: "node03".to_string(), reason: "Not responding".to_string(),
+        set_by: Some("slurm(202)".to_string()), since: Some("2015-12-18T08:15:00".to_string()) });
+    // The reason contains spaces, a comma and a "|", the compressed node list gives one entry per node
+    assert_eq!((&reasons[1].node[..], &reasons[1].reason[..]), ("node07", "Bad DIMM | replace it, ticket 4711"));
+    assert_eq!((&reasons[2].node[..], &reasons[2].reason[..]), ("node08", "Bad DIMM | replace it, ticket 4711"));
+    assert_eq!(reasons[2].set_by, Some("root(0)".to_string()));
+    assert_eq!((&reasons[3].reason[..], reasons[3].since.as_ref()), ("", None));
+    assert!(get_down_reasons_util("").is_empty());
+}
+
+#[test]
+fn test_merge_down_reasons() {
+    let mut node_info = get_partition_node_info_test();
+    node_info.push(PartitionNodeInfo{ partition: "gpu".to_string(), .. node_info[2].clone() });
+    node_info.push(PartitionNodeInfo{ cluster: "cluster2".to_string(), .. node_info[6].clone() });
+    node_info[0].reason = Some("outdated".to_string());
+    merge_down_reasons(&mut node_info, &get_down_reasons_test());
+
+    let reasons: Vec<(&str, &str, Option<&str>)> = node_info.iter().filter(|node| node.reason.is_some())
+        .map(|node| (&node.partition[..], &node.node[..], node.reason.as_ref().map(|reason| &reason[..]))).collect();
+    // node03 has its reason in both partitions, node07 of another cluster and node01 (no longer down) have none
+    assert_eq!(reasons, vec![
+        ("esd", "node03", Some("Not responding")),
+        ("esd", "node07", Some("Bad DIMM, replaced on Monday")),
+        ("esd", "node08", Some("Kernel update")),
+        ("esd", "node09", Some("Epilog error")),
+        ("esd", "node10", Some("Epilog error")),
+        ("gpu", "node03", Some("Not responding"))
+    ]);
+}
+
+/// Public helper function to parse a memory size of sinfo in MB (%m and %e), None for a placeholder like "N/A"
+/// sinfo appends a "+" if the nodes of a line have different sizes, then the smallest size is used
+pub fn parse_memory(value: &str) -> Option<u64> {
+    value.trim_end_matches('+').parse::<u64>().ok()
+}
+
+#[test]
+fn test_parse_memory() {
+    assert_eq!(parse_memory("257000"), Some(257000));
+    assert_eq!(parse_memory("4000+"), Some(4000));
+    assert_eq!(parse_memory("N/A"), None);
+    assert_eq!(parse_memory("-"), None);
+    assert_eq!(parse_memory("1.5G"), None);
+}
+
+#[test]
+fn test_memory_used() {
+    let mut node = get_partition_node_info_test().remove(0);
+    assert_eq!((node.memory_total, node.memory_free), (Some(32000), Some(28000)));
+    assert_eq!(node.memory_used(), Some(4000));
+    assert_eq!(node.memory_percent(), Some(12.5));
+    assert!(!node.has_memory_warning());
+
+    // 90% is still fine, above it is a warning
+    node.memory_free = Some(3200);
+    assert_eq!(node.memory_percent(), Some(90.0));
+    assert!(!node.has_memory_warning());
+    node.memory_free = Some(3199);
+    assert!(node.has_memory_warning());
+
+    // The OS reports more free memory than the node has
+    node.memory_free = Some(40000);
+    assert_eq!((node.memory_used(), node.memory_percent()), (Some(0), Some(0.0)));
+
+    // Without the free memory (older SLURM versions) only the total is known
+    node.memory_free = None;
+    assert_eq!((node.memory_used(), node.memory_percent()), (None, None));
+    assert!(!node.has_memory_warning());
+
+    node.memory_free = Some(0);
+    node.memory_total = Some(0);
+    assert_eq!((node.memory_used(), node.memory_percent()), (Some(0), None));
+    node.memory_total = None;
+    assert_eq!((node.memory_used(), node.memory_percent()), (None, None));
 }
 
 // Private helper function to parse the output of "sinfo" and return a list of PartitionNodeInfo
-fn get_pn_info_util(sinfo_output: &str) -> Vec<PartitionNodeInfo> {
-    let mut result: Vec<PartitionNodeInfo> = Vec::new();
+// Lines with 12 fields (recorded before the memory was added to the format) have no memory, invalid lines are skipped and counted
+fn get_pn_info_util(sinfo_output: &str, warnings: &mut SkippedWarnings) -> ParseResult<PartitionNodeInfo> {
+    let mut result = ParseResult::new();
 
-    for line in sinfo_output.lines() {
+    for line in sinfo_output.lines().filter(|line| is_data_line(line)) {
         let items: Vec<&str> = line.split_whitespace().collect();
+        result.total += 1;
 
         // Skip invalid lines
-        if items.len() != 10 {
-            debug!("number of items in line: {}", items.len());
+        if items.len() != 12 && items.len() != 14 {
+            warnings.warn_skipped_line("sinfo", "12 or 14", items.len(), line);
+            result.skipped += 1;
             continue;
         }
 
-        result.push( PartitionNodeInfo{
+        let (node_state, node_flags) = str_to_node_state_and_flags(items[6]);
+        let cpus = str_to_cpus(items[10]);
+
+        result.items.push( PartitionNodeInfo{
+            cluster: String::new(),
             partition: items[0].to_string(),
             availability: str_to_availability(items[1]),
-            hostname: items[2].to_string(),
+            hostname: optional_string(items[2]),
             node: items[3].to_string(),
             error: str_to_error(items[4]),
             cpu_load: items[5].parse::<f64>().ok(),
-            node_state: str_to_node_state(items[6]),
+            node_state: node_state,
+            node_flags: node_flags,
             node_sockets: items[7].parse::<u32>().ok(),
             node_cores: items[8].parse::<u32>().ok(),
-            node_threads: items[9].parse::<u32>().ok()
+            node_threads: items[9].parse::<u32>().ok(),
+            cpus_alloc: cpus.map(|cpus| cpus.0),
+            cpus_idle: cpus.map(|cpus| cpus.1),
+            cpus_other: cpus.map(|cpus| cpus.2),
+            cpus_total: cpus.map(|cpus| cpus.3),
+            gres: parse_gres(items[11]),
+            memory_total: items.get(12).and_then(|memory| parse_memory(memory)),
+            memory_free: items.get(13).and_then(|memory| parse_memory(memory)),
+            reason: None
         })
     }
 
+    sort_partition_node_info(&mut result.items);
+
     result
 }
 
+/// Public helper function to sort the nodes by cluster, partition and then by node name, all in natural order
+pub fn sort_partition_node_info(node_info: &mut Vec<PartitionNodeInfo>) {
+    node_info.sort_by(|a, b| {
+        match natural_cmp(&a.cluster, &b.cluster) {
+            Ordering::Equal => match natural_cmp(&a.partition, &b.partition) {
+                Ordering::Equal => natural_cmp(&a.node, &b.node),
+                ordering => ordering
+            },
+            ordering => ordering
+        }
+    });
+}
+
 #[test]
 fn test_get_pn_info_util_empty() {
-    assert!(get_pn_info_util("").len() == 0);
+    assert_eq!(get_pn_info_util("", &mut SkippedWarnings::new()), ParseResult{ items: vec![], skipped: 0, total: 0 });
 }
 
 #[test]
 fn test_get_pn_info_util_invalid() {
-    assert!(get_pn_info_util("1 2 3").len() == 0);
+    let result = get_pn_info_util("1 2 3\nesd up node01 node01 none 0.22 idle 2 2 2 0/8/0/8 (null)\n\nCLUSTER: cluster2", &mut SkippedWarnings::new());
+    assert_eq!((result.items.len(), result.skipped, result.total), (1, 1, 2));
+}
+
+#[test]
+fn test_get_pn_info_util_placeholders() {
+    use placeholder::PLACEHOLDERS;
+
+    let line = "longrun up node01.foo.bar node01 down 0.22 idle 2 2 2 0/8/0/8 (null)";
+    // Position in the line and name in the JSON of the fields that are missing if sinfo prints a placeholder
+    let fields = [(2, "hostname"), (5, "cpu_load"), (7, "node_sockets"), (8, "node_cores"), (9, "node_threads")];
+
+    for placeholder in &PLACEHOLDERS {
+        for &(position, field) in &fields {
+            let mut items: Vec<&str> = line.split_whitespace().collect();
+            items[position] = placeholder;
+            let result = get_pn_info_util(&items.join(" "), &mut SkippedWarnings::new());
+
+            assert_eq!(result.items.len(), 1);
+            assert_eq!(result.items[0].to_json().find(field), Some(&Json::Null), "{} as {}", placeholder, field);
+        }
+
+        let mut items: Vec<&str> = line.split_whitespace().collect();
+        items[4] = placeholder;
+        assert_eq!(get_pn_info_util(&items.join(" "), &mut SkippedWarnings::new()).items[0].error, ErrorCause::None);
+    }
 }
 
 #[test]
 fn test_get_pn_info_util_01() {
-    let input = "longrun up node01.foo.bar node01 none 0.22 idle 2 2 2";
+    let input = "longrun up node01.foo.bar node01 none 0.22 idle 2 2 2 0/8/0/8 (null) 32000 28000+";
     let output = vec![PartitionNodeInfo{
+        cluster: String::new(),
         partition: "longrun".to_string(),
         availability: PartitionAvailability::Up,
-        hostname: "node01.foo.bar".to_string(),
+        hostname: Some("node01.foo.bar".to_string()),
         node: "node01".to_string(),
         error: ErrorCause::None,
         cpu_load: Some(0.22),
         node_state: NodeState::Idle,
+        node_flags: Vec::new(),
         node_sockets: Some(2),
         node_cores: Some(2),
-        node_threads: Some(2)
+        node_threads: Some(2),
+        cpus_alloc: Some(0),
+        cpus_idle: Some(8),
+        cpus_other: Some(0),
+        cpus_total: Some(8),
+        gres: Vec::new(),
+        memory_total: Some(32000),
+        memory_free: Some(28000),
+        reason: None
     }];
 
-    assert_eq!(get_pn_info_util(input), output);
+    assert_eq!(get_pn_info_util(input, &mut SkippedWarnings::new()).items, output);
 }
 
 #[test]
 fn test_get_pn_info_util_02() {
-    let input = "longrun up node01.foo.bar node01 none 0.22 idle 2 2 2\nlongrun up node02.foo.bar node02 down 0.1 idle 1 2 4";
+    // The first line has no memory (recorded before %m and %e were added), the second one no free memory
+    let input = "longrun up node01.foo.bar node01 none 0.22 idle 2 2 2 0/8/0/8 (null)\nlongrun up node02.foo.bar node02 down 0.1 idle 1 2 4 0/8/0/8 (null) 64000 N/A";
     let output = vec![
         PartitionNodeInfo{
+            cluster: String::new(),
             partition: "longrun".to_string(),
             availability: PartitionAvailability::Up,
-            hostname: "node01.foo.bar".to_string(),
+            hostname: Some("node01.foo.bar".to_string()),
             node: "node01".to_string(),
             error: ErrorCause::None,
             cpu_load: Some(0.22),
             node_state: NodeState::Idle,
+            node_flags: Vec::new(),
             node_sockets: Some(2),
             node_cores: Some(2),
-            node_threads: Some(2)
+            node_threads: Some(2),
+            cpus_alloc: Some(0),
+            cpus_idle: Some(8),
+            cpus_other: Some(0),
+            cpus_total: Some(8),
+            gres: Vec::new(),
+            memory_total: None,
+            memory_free: None,
+            reason: None
         },
         PartitionNodeInfo{
+            cluster: String::new(),
             partition: "longrun".to_string(),
             availability: PartitionAvailability::Up,
-            hostname: "node02.foo.bar".to_string(),
+            hostname: Some("node02.foo.bar".to_string()),
             node: "node02".to_string(),
             error: ErrorCause::Down,
             cpu_load: Some(0.1),
             node_state: NodeState::Idle,
+            node_flags: Vec::new(),
             node_sockets: Some(1),
             node_cores: Some(2),
-            node_threads: Some(4)
+            node_threads: Some(4),
+            cpus_alloc: Some(0),
+            cpus_idle: Some(8),
+            cpus_other: Some(0),
+            cpus_total: Some(8),
+            gres: Vec::new(),
+            memory_total: Some(64000),
+            memory_free: None,
+            reason: None
         }
     ];
 
-    assert_eq!(get_pn_info_util(input), output);
+    assert_eq!(get_pn_info_util(input, &mut SkippedWarnings::new()).items, output);
+}
+
+#[test]
+fn test_get_partition_node_info_clusters() {
+    // Returns different output for each cluster
+    struct ClusterRunner;
+    impl CommandRunner for ClusterRunner {
+        fn run(&self, _program: &str, args: &[String], _environment: &[(&str, &str)]) -> Result<String, String> {
+            match &args[1][..] {
+                "cluster1" => Ok("CLUSTER: cluster1\nesd up node02 node02 none 0.0 idle 1 1 1 0/1/0/1 (null)\nesd up node01 node01 none 0.0 idle 1 1 1 0/1/0/1 (null)".to_string()),
+                "cluster2" => Ok("CLUSTER: cluster2\nesd up node01 node01 none 0.0 alloc 1 1 1 1/0/0/1 (null)".to_string()),
+                _ => Err("Invalid cluster".to_string())
+            }
+        }
+    }
+
+    let clusters = vec!["cluster2".to_string(), "cluster1".to_string()];
+    let result = get_partition_node_info(&ClusterRunner, &CommandConfig::new("sinfo", &[]), &clusters, &mut SkippedWarnings::new()).unwrap();
+    let nodes: Vec<(&str, &str, NodeState)> = result.items.iter().map(|info| (&info.cluster[..], &info.node[..], info.node_state)).collect();
+
+    assert_eq!(nodes, vec![
+        ("cluster1", "node01", NodeState::Idle),
+        ("cluster1", "node02", NodeState::Idle),
+        ("cluster2", "node01", NodeState::Allocated)
+    ]);
+    assert_eq!((result.skipped, result.total), (0, 3));
+
+    let clusters = vec!["cluster1".to_string(), "cluster3".to_string()];
+    assert!(get_partition_node_info(&ClusterRunner, &CommandConfig::new("sinfo", &[]), &clusters, &mut SkippedWarnings::new()).is_err());
 }
 
 /*
-    sinfo -o "%R %a %n %N %E %O %T %X %Y %Z" -h
+    sinfo -o "%R %a %n %N %E %O %T %X %Y %Z %C %G %m %e" -h
     %R: partition name
     %a: availability of partition (up / down)
     %n: host name
@@ -184,41 +836,153 @@ fn test_get_pn_info_util_02() {
     %X: number of sockets per node
     %Y: number of cores per node
     %Z: number of threads per node
+    %C: number of CPUs by state: allocated/idle/other/total
+    %G: generic resources (gres), for example gpu:a100:4(S:0-1)
+    %m: memory of the node in MB
+    %e: free memory of the node in MB, N/A if SLURM doesn't know it
 
     example output:
 
-    high_mem up node01.foo.bar node01 none 0.01 idle 2 2 2
-    high_mem up node02.foo.bar node02 none 0.02 idle 2 2 2
-    high_mem up node03.foo.bar node03 none 0.12 idle 2 2 2
-    high_mem up node04.foo.bar node04 none 0.03 idle 2 6 2
-    high_mem up node05.foo.bar node05 none 0.01 idle 2 4 2
-    high_mem up node06.foo.bar node06 none 0.01 idle 2 4 2
-    high_mem up node07.foo.bar node07 none 0.01 idle 2 4 2
-    high_mem up node08.foo.bar node08 none 0.01 idle 2 8 2
-    high_mem up node09.foo.bar node09 none 0.01 idle 2 8 2
-    high_mem up node10.foo.bar node10 none 0.01 idle 2 8 2
-    high_mem up node11.foo.bar node11 none 0.01 idle 2 2 2
+    high_mem up node01.foo.bar node01 none 0.01 idle 2 2 2 0/8/0/8 (null) 64000 61234
+    high_mem up node02.foo.bar node02 none 0.02 idle 2 2 2 0/8/0/8 (null) 64000 60987
+    high_mem up node03.foo.bar node03 none 0.12 idle 2 2 2 0/8/0/8 (null) 64000 59811
+    high_mem up node04.foo.bar node04 none 0.03 idle 2 6 2 0/24/0/24 (null) 192000 187345
+    high_mem up node05.foo.bar node05 none 0.01 idle 2 4 2 0/16/0/16 (null) 128000 N/A
+    high_mem up node06.foo.bar node06 none 0.01 idle 2 4 2 0/16/0/16 (null) 128000 125002
+    high_mem up node07.foo.bar node07 none 0.01 idle 2 4 2 0/16/0/16 (null) 128000 124876
+    high_mem up node08.foo.bar node08 none 0.01 idle 2 8 2 0/32/0/32 (null) 256000 250113
+    high_mem up node09.foo.bar node09 none 0.01 idle 2 8 2 0/32/0/32 (null) 256000 251002
+    high_mem up node10.foo.bar node10 none 0.01 idle 2 8 2 0/32/0/32 (null) 256000 249870
+    high_mem up node11.foo.bar node11 none 0.01 idle 2 2 2 0/8/0/8 (null) 64000 62001
 */
 
 // Private helper function to execute the "sinfo" SLURM command and return its output as a string
-// On error returns an empty string. TODO: better error handling
-fn call_sinfo() -> String {
-    let output = Command::new("sinfo")
-        .arg("-h")
-        .arg("-o")
-        .arg("%R %a %n %N %E %O %T %X %Y %Z")
-        .output();
-
-    match output {
-        Result::Ok(val) => String::from_utf8_lossy(&val.stdout).to_string(),
-        Result::Err(err) => {
-            error!("Could not execute 'sinfo': {}", err);
-            // return empty string on error, but continue with the program
-            String::new()
-        }
+fn call_sinfo(runner: &dyn CommandRunner, command: &CommandConfig) -> Result<String, String> {
+    run_command(runner, command, &["-h", "-o", "%R %a %n %N %E %O %T %X %Y %Z %C %G %m %e"])
+}
+
+#[test]
+fn test_call_sinfo_args() {
+    use command_runner::{TestRunner, split_args};
+
+    let runner = TestRunner::new(Ok("esd up node01 node01 none 0.22 idle 2 2 2 0/8/0/8 (null)".to_string()));
+    let output = call_sinfo(&runner, &CommandConfig::new("/opt/slurm/current/bin/sinfo", &split_args("-M cluster2")));
+
+    assert_eq!(get_pn_info_util(&output.unwrap(), &mut SkippedWarnings::new()).items.len(), 1);
+    let calls = runner.calls.lock().unwrap();
+    assert_eq!(calls[0].0, "/opt/slurm/current/bin/sinfo");
+    assert_eq!(calls[0].1, vec!["-M", "cluster2", "-h", "-o", "%R %a %n %N %E %O %T %X %Y %Z %C %G %m %e"]);
+    assert!(calls[0].2.contains(&("LC_ALL".to_string(), "C".to_string())));
+    assert!(calls[0].2.contains(&("SLURM_TIME_FORMAT".to_string(), "standard".to_string())));
+}
+
+// Private helper function to execute "sinfo --list-reasons" (-R), the reason is the only field with spaces, so "|" separates the fields
+fn call_sinfo_reasons(runner: &dyn CommandRunner, command: &CommandConfig) -> Result<String, String> {
+    run_command(runner, command, &["--list-reasons", "-h", "-o", "%n|%E|%U|%H"])
+}
+
+#[test]
+fn test_get_down_reasons_clusters() {
+    use command_runner::TestRunner;
+
+    let runner = TestRunner::new(Ok("CLUSTER: cluster2\nnode07|Bad DIMM|root(0)|2015-12-17T16:40:12".to_string()));
+    let reasons = get_down_reasons(&runner, &CommandConfig::new("sinfo", &[]), &["cluster2".to_string()]).unwrap();
+
+    assert_eq!(reasons.len(), 1);
+    assert_eq!((&reasons[0].cluster[..], &reasons[0].node[..]), ("cluster2", "node07"));
+    assert_eq!(runner.calls.lock().unwrap()[0].1, vec!["-M", "cluster2", "--list-reasons", "-h", "-o", "%n|%E|%U|%H"]);
+}
+
+// Private helper function to parse the number of CPUs by state, for example "2/6/0/8" (allocated/idle/other/total)
+// Returns None for "-" and if it isn't exactly four numbers
+fn str_to_cpus(cpus: &str) -> Option<(u32, u32, u32, u32)> {
+    let numbers: Vec<u32> = cpus.split('/').map(|number| number.parse::<u32>().ok()).collect::<Option<Vec<u32>>>()?;
+
+    if numbers.len() == 4 {
+        Some((numbers[0], numbers[1], numbers[2], numbers[3]))
+    } else {
+        None
     }
 }
 
+#[test]
+fn test_str_to_cpus() {
+    assert_eq!(str_to_cpus("2/6/0/8"), Some((2, 6, 0, 8)));
+    assert_eq!(str_to_cpus("0/0/128/128"), Some((0, 0, 128, 128)));
+}
+
+#[test]
+fn test_str_to_cpus_invalid() {
+    assert_eq!(str_to_cpus("-"), None);
+    assert_eq!(str_to_cpus("N/A"), None);
+    assert_eq!(str_to_cpus("2/6/0"), None);
+    assert_eq!(str_to_cpus("2/6/0/8/1"), None);
+    assert_eq!(str_to_cpus("2/x/0/8"), None);
+    assert_eq!(str_to_cpus(""), None);
+}
+
+#[test]
+fn test_cpu_utilization() {
+    let nodes = get_partition_node_info_test();
+    let node = |name: &str| nodes.iter().find(|node| node.node == name).unwrap();
+
+    assert_eq!((node("node01").cpus_alloc, node("node01").cpus_idle, node("node01").cpus_other, node("node01").cpus_total), (Some(0), Some(8), Some(0), Some(8)));
+    assert_eq!(node("node01").cpu_utilization(), Some(0.0));
+    assert_eq!(node("node05").cpu_utilization(), Some(100.0));
+    // Down node: "-"
+    assert_eq!(node("node03").cpus_total, None);
+    assert_eq!(node("node03").cpu_utilization(), None);
+}
+
+#[test]
+fn test_has_load_mismatch() {
+    let nodes = get_partition_node_info_test();
+    let node = |name: &str| nodes.iter().find(|node| node.node == name).unwrap().clone();
+
+    // Idle with low load and idle with high load
+    assert!(!node("node01").has_load_mismatch());
+    assert!(node("node04").has_load_mismatch());
+    // Allocated without load, likely a hung job
+    assert!(node("node05").has_load_mismatch());
+    let busy = PartitionNodeInfo{ cpu_load: Some(0.95), .. node("node05") };
+    assert!(!busy.has_load_mismatch());
+    // Nothing known about the down node
+    assert!(!node("node03").has_load_mismatch());
+}
+
+#[test]
+fn test_classify_load() {
+    // Oversubscribed above 110 %, in any state
+    assert_eq!(classify_load(110.0, NodeState::Allocated), LoadLevel::Normal);
+    assert_eq!(classify_load(110.1, NodeState::Allocated), LoadLevel::Oversubscribed);
+    assert_eq!(classify_load(800.0, NodeState::Idle), LoadLevel::Oversubscribed);
+    // Suspiciously idle below 5 %, only if allocated
+    assert_eq!(classify_load(5.0, NodeState::Allocated), LoadLevel::Normal);
+    assert_eq!(classify_load(4.9, NodeState::Allocated), LoadLevel::IdleAllocated);
+    assert_eq!(classify_load(0.0, NodeState::Allocated), LoadLevel::IdleAllocated);
+    assert_eq!(classify_load(0.0, NodeState::Idle), LoadLevel::Normal);
+    assert_eq!(classify_load(0.0, NodeState::Mixed), LoadLevel::Normal);
+}
+
+#[test]
+fn test_load_level() {
+    let nodes = get_partition_node_info_test();
+    let node = |name: &str| nodes.iter().find(|node| node.node == name).unwrap().clone();
+
+    // 2 sockets * 2 cores * 2 threads
+    assert_eq!(node("node01").core_count(), Some(8));
+    let busy = PartitionNodeInfo{ cpu_load: Some(16.0), node_state: NodeState::Allocated, .. node("node01") };
+    assert_eq!(busy.load_percent(), Some(200.0));
+    assert_eq!(busy.load_level(), Some(LoadLevel::Oversubscribed));
+    let hung = PartitionNodeInfo{ cpu_load: Some(0.2), .. busy.clone() };
+    assert_eq!(hung.load_level(), Some(LoadLevel::IdleAllocated));
+    // Missing socket, core or thread values or no load: no classification
+    assert_eq!(PartitionNodeInfo{ node_threads: None, .. busy.clone() }.load_level(), None);
+    assert_eq!(PartitionNodeInfo{ node_sockets: None, .. busy.clone() }.core_count(), None);
+    assert_eq!(PartitionNodeInfo{ node_cores: Some(0), .. busy.clone() }.load_percent(), None);
+    assert_eq!(PartitionNodeInfo{ cpu_load: None, .. busy }.load_level(), None);
+}
+
 // Private helper function to parse partition availability
 fn str_to_availability(avail: &str) -> PartitionAvailability {
     match &*avail.to_lowercase() {
@@ -243,6 +1007,10 @@ fn test_str_to_availability_down() {
 
 // Private helper function to parse partition error cause
 fn str_to_error(error: &str) -> ErrorCause {
+    if is_placeholder(error) {
+        return ErrorCause::None;
+    }
+
     match &*error.to_lowercase() {
         "down" => ErrorCause::Down,
         "drained" => ErrorCause::Drained,
@@ -280,8 +1048,8 @@ fn test_str_to_error_none() {
     assert_eq!(str_to_error("NONE"), ErrorCause::None);
 }
 
-// Private helper function to parse node state
-fn str_to_node_state(n_state: &str) -> NodeState {
+/// Public helper function to parse the node state, also used for the node state filter
+pub fn str_to_node_state(n_state: &str) -> NodeState {
     match &*n_state.to_lowercase() {
             "alloc" | "allocated" => NodeState::Allocated,
             "completing" => NodeState::Completing,
@@ -292,10 +1060,40 @@ fn str_to_node_state(n_state: &str) -> NodeState {
             "failing" => NodeState::Failing,
             "idle" => NodeState::Idle,
             "maint" => NodeState::Maint,
+            "mix" | "mixed" => NodeState::Mixed,
+            "resv" | "reserved" => NodeState::Reserved,
+            "powered_down" | "power_down" => NodeState::PoweredDown,
+            "planned" => NodeState::Planned,
             _ => NodeState::Unknown
     }
 }
 
+// Private helper function to parse a single flag character of the node state
+fn char_to_node_flag(flag: char) -> Option<NodeFlag> {
+    match flag {
+        '*' => Some(NodeFlag::NotResponding),
+        '~' => Some(NodeFlag::PoweredOff),
+        '#' => Some(NodeFlag::PoweringUp),
+        '%' => Some(NodeFlag::PoweringDown),
+        '!' => Some(NodeFlag::PendingPowerDown),
+        '$' => Some(NodeFlag::Maintenance),
+        '@' => Some(NodeFlag::PendingReboot),
+        '^' => Some(NodeFlag::Rebooting),
+        '-' => Some(NodeFlag::PlannedByBackfill),
+        _ => None
+    }
+}
+
+// Private helper function to parse node state with optional flag suffixes like "idle~" or "down*"
+fn str_to_node_state_and_flags(n_state: &str) -> (NodeState, Vec<NodeFlag>) {
+    let state_len = n_state.char_indices().rev()
+        .find(|&(_, c)| char_to_node_flag(c).is_none())
+        .map_or(0, |(index, c)| index + c.len_utf8());
+    let flags = n_state[state_len..].chars().filter_map(char_to_node_flag).collect();
+
+    (str_to_node_state(&n_state[..state_len]), flags)
+}
+
 #[test]
 fn test_str_to_node_state_alloc() {
     assert_eq!(str_to_node_state("alloc"), NodeState::Allocated);
@@ -368,3 +1166,74 @@ fn test_str_to_node_state_unknown() {
     assert_eq!(str_to_node_state("Unknown"), NodeState::Unknown);
     assert_eq!(str_to_node_state("UNKNOWN"), NodeState::Unknown);
 }
+
+#[test]
+fn test_str_to_node_state_new_states() {
+    assert_eq!(str_to_node_state("mixed"), NodeState::Mixed);
+    assert_eq!(str_to_node_state("MIX"), NodeState::Mixed);
+    assert_eq!(str_to_node_state("reserved"), NodeState::Reserved);
+    assert_eq!(str_to_node_state("powered_down"), NodeState::PoweredDown);
+    assert_eq!(str_to_node_state("planned"), NodeState::Planned);
+}
+
+#[test]
+fn test_node_enums_round_trip() {
+    for availability in &[PartitionAvailability::Up, PartitionAvailability::Down] {
+        assert_eq!(str_to_availability(availability.as_slurm_str()), *availability);
+    }
+
+    for error in &[ErrorCause::Down, ErrorCause::Drained, ErrorCause::Draining, ErrorCause::None, ErrorCause::Unknown] {
+        assert_eq!(str_to_error(error.as_slurm_str()), *error);
+    }
+
+    let states = [NodeState::Allocated, NodeState::Completing, NodeState::Down, NodeState::Drained, NodeState::Draining,
+        NodeState::Fail, NodeState::Failing, NodeState::Idle, NodeState::Maint, NodeState::Mixed, NodeState::Reserved,
+        NodeState::PoweredDown, NodeState::Planned, NodeState::Unknown];
+    for state in &states {
+        assert_eq!(str_to_node_state(state.as_slurm_str()), *state);
+        assert_eq!(str_to_node_state(&state.to_string()), *state);
+    }
+
+    assert_eq!(NodeState::PoweredDown.to_string(), "powered_down");
+    assert_eq!(PartitionAvailability::Up.to_string(), "up");
+}
+
+#[test]
+fn test_str_to_node_state_and_flags() {
+    assert_eq!(str_to_node_state_and_flags("idle~"), (NodeState::Idle, vec![NodeFlag::PoweredOff]));
+    assert_eq!(str_to_node_state_and_flags("mixed"), (NodeState::Mixed, vec![]));
+    assert_eq!(str_to_node_state_and_flags("down*"), (NodeState::Down, vec![NodeFlag::NotResponding]));
+    assert_eq!(str_to_node_state_and_flags("drained$"), (NodeState::Drained, vec![NodeFlag::Maintenance]));
+    assert_eq!(str_to_node_state_and_flags("idle#*"), (NodeState::Idle, vec![NodeFlag::PoweringUp, NodeFlag::NotResponding]));
+    assert_eq!(str_to_node_state_and_flags("powered_down"), (NodeState::PoweredDown, vec![]));
+    assert_eq!(str_to_node_state_and_flags("*"), (NodeState::Unknown, vec![NodeFlag::NotResponding]));
+}
+
+#[test]
+fn test_get_pn_info_util_flags() {
+    let result = get_pn_info_util("longrun up node01 node01 none 0.0 idle~ 1 1 1 0/1/0/1 (null)", &mut SkippedWarnings::new()).items;
+
+    assert_eq!(result.len(), 1);
+    assert_eq!(result[0].node_state, NodeState::Idle);
+    assert_eq!(result[0].node_flags, vec![NodeFlag::PoweredOff]);
+}
+
+#[test]
+fn test_get_pn_info_util_sorted() {
+    let input = "
+        longrun up node10 node10 none 0.0 idle 1 1 1 0/1/0/1 (null)
+        gpu up gpu-a10 gpu-a10 none 0.0 idle 1 1 1 0/1/0/1 (null)
+        longrun up node2 node2 none 0.0 idle 1 1 1 0/1/0/1 (null)
+        gpu up gpu-a1 gpu-a1 none 0.0 idle 1 1 1 0/1/0/1 (null)
+        longrun up node1 node1 none 0.0 idle 1 1 1 0/1/0/1 (null)
+    ";
+    let nodes: Vec<(String, String)> = get_pn_info_util(input, &mut SkippedWarnings::new()).items.into_iter().map(|info| (info.partition, info.node)).collect();
+
+    assert_eq!(nodes, vec![
+        ("gpu".to_string(), "gpu-a1".to_string()),
+        ("gpu".to_string(), "gpu-a10".to_string()),
+        ("longrun".to_string(), "node1".to_string()),
+        ("longrun".to_string(), "node2".to_string()),
+        ("longrun".to_string(), "node10".to_string())
+    ]);
+}
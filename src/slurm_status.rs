@@ -1,184 +1,3114 @@
-//! SLURM status data structure, updating and converting to HTML
+//! SLURM status data structure, updating and converting to HTML and plain text
 //! Contains partition, node and job information and also time of last update
 //! Runs a background thread in an endless loop to periodically check SLURM status
 //! and updates data structure accordingly
 
 // System modules:
-use std::sync::{Arc, Mutex};
+use std::sync::Arc;
 use std::thread;
-use std::thread::sleep;
 use std::time::Duration;
+use std::collections::{BTreeMap, BTreeSet, VecDeque};
 
 // External modules:
-use time::{strftime, now};
+use time::{strftime, now, get_time, precise_time_ns};
+use rustc_serialize::json::{Json, ToJson};
 
 // Internal modules:
-use sinfo_util::{PartitionNodeInfo, PartitionAvailability, get_partition_node_info, get_partition_node_info_test};
-use squeue_util::{JobInfo, get_job_info, get_job_info_test};
-use configuration::Configuration;
+use sinfo_util::{PartitionNodeInfo, PartitionAvailability, NodeState, DownReason, get_partition_node_info, get_partition_node_info_test,
+    get_partition_node_info_synthetic, get_down_reasons, get_down_reasons_test, merge_down_reasons};
+use squeue_util::{JobInfo, JobId, JobState, StateReason, get_job_info, get_job_info_test, get_job_info_synthetic, group_job_steps,
+    remove_job_steps};
+use sacct_util::{FinishedJobInfo, get_finished_job_info, get_finished_job_info_test};
+use sdiag_util::{SchedulerStats, get_scheduler_stats, get_scheduler_stats_test};
+use sshare_util::{ShareInfo, get_share_info, get_share_info_test};
+use sacctmgr_util::{QosInfo, get_qos_info, get_qos_info_test};
+use sreport_util::{UsageRecord, get_usage_info, get_usage_info_test, month_start};
+use sprio_util::{PriorityInfo, get_priority_info, get_priority_info_test, join_priorities};
+use scontrol_util::{ReservationInfo, PartitionLimits, UPCOMING_RESERVATION_HOURS, get_reservation_info, get_reservation_info_test, reservation_time,
+    get_partition_limits, get_partition_limits_test, LicenseInfo, get_license_info, get_license_info_test};
+use configuration::{Configuration, PartitionSelection};
+use rendered_status::RenderedStatus;
+use inspector_metrics::{InspectorMetrics, ParseResult, SkippedWarnings, metrics_to_prometheus};
+use gres::gpu_count;
+use job_arrays::group_job_arrays;
+use pagination::Pagination;
+use template::{Templates, STATUS_TEMPLATE, builtin_templates};
+use status_page::status_page;
+use status_filter::StatusFilter;
+use anonymize::{anonymize_status, anonymize_down_reasons};
+use history::{History, HistoryEntry, DEFAULT_HISTORY_SIZE};
+use recent_jobs::RecentlyFinished;
+use admin_actions::{AdminNotice, SimulatedNode, apply_simulated_nodes};
+use prom_textfile::write_prom_textfile;
+use display_time::DisplayTime;
+use raw_output::{RawOutput, CapturingRunner};
+use node_events::{NodeEvent, diff_node_info, diff_partition_availability, record_node_events};
+use webhook::{WebhookNotifier, HttpSender};
+use email_notify::{EmailNotifier, SmtpMailer, start_mail_thread};
+use history_db::HistoryDb;
+use command_runner::{CommandConfig, CommandRunner, SystemRunner, SshRunner};
+use command_record::{RecordingRunner, ReplayRunner};
+use hostlist::compress_hostlist;
+use update_schedule::{UpdateSchedule, DueUpdates};
+use refresh::RefreshControl;
+use status_stream::{Subscribers, StatusEvent};
+use wait_times::{WaitStatistics, TIME_FORMAT, set_wait_times};
+use synthetic_data::TEST_NOW;
+use build_info::BuildInfo;
+use top_jobs::{TOP_JOBS, longest_running, longest_waiting};
+use status_changes::StatusSnapshot;
+use shared_status::SharedStatus;
+use occupancy_grid::DEFAULT_GRID_WIDTH;
+use static_files::read_banner;
+use job_sort::JobSort;
+use error_log::{ErrorCategory, record_error};
+use config_view::{ConfigEntry, config_entries};
+use scheduler_pressure::{PressureThresholds, pending_ratio, pressure_to_prometheus};
 
-/// SlurmStatus type. This is shared accross the Iron response threads and the SLURM status thread
+/// SlurmStatus type. This is shared accross the Iron response threads and the SLURM status thread as immutable snapshot, see SharedStatus
+#[derive(Debug, Clone)]
 pub struct SlurmStatus {
     /// List of partition and node information
     pub node_info: Vec<PartitionNodeInfo>,
+    /// Why nodes are down, drained or failing, one entry per node
+    pub down_reasons: Vec<DownReason>,
     /// List of job information
     pub job_info: Vec<JobInfo>,
-    /// The last time the above two lists have been updated
-    /// Format: %Y.%m.%d - %H:%M
-    pub last_update: String
+    /// List of jobs that have finished recently
+    pub finished_job_info: Vec<FinishedJobInfo>,
+    /// Jobs that disappeared from squeue in the last minutes (--recently-finished-minutes)
+    pub recently_finished: RecentlyFinished,
+    /// Statistics of the SLURM scheduler
+    pub scheduler_stats: SchedulerStats,
+    /// Fair-share information of accounts and users, empty if sshare is not enabled
+    pub share_info: Vec<ShareInfo>,
+    /// Priority factors of the pending jobs, empty if sprio is not enabled
+    pub priority_info: Vec<PriorityInfo>,
+    /// Reservations of nodes, for example for a maintenance
+    pub reservations: Vec<ReservationInfo>,
+    /// Licenses of the cluster, empty if the licenses are not enabled
+    pub licenses: Vec<LicenseInfo>,
+    /// QOS of the cluster with their limits, empty if the QOS are not enabled
+    pub qos_info: Vec<QosInfo>,
+    /// CPU minutes of the accounts and users in the current month, sorted by CPU minutes, empty if sreport is not enabled
+    pub usage_info: Vec<UsageRecord>,
+    /// Time and errors of the last sreport call, they are not update errors since sreport runs in its own thread
+    pub usage_update: TableUpdate,
+    /// Limits of each partition by partition name, empty if the partition limits are not enabled
+    pub partition_limits: BTreeMap<String, PartitionLimits>,
+    /// Name of the cluster and version of SLURM, detected at startup or given with --cluster-name
+    pub cluster_name: String,
+    pub slurm_version: String,
+    /// The last time the above lists have been updated
+    /// Format: --date-format in the time zone of --display-timezone, default: %Y.%m.%d - %H:%M
+    pub last_update: String,
+    /// Time and errors of the last update of the node information and partition limits
+    pub node_update: TableUpdate,
+    /// Time and errors of the last update of the job information, finished jobs, scheduler statistics, fair-share information, reservations, licenses and QOS
+    pub job_update: TableUpdate,
+    /// Number of nodes and jobs per state of the last updates
+    pub history: History,
+    /// The most recent node state changes, oldest first
+    pub node_events: VecDeque<NodeEvent>,
+    /// Errors of the last update of each table, for example if sinfo could not be executed
+    pub update_errors: Vec<String>,
+    /// Number of partitions that are hidden by --exclude-partitions or --include-partitions
+    pub hidden_partitions: usize,
+    /// Time of the last update without errors in seconds since the epoch, None if there was none yet
+    pub last_success: Option<i64>,
+    /// Number of updates with errors since the last update without errors
+    pub consecutive_failures: u32,
+    /// Wall time and parsed / skipped lines of the sinfo and squeue calls
+    pub metrics: InspectorMetrics,
+    /// Version, commit and start time of slurm_inspector
+    pub build_info: BuildInfo,
+    /// Nodes drained or resumed by an admin action in test mode by node name, applied to the test data after every update
+    pub simulated_nodes: BTreeMap<String, SimulatedNode>,
+    /// Raw output of the sinfo and squeue calls of the last update, only kept with --debug-endpoints
+    pub raw_sinfo: Option<RawOutput>,
+    pub raw_squeue: Option<RawOutput>,
+    /// The HTML page, JSON and plain text rendered (and compressed) after the last update
+    pub rendered: RenderedStatus,
+    /// Jobs and nodes of the snapshot before the last change, for /api/changes
+    pub previous_snapshot: Option<StatusSnapshot>,
+    /// Text of the banner file (--banner-file) at the last update, None if there is no banner
+    pub banner: Option<String>
 }
 
-/// Public function that starts the SLURM status thread and update the SlurmStatus object accordingly every time interval
-/// TODO: better error handling
-pub fn check_slurm_status(local_slurm_status: &Arc<Mutex<SlurmStatus>>, config: Configuration) {
+impl SlurmStatus {
+    /// Create an empty SlurmStatus object
+    pub fn new() -> SlurmStatus {
+        SlurmStatus {
+            node_info: Vec::new(),
+            down_reasons: Vec::new(),
+            job_info: Vec::new(),
+            finished_job_info: Vec::new(),
+            recently_finished: RecentlyFinished::new(),
+            scheduler_stats: SchedulerStats::new(),
+            share_info: Vec::new(),
+            priority_info: Vec::new(),
+            reservations: Vec::new(),
+            licenses: Vec::new(),
+            qos_info: Vec::new(),
+            usage_info: Vec::new(),
+            usage_update: TableUpdate::new(),
+            partition_limits: BTreeMap::new(),
+            cluster_name: String::new(),
+            slurm_version: String::new(),
+            last_update: String::new(),
+            node_update: TableUpdate::new(),
+            job_update: TableUpdate::new(),
+            history: History::new(DEFAULT_HISTORY_SIZE),
+            node_events: VecDeque::new(),
+            update_errors: Vec::new(),
+            hidden_partitions: 0,
+            last_success: None,
+            consecutive_failures: 0,
+            metrics: InspectorMetrics::new(),
+            build_info: BuildInfo::new(),
+            simulated_nodes: BTreeMap::new(),
+            raw_sinfo: None,
+            raw_squeue: None,
+            rendered: RenderedStatus::new(),
+            previous_snapshot: None,
+            banner: None
+        }
+    }
+}
+
+/// Node and job information are fetched with their own interval, so each table has its own update time
+#[derive(Debug, Clone, PartialEq)]
+pub struct TableUpdate {
+    /// Format: like SlurmStatus::last_update, empty before the first update
+    pub time: String,
+    /// Errors of the last update of this table
+    pub errors: Vec<String>
+}
+
+impl TableUpdate {
+    /// Create a TableUpdate for a table that has not been updated yet
+    pub fn new() -> TableUpdate {
+        TableUpdate {
+            time: String::new(),
+            errors: Vec::new()
+        }
+    }
+}
+
+/// Aggregated cluster usage of a single user
+#[derive(Debug, Clone, PartialEq)]
+pub struct UserUsage {
+    pub user_name: String,
+    /// Number of jobs in state running
+    pub running_jobs: u32,
+    /// Number of jobs in state pending
+    pub pending_jobs: u32,
+    /// Sum of CPUs of all running jobs, jobs without CPU information count as 0
+    pub cpus_in_use: u32,
+    /// Number of distinct nodes used by all jobs of the user
+    pub nodes: u32
+}
+
+impl ToJson for UserUsage {
+    fn to_json(&self) -> Json {
+        let mut result = BTreeMap::new();
+        result.insert("user_name".to_string(), self.user_name.to_json());
+        result.insert("running_jobs".to_string(), self.running_jobs.to_json());
+        result.insert("pending_jobs".to_string(), self.pending_jobs.to_json());
+        result.insert("cpus_in_use".to_string(), self.cpus_in_use.to_json());
+        result.insert("nodes".to_string(), self.nodes.to_json());
+        Json::Object(result)
+    }
+}
+
+/// Public helper function that groups the jobs by user name and computes the usage of each user
+/// The result is sorted by CPUs in use (descending), then by user name
+pub fn usage_by_user(job_info: &[JobInfo]) -> Vec<UserUsage> {
+    let mut users: BTreeMap<&str, (UserUsage, BTreeSet<&str>)> = BTreeMap::new();
+
+    for job in job_info {
+        let entry = users.entry(&job.user_name).or_insert_with(|| (UserUsage{
+            user_name: job.user_name.clone(),
+            running_jobs: 0,
+            pending_jobs: 0,
+            cpus_in_use: 0,
+            nodes: 0
+        }, BTreeSet::new()));
+
+        match job.job_state {
+            JobState::Running => {
+                entry.0.running_jobs += 1;
+                entry.0.cpus_in_use += job.num_cpu.unwrap_or(0);
+            },
+            JobState::Pending => entry.0.pending_jobs += 1,
+            _ => {}
+        }
+
+        for node in &job.list_of_nodes {
+            entry.1.insert(node);
+        }
+    }
+
+    let mut result: Vec<UserUsage> = users.into_iter().map(|(_, (mut usage, nodes))| {
+        usage.nodes = nodes.len() as u32;
+        usage
+    }).collect();
+
+    result.sort_by(|a, b| b.cpus_in_use.cmp(&a.cpus_in_use).then_with(|| a.user_name.cmp(&b.user_name)));
+
+    result
+}
+
+#[test]
+fn test_usage_by_user_empty() {
+    assert_eq!(usage_by_user(&[]), Vec::new());
+}
+
+#[test]
+fn test_usage_by_user() {
+    let usage = usage_by_user(&get_job_info_test());
+
+    assert_eq!(usage.len(), 5);
+    assert_eq!(usage[0], UserUsage{ user_name: "user01".to_string(), running_jobs: 1, pending_jobs: 0, cpus_in_use: 2, nodes: 1 });
+    assert_eq!(usage[1], UserUsage{ user_name: "user02".to_string(), running_jobs: 0, pending_jobs: 1, cpus_in_use: 0, nodes: 2 });
+    assert_eq!(usage[2], UserUsage{ user_name: "user03".to_string(), running_jobs: 0, pending_jobs: 0, cpus_in_use: 0, nodes: 1 });
+    assert_eq!(usage[3], UserUsage{ user_name: "user04".to_string(), running_jobs: 0, pending_jobs: 0, cpus_in_use: 0, nodes: 1 });
+    assert_eq!(usage[4], UserUsage{ user_name: "user05".to_string(), running_jobs: 0, pending_jobs: 0, cpus_in_use: 0, nodes: 4 });
+}
+
+#[test]
+fn test_usage_by_user_missing_cpu() {
+    let mut jobs = get_job_info_test();
+    for job in jobs.iter_mut() {
+        job.job_state = JobState::Running;
+    }
+    jobs[0].num_cpu = None;
+
+    let usage = usage_by_user(&jobs);
+    let user01 = usage.iter().find(|usage| usage.user_name == "user01").unwrap();
+
+    // user01 has two running jobs, but only one with CPU information
+    assert_eq!(user01.running_jobs, 2);
+    assert_eq!(user01.cpus_in_use, 2);
+    // Sorted by CPUs: user02 and user05 have 3 jobs with 2 CPUs each, then sorted by name
+    assert_eq!(usage[0].user_name, "user02");
+    assert_eq!(usage[0].cpus_in_use, 6);
+    assert_eq!(usage[1].user_name, "user05");
+    assert_eq!(usage[1].cpus_in_use, 6);
+}
+
+/// Public helper function that maps each node to the IDs of the jobs on it, keyed by (cluster, node name)
+/// Jobs without job ID or without nodes (for example pending jobs) and job steps are left out
+pub fn jobs_by_node(job_info: &[JobInfo]) -> BTreeMap<(String, String), Vec<JobId>> {
+    let mut result: BTreeMap<(String, String), Vec<JobId>> = BTreeMap::new();
+
+    for job in job_info {
+        if let Some(ref job_id) = job.job_id {
+            if job_id.is_step() {
+                continue;
+            }
+            for node in &job.list_of_nodes {
+                result.entry((job.cluster.clone(), node.clone())).or_insert_with(Vec::new).push(job_id.clone());
+            }
+        }
+    }
+
+    result
+}
+
+#[test]
+fn test_jobs_by_node() {
+    let mut job_info = get_job_info_test();
+    let mut step = job_info[1].clone();
+    step.job_id = ::squeue_util::str_to_job_id("2.batch");
+    job_info.push(step);
+    let node_jobs = jobs_by_node(&job_info);
+    let job_ids = |node: &str| node_jobs.get(&(String::new(), node.to_string())).map(|job_ids| job_ids.iter().map(|job_id| job_id.id).collect::<Vec<u32>>());
+
+    assert_eq!(node_jobs.len(), 5);
+    assert_eq!(job_ids("node01"), Some(vec![1, 2, 3, 4, 5, 7, 8, 9, 10, 11, 12]));
+    assert_eq!(job_ids("node02"), Some(vec![2]));
+    assert_eq!(job_ids("node04"), Some(vec![6]));
+    assert_eq!(job_ids("node06"), None);
+}
+
+#[test]
+fn test_jobs_by_node_empty_and_clusters() {
+    let mut jobs = get_job_info_test();
+    jobs.truncate(2);
+    jobs[0].list_of_nodes.clear();
+    jobs[1].cluster = "cluster2".to_string();
+    jobs[1].list_of_nodes = ::hostlist::expand_hostlist("node[01-03]");
+
+    let node_jobs = jobs_by_node(&jobs);
+    assert_eq!(node_jobs.keys().cloned().collect::<Vec<(String, String)>>(), vec![
+        ("cluster2".to_string(), "node01".to_string()),
+        ("cluster2".to_string(), "node02".to_string()),
+        ("cluster2".to_string(), "node03".to_string())
+    ]);
+    assert!(jobs_by_node(&[]).is_empty());
+}
+
+/// Headline numbers shown at the top of the page
+#[derive(Debug, Clone, PartialEq)]
+pub struct DashboardSummary {
+    /// Number of distinct nodes, a node can be in several partitions
+    pub total_nodes: u32,
+    /// Number of nodes that are down or failed
+    pub nodes_down: u32,
+    /// Number of nodes that are draining or drained
+    pub nodes_draining: u32,
+    pub running_jobs: u32,
+    pub pending_jobs: u32,
+    /// Number of distinct users with running or pending jobs
+    pub active_users: u32,
+    /// Sum of CPUs of all running jobs, jobs without CPU information count as 0
+    pub allocated_cpus: u32,
+    /// Number of distinct nodes used by running jobs, nodes shared by several jobs are counted once
+    pub nodes_in_use: u32,
+    /// Number of GPUs of all distinct nodes
+    pub total_gpus: u32,
+    /// Sum of GPUs of all running jobs, the GPUs per node times the number of nodes
+    pub allocated_gpus: u32,
+    /// Number of distinct users with pending jobs
+    pub pending_users: u32,
+    /// Wait time in seconds of the pending job that waits longest, None if no pending job has a wait time
+    pub oldest_pending: Option<u64>,
+    /// Number of pending jobs waiting for resources (reason Resources)
+    pub pending_resources: u32,
+    /// Number of pending jobs waiting for jobs with a higher priority (reason Priority)
+    pub pending_priority: u32
+}
+
+impl DashboardSummary {
+    /// Number of pending jobs per running job, see scheduler_pressure::pending_ratio
+    pub fn pending_ratio(&self) -> f64 {
+        pending_ratio(self.pending_jobs, self.running_jobs)
+    }
+}
+
+impl ToJson for DashboardSummary {
+    fn to_json(&self) -> Json {
+        let mut result = BTreeMap::new();
+        result.insert("total_nodes".to_string(), self.total_nodes.to_json());
+        result.insert("nodes_down".to_string(), self.nodes_down.to_json());
+        result.insert("nodes_draining".to_string(), self.nodes_draining.to_json());
+        result.insert("running_jobs".to_string(), self.running_jobs.to_json());
+        result.insert("pending_jobs".to_string(), self.pending_jobs.to_json());
+        result.insert("active_users".to_string(), self.active_users.to_json());
+        result.insert("allocated_cpus".to_string(), self.allocated_cpus.to_json());
+        result.insert("nodes_in_use".to_string(), self.nodes_in_use.to_json());
+        result.insert("total_gpus".to_string(), self.total_gpus.to_json());
+        result.insert("allocated_gpus".to_string(), self.allocated_gpus.to_json());
+        result.insert("pending_users".to_string(), self.pending_users.to_json());
+        result.insert("oldest_pending".to_string(), self.oldest_pending.to_json());
+        result.insert("pending_ratio".to_string(), self.pending_ratio().to_json());
+        result.insert("pending_resources".to_string(), self.pending_resources.to_json());
+        result.insert("pending_priority".to_string(), self.pending_priority.to_json());
+        Json::Object(result)
+    }
+}
+
+/// Public helper function that computes the headline numbers of the nodes and jobs that pass the filter
+pub fn dashboard_summary(status: &SlurmStatus, filter: &StatusFilter) -> DashboardSummary {
+    // A node can be in several partitions, but it has the same state and GPUs in all of them
+    let nodes: BTreeMap<(&str, &str), (NodeState, u64)> = status.node_info.iter()
+        .filter(|node| filter.node_matches(node))
+        .map(|node| ((&node.cluster[..], &node.node[..]), (node.node_state, gpu_count(&node.gres))))
+        .collect();
+    let count_nodes = |matches: &dyn Fn(NodeState) -> bool| nodes.values().filter(|&&(state, _)| matches(state)).count() as u32;
+    let mut users: BTreeSet<&str> = BTreeSet::new();
+    let mut pending_users: BTreeSet<&str> = BTreeSet::new();
+    let mut nodes_in_use: BTreeSet<(&str, &str)> = BTreeSet::new();
+    let mut result = DashboardSummary {
+        total_nodes: nodes.len() as u32,
+        nodes_down: count_nodes(&|state| state == NodeState::Down || state == NodeState::Fail),
+        nodes_draining: count_nodes(&|state| state == NodeState::Draining || state == NodeState::Drained),
+        running_jobs: 0,
+        pending_jobs: 0,
+        active_users: 0,
+        allocated_cpus: 0,
+        nodes_in_use: 0,
+        total_gpus: nodes.values().fold(0, |total: u64, &(_, gpus)| total.saturating_add(gpus)) as u32,
+        allocated_gpus: 0,
+        pending_users: 0,
+        oldest_pending: None,
+        pending_resources: 0,
+        pending_priority: 0
+    };
+
+    for job in status.job_info.iter().filter(|job| filter.job_matches(job)) {
+        match job.job_state {
+            JobState::Running => {
+                result.running_jobs += 1;
+                result.allocated_cpus += job.num_cpu.unwrap_or(0);
+                // The GRES counts come from squeue, a bogus count must not overflow
+                result.allocated_gpus = result.allocated_gpus.saturating_add(gpu_count(&job.gres).saturating_mul(job.num_nodes.unwrap_or(1) as u64) as u32);
+                for node in &job.list_of_nodes {
+                    nodes_in_use.insert((&job.cluster, node));
+                }
+            },
+            JobState::Pending => {
+                result.pending_jobs += 1;
+                result.oldest_pending = result.oldest_pending.max(job.wait_time);
+                match job.state_reason {
+                    StateReason::Resources => result.pending_resources += 1,
+                    StateReason::Priority => result.pending_priority += 1,
+                    _ => ()
+                }
+                pending_users.insert(&job.user_name);
+            },
+            _ => continue
+        }
+        users.insert(&job.user_name);
+    }
+
+    result.active_users = users.len() as u32;
+    result.pending_users = pending_users.len() as u32;
+    result.nodes_in_use = nodes_in_use.len() as u32;
+
+    result
+}
+
+/// Public helper function that returns the text of /metrics (and of --prom-textfile): the metrics of the SLURM commands
+/// and the scheduler pressure of all jobs
+pub fn status_to_prometheus(status: &SlurmStatus) -> String {
+    let mut result = metrics_to_prometheus(&status.metrics, &status.cluster_name, &status.slurm_version);
+    result.push_str(&pressure_to_prometheus(&dashboard_summary(status, &StatusFilter::new()), &status.cluster_name));
+    result
+}
+
+#[test]
+fn test_dashboard_summary() {
+    use sinfo_util::get_partition_node_info_test;
+
+    let mut status = SlurmStatus::new();
+    status.node_info = get_partition_node_info_test();
+    status.job_info = get_job_info_test();
+
+    assert_eq!(dashboard_summary(&status, &StatusFilter::new()), DashboardSummary {
+        total_nodes: 12,
+        nodes_down: 1,
+        nodes_draining: 2,
+        running_jobs: 1,
+        pending_jobs: 1,
+        active_users: 2,
+        allocated_cpus: 2,
+        nodes_in_use: 1,
+        total_gpus: 4,
+        allocated_gpus: 2,
+        pending_users: 1,
+        oldest_pending: None,
+        pending_resources: 0,
+        pending_priority: 0
+    });
+}
+
+#[test]
+fn test_dashboard_summary_pressure() {
+    // Crafted jobs: user01 runs one job and waits with two, user02 waits with one, user03 waits behind a dependency
+    let template = get_job_info_test().remove(0);
+    let job = |user: &str, state: JobState, reason: StateReason, wait_time: Option<u64>| JobInfo {
+        user_name: user.to_string(),
+        job_state: state,
+        state_reason: reason,
+        wait_time: wait_time,
+        .. template.clone()
+    };
+    let mut status = SlurmStatus::new();
+    status.job_info = vec![
+        job("user01", JobState::Running, StateReason::None, None),
+        job("user01", JobState::Pending, StateReason::Resources, Some(600)),
+        job("user01", JobState::Pending, StateReason::Priority, Some(7200)),
+        job("user02", JobState::Pending, StateReason::Resources, Some(90000)),
+        job("user03", JobState::Pending, StateReason::Dependency, None),
+        job("user04", JobState::Completed, StateReason::None, None)
+    ];
+
+    let summary = dashboard_summary(&status, &StatusFilter::new());
+    assert_eq!((summary.running_jobs, summary.pending_jobs), (1, 4));
+    assert_eq!(summary.pending_users, 3);
+    assert_eq!(summary.oldest_pending, Some(90000));
+    assert_eq!((summary.pending_resources, summary.pending_priority), (2, 1));
+    assert_eq!(summary.pending_ratio(), 4.0);
+
+    // The indicators follow the filter
+    let filter = StatusFilter{ user: Some("user01".to_string()), .. StatusFilter::new() };
+    let summary = dashboard_summary(&status, &filter);
+    assert_eq!((summary.pending_users, summary.oldest_pending, summary.pending_resources), (1, Some(7200), 1));
+    assert_eq!(summary.pending_ratio(), 2.0);
+
+    // No pending jobs at all
+    status.job_info.retain(|job| job.job_state != JobState::Pending);
+    let summary = dashboard_summary(&status, &StatusFilter::new());
+    assert_eq!((summary.pending_users, summary.oldest_pending, summary.pending_ratio()), (0, None, 0.0));
+}
+
+#[test]
+fn test_dashboard_summary_multi_node_jobs() {
+    let mut status = SlurmStatus::new();
+    status.job_info = get_job_info_test();
+    for job in status.job_info.iter_mut() {
+        job.job_state = JobState::Running;
+    }
+    status.job_info[0].num_cpu = None;
+    // The same node in two partitions is only counted once
+    status.node_info = get_partition_node_info_test();
+    let mut other_partition = status.node_info[0].clone();
+    other_partition.partition = "gpu".to_string();
+    status.node_info.push(other_partition);
+
+    let summary = dashboard_summary(&status, &StatusFilter::new());
+    assert_eq!(summary.total_nodes, 12);
+    assert_eq!(summary.running_jobs, 12);
+    assert_eq!(summary.active_users, 5);
+    // 11 jobs with CPU information, job 1 has none
+    assert_eq!(summary.allocated_cpus, 22);
+    // node01 to node05, although most jobs run on node01
+    assert_eq!(summary.nodes_in_use, 5);
+    // node01 has 4 GPUs, job 1 uses 2 GPUs on one node
+    assert_eq!((summary.total_gpus, summary.allocated_gpus), (4, 2));
+}
+
+/// Summary of a partition, used for the partition table, the text report and /api/partitions
+#[derive(Debug, Clone, PartialEq)]
+pub struct PartitionSummary {
+    /// Name of the cluster, empty if only the local cluster is monitored
+    pub cluster: String,
+    pub name: String,
+    /// Down if any node of the partition reports the partition as down
+    pub availability: PartitionAvailability,
+    pub nodes: u32,
+    /// Number of nodes per node state
+    pub node_states: BTreeMap<String, u32>,
+    pub gpus: u64,
+    /// Sum of the memory of the nodes in MB, nodes without memory information count as 0
+    pub memory_total: u64,
+    /// Sum of the used memory of the nodes that report their free memory, None if no node does
+    pub memory_used: Option<u64>,
+    /// Limits from scontrol, None if the partition limits are not enabled or scontrol doesn't know the partition
+    pub limits: Option<PartitionLimits>,
+    /// Wait times of the pending jobs that have been submitted to the partition, None if there are none
+    pub wait_times: Option<WaitStatistics>
+}
+
+impl ToJson for PartitionSummary {
+    fn to_json(&self) -> Json {
+        let mut result = BTreeMap::new();
+        result.insert("cluster".to_string(), self.cluster.to_json());
+        result.insert("name".to_string(), self.name.to_json());
+        result.insert("availability".to_string(), self.availability.to_json());
+        result.insert("nodes".to_string(), self.nodes.to_json());
+        result.insert("node_states".to_string(), self.node_states.to_json());
+        result.insert("gpus".to_string(), self.gpus.to_json());
+        result.insert("memory_total".to_string(), self.memory_total.to_json());
+        result.insert("memory_used".to_string(), self.memory_used.to_json());
+        result.insert("limits".to_string(), self.limits.to_json());
+        result.insert("wait_times".to_string(), self.wait_times.to_json());
+        Json::Object(result)
+    }
+}
+
+/// Public helper function that summarizes the nodes that pass the filter by partition
+/// The nodes are sorted by cluster and partition, so each group of nodes is a single partition
+/// The wait times only include the pending jobs that pass the filter, a job submitted to several partitions counts in each of them
+pub fn partition_summaries(status: &SlurmStatus, filter: &StatusFilter) -> Vec<PartitionSummary> {
+    let node_info: Vec<&PartitionNodeInfo> = status.node_info.iter().filter(|node| filter.node_matches(node)).collect();
+    let waiting_jobs: Vec<&JobInfo> = status.job_info.iter().filter(|job| job.wait_time.is_some() && filter.job_matches(job)).collect();
+    let mut result = Vec::new();
+    let mut index = 0;
+
+    while index < node_info.len() {
+        let node = node_info[index];
+        let group: Vec<&PartitionNodeInfo> = node_info[index..].iter()
+            .take_while(|other| other.cluster == node.cluster && other.partition == node.partition)
+            .cloned()
+            .collect();
+        let mut node_states = BTreeMap::new();
+        for member in &group {
+            *node_states.entry(member.node_state.to_string()).or_insert(0) += 1;
+        }
+
+        result.push(PartitionSummary {
+            cluster: node.cluster.clone(),
+            name: node.partition.clone(),
+            availability: if group.iter().any(|member| member.availability == PartitionAvailability::Down) { PartitionAvailability::Down } else { PartitionAvailability::Up },
+            nodes: group.len() as u32,
+            node_states: node_states,
+            gpus: group.iter().fold(0, |total: u64, member| total.saturating_add(gpu_count(&member.gres))),
+            memory_total: group.iter().filter_map(|member| member.memory_total).sum(),
+            memory_used: group.iter().filter_map(|member| member.memory_used()).fold(None, |sum, used| Some(sum.unwrap_or(0) + used)),
+            limits: status.partition_limits.get(&node.partition).cloned(),
+            wait_times: WaitStatistics::new(&waiting_jobs.iter()
+                .filter(|job| job.cluster == node.cluster && job.partition.split(',').any(|partition| partition == node.partition))
+                .filter_map(|job| job.wait_time)
+                .collect::<Vec<u64>>())
+        });
+        index += group.len();
+    }
+
+    result
+}
+
+#[test]
+fn test_partition_summaries() {
+    let mut status = SlurmStatus::new();
+    status.node_info = get_partition_node_info_test();
+    status.partition_limits = get_partition_limits_test();
+    let mut other_partition = status.node_info[0].clone();
+    other_partition.partition = "gpu".to_string();
+    status.node_info.push(other_partition);
+
+    let summaries = partition_summaries(&status, &StatusFilter::new());
+    assert_eq!(summaries.len(), 2);
+    assert_eq!((&summaries[0].name[..], summaries[0].availability, summaries[0].nodes, summaries[0].gpus), ("esd", PartitionAvailability::Down, 12, 4));
+    assert_eq!(summaries[0].node_states["idle"], 3);
+    assert_eq!(summaries[0].limits.as_ref().and_then(|limits| limits.max_time.as_ref()).map(|max_time| max_time.to_string()), Some("7-00:00:00".to_string()));
+    // scontrol doesn't know this partition
+    assert_eq!((&summaries[1].name[..], summaries[1].nodes), ("gpu", 1));
+    assert_eq!(summaries[1].limits, None);
+    assert_eq!(summaries[1].wait_times, None);
+    // node12 has no memory information, node03, node09 and node10 don't report their free memory
+    assert_eq!((summaries[0].memory_total, summaries[0].memory_used), (196000, Some(20100)));
+    assert_eq!((summaries[1].memory_total, summaries[1].memory_used), (32000, Some(4000)));
+    status.node_info[12].memory_free = None;
+    assert_eq!(partition_summaries(&status, &StatusFilter::new())[1].memory_used, None);
+
+    // Job 8 is pending in esd, the other jobs are pending in gpu and in both partitions
+    status.job_info = get_job_info_test();
+    for (index, &(partition, wait_time)) in [("gpu", 60), ("esd,gpu", 600), ("gpu", 3600)].iter().enumerate() {
+        status.job_info[index].job_state = JobState::Pending;
+        status.job_info[index].partition = partition.to_string();
+        status.job_info[index].wait_time = Some(wait_time);
+    }
+    status.job_info[7].wait_time = Some(120);
+    let summaries = partition_summaries(&status, &StatusFilter::new());
+    assert_eq!(summaries[0].wait_times, Some(WaitStatistics{ jobs: 2, min: 120, median: 120, p95: 600, max: 600 }));
+    assert_eq!(summaries[1].wait_times, Some(WaitStatistics{ jobs: 3, min: 60, median: 600, p95: 3600, max: 3600 }));
+    assert_eq!(summaries[1].to_json().find_path(&["wait_times", "median"]), Some(&Json::U64(600)));
+}
+
+/// Options that change how the HTML page is rendered
+#[derive(Debug, Clone, PartialEq)]
+pub struct PageOptions {
+    /// Auto refresh interval of the web page in seconds, 0 disables auto refresh
+    pub page_refresh: u64,
+    /// Link the custom stylesheet (/static/custom.css) after the default one
+    pub custom_css: bool,
+    /// Finished jobs are shown for this number of hours
+    pub finished_hours: u64,
+    /// Show the fair-share table
+    pub show_shares: bool,
+    /// Show the table with the CPU minutes of this month (sreport)
+    pub show_usage: bool,
+    /// Only show jobs that pass this filter
+    pub filter: StatusFilter,
+    /// Show each task of a job array in its own row instead of grouping the tasks
+    pub expand_arrays: bool,
+    /// Order of the job table (--default-sort or ?sort=)
+    pub sort: JobSort,
+    /// Rows of the job table that are shown
+    pub pagination: Pagination,
+    /// Number of nodes per row of the occupancy grid, 0 hides the grid
+    pub grid_width: usize,
+    /// Query string of the request, the other parameters are kept in the links to the previous and next page
+    pub query: String,
+    /// Templates of the page, the built-in ones or the ones from --template-dir
+    pub templates: Arc<Templates>,
+    /// Prepended to the links to the stylesheets and the other pages, see Configuration::url_prefix
+    pub url_prefix: String,
+    /// Ids of the shown columns of the job and the node table, empty shows all columns
+    pub job_columns: Vec<String>,
+    pub node_columns: Vec<String>,
+    /// Unknown column ids of the query string (?columns= and ?node_columns=), shown in a notice on the page
+    pub unknown_job_columns: Vec<String>,
+    pub unknown_node_columns: Vec<String>,
+    /// Show the buttons of the admin actions like cancelling a job
+    pub admin_actions: bool,
+    /// Result of the admin action that redirected to the page (?done= or ?failed=)
+    pub admin_notice: Option<AdminNotice>,
+    /// User of the request (?me= or the header of --remote-user-header), the jobs of this user are highlighted
+    pub me: Option<String>,
+    /// Settings of the configuration in effect, shown in a collapsible section with --debug-endpoints, empty otherwise
+    pub config: Vec<ConfigEntry>,
+    /// Thresholds of the scheduler pressure indicators of the dashboard (--pressure-thresholds)
+    pub pressure_thresholds: PressureThresholds,
+    /// Time zone and format of the timestamps of SLURM (start times, reservations, ...), see Configuration::display_time
+    pub display_time: DisplayTime
+}
+
+impl PageOptions {
+    /// Create the page options from the configuration
+    pub fn new(config: &Configuration) -> PageOptions {
+        PageOptions {
+            page_refresh: config.page_refresh,
+            custom_css: config.custom_css.is_some(),
+            finished_hours: config.finished_hours,
+            show_shares: config.enable_sshare,
+            show_usage: config.enable_sreport,
+            filter: StatusFilter{ hide_finished: true, .. StatusFilter::for_team(&config.users) },
+            expand_arrays: false,
+            sort: config.default_sort,
+            pagination: Pagination::new(config.page_limit, 1),
+            grid_width: config.grid_width,
+            query: String::new(),
+            templates: config.templates.clone(),
+            url_prefix: config.url_prefix.clone(),
+            job_columns: config.job_columns.clone(),
+            node_columns: config.node_columns.clone(),
+            unknown_job_columns: Vec::new(),
+            unknown_node_columns: Vec::new(),
+            admin_actions: config.enable_admin_actions,
+            admin_notice: None,
+            me: None,
+            config: if config.debug_endpoints { config_entries(config) } else { Vec::new() },
+            pressure_thresholds: config.pressure_thresholds.clone(),
+            display_time: config.display_time.clone()
+        }
+    }
+}
+
+impl Default for PageOptions {
+    fn default() -> PageOptions {
+        PageOptions {
+            page_refresh: 0,
+            custom_css: false,
+            finished_hours: 24,
+            show_shares: false,
+            show_usage: false,
+            filter: StatusFilter::new(),
+            expand_arrays: false,
+            sort: JobSort::default(),
+            pagination: Pagination::default(),
+            grid_width: DEFAULT_GRID_WIDTH,
+            query: String::new(),
+            templates: Arc::new(builtin_templates()),
+            url_prefix: String::new(),
+            job_columns: Vec::new(),
+            node_columns: Vec::new(),
+            unknown_job_columns: Vec::new(),
+            unknown_node_columns: Vec::new(),
+            admin_actions: false,
+            admin_notice: None,
+            me: None,
+            config: Vec::new(),
+            pressure_thresholds: PressureThresholds::default(),
+            display_time: DisplayTime::default()
+        }
+    }
+}
+
+/// Public helper function to create the runner for the SLURM commands, either local or on the remote host via ssh
+/// With --record-dir the output is recorded as well, with --replay-dir the recorded output is read instead
+pub fn create_runner(config: &Configuration) -> Box<dyn CommandRunner + Send> {
+    if let Some(ref dir) = config.replay_dir {
+        return Box::new(ReplayRunner::new(dir));
+    }
+
+    let runner: Box<dyn CommandRunner + Send> = match config.remote_host {
+        Some(ref host) => Box::new(SshRunner::new(host, &config.remote_ssh_args, SystemRunner)),
+        None => Box::new(SystemRunner)
+    };
+    match config.record_dir {
+        Some(ref dir) => Box::new(RecordingRunner::new(dir, config.record_keep, runner)),
+        None => runner
+    }
+}
+
+/// Public helper function to remove the nodes of partitions that are hidden by the configuration
+/// Returns the number of hidden partitions, a node that is also in a visible partition is kept in that partition
+pub fn hide_partitions(node_info: &mut Vec<PartitionNodeInfo>, partitions: &PartitionSelection) -> usize {
+    let hidden: BTreeSet<(String, String)> = node_info.iter()
+        .filter(|node| !partitions.is_visible(&node.partition))
+        .map(|node| (node.cluster.clone(), node.partition.clone()))
+        .collect();
+    node_info.retain(|node| partitions.is_visible(&node.partition));
+
+    hidden.len()
+}
+
+/// Public helper function to remove the jobs that only run in partitions that are hidden by the configuration
+/// Pending jobs can be submitted to several partitions, they are kept if one of them is visible
+pub fn hide_partition_jobs(job_info: &mut Vec<JobInfo>, partitions: &PartitionSelection) {
+    job_info.retain(|job| job.partition.is_empty() || job.partition.split(',').any(|partition| partitions.is_visible(partition)));
+}
+
+/// Public helper function to remove the job steps, or to move them below their job if they are shown
+pub fn select_job_steps(job_info: &mut Vec<JobInfo>, show_job_steps: bool) {
+    if show_job_steps {
+        group_job_steps(job_info);
+    } else {
+        remove_job_steps(job_info);
+    }
+}
+
+/// Public helper function for the note about failed updates in a row on the page and in the text report,
+/// None after the first failure, the error itself is shown anyway
+pub fn failures_notice(consecutive_failures: u32) -> Option<String> {
+    if consecutive_failures > 1 {
+        Some(format!("Still failing after {} attempts in a row, retrying with backoff", consecutive_failures))
+    } else {
+        None
+    }
+}
+
+/// Public helper function for the note about the team of --users on the page and in the text report, None without team
+pub fn team_notice(team: &[String]) -> Option<String> {
+    match team.len() {
+        0 => None,
+        1 => Some("Showing jobs for configured team (1 user)".to_string()),
+        users => Some(format!("Showing jobs for configured team ({} users)", users))
+    }
+}
+
+/// Public helper function for the line about the jobs of the user of the request, for example "Your jobs: 2 running, 1 pending", job steps are not counted
+pub fn own_jobs_notice(jobs: &[JobInfo], user: &str) -> String {
+    let count = |state: JobState| jobs.iter().filter(|job| job.user_name == user && job.job_state == state &&
+        !job.job_id.as_ref().map_or(false, |job_id| job_id.is_step())).count();
+    format!("Your jobs: {} running, {} pending", count(JobState::Running), count(JobState::Pending))
+}
+
+#[test]
+fn test_own_jobs_notice() {
+    let mut jobs = get_job_info_test();
+    assert_eq!(own_jobs_notice(&jobs, "user01"), "Your jobs: 1 running, 0 pending");
+    assert_eq!(own_jobs_notice(&jobs, "user02"), "Your jobs: 0 running, 1 pending");
+    jobs[1].job_state = JobState::Running;
+    assert_eq!(own_jobs_notice(&jobs, "user02"), "Your jobs: 1 running, 1 pending");
+    assert_eq!(own_jobs_notice(&jobs, "nobody"), "Your jobs: 0 running, 0 pending");
+    // Job steps are not counted
+    let mut step = jobs[0].clone();
+    step.job_id = ::squeue_util::str_to_job_id("1.batch");
+    jobs.push(step);
+    assert_eq!(own_jobs_notice(&jobs, "user01"), "Your jobs: 1 running, 0 pending");
+}
+
+/// Public helper function for the note about hidden partitions on the page and in the text report
+pub fn hidden_partitions_notice(hidden_partitions: usize) -> String {
+    if hidden_partitions == 1 {
+        "1 partition hidden by configuration".to_string()
+    } else {
+        format!("{} partitions hidden by configuration", hidden_partitions)
+    }
+}
+
+/// Public helper function for the note about the lines of sinfo and squeue that could not be parsed in the last update, None if all
+/// lines were parsed. Some rows of the tables are missing then, the lines themselves are logged
+pub fn skipped_lines_notice(skipped: usize) -> Option<String> {
+    match skipped {
+        0 => None,
+        1 => Some("1 line could not be parsed in the last update".to_string()),
+        _ => Some(format!("{} lines could not be parsed in the last update", skipped))
+    }
+}
+
+// Private helper function to get the milliseconds since start (from precise_time_ns)
+fn elapsed_ms(start: u64) -> f64 {
+    (precise_time_ns() - start) as f64 / 1000000.0
+}
+
+/// Public function that fetches the current SLURM status once and updates the SlurmStatus object
+/// On error the data of the previous update is kept and the error is added to update_errors
+/// Node state changes are recorded and queued in the webhook notifier, if there is one
+pub fn update_slurm_status(status: &mut SlurmStatus, config: &Configuration, runner: &dyn CommandRunner, notifier: Option<&mut WebhookNotifier>) {
+    update_slurm_status_parts(status, config, runner, notifier, DueUpdates::all());
+}
+
+/// Output of the node commands of one update (sinfo and scontrol), fetched without the status so they can run next to the job commands
+struct NodeFetch {
+    node_info: Result<ParseResult<PartitionNodeInfo>, String>,
+    /// Duration of the sinfo call for the nodes in milliseconds
+    sinfo_ms: f64,
+    /// Output of sinfo, only with --debug-endpoints
+    raw_sinfo: Option<RawOutput>,
+    down_reasons: Result<Vec<DownReason>, String>,
+    /// None if the partition limits are not enabled or scontrol failed
+    partition_limits: Option<BTreeMap<String, PartitionLimits>>
+}
+
+impl NodeFetch {
+    /// The node commands could not be run at all, the previous data is kept
+    fn failed(err: &str) -> NodeFetch {
+        NodeFetch {
+            node_info: Err(err.to_string()),
+            sinfo_ms: 0.0,
+            raw_sinfo: None,
+            down_reasons: Err(err.to_string()),
+            partition_limits: None
+        }
+    }
+}
+
+/// Output of the job commands of one update (squeue, sacct, sdiag, sshare, sprio, scontrol and sacctmgr)
+struct JobFetch {
+    job_info: Result<ParseResult<JobInfo>, String>,
+    /// Duration of the squeue call in milliseconds
+    squeue_ms: f64,
+    /// Output of squeue, only with --debug-endpoints
+    raw_squeue: Option<RawOutput>,
+    finished_job_info: Vec<FinishedJobInfo>,
+    scheduler_stats: SchedulerStats,
+    /// None if sshare is not enabled
+    share_info: Option<Vec<ShareInfo>>,
+    /// None if sprio is not enabled
+    priority_info: Option<Vec<PriorityInfo>>,
+    /// None if scontrol failed
+    reservations: Option<Vec<ReservationInfo>>,
+    /// None if the licenses are not enabled or scontrol failed
+    licenses: Option<Vec<LicenseInfo>>,
+    /// None if the QOS are not enabled or sacctmgr failed
+    qos_info: Option<Vec<QosInfo>>
+}
+
+// Private helper function that tells if the output of sinfo and squeue is kept for /debug/raw/
+// With --anonymize it isn't, the output contains the real user and job names
+fn capture_raw_output(config: &Configuration) -> bool {
+    config.debug_endpoints && !config.anonymize
+}
+
+// Private helper function that runs the node commands, hidden partitions are removed later when the output is applied
+fn fetch_node_info(config: &Configuration, runner: &dyn CommandRunner, warnings: &mut SkippedWarnings) -> NodeFetch {
+    let sinfo = CommandConfig::new(&config.sinfo_path, &config.sinfo_args);
+    let capture = CapturingRunner::new(runner);
+    let start = precise_time_ns();
+    let node_info = get_partition_node_info(if capture_raw_output(config) { &capture } else { runner }, &sinfo, &config.clusters, warnings);
+    let sinfo_ms = elapsed_ms(start);
+
+    NodeFetch {
+        node_info: node_info,
+        sinfo_ms: sinfo_ms,
+        raw_sinfo: if capture_raw_output(config) { Some(capture.into_output(get_time().sec)) } else { None },
+        down_reasons: get_down_reasons(runner, &sinfo, &config.clusters),
+        partition_limits: if config.enable_partition_limits { get_partition_limits(runner, &CommandConfig::new("scontrol", &[])).ok() } else { None }
+    }
+}
+
+// Private helper function that runs the job commands
+fn fetch_job_info(config: &Configuration, runner: &dyn CommandRunner, warnings: &mut SkippedWarnings) -> JobFetch {
+    let squeue = CommandConfig::new(&config.squeue_path, &config.squeue_args);
+    let capture = CapturingRunner::new(runner);
+    let start = precise_time_ns();
+    let job_info = get_job_info(if capture_raw_output(config) { &capture } else { runner }, &squeue, &config.clusters,
+        config.squeue_states.as_ref().map(|states| &states[..]), warnings);
+    let squeue_ms = elapsed_ms(start);
+
+    JobFetch {
+        job_info: job_info,
+        squeue_ms: squeue_ms,
+        raw_squeue: if capture_raw_output(config) { Some(capture.into_output(get_time().sec)) } else { None },
+        finished_job_info: get_finished_job_info(runner, config.finished_hours),
+        scheduler_stats: get_scheduler_stats(runner),
+        share_info: if config.enable_sshare { Some(get_share_info(runner)) } else { None },
+        priority_info: if config.enable_sprio { Some(get_priority_info(runner)) } else { None },
+        reservations: get_reservation_info(runner, &CommandConfig::new("scontrol", &[])).ok(),
+        licenses: if config.enable_licenses { get_license_info(runner, &CommandConfig::new("scontrol", &[])).ok() } else { None },
+        qos_info: if config.enable_qos { get_qos_info(runner).ok() } else { None }
+    }
+}
+
+// Private helper function that runs the due node and job commands at the same time, the node commands in a second thread
+// The update takes as long as the slower of both, instead of the sum. Nothing is written to the status here,
+// so a failure of one side doesn't touch the fresh output of the other one. Only the rate limits of the warnings about skipped lines
+// (see InspectorMetrics) are changed, each side has its own
+fn fetch_slurm_status(config: &Configuration, runner: &dyn CommandRunner, due: DueUpdates, sinfo_warnings: &mut SkippedWarnings,
+    squeue_warnings: &mut SkippedWarnings) -> (Option<NodeFetch>, Option<JobFetch>) {
+    let start = precise_time_ns();
+
+    let (node_fetch, job_fetch) = thread::scope(|scope| {
+        let nodes = if due.node_info { Some(scope.spawn(|| fetch_node_info(config, runner, sinfo_warnings))) } else { None };
+        let jobs = if due.job_info { Some(fetch_job_info(config, runner, squeue_warnings)) } else { None };
+        let nodes = nodes.map(|handle| handle.join().unwrap_or_else(|_| {
+            error!("The thread of the node commands panicked");
+            NodeFetch::failed("The thread of the node commands panicked")
+        }));
+        (nodes, jobs)
+    });
+
+    debug!("SLURM commands took {:.1} ms (sinfo: {} ms, squeue: {} ms)", elapsed_ms(start),
+        node_fetch.as_ref().map_or("-".to_string(), |fetch| format!("{:.1}", fetch.sinfo_ms)),
+        job_fetch.as_ref().map_or("-".to_string(), |fetch| format!("{:.1}", fetch.squeue_ms)));
+
+    (node_fetch, job_fetch)
+}
+
+// Private helper function that updates the partition and node information and the partition limits
+// In test mode the test data is used, otherwise the fetched output of the node commands
+fn update_node_info(status: &mut SlurmStatus, config: &Configuration, fetch: Option<NodeFetch>) {
+    status.node_update.errors.clear();
+    if config.test_mode {
+        status.node_info = match config.test_data {
+            Some(ref data) => get_partition_node_info_synthetic(data),
+            None => get_partition_node_info_test()
+        };
+        status.hidden_partitions = hide_partitions(&mut status.node_info, &config.partitions);
+        // The synthetic nodes have no reasons
+        status.down_reasons = if config.test_data.is_some() { Vec::new() } else { get_down_reasons_test() };
+        apply_simulated_nodes(&mut status.node_info, &mut status.down_reasons, &status.simulated_nodes);
+        if config.enable_partition_limits {
+            status.partition_limits = get_partition_limits_test();
+        }
+    } else if let Some(fetch) = fetch {
+        // Hidden partitions are removed here, so the page, the text report and the JSON API all show the same
+        match fetch.node_info {
+            Ok(mut node_info) => {
+                status.metrics.sinfo.record_success(fetch.sinfo_ms, &node_info);
+                record_skipped_lines("sinfo", node_info.skipped);
+                status.hidden_partitions = hide_partitions(&mut node_info.items, &config.partitions);
+                status.node_info = node_info.items;
+            },
+            Err(err) => {
+                status.metrics.sinfo.record_failure(fetch.sinfo_ms);
+                record_error(ErrorCategory::Update, &err);
+                status.node_update.errors.push(err)
+            }
+        }
+        if fetch.raw_sinfo.is_some() {
+            status.raw_sinfo = fetch.raw_sinfo;
+        }
+        // The reasons are only additional information, so an error is logged but the previous ones are kept
+        match fetch.down_reasons {
+            Ok(down_reasons) => status.down_reasons = down_reasons,
+            Err(err) => warn!("Could not get the reasons of the down nodes: {}", err)
+        }
+        if let Some(partition_limits) = fetch.partition_limits {
+            status.partition_limits = partition_limits;
+        }
+    }
+    merge_down_reasons(&mut status.node_info, &status.down_reasons);
+    status.node_update.time = config.display_time.format(get_time().sec);
+}
+
+// Private helper function that updates the job information, the finished jobs, the scheduler statistics, the fair-share information,
+// the priorities, the reservations, the licenses and the QOS, everything with user names is fetched together so the pseudonyms of --anonymize stay consistent
+fn update_job_info(status: &mut SlurmStatus, config: &Configuration, fetch: Option<JobFetch>) {
+    status.job_update.errors.clear();
+    if config.test_mode {
+        status.job_info = match config.test_data {
+            Some(ref data) => get_job_info_synthetic(data),
+            None => get_job_info_test()
+        };
+        hide_partition_jobs(&mut status.job_info, &config.partitions);
+        select_job_steps(&mut status.job_info, config.show_job_steps);
+        // The test jobs have been submitted on 2000-01-01, so the wait times stay the same from run to run
+        set_wait_times(&mut status.job_info, TEST_NOW);
+        status.finished_job_info = get_finished_job_info_test();
+        status.scheduler_stats = get_scheduler_stats_test();
+        if config.enable_sshare {
+            status.share_info = get_share_info_test();
+        }
+        if config.enable_sprio {
+            status.priority_info = get_priority_info_test();
+        }
+        status.reservations = get_reservation_info_test();
+        if config.enable_licenses {
+            status.licenses = get_license_info_test();
+        }
+        if config.enable_qos {
+            status.qos_info = get_qos_info_test();
+        }
+        // Outside of the test mode sreport runs in its own thread, see update_usage_info
+        if config.enable_sreport {
+            apply_usage_info(status, config, Ok(get_usage_info_test()));
+        }
+    } else if let Some(fetch) = fetch {
+        match fetch.job_info {
+            Ok(mut job_info) => {
+                status.metrics.squeue.record_success(fetch.squeue_ms, &job_info);
+                record_skipped_lines("squeue", job_info.skipped);
+                hide_partition_jobs(&mut job_info.items, &config.partitions);
+                select_job_steps(&mut job_info.items, config.show_job_steps);
+                set_wait_times(&mut job_info.items, &strftime(TIME_FORMAT, &now()).unwrap());
+                status.job_info = job_info.items;
+            },
+            Err(err) => {
+                status.metrics.squeue.record_failure(fetch.squeue_ms);
+                record_error(ErrorCategory::Update, &err);
+                status.job_update.errors.push(err)
+            }
+        }
+        if fetch.raw_squeue.is_some() {
+            status.raw_squeue = fetch.raw_squeue;
+        }
+        status.finished_job_info = fetch.finished_job_info;
+        status.scheduler_stats = fetch.scheduler_stats;
+        if let Some(share_info) = fetch.share_info {
+            status.share_info = share_info;
+        }
+        if let Some(priority_info) = fetch.priority_info {
+            status.priority_info = priority_info;
+        }
+        // The reservations are only informational, so an error is logged but the previous ones are kept
+        if let Some(reservations) = fetch.reservations {
+            status.reservations = reservations;
+        }
+        // The same for the licenses
+        if let Some(licenses) = fetch.licenses {
+            status.licenses = licenses;
+        }
+        if let Some(qos_info) = fetch.qos_info {
+            status.qos_info = qos_info;
+        }
+    }
+    status.job_update.time = config.display_time.format(get_time().sec);
+}
+
+/// The output of the due SLURM commands of one update and the rate limits of the warnings about skipped lines after it
+pub struct SlurmFetch {
+    node_fetch: Option<NodeFetch>,
+    job_fetch: Option<JobFetch>,
+    sinfo_warnings: SkippedWarnings,
+    squeue_warnings: SkippedWarnings
+}
+
+/// Public function that runs the due SLURM commands without changing the status, so no lock has to be held while they run
+/// The rate limits of the warnings start from the ones of the given status, apply_slurm_status_parts stores them again
+pub fn fetch_slurm_status_parts(status: &SlurmStatus, config: &Configuration, runner: &dyn CommandRunner, due: DueUpdates) -> SlurmFetch {
+    let mut sinfo_warnings = status.metrics.sinfo.skipped_warnings.clone();
+    let mut squeue_warnings = status.metrics.squeue.skipped_warnings.clone();
+    let (node_fetch, job_fetch) = if config.test_mode {
+        (None, None)
+    } else {
+        fetch_slurm_status(config, runner, due, &mut sinfo_warnings, &mut squeue_warnings)
+    };
+    SlurmFetch{ node_fetch: node_fetch, job_fetch: job_fetch, sinfo_warnings: sinfo_warnings, squeue_warnings: squeue_warnings }
+}
+
+/// Public function that fetches only the due parts of the SLURM status and updates the SlurmStatus object
+/// The errors of the parts that are not due are kept until their next update
+pub fn update_slurm_status_parts(status: &mut SlurmStatus, config: &Configuration, runner: &dyn CommandRunner, notifier: Option<&mut WebhookNotifier>,
+    due: DueUpdates) {
+    let fetch = fetch_slurm_status_parts(status, config, runner, due);
+    apply_slurm_status_parts(status, config, fetch, notifier, due);
+}
+
+/// Public function that updates the due parts of the SlurmStatus object with the output of fetch_slurm_status_parts
+/// The errors of the parts that are not due are kept until their next update
+pub fn apply_slurm_status_parts(status: &mut SlurmStatus, config: &Configuration, fetch: SlurmFetch, notifier: Option<&mut WebhookNotifier>,
+    due: DueUpdates) {
+    debug!("Update slurm status: {:?}", due);
+    let previous_node_info = status.node_info.clone();
+    let previous_job_info = if due.job_info { status.job_info.clone() } else { Vec::new() };
+    status.cluster_name = config.cluster_name.clone();
+    status.slurm_version = config.slurm_version.clone();
+    // Read on every update, so operators can add and remove the banner without a restart
+    status.banner = config.banner_file.as_ref().and_then(|path| read_banner(path));
+    status.metrics.sinfo.skipped_warnings = fetch.sinfo_warnings;
+    status.metrics.squeue.skipped_warnings = fetch.squeue_warnings;
+    if due.node_info {
+        update_node_info(status, config, fetch.node_fetch);
+    }
+    if due.job_info {
+        update_job_info(status, config, fetch.job_fetch);
+    }
+    if due.job_info && config.anonymize {
+        anonymize_status(status);
+    } else if due.node_info && config.anonymize {
+        anonymize_down_reasons(&mut status.down_reasons);
+    }
+    // After a failed squeue call the previous jobs are still shown, so there is nothing to compare
+    if due.job_info && status.job_update.errors.is_empty() {
+        status.recently_finished.update(&previous_job_info, &status.job_info, get_time().sec, config.recently_finished_minutes);
+    } else {
+        status.recently_finished.expire(get_time().sec, config.recently_finished_minutes);
+    }
+    status.last_update = config.display_time.format(get_time().sec);
+    status.update_errors = status.node_update.errors.iter().chain(status.job_update.errors.iter()).cloned().collect();
+    if status.update_errors.is_empty() {
+        status.last_success = Some(get_time().sec);
+        status.consecutive_failures = 0;
+    } else {
+        status.consecutive_failures += 1;
+    }
+    let mut entry = HistoryEntry::new(get_time().sec, &status.node_info, &status.job_info);
+    if !status.job_update.errors.is_empty() {
+        entry.pending_by_partition = None;
+    }
+    status.history.push(entry);
+    // Nothing to compare on the first update, and an empty list usually means that sinfo failed
+    if due.node_info && !previous_node_info.is_empty() && !status.node_info.is_empty() {
+        let events = diff_node_info(&previous_node_info, &status.node_info, &status.last_update);
+        if let Some(notifier) = notifier {
+            let partition_events = diff_partition_availability(&previous_node_info, &status.node_info, &status.last_update);
+            notifier.queue_node_events(&events, get_time().sec);
+            notifier.queue_partition_events(&partition_events, get_time().sec);
+        }
+        record_node_events(&mut status.node_events, events);
+    }
+}
+
+/// Public function to update the shared status without holding the lock while the SLURM commands run
+/// The status is updated on a copy, then the page, JSON and plain text are rendered (and compressed) once for all requests
+/// and the copy is swapped in as new snapshot, so requests always see one complete snapshot, see SharedStatus
+pub fn update_shared_status(shared_slurm_status: &SharedStatus, page_options: &PageOptions, update: &mut dyn FnMut(&mut SlurmStatus)) {
+    let result = shared_slurm_status.update(&mut |status| {
+        let previous = StatusSnapshot::new(status, &StatusFilter::for_team(&page_options.filter.team));
+        update(status);
+        status.rendered = RenderedStatus::render(status, page_options);
+        // If nothing has changed, the snapshot before the last change is kept
+        if status.rendered.etag != previous.token {
+            status.previous_snapshot = Some(previous);
+        }
+    });
+
+    if let Err(err) = result {
+        error!("Could not lock Mutex: {}", err);
+    }
+}
+
+// Private helper function that records the lines of a SLURM command that could not be parsed in the error log
+fn record_skipped_lines(command: &str, skipped: usize) {
+    if skipped > 0 {
+        record_error(ErrorCategory::SkippedLines, &format!("{}: {} lines could not be parsed", command, skipped));
+    }
+}
+
+/// Public helper function that stores the result of sreport in the status, with --anonymize only the totals of the accounts are kept
+/// After an error the previous usage is kept, the error is logged and shown with the usage table, but the status is not marked as failed
+pub fn apply_usage_info(status: &mut SlurmStatus, config: &Configuration, usage_info: Result<Vec<UsageRecord>, String>) {
+    match usage_info {
+        Ok(usage_info) => {
+            status.usage_info = usage_info.into_iter().filter(|record| !config.anonymize || record.user.is_none()).collect();
+            status.usage_update.errors.clear();
+        },
+        Err(err) => {
+            warn!("Could not get the usage of this month: {}", err);
+            record_error(ErrorCategory::Update, &err);
+            status.usage_update.errors = vec![err];
+        }
+    }
+    status.usage_update.time = config.display_time.format(get_time().sec);
+}
+
+/// Public function that runs sreport for the current month and swaps the result into the shared status
+/// sreport runs without holding any lock, so a slow accounting database doesn't delay the updates of the nodes and jobs
+pub fn update_usage_info(shared_slurm_status: &SharedStatus, page_options: &PageOptions, config: &Configuration, runner: &dyn CommandRunner) {
+    let mut usage_info = Some(get_usage_info(runner, &month_start(&now())));
+    update_shared_status(shared_slurm_status, page_options, &mut |status| {
+        if let Some(usage_info) = usage_info.take() {
+            apply_usage_info(status, config, usage_info);
+        }
+    });
+}
+
+// Private helper function that checks the triggers of the email notifications after an update, the messages go to the mail thread
+// Like for the webhook, the nodes are only compared if both updates have nodes
+fn check_email_triggers(email: &mut EmailNotifier, previous_node_info: &[PartitionNodeInfo], status: &SlurmStatus, now: i64) {
+    if !previous_node_info.is_empty() && !status.node_info.is_empty() {
+        let node_events = diff_node_info(previous_node_info, &status.node_info, &status.last_update);
+        let partition_events = diff_partition_availability(previous_node_info, &status.node_info, &status.last_update);
+        email.check_events(&node_events, &partition_events, now);
+    }
+    email.check_errors(&status.update_errors, now);
+}
+
+/// Public function that runs one step of the SLURM status thread: sleeps until an update is due or a refresh is requested,
+/// then updates the shared status, finishes the refresh (if there was one) and sends the new status to the /events streams
+/// The SLURM commands run before the update, like sreport in update_usage_info, so the status is only locked to apply their output
+pub fn next_status_update(shared_slurm_status: &SharedStatus, page_options: &PageOptions, config: &Configuration, runner: &dyn CommandRunner,
+    notifier: &mut Option<WebhookNotifier>, email: &mut Option<EmailNotifier>, schedule: &mut UpdateSchedule, refresh: &RefreshControl,
+    subscribers: &Subscribers) {
+    let due = schedule.wait(refresh);
+    let mut fetch = match shared_slurm_status.snapshot() {
+        Ok(status) => Some(fetch_slurm_status_parts(&status, config, runner, due)),
+        Err(err) => {
+            error!("Could not lock Mutex: {}", err);
+            None
+        }
+    };
+    // Without an update all due parts are retried sooner
+    let mut failed = due;
+    update_shared_status(shared_slurm_status, page_options, &mut |status| {
+        let fetch = match fetch.take() {
+            Some(fetch) => fetch,
+            None => return
+        };
+        let previous_node_info = if email.is_some() && due.node_info { status.node_info.clone() } else { Vec::new() };
+        apply_slurm_status_parts(status, config, fetch, notifier.as_mut(), due);
+        if let Some(ref mut email) = *email {
+            check_email_triggers(email, &previous_node_info, status, get_time().sec);
+        }
+        failed = DueUpdates{ node_info: !status.node_update.errors.is_empty(), job_info: !status.job_update.errors.is_empty() };
+        // After a failed update the file is left as it is, slurm_inspector_last_write shows how old it is
+        if let Some(ref path) = config.prom_textfile {
+            if status.update_errors.is_empty() {
+                write_prom_textfile(path, status, get_time().sec);
+            }
+        }
+    });
+    // A failed part is retried sooner, with exponential backoff
+    schedule.record_result(due, failed);
+    refresh.finish();
+
+    if !subscribers.is_empty() {
+        let event = match shared_slurm_status.snapshot() {
+            Ok(status) => StatusEvent::new(&status, &StatusFilter::for_team(&config.users)),
+            Err(err) => {
+                error!("Could not lock Mutex: {}", err);
+                return;
+            }
+        };
+        subscribers.broadcast(event);
+    }
+}
+
+/// Public function that starts the SLURM status thread and update the SlurmStatus object accordingly
+/// The node and the job information are fetched with their own interval (--sinfo-interval and --squeue-interval),
+/// a POST /refresh wakes the thread up through the refresh control
+pub fn check_slurm_status(local_slurm_status: &Arc<SharedStatus>, refresh: &Arc<RefreshControl>, subscribers: &Arc<Subscribers>,
+    config: Configuration) {
     let shared_slurm_status = local_slurm_status.clone();
+    let refresh = refresh.clone();
+    let subscribers = subscribers.clone();
+    let page_options = PageOptions::new(&config);
+    let runner = create_runner(&config);
+    let mut notifier = config.webhook_url.as_ref().map(|url| WebhookNotifier::new(url, &config.cluster_name, config.webhook_cooldown as i64));
+    // The messages are sent by their own thread, so a slow mail server doesn't delay the updates
+    let mut email = config.email.as_ref()
+        .map(|email| EmailNotifier::new(email, &config.cluster_name, start_mail_thread(Box::new(SmtpMailer { config: email.clone() }))));
+
+    let mut schedule = UpdateSchedule::new(config.sinfo_interval, config.squeue_interval);
+    let mut history_db = config.history_db.as_ref().and_then(|path| match HistoryDb::open(path) {
+        Ok(history_db) => Some(history_db),
+        Err(err) => {
+            error!("Could not open the history database '{}': {}, the history is not saved", path, err);
+            None
+        }
+    });
+    let retention = config.history_retention as i64 * 24 * 3600;
+
+    // sreport has its own thread and interval, in test mode the usage is set with the test data of the jobs
+    if config.enable_sreport && !config.test_mode {
+        let (shared_slurm_status, page_options, config) = (shared_slurm_status.clone(), page_options.clone(), config.clone());
+        thread::spawn(move || {
+            let runner = create_runner(&config);
+            loop {
+                update_usage_info(&shared_slurm_status, &page_options, &config, &*runner);
+                thread::sleep(Duration::from_secs(config.sreport_interval));
+            }
+        });
+    }
+
+    thread::spawn(move || {
+        // Endless loop, just keep checking the status of SLURM, sinfo and squeue run when they are due
+        loop {
+            next_status_update(&shared_slurm_status, &page_options, &config, &*runner, &mut notifier, &mut email, &mut schedule, &refresh, &subscribers);
+
+            // Send outside of the lock, so a slow receiver doesn't block the web page
+            if let Some(ref mut notifier) = notifier {
+                notifier.send_pending(&HttpSender);
+            }
+            // Written outside of the lock as well, only the newest entry is copied
+            if let Some(ref mut history_db) = history_db {
+                let newest = shared_slurm_status.snapshot().ok().and_then(|status| status.history.entries().back().cloned());
+                if let Some(entry) = newest {
+                    history_db.store(&entry, get_time().sec - retention);
+                }
+            }
+        }
+    });
+}
+
+/// Estimated size of the page without the table rows in bytes
+const PAGE_BASE_BYTES: usize = 32 * 1024;
+
+/// Estimated size of one row of the job or node tables in bytes, a bit more than the built-in templates need
+const PAGE_ROW_BYTES: usize = 512;
+
+/// Public helper function that estimates the size of the HTML page, to render it into a buffer that doesn't have to grow
+pub fn estimated_page_size(status: &SlurmStatus) -> usize {
+    let rows = status.job_info.len() + status.node_info.len() + status.finished_job_info.len() + status.share_info.len() + status.reservations.len();
+    PAGE_BASE_BYTES + rows * PAGE_ROW_BYTES
+}
+
+/// Public helper function accepts SlurmStatus and returns a string containing the HTML representation of the status
+/// The page is rendered with the templates of the page options, a template that can't be rendered returns an error
+pub fn status_to_html(status: &SlurmStatus, options: &PageOptions) -> Result<String, String> {
+    let page = status_page(status, options, &reservation_time(0), &reservation_time(UPCOMING_RESERVATION_HOURS * 3600));
+    options.templates.render_with_capacity(STATUS_TEMPLATE, &page.to_json(), estimated_page_size(status))
+}
+
+/// Public helper function to render a page that only shows the part of the status that passes the filter, like the user and partition pages
+/// The template gets the view-model of the status page (with all jobs on one page) and the summary of the page as "summary"
+pub fn render_filtered_status(status: &SlurmStatus, options: &PageOptions, filter: StatusFilter, template: &str, summary: Json) -> Result<String, String> {
+    let options = PageOptions { filter: filter, pagination: Pagination::unlimited(), .. options.clone() };
+    let page = status_page(status, &options, &reservation_time(0), &reservation_time(UPCOMING_RESERVATION_HOURS * 3600));
+    let mut context = page.to_json();
+    if let Json::Object(ref mut fields) = context {
+        fields.insert("summary".to_string(), summary);
+    }
+
+    options.templates.render_with_capacity(template, &context, estimated_page_size(status))
+}
+
+/// Maximum width of the job name column in the plain text report, longer names are truncated
+const TEXT_MAX_JOB_NAME: usize = 20;
+
+// Private helper function to shorten a text to the given number of characters, the last one is replaced with "…"
+fn truncate_text(text: &str, max_len: usize) -> String {
+    if text.chars().count() <= max_len {
+        text.to_string()
+    } else {
+        let mut result: String = text.chars().take(max_len.saturating_sub(1)).collect();
+        result.push('…');
+        result
+    }
+}
+
+// Private helper function to render a table with aligned columns, the width of each column adapts to its content
+fn text_table(header: &[&str], rows: &[Vec<String>]) -> String {
+    let mut widths: Vec<usize> = header.iter().map(|name| name.chars().count()).collect();
+    for row in rows {
+        for (width, cell) in widths.iter_mut().zip(row.iter()) {
+            *width = ::std::cmp::max(*width, cell.chars().count());
+        }
+    }
+
+    let header_row: Vec<String> = header.iter().map(|name| name.to_string()).collect();
+    let mut result = String::new();
+
+    for row in Some(&header_row).into_iter().chain(rows.iter()) {
+        let cells: Vec<String> = row.iter().zip(widths.iter())
+            .map(|(cell, width)| format!("{}{}", cell, " ".repeat(width - cell.chars().count())))
+            .collect();
+        result.push_str(cells.join("  ").trim_end());
+        result.push('\n');
+    }
+
+    result
+}
+
+// Private helper function to format the number of nodes per state, for example " (Allocated 2, Idle 3)"
+fn state_counts_to_text(counts: &BTreeMap<String, u32>) -> String {
+    let counts: Vec<String> = counts.iter().map(|(state, count)| format!("{} {}", state, count)).collect();
+
+    if counts.is_empty() {
+        String::new()
+    } else {
+        format!(" ({})", counts.join(", "))
+    }
+}
+
+/// Public helper function accepts SlurmStatus and returns a compact plain text report, for example for curl
+/// Contains one summary line per partition, the number of nodes per state and the running and pending jobs
+pub fn status_to_text(status: &SlurmStatus, filter: &StatusFilter) -> String {
+    let show_cluster = status.node_info.iter().any(|node| !node.cluster.is_empty()) || status.job_info.iter().any(|job| !job.cluster.is_empty());
+    let node_info: Vec<&PartitionNodeInfo> = status.node_info.iter().filter(|node| filter.node_matches(node)).collect();
+    let mut result = String::new();
+
+    result.push_str(&format!("Slurm Inspector, last update: {}\n", if status.last_update.is_empty() { "-" } else { &status.last_update }));
+    if let Some(ref banner) = status.banner {
+        result.push_str(&format!("{}\n", banner));
+    }
+    for error in &status.update_errors {
+        result.push_str(&format!("Error: {}\n", error));
+    }
+    if let Some(notice) = failures_notice(status.consecutive_failures) {
+        result.push_str(&format!("{}\n", notice));
+    }
+    if status.hidden_partitions > 0 {
+        result.push_str(&format!("{}\n", hidden_partitions_notice(status.hidden_partitions)));
+    }
+    if let Some(notice) = team_notice(&filter.team) {
+        result.push_str(&format!("{}\n", notice));
+    }
+    result.push_str("\n");
+
+    result.push_str("Partitions:\n");
+    for summary in partition_summaries(status, filter) {
+        let name = if show_cluster { format!("{}/{}", summary.cluster, summary.name) } else { summary.name.clone() };
+        let gpus = if summary.gpus > 0 { format!(", {} GPUs", summary.gpus) } else { String::new() };
+        let max_time = summary.limits.as_ref().and_then(|limits| limits.max_time.as_ref()).map_or(String::new(), |max_time| format!(", max. time {}", max_time));
+        let wait_times = summary.wait_times.as_ref().map_or(String::new(), |wait_times| format!(", {} pending, wait {} (min / median / 95% / max)", wait_times.jobs, wait_times.to_text()));
+
+        result.push_str(&format!("  {}: {}, {} nodes{}{}{}{}\n", name, summary.availability, summary.nodes, state_counts_to_text(&summary.node_states), gpus, max_time, wait_times));
+    }
+
+    // A node can be in several partitions, so each node is only counted once
+    let nodes: BTreeMap<(&str, &str), String> = node_info.iter().map(|node| ((&node.cluster[..], &node.node[..]), node.node_state.to_string())).collect();
+    let mut counts = BTreeMap::new();
+    for state in nodes.values() {
+        *counts.entry(state.clone()).or_insert(0) += 1;
+    }
+    result.push_str(&format!("\nNodes: {}{}\n\n", nodes.len(), state_counts_to_text(&counts)));
+
+    let jobs: Vec<&JobInfo> = status.job_info.iter()
+        .filter(|job| filter.job_matches(job) && (job.job_state == JobState::Running || job.job_state == JobState::Pending))
+        .collect();
+
+    if jobs.is_empty() {
+        result.push_str("No running or pending jobs\n");
+    } else {
+        let mut header = vec!["Job ID", "User", "Name", "State", "Time", "Nodes / Reason"];
+        if show_cluster {
+            header.insert(0, "Cluster");
+        }
+        let rows: Vec<Vec<String>> = jobs.iter().map(|job| {
+            let mut row = vec![
+                match job.job_id {
+                    Some(ref job_id) if job_id.is_step() => format!("  {}", job_id),
+                    Some(ref job_id) => job_id.to_string(),
+                    None => "-".to_string()
+                },
+                job.user_name.clone(),
+                truncate_text(&job.job_name, TEXT_MAX_JOB_NAME),
+                job.job_state.to_string(),
+                job.run_time.clone().unwrap_or("-".to_string()),
+                if job.job_state == JobState::Running { compress_hostlist(&job.list_of_nodes) } else { job.state_reason.to_string() }
+            ];
+            if show_cluster {
+                row.insert(0, job.cluster.clone());
+            }
+            row
+        }).collect();
+
+        result.push_str(&text_table(&header, &rows));
+    }
+
+    result
+}
+
+/// Public helper function that returns the time of the last updates, the errors, the headline numbers, the wait times
+/// of the pending jobs per partition and the longest running and waiting jobs as JSON.
+/// Used for /api/summary and the events of the /events stream, which should stay small
+pub fn summary_to_json(status: &SlurmStatus, filter: &StatusFilter) -> String {
+    let mut result = BTreeMap::new();
+    let jobs: Vec<JobInfo> = status.job_info.iter().filter(|job| filter.job_matches(job)).cloned().collect();
+    let show_cluster = status.node_info.iter().any(|node| !node.cluster.is_empty()) || status.job_info.iter().any(|job| !job.cluster.is_empty());
+    let wait_times: BTreeMap<String, Json> = partition_summaries(status, filter).iter().map(|summary| {
+        let name = if show_cluster { format!("{}/{}", summary.cluster, summary.name) } else { summary.name.clone() };
+        (name, summary.wait_times.to_json())
+    }).collect();
+
+    result.insert("cluster_name".to_string(), status.cluster_name.to_json());
+    result.insert("slurm_version".to_string(), status.slurm_version.to_json());
+    result.insert("last_update".to_string(), status.last_update.to_json());
+    result.insert("banner".to_string(), status.banner.to_json());
+    result.insert("node_update".to_string(), status.node_update.time.to_json());
+    result.insert("job_update".to_string(), status.job_update.time.to_json());
+    result.insert("update_errors".to_string(), status.update_errors.to_json());
+    result.insert("dashboard".to_string(), dashboard_summary(status, filter).to_json());
+    result.insert("wait_times".to_string(), Json::Object(wait_times));
+    result.insert("longest_running".to_string(), longest_running(&jobs, TOP_JOBS).to_json());
+    result.insert("longest_waiting".to_string(), longest_waiting(&jobs, TOP_JOBS).to_json());
+
+    Json::Object(result).to_string()
+}
+
+/// Public helper function accepts SlurmStatus and returns a string containing the JSON representation of the status
+pub fn status_to_json(status: &SlurmStatus, filter: &StatusFilter) -> String {
+    status_to_json_paged(status, filter, &Pagination::unlimited())
+}
+
+/// Public helper function like status_to_json, but only the jobs on the requested page are included
+/// The number of jobs that pass the filter and the page are in "job_pages"
+pub fn status_to_json_paged(status: &SlurmStatus, filter: &StatusFilter, pagination: &Pagination) -> String {
+    Json::Object(status_json_fields(status, filter, pagination)).to_string()
+}
+
+/// Public helper function like status_to_json, with the summary of a user or partition page added as "summary"
+pub fn filtered_status_to_json(status: &SlurmStatus, filter: &StatusFilter, summary: Json) -> String {
+    let mut result = status_json_fields(status, filter, &Pagination::unlimited());
+    result.insert("summary".to_string(), summary);
+    Json::Object(result).to_string()
+}
+
+// Private helper function that returns the fields of the status as JSON, only the jobs on the requested page are included
+fn status_json_fields(status: &SlurmStatus, filter: &StatusFilter, pagination: &Pagination) -> BTreeMap<String, Json> {
+    let mut result = BTreeMap::new();
+    let matching_jobs: Vec<JobInfo> = status.job_info.iter().filter(|job| filter.job_matches(job)).cloned().collect();
+    let job_pages = pagination.range(matching_jobs.len());
+    let jobs = pagination.apply(&matching_jobs).to_vec();
+    let job_info: Vec<Json> = jobs.iter().map(|job| job.to_json()).collect();
+    let finished_job_info: Vec<Json> = status.finished_job_info.iter().filter(|job| filter.finished_job_matches(job)).map(|job| job.to_json()).collect();
+    let recently_finished: Vec<Json> = status.recently_finished.jobs.iter().filter(|recent| filter.job_matches(&recent.job)).map(|recent| recent.to_json()).collect();
+    let node_info: Vec<Json> = status.node_info.iter().filter(|node| filter.node_matches(node)).map(|node| node.to_json()).collect();
+    let down_reasons: Vec<Json> = status.down_reasons.iter().filter(|reason| filter.cluster_matches(&reason.cluster)).map(|reason| reason.to_json()).collect();
+
+    result.insert("cluster_name".to_string(), status.cluster_name.to_json());
+    result.insert("slurm_version".to_string(), status.slurm_version.to_json());
+    result.insert("last_update".to_string(), status.last_update.to_json());
+    result.insert("banner".to_string(), status.banner.to_json());
+    result.insert("node_update".to_string(), status.node_update.time.to_json());
+    result.insert("job_update".to_string(), status.job_update.time.to_json());
+    result.insert("update_errors".to_string(), status.update_errors.to_json());
+    result.insert("hidden_partitions".to_string(), status.hidden_partitions.to_json());
+    result.insert("node_info".to_string(), Json::Array(node_info));
+    result.insert("down_reasons".to_string(), Json::Array(down_reasons));
+    result.insert("dashboard".to_string(), dashboard_summary(status, filter).to_json());
+    result.insert("unknown_states".to_string(), filter.unknown_states.to_json());
+    result.insert("job_info".to_string(), Json::Array(job_info));
+    result.insert("job_groups".to_string(), group_job_arrays(jobs).to_json());
+    result.insert("job_pages".to_string(), job_pages.to_json());
+    result.insert("finished_job_info".to_string(), Json::Array(finished_job_info));
+    result.insert("recently_finished".to_string(), Json::Array(recently_finished));
+    result.insert("scheduler_stats".to_string(), status.scheduler_stats.to_json());
+    result.insert("share_info".to_string(), status.share_info.to_json());
+    result.insert("reservations".to_string(), status.reservations.to_json());
+    result.insert("licenses".to_string(), status.licenses.to_json());
+    result.insert("qos_info".to_string(), status.qos_info.to_json());
+    result.insert("inspector".to_string(), status.metrics.to_json());
+
+    result
+}
+
+/// Public helper function accepts SlurmStatus and returns a string containing the history of node and job states as JSON
+/// The history only contains counts, so the filter doesn't apply
+pub fn history_to_json(status: &SlurmStatus, _filter: &StatusFilter) -> String {
+    status.history.to_json().to_string()
+}
+
+/// Public helper function accepts SlurmStatus and returns a string containing the recent node state changes as JSON
+pub fn events_to_json(status: &SlurmStatus, _filter: &StatusFilter) -> String {
+    Json::Array(status.node_events.iter().map(|event| event.to_json()).collect()).to_string()
+}
+
+/// Public helper function accepts SlurmStatus and returns a string containing the usage of each user as JSON
+pub fn users_to_json(status: &SlurmStatus, filter: &StatusFilter) -> String {
+    let cluster_jobs: Vec<JobInfo> = status.job_info.iter().filter(|job| filter.cluster_matches(&job.cluster)).cloned().collect();
+    let users: Vec<Json> = usage_by_user(&cluster_jobs).iter().filter(|usage| filter.user_matches(&usage.user_name)).map(|usage| usage.to_json()).collect();
+    Json::Array(users).to_string()
+}
+
+/// Public helper function accepts SlurmStatus and returns a string containing the summary and the limits of each partition as JSON
+pub fn partitions_to_json(status: &SlurmStatus, filter: &StatusFilter) -> String {
+    let partitions: Vec<Json> = partition_summaries(status, filter).iter().map(|summary| summary.to_json()).collect();
+    Json::Array(partitions).to_string()
+}
+
+#[test]
+fn test_partitions_to_json() {
+    let mut status = SlurmStatus::new();
+    status.node_info = get_partition_node_info_test();
+    status.partition_limits = get_partition_limits_test();
+
+    let json = Json::from_str(&partitions_to_json(&status, &StatusFilter::new())).unwrap();
+    let partitions = json.as_array().unwrap();
+    assert_eq!(partitions.len(), 1);
+    assert_eq!(partitions[0].find("name").and_then(|val| val.as_string()), Some("esd"));
+    assert_eq!(partitions[0].find_path(&["node_states", "idle"]).and_then(|val| val.as_u64()), Some(3));
+    assert_eq!(partitions[0].find_path(&["limits", "max_nodes"]).and_then(|val| val.as_string()), Some("UNLIMITED"));
+    assert_eq!(partitions[0].find_path(&["limits", "default_time"]).and_then(|val| val.as_string()), Some("01:00:00"));
+
+    status.partition_limits.clear();
+    let json = Json::from_str(&partitions_to_json(&status, &StatusFilter::new())).unwrap();
+    assert_eq!(json.as_array().unwrap()[0].find("limits"), Some(&Json::Null));
+}
+
+/// Public helper function accepts SlurmStatus and returns a string containing the fair-share information as JSON
+/// With a user filter only the lines of that user are returned, the account lines are left out
+pub fn shares_to_json(status: &SlurmStatus, filter: &StatusFilter) -> String {
+    let shares: Vec<Json> = status.share_info.iter()
+        .filter(|share| share.user.as_ref().map_or(filter.is_empty(), |user| filter.user_matches(user)))
+        .map(|share| share.to_json()).collect();
+    Json::Array(shares).to_string()
+}
+
+/// Public helper function that returns the licenses as JSON (/api/licenses), the filter doesn't apply to them
+pub fn licenses_to_json(status: &SlurmStatus, _filter: &StatusFilter) -> String {
+    status.licenses.to_json().to_string()
+}
+
+/// Public helper function that returns the CPU minutes of this month as JSON (/api/usage), sorted by CPU minutes
+/// With a user filter only the lines of that user are returned, the account totals are left out (like /api/shares)
+pub fn usage_to_json(status: &SlurmStatus, filter: &StatusFilter) -> String {
+    let usage: Vec<Json> = status.usage_info.iter()
+        .filter(|record| record.user.as_ref().map_or(filter.is_empty(), |user| filter.user_matches(user)))
+        .map(|record| record.to_json()).collect();
+    Json::Array(usage).to_string()
+}
+
+/// Public helper function that returns the QOS and their limits as JSON (/api/qos), the filter doesn't apply to them
+pub fn qos_to_json(status: &SlurmStatus, _filter: &StatusFilter) -> String {
+    status.qos_info.to_json().to_string()
+}
+
+/// Public helper function that returns the priority factors of the pending jobs that match the filter as JSON (/api/priorities)
+/// Each entry has the fields of sprio and the user name of the job, jobs missing in the output of sprio are left out
+pub fn priorities_to_json(status: &SlurmStatus, filter: &StatusFilter) -> String {
+    let priorities: Vec<Json> = join_priorities(&status.job_info, &status.priority_info).into_iter()
+        .filter(|&(job, _)| filter.job_matches(job))
+        .map(|(job, priority)| {
+            let mut entry = priority.to_json();
+            if let Json::Object(ref mut fields) = entry {
+                fields.insert("user_name".to_string(), job.user_name.to_json());
+            }
+            entry
+        }).collect();
+    Json::Array(priorities).to_string()
+}
+
+#[cfg(test)]
+fn test_page_options(page_refresh: u64, custom_css: bool) -> PageOptions {
+    PageOptions{ page_refresh: page_refresh, custom_css: custom_css, .. PageOptions::default() }
+}
+
+#[test]
+fn test_status_to_html_refresh() {
+    let page = status_to_html(&SlurmStatus::new(), &test_page_options(30, false)).unwrap();
+    assert!(page.contains("<meta http-equiv=\"refresh\" content=\"30\">"));
+    assert!(page.contains("(page refresh every 30 sec.)"));
+}
+
+#[test]
+fn test_status_to_html_no_refresh() {
+    let page = status_to_html(&SlurmStatus::new(), &test_page_options(0, false)).unwrap();
+    assert!(!page.contains("http-equiv=\"refresh\""));
+    assert!(page.contains("(page refresh disabled)"));
+}
+
+#[test]
+fn test_status_to_html_stylesheet() {
+    let page = status_to_html(&SlurmStatus::new(), &test_page_options(0, false)).unwrap();
+    assert!(page.contains("href=\"/static/style.css\""));
+    assert!(!page.contains("href=\"/static/custom.css\""));
+}
+
+#[test]
+fn test_status_to_html_custom_stylesheet() {
+    let page = status_to_html(&SlurmStatus::new(), &test_page_options(0, true)).unwrap();
+    let default_pos = page.find("href=\"/static/style.css\"").unwrap();
+    let custom_pos = page.find("href=\"/static/custom.css\"").unwrap();
+    assert!(default_pos < custom_pos);
+}
+
+#[test]
+fn test_status_to_html_finished_jobs() {
+    let mut status = SlurmStatus::new();
+    status.finished_job_info = get_finished_job_info_test();
+    let page = status_to_html(&status, &test_page_options(0, false)).unwrap();
+    assert!(page.contains("<h3>Jobs finished in the last 24h:</h3>"));
+    assert!(page.contains("<td>small_test03</td>"));
+    assert!(page.contains("<td>0:15 <span class=\"exit_hint\" title=\"Guessed from the exit code, the real cause may differ\">terminated by signal 15 (likely scancel or the time limit)</span></td>"));
+    // No hint for a job that succeeded
+    assert!(page.contains("<td>0:0</td>"));
+}
+
+#[test]
+fn test_status_to_json() {
+    let mut status = SlurmStatus::new();
+    status.job_info = get_job_info_test();
+    status.finished_job_info = get_finished_job_info_test();
+    status.last_update = "2015.12.20 - 12:00".to_string();
+
+    let json = Json::from_str(&status_to_json(&status, &StatusFilter::new())).unwrap();
+    assert_eq!(json.find("last_update").and_then(|val| val.as_string()), Some("2015.12.20 - 12:00"));
+    assert_eq!(json.find("job_info").and_then(|val| val.as_array()).map(|val| val.len()), Some(12));
+    assert_eq!(json.find("node_info").and_then(|val| val.as_array()).map(|val| val.len()), Some(0));
+
+    let finished = json.find("finished_job_info").and_then(|val| val.as_array()).unwrap();
+    assert_eq!(finished.len(), 5);
+    assert_eq!(finished[2].find("job_state").and_then(|val| val.as_string()), Some("Cancelled"));
+    assert_eq!(finished[2].find_path(&["exit_code", "signal"]).and_then(|val| val.as_u64()), Some(15));
+    assert_eq!(finished[1].find_path(&["exit_code", "hint"]).and_then(|val| val.as_string()), Some("exit code 1"));
+}
+
+#[test]
+fn test_status_to_html_scheduler_stats() {
+    let mut status = SlurmStatus::new();
+    status.scheduler_stats = get_scheduler_stats_test();
+    let page = status_to_html(&status, &test_page_options(0, false)).unwrap();
+    assert!(page.contains("<h3>Scheduler statistics:</h3>"));
+    assert!(page.contains("<td>Main</td><td>1312</td>"));
+    assert!(page.contains("<td>Backfill</td><td>10345</td>"));
+}
+
+#[test]
+fn test_status_gpus() {
+    let mut status = SlurmStatus::new();
+    status.node_info = get_partition_node_info_test();
+    status.job_info = get_job_info_test();
+
+    let page = status_to_html(&status, &PageOptions::default()).unwrap();
+    assert!(page.contains("<span class=\"dashboard_number\">4</span><br>GPUs</div>"));
+    assert!(page.contains("<span class=\"dashboard_number\">2</span><br>Allocated GPUs</div>"));
+    assert!(page.contains("<td>0/8/0/8</td><td>0%</td><td data-sort=\"13\"><meter min=\"0\" max=\"100\" high=\"90\" value=\"13\"></meter> 3.9 GB / 31.2 GB (13%)</td><td>4 (a100)</td>"));
+    // Non-GPU nodes have an empty cell
+    assert!(page.contains("<td>0/32/0/32</td><td>0%</td><td data-sort=\"6\"><meter min=\"0\" max=\"100\" high=\"90\" value=\"6\"></meter> 7.8 GB / 125.0 GB (6%)</td><td></td>"));
+    assert!(page.contains("<td>1</td><td>2 (a100)</td>"));
+
+    let json = Json::from_str(&status_to_json(&status, &StatusFilter::new())).unwrap();
+    let gres = json.find("node_info").and_then(|val| val.as_array()).unwrap()[0].find("gres").and_then(|val| val.as_array()).unwrap().clone();
+    assert_eq!(gres.len(), 1);
+    assert_eq!(gres[0].find("type").and_then(|val| val.as_string()), Some("a100"));
+    assert_eq!(gres[0].find("count").and_then(|val| val.as_u64()), Some(4));
+    assert_eq!(json.find("job_info").and_then(|val| val.as_array()).unwrap()[0].find("gres").and_then(|val| val.as_array()).map(|val| val.len()), Some(1));
+    assert_eq!(json.find_path(&["dashboard", "total_gpus"]).and_then(|val| val.as_u64()), Some(4));
+
+    // Without GPUs there are no GPU boxes
+    status.node_info = Vec::new();
+    assert!(!status_to_html(&status, &PageOptions::default()).unwrap().contains("<br>GPUs</div>"));
+}
+
+#[test]
+fn test_status_to_json_scheduler_stats() {
+    let mut status = SlurmStatus::new();
+    status.scheduler_stats = get_scheduler_stats_test();
+
+    let json = Json::from_str(&status_to_json(&status, &StatusFilter::new())).unwrap();
+    assert_eq!(json.find_path(&["scheduler_stats", "jobs_submitted"]).and_then(|val| val.as_u64()), Some(12));
+    assert_eq!(json.find_path(&["scheduler_stats", "rpc_count"]).and_then(|val| val.as_u64()), Some(287));
+}
+
+#[test]
+fn test_status_to_html_shares() {
+    let mut status = SlurmStatus::new();
+    status.share_info = get_share_info_test();
+
+    let page = status_to_html(&status, &test_page_options(0, false)).unwrap();
+    assert!(!page.contains("Fair-share information"));
+
+    let page = status_to_html(&status, &PageOptions{ show_shares: true, .. test_page_options(0, false) }).unwrap();
+    assert!(page.contains("<summary><b>Fair-share information</b></summary>"));
+    assert!(page.contains("<td>esd</td><td>user01</td>"));
+}
+
+#[test]
+fn test_shares_to_json() {
+    let mut status = SlurmStatus::new();
+    status.share_info = get_share_info_test();
+
+    let json = Json::from_str(&shares_to_json(&status, &StatusFilter::new())).unwrap();
+    let shares = json.as_array().unwrap();
+    assert_eq!(shares.len(), 6);
+    assert_eq!(shares[0].find("user"), Some(&Json::Null));
+    assert_eq!(shares[3].find("user").and_then(|val| val.as_string()), Some("user01"));
+}
+
+#[test]
+fn test_licenses_to_json() {
+    let mut status = SlurmStatus::new();
+    assert_eq!(licenses_to_json(&status, &StatusFilter::new()), "[]");
+
+    status.licenses = get_license_info_test();
+    let json = Json::from_str(&licenses_to_json(&status, &StatusFilter::new())).unwrap();
+    let licenses = json.as_array().unwrap();
+    assert_eq!(licenses.len(), 3);
+    assert_eq!(licenses[1].find("name").and_then(|val| val.as_string()), Some("ansys"));
+    assert_eq!(licenses[1].find("free").and_then(|val| val.as_u64()), Some(0));
+}
+
+#[test]
+fn test_usage_to_json() {
+    let mut status = SlurmStatus::new();
+    assert_eq!(usage_to_json(&status, &StatusFilter::new()), "[]");
+
+    status.usage_info = get_usage_info_test();
+    let json = Json::from_str(&usage_to_json(&status, &StatusFilter::new())).unwrap();
+    let usage = json.as_array().unwrap();
+    assert_eq!(usage.len(), 6);
+    assert_eq!(usage[0].find("account").and_then(|val| val.as_string()), Some("esd"));
+    assert_eq!(usage[0].find("user"), Some(&Json::Null));
+    assert_eq!(usage[0].find("cpu_minutes").and_then(|val| val.as_u64()), Some(8192));
+
+    // A user filter leaves out the account totals
+    let filter = StatusFilter{ user: Some("user02".to_string()), .. StatusFilter::new() };
+    let json = Json::from_str(&usage_to_json(&status, &filter)).unwrap();
+    assert_eq!(json.as_array().map(|usage| usage.len()), Some(1));
+    assert_eq!(json[0].find("cpu_minutes").and_then(|val| val.as_u64()), Some(3072));
+}
+
+#[test]
+fn test_apply_usage_info() {
+    let config = Configuration::default();
+    let mut status = SlurmStatus::new();
+
+    apply_usage_info(&mut status, &config, Ok(get_usage_info_test()));
+    assert_eq!(status.usage_info.len(), 6);
+    assert!(status.usage_update.errors.is_empty());
+    assert!(!status.usage_update.time.is_empty());
+
+    // A failed sreport call keeps the previous usage and is not an update error
+    apply_usage_info(&mut status, &config, Err("sreport: timed out".to_string()));
+    assert_eq!(status.usage_info.len(), 6);
+    assert_eq!(status.usage_update.errors, vec!["sreport: timed out".to_string()]);
+    assert!(status.update_errors.is_empty());
+
+    apply_usage_info(&mut status, &config, Ok(Vec::new()));
+    assert!(status.usage_info.is_empty());
+    assert!(status.usage_update.errors.is_empty());
+
+    // Anonymized: only the totals of the accounts
+    let config = Configuration{ anonymize: true, .. config };
+    apply_usage_info(&mut status, &config, Ok(get_usage_info_test()));
+    assert!(status.usage_info.iter().all(|record| record.user.is_none()));
+    assert_eq!(status.usage_info.len(), 2);
+}
+
+#[test]
+fn test_update_usage_info() {
+    let config = Configuration{ enable_sreport: true, .. Configuration::default() };
+    let shared_slurm_status = SharedStatus::new(SlurmStatus::new());
+    update_shared_status(&shared_slurm_status, &PageOptions::default(), &mut |status| status.job_info = get_job_info_test());
 
-    thread::spawn(move || {
-        // Endless loop, just keep checking the status of SLURM
-        loop {
-            match shared_slurm_status.lock() {
-                Ok(mut status) => {
-                    debug!("Update slurm status");
-                    if config.test_mode {
-                        status.node_info = get_partition_node_info_test();
-                        status.job_info = get_job_info_test();
-                    } else {
-                        status.node_info = get_partition_node_info();
-                        status.job_info = get_job_info();
-                    }
-                    status.last_update = strftime("%Y.%m.%d - %H:%M", &now()).unwrap();
+    let runner = ::command_runner::TestRunner::new(Ok("cluster1|esd|||10|0\ncluster1| esd|user01|User One|10|0\n".to_string()));
+    update_usage_info(&shared_slurm_status, &PageOptions::new(&config), &config, &runner);
+    let status = shared_slurm_status.snapshot().unwrap();
+    assert_eq!(status.usage_info.len(), 2);
+    assert_eq!(status.job_info.len(), get_job_info_test().len());
+    assert!(status.rendered.html.plain.contains("<td>esd</td><td>user01</td><td>10</td><td>0.2</td>"));
+    assert_eq!(runner.calls.lock().unwrap()[0].0, "sreport");
+
+    // sreport fails: the jobs and the previous usage stay, the status is not marked as failed
+    let runner = ::command_runner::TestRunner::new(Err("sreport: Problem talking to the database".to_string()));
+    update_usage_info(&shared_slurm_status, &PageOptions::new(&config), &config, &runner);
+    let status = shared_slurm_status.snapshot().unwrap();
+    assert_eq!(status.usage_info.len(), 2);
+    assert_eq!(status.job_info.len(), get_job_info_test().len());
+    assert!(status.update_errors.is_empty());
+    assert_eq!(status.usage_update.errors.len(), 1);
+    assert!(status.rendered.html.plain.contains("Problem talking to the database"));
+}
+
+#[test]
+fn test_qos_to_json() {
+    let mut status = SlurmStatus::new();
+    assert_eq!(qos_to_json(&status, &StatusFilter::new()), "[]");
+
+    status.qos_info = get_qos_info_test();
+    let json = Json::from_str(&qos_to_json(&status, &StatusFilter::new())).unwrap();
+    let qos = json.as_array().unwrap();
+    assert_eq!(qos.len(), 3);
+    assert_eq!(qos[1].find("name").and_then(|val| val.as_string()), Some("long"));
+    assert_eq!(qos[1].find_path(&["max_tres_per_user", "cpu"]).and_then(|val| val.as_string()), Some("16"));
+}
+
+#[test]
+fn test_fetch_job_info_qos() {
+    use command_runner::TestRunner;
+
+    let sacctmgr_calls = |runner: &TestRunner| runner.calls.lock().unwrap().iter().filter(|call| call.0 == "sacctmgr").count();
+
+    // Sites without sacctmgr: it's never called without --enable-qos
+    let runner = TestRunner::new(Err("No such file or directory".to_string()));
+    assert_eq!(fetch_job_info(&Configuration::default(), &runner, &mut SkippedWarnings::new()).qos_info, None);
+    assert_eq!(sacctmgr_calls(&runner), 0);
+
+    // An error keeps the QOS of the last update
+    let config = Configuration { enable_qos: true, .. Configuration::default() };
+    assert_eq!(fetch_job_info(&config, &runner, &mut SkippedWarnings::new()).qos_info, None);
+    assert_eq!(sacctmgr_calls(&runner), 1);
+
+    let runner = TestRunner::new(Ok("normal|50|2-00:00:00|cpu=64|50|100\n".to_string()));
+    let qos_info = fetch_job_info(&config, &runner, &mut SkippedWarnings::new()).qos_info.unwrap();
+    assert_eq!(qos_info.iter().map(|qos| &qos.name[..]).collect::<Vec<&str>>(), vec!["normal"]);
+    let calls = runner.calls.lock().unwrap();
+    let call = calls.iter().find(|call| call.0 == "sacctmgr").unwrap();
+    assert_eq!(call.1, vec!["-n", "-P", "show", "qos", "format=Name,Priority,MaxWall,MaxTRESPU,MaxJobsPU,MaxSubmitPU"]);
+}
+
+#[test]
+fn test_priorities_to_json() {
+    let mut status = SlurmStatus::new();
+    status.job_info = get_job_info_test();
+    status.priority_info = get_priority_info_test();
+
+    // Job 13 is only in sprio, the other jobs are not pending
+    let json = Json::from_str(&priorities_to_json(&status, &StatusFilter::new())).unwrap();
+    let priorities = json.as_array().unwrap();
+    assert_eq!(priorities.len(), 1);
+    assert_eq!(priorities[0].find("job_id").and_then(|val| val.as_u64()), Some(8));
+    assert_eq!(priorities[0].find("fair_share").and_then(|val| val.as_u64()), Some(500));
+    assert_eq!(priorities[0].find("user_name").and_then(|val| val.as_string()), Some("user02"));
+
+    let filter = StatusFilter{ user: Some("user01".to_string()), .. StatusFilter::new() };
+    assert_eq!(priorities_to_json(&status, &filter), "[]");
+}
+
+#[test]
+fn test_status_to_html_usage_by_user() {
+    let mut status = SlurmStatus::new();
+    status.job_info = get_job_info_test();
+    let page = status_to_html(&status, &test_page_options(0, false)).unwrap();
+    assert!(page.contains("<h3>Usage by user:</h3>"));
+    assert!(page.contains("<td><a href=\"/user/user01\">user01</a></td><td>1</td><td>0</td><td>2</td><td>1</td>"));
+}
+
+#[test]
+fn test_users_to_json() {
+    let mut status = SlurmStatus::new();
+    status.job_info = get_job_info_test();
+
+    let json = Json::from_str(&users_to_json(&status, &StatusFilter::new())).unwrap();
+    let users = json.as_array().unwrap();
+    assert_eq!(users.len(), 5);
+    assert_eq!(users[0].find("user_name").and_then(|val| val.as_string()), Some("user01"));
+    assert_eq!(users[0].find("cpus_in_use").and_then(|val| val.as_u64()), Some(2));
+}
+
+#[test]
+fn test_status_to_html_user_filter() {
+    let mut status = SlurmStatus::new();
+    status.job_info = get_job_info_test();
+    status.finished_job_info = get_finished_job_info_test();
+
+    let options = PageOptions{ filter: StatusFilter{ user: Some("user02".to_string()), .. StatusFilter::new() }, .. PageOptions::default() };
+    let page = status_to_html(&status, &options).unwrap();
+    assert!(page.contains("<td>small_test02</td>"));
+    assert!(!page.contains("<td>small_test01</td>"));
+    assert!(!page.contains("<td>user01</td>"));
+}
+
+#[test]
+fn test_status_to_json_user_filter() {
+    let mut status = SlurmStatus::new();
+    status.job_info = get_job_info_test();
+    status.finished_job_info = get_finished_job_info_test();
+
+    let json = Json::from_str(&status_to_json(&status, &StatusFilter{ user: Some("user02".to_string()), .. StatusFilter::new() })).unwrap();
+    assert_eq!(json.find("job_info").and_then(|val| val.as_array()).map(|val| val.len()), Some(3));
+    assert_eq!(json.find("finished_job_info").and_then(|val| val.as_array()).map(|val| val.len()), Some(1));
+}
+
+#[test]
+fn test_status_team_view() {
+    let mut status = SlurmStatus::new();
+    status.job_info = get_job_info_test();
+    status.finished_job_info = get_finished_job_info_test();
+    let config = Configuration{ users: vec!["user01".to_string(), "user02".to_string()], .. Configuration::default() };
+
+    // The default page, JSON and text only contain the jobs and the usage of the team
+    let options = PageOptions::new(&config);
+    let page = status_to_html(&status, &options).unwrap();
+    assert!(page.contains("<p class=\"filter_notice\">Showing jobs for configured team (2 users)</p>"));
+    assert!(page.contains("<td>small_test02</td>"));
+    assert!(!page.contains("<td>small_test03</td>"));
+    assert!(!page.contains("user03"));
+    let rendered = RenderedStatus::render(&status, &options);
+    let json = Json::from_str(&rendered.json.plain).unwrap();
+    assert!(json.find("job_info").and_then(|val| val.as_array()).unwrap().iter()
+        .all(|job| job.find("user_name").and_then(|val| val.as_string()).map_or(false, |user| user == "user01" || user == "user02")));
+    assert!(rendered.text.plain.contains("Showing jobs for configured team (2 users)\n"));
+    let users = Json::from_str(&users_to_json(&status, &options.filter)).unwrap();
+    assert_eq!(users.as_array().unwrap().iter().map(|usage| usage.find("user_name").and_then(|val| val.as_string()).unwrap().to_string())
+        .collect::<Vec<String>>(), vec!["user01", "user02"]);
+
+    // ?user= narrows the team down, but a user outside of the team shows nothing
+    let member = StatusFilter{ user: Some("user02".to_string()), .. StatusFilter::for_team(&config.users) };
+    assert_eq!(Json::from_str(&status_to_json(&status, &member)).unwrap().find("job_info").and_then(|val| val.as_array()).map(|val| val.len()), Some(3));
+    let outsider = StatusFilter{ user: Some("user03".to_string()), .. StatusFilter::for_team(&config.users) };
+    let json = Json::from_str(&status_to_json(&status, &outsider)).unwrap();
+    assert_eq!(json.find("job_info").and_then(|val| val.as_array()).map(|val| val.len()), Some(0));
+    assert_eq!(json.find("finished_job_info").and_then(|val| val.as_array()).map(|val| val.len()), Some(0));
+
+    // Without --users there is no notice
+    assert!(!status_to_html(&status, &PageOptions::new(&Configuration::default())).unwrap().contains("configured team"));
+    assert_eq!(team_notice(&["willi".to_string()]), Some("Showing jobs for configured team (1 user)".to_string()));
+}
+
+#[test]
+fn test_status_to_html_clusters() {
+    let mut status = SlurmStatus::new();
+    let mut other_cluster = get_job_info_test()[0].clone();
+    other_cluster.cluster = "cluster2".to_string();
+    other_cluster.job_name = "other_cluster".to_string();
+    status.job_info = vec![get_job_info_test()[1].clone(), other_cluster];
+    status.job_info[0].cluster = "cluster1".to_string();
+
+    let page = status_to_html(&status, &PageOptions::default()).unwrap();
+    assert!(page.contains("<th>Cluster</th>"));
+    assert!(page.contains("<td>cluster2</td>"));
+
+    let options = PageOptions{ filter: StatusFilter{ cluster: Some("cluster1".to_string()), .. StatusFilter::new() }, .. PageOptions::default() };
+    let page = status_to_html(&status, &options).unwrap();
+    assert!(page.contains("<td>small_test02</td>"));
+    assert!(!page.contains("<td>other_cluster</td>"));
+
+    // Without clusters there is no cluster column
+    status.job_info = get_job_info_test();
+    assert!(!status_to_html(&status, &PageOptions::default()).unwrap().contains("<th>Cluster</th>"));
+}
+
+#[test]
+fn test_status_to_html_cluster_rowspan() {
+    use sinfo_util::get_partition_node_info_test;
+
+    let mut status = SlurmStatus::new();
+    let mut node_info = get_partition_node_info_test();
+    for node in node_info.iter_mut() {
+        node.cluster = "cluster1".to_string();
+    }
+    let mut other_cluster = node_info[0].clone();
+    other_cluster.cluster = "cluster2".to_string();
+    node_info.push(other_cluster);
+    status.node_info = node_info;
+
+    // The same partition on two clusters are two groups
+    let page = status_to_html(&status, &PageOptions::default()).unwrap();
+    assert!(page.contains("<td rowspan=\"12\" class=\"partition_group\"><a href=\"/partition/esd\">esd</a></td>"));
+    assert!(page.contains("<td rowspan=\"1\" class=\"partition_group\"><a href=\"/partition/esd\">esd</a></td>"));
+
+    let json = Json::from_str(&status_to_json(&status, &StatusFilter{ cluster: Some("cluster2".to_string()), .. StatusFilter::new() })).unwrap();
+    assert_eq!(json.find("node_info").and_then(|val| val.as_array()).map(|val| val.len()), Some(1));
+    assert_eq!(json.find_path(&["node_info"]).and_then(|val| val[0].find("cluster")).and_then(|val| val.as_string()), Some("cluster2"));
+}
+
+#[test]
+fn test_status_to_html_partition_rowspan() {
+    use sinfo_util::get_partition_node_info_test;
+
+    let mut status = SlurmStatus::new();
+    status.node_info = get_partition_node_info_test();
+    let page = status_to_html(&status, &test_page_options(60, false)).unwrap();
+
+    assert_eq!(page.matches("<td rowspan=\"12\" class=\"partition_group\"><a href=\"/partition/esd\">esd</a></td>").count(), 1);
+    assert_eq!(page.matches("<td>esd</td>").count(), 0);
+}
+
+#[test]
+fn test_status_to_html_node_flags() {
+    use sinfo_util::get_partition_node_info_test;
+
+    let mut status = SlurmStatus::new();
+    status.node_info = get_partition_node_info_test();
+    let page = status_to_html(&status, &test_page_options(60, false)).unwrap();
+
+    assert!(page.contains("<td title=\"not responding\">unknown*</td>"));
+    assert!(page.contains("<td>idle</td>"));
+}
+
+#[test]
+fn test_slurm_status_snapshot_clone() {
+    use sinfo_util::get_partition_node_info_test;
+
+    let mut status = SlurmStatus::new();
+    status.node_info = get_partition_node_info_test();
+    status.job_info = get_job_info_test();
+    status.last_update = "2000.01.01 - 09:00".to_string();
+
+    let snapshot = status.clone();
+    status.job_info[0].job_state = JobState::Completed;
+    status.node_info.clear();
+
+    assert_eq!(snapshot.job_info[0].job_state, JobState::Running);
+    assert_eq!(snapshot.job_info[1..], status.job_info[1..]);
+    assert_eq!(snapshot.node_info, get_partition_node_info_test());
+    assert_eq!(snapshot.last_update, status.last_update);
+}
+
+#[test]
+fn test_status_to_html_queue_summary() {
+    let mut status = SlurmStatus::new();
+    let page = status_to_html(&status, &test_page_options(60, false)).unwrap();
+    assert!(!page.contains("Queue length over the last hour"));
+
+    status.job_info = get_job_info_test();
+    status.history.push(HistoryEntry::new(0, &status.node_info, &status.job_info));
+    status.history.push(HistoryEntry::new(60, &status.node_info, &[]));
+    let page = status_to_html(&status, &test_page_options(60, false)).unwrap();
+    assert!(page.contains("<p>Queue length over the last hour: min 0, max 1, avg 0.5 (2 updates)</p>"));
+}
+
+#[test]
+fn test_status_to_html_backlog_chart() {
+    let mut status = SlurmStatus::new();
+    status.job_info = get_job_info_test();
+    status.history.push(HistoryEntry::new(0, &status.node_info, &status.job_info));
+    let page = status_to_html(&status, &test_page_options(60, false)).unwrap();
+    assert!(!page.contains("Pending jobs per partition"));
+
+    status.history.push(HistoryEntry::new(60, &status.node_info, &[]));
+    let page = status_to_html(&status, &test_page_options(60, false)).unwrap();
+    assert!(page.contains("<h3>Pending jobs per partition:</h3>\n<svg xmlns=\"http://www.w3.org/2000/svg\" class=\"backlog_chart\""));
+    assert!(page.contains(">esd</text>"));
+}
+
+#[test]
+fn test_history_to_json() {
+    let mut status = SlurmStatus::new();
+    status.history.push(HistoryEntry::new(60, &[], &get_job_info_test()));
+
+    let json = Json::from_str(&history_to_json(&status, &StatusFilter::new())).unwrap();
+    assert_eq!(json.as_array().map(|entries| entries.len()), Some(1));
+    assert_eq!(json[0].find_path(&["job_states", "Pending"]).and_then(|val| val.as_u64()), Some(1));
+}
+
+#[test]
+fn test_status_to_html_node_events() {
+    use sinfo_util::{NodeState, ErrorCause};
+    use node_events::NodeEventKind;
+
+    let mut status = SlurmStatus::new();
+    let page = status_to_html(&status, &test_page_options(60, false)).unwrap();
+    assert!(!page.contains("Recent events"));
+
+    status.node_events.push_back(NodeEvent{ time: "2000.01.01 - 03:00".to_string(), node: "node07".to_string(),
+        kind: NodeEventKind::StateChanged{ from: NodeState::Idle, to: NodeState::Down, reason: ErrorCause::Down } });
+    status.node_events.push_back(NodeEvent{ time: "2000.01.01 - 04:00".to_string(), node: "node07".to_string(),
+        kind: NodeEventKind::StateChanged{ from: NodeState::Down, to: NodeState::Idle, reason: ErrorCause::None } });
+    let page = status_to_html(&status, &test_page_options(60, false)).unwrap();
+
+    let newest = page.find("<li>2000.01.01 - 04:00 - node07: down -&gt; idle, reason: none</li>").unwrap();
+    let oldest = page.find("<li class=\"event_alarm\">2000.01.01 - 03:00 - node07: idle -&gt; down, reason: down</li>").unwrap();
+    assert!(newest < oldest);
+
+    let json = Json::from_str(&events_to_json(&status, &StatusFilter::new())).unwrap();
+    assert_eq!(json.as_array().map(|events| events.len()), Some(2));
+    assert_eq!(json[0].find("to").and_then(|val| val.as_string()), Some("Down"));
+}
+
+#[test]
+fn test_status_to_html_update_errors() {
+    let mut status = SlurmStatus::new();
+    let page = status_to_html(&status, &test_page_options(60, false)).unwrap();
+    assert!(!page.contains("update_error"));
+
+    status.update_errors.push("Could not execute 'sinfo': ssh user@login01: Connection refused".to_string());
+    let page = status_to_html(&status, &test_page_options(60, false)).unwrap();
+    assert!(page.contains("<div class=\"update_error\">\n<p>Could not execute 'sinfo': ssh user@login01: Connection refused</p>\n</div>"));
+
+    let json = Json::from_str(&status_to_json(&status, &StatusFilter::new())).unwrap();
+    assert_eq!(json.find("update_errors").and_then(|val| val.as_array()).map(|val| val.len()), Some(1));
+
+    // Only shown once an error repeats
+    status.consecutive_failures = 1;
+    assert!(!status_to_html(&status, &test_page_options(60, false)).unwrap().contains("Still failing"));
+    status.consecutive_failures = 3;
+    let page = status_to_html(&status, &test_page_options(60, false)).unwrap();
+    assert!(page.contains("Connection refused</p>\n<p class=\"failures\">Still failing after 3 attempts in a row, retrying with backoff</p>\n</div>"));
+    assert!(status_to_text(&status, &StatusFilter::new()).contains("\nStill failing after 3 attempts in a row, retrying with backoff\n"));
+    assert_eq!(failures_notice(0), None);
+}
+
+#[test]
+fn test_skipped_lines_notice() {
+    assert_eq!(skipped_lines_notice(0), None);
+    assert_eq!(skipped_lines_notice(1), Some("1 line could not be parsed in the last update".to_string()));
+    assert_eq!(skipped_lines_notice(3), Some("3 lines could not be parsed in the last update".to_string()));
+}
+
+#[cfg(test)]
+fn test_mode_status() -> SlurmStatus {
+    let config = Configuration{ test_mode: true, .. Configuration::default() };
+    let mut status = SlurmStatus::new();
+    update_slurm_status(&mut status, &config, &::command_runner::TestRunner::new(Err("must not be called".to_string())), None);
+    status
+}
+
+#[test]
+fn test_render_filtered_status() {
+    let mut status = test_mode_status();
+    status.node_info[0].partition = "gpu".to_string();
+    status.job_info[0].partition = "gpu".to_string();
+    let mut templates = builtin_templates();
+    templates.register("filtered", "{{summary.name}}:{{#each jobs}}{{#with job}} {{job_id}}/{{user_name}}{{/with}}{{/each}}|{{#each nodes}} {{node}}{{/each}}").unwrap();
+    let options = PageOptions{ templates: Arc::new(templates), expand_arrays: true, pagination: Pagination::new(1, 1), .. PageOptions::default() };
+    let summary = |name: &str| {
+        let mut summary = BTreeMap::new();
+        summary.insert("name".to_string(), name.to_json());
+        Json::Object(summary)
+    };
+
+    // All jobs of the user are shown, even with a page limit
+    let html = render_filtered_status(&status, &options, StatusFilter{ user: Some("user02".to_string()), .. StatusFilter::new() }, "filtered", summary("user02")).unwrap();
+    let (jobs, nodes) = html.split_at(html.find('|').unwrap());
+    assert!(jobs.starts_with("user02: "));
+    assert!(jobs.matches("/user02").count() > 1);
+    assert!(!jobs.contains("/user01"));
+    assert_eq!(nodes.matches(' ').count(), status.node_info.len());
+
+    let html = render_filtered_status(&status, &options, StatusFilter{ partition: Some("gpu".to_string()), .. StatusFilter::new() }, "filtered", summary("gpu")).unwrap();
+    let job = &status.job_info[0];
+    assert_eq!(html, format!("gpu: {}/{}| {}", job.job_id.as_ref().unwrap(), job.user_name, status.node_info[0].node));
+
+    assert!(render_filtered_status(&status, &options, StatusFilter::new(), "missing", Json::Null).is_err());
+}
+
+#[test]
+fn test_filtered_status_to_json() {
+    let mut status = test_mode_status();
+    status.node_info[0].partition = "gpu".to_string();
+    let filter = StatusFilter{ partition: Some("esd".to_string()), .. StatusFilter::new() };
+
+    let json = Json::from_str(&filtered_status_to_json(&status, &filter, "esd".to_json())).unwrap();
+    assert_eq!(json.find("summary").and_then(|val| val.as_string()), Some("esd"));
+    assert_eq!(json.find("node_info").and_then(|val| val.as_array()).map(|nodes| nodes.len()), Some(status.node_info.len() - 1));
+    // The same fields as /api/status
+    let full = Json::from_str(&status_to_json(&status, &filter)).unwrap();
+    assert_eq!(json.as_object().map(|fields| fields.len()), full.as_object().map(|fields| fields.len() + 1));
+    assert_eq!(json.find("job_info"), full.find("job_info"));
+}
+
+#[test]
+fn test_truncate_text() {
+    assert_eq!(truncate_text("small_test01", 20), "small_test01");
+    assert_eq!(truncate_text("a_very_long_job_name_indeed", 10), "a_very_lo…");
+    assert_eq!(truncate_text("über", 4), "über");
+}
+
+#[test]
+fn test_status_to_text() {
+    use sinfo_util::get_partition_node_info_test;
+
+    let mut status = SlurmStatus::new();
+    status.last_update = "2000.01.01 - 09:00".to_string();
+    status.node_info = get_partition_node_info_test();
+    status.job_info = get_job_info_test();
+    status.job_info[7].job_name = "a_very_long_job_name_for_testing".to_string();
+
+    assert_eq!(status_to_text(&status, &StatusFilter::new()), "\
+Slurm Inspector, last update: 2000.01.01 - 09:00
+
+Partitions:
+  esd: down, 12 nodes (allocated 1, completing 1, drained 1, draining 1, fail 1, failing 1, idle 3, maint 1, unknown 2), 4 GPUs
+
+Nodes: 12 (allocated 1, completing 1, drained 1, draining 1, fail 1, failing 1, idle 3, maint 1, unknown 2)
+
+Job ID  User    Name                  State    Time  Nodes / Reason
+1       user01  small_test01          RUNNING  1:00  node01
+8       user02  a_very_long_job_nam…  PENDING  5:00  Dependency
+");
+}
+
+#[test]
+fn test_status_to_text_empty() {
+    let mut status = SlurmStatus::new();
+    status.update_errors.push("Could not execute 'sinfo': not found".to_string());
+
+    assert_eq!(status_to_text(&status, &StatusFilter::new()), "\
+Slurm Inspector, last update: -
+Error: Could not execute 'sinfo': not found
+
+Partitions:
+
+Nodes: 0
+
+No running or pending jobs
+");
+}
+
+#[test]
+fn test_update_slurm_status_test_mode() {
+    let config = Configuration{ test_mode: true, enable_sshare: true, .. Configuration::default() };
+    let runner = ::command_runner::TestRunner::new(Err("must not be called".to_string()));
+    let mut status = SlurmStatus::new();
+
+    update_slurm_status(&mut status, &config, &runner, None);
+
+    let mut node_info = get_partition_node_info_test();
+    merge_down_reasons(&mut node_info, &get_down_reasons_test());
+    assert_eq!(status.node_info, node_info);
+    assert_eq!(status.node_info[2].reason, Some("Not responding".to_string()));
+    let mut job_info = get_job_info_test();
+    set_wait_times(&mut job_info, "2000-01-01T10:00:00");
+    assert_eq!(status.job_info, job_info);
+    assert_eq!(status.job_info[7].wait_time, Some(8088));
+    assert!(status.share_info.len() > 0);
+    assert!(status.update_errors.is_empty());
+    assert!(status.last_update.len() > 0);
+    assert_eq!(status.history.entries().len(), 1);
+    assert!(status.last_success.is_some());
+    assert_eq!(status.consecutive_failures, 0);
+    assert_eq!(runner.calls.lock().unwrap().len(), 0);
+}
+
+#[test]
+fn test_update_slurm_status_synthetic() {
+    use synthetic_data::SyntheticData;
+
+    let data = SyntheticData{ nodes: 500, partitions: 6, jobs: 5000, seed: 3 };
+    let config = Configuration{ test_mode: true, test_data: Some(data), page_limit: 0, .. Configuration::default() };
+    let runner = ::command_runner::TestRunner::new(Err("must not be called".to_string()));
+    let mut status = SlurmStatus::new();
+
+    update_slurm_status(&mut status, &config, &runner, None);
+    assert_eq!(status.node_info.len(), 500);
+    assert_eq!(status.job_info.len(), 5000);
+    assert!(status.job_info.iter().any(|job| job.wait_time.is_some()));
+    assert!(status.update_errors.is_empty());
+    assert_eq!(runner.calls.lock().unwrap().len(), 0);
+
+    // The whole page with all jobs can be rendered
+    let html = status_to_html(&status, &PageOptions::new(&config)).unwrap();
+    assert!(html.contains("node0500"));
+    assert!(status_to_json(&status, &StatusFilter::new()).len() > 5000);
+}
+
+#[test]
+fn test_update_slurm_status_anonymize() {
+    let config = Configuration{ test_mode: true, anonymize: true, .. Configuration::default() };
+    let mut status = SlurmStatus::new();
+
+    update_slurm_status(&mut status, &config, &::command_runner::TestRunner::new(Ok(String::new())), None);
+    assert!(status.job_info.iter().all(|job| !job.user_name.starts_with("user0")));
+}
+
+#[test]
+fn test_update_slurm_status_errors() {
+    let config = Configuration{ squeue_path: "/opt/slurm/bin/squeue".to_string(), .. Configuration::default() };
+    let runner = ::command_runner::TestRunner::new(Err("No such file or directory".to_string()));
+    let mut status = SlurmStatus::new();
+    status.job_info = get_job_info_test();
+
+    update_slurm_status(&mut status, &config, &runner, None);
+
+    // The jobs of the previous update are kept
+    assert_eq!(status.job_info, get_job_info_test());
+    assert_eq!(status.update_errors, vec![
+        "Could not execute 'sinfo': No such file or directory".to_string(),
+        "Could not execute '/opt/slurm/bin/squeue': No such file or directory".to_string()
+    ]);
+    assert_eq!(status.last_success, None);
+    assert_eq!(status.consecutive_failures, 1);
+
+    update_slurm_status(&mut status, &config, &runner, None);
+    assert_eq!(status.consecutive_failures, 2);
+    assert_eq!((status.metrics.sinfo.calls, status.metrics.sinfo.failures), (2, 2));
+}
+
+#[test]
+fn test_update_slurm_status_parts() {
+    let config = Configuration::default();
+    let runner = ::command_runner::TestRunner::new(Err("No such file or directory".to_string()));
+    let mut status = SlurmStatus::new();
+
+    update_slurm_status_parts(&mut status, &config, &runner, None, DueUpdates{ node_info: true, job_info: false });
+    assert_eq!(status.update_errors, vec!["Could not execute 'sinfo': No such file or directory".to_string()]);
+    assert!(status.node_update.time.len() > 0);
+    assert_eq!(status.job_update, TableUpdate::new());
+    assert_eq!((status.metrics.sinfo.calls, status.metrics.squeue.calls), (1, 0));
+
+    // The sinfo error is kept until the node information is fetched again
+    update_slurm_status_parts(&mut status, &config, &runner, None, DueUpdates{ node_info: false, job_info: true });
+    assert_eq!(status.update_errors, vec![
+        "Could not execute 'sinfo': No such file or directory".to_string(),
+        "Could not execute 'squeue': No such file or directory".to_string()
+    ]);
+    assert!(status.job_update.time.len() > 0);
+    assert_eq!((status.metrics.sinfo.calls, status.metrics.squeue.calls), (1, 1));
+    assert_eq!(status.consecutive_failures, 2);
+    assert_eq!(status.history.entries().len(), 2);
+
+    let json = Json::from_str(&status_to_json(&status, &StatusFilter::new())).unwrap();
+    assert_eq!(json.find("job_update").and_then(|val| val.as_string()), Some(&status.job_update.time[..]));
+}
+
+#[test]
+fn test_update_slurm_status_concurrent() {
+    use std::time::{Duration, Instant};
+
+    // sinfo and squeue take 500 ms each, in a row the update would take at least one second
+    struct DelayRunner {
+        fail_sinfo: bool
+    }
+    impl CommandRunner for DelayRunner {
+        fn run(&self, program: &str, args: &[String], _environment: &[(&str, &str)]) -> Result<String, String> {
+            match (program, args.first().map(|arg| &arg[..])) {
+                ("sinfo", Some("-h")) => {
+                    thread::sleep(Duration::from_millis(500));
+                    if self.fail_sinfo { Err("timed out".to_string()) } else { Ok("esd up node01 node01 none 0.0 idle 1 1 1 0/1/0/1 (null)\n".to_string()) }
                 },
-                Err(err) => {
-                    error!("Could not lock Mutex: {}", err);
-                }
+                ("squeue", _) => {
+                    thread::sleep(Duration::from_millis(500));
+                    Ok("node01 1 2 1 N/A * 1 * sim * N/A 1:00 node01 0.9 None 2000-01-01T09:00:00 RUNNING willi 1000 esd N/A 2000-01-01T08:30:00 1-00:00:00 (null)\n".to_string())
+                },
+                _ => Err("not available".to_string())
             }
+        }
+    }
+
+    let config = Configuration::default();
+    let mut status = SlurmStatus::new();
+    let start = Instant::now();
+    update_slurm_status(&mut status, &config, &DelayRunner{ fail_sinfo: false }, None);
+    let elapsed = start.elapsed();
+
+    assert!(elapsed >= Duration::from_millis(500));
+    assert!(elapsed < Duration::from_millis(900), "the update took {:?}", elapsed);
+    assert_eq!(status.node_info.len(), 1);
+    assert_eq!(status.job_info.len(), 1);
+    assert!(status.metrics.sinfo.last_duration_ms >= 500.0);
+    assert!(status.metrics.squeue.last_duration_ms >= 500.0);
+
+    // A failing sinfo doesn't discard the fresh jobs, the previous nodes are kept
+    status.job_info.clear();
+    update_slurm_status(&mut status, &config, &DelayRunner{ fail_sinfo: true }, None);
+    assert_eq!(status.job_info.len(), 1);
+    assert_eq!(status.node_info.len(), 1);
+    assert_eq!(status.update_errors, vec!["Could not execute 'sinfo': timed out".to_string()]);
+}
+
+#[test]
+fn test_next_status_update_refresh() {
+    use std::time::{Duration, Instant};
+
+    let config = Configuration{ test_mode: true, sinfo_interval: 3600, squeue_interval: 3600, .. Configuration::default() };
+    let shared_slurm_status = Arc::new(SharedStatus::new(SlurmStatus::new()));
+    let refresh = Arc::new(RefreshControl::new(0));
+    let start = Instant::now();
+
+    let subscribers = Arc::new(Subscribers::new());
+    let events = subscribers.subscribe().unwrap();
 
-            sleep(Duration::new(config.interval, 0));
+    let (thread_status, thread_refresh, thread_subscribers) = (shared_slurm_status.clone(), refresh.clone(), subscribers.clone());
+    let handle = thread::spawn(move || {
+        let runner = ::command_runner::TestRunner::new(Err("must not be called".to_string()));
+        let mut schedule = UpdateSchedule::new(config.sinfo_interval, config.squeue_interval);
+        // The first update runs right away, the second one only because of the refresh
+        for _ in 0..2 {
+            next_status_update(&thread_status, &PageOptions::default(), &config, &runner, &mut None, &mut None, &mut schedule, &thread_refresh,
+                &thread_subscribers);
         }
+        let calls = runner.calls.lock().unwrap().len();
+        calls
     });
+
+    while shared_slurm_status.snapshot().unwrap().history.entries().is_empty() {
+        assert!(start.elapsed() < Duration::new(10, 0));
+        thread::sleep(Duration::from_millis(1));
+    }
+    let number = refresh.request(get_time().sec).unwrap();
+    assert!(refresh.wait_finished(number, Duration::new(10, 0)));
+    assert_eq!(handle.join().unwrap(), 0);
+    assert_eq!(shared_slurm_status.snapshot().unwrap().history.entries().len(), 2);
+    assert!(start.elapsed() < Duration::new(10, 0));
+
+    // Every update is sent to the /events streams
+    assert_eq!(events.try_iter().count(), 2);
 }
 
-/// Public helper function accepts SlurmStatus and returns a string containing the HTML representation of the status
-/// TODO: use some template mechanism (HandleBars, Roustache, ...)
-pub fn status_to_html(status: &SlurmStatus) -> String {
-    let mut result = String::new();
+#[test]
+fn test_raw_output_capture() {
+    let runner = ::command_runner::TestRunner::new(Ok("node01 1 2 1 N/A * 1 * sim * N/A 1:00 node01 0.9 None 2000-01-01T09:00:00 RUNNING willi 1000 esd N/A 2000-01-01T08:30:00 1-00:00:00 (null)\n".to_string()));
+    let config = Configuration{ debug_endpoints: true, .. Configuration::default() };
+    let mut status = SlurmStatus::new();
+    update_slurm_status(&mut status, &config, &runner, None);
+    assert!(status.raw_sinfo.is_some());
+    assert!(status.raw_squeue.as_ref().map_or(false, |raw_output| raw_output.to_text(&config.display_time).contains("willi")));
+
+    // The raw output would show the real names next to the pseudonyms
+    let config = Configuration{ anonymize: true, .. config };
+    let mut status = SlurmStatus::new();
+    update_slurm_status(&mut status, &config, &runner, None);
+    assert_eq!(status.raw_sinfo, None);
+    assert_eq!(status.raw_squeue, None);
+}
+
+#[test]
+fn test_next_status_update_commands_without_lock() {
+    use std::sync::mpsc::channel;
 
-    // Page header and some CSS
-    result.push_str("<html>\n");
-    result.push_str("<head>\n");
-    result.push_str("<title>Slurm Inspector</title>\n");
-    result.push_str("<style>\n");
-    result.push_str("table, { border: 1px solid black; }\n");
-    result.push_str("th, td { border: 1px solid black; padding: 10px; }\n");
-    result.push_str("th { background: #e0e0e0; }\n");
-    result.push_str("#partition_down { background: #ffa0a0; }\n");
-    result.push_str("</style>\n");
-    result.push_str("</head>\n");
-    result.push_str("<body>\n");
-
-    // When was the SLURM status information last updated ?
-    result.push_str(&format!("<h3>Last update: {}</h3>", status.last_update));
-
-    result.push_str("<br>\n<br>\n<br>\n<br>\n");
-
-    // Prepare first table (partition and node) with header
-    result.push_str("<h3>Partition and node information:</h3>\n");
-    result.push_str("<table>\n");
-    result.push_str("<tr>\n");
-    result.push_str("<th>Partition</th>");
-    result.push_str("<th>Availability</th>");
-    result.push_str("<th>Hostname</th>");
-    result.push_str("<th>Node</th>");
-    result.push_str("<th>Error</th>");
-    result.push_str("<th>CPU load</th>");
-    result.push_str("<th>Node state</th>");
-    result.push_str("<th>Node sockets</th>");
-    result.push_str("<th>Node cores</th>");
-    result.push_str("<th>Node threads</th>");
-    result.push_str("</tr>\n");
-
-    for node in &status.node_info {
-        result.push_str("<tr>\n");
-        result.push_str(&format!("<td>{}</td>", node.partition));
-        result.push_str(
-            match node.availability {
-                PartitionAvailability::Up => "<td>Up</td>",
-                _ => "<td id=\"partition_down\">Down</td>"
+    // Updates another time while squeue runs, like an admin action, which would wait forever if the update held the lock
+    struct UpdatingRunner {
+        shared: Arc<SharedStatus>
+    }
+    impl CommandRunner for UpdatingRunner {
+        fn run(&self, program: &str, _args: &[String], _environment: &[(&str, &str)]) -> Result<String, String> {
+            if program == "squeue" {
+                let shared = self.shared.clone();
+                let (sender, receiver) = channel();
+                thread::spawn(move || {
+                    shared.update(&mut |status| status.simulated_nodes.clear()).unwrap();
+                    sender.send(()).unwrap();
+                });
+                assert!(receiver.recv_timeout(Duration::new(10, 0)).is_ok());
             }
-        );
-        result.push_str(&format!("<td>{}</td>", node.hostname));
-        result.push_str(&format!("<td>{}</td>", node.node));
-        result.push_str(&format!("<td>{:?}</td>", node.error));
-        result.push_str(node.cpu_load.map_or("<td>-</td>".to_string(), |val| format!("<td>{}</td>", val)).as_ref());
-        result.push_str(format!("<td>{:?}</td>", node.node_state).as_ref());
-        result.push_str(node.node_sockets.map_or("<td>-</td>".to_string(), |val| format!("<td>{}</td>", val)).as_ref());
-        result.push_str(node.node_cores.map_or("<td>-</td>".to_string(), |val| format!("<td>{}</td>", val)).as_ref());
-        result.push_str(node.node_threads.map_or("<td>-</td>".to_string(), |val| format!("<td>{}</td>", val)).as_ref());
-        result.push_str("</tr>\n");
-    }
-
-    result.push_str("</table>\n");
-
-    result.push_str("<br>\n<br>\n<br>\n<br>\n");
-
-    // Prepare second table (job information) with header
-    result.push_str("<h3>Job information:</h3>\n");
-    result.push_str("<table>\n");
-    result.push_str("<tr>\n");
-    result.push_str("<th>Executing host</th>");
-    result.push_str("<th>Min CPU</th>");
-    result.push_str("<th>Num CPU</th>");
-    result.push_str("<th>Num nodes</th>");
-    result.push_str("<th>Job array ID</th>");
-    result.push_str("<th>Number of Sockets</th>");
-    result.push_str("<th>Job ID</th>");
-    result.push_str("<th>Number of Cores</th>");
-    result.push_str("<th>Job name</th>");
-    result.push_str("<th>Number of threads</th>");
-    result.push_str("<th>Job array index</th>");
-    result.push_str("<th>Run time</th>");
-    result.push_str("<th>List of nodes</th>");
-    result.push_str("<th>Priority</th>");
-    result.push_str("<th>State reason</th>");
-    result.push_str("<th>Start time</th>");
-    result.push_str("<th>Job state</th>");
-    result.push_str("<th>User name</th>");
-    result.push_str("<th>User ID</th>");
-    result.push_str("</tr>\n");
-
-    for job in &status.job_info {
-        result.push_str("<tr>\n");
-        result.push_str(&format!("<td>{}</td>", job.executing_host));
-
-        result.push_str(job.minimum_cpu.map_or("<td>-</td>".to_string(), |val| format!("<td>{}</td>", val)).as_ref());
-        result.push_str(job.num_cpu.map_or("<td>-</td>".to_string(), |val| format!("<td>{}</td>", val)).as_ref());
-        result.push_str(job.num_nodes.map_or("<td>-</td>".to_string(), |val| format!("<td>{}</td>", val)).as_ref());
-        result.push_str(job.job_array_id.map_or("<td>-</td>".to_string(), |val| format!("<td>{}</td>", val)).as_ref());
-        result.push_str(job.num_sockets.map_or("<td>-</td>".to_string(), |val| format!("<td>{}</td>", val)).as_ref());
-        result.push_str(job.job_id.map_or("<td>-</td>".to_string(), |val| format!("<td>{}</td>", val)).as_ref());
-        result.push_str(job.num_cores.map_or("<td>-</td>".to_string(), |val| format!("<td>{}</td>", val)).as_ref());
-        result.push_str(&format!("<td>{}</td>", job.job_name));
-        result.push_str(job.num_threads.map_or("<td>-</td>".to_string(), |val| format!("<td>{}</td>", val)).as_ref());
-        result.push_str(job.job_array_index.map_or("<td>-</td>".to_string(), |val| format!("<td>{}</td>", val)).as_ref());
-        result.push_str(&format!("<td>{}</td>", job.run_time));
-        result.push_str("<td>");
-        for node in &job.list_of_nodes {
-            result.push_str(&format!("{},", node));
+            Err("not available".to_string())
         }
-        result.push_str("</td>");
-        result.push_str(job.priority.map_or("<td>-</td>".to_string(), |val| format!("<td>{}</td>", val)).as_ref());
-        result.push_str(&format!("<td>{:?}</td>", job.state_reason));
-        result.push_str(&format!("<td>{}</td>", job.start_time));
-        result.push_str(&format!("<td>{:?}</td>", job.job_state));
-        result.push_str(&format!("<td>{}</td>", job.user_name));
-        result.push_str(job.user_id.map_or("<td>-</td>".to_string(), |val| format!("<td>{}</td>", val)).as_ref());
-        result.push_str("</tr>\n");
     }
 
-    result.push_str("</table>\n");
+    let config = Configuration{ sinfo_interval: 3600, squeue_interval: 3600, .. Configuration::default() };
+    let shared_slurm_status = Arc::new(SharedStatus::new(SlurmStatus::new()));
+    let runner = UpdatingRunner{ shared: shared_slurm_status.clone() };
+    let mut schedule = UpdateSchedule::new(config.sinfo_interval, config.squeue_interval);
+    next_status_update(&shared_slurm_status, &PageOptions::default(), &config, &runner, &mut None, &mut None, &mut schedule,
+        &RefreshControl::new(0), &Subscribers::new());
 
-    result.push_str("</body>\n");
-    result.push_str("</html>\n");
+    let status = shared_slurm_status.snapshot().unwrap();
+    assert_eq!(status.history.entries().len(), 1);
+    assert_eq!(status.update_errors.len(), 2);
+}
 
-    result
+#[test]
+fn test_summary_to_json() {
+    let mut status = SlurmStatus::new();
+    status.node_info = get_partition_node_info_test();
+    status.last_update = "2015.12.20 - 12:00".to_string();
+    status.job_update.time = "2015.12.20 - 12:00".to_string();
+    status.job_info = get_job_info_test();
+    set_wait_times(&mut status.job_info, "2000-01-01T10:00:00");
+
+    let json = Json::from_str(&summary_to_json(&status, &StatusFilter::new())).unwrap();
+    assert_eq!(json.find("last_update").and_then(|val| val.as_string()), Some("2015.12.20 - 12:00"));
+    assert_eq!(json.find("node_update").and_then(|val| val.as_string()), Some(""));
+    assert_eq!(json.find_path(&["dashboard", "total_nodes"]).and_then(|val| val.as_u64()), Some(dashboard_summary(&status, &StatusFilter::new()).total_nodes as u64));
+    assert!(json.find("job_info").is_none());
+    // Job 8 is the only pending job, submitted at 07:45:12
+    assert_eq!(json.find_path(&["wait_times", "esd", "max"]).and_then(|val| val.as_u64()), Some(8088));
+    assert_eq!(json.find_path(&["wait_times", "esd", "jobs"]).and_then(|val| val.as_u64()), Some(1));
+    assert_eq!(json.find("longest_waiting").and_then(|val| val.as_array()).map(|jobs| jobs.len()), Some(1));
+    assert_eq!(json.find("longest_waiting").map(|val| val[0].find("seconds").and_then(|val| val.as_u64())), Some(Some(8088)));
+    assert_eq!(json.find("longest_running").map(|val| val[0].find("job_id").and_then(|val| val.as_string())), Some(Some("1")));
+}
+
+#[test]
+fn test_update_slurm_status_metrics() {
+    let config = Configuration::default();
+    // Neither sinfo nor squeue can parse this line
+    let runner = ::command_runner::TestRunner::new(Ok("1 2 3\n".to_string()));
+    let mut status = SlurmStatus::new();
+    let page = status_to_html(&status, &PageOptions::default()).unwrap();
+    assert!(!page.contains("Inspector internals"));
+    assert!(!page.contains("skipped_lines"));
+
+    update_slurm_status(&mut status, &config, &runner, None);
+    update_slurm_status(&mut status, &config, &runner, None);
+
+    for (_, metrics) in status.metrics.commands() {
+        assert_eq!((metrics.calls, metrics.failures), (2, 0));
+        assert_eq!((metrics.last_parsed, metrics.last_skipped), (0, 1));
+        assert_eq!((metrics.total_parsed, metrics.total_skipped), (0, 2));
+        assert!(metrics.last_duration_ms >= 0.0);
+    }
+
+    let page = status_to_html(&status, &PageOptions::default()).unwrap();
+    assert!(page.contains("<summary><b>Inspector internals</b></summary>"));
+    // One line of sinfo and one of squeue in the last update
+    assert!(page.contains("<p class=\"skipped_lines\">2 lines could not be parsed in the last update</p>"));
+    assert!(page.contains("<td>squeue</td>"));
+
+    let json = Json::from_str(&status_to_json(&status, &StatusFilter::new())).unwrap();
+    assert_eq!(json.find_path(&["inspector", "sinfo", "total_skipped"]).and_then(|val| val.as_u64()), Some(2));
+}
+
+#[test]
+fn test_update_slurm_status_node_events() {
+    use webhook::WebhookNotifier;
+
+    let config = Configuration{ test_mode: true, .. Configuration::default() };
+    let runner = ::command_runner::TestRunner::new(Ok(String::new()));
+    let mut notifier = WebhookNotifier::new("http://localhost/hook", "slurm", 600);
+    let mut status = SlurmStatus::new();
+    status.node_info = get_partition_node_info_test();
+    status.node_info[0].node_state = ::sinfo_util::NodeState::Allocated;
+
+    update_slurm_status(&mut status, &config, &runner, Some(&mut notifier));
+
+    assert_eq!(status.node_events.len(), 1);
+    assert_eq!(status.node_events[0].node, "node01");
+    // Allocated -> Idle is not a problem
+    assert_eq!(notifier.pending().len(), 0);
+}
+
+#[test]
+fn test_check_email_triggers() {
+    use std::sync::mpsc::channel;
+    use email_notify::EmailConfig;
+
+    let (sender, receiver) = channel();
+    let email_config = EmailConfig { smtp_server: "localhost".to_string(), smtp_port: 25, from: "slurm@example.com".to_string(),
+        to: vec!["admin@example.com".to_string()], smtp_user: None, smtp_password_file: None, node_threshold: 5, failing_minutes: 30, cooldown: 3600 };
+    let mut email = EmailNotifier::new(&email_config, "cluster1", sender);
+    let previous = get_partition_node_info_test();
+    let mut status = SlurmStatus::new();
+    status.node_info = get_partition_node_info_test();
+    for node in status.node_info.iter_mut() {
+        node.availability = PartitionAvailability::Down;
+    }
+    status.last_update = "2000.01.01 - 03:00".to_string();
+
+    // Nothing to compare on the first update
+    check_email_triggers(&mut email, &[], &status, 0);
+    assert_eq!(receiver.try_iter().count(), 0);
+
+    check_email_triggers(&mut email, &previous, &status, 60);
+    let subjects: Vec<String> = receiver.try_iter().map(|message| message.subject).collect();
+    assert_eq!(subjects, vec!["[slurm_inspector cluster1] Partition esd is down"]);
+
+    status.update_errors = vec!["Could not execute 'sinfo': timeout".to_string()];
+    check_email_triggers(&mut email, &[], &status, 120);
+    check_email_triggers(&mut email, &[], &status, 120 + 1860);
+    let subjects: Vec<String> = receiver.try_iter().map(|message| message.subject).collect();
+    assert_eq!(subjects, vec!["[slurm_inspector cluster1] Updates failing for 31 minutes"]);
+}
+
+#[test]
+fn test_status_to_html_admin_actions() {
+    use admin_actions::admin_notice;
+
+    let mut status = SlurmStatus::new();
+    status.job_info = get_job_info_test();
+    status.node_info = get_partition_node_info_test();
+    let page = status_to_html(&status, &test_page_options(60, false)).unwrap();
+    assert!(!page.contains("<th>Actions</th>"));
+    assert!(!page.contains("/cancel"));
+    assert!(!page.contains("/drain"));
+
+    let options = PageOptions{ admin_actions: true, admin_notice: admin_notice(Some("job/1/cancel"), None), .. test_page_options(60, false) };
+    let page = status_to_html(&status, &options).unwrap();
+    assert!(page.contains("<th>Actions</th></tr>"));
+    assert!(page.contains("<a class=\"admin_action\" href=\"/job/1/cancel\">Cancel</a>"));
+    assert!(page.contains("<p class=\"admin_done\">Job 1 has been cancelled</p>"));
+    let node = &status.node_info[0].node;
+    assert!(page.contains(&format!("<a class=\"admin_action\" href=\"/node/{}/", node)));
+}
+
+#[test]
+fn test_update_slurm_status_simulated_nodes() {
+    use admin_actions::{AdminAction, simulate_node_action};
+
+    let config = Configuration{ test_mode: true, .. Configuration::default() };
+    let runner = ::command_runner::TestRunner::new(Err("must not be called".to_string()));
+    let mut status = SlurmStatus::new();
+    update_slurm_status(&mut status, &config, &runner, None);
+    let node = status.node_info.iter().find(|node| node.node_state == NodeState::Idle).unwrap().node.clone();
+
+    let drain = AdminAction::DrainNode { node: node.clone(), reason: "bad DIMM".to_string() };
+    simulate_node_action(&drain, "admin", "2000-01-01T12:00:00", &mut status.simulated_nodes);
+    update_slurm_status_parts(&mut status, &config, &runner, None, DueUpdates{ node_info: true, job_info: false });
+    let drained = status.node_info.iter().find(|info| info.node == node).unwrap();
+    assert_eq!((drained.node_state, drained.reason.as_ref().map(|reason| &reason[..])), (NodeState::Drained, Some("bad DIMM")));
+
+    // Kept after the next update, until the node is resumed
+    update_slurm_status(&mut status, &config, &runner, None);
+    assert_eq!(status.node_info.iter().find(|info| info.node == node).unwrap().node_state, NodeState::Drained);
+    simulate_node_action(&AdminAction::ResumeNode(node.clone()), "admin", "2000-01-01T12:00:00", &mut status.simulated_nodes);
+    update_slurm_status(&mut status, &config, &runner, None);
+    assert_eq!(status.node_info.iter().find(|info| info.node == node).unwrap().node_state, NodeState::Idle);
+}
+
+#[test]
+fn test_update_slurm_status_recently_finished() {
+    use squeue_util::JobId;
+
+    let config = Configuration{ test_mode: true, .. Configuration::default() };
+    let runner = ::command_runner::TestRunner::new(Err("must not be called".to_string()));
+    let mut status = SlurmStatus::new();
+    update_slurm_status(&mut status, &config, &runner, None);
+    assert!(status.recently_finished.jobs.is_empty());
+
+    // A job that is gone at the next update
+    let gone = JobInfo{ job_id: Some(JobId::new(999)), .. status.job_info[0].clone() };
+    status.job_info.push(gone);
+    update_slurm_status(&mut status, &config, &runner, None);
+    assert_eq!(status.recently_finished.jobs.len(), 1);
+    assert_eq!(status.recently_finished.jobs[0].job.job_id, Some(JobId::new(999)));
+
+    let page = status_to_html(&status, &test_page_options(60, false)).unwrap();
+    assert!(page.contains("<table class=\"recently_finished\">"));
+    assert!(page.contains(&format!("<td>999</td><td>{}</td>", status.job_info[0].job_name)));
+    let json = Json::from_str(&status_to_json(&status, &StatusFilter::new())).unwrap();
+    assert_eq!(json.find("recently_finished").and_then(|val| val.as_array()).map(|jobs| jobs.len()), Some(1));
+    assert_eq!(json.find("recently_finished").and_then(|val| val[0].find("last_state")).and_then(|val| val.as_string()),
+        Some(&format!("{:?}", status.job_info[0].job_state)[..]));
+
+    // Disabled with --recently-finished-minutes 0
+    let config = Configuration{ recently_finished_minutes: 0, .. config };
+    update_slurm_status(&mut status, &config, &runner, None);
+    assert!(status.recently_finished.jobs.is_empty());
+    assert!(!status_to_html(&status, &test_page_options(60, false)).unwrap().contains("recently_finished"));
+}
+
+#[test]
+fn test_status_to_html_dashboard() {
+    use sinfo_util::get_partition_node_info_test;
+
+    let mut status = SlurmStatus::new();
+    status.node_info = get_partition_node_info_test();
+    status.job_info = get_job_info_test();
+
+    let page = status_to_html(&status, &PageOptions::default()).unwrap();
+    assert!(page.contains("<div class=\"dashboard_box\"><span class=\"dashboard_number\">12</span><br>Nodes</div>"));
+    assert!(page.contains("<div class=\"dashboard_box dashboard_problem\"><span class=\"dashboard_number\">1</span><br>Nodes down</div>"));
+    assert!(page.find("class=\"dashboard\"") < page.find("Partition and node information"));
+
+    status.node_info.clear();
+    let page = status_to_html(&status, &PageOptions::default()).unwrap();
+    assert!(page.contains("<div class=\"dashboard_box\"><span class=\"dashboard_number\">0</span><br>Nodes down</div>"));
+
+    let json = Json::from_str(&status_to_json(&status, &StatusFilter::new())).unwrap();
+    assert_eq!(json.find_path(&["dashboard", "running_jobs"]).and_then(|val| val.as_u64()), Some(1));
+}
+
+#[test]
+fn test_status_to_html_state_filter() {
+    use sinfo_util::get_partition_node_info_test;
+
+    let mut status = SlurmStatus::new();
+    status.node_info = get_partition_node_info_test();
+    status.job_info = get_job_info_test();
+
+    let filter = StatusFilter{ job_states: vec![JobState::Pending], node_states: vec![NodeState::Fail],
+        unknown_states: vec!["<b>sleeping</b>".to_string()], .. StatusFilter::new() };
+    let page = status_to_html(&status, &PageOptions{ filter: filter.clone(), .. PageOptions::default() }).unwrap();
+
+    assert!(page.contains("<p class=\"filter_notice\">Unknown states ignored: &lt;b&gt;sleeping&lt;/b&gt;</p>"));
+    assert!(page.contains("<td>small_test08</td>"));
+    assert!(!page.contains("<td>small_test01</td>"));
+    assert!(page.contains("<td>node09</td>"));
+    assert!(!page.contains("<td>node01</td>"));
+
+    let json = Json::from_str(&status_to_json(&status, &filter)).unwrap();
+    assert_eq!(json.find("node_info").and_then(|val| val.as_array()).map(|val| val.len()), Some(1));
+    assert_eq!(json.find("job_info").and_then(|val| val.as_array()).map(|val| val.len()), Some(1));
+    assert_eq!(json.find("unknown_states").and_then(|val| val.as_array()).map(|val| val.len()), Some(1));
+}
+
+#[test]
+fn test_status_to_html_node_job_links() {
+    use sinfo_util::get_partition_node_info_test;
+
+    let mut status = SlurmStatus::new();
+    status.node_info = get_partition_node_info_test();
+    status.job_info = get_job_info_test();
+    let page = status_to_html(&status, &PageOptions::default()).unwrap();
+
+    // Node table: node name links to the first job, the jobs column lists all jobs
+    assert!(page.contains("<tr id=\"node-node02\">"));
+    assert!(page.contains("<td><a href=\"#job-2\">node02</a></td>"));
+    assert!(page.contains("<td><a href=\"#job-6\">6</a></td>"));
+    assert!(page.contains("<td>node09</td>"));
+    // Job table: the compressed node list links to the node table
+    assert!(page.contains("<tr id=\"job-2\">"));
+    assert!(page.contains("<td><a href=\"#node-node01\">node[01-02]</a></td>"));
+}
+
+#[test]
+fn test_update_shared_status() {
+    let shared_slurm_status = SharedStatus::new(SlurmStatus::new());
+    let last_update_of = |status: &SlurmStatus| {
+        let json = Json::from_str(&status.rendered.json.plain).unwrap();
+        let last_update = json.find("last_update").and_then(|val| val.as_string()).unwrap().to_string();
+        assert!(status.rendered.html.plain.contains(&format!("Last update: {} ", last_update)));
+        assert!(status.rendered.text.plain.contains(&format!("last update: {}\n", last_update)));
+        last_update
+    };
+
+    update_shared_status(&shared_slurm_status, &PageOptions::default(), &mut |status| status.last_update = "2000.01.01 - 09:00".to_string());
+    assert_eq!(last_update_of(&shared_slurm_status.snapshot().unwrap()), "2000.01.01 - 09:00");
+
+    // While an update is in progress, the lock is free and requests still see the complete previous snapshot
+    update_shared_status(&shared_slurm_status, &PageOptions::default(), &mut |status| {
+        status.job_info = get_job_info_test();
+        status.last_update = "2000.01.01 - 09:01".to_string();
+        let shared = shared_slurm_status.snapshot().unwrap();
+        assert!(shared.job_info.is_empty());
+        assert_eq!(last_update_of(&shared), "2000.01.01 - 09:00");
+    });
+
+    let token = {
+        let status = shared_slurm_status.snapshot().unwrap();
+        assert_eq!(last_update_of(&status), "2000.01.01 - 09:01");
+        assert_eq!(status.job_info.len(), get_job_info_test().len());
+        // The snapshot before the update is kept for /api/changes
+        let previous = status.previous_snapshot.as_ref().unwrap();
+        assert!(previous.jobs.is_empty());
+        assert!(previous.token != status.rendered.etag);
+        previous.token.clone()
+    };
+
+    // Nothing changed, the snapshot before the last change is kept
+    update_shared_status(&shared_slurm_status, &PageOptions::default(), &mut |_| {});
+    assert_eq!(shared_slurm_status.snapshot().unwrap().previous_snapshot.as_ref().map(|previous| &previous.token[..]), Some(&token[..]));
+}
+
+#[test]
+fn test_update_shared_status_banner() {
+    use std::env::temp_dir;
+    use std::fs::{File, remove_file};
+    use std::io::Write;
+
+    let path = temp_dir().join(format!("slurm_inspector_banner_{}.txt", ::std::process::id()));
+    let config = Configuration{ test_mode: true, banner_file: Some(path.to_string_lossy().to_string()), .. Configuration::default() };
+    let runner = ::command_runner::TestRunner::new(Err("must not be called".to_string()));
+    let shared_slurm_status = SharedStatus::new(SlurmStatus::new());
+    let mut update = |status: &mut SlurmStatus| update_slurm_status(status, &config, &runner, None);
+    let banner_of = |status: &SlurmStatus| Json::from_str(&status.rendered.json.plain).unwrap().find("banner").and_then(|val| val.as_string()).map(|banner| banner.to_string());
+
+    // No file, no banner
+    update_shared_status(&shared_slurm_status, &PageOptions::default(), &mut update);
+    assert_eq!(banner_of(&shared_slurm_status.snapshot().unwrap()), None);
+    assert!(!shared_slurm_status.snapshot().unwrap().rendered.html.plain.contains("class=\"banner\""));
+
+    // The file is picked up by the next update, the text is escaped in the page
+    File::create(&path).and_then(|mut file| file.write_all(b"Maintenance <b>Saturday</b> 08:00-12:00\n")).unwrap();
+    update_shared_status(&shared_slurm_status, &PageOptions::default(), &mut update);
+    {
+        let status = shared_slurm_status.snapshot().unwrap();
+        assert_eq!(banner_of(&status), Some("Maintenance <b>Saturday</b> 08:00-12:00".to_string()));
+        assert!(status.rendered.html.plain.contains("<div class=\"banner\">Maintenance &lt;b&gt;Saturday&lt;/b&gt; 08:00-12:00</div>"));
+        assert!(status.rendered.text.plain.contains("\nMaintenance <b>Saturday</b> 08:00-12:00\n"));
+    }
+
+    // Emptied and removed again
+    File::create(&path).unwrap();
+    update_shared_status(&shared_slurm_status, &PageOptions::default(), &mut update);
+    assert_eq!(banner_of(&shared_slurm_status.snapshot().unwrap()), None);
+    File::create(&path).and_then(|mut file| file.write_all(b"Queues will drain")).unwrap();
+    update_shared_status(&shared_slurm_status, &PageOptions::default(), &mut update);
+    assert_eq!(banner_of(&shared_slurm_status.snapshot().unwrap()), Some("Queues will drain".to_string()));
+    remove_file(&path).unwrap();
+    update_shared_status(&shared_slurm_status, &PageOptions::default(), &mut update);
+    assert_eq!(banner_of(&shared_slurm_status.snapshot().unwrap()), None);
+}
+
+#[test]
+fn test_hide_partitions() {
+    use sinfo_util::get_partition_node_info_test;
+
+    let mut node_info = get_partition_node_info_test();
+    for node in node_info.iter_mut().skip(10) {
+        node.partition = "maintenance".to_string();
+    }
+    // node01 is in the visible partition "esd" and in the hidden partition "cloud"
+    let mut cloud_node = node_info[0].clone();
+    cloud_node.partition = "cloud".to_string();
+    node_info.push(cloud_node);
+
+    let mut visible = node_info.clone();
+    let exclude = PartitionSelection::Exclude(vec!["maintenance".to_string(), "cloud".to_string(), "gpu".to_string()]);
+    assert_eq!(hide_partitions(&mut visible, &exclude), 2);
+    assert_eq!(visible.len(), 10);
+    assert!(visible.iter().all(|node| node.partition == "esd"));
+    assert_eq!(visible[0].node, "node01");
+
+    let mut visible = node_info.clone();
+    assert_eq!(hide_partitions(&mut visible, &PartitionSelection::Include(vec!["maintenance".to_string()])), 2);
+    assert_eq!(visible.len(), 2);
+
+    let mut visible = node_info.clone();
+    assert_eq!(hide_partitions(&mut visible, &PartitionSelection::All), 0);
+    assert_eq!(visible, node_info);
+}
+
+#[test]
+fn test_hide_partition_jobs() {
+    let mut job_info = get_job_info_test();
+    job_info[0].partition = "maintenance".to_string();
+    job_info[1].partition = "maintenance,esd".to_string();
+    job_info[2].partition = String::new();
+
+    let exclude = PartitionSelection::Exclude(vec!["maintenance".to_string()]);
+    let mut visible = job_info.clone();
+    hide_partition_jobs(&mut visible, &exclude);
+    let job_ids: Vec<Option<u32>> = visible.iter().map(|job| job.job_id.as_ref().map(|job_id| job_id.id)).take(2).collect();
+    assert_eq!(visible.len(), job_info.len() - 1);
+    assert_eq!(job_ids, vec![Some(2), Some(3)]);
+
+    let mut visible = job_info.clone();
+    hide_partition_jobs(&mut visible, &PartitionSelection::Include(vec!["maintenance".to_string()]));
+    let job_ids: Vec<Option<u32>> = visible.iter().map(|job| job.job_id.as_ref().map(|job_id| job_id.id)).collect();
+    assert_eq!(job_ids, vec![Some(1), Some(2), Some(3)]);
+}
+
+#[test]
+fn test_update_slurm_status_hidden_partitions() {
+    use command_runner::TestRunner;
+
+    let config = Configuration{ test_mode: true, partitions: PartitionSelection::Exclude(vec!["esd".to_string()]), .. Configuration::default() };
+    let mut status = SlurmStatus::new();
+    update_slurm_status(&mut status, &config, &TestRunner::new(Ok(String::new())), None);
+
+    assert!(status.node_info.is_empty());
+    assert!(status.job_info.is_empty());
+    assert_eq!(status.hidden_partitions, 1);
+
+    let page = status_to_html(&status, &PageOptions::default()).unwrap();
+    assert!(page.contains("<p class=\"filter_notice\">1 partition hidden by configuration</p>"));
+    assert!(status_to_text(&status, &StatusFilter::new()).contains("\n1 partition hidden by configuration\n"));
+    let json = Json::from_str(&status_to_json(&status, &StatusFilter::new())).unwrap();
+    assert_eq!(json.find("hidden_partitions").and_then(|val| val.as_u64()), Some(1));
+    assert_eq!(hidden_partitions_notice(3), "3 partitions hidden by configuration");
+}
+
+#[test]
+fn test_status_to_html_job_steps() {
+    use squeue_util::str_to_job_id;
+
+    let mut status = SlurmStatus::new();
+    status.job_info = get_job_info_test();
+    let mut step = status.job_info[0].clone();
+    step.job_id = str_to_job_id("1.batch");
+    status.job_info.push(step);
+    status.job_info[1].job_id = str_to_job_id("2_[0-99]");
+
+    let mut job_info = status.job_info.clone();
+    select_job_steps(&mut job_info, false);
+    assert_eq!(job_info.len(), 12);
+
+    select_job_steps(&mut status.job_info, true);
+    assert_eq!(status.job_info[1].job_id, str_to_job_id("1.batch"));
+
+    let page = status_to_html(&status, &PageOptions::default()).unwrap();
+    assert!(page.contains("<tr id=\"job-1.batch\" class=\"job_step\">"));
+    assert!(page.contains("<td class=\"job_step_id\">1.batch</td>"));
+    assert!(page.contains("<td>2_[0-99]</td>"));
+
+    let json = Json::from_str(&status_to_json(&status, &StatusFilter::new())).unwrap();
+    let jobs = json.find("job_info").and_then(|val| val.as_array()).unwrap();
+    assert_eq!(jobs[1].find("job_id").and_then(|val| val.as_string()), Some("1.batch"));
+    assert_eq!(jobs[2].find("job_id").and_then(|val| val.as_string()), Some("2_[0-99]"));
+}
+
+#[test]
+fn test_status_to_html_job_arrays() {
+    use squeue_util::str_to_job_id;
+
+    let mut status = SlurmStatus::new();
+    status.job_info = get_job_info_test();
+    for (index, job_id) in ["7_1", "7_2", "7_[3-9]"].iter().enumerate() {
+        status.job_info[index].job_id = str_to_job_id(job_id);
+        status.job_info[index].job_array_id = Some(7);
+    }
+
+    let page = status_to_html(&status, &PageOptions::default()).unwrap();
+    assert!(page.contains("<tr id=\"job-7\" class=\"job_array\">\n<td colspan=\"23\"><details><summary>Job array 7: small_test01 (user01), 3 tasks: CANCELLED 1, COMPLETED 1, RUNNING 1, run time 1:00 - 2:00</summary>"));
+    assert!(page.contains("<tr id=\"job-7_2\">"));
+    assert!(page.contains("<tr id=\"job-4\">"));
+
+    let page = status_to_html(&status, &PageOptions{ expand_arrays: true, .. PageOptions::default() }).unwrap();
+    assert!(!page.contains("job_array"));
+    assert!(page.contains("<tr id=\"job-7_[3-9]\">"));
+
+    let json = Json::from_str(&status_to_json(&status, &StatusFilter::new())).unwrap();
+    assert_eq!(json.find("job_info").and_then(|val| val.as_array()).map(|val| val.len()), Some(12));
+    let groups = json.find("job_groups").and_then(|val| val.as_array()).unwrap();
+    assert_eq!(groups.len(), 10);
+    assert_eq!(groups[0].find_path(&["array", "array_id"]).and_then(|val| val.as_u64()), Some(7));
+    assert_eq!(groups[0].find_path(&["array", "tasks"]).and_then(|val| val.as_array()).map(|val| val.len()), Some(3));
+    assert_eq!(groups[1].find_path(&["job", "job_id"]).and_then(|val| val.as_string()), Some("4"));
+}
+
+#[test]
+fn test_status_to_html_pagination() {
+    let mut status = SlurmStatus::new();
+    status.job_info = get_job_info_test();
+
+    let options = PageOptions{ pagination: Pagination::new(5, 3), query: "user=&limit=5&page=3".to_string(), .. PageOptions::default() };
+    let page = status_to_html(&status, &options).unwrap();
+    assert!(page.contains("<p class=\"pagination\"><a href=\"?user=&amp;limit=5&amp;page=2\">Previous</a> showing rows 11&ndash;12 of 12</p>"));
+    assert!(page.contains("<tr id=\"job-11\">"));
+    assert!(!page.contains("<tr id=\"job-10\">"));
+
+    let page = status_to_html(&status, &PageOptions::default()).unwrap();
+    assert!(!page.contains("class=\"pagination\""));
+    assert!(page.contains("<tr id=\"job-1\">"));
+
+    let json = Json::from_str(&status_to_json_paged(&status, &StatusFilter::new(), &Pagination::new(5, 2))).unwrap();
+    let jobs = json.find("job_info").and_then(|val| val.as_array()).unwrap();
+    assert_eq!(jobs.len(), 5);
+    assert_eq!(jobs[0].find("job_id").and_then(|val| val.as_string()), Some("6"));
+    assert_eq!(json.find_path(&["job_pages", "total"]).and_then(|val| val.as_u64()), Some(12));
+    assert_eq!(json.find_path(&["job_pages", "pages"]).and_then(|val| val.as_u64()), Some(3));
+}
+
+#[test]
+fn test_status_to_html_missing_values() {
+    let mut status = SlurmStatus::new();
+    status.job_info = get_job_info_test();
+    status.job_info.truncate(1);
+    status.job_info[0].executing_host = None;
+    status.job_info[0].start_time = None;
+    status.node_info = get_partition_node_info_test();
+    status.node_info.truncate(1);
+    status.node_info[0].hostname = None;
+
+    let page = status_to_html(&status, &PageOptions::default()).unwrap();
+    assert!(page.contains("<td>Up</td><td>-</td>"));
+    assert!(page.contains("<tr id=\"job-1\">\n<td>-</td>"));
+    assert!(page.contains("<td>None</td><td>-</td>"));
+    assert!(!page.contains("N/A"));
+
+    let json = Json::from_str(&status_to_json(&status, &StatusFilter::new())).unwrap();
+    let jobs = json.find("job_info").and_then(|val| val.as_array()).unwrap();
+    assert_eq!(jobs[0].find("start_time"), Some(&Json::Null));
 }
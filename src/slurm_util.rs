@@ -1,14 +1,82 @@
 //! Utility function used by slurm_inspector
 //! Just contains references to external and internal modules
 
-#[macro_use] extern crate clap;
+extern crate clap;
 #[macro_use] extern crate log;
 extern crate flexi_logger;
 extern crate iron;
+extern crate hyper;
+extern crate openssl;
 extern crate time;
+extern crate flate2;
+extern crate rustc_serialize;
+#[macro_use] extern crate rusqlite;
 
+pub mod command_runner;
+pub mod natural_sort;
 pub mod sinfo_util;
 pub mod squeue_util;
+pub mod sacct_util;
+pub mod sdiag_util;
+pub mod sshare_util;
+pub mod sprio_util;
+pub mod scontrol_util;
+pub mod sacctmgr_util;
+pub mod sreport_util;
 pub mod configuration;
 pub mod request_handler;
 pub mod slurm_status;
+pub mod compression;
+pub mod static_files;
+pub mod status_filter;
+pub mod anonymize;
+pub mod history;
+pub mod node_events;
+pub mod webhook;
+pub mod csv_export;
+pub mod hostlist;
+pub mod rendered_status;
+pub mod health;
+pub mod access_log;
+pub mod logging;
+pub mod inspector_metrics;
+pub mod placeholder;
+pub mod gres;
+pub mod job_arrays;
+pub mod pagination;
+pub mod template;
+pub mod status_page;
+pub mod columns;
+pub mod update_schedule;
+pub mod refresh;
+pub mod status_stream;
+pub mod cluster_info;
+pub mod wait_times;
+pub mod time_limit;
+pub mod request_limit;
+pub mod command_record;
+pub mod synthetic_data;
+pub mod build_info;
+pub mod user_page;
+pub mod partition_page;
+pub mod recent_jobs;
+pub mod admin_actions;
+pub mod prom_textfile;
+pub mod display_time;
+pub mod raw_output;
+pub mod backlog_chart;
+pub mod email_notify;
+pub mod security;
+pub mod history_db;
+pub mod top_jobs;
+pub mod unix_socket;
+pub mod tls;
+pub mod status_changes;
+pub mod occupancy_grid;
+pub mod job_dependencies;
+pub mod config_view;
+pub mod shared_status;
+pub mod scheduler_pressure;
+pub mod cors;
+pub mod error_log;
+pub mod job_sort;
@@ -0,0 +1,231 @@
+//! Abstraction for the SLURM sprio command
+//! Runs sprio, parses output into data structure (PriorityInfo) and joins it with the pending jobs of squeue,
+//! so the page can explain why another job starts first
+
+// System modules:
+use std::collections::BTreeMap;
+
+// External modules:
+use rustc_serialize::json::{Json, ToJson};
+
+// Internal modules:
+use command_runner::{CommandRunner, CommandConfig, run_command};
+use squeue_util::{JobInfo, JobState};
+
+/// Priority of a pending job and its weighted factors in one partition
+#[derive(Debug, Clone, PartialEq)]
+pub struct PriorityInfo {
+    pub job_id: u32,
+    pub partition: String,
+    pub priority: Option<u64>,
+    pub age: Option<u64>,
+    pub fair_share: Option<u64>,
+    pub job_size: Option<u64>,
+    /// Factor of the partition (PriorityWeightPartition), the name of the partition is in partition
+    pub partition_factor: Option<u64>,
+    pub qos: Option<u64>
+}
+
+impl PriorityInfo {
+    /// The factors as text for the tooltip of the priority, for example "Age: 1000, Fair-share: 500, Job size: 10, Partition: 1000, QOS: 0"
+    pub fn factors_text(&self) -> String {
+        let factor = |value: Option<u64>| value.map_or("-".to_string(), |value| value.to_string());
+        format!("Age: {}, Fair-share: {}, Job size: {}, Partition: {}, QOS: {}", factor(self.age), factor(self.fair_share),
+            factor(self.job_size), factor(self.partition_factor), factor(self.qos))
+    }
+}
+
+impl ToJson for PriorityInfo {
+    fn to_json(&self) -> Json {
+        let mut result = BTreeMap::new();
+        result.insert("job_id".to_string(), self.job_id.to_json());
+        result.insert("partition".to_string(), self.partition.to_json());
+        result.insert("priority".to_string(), self.priority.to_json());
+        result.insert("age".to_string(), self.age.to_json());
+        result.insert("fair_share".to_string(), self.fair_share.to_json());
+        result.insert("job_size".to_string(), self.job_size.to_json());
+        result.insert("partition_factor".to_string(), self.partition_factor.to_json());
+        result.insert("qos".to_string(), self.qos.to_json());
+        Json::Object(result)
+    }
+}
+
+/// Public helper function to generate test data, job 8 is the pending job of the squeue test data
+pub fn get_priority_info_test() -> Vec<PriorityInfo> {
+    let test_data = "
+        8|esd|11500|1000|500|10|10000|0
+        13|esd|10200|200|0|0|10000|0
+    ";
+
+    get_priority_info_util(test_data)
+}
+
+/// Public helper function to retrieve the current priorities of the pending jobs
+pub fn get_priority_info(runner: &dyn CommandRunner) -> Vec<PriorityInfo> {
+    get_priority_info_util(&call_sprio(runner))
+}
+
+// Private helper function to parse the output of "sprio" and return a list of PriorityInfo
+fn get_priority_info_util(sprio_output: &str) -> Vec<PriorityInfo> {
+    let mut result: Vec<PriorityInfo> = Vec::new();
+
+    for line in sprio_output.lines() {
+        let items: Vec<&str> = line.split('|').map(|item| item.trim()).collect();
+
+        // Skip invalid line
+        let job_id = match items.get(0).and_then(|job_id| job_id.parse::<u32>().ok()) {
+            Some(job_id) if items.len() == 8 => job_id,
+            _ => {
+                debug!("number of items in line: {}", items.len());
+                continue
+            }
+        };
+
+        result.push( PriorityInfo{
+                job_id: job_id,
+                partition: items[1].to_string(),
+                priority: items[2].parse::<u64>().ok(),
+                age: items[3].parse::<u64>().ok(),
+                fair_share: items[4].parse::<u64>().ok(),
+                job_size: items[5].parse::<u64>().ok(),
+                partition_factor: items[6].parse::<u64>().ok(),
+                qos: items[7].parse::<u64>().ok()
+        })
+    }
+
+    result
+}
+
+/// Public helper function that indexes the priorities by job id, a job pending in several partitions has one entry per partition
+pub fn priorities_by_job(priorities: &[PriorityInfo]) -> BTreeMap<u32, Vec<&PriorityInfo>> {
+    let mut result: BTreeMap<u32, Vec<&PriorityInfo>> = BTreeMap::new();
+    for priority in priorities {
+        result.entry(priority.job_id).or_insert_with(Vec::new).push(priority);
+    }
+    result
+}
+
+/// Public helper function that returns the priority of a pending job, None for the other jobs and for jobs missing in sprio
+/// sprio only runs on the local cluster, so jobs of other clusters and job steps have none.
+/// If the job is pending in several partitions, the entry of its first partition is used
+pub fn job_priority<'a>(job: &JobInfo, index: &BTreeMap<u32, Vec<&'a PriorityInfo>>) -> Option<&'a PriorityInfo> {
+    let job_id = match job.job_id {
+        Some(ref job_id) if job.job_state == JobState::Pending && job.cluster.is_empty() && !job_id.is_step() => job_id,
+        _ => return None
+    };
+    let entries = index.get(&job_id.id)?;
+    let partition = job.partition.split(',').next().unwrap_or("");
+
+    entries.iter().find(|entry| entry.partition == partition).or_else(|| entries.first()).cloned()
+}
+
+/// Public helper function that joins the pending jobs with their priorities, jobs without priority and priorities without job are left out
+pub fn join_priorities<'a, 'b>(jobs: &'a [JobInfo], priorities: &'b [PriorityInfo]) -> Vec<(&'a JobInfo, &'b PriorityInfo)> {
+    let index = priorities_by_job(priorities);
+    jobs.iter().filter_map(|job| job_priority(job, &index).map(|priority| (job, priority))).collect()
+}
+
+#[test]
+fn test_get_priority_info_util_empty() {
+    assert_eq!(get_priority_info_util("").len(), 0);
+}
+
+#[test]
+fn test_get_priority_info_util_invalid() {
+    assert_eq!(get_priority_info_util("1|2|3").len(), 0);
+    assert_eq!(get_priority_info_util("JOBID|PARTITION|PRIORITY|AGE|FAIRSHARE|JOBSIZE|PARTITION|QOS").len(), 0);
+    assert_eq!(get_priority_info_util("|esd|11500|1000|500|10|10000|0").len(), 0);
+}
+
+#[test]
+fn test_get_priority_info_util() {
+    let input = "     8|esd     |     11500|      1000|       500|        10|     10000|         0\n  13|gpu|10200|200||0|10000|0";
+    let output = vec![
+        PriorityInfo{
+            job_id: 8,
+            partition: "esd".to_string(),
+            priority: Some(11500),
+            age: Some(1000),
+            fair_share: Some(500),
+            job_size: Some(10),
+            partition_factor: Some(10000),
+            qos: Some(0)
+        },
+        PriorityInfo{
+            job_id: 13,
+            partition: "gpu".to_string(),
+            priority: Some(10200),
+            age: Some(200),
+            fair_share: None,
+            job_size: Some(0),
+            partition_factor: Some(10000),
+            qos: Some(0)
+        }
+    ];
+
+    assert_eq!(get_priority_info_util(input), output);
+}
+
+#[test]
+fn test_priority_info_factors_text() {
+    let priorities = get_priority_info_test();
+    assert_eq!(priorities[0].factors_text(), "Age: 1000, Fair-share: 500, Job size: 10, Partition: 10000, QOS: 0");
+    assert_eq!(PriorityInfo{ fair_share: None, .. priorities[1].clone() }.factors_text(), "Age: 200, Fair-share: -, Job size: 0, Partition: 10000, QOS: 0");
+}
+
+#[test]
+fn test_join_priorities() {
+    use squeue_util::test_job;
+
+    let priorities = get_priority_info_util("8|esd|11500|1000|500|10|10000|0\n20|esd|300|300|0|0|0|0\n21|gpu|500|0|0|0|500|0\n21|esd|100|0|0|0|100|0\n25|esd|50|50|0|0|0|0");
+    // The test job is in the partition esd
+    let pending_job = |job_id: &str, partition: &str| JobInfo { partition: partition.to_string(), .. test_job(job_id, JobState::Pending) };
+    let jobs = vec![
+        test_job("8", JobState::Pending),
+        // Pending in squeue, but not in sprio
+        test_job("7", JobState::Pending),
+        // In sprio, but not pending (the priorities are from another time than the jobs)
+        test_job("9", JobState::Running),
+        test_job("20.batch", JobState::Pending),
+        JobInfo { cluster: "cluster2".to_string(), .. test_job("20", JobState::Pending) },
+        // Pending in two partitions, the first partition of squeue is used
+        pending_job("21", "esd,gpu"),
+        // Pending array tasks have the id of the array
+        test_job("25_[1-10]", JobState::Pending)
+    ];
+
+    let joined = join_priorities(&jobs, &priorities);
+    assert_eq!(joined.iter().map(|&(job, priority)| (job.job_id.as_ref().unwrap().to_string(), priority.job_id, &priority.partition[..], priority.priority))
+        .collect::<Vec<_>>(), vec![("8".to_string(), 8, "esd", Some(11500)), ("21".to_string(), 21, "esd", Some(100)), ("25_[1-10]".to_string(), 25, "esd", Some(50))]);
+
+    // A partition that is not in sprio falls back to the first entry
+    let index = priorities_by_job(&priorities);
+    assert_eq!(job_priority(&pending_job("21", "long"), &index).map(|priority| &priority.partition[..]), Some("gpu"));
+    assert_eq!(join_priorities(&jobs, &[]).len(), 0);
+    assert_eq!(join_priorities(&[], &priorities).len(), 0);
+}
+
+/*
+    sprio -n -o '%i|%r|%Y|%A|%F|%J|%P|%Q'
+    -n: no header
+    -o: output format
+    %i: Job ID
+    %r: Partition name, a job pending in several partitions has one line per partition
+    %Y: Priority of the job (weighted sum of the factors)
+    %A: Weighted age factor
+    %F: Weighted fair-share factor
+    %J: Weighted job size factor
+    %P: Weighted partition factor
+    %Q: Weighted QOS factor
+
+    Output looks like:
+         8|esd     |     11500|      1000|       500|        10|     10000|         0
+*/
+
+// Private helper function to execute the external "sprio" SLURM command and return its output into a string
+// On error returns an empty string, the jobs are shown without priority factors
+fn call_sprio(runner: &dyn CommandRunner) -> String {
+    // return empty string on error (run_command logs it), but continue with the program
+    run_command(runner, &CommandConfig::new("sprio", &[]), &["-n", "-o", "%i|%r|%Y|%A|%F|%J|%P|%Q"])
+        .unwrap_or(String::new())
+}
@@ -2,10 +2,27 @@
 //! Runs squeue, parses output into data structure (JobInfo)
 
 // System modules:
-use std::process::Command;
+use std::collections::BTreeMap;
+use std::fmt;
+
+// External modules:
+use rustc_serialize::json::{Json, ToJson};
+
+// Internal modules:
+use command_runner::{CommandConfig, CommandRunner, run_command};
+use hostlist::expand_hostlist;
+use inspector_metrics::{ParseResult, SkippedWarnings, is_data_line};
+use placeholder::{is_placeholder, optional_string};
+use gres::{GresInfo, parse_gres};
+use synthetic_data::{SyntheticData, synthetic_squeue_output};
+use job_dependencies::{Dependency, parse_dependencies};
+
+/// Job states listed by squeue (--states), default of --squeue-states
+/// Without it squeue only lists the pending and running jobs, so the finished ones would never show up
+pub const DEFAULT_SQUEUE_STATES: &'static str = "all";
 
 /// State reason, why is the job in the current state ?
-#[derive(Debug, PartialEq)]
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
 pub enum StateReason {
         Dependency,
         None,
@@ -21,126 +38,549 @@ pub enum StateReason {
         NonZeroExitCode,
         TimeLimit,
         InactiveLimit,
+        /// The QOS limits of the user are reached (sacctmgr show qos, MaxTRESPU, MaxJobsPU, MaxSubmitPU and MaxWall)
+        QOSMaxCpuPerUserLimit,
+        QOSMaxNodePerUserLimit,
+        QOSMaxMemoryPerUser,
+        QOSMaxGRESPerUser,
+        QOSMaxJobsPerUserLimit,
+        QOSMaxSubmitJobPerUserLimit,
+        QOSMaxWallDurationPerJobLimit,
         Unknown
 }
 
 /// In which state is the current job in ?
-#[derive(Debug, PartialEq)]
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
 pub enum JobState {
+        /// Terminated because one of the allocated nodes failed to boot
+        BootFail,
         Cancelled,
         Completed,
         Configuring,
         Completing,
+        /// Terminated on reaching its deadline
+        Deadline,
         Failed,
         NodeFail,
+        /// Killed because it ran out of memory
+        OutOfMemory,
         Pending,
         Preempted,
+        Requeued,
+        Resizing,
+        /// Sibling job of a federated job that was removed
+        Revoked,
         Running,
+        /// Requeued in a special state by the SPECIAL_EXIT exit code
+        SpecialExit,
+        /// Stopped by SIGSTOP, but still holds its resources
+        Stopped,
         Suspended,
         Timeout,
         Unknown
 }
 
+impl JobState {
+    /// Returns true if the job has ended and won't run again, these jobs are hidden from the job table by default
+    /// (squeue only lists them for MinJobAge seconds after they ended)
+    pub fn is_finished(&self) -> bool {
+        match *self {
+            JobState::BootFail | JobState::Cancelled | JobState::Completed | JobState::Deadline | JobState::Failed |
+            JobState::NodeFail | JobState::OutOfMemory | JobState::Preempted | JobState::Revoked | JobState::Timeout => true,
+            _ => false
+        }
+    }
+}
+
+/// Job ID as printed by squeue: "1234", array tasks "1234_5" or "1234_[0-99]" (pending) and job steps "1234.batch" or "1234_5.0"
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct JobId {
+    /// The numeric base ID
+    pub id: u32,
+    /// Index of the array task, or the range of the pending tasks, for example "5" or "[0-99]"
+    pub array_index: Option<String>,
+    /// Name of the job step, for example "batch" or "0"
+    pub step: Option<String>
+}
+
+impl JobId {
+    /// Create the ID of a plain job
+    pub fn new(id: u32) -> JobId {
+        JobId {
+            id: id,
+            array_index: None,
+            step: None
+        }
+    }
+
+    /// Returns true if this is a job step and not a job
+    pub fn is_step(&self) -> bool {
+        self.step.is_some()
+    }
+
+    /// Returns the ID of the job this step belongs to, for a job the ID itself
+    pub fn parent(&self) -> JobId {
+        JobId { step: None, .. self.clone() }
+    }
+}
+
+impl fmt::Display for JobId {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.id)?;
+        if let Some(ref array_index) = self.array_index {
+            write!(f, "_{}", array_index)?;
+        }
+        if let Some(ref step) = self.step {
+            write!(f, ".{}", step)?;
+        }
+        Ok(())
+    }
+}
+
+impl ToJson for JobId {
+    fn to_json(&self) -> Json {
+        Json::String(self.to_string())
+    }
+}
+
 /// All the information about a SLURM job
-#[derive(Debug, PartialEq)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct JobInfo {
-    pub executing_host: String,
+    /// Name of the cluster, empty if only the local cluster is monitored
+    pub cluster: String,
+    /// None if squeue printed a placeholder like "N/A", also for run_time and start_time
+    pub executing_host: Option<String>,
     pub minimum_cpu: Option<u32>,
     pub num_cpu: Option<u32>,
     pub num_nodes: Option<u32>,
     pub job_array_id: Option<u32>,
     pub num_sockets: Option<u32>,
-    pub job_id: Option<u32>,
+    /// None if squeue printed something that is not a job ID
+    pub job_id: Option<JobId>,
     pub num_cores: Option<u32>,
     pub job_name: String,
     pub num_threads: Option<u32>,
     pub job_array_index: Option<u32>,
-    pub run_time: String,
+    pub run_time: Option<String>,
+    /// For example "1-00:00:00" or "UNLIMITED", None if squeue printed a placeholder
+    pub time_limit: Option<String>,
     pub list_of_nodes: Vec<String>,
     pub priority: Option<f64>,
     pub state_reason: StateReason,
-    pub start_time: String,
+    pub start_time: Option<String>,
     pub job_state: JobState,
+    /// User ID of the user who cancelled the job, squeue prints it after the state: "CANCELLED by 1000"
+    pub cancelled_by: Option<u32>,
     pub user_name: String,
-    pub user_id: Option<u32>
+    pub user_id: Option<u32>,
+    /// Partitions the job runs in (or was submitted to, if it's pending), comma separated
+    pub partition: String,
+    /// Generic resources like GPUs the job requested per node, empty if squeue printed "N/A"
+    pub gres: Vec<GresInfo>,
+    pub submit_time: Option<String>,
+    /// Jobs this job waits for, empty if it has no dependencies
+    pub dependencies: Vec<Dependency>,
+    /// Seconds since the submission for pending jobs, computed after each update (see wait_times)
+    pub wait_time: Option<u64>
+}
+
+impl StateReason {
+    /// The spelling of squeue (%r), str_to_state_reason parses it back
+    pub fn as_slurm_str(&self) -> &'static str {
+        match *self {
+            StateReason::Dependency => "Dependency",
+            StateReason::None => "None",
+            StateReason::PartitionDown => "PartitionDown",
+            StateReason::PartitionNodeLimit => "PartitionNodeLimit",
+            StateReason::PartitionTimeLimit => "PartitionTimeLimit",
+            StateReason::Priority => "Priority",
+            StateReason::Resources => "Resources",
+            StateReason::NodeDown => "NodeDown",
+            StateReason::BadConstraints => "BadConstraints",
+            StateReason::SystemFailure => "SystemFailure",
+            StateReason::JobLaunchFailure => "JobLaunchFailure",
+            StateReason::NonZeroExitCode => "NonZeroExitCode",
+            StateReason::TimeLimit => "TimeLimit",
+            StateReason::InactiveLimit => "InactiveLimit",
+            StateReason::QOSMaxCpuPerUserLimit => "QOSMaxCpuPerUserLimit",
+            StateReason::QOSMaxNodePerUserLimit => "QOSMaxNodePerUserLimit",
+            StateReason::QOSMaxMemoryPerUser => "QOSMaxMemoryPerUser",
+            StateReason::QOSMaxGRESPerUser => "QOSMaxGRESPerUser",
+            StateReason::QOSMaxJobsPerUserLimit => "QOSMaxJobsPerUserLimit",
+            StateReason::QOSMaxSubmitJobPerUserLimit => "QOSMaxSubmitJobPerUserLimit",
+            StateReason::QOSMaxWallDurationPerJobLimit => "QOSMaxWallDurationPerJobLimit",
+            StateReason::Unknown => "Unknown"
+        }
+    }
+}
+
+impl fmt::Display for StateReason {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.as_slurm_str())
+    }
+}
+
+impl JobState {
+    /// The long form of the state as printed by squeue (%T) and sacct, str_to_job_state parses it back
+    pub fn as_slurm_str(&self) -> &'static str {
+        match *self {
+            JobState::BootFail => "BOOT_FAIL",
+            JobState::Cancelled => "CANCELLED",
+            JobState::Completed => "COMPLETED",
+            JobState::Configuring => "CONFIGURING",
+            JobState::Completing => "COMPLETING",
+            JobState::Deadline => "DEADLINE",
+            JobState::Failed => "FAILED",
+            JobState::NodeFail => "NODE_FAIL",
+            JobState::OutOfMemory => "OUT_OF_MEMORY",
+            JobState::Pending => "PENDING",
+            JobState::Preempted => "PREEMPTED",
+            JobState::Requeued => "REQUEUED",
+            JobState::Resizing => "RESIZING",
+            JobState::Revoked => "REVOKED",
+            JobState::Running => "RUNNING",
+            JobState::SpecialExit => "SPECIAL_EXIT",
+            JobState::Stopped => "STOPPED",
+            JobState::Suspended => "SUSPENDED",
+            JobState::Timeout => "TIMEOUT",
+            JobState::Unknown => "UNKNOWN"
+        }
+    }
+}
+
+impl fmt::Display for JobState {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.as_slurm_str())
+    }
+}
+
+// The JSON keeps the variant names, the API and the stored history use them
+impl ToJson for StateReason {
+    fn to_json(&self) -> Json {
+        Json::String(format!("{:?}", self))
+    }
+}
+
+impl ToJson for JobState {
+    fn to_json(&self) -> Json {
+        Json::String(format!("{:?}", self))
+    }
+}
+
+impl ToJson for JobInfo {
+    fn to_json(&self) -> Json {
+        let mut result = BTreeMap::new();
+        result.insert("cluster".to_string(), self.cluster.to_json());
+        result.insert("executing_host".to_string(), self.executing_host.to_json());
+        result.insert("minimum_cpu".to_string(), self.minimum_cpu.to_json());
+        result.insert("num_cpu".to_string(), self.num_cpu.to_json());
+        result.insert("num_nodes".to_string(), self.num_nodes.to_json());
+        result.insert("job_array_id".to_string(), self.job_array_id.to_json());
+        result.insert("num_sockets".to_string(), self.num_sockets.to_json());
+        result.insert("job_id".to_string(), self.job_id.to_json());
+        result.insert("num_cores".to_string(), self.num_cores.to_json());
+        result.insert("job_name".to_string(), self.job_name.to_json());
+        result.insert("num_threads".to_string(), self.num_threads.to_json());
+        result.insert("job_array_index".to_string(), self.job_array_index.to_json());
+        result.insert("run_time".to_string(), self.run_time.to_json());
+        result.insert("time_limit".to_string(), self.time_limit.to_json());
+        result.insert("list_of_nodes".to_string(), self.list_of_nodes.to_json());
+        result.insert("priority".to_string(), self.priority.to_json());
+        result.insert("state_reason".to_string(), self.state_reason.to_json());
+        result.insert("start_time".to_string(), self.start_time.to_json());
+        result.insert("job_state".to_string(), self.job_state.to_json());
+        result.insert("cancelled_by".to_string(), self.cancelled_by.to_json());
+        result.insert("user_name".to_string(), self.user_name.to_json());
+        result.insert("user_id".to_string(), self.user_id.to_json());
+        result.insert("partition".to_string(), self.partition.to_json());
+        result.insert("gres".to_string(), self.gres.to_json());
+        result.insert("submit_time".to_string(), self.submit_time.to_json());
+        result.insert("dependencies".to_string(), self.dependencies.to_json());
+        result.insert("wait_time".to_string(), self.wait_time.to_json());
+        Json::Object(result)
+    }
 }
 
 /// Public helper function to generate test data
 pub fn get_job_info_test() -> Vec<JobInfo> {
     let test_data = "
-        node01 1 2 1 N/A * 1 * small_test01 * N/A 1:00 node01 0.9 None 2000-01-01T09:00:00 RUNNING user01 1000
-        node01 1 2 2 N/A * 2 * small_test02 * N/A 1:15 node01,node02 0.9 None 2000-01-01T09:00:00 cancelled user02 1001
-        node01 1 2 4 N/A * 3 * small_test03 * N/A 2:00 node01 0.1 None 2000-01-01T09:00:00 completed user03 1002
-        node02 1 2 1 N/A * 4 * small_test04 * N/A 2:00 node01 0.2 None 2000-01-01T09:00:00 configuring user04 1003
-        node03 1 2 1 N/A * 5 * small_test05 * N/A 2:46 node01 0.9 None 2000-01-01T09:00:00 Completing user05 1004
-        node04 1 2 6 N/A * 6 * small_test06 * N/A 3:12 node03,node04,node05 0.9 None 2000-01-01T09:00:00 FAILED user05 1004
-        node05 1 2 1 N/A * 7 * small_test07 * N/A 4:02 node01 0.9 None 2000-01-01T09:00:00 nodefail user01 1000
-        node06 1 2 1 N/A * 8 * small_test08 * N/A 5:00 node01 0.9 None 2000-01-01T09:00:00 Pending user02 1001
-        node07 1 2 2 N/A * 9 * small_test09 * N/A 1:00 node01 0.5 None 2000-01-01T09:00:00 preempted user02 1001
-        node08 1 2 2 N/A * 10 * small_test10 * N/A 2:01 node01 0.6 None 2000-01-01T09:00:00 suspended user03 1002
-        node08 1 2 10 N/A * 11 * small_test11 * N/A 2:06 node01 0.9 None 2000-01-01T09:00:00 timeout user04 1003
-        node08 1 2 6 N/A * 12 * small_test12 * N/A 4:09 node01 0.2 None 2000-01-01T09:00:00 UNKNOWN user05 1004
+        node01 1 2 1 N/A * 1 * small_test01 * N/A 1:00 node01 0.9 None 2000-01-01T09:00:00 RUNNING user01 1000 esd gres:gpu:a100:2 2000-01-01T08:00:00 2:00 (null)
+        node01 1 2 2 N/A * 2 * small_test02 * N/A 1:15 node01,node02 0.9 None 2000-01-01T09:00:00 cancelled user02 1001 esd N/A 2000-01-01T08:00:00 1:00:00 (null)
+        node01 1 2 4 N/A * 3 * small_test03 * N/A 2:00 node01 0.1 None 2000-01-01T09:00:00 completed user03 1002 esd N/A 2000-01-01T08:00:00 1:00:00 (null)
+        node02 1 2 1 N/A * 4 * small_test04 * N/A 2:00 node01 0.2 None 2000-01-01T09:00:00 configuring user04 1003 esd N/A 2000-01-01T08:00:00 1:00:00 (null)
+        node03 1 2 1 N/A * 5 * small_test05 * N/A 2:46 node01 0.9 None 2000-01-01T09:00:00 Completing user05 1004 esd N/A 2000-01-01T08:00:00 1:00:00 (null)
+        node04 1 2 6 N/A * 6 * small_test06 * N/A 3:12 node03,node04,node05 0.9 None 2000-01-01T09:00:00 FAILED user05 1004 esd N/A 2000-01-01T08:00:00 1:00:00 (null)
+        node05 1 2 1 N/A * 7 * small_test07 * N/A 4:02 node01 0.9 None 2000-01-01T09:00:00 nodefail user01 1000 esd N/A 2000-01-01T08:00:00 1:00:00 (null)
+        node06 1 2 1 N/A * 8 * small_test08 * N/A 5:00 node01 0.9 Dependency 2000-01-01T09:00:00 Pending user02 1001 esd N/A 2000-01-01T07:45:12 1:00:00 afterok:6(unfulfilled)
+        node07 1 2 2 N/A * 9 * small_test09 * N/A 1:00 node01 0.5 None 2000-01-01T09:00:00 preempted user02 1001 esd N/A 2000-01-01T08:00:00 1:00:00 (null)
+        node08 1 2 2 N/A * 10 * small_test10 * N/A 2:01 node01 0.6 None 2000-01-01T09:00:00 suspended user03 1002 esd N/A 2000-01-01T08:00:00 1:00:00 (null)
+        node08 1 2 10 N/A * 11 * small_test11 * N/A 2:06 node01 0.9 None 2000-01-01T09:00:00 timeout user04 1003 esd N/A 2000-01-01T08:00:00 1:00:00 (null)
+        node08 1 2 6 N/A * 12 * small_test12 * N/A 4:09 node01 0.2 None 2000-01-01T09:00:00 UNKNOWN user05 1004 esd N/A 2000-01-01T08:00:00 1:00:00 (null)
     ";
 
-    get_job_info_util(test_data)
+    get_job_info_util(test_data, &mut SkippedWarnings::new()).items
+}
+
+/// Public helper function for the tests: the first job of the test data with another ID (for example "42", "20_1" or "11.batch")
+/// and state, other fields are set with the struct update syntax
+#[cfg(test)]
+pub fn test_job(job_id: &str, job_state: JobState) -> JobInfo {
+    JobInfo {
+        job_id: Some(str_to_job_id(job_id).expect("invalid job id of a test job")),
+        job_state: job_state,
+        .. get_job_info_test()[0].clone()
+    }
+}
+
+/// Public helper function to get the jobs of a synthetic cluster of the given size (--test-jobs)
+pub fn get_job_info_synthetic(data: &SyntheticData) -> Vec<JobInfo> {
+    get_job_info_util(&synthetic_squeue_output(data), &mut SkippedWarnings::new()).items
 }
 
 /// Public helper function to retrieve the current list of jobs and their states
-pub fn get_job_info() -> Vec<JobInfo> {
-    get_job_info_util(&call_squeue())
+/// With a list of clusters squeue is called once per cluster (-M) and the results are merged
+/// states is passed to squeue as --states (see --squeue-states), None lists the jobs squeue shows by default
+/// Returns an error if squeue could not be executed, otherwise the jobs and the number of lines that could not be parsed
+/// The lines that could not be parsed are logged, rate limited by warnings (see InspectorMetrics)
+pub fn get_job_info(runner: &dyn CommandRunner, command: &CommandConfig, clusters: &[String], states: Option<&str>, warnings: &mut SkippedWarnings)
+    -> Result<ParseResult<JobInfo>, String> {
+    if clusters.is_empty() {
+        return call_squeue(runner, command, states).map(|output| get_job_info_util(&output, warnings));
+    }
+
+    let mut result = ParseResult::new();
+
+    for cluster in clusters {
+        let cluster_command = CommandConfig::new(&command.path, &command.build_args(&["-M", cluster]));
+        let mut job_info = get_job_info_util(&call_squeue(runner, &cluster_command, states)?, warnings);
+
+        for job in job_info.items.iter_mut() {
+            job.cluster = cluster.clone();
+        }
+        result.append(&mut job_info);
+    }
+
+    Ok(result)
 }
 
 // Private helper function to parse the output of "squeue" and return a list of JobInfo
-fn get_job_info_util(squeue_output: &str) -> Vec<JobInfo> {
-    let mut result: Vec<JobInfo> = Vec::new();
+// Invalid lines are skipped and counted
+fn get_job_info_util(squeue_output: &str, warnings: &mut SkippedWarnings) -> ParseResult<JobInfo> {
+    let mut result = ParseResult::new();
+
+    for line in squeue_output.lines().filter(|line| is_data_line(line)) {
+        let mut items: Vec<&str> = line.split_whitespace().collect();
+        result.total += 1;
 
-    for line in squeue_output.lines() {
-        let items: Vec<&str> = line.split_whitespace().collect();
+        // A cancelled job has the user who cancelled it after the state ("CANCELLED by 1000"), these two items are taken out
+        let cancelled_by = if items.len() == 26 && items[17].to_lowercase() == "by" {
+            let user_id = items[18].parse::<u32>().ok();
+            items.drain(17..19);
+            user_id
+        } else {
+            None
+        };
 
         // Skip invalid line
-        if items.len() != 19 {
-            debug!("number of items in line: {}", items.len());
+        if items.len() != 24 {
+            warnings.warn_skipped_line("squeue", "24", items.len(), line);
+            result.skipped += 1;
             continue
         }
 
-        result.push( JobInfo{
-                executing_host: items[0].to_string(),
+        result.items.push( JobInfo{
+                cluster: String::new(),
+                executing_host: optional_string(items[0]),
                 minimum_cpu: items[1].parse::<u32>().ok(),
                 num_cpu: items[2].parse::<u32>().ok(),
                 num_nodes: items[3].parse::<u32>().ok(),
                 job_array_id: items[4].parse::<u32>().ok(),
                 num_sockets: items[5].parse::<u32>().ok(),
-                job_id: items[6].parse::<u32>().ok(),
+                job_id: str_to_job_id(items[6]),
                 num_cores: items[7].parse::<u32>().ok(),
                 job_name: items[8].to_string(),
                 num_threads: items[9].parse::<u32>().ok(),
                 job_array_index: items[10].parse::<u32>().ok(),
-                run_time: items[11].to_string(),
+                run_time: optional_string(items[11]),
+                time_limit: optional_string(items[22]),
                 list_of_nodes: str_to_list_of_nodes(items[12]),
                 priority: items[13].parse::<f64>().ok(),
                 state_reason: str_to_state_reason(items[14]),
-                start_time: items[15].to_string(),
+                start_time: optional_string(items[15]),
                 job_state: str_to_job_state(items[16]),
+                cancelled_by: cancelled_by,
                 user_name: items[17].to_string(),
-                user_id: items[18].parse::<u32>().ok()
+                user_id: items[18].parse::<u32>().ok(),
+                partition: items[19].to_string(),
+                gres: parse_gres(items[20]),
+                submit_time: optional_string(items[21]),
+                dependencies: parse_dependencies(items[23]),
+                wait_time: None
         })
     }
 
     result
 }
 
+#[test]
+fn test_job_state_is_finished() {
+    assert!(JobState::Completed.is_finished());
+    assert!(JobState::Cancelled.is_finished());
+    assert!(JobState::Failed.is_finished());
+    assert!(JobState::Timeout.is_finished());
+    assert!(JobState::OutOfMemory.is_finished());
+    // Still holds resources or may run again
+    assert!(!JobState::Completing.is_finished());
+    assert!(!JobState::Running.is_finished());
+    assert!(!JobState::Pending.is_finished());
+    assert!(!JobState::Requeued.is_finished());
+    assert!(!JobState::Suspended.is_finished());
+    assert!(!JobState::Unknown.is_finished());
+}
+
 #[test]
 fn test_get_job_info_util_empty() {
-    assert_eq!(get_job_info_util("").len(), 0);
+    assert_eq!(get_job_info_util("", &mut SkippedWarnings::new()), ParseResult{ items: vec![], skipped: 0, total: 0 });
 }
 
 #[test]
 fn test_get_job_info_util_invalid() {
-    assert_eq!(get_job_info_util("1 2 3 4").len(), 0);
+    let result = get_job_info_util("1 2 3 4\n\n1 2 3 4 5", &mut SkippedWarnings::new());
+    assert_eq!((result.items.len(), result.skipped, result.total), (0, 2, 2));
+
+    // Valid and invalid lines mixed, for example after an upgrade of SLURM that changed the output of some jobs
+    let valid = "node01 1 2 1 N/A * 1 * small_test01 * N/A 1:00 node01 0.9 None 2000-01-01T09:00:00 RUNNING user01 1000 esd gres:gpu:a100:2 2000-01-01T08:00:00 2:00 (null)";
+    let result = get_job_info_util(&format!("{}\n{} extra_field\nCLUSTER: cluster2\n{}\n", valid, valid, valid), &mut SkippedWarnings::new());
+    assert_eq!((result.items.len(), result.skipped, result.total), (2, 1, 3));
+}
+
+#[test]
+fn test_get_job_info_util_01() {
+    let input = "node01 1 2 2 N/A * 42 * sim * N/A 1:15 node01,node02 0.9 Resources 2000-01-01T09:00:00 PENDING willi 1000 batch,long N/A 2000-01-01T08:30:00 1-00:00:00 (null)";
+    let output = vec![JobInfo{
+        cluster: String::new(),
+        executing_host: Some("node01".to_string()),
+        minimum_cpu: Some(1),
+        num_cpu: Some(2),
+        num_nodes: Some(2),
+        job_array_id: None,
+        num_sockets: None,
+        job_id: Some(JobId::new(42)),
+        num_cores: None,
+        job_name: "sim".to_string(),
+        num_threads: None,
+        job_array_index: None,
+        run_time: Some("1:15".to_string()),
+        time_limit: Some("1-00:00:00".to_string()),
+        list_of_nodes: vec!["node01".to_string(), "node02".to_string()],
+        priority: Some(0.9),
+        state_reason: StateReason::Resources,
+        start_time: Some("2000-01-01T09:00:00".to_string()),
+        job_state: JobState::Pending,
+        cancelled_by: None,
+        user_name: "willi".to_string(),
+        user_id: Some(1000),
+        partition: "batch,long".to_string(),
+        gres: Vec::new(),
+        submit_time: Some("2000-01-01T08:30:00".to_string()),
+        dependencies: Vec::new(),
+        wait_time: None
+    }];
+
+    assert_eq!(get_job_info_util(input, &mut SkippedWarnings::new()), ParseResult{ items: output, skipped: 0, total: 1 });
+}
+
+#[test]
+fn test_get_job_info_util_placeholders() {
+    use placeholder::PLACEHOLDERS;
+
+    let line = "node01 1 2 2 3 2 42 4 sim 2 5 1:15 node01,node02 0.9 Resources 2000-01-01T09:00:00 PENDING willi 1000 batch N/A 2000-01-01T08:30:00 1-00:00:00 afterok:41";
+    // Position in the line and name in the JSON of the fields that are missing if squeue prints a placeholder
+    let fields = [(0, "executing_host"), (1, "minimum_cpu"), (2, "num_cpu"), (3, "num_nodes"), (4, "job_array_id"), (5, "num_sockets"),
+        (6, "job_id"), (7, "num_cores"), (9, "num_threads"), (10, "job_array_index"), (11, "run_time"), (13, "priority"),
+        (15, "start_time"), (18, "user_id"), (21, "submit_time"), (22, "time_limit")];
+
+    for placeholder in &PLACEHOLDERS {
+        for &(position, field) in &fields {
+            let mut items: Vec<&str> = line.split_whitespace().collect();
+            items[position] = placeholder;
+            let result = get_job_info_util(&items.join(" "), &mut SkippedWarnings::new());
+
+            assert_eq!(result.items.len(), 1);
+            assert_eq!(result.items[0].to_json().find(field), Some(&Json::Null), "{} as {}", placeholder, field);
+        }
+
+        let mut items: Vec<&str> = line.split_whitespace().collect();
+        items[12] = placeholder;
+        items[14] = placeholder;
+        items[23] = placeholder;
+        let job = get_job_info_util(&items.join(" "), &mut SkippedWarnings::new()).items.remove(0);
+        assert_eq!(job.list_of_nodes, Vec::<String>::new());
+        assert_eq!(job.state_reason, StateReason::None);
+        assert_eq!(job.dependencies, Vec::new());
+    }
+}
+
+#[test]
+fn test_get_job_info_util_cancelled_by() {
+    let output = "\
+        node17 1 16 1 N/A * 4711 * relax * N/A 12:03 node17 0.00001192092896 None 2021-03-04T10:11:12 CANCELLED by 1003 anna 1003 batch N/A 2021-03-04T10:00:01 2-00:00:00 (null)
+        node18 1 4 1 N/A * 4712 * relax * N/A 0:00 (null) 0.00001192092896 None 2021-03-04T10:11:12 CANCELLED by 0 anna 1003 batch N/A 2021-03-04T10:00:02 2-00:00:00 (null)
+        node19 1 4 1 N/A * 4713 * relax * N/A 1:02:03 node19 0.00001192092896 None 2021-03-04T09:11:12 COMPLETED anna 1003 batch N/A 2021-03-04T09:00:03 2-00:00:00 (null)
+        node20 1 4 1 N/A * 4714 * relax * N/A 0:00 n/a 0.00001192092896 BeginTime 2021-03-04T12:00:00 REQUEUED anna 1003 batch N/A 2021-03-04T09:00:04 2-00:00:00 (null)";
+    let result = get_job_info_util(output, &mut SkippedWarnings::new());
+
+    assert_eq!((result.items.len(), result.skipped, result.total), (4, 0, 4));
+    let states: Vec<(JobState, Option<u32>)> = result.items.iter().map(|job| (job.job_state, job.cancelled_by)).collect();
+    assert_eq!(states, vec![(JobState::Cancelled, Some(1003)), (JobState::Cancelled, Some(0)), (JobState::Completed, None),
+        (JobState::Requeued, None)]);
+    // The fields after the state are at the right place
+    assert_eq!(result.items[0].user_name, "anna");
+    assert_eq!(result.items[0].user_id, Some(1003));
+    assert_eq!(result.items[0].partition, "batch");
+    assert_eq!(result.items[0].time_limit, Some("2-00:00:00".to_string()));
+    assert_eq!(result.items[0].to_json().find("cancelled_by"), Some(&Json::U64(1003)));
+    assert_eq!(result.items[2].to_json().find("cancelled_by"), Some(&Json::Null));
+}
+
+#[test]
+fn test_get_job_info_util_dependencies() {
+    use job_dependencies::DependencyKind;
+
+    let jobs = get_job_info_test();
+    assert_eq!(jobs[7].dependencies, vec![Dependency { kind: DependencyKind::AfterOk, job_id: Some(JobId::new(6)) }]);
+    assert_eq!(jobs[7].state_reason, StateReason::Dependency);
+    assert!(jobs[0].dependencies.is_empty());
+    assert_eq!(jobs[7].to_json().find_path(&["dependencies"]).map(|val| val.to_string()), Some("[{\"job_id\":\"6\",\"kind\":\"afterok\"}]".to_string()));
+}
+
+#[test]
+fn test_get_job_info_util_clone() {
+    let jobs = get_job_info_test();
+    let copy: Vec<JobInfo> = jobs.iter().filter(|job| job.job_state == JobState::Running).cloned().collect();
+
+    assert_eq!(copy.len(), 1);
+    assert_eq!(copy[0], jobs[0]);
+}
+
+#[test]
+fn test_get_job_info_clusters() {
+    use command_runner::TestRunner;
+
+    let runner = TestRunner::new(Ok("CLUSTER: cluster1\nnode01 1 2 1 N/A * 1 * sim * N/A 1:00 node01 0.9 None 2000-01-01T09:00:00 RUNNING willi 1000 batch N/A 2000-01-01T08:30:00 1-00:00:00 (null)".to_string()));
+    let clusters = vec!["cluster1".to_string(), "cluster2".to_string()];
+    let result = get_job_info(&runner, &CommandConfig::new("squeue", &[]), &clusters, None, &mut SkippedWarnings::new()).unwrap();
+    let job_clusters: Vec<&str> = result.items.iter().map(|job| &job.cluster[..]).collect();
+    let calls = runner.calls.lock().unwrap();
+
+    assert_eq!(job_clusters, vec!["cluster1", "cluster2"]);
+    assert_eq!((result.skipped, result.total), (0, 2));
+    assert_eq!(calls[0].1[..3].to_vec(), vec!["-M", "cluster1", "-h"]);
+    assert_eq!(calls[1].1[..3].to_vec(), vec!["-M", "cluster2", "-h"]);
 }
 
 /*
-    squeue -h -o "%B %c %C %D %F %H %i %I %j %J %K %M %N %p %r %S %T %u %U"
+    squeue -h -o "%B %c %C %D %F %H %i %I %j %J %K %M %N %p %r %S %T %u %U %P %b %V %l %d"
     %B: Executing host
     %c: Minimum number of CPUs
     %C: Number of CPUs
@@ -160,44 +600,52 @@ fn test_get_job_info_util_invalid() {
     %T: Job state
     %u: User name
     %U: User ID
+    %P: Partition
+    %b: Generic resources per node (tres-per-node), for example gres:gpu:2
+    %V: Submit time
+    %l: Time limit, for example 1-00:00:00 or UNLIMITED
+    %d: Dependencies, for example afterok:81,afterany:80 or (null)
 
     Output looks like:
-    agassiz 1 2 1 N/A * 82 * small_test * N/A 2:46 agassiz 0.99998474074527 None 2015-11-12T09:51:32 RUNNING willi 1000
-    82 agassiz 1 2 1 N/A * 82 * small_test * N/A 2:46 agassiz 0.99998474074527 None 2015-11-12T09:51:32 RUNNING willi 1000
+    agassiz 1 2 1 N/A * 82 * small_test * N/A 2:46 agassiz 0.99998474074527 None 2015-11-12T09:51:32 RUNNING willi 1000 batch N/A 2015-11-12T09:51:30 1-00:00:00 (null)
+    82 agassiz 1 2 1 N/A * 82 * small_test * N/A 2:46 agassiz 0.99998474074527 None 2015-11-12T09:51:32 RUNNING willi 1000 batch
 */
 
 // Private helper function to execute the external "squeue" SLURM command and return its output into a string
-// On error returns an empty string. TODO: better error handling
-fn call_squeue() -> String {
-    let output = Command::new("squeue")
-        .arg("-h")
-        .arg("-o")
-        .arg("%B %c %C %D %F %H %i %I %j %J %K %M %N %p %r %S %T %u %U")
-        .output();
-
-    match output {
-        Result::Ok(val) => String::from_utf8_lossy(&val.stdout).to_string(),
-        Result::Err(err) => {
-            error!("Could not execute 'squeue': {}", err);
-            // return empty string on error, but continue with the program
-            String::new()
-        }
+fn call_squeue(runner: &dyn CommandRunner, command: &CommandConfig, states: Option<&str>) -> Result<String, String> {
+    let states = states.map(|states| format!("--states={}", states));
+    let mut args = vec!["-h", "-o", "%B %c %C %D %F %H %i %I %j %J %K %M %N %p %r %S %T %u %U %P %b %V %l %d"];
+    if let Some(ref states) = states {
+        args.push(states);
     }
+
+    run_command(runner, command, &args)
 }
 
-// Private helper function to parse the list of node the job is runnin on
-fn str_to_list_of_nodes(nodes: &str) -> Vec<String> {
-    let mut result: Vec<String> = Vec::new();
+#[test]
+fn test_call_squeue_args() {
+    use command_runner::{TestRunner, split_args};
 
-    if nodes.len() == 0 {
-        return Vec::new();
-    }
+    let runner = TestRunner::new(Ok(String::new()));
+    call_squeue(&runner, &CommandConfig::new("/opt/slurm/current/bin/squeue", &split_args("-M cluster2")), None).unwrap();
+    call_squeue(&runner, &CommandConfig::new("squeue", &[]), Some(DEFAULT_SQUEUE_STATES)).unwrap();
 
-    for node_name in nodes.split(',') {
-        result.push(node_name.to_string());
-    }
+    let calls = runner.calls.lock().unwrap();
+    assert_eq!(calls[0].0, "/opt/slurm/current/bin/squeue");
+    assert_eq!(calls[0].1, vec!["-M", "cluster2", "-h", "-o", "%B %c %C %D %F %H %i %I %j %J %K %M %N %p %r %S %T %u %U %P %b %V %l %d"]);
+    assert!(calls[0].2.contains(&("LC_ALL".to_string(), "C".to_string())));
+    assert!(calls[0].2.contains(&("SLURM_TIME_FORMAT".to_string(), "standard".to_string())));
+    assert_eq!(calls[1].1, vec!["-h", "-o", "%B %c %C %D %F %H %i %I %j %J %K %M %N %p %r %S %T %u %U %P %b %V %l %d", "--states=all"]);
+}
 
-    result
+// Private helper function to parse the list of node the job is runnin on
+// squeue compresses the list, for example "node[01-03]", so it's expanded into single node names
+fn str_to_list_of_nodes(nodes: &str) -> Vec<String> {
+    if is_placeholder(nodes) {
+        Vec::new()
+    } else {
+        expand_hostlist(nodes)
+    }
 }
 
 #[test]
@@ -220,8 +668,17 @@ fn test_str_to_list_of_nodes_03() {
     assert_eq!(str_to_list_of_nodes("node1,node2,node3"), vec!["node1", "node2", "node3"]);
 }
 
+#[test]
+fn test_str_to_list_of_nodes_hostlist() {
+    assert_eq!(str_to_list_of_nodes("node[01-03],gpu1"), vec!["node01", "node02", "node03", "gpu1"]);
+}
+
 // Private helper function to parse the job state reason
 fn str_to_state_reason(reason: &str) -> StateReason {
+    if is_placeholder(reason) {
+        return StateReason::None;
+    }
+
     match &*reason.to_lowercase() {
         "dependency" => StateReason::Dependency,
         "none" => StateReason::None,
@@ -237,6 +694,13 @@ fn str_to_state_reason(reason: &str) -> StateReason {
         "nonzeroexitcode" => StateReason::NonZeroExitCode,
         "timelimit" => StateReason::TimeLimit,
         "inactivelimit" => StateReason::InactiveLimit,
+        "qosmaxcpuperuserlimit" => StateReason::QOSMaxCpuPerUserLimit,
+        "qosmaxnodeperuserlimit" => StateReason::QOSMaxNodePerUserLimit,
+        "qosmaxmemoryperuser" => StateReason::QOSMaxMemoryPerUser,
+        "qosmaxgresperuser" => StateReason::QOSMaxGRESPerUser,
+        "qosmaxjobsperuserlimit" => StateReason::QOSMaxJobsPerUserLimit,
+        "qosmaxsubmitjobperuserlimit" => StateReason::QOSMaxSubmitJobPerUserLimit,
+        "qosmaxwalldurationperjoblimit" => StateReason::QOSMaxWallDurationPerJobLimit,
         _ => StateReason::Unknown
     }
 }
@@ -339,6 +803,15 @@ fn test_str_to_state_reason_inactive_limit() {
     assert_eq!(str_to_state_reason("INACTIVELIMIT"), StateReason::InactiveLimit);
 }
 
+#[test]
+fn test_str_to_state_reason_qos() {
+    assert_eq!(str_to_state_reason("QOSMaxCpuPerUserLimit"), StateReason::QOSMaxCpuPerUserLimit);
+    assert_eq!(str_to_state_reason("QOSMaxGRESPerUser"), StateReason::QOSMaxGRESPerUser);
+    assert_eq!(str_to_state_reason("qosmaxwalldurationperjoblimit"), StateReason::QOSMaxWallDurationPerJobLimit);
+    // Group limits are not explained
+    assert_eq!(str_to_state_reason("QOSGrpCpuLimit"), StateReason::Unknown);
+}
+
 #[test]
 fn test_str_to_state_reason_unknown() {
     assert_eq!(str_to_state_reason("unknown"), StateReason::Unknown);
@@ -346,20 +819,146 @@ fn test_str_to_state_reason_unknown() {
     assert_eq!(str_to_state_reason("UNKNOWN"), StateReason::Unknown);
 }
 
-// Private helper function to parse the job state
-fn str_to_job_state(state: &str) -> JobState {
+#[test]
+fn test_state_reason_round_trip() {
+    let reasons = [StateReason::Dependency, StateReason::None, StateReason::PartitionDown, StateReason::PartitionNodeLimit,
+        StateReason::PartitionTimeLimit, StateReason::Priority, StateReason::Resources, StateReason::NodeDown,
+        StateReason::BadConstraints, StateReason::SystemFailure, StateReason::JobLaunchFailure, StateReason::NonZeroExitCode,
+        StateReason::TimeLimit, StateReason::InactiveLimit, StateReason::QOSMaxCpuPerUserLimit, StateReason::QOSMaxNodePerUserLimit,
+        StateReason::QOSMaxMemoryPerUser, StateReason::QOSMaxGRESPerUser, StateReason::QOSMaxJobsPerUserLimit,
+        StateReason::QOSMaxSubmitJobPerUserLimit, StateReason::QOSMaxWallDurationPerJobLimit, StateReason::Unknown];
+    for reason in &reasons {
+        assert_eq!(str_to_state_reason(reason.as_slurm_str()), *reason);
+        assert_eq!(str_to_state_reason(&reason.to_string()), *reason);
+    }
+}
+
+/// Public helper function to parse the job ID, returns None if it doesn't start with a number
+/// The step is separated by a dot and the array index by an underscore, for example "1234_5.batch"
+pub fn str_to_job_id(job_id: &str) -> Option<JobId> {
+    let mut parts = job_id.splitn(2, '.');
+    let job = parts.next().unwrap_or("");
+    let step = parts.next();
+    let mut parts = job.splitn(2, '_');
+    let id = parts.next().and_then(|id| id.parse::<u32>().ok());
+    let array_index = parts.next();
+
+    match (id, array_index, step) {
+        (_, Some(""), _) | (_, _, Some("")) => None,
+        (Some(id), array_index, step) => Some(JobId {
+            id: id,
+            array_index: array_index.map(|index| index.to_string()),
+            step: step.map(|step| step.to_string())
+        }),
+        (None, _, _) => None
+    }
+}
+
+#[test]
+fn test_str_to_job_id() {
+    assert_eq!(str_to_job_id("1234"), Some(JobId::new(1234)));
+    assert_eq!(str_to_job_id("1234_5"), Some(JobId{ id: 1234, array_index: Some("5".to_string()), step: None }));
+    assert_eq!(str_to_job_id("1234_[0-99]"), Some(JobId{ id: 1234, array_index: Some("[0-99]".to_string()), step: None }));
+    assert_eq!(str_to_job_id("1234.batch"), Some(JobId{ id: 1234, array_index: None, step: Some("batch".to_string()) }));
+    assert_eq!(str_to_job_id("1234.0"), Some(JobId{ id: 1234, array_index: None, step: Some("0".to_string()) }));
+    assert_eq!(str_to_job_id("1234_5.0"), Some(JobId{ id: 1234, array_index: Some("5".to_string()), step: Some("0".to_string()) }));
+}
+
+#[test]
+fn test_str_to_job_id_invalid() {
+    assert_eq!(str_to_job_id(""), None);
+    assert_eq!(str_to_job_id("N/A"), None);
+    assert_eq!(str_to_job_id("batch.0"), None);
+    assert_eq!(str_to_job_id("1234_"), None);
+    assert_eq!(str_to_job_id("1234."), None);
+}
+
+#[test]
+fn test_job_id_display() {
+    for job_id in &["1234", "1234_5", "1234_[0-99]", "1234.batch", "1234.0", "1234_5.extern"] {
+        assert_eq!(str_to_job_id(job_id).unwrap().to_string(), *job_id);
+    }
+}
+
+#[test]
+fn test_job_id_parent() {
+    let step = str_to_job_id("1234_5.batch").unwrap();
+
+    assert!(step.is_step());
+    assert_eq!(step.parent(), str_to_job_id("1234_5").unwrap());
+    assert!(!step.parent().is_step());
+    assert_eq!(JobId::new(1234).parent(), JobId::new(1234));
+}
+
+/// Public helper function to remove the job steps, only the jobs are kept
+pub fn remove_job_steps(job_info: &mut Vec<JobInfo>) {
+    job_info.retain(|job| !job.job_id.as_ref().map_or(false, |job_id| job_id.is_step()));
+}
+
+/// Public helper function to move each job step right after its job (or its previous step), so it can be shown below the job
+/// Steps whose job is not in the list are moved to the end, otherwise the order is kept
+pub fn group_job_steps(job_info: &mut Vec<JobInfo>) {
+    let is_step = |job: &JobInfo| job.job_id.as_ref().map_or(false, |job_id| job_id.is_step());
+    let (steps, jobs): (Vec<JobInfo>, Vec<JobInfo>) = job_info.drain(..).partition(|job| is_step(job));
+    let mut steps: Vec<Option<JobInfo>> = steps.into_iter().map(Some).collect();
+
+    for job in jobs {
+        let job_id = job.job_id.clone();
+        let cluster = job.cluster.clone();
+        job_info.push(job);
+
+        if let Some(job_id) = job_id {
+            for slot in steps.iter_mut() {
+                let belongs_to_job = slot.as_ref().map_or(false, |step|
+                    step.cluster == cluster && step.job_id.as_ref().map(|step_id| step_id.parent()) == Some(job_id.clone()));
+                if belongs_to_job {
+                    job_info.push(slot.take().unwrap());
+                }
+            }
+        }
+    }
+
+    job_info.extend(steps.into_iter().filter_map(|step| step));
+}
+
+#[test]
+fn test_job_steps() {
+    let line = |job_id: &str| format!("node01 1 2 1 N/A * {} * sim * N/A 1:00 node01 0.9 None 2000-01-01T09:00:00 RUNNING willi 1000 batch N/A 2000-01-01T08:30:00 1-00:00:00 (null)", job_id);
+    let input: Vec<String> = ["1234.batch", "1234", "99.0", "1235_7", "1234.0", "1235_7.batch"].iter().map(|job_id| line(job_id)).collect();
+    let job_ids = |jobs: &[JobInfo]| -> Vec<String> { jobs.iter().map(|job| job.job_id.as_ref().unwrap().to_string()).collect() };
+
+    let mut jobs = get_job_info_util(&input.join("\n"), &mut SkippedWarnings::new()).items;
+    assert_eq!(jobs.len(), 6);
+    group_job_steps(&mut jobs);
+    assert_eq!(job_ids(&jobs), vec!["1234", "1234.batch", "1234.0", "1235_7", "1235_7.batch", "99.0"]);
+
+    remove_job_steps(&mut jobs);
+    assert_eq!(job_ids(&jobs), vec!["1234", "1235_7"]);
+}
+
+/// Public helper function to parse the job state, also used for sacct output
+/// Accepts the long form (RUNNING) and the abbreviated form (R) of the state
+pub fn str_to_job_state(state: &str) -> JobState {
     match &*state.to_lowercase() {
-        "cancelled" => JobState::Cancelled,
-        "completed" => JobState::Completed,
-        "configuring" => JobState::Configuring,
-        "completing" => JobState::Completing,
-        "failed" => JobState::Failed,
-        "node_fail" => JobState::NodeFail,
-        "pending" => JobState::Pending,
-        "preempted" => JobState::Preempted,
-        "running" => JobState::Running,
-        "suspended" => JobState::Suspended,
-        "timeout" => JobState::Timeout,
+        "boot_fail" | "bf" => JobState::BootFail,
+        "cancelled" | "ca" => JobState::Cancelled,
+        "completed" | "cd" => JobState::Completed,
+        "configuring" | "cf" => JobState::Configuring,
+        "completing" | "cg" => JobState::Completing,
+        "deadline" | "dl" => JobState::Deadline,
+        "failed" | "f" => JobState::Failed,
+        "node_fail" | "nf" => JobState::NodeFail,
+        "out_of_memory" | "oom" => JobState::OutOfMemory,
+        "pending" | "pd" => JobState::Pending,
+        "preempted" | "pr" => JobState::Preempted,
+        "requeued" | "rq" => JobState::Requeued,
+        "resizing" | "rs" => JobState::Resizing,
+        "revoked" | "rv" => JobState::Revoked,
+        "running" | "r" => JobState::Running,
+        "special_exit" | "se" => JobState::SpecialExit,
+        "stopped" | "st" => JobState::Stopped,
+        "suspended" | "s" => JobState::Suspended,
+        "timeout" | "to" => JobState::Timeout,
         _ => JobState::Unknown
     }
 }
@@ -449,3 +1048,50 @@ fn test_str_to_job_state_unknown() {
     assert_eq!(str_to_job_state("Unknown"), JobState::Unknown);
     assert_eq!(str_to_job_state("UNKNOWN"), JobState::Unknown);
 }
+
+#[test]
+fn test_str_to_job_state_new_states() {
+    assert_eq!(str_to_job_state("BOOT_FAIL"), JobState::BootFail);
+    assert_eq!(str_to_job_state("DEADLINE"), JobState::Deadline);
+    assert_eq!(str_to_job_state("OUT_OF_MEMORY"), JobState::OutOfMemory);
+    assert_eq!(str_to_job_state("out_of_memory"), JobState::OutOfMemory);
+    assert_eq!(str_to_job_state("REQUEUED"), JobState::Requeued);
+    assert_eq!(str_to_job_state("RESIZING"), JobState::Resizing);
+    assert_eq!(str_to_job_state("REVOKED"), JobState::Revoked);
+    assert_eq!(str_to_job_state("SPECIAL_EXIT"), JobState::SpecialExit);
+    assert_eq!(str_to_job_state("STOPPED"), JobState::Stopped);
+}
+
+#[test]
+fn test_job_state_round_trip() {
+    let states = [JobState::BootFail, JobState::Cancelled, JobState::Completed, JobState::Configuring, JobState::Completing,
+        JobState::Deadline, JobState::Failed, JobState::NodeFail, JobState::OutOfMemory, JobState::Pending, JobState::Preempted,
+        JobState::Requeued, JobState::Resizing, JobState::Revoked, JobState::Running, JobState::SpecialExit, JobState::Stopped,
+        JobState::Suspended, JobState::Timeout, JobState::Unknown];
+    for state in &states {
+        assert_eq!(str_to_job_state(state.as_slurm_str()), *state);
+        assert_eq!(str_to_job_state(&state.to_string()), *state);
+    }
+
+    assert_eq!(JobState::NodeFail.to_string(), "NODE_FAIL");
+}
+
+#[test]
+fn test_str_to_job_state_short_forms() {
+    assert_eq!(str_to_job_state("BF"), JobState::BootFail);
+    assert_eq!(str_to_job_state("CA"), JobState::Cancelled);
+    assert_eq!(str_to_job_state("CD"), JobState::Completed);
+    assert_eq!(str_to_job_state("CF"), JobState::Configuring);
+    assert_eq!(str_to_job_state("CG"), JobState::Completing);
+    assert_eq!(str_to_job_state("DL"), JobState::Deadline);
+    assert_eq!(str_to_job_state("F"), JobState::Failed);
+    assert_eq!(str_to_job_state("NF"), JobState::NodeFail);
+    assert_eq!(str_to_job_state("OOM"), JobState::OutOfMemory);
+    assert_eq!(str_to_job_state("PD"), JobState::Pending);
+    assert_eq!(str_to_job_state("PR"), JobState::Preempted);
+    assert_eq!(str_to_job_state("R"), JobState::Running);
+    assert_eq!(str_to_job_state("r"), JobState::Running);
+    assert_eq!(str_to_job_state("S"), JobState::Suspended);
+    assert_eq!(str_to_job_state("TO"), JobState::Timeout);
+    assert_eq!(str_to_job_state("XY"), JobState::Unknown);
+}
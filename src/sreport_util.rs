@@ -0,0 +1,150 @@
+//! Abstraction for the SLURM sreport command
+//! Runs "sreport cluster AccountUtilizationByUser" for the current month, parses output into data structure (UsageRecord)
+//! sreport can take a while on a big accounting database, so it runs in its own thread with its own interval (--sreport-interval)
+
+// System modules:
+use std::collections::BTreeMap;
+
+// External modules:
+use rustc_serialize::json::{Json, ToJson};
+use time::{Tm, strftime};
+
+// Internal modules:
+use command_runner::{CommandRunner, CommandConfig, run_command};
+
+/// Default time between two sreport calls in seconds: 1 hour
+pub const DEFAULT_SREPORT_INTERVAL: u64 = 3600;
+
+/// CPU minutes of an account or a user within an account since the start of the month
+#[derive(Debug, Clone, PartialEq)]
+pub struct UsageRecord {
+    pub account: String,
+    /// None for the lines with the total of the account
+    pub user: Option<String>,
+    pub cpu_minutes: u64
+}
+
+impl ToJson for UsageRecord {
+    fn to_json(&self) -> Json {
+        let mut result = BTreeMap::new();
+        result.insert("account".to_string(), self.account.to_json());
+        result.insert("user".to_string(), self.user.to_json());
+        result.insert("cpu_minutes".to_string(), self.cpu_minutes.to_json());
+        Json::Object(result)
+    }
+}
+
+/// Public helper function to generate test data
+pub fn get_usage_info_test() -> Vec<UsageRecord> {
+    let test_data = "
+        cluster1|root|||8192|0
+        cluster1| root|root|root|0|0
+        cluster1| esd|||8192|0
+        cluster1|  esd|user01|User One|4096|0
+        cluster1|  esd|user02|User Two|3072|0
+        cluster1|  esd|user03|User Three|1024|0
+    ";
+
+    get_usage_info_util(test_data)
+}
+
+/// Public helper function that returns the first day of the month of the given time, the start of the report, for example "2015-12-01"
+pub fn month_start(time: &Tm) -> String {
+    strftime("%Y-%m-01", time).unwrap()
+}
+
+/// Public helper function to retrieve the CPU minutes of the accounts and users since the given day, sorted by CPU minutes
+/// Returns an error if sreport could not be executed
+pub fn get_usage_info(runner: &dyn CommandRunner, start: &str) -> Result<Vec<UsageRecord>, String> {
+    let start = format!("start={}", start);
+    run_command(runner, &CommandConfig::new("sreport", &[]), &["-n", "-P", "cluster", "AccountUtilizationByUser", &start])
+        .map(|output| get_usage_info_util(&output))
+}
+
+// Private helper function to parse the output of "sreport -n -P cluster AccountUtilizationByUser" and return a list of UsageRecord
+// The fields are Cluster|Account|Login|Proper Name|Used (and Energy in newer versions). The total of an account has no login,
+// sub-accounts and their users are indented by one space per level. The records are sorted by CPU minutes, the largest first
+fn get_usage_info_util(sreport_output: &str) -> Vec<UsageRecord> {
+    let mut result: Vec<UsageRecord> = Vec::new();
+
+    for line in sreport_output.lines() {
+        let items: Vec<&str> = line.split('|').map(|item| item.trim()).collect();
+
+        // Skip invalid line
+        if (items.len() != 5 && items.len() != 6) || items[1].is_empty() {
+            debug!("number of items in line: {}", items.len());
+            continue
+        }
+
+        let cpu_minutes = match items[4].parse::<u64>() {
+            Ok(cpu_minutes) => cpu_minutes,
+            Err(_) => {
+                debug!("invalid CPU minutes: {}", items[4]);
+                continue
+            }
+        };
+
+        result.push( UsageRecord{
+                account: items[1].to_string(),
+                user: if items[2].is_empty() { None } else { Some(items[2].to_string()) },
+                cpu_minutes: cpu_minutes
+        })
+    }
+
+    result.sort_by(|a, b| b.cpu_minutes.cmp(&a.cpu_minutes).then_with(|| a.account.cmp(&b.account)).then_with(|| a.user.cmp(&b.user)));
+    result
+}
+
+#[test]
+fn test_get_usage_info_util_invalid() {
+    assert!(get_usage_info_util("").is_empty());
+    assert!(get_usage_info_util("cluster1|esd|user01").is_empty());
+    assert!(get_usage_info_util("cluster1||user01|User One|10|0").is_empty());
+    assert!(get_usage_info_util("cluster1|esd|user01|User One|lots|0").is_empty());
+}
+
+#[test]
+fn test_get_usage_info_util_fixture() {
+    let usage = get_usage_info_util(include_str!("../tests/fixtures/sreport_usage.txt"));
+    let record = |account: &str, user: Option<&str>, cpu_minutes: u64| UsageRecord {
+        account: account.to_string(),
+        user: user.map(|user| user.to_string()),
+        cpu_minutes: cpu_minutes
+    };
+
+    assert_eq!(usage, vec![
+        record("root", None, 1845310),
+        record("chemistry", None, 1204800),
+        record("chemistry", Some("chem01"), 904800),
+        record("physics", None, 640485),
+        record("physics", Some("phys01"), 640485),
+        record("chemistry", Some("chem02"), 300000),
+        record("root", Some("root"), 25),
+        record("physics", Some("phys02"), 0)
+    ]);
+}
+
+#[test]
+fn test_get_usage_info_util_without_energy() {
+    // Older versions of sreport have no Energy field
+    assert_eq!(get_usage_info_util("cluster1|  esd|user01|User One|4096"), vec![UsageRecord {
+        account: "esd".to_string(),
+        user: Some("user01".to_string()),
+        cpu_minutes: 4096
+    }]);
+}
+
+#[test]
+fn test_get_usage_info() {
+    use command_runner::TestRunner;
+    use time::{at_utc, Timespec};
+
+    assert_eq!(month_start(&at_utc(Timespec::new(1450432800, 0))), "2015-12-01");
+
+    let runner = TestRunner::new(Ok("cluster1|esd|||10|0\n".to_string()));
+    assert_eq!(get_usage_info(&runner, "2015-12-01").map(|usage| usage.len()), Ok(1));
+    assert_eq!(runner.calls.lock().unwrap()[0].1, vec!["-n", "-P", "cluster", "AccountUtilizationByUser", "start=2015-12-01"]);
+
+    let runner = TestRunner::new(Err("sreport: command not found".to_string()));
+    assert!(get_usage_info(&runner, "2015-12-01").is_err());
+}
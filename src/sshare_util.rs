@@ -0,0 +1,175 @@
+//! Abstraction for the SLURM sshare command
+//! Runs sshare, parses output into data structure (ShareInfo)
+
+// System modules:
+use std::collections::BTreeMap;
+
+// External modules:
+use rustc_serialize::json::{Json, ToJson};
+
+// Internal modules:
+use command_runner::{CommandRunner, CommandConfig, run_command};
+
+/// Fair-share information of an account or a user within an account
+#[derive(Debug, Clone, PartialEq)]
+pub struct ShareInfo {
+    pub account: String,
+    /// None for the lines that describe the account itself
+    pub user: Option<String>,
+    pub raw_shares: Option<u64>,
+    pub norm_shares: Option<f64>,
+    pub raw_usage: Option<u64>,
+    pub effective_usage: Option<f64>,
+    pub fair_share: Option<f64>
+}
+
+impl ToJson for ShareInfo {
+    fn to_json(&self) -> Json {
+        let mut result = BTreeMap::new();
+        result.insert("account".to_string(), self.account.to_json());
+        result.insert("user".to_string(), self.user.to_json());
+        result.insert("raw_shares".to_string(), self.raw_shares.to_json());
+        result.insert("norm_shares".to_string(), self.norm_shares.to_json());
+        result.insert("raw_usage".to_string(), self.raw_usage.to_json());
+        result.insert("effective_usage".to_string(), self.effective_usage.to_json());
+        result.insert("fair_share".to_string(), self.fair_share.to_json());
+        Json::Object(result)
+    }
+}
+
+/// Public helper function to generate test data
+pub fn get_share_info_test() -> Vec<ShareInfo> {
+    let test_data = "
+        root|||0.000000|4096||1.000000
+         root|root|1|0.333333|0|0.000000|1.000000
+         esd||2|0.666667|4096|1.000000|
+          esd|user01|1|0.333333|2048|0.500000|0.500000
+          esd|user02|1|0.333333|1024|0.250000|0.707107
+          esd|user03|1|0.333333|1024|0.250000|0.707107
+    ";
+
+    get_share_info_util(test_data)
+}
+
+/// Public helper function to retrieve the current fair-share information
+pub fn get_share_info(runner: &dyn CommandRunner) -> Vec<ShareInfo> {
+    get_share_info_util(&call_sshare(runner))
+}
+
+// Private helper function to parse the output of "sshare" and return a list of ShareInfo
+fn get_share_info_util(sshare_output: &str) -> Vec<ShareInfo> {
+    let mut result: Vec<ShareInfo> = Vec::new();
+
+    for line in sshare_output.lines() {
+        let items: Vec<&str> = line.split('|').map(|item| item.trim()).collect();
+
+        // Skip invalid line
+        if items.len() != 7 || items[0].len() == 0 {
+            debug!("number of items in line: {}", items.len());
+            continue
+        }
+
+        result.push( ShareInfo{
+                account: items[0].to_string(),
+                user: if items[1].len() == 0 { None } else { Some(items[1].to_string()) },
+                raw_shares: items[2].parse::<u64>().ok(),
+                norm_shares: items[3].parse::<f64>().ok(),
+                raw_usage: items[4].parse::<u64>().ok(),
+                effective_usage: items[5].parse::<f64>().ok(),
+                fair_share: items[6].parse::<f64>().ok()
+        })
+    }
+
+    result
+}
+
+#[test]
+fn test_get_share_info_util_empty() {
+    assert_eq!(get_share_info_util("").len(), 0);
+}
+
+#[test]
+fn test_get_share_info_util_invalid() {
+    assert_eq!(get_share_info_util("1|2|3").len(), 0);
+    assert_eq!(get_share_info_util("|user01|1|0.5|0|0.0|1.0").len(), 0);
+}
+
+#[test]
+fn test_get_share_info_util_root() {
+    let input = "root|||0.000000|4096||1.000000";
+    let output = vec![ShareInfo{
+        account: "root".to_string(),
+        user: None,
+        raw_shares: None,
+        norm_shares: Some(0.0),
+        raw_usage: Some(4096),
+        effective_usage: None,
+        fair_share: Some(1.0)
+    }];
+
+    assert_eq!(get_share_info_util(input), output);
+}
+
+#[test]
+fn test_get_share_info_util_account_and_user() {
+    let input = " esd||2|0.666667|4096|1.000000|\n  esd|user01|1|0.333333|2048|0.500000|0.500000";
+    let output = vec![
+        ShareInfo{
+            account: "esd".to_string(),
+            user: None,
+            raw_shares: Some(2),
+            norm_shares: Some(0.666667),
+            raw_usage: Some(4096),
+            effective_usage: Some(1.0),
+            fair_share: None
+        },
+        ShareInfo{
+            account: "esd".to_string(),
+            user: Some("user01".to_string()),
+            raw_shares: Some(1),
+            norm_shares: Some(0.333333),
+            raw_usage: Some(2048),
+            effective_usage: Some(0.5),
+            fair_share: Some(0.5)
+        }
+    ];
+
+    assert_eq!(get_share_info_util(input), output);
+}
+
+#[test]
+fn test_get_share_info_util_parent_shares() {
+    let input = "  esd|user04|parent|0.333333|0|0.000000|1.000000";
+    let result = get_share_info_util(input);
+
+    assert_eq!(result.len(), 1);
+    assert_eq!(result[0].raw_shares, None);
+    assert_eq!(result[0].user, Some("user04".to_string()));
+}
+
+/*
+    sshare -n -P -o Account,User,RawShares,NormShares,RawUsage,EffectvUsage,FairShare
+    -n: no header
+    -P: output is delimited by "|", no trailing "|"
+    Account: Account name, indented by its depth in the account hierarchy
+    User: User name, empty for the line of the account itself
+    RawShares: Shares assigned to the user or account ("parent" if the parent's shares are used)
+    NormShares: Shares normalized to the total number of shares
+    RawUsage: Number of cpu-seconds used
+    EffectvUsage: Usage normalized to the total usage
+    FairShare: Fair-share factor (0.0 - 1.0)
+
+    Output looks like:
+    root|||0.000000|4096||1.000000
+     root|root|1|0.333333|0|0.000000|1.000000
+     esd||2|0.666667|4096|1.000000|
+      esd|willi|1|0.333333|2048|0.500000|0.500000
+*/
+
+// Private helper function to execute the external "sshare" SLURM command and return its output into a string
+// On error returns an empty string. TODO: better error handling
+fn call_sshare(runner: &dyn CommandRunner) -> String {
+    // return empty string on error (run_command logs it), but continue with the program
+    run_command(runner, &CommandConfig::new("sshare", &[]), &["-n", "-P", "-o", "Account,User,RawShares,NormShares,RawUsage,EffectvUsage,FairShare"])
+        .unwrap_or(String::new())
+}
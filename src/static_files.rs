@@ -0,0 +1,140 @@
+//! Static files served by slurm_inspector
+//! Contains the default stylesheet, the favicon and robots.txt, and loads the optional custom stylesheet and banner
+
+// System modules:
+use std::fs::File;
+use std::io::{self, Read};
+
+/// The default stylesheet, served at /static/style.css
+pub const DEFAULT_CSS: &'static str = "table, { border: 1px solid black; }
+th, td { border: 1px solid black; padding: 10px; }
+th { background: #e0e0e0; }
+#partition_down { background: #ffa0a0; }
+.partition_group { vertical-align: top; font-weight: bold; }
+.partition_name { font-weight: bold; }
+.job_failed { background: #ffa0a0; }
+.job_alarm { background: #ff4040; color: white; font-weight: bold; }
+.job_step_id { padding-left: 30px; }
+.job_array summary { cursor: pointer; font-weight: bold; }
+.load_warning { background: #ffd080; }
+.memory_warning { background: #ffd080; }
+meter { width: 60px; }
+.load_oversubscribed { background: #ff9090; }
+.load_idle { background: #ffd080; }
+.recently_finished { color: #909090; }
+.exit_hint { color: #606060; font-style: italic; }
+.time_limit_warning { background: #ffc040; }
+.time_limit_critical { background: #ff6060; }
+.reservation_active { background: #ffd080; }
+.reservation_upcoming { background: #ffffa0; }
+.license_exhausted { background: #ffa0a0; }
+.dependency_stuck { color: red; font-weight: bold; }
+.own_job { background: #fff3b0; }
+.own_jobs { font-weight: bold; }
+.node_down { background: #ffa0a0; }
+.event_alarm { color: #c00000; font-weight: bold; }
+.update_error { background: #ffa0a0; padding: 5px; }
+.filter_notice { background: #ffffa0; padding: 5px; }
+.skipped_lines { background: #ffd0a0; padding: 5px; }
+.banner { background: #d00000; color: white; font-size: 150%; font-weight: bold; padding: 10px; margin-bottom: 10px; white-space: pre-line; }
+.refresh { margin-bottom: 10px; }
+.admin_done { background: #a0ffa0; padding: 5px; }
+.admin_failed { background: #ffa0a0; padding: 5px; }
+.admin_action { margin: 0; }
+.slurm_version { font-size: 50%; font-weight: normal; }
+.build_info { margin-top: 20px; font-size: 80%; color: #808080; }
+.dashboard_box { display: inline-block; border: 1px solid black; padding: 10px; margin: 5px; min-width: 100px; text-align: center; }
+.dashboard_number { font-size: 200%; font-weight: bold; }
+.dashboard_problem .dashboard_number { color: #c00000; }
+.dashboard_amber { background: #ffe080; }
+.dashboard_red { background: #ff6060; }
+.top_jobs { margin-bottom: 20px; }
+.top_jobs table { display: inline-table; vertical-align: top; margin-right: 20px; }
+.grid { margin-bottom: 20px; }
+.grid_partition { margin-bottom: 10px; }
+.grid_row { line-height: 0; }
+.grid_cell { display: inline-block; width: 14px; height: 14px; margin: 1px; border: 2px solid white; background: #c0c0c0; }
+.grid_busy { border-color: black; }
+.grid_idle { background: #a0ffa0; }
+.grid_mixed { background: #ffe080; }
+.grid_allocated, .grid_completing { background: #ffb040; }
+.grid_down, .grid_fail, .grid_failing { background: #ff6060; }
+.grid_drained, .grid_draining, .grid_maint { background: #a0a0ff; }
+.grid_reserved, .grid_planned { background: #ffffa0; }
+.grid_powered_down { background: #e0e0e0; }
+";
+
+/// The icon of the browser tabs, served at /favicon.ico (as SVG, which all current browsers accept)
+pub const FAVICON: &'static [u8] = include_bytes!("../static/favicon.svg");
+
+/// Served at /robots.txt, the status of a cluster shouldn't end up in search engines
+pub const ROBOTS_TXT: &'static str = "User-agent: *
+Disallow: /
+";
+
+/// Public helper function to read the custom stylesheet given by the user
+/// Missing or unreadable files are logged and None is returned
+pub fn read_custom_css(path: &str) -> Option<String> {
+    let mut content = String::new();
+
+    match File::open(path).and_then(|mut file| file.read_to_string(&mut content)) {
+        Result::Ok(_) => Some(content),
+        Result::Err(err) => {
+            warn!("Could not read custom stylesheet '{}': {}", path, err);
+            None
+        }
+    }
+}
+
+/// Public helper function to read the banner file (--banner-file), the text is trimmed
+/// A missing or empty file means that there is no banner, other errors are logged
+pub fn read_banner(path: &str) -> Option<String> {
+    let mut content = String::new();
+
+    match File::open(path).and_then(|mut file| file.read_to_string(&mut content)) {
+        Result::Ok(_) if content.trim().is_empty() => None,
+        Result::Ok(_) => Some(content.trim().to_string()),
+        Result::Err(ref err) if err.kind() == io::ErrorKind::NotFound => None,
+        Result::Err(err) => {
+            warn!("Could not read the banner file '{}': {}", path, err);
+            None
+        }
+    }
+}
+
+#[test]
+fn test_read_banner() {
+    use std::env::temp_dir;
+    use std::fs::remove_file;
+    use std::io::Write;
+
+    let path = temp_dir().join("slurm_inspector_test_banner.txt");
+    let path_str = path.to_str().unwrap();
+    File::create(&path).and_then(|mut file| file.write_all("\nCluster maintenance Saturday 08:00–12:00\n\n".as_bytes())).unwrap();
+    assert_eq!(read_banner(path_str), Some("Cluster maintenance Saturday 08:00–12:00".to_string()));
+
+    File::create(&path).and_then(|mut file| file.write_all(b" \n")).unwrap();
+    assert_eq!(read_banner(path_str), None);
+
+    remove_file(&path).unwrap();
+    assert_eq!(read_banner(path_str), None);
+}
+
+#[test]
+fn test_read_custom_css_missing() {
+    assert_eq!(read_custom_css("/this/file/does/not/exist.css"), None);
+}
+
+#[test]
+fn test_read_custom_css() {
+    use std::env::temp_dir;
+    use std::fs::remove_file;
+    use std::io::Write;
+
+    let path = temp_dir().join("slurm_inspector_test_custom.css");
+    File::create(&path).and_then(|mut file| file.write_all(b"th { background: red; }")).unwrap();
+
+    assert_eq!(read_custom_css(path.to_str().unwrap()), Some("th { background: red; }".to_string()));
+
+    remove_file(&path).unwrap();
+}
@@ -0,0 +1,251 @@
+//! Changes of the jobs and nodes since an earlier update, for clients that poll /api/changes
+//! Each snapshot is identified by the etag of its rendered representations (the token). The status keeps the jobs and nodes
+//! of the snapshot before the last change, so a client that sends its token gets only the jobs and nodes that changed since.
+//! A client with an older or unknown token gets the full status instead
+
+// System modules:
+use std::collections::BTreeMap;
+
+// External modules:
+use rustc_serialize::json::{Json, ToJson};
+
+// Internal modules:
+use sinfo_util::PartitionNodeInfo;
+use squeue_util::{JobInfo, JobId, JobState};
+use node_events::{NodeEvent, diff_node_info};
+use slurm_status::SlurmStatus;
+use status_filter::StatusFilter;
+
+/// The jobs and nodes of an earlier snapshot, only the job states are kept since new jobs come from the current snapshot
+#[derive(Debug, Clone, PartialEq)]
+pub struct StatusSnapshot {
+    /// The etag of the snapshot
+    pub token: String,
+    /// State of each job by cluster and job id, jobs without a valid job id are left out
+    pub jobs: BTreeMap<(String, JobId), JobState>,
+    pub node_info: Vec<PartitionNodeInfo>
+}
+
+impl StatusSnapshot {
+    /// Keeps the jobs that pass the filter (the team of --users) and the nodes of the given status,
+    /// the token is the etag of its rendered representations
+    pub fn new(status: &SlurmStatus, filter: &StatusFilter) -> StatusSnapshot {
+        let jobs: Vec<JobInfo> = status.job_info.iter().filter(|job| filter.job_matches(job)).cloned().collect();
+        StatusSnapshot {
+            token: status.rendered.etag.clone(),
+            jobs: jobs_by_id(&jobs).into_iter().map(|(key, job)| (key, job.job_state)).collect(),
+            node_info: status.node_info.clone()
+        }
+    }
+}
+
+/// A job that is in both snapshots, but in another state
+#[derive(Debug, Clone, PartialEq)]
+pub struct JobChange {
+    pub cluster: String,
+    pub job_id: JobId,
+    pub from: JobState,
+    pub to: JobState
+}
+
+impl ToJson for JobChange {
+    fn to_json(&self) -> Json {
+        let mut result = BTreeMap::new();
+        result.insert("cluster".to_string(), self.cluster.to_json());
+        result.insert("job_id".to_string(), self.job_id.to_json());
+        result.insert("from".to_string(), self.from.to_json());
+        result.insert("to".to_string(), self.to.to_json());
+        Json::Object(result)
+    }
+}
+
+/// Differences between an earlier snapshot and the current status, the jobs are sorted by cluster and job id, the nodes by name
+#[derive(Debug, Clone, PartialEq)]
+pub struct StatusChanges<'a> {
+    /// Jobs that are new, with all their information
+    pub added: Vec<&'a JobInfo>,
+    /// Cluster and job id of the jobs that are gone
+    pub removed: Vec<(String, JobId)>,
+    pub changed: Vec<JobChange>,
+    /// Nodes that changed their state, appeared or disappeared
+    pub nodes: Vec<NodeEvent>
+}
+
+impl<'a> StatusChanges<'a> {
+    /// True if neither the jobs nor the nodes have changed
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.changed.is_empty() && self.nodes.is_empty()
+    }
+}
+
+// Private helper function to index the jobs by cluster and job id
+fn jobs_by_id(jobs: &[JobInfo]) -> BTreeMap<(String, JobId), &JobInfo> {
+    jobs.iter().filter_map(|job| job.job_id.as_ref().map(|job_id| ((job.cluster.clone(), job_id.clone()), job))).collect()
+}
+
+/// Public helper function that compares the jobs and nodes of an earlier snapshot with the current ones
+pub fn diff_status<'a>(previous: &StatusSnapshot, jobs: &'a [JobInfo], node_info: &[PartitionNodeInfo], time: &str) -> StatusChanges<'a> {
+    let current = jobs_by_id(jobs);
+    let mut added = Vec::new();
+    let mut changed = Vec::new();
+
+    for (key, job) in &current {
+        match previous.jobs.get(key) {
+            Some(&state) if state != job.job_state => changed.push(JobChange {
+                cluster: key.0.clone(),
+                job_id: key.1.clone(),
+                from: state,
+                to: job.job_state
+            }),
+            Some(_) => {},
+            None => added.push(*job)
+        }
+    }
+
+    StatusChanges {
+        added: added,
+        removed: previous.jobs.keys().filter(|key| !current.contains_key(*key)).cloned().collect(),
+        changed: changed,
+        nodes: diff_node_info(&previous.node_info, node_info, time)
+    }
+}
+
+/// Public helper function that returns the answer of /api/changes?since=<token>
+/// Nothing has changed if the token is the current one, the changes are returned for the token of the previous snapshot
+/// and the full status for all other tokens (or no token). The JSON of the full status is the one of /api/status
+/// Only the jobs that pass the filter (the team of --users, like the snapshot) are compared
+pub fn changes_to_json(status: &SlurmStatus, since: Option<&str>, filter: &StatusFilter) -> String {
+    let token = &status.rendered.etag;
+    let mut result = BTreeMap::new();
+    result.insert("token".to_string(), token.to_json());
+
+    if since == Some(&token[..]) {
+        result.insert("changed".to_string(), false.to_json());
+        return Json::Object(result).to_string();
+    }
+
+    match status.previous_snapshot {
+        Some(ref previous) if since == Some(&previous.token[..]) => {
+            let jobs: Vec<JobInfo> = status.job_info.iter().filter(|job| filter.job_matches(job)).cloned().collect();
+            let changes = diff_status(previous, &jobs, &status.node_info, &status.last_update);
+            result.insert("changed".to_string(), true.to_json());
+            result.insert("full".to_string(), false.to_json());
+            result.insert("jobs_added".to_string(), Json::Array(changes.added.iter().map(|job| job.to_json()).collect()));
+            result.insert("jobs_removed".to_string(), Json::Array(changes.removed.iter().map(|&(ref cluster, ref job_id)| {
+                let mut removed = BTreeMap::new();
+                removed.insert("cluster".to_string(), cluster.to_json());
+                removed.insert("job_id".to_string(), job_id.to_json());
+                Json::Object(removed)
+            }).collect()));
+            result.insert("jobs_changed".to_string(), changes.changed.to_json());
+            result.insert("nodes_changed".to_string(), changes.nodes.to_json());
+            Json::Object(result).to_string()
+        },
+        _ => {
+            // The rendered JSON is inserted as it is instead of parsing it again
+            result.insert("changed".to_string(), true.to_json());
+            result.insert("full".to_string(), true.to_json());
+            let fields = Json::Object(result).to_string();
+            format!("{},\"status\":{}}}", &fields[..fields.len() - 1], status.rendered.json.plain)
+        }
+    }
+}
+
+#[cfg(test)]
+fn test_job(job_id: &str, cluster: &str, state: JobState) -> JobInfo {
+    JobInfo { cluster: cluster.to_string(), .. ::squeue_util::test_job(job_id, state) }
+}
+
+#[test]
+fn test_diff_status() {
+    use sinfo_util::{NodeState, get_partition_node_info_test};
+    use node_events::NodeEventKind;
+
+    let mut status = SlurmStatus::new();
+    status.job_info = vec![test_job("1", "", JobState::Pending), test_job("2", "", JobState::Running), test_job("3", "", JobState::Running),
+        test_job("1", "cluster2", JobState::Running)];
+    status.node_info = get_partition_node_info_test();
+    let previous = StatusSnapshot::new(&status, &StatusFilter::new());
+
+    let mut node_info = get_partition_node_info_test();
+    node_info[0].node_state = NodeState::Down;
+    let mut invalid = test_job("9", "", JobState::Running);
+    invalid.job_id = None;
+    let jobs = vec![
+        // Started
+        test_job("1", "", JobState::Running),
+        test_job("3", "", JobState::Running),
+        // Same id on another cluster
+        test_job("1", "cluster2", JobState::Completing),
+        test_job("4", "", JobState::Pending),
+        invalid
+    ];
+
+    let changes = diff_status(&previous, &jobs, &node_info, "2000.01.01 - 09:00");
+    assert_eq!(changes.added, vec![&jobs[3]]);
+    assert_eq!(changes.removed, vec![(String::new(), JobId::new(2))]);
+    assert_eq!(changes.changed, vec![
+        JobChange { cluster: String::new(), job_id: JobId::new(1), from: JobState::Pending, to: JobState::Running },
+        JobChange { cluster: "cluster2".to_string(), job_id: JobId::new(1), from: JobState::Running, to: JobState::Completing }]);
+    assert_eq!(changes.nodes.len(), 1);
+    assert_eq!(changes.nodes[0].node, node_info[0].node);
+    match changes.nodes[0].kind {
+        NodeEventKind::StateChanged { to, .. } => assert_eq!(to, NodeState::Down),
+        ref kind => panic!("unexpected node event: {:?}", kind)
+    }
+    assert!(!changes.is_empty());
+
+    // The same snapshot has no changes
+    assert!(diff_status(&previous, &status.job_info, &status.node_info, "2000.01.01 - 09:00").is_empty());
+}
+
+#[test]
+fn test_changes_to_json() {
+    use rendered_status::RenderedStatus;
+    use slurm_status::PageOptions;
+
+    let mut status = SlurmStatus::new();
+    status.job_info = vec![test_job("1", "", JobState::Pending), test_job("2", "", JobState::Running)];
+    status.rendered = RenderedStatus::render(&status, &PageOptions::default());
+    let previous = StatusSnapshot::new(&status, &StatusFilter::new());
+    let old_token = previous.token.clone();
+    status.previous_snapshot = Some(previous);
+    status.job_info = vec![test_job("1", "", JobState::Running), test_job("3", "", JobState::Pending)];
+    status.rendered = RenderedStatus::render(&status, &PageOptions::default());
+    let token = status.rendered.etag.clone();
+    assert!(token != old_token);
+
+    let json = Json::from_str(&changes_to_json(&status, Some(&token), &StatusFilter::new())).unwrap();
+    assert_eq!(json.find("changed").and_then(|val| val.as_boolean()), Some(false));
+    assert_eq!(json.find("token").and_then(|val| val.as_string()), Some(&token[..]));
+    assert!(json.find("jobs_added").is_none());
+
+    let json = Json::from_str(&changes_to_json(&status, Some(&old_token), &StatusFilter::new())).unwrap();
+    assert_eq!(json.find("changed").and_then(|val| val.as_boolean()), Some(true));
+    assert_eq!(json.find("full").and_then(|val| val.as_boolean()), Some(false));
+    assert_eq!(json.find("token").and_then(|val| val.as_string()), Some(&token[..]));
+    let ids = |name: &str| json.find(name).and_then(|val| val.as_array()).unwrap().iter()
+        .map(|job| job.find("job_id").and_then(|val| val.as_string()).unwrap().to_string()).collect::<Vec<String>>();
+    assert_eq!(ids("jobs_added"), vec!["3"]);
+    assert_eq!(ids("jobs_removed"), vec!["2"]);
+    assert_eq!(ids("jobs_changed"), vec!["1"]);
+    assert_eq!(json.find_path(&["jobs_changed"]).and_then(|val| val.as_array()).unwrap()[0].find("from").and_then(|val| val.as_string()),
+        Some("Pending"));
+    assert_eq!(json.find("nodes_changed").and_then(|val| val.as_array()).map(|nodes| nodes.len()), Some(0));
+
+    // Jobs of users outside of the team (all test jobs are of user01) are neither kept nor sent
+    let team = StatusFilter::for_team(&["user02".to_string()]);
+    assert!(StatusSnapshot::new(&status, &team).jobs.is_empty());
+    let json = Json::from_str(&changes_to_json(&status, Some(&old_token), &team)).unwrap();
+    let count = |name: &str| json.find(name).and_then(|val| val.as_array()).map(|jobs| jobs.len());
+    assert_eq!((count("jobs_added"), count("jobs_changed")), (Some(0), Some(0)));
+
+    // Unknown, ancient or no token: the full status
+    for since in &[Some("0123456789abcdef"), Some(""), None] {
+        let json = Json::from_str(&changes_to_json(&status, *since, &StatusFilter::new())).unwrap();
+        assert_eq!(json.find("changed").and_then(|val| val.as_boolean()), Some(true));
+        assert_eq!(json.find("full").and_then(|val| val.as_boolean()), Some(true));
+        assert_eq!(json.find("token").and_then(|val| val.as_string()), Some(&token[..]));
+        assert_eq!(json.find("status"), Json::from_str(&status.rendered.json.plain).ok().as_ref());
+    }
+}
@@ -0,0 +1,288 @@
+//! Filters that restrict which parts of the SLURM status are shown
+//! The filters are set via query parameters, for example: /?user=willi&cluster=cluster2&job_state=pending,running&node_state=down
+//! The user and partition pages (/user/<name>, /partition/<name>) set the user or the partition from the path
+
+// Internal modules:
+use squeue_util::{JobInfo, JobState, str_to_job_state};
+use sinfo_util::{PartitionNodeInfo, NodeState, str_to_node_state};
+use sacct_util::FinishedJobInfo;
+
+/// Restricts the jobs that are shown on the web page and in the JSON API
+#[derive(Debug, Clone, PartialEq)]
+pub struct StatusFilter {
+    /// Only show jobs of this user
+    pub user: Option<String>,
+    /// Only show jobs of these users (--users), empty shows the jobs of all users
+    /// Unlike the other filters it can't be changed by the query string, ?user= only narrows it further
+    pub team: Vec<String>,
+    /// Only show jobs and nodes of this cluster
+    pub cluster: Option<String>,
+    /// Only show jobs and nodes of this partition
+    pub partition: Option<String>,
+    /// Only show jobs in one of these states, empty shows all jobs
+    pub job_states: Vec<JobState>,
+    /// Only show nodes in one of these states, empty shows all nodes
+    pub node_states: Vec<NodeState>,
+    /// State names given by the user that could not be parsed, they are reported on the page
+    pub unknown_states: Vec<String>,
+    /// Hide the jobs that have finished (see JobState::is_finished), the default of the web page (?all_jobs=)
+    /// Ignored if job states are given, so ?job_state=completed still shows the completed jobs
+    pub hide_finished: bool
+}
+
+impl StatusFilter {
+    /// Create an empty filter that shows everything
+    pub fn new() -> StatusFilter {
+        StatusFilter {
+            user: None,
+            team: Vec::new(),
+            cluster: None,
+            partition: None,
+            job_states: Vec::new(),
+            node_states: Vec::new(),
+            unknown_states: Vec::new(),
+            hide_finished: false
+        }
+    }
+
+    /// Create a filter that only shows the jobs of the given users (--users), empty shows everything
+    pub fn for_team(users: &[String]) -> StatusFilter {
+        StatusFilter { team: users.to_vec(), .. StatusFilter::new() }
+    }
+
+    /// Returns true if no filter is set, hiding the finished jobs and the team of --users are the default view and don't count
+    pub fn is_empty(&self) -> bool {
+        self.user.is_none() && self.cluster.is_none() && self.partition.is_none() && self.job_states.is_empty() && self.node_states.is_empty() && self.unknown_states.is_empty()
+    }
+
+    /// Returns true if the given user passes the filter, the user has to be in the team and match ?user=
+    pub fn user_matches(&self, user_name: &str) -> bool {
+        (self.team.is_empty() || self.team.iter().any(|member| member == user_name)) && self.user.as_ref().map_or(true, |user| user == user_name)
+    }
+
+    /// Returns true if the given cluster passes the filter
+    pub fn cluster_matches(&self, cluster_name: &str) -> bool {
+        self.cluster.as_ref().map_or(true, |cluster| cluster == cluster_name)
+    }
+
+    /// Returns true if one of the given partitions passes the filter
+    /// A pending job can be submitted to several partitions, squeue lists them separated by commas
+    pub fn partition_matches(&self, partitions: &str) -> bool {
+        self.partition.as_ref().map_or(true, |partition| partitions.split(',').any(|name| name == partition))
+    }
+
+    /// Returns true if the given job state passes the filter
+    pub fn job_state_matches(&self, job_state: JobState) -> bool {
+        self.job_states.is_empty() || self.job_states.contains(&job_state)
+    }
+
+    /// Returns true if the given node state passes the filter
+    pub fn node_state_matches(&self, node_state: NodeState) -> bool {
+        self.node_states.is_empty() || self.node_states.contains(&node_state)
+    }
+
+    /// Returns true if a job in the given state is shown, finished jobs are only hidden if no job states are given
+    pub fn finished_matches(&self, job_state: JobState) -> bool {
+        !self.hide_finished || !self.job_states.is_empty() || !job_state.is_finished()
+    }
+
+    /// Returns true if the given job passes the filter
+    pub fn job_matches(&self, job: &JobInfo) -> bool {
+        self.user_matches(&job.user_name) && self.cluster_matches(&job.cluster) && self.partition_matches(&job.partition) &&
+            self.job_state_matches(job.job_state) && self.finished_matches(job.job_state)
+    }
+
+    /// Returns the same filter, but with the finished jobs shown
+    pub fn with_finished(&self) -> StatusFilter {
+        StatusFilter { hide_finished: false, .. self.clone() }
+    }
+
+    /// Returns true if the given node passes the filter
+    pub fn node_matches(&self, node: &PartitionNodeInfo) -> bool {
+        self.cluster_matches(&node.cluster) && self.partition_matches(&node.partition) && self.node_state_matches(node.node_state)
+    }
+
+    /// Returns true if the given finished job passes the filter
+    pub fn finished_job_matches(&self, job: &FinishedJobInfo) -> bool {
+        self.user_matches(&job.user_name) && self.partition_matches(&job.partition) && self.job_state_matches(job.job_state)
+    }
+}
+
+// Private helper function to parse a comma separated list of states
+// Returns the parsed states and the names that are not valid states, parse returns the unknown state for those
+fn parse_states<T: PartialEq + Copy>(value: &str, parse: fn(&str) -> T, unknown: T) -> (Vec<T>, Vec<String>) {
+    let mut states = Vec::new();
+    let mut unknown_names = Vec::new();
+
+    for name in value.split(',').map(|name| name.trim()).filter(|name| !name.is_empty()) {
+        let state = parse(name);
+
+        if state == unknown && name.to_lowercase() != "unknown" {
+            unknown_names.push(name.to_string());
+        } else if !states.contains(&state) {
+            states.push(state);
+        }
+    }
+
+    (states, unknown_names)
+}
+
+/// Public helper function to parse a comma separated list of job states, for example "pending,running" or "pd,r"
+/// Returns the job states and the names that are not valid job states
+pub fn parse_job_states(value: &str) -> (Vec<JobState>, Vec<String>) {
+    parse_states(value, str_to_job_state, JobState::Unknown)
+}
+
+/// Public helper function to parse a comma separated list of node states, for example "down,drained"
+/// Returns the node states and the names that are not valid node states
+pub fn parse_node_states(value: &str) -> (Vec<NodeState>, Vec<String>) {
+    parse_states(value, str_to_node_state, NodeState::Unknown)
+}
+
+#[test]
+fn test_status_filter_empty() {
+    use squeue_util::get_job_info_test;
+
+    let filter = StatusFilter::new();
+    assert!(filter.is_empty());
+    assert!(get_job_info_test().iter().all(|job| filter.job_matches(job)));
+}
+
+#[test]
+fn test_status_filter_team() {
+    use squeue_util::get_job_info_test;
+    use sacct_util::get_finished_job_info_test;
+
+    let team = StatusFilter::for_team(&["user01".to_string(), "user02".to_string()]);
+    let job_ids = |filter: &StatusFilter| get_job_info_test().iter().filter(|job| filter.job_matches(job))
+        .map(|job| job.job_id.as_ref().map(|job_id| job_id.id)).collect::<Vec<Option<u32>>>();
+
+    // The team is the default view
+    assert!(team.is_empty());
+    assert!(team.user_matches("user01") && team.user_matches("user02"));
+    assert!(!team.user_matches("user03") && !team.user_matches(""));
+    assert!(get_job_info_test().iter().filter(|job| team.job_matches(job)).all(|job| job.user_name == "user01" || job.user_name == "user02"));
+    assert!(get_finished_job_info_test().iter().filter(|job| team.finished_job_matches(job)).all(|job| job.user_name == "user01" || job.user_name == "user02"));
+
+    // ?user= of a team member narrows the team down to this user
+    let member = StatusFilter{ user: Some("user02".to_string()), .. team.clone() };
+    assert_eq!(job_ids(&member), vec![Some(2), Some(8), Some(9)]);
+    assert!(!member.user_matches("user01"));
+
+    // ?user= outside of the team doesn't escape it, nothing is shown
+    let outsider = StatusFilter{ user: Some("user03".to_string()), .. team.clone() };
+    assert_eq!(job_ids(&outsider), vec![]);
+    assert!(!outsider.user_matches("user03"));
+    assert_eq!(job_ids(&StatusFilter{ user: Some("user03".to_string()), .. StatusFilter::new() }), vec![Some(3), Some(10)]);
+
+    // Without team every user passes
+    assert!(StatusFilter::for_team(&[]).user_matches("user03"));
+    assert_eq!(StatusFilter::for_team(&[]), StatusFilter::new());
+}
+
+#[test]
+fn test_status_filter_user() {
+    use squeue_util::get_job_info_test;
+
+    let filter = StatusFilter{ user: Some("user02".to_string()), .. StatusFilter::new() };
+    let jobs: Vec<Option<u32>> = get_job_info_test().iter().filter(|job| filter.job_matches(job)).map(|job| job.job_id.as_ref().map(|job_id| job_id.id)).collect();
+
+    assert!(!filter.is_empty());
+    assert_eq!(jobs, vec![Some(2), Some(8), Some(9)]);
+}
+
+#[test]
+fn test_status_filter_unknown_user() {
+    use squeue_util::get_job_info_test;
+
+    let filter = StatusFilter{ user: Some("nobody".to_string()), .. StatusFilter::new() };
+    assert_eq!(get_job_info_test().iter().filter(|job| filter.job_matches(job)).count(), 0);
+}
+
+#[test]
+fn test_status_filter_cluster() {
+    use squeue_util::get_job_info_test;
+
+    let mut jobs = get_job_info_test();
+    jobs[0].cluster = "cluster2".to_string();
+    let filter = StatusFilter{ cluster: Some("cluster2".to_string()), .. StatusFilter::new() };
+
+    assert!(!filter.is_empty());
+    assert_eq!(jobs.iter().filter(|job| filter.job_matches(job)).count(), 1);
+}
+
+#[test]
+fn test_status_filter_partition() {
+    use squeue_util::get_job_info_test;
+    use sinfo_util::get_partition_node_info_test;
+
+    let mut jobs = get_job_info_test();
+    jobs[0].partition = "gpu".to_string();
+    jobs[1].partition = "esd,gpu".to_string();
+    let mut nodes = get_partition_node_info_test();
+    nodes[0].partition = "gpu".to_string();
+    let filter = StatusFilter{ partition: Some("gpu".to_string()), .. StatusFilter::new() };
+
+    assert!(!filter.is_empty());
+    assert_eq!(jobs.iter().filter(|job| filter.job_matches(job)).count(), 2);
+    assert_eq!(nodes.iter().filter(|node| filter.node_matches(node)).map(|node| &node.node[..]).collect::<Vec<&str>>(), vec!["node01"]);
+    assert!(!filter.partition_matches("gpu2"));
+    assert!(StatusFilter::new().partition_matches("esd"));
+}
+
+#[test]
+fn test_status_filter_state() {
+    use squeue_util::get_job_info_test;
+
+    let filter = StatusFilter{ job_states: vec![JobState::Pending, JobState::Running], .. StatusFilter::new() };
+    let jobs: Vec<Option<u32>> = get_job_info_test().iter().filter(|job| filter.job_matches(job)).map(|job| job.job_id.as_ref().map(|job_id| job_id.id)).collect();
+
+    assert!(!filter.is_empty());
+    assert_eq!(jobs, vec![Some(1), Some(8)]);
+}
+
+#[test]
+fn test_status_filter_hide_finished() {
+    use squeue_util::get_job_info_test;
+
+    let mut jobs = get_job_info_test();
+    for job in &mut jobs {
+        job.job_state = JobState::Running;
+    }
+    jobs[0].job_state = JobState::Completed;
+    jobs[1].job_state = JobState::Timeout;
+    jobs[2].job_state = JobState::Completing;
+    let filter = StatusFilter{ hide_finished: true, .. StatusFilter::new() };
+
+    assert!(filter.is_empty());
+    assert_eq!(jobs.iter().filter(|job| filter.job_matches(job)).count(), jobs.len() - 2);
+    assert_eq!(jobs.iter().filter(|job| filter.with_finished().job_matches(job)).count(), jobs.len());
+    // Explicitly asked for
+    let filter = StatusFilter{ job_states: vec![JobState::Completed], .. filter };
+    assert_eq!(jobs.iter().filter(|job| filter.job_matches(job)).count(), 1);
+}
+
+#[test]
+fn test_status_filter_node_state() {
+    use sinfo_util::get_partition_node_info_test;
+
+    let filter = StatusFilter{ node_states: vec![NodeState::Idle, NodeState::Fail], .. StatusFilter::new() };
+    let nodes: Vec<String> = get_partition_node_info_test().iter().filter(|node| filter.node_matches(node)).map(|node| node.node.clone()).collect();
+
+    assert_eq!(nodes, vec!["node01", "node02", "node04", "node09"]);
+}
+
+#[test]
+fn test_parse_job_states() {
+    assert_eq!(parse_job_states("pending,running"), (vec![JobState::Pending, JobState::Running], vec![]));
+    assert_eq!(parse_job_states(" PD , r,pending,"), (vec![JobState::Pending, JobState::Running], vec![]));
+    assert_eq!(parse_job_states("unknown"), (vec![JobState::Unknown], vec![]));
+    assert_eq!(parse_job_states("running,sleeping"), (vec![JobState::Running], vec!["sleeping".to_string()]));
+    assert_eq!(parse_job_states(""), (vec![], vec![]));
+}
+
+#[test]
+fn test_parse_node_states() {
+    assert_eq!(parse_node_states("down,Drained,MIX"), (vec![NodeState::Down, NodeState::Drained, NodeState::Mixed], vec![]));
+    assert_eq!(parse_node_states("down,broken"), (vec![NodeState::Down], vec!["broken".to_string()]));
+}
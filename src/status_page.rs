@@ -0,0 +1,2030 @@
+//! View-model of the HTML status page: the SLURM status prepared for the page templates
+//! All values are already formatted (missing values are "-"), so the templates only contain the markup
+
+// System modules:
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::Display;
+
+// External modules:
+use rustc_serialize::json::{Json, ToJson};
+use time::get_time;
+
+// Internal modules:
+use sinfo_util::{PartitionNodeInfo, PartitionAvailability, DownReason, LoadLevel};
+use squeue_util::{JobInfo, JobState};
+use scontrol_util::{ReservationInfo, LicenseInfo};
+use sacctmgr_util::{QosInfo, qos_limits_text};
+use slurm_status::{SlurmStatus, PageOptions, PartitionSummary, UserUsage, DashboardSummary, dashboard_summary, partition_summaries, usage_by_user, jobs_by_node,
+    hidden_partitions_notice, failures_notice, skipped_lines_notice, team_notice, own_jobs_notice};
+use gres::gpus_to_text;
+use job_arrays::{JobArraySummary, JobGroup, group_job_arrays};
+use pagination::{PageLinks, page_links, query_with_param};
+use backlog_chart::{backlog_chart, backlog_samples};
+use hostlist::{compress_hostlist, compress_hostlist_entries, expand_hostlist};
+use time_limit::{RemainingTime, format_duration, format_duration_short};
+use admin_actions::{AdminNotice, AdminAction, node_action};
+use display_time::DisplayTime;
+use columns::{Cell, Column, job_columns, node_columns, selected_columns, row_cells, column_ids};
+use top_jobs::{TopJob, TOP_JOBS, longest_running, longest_waiting};
+use sprio_util::{priorities_by_job, job_priority};
+use occupancy_grid::{GridPartition, occupancy_grid};
+use job_dependencies::{DependencyKind, DependencyGraph};
+use config_view::ConfigEntry;
+use scheduler_pressure::{PressureLevel, PressureThresholds, Threshold, pressure_level};
+use job_sort::{sort_jobs, format_priority};
+
+/// Maximum number of node ranges in the job table, for example "node[001-256]" is one range
+const MAX_NODE_RANGES: usize = 8;
+
+/// A link to another row of the page
+#[derive(Debug, Clone, PartialEq)]
+pub struct Link {
+    /// HTML fragment id of the row
+    pub anchor: String,
+    pub text: String,
+    /// CSS class of the link, for example "dependency_stuck"
+    pub class: Option<String>
+}
+
+impl ToJson for Link {
+    fn to_json(&self) -> Json {
+        let mut result = BTreeMap::new();
+        result.insert("anchor".to_string(), self.anchor.to_json());
+        result.insert("text".to_string(), self.text.to_json());
+        result.insert("class".to_string(), self.class.to_json());
+        Json::Object(result)
+    }
+}
+
+/// One entry of the recent events list
+#[derive(Debug, Clone, PartialEq)]
+pub struct EventRow {
+    pub time: String,
+    pub text: String,
+    /// The event needs attention, for example a node that went down
+    pub alarm: bool
+}
+
+impl ToJson for EventRow {
+    fn to_json(&self) -> Json {
+        let mut result = BTreeMap::new();
+        result.insert("time".to_string(), self.time.to_json());
+        result.insert("text".to_string(), self.text.to_json());
+        result.insert("alarm".to_string(), self.alarm.to_json());
+        Json::Object(result)
+    }
+}
+
+/// One headline number of the dashboard
+#[derive(Debug, Clone, PartialEq)]
+pub struct DashboardBox {
+    /// The formatted number, for example "12", "2.5" or "1h 30m"
+    pub number: String,
+    pub label: String,
+    /// The number is a problem (for example nodes down) and not zero
+    pub problem: bool,
+    /// Color of the scheduler pressure indicators, see scheduler_pressure::pressure_level
+    pub level: PressureLevel
+}
+
+impl ToJson for DashboardBox {
+    fn to_json(&self) -> Json {
+        let mut result = BTreeMap::new();
+        result.insert("number".to_string(), self.number.to_json());
+        result.insert("label".to_string(), self.label.to_json());
+        result.insert("problem".to_string(), self.problem.to_json());
+        result.insert("level".to_string(), self.level.css_class().map(|class| class.to_string()).to_json());
+        Json::Object(result)
+    }
+}
+
+// Private helper function that returns the second row of the dashboard, the scheduler pressure indicators with their color
+fn pressure_boxes(summary: &DashboardSummary, thresholds: &PressureThresholds) -> Vec<DashboardBox> {
+    let pressure_box = |number: String, label: &str, value: f64, threshold: Option<Threshold>| DashboardBox {
+        number: number,
+        label: label.to_string(),
+        problem: false,
+        level: pressure_level(value, threshold)
+    };
+    let oldest_pending = summary.oldest_pending.unwrap_or(0);
+    let ratio = summary.pending_ratio();
+
+    vec![
+        pressure_box(summary.pending_users.to_string(), "Users waiting", summary.pending_users as f64, thresholds.pending_users),
+        pressure_box(summary.oldest_pending.map_or("-".to_string(), format_duration_short), "Oldest pending job",
+            oldest_pending as f64 / 3600.0, thresholds.oldest_pending),
+        pressure_box(format!("{:.1}", ratio), "Pending per running job", ratio, thresholds.pending_ratio),
+        pressure_box(summary.pending_resources.to_string(), "Waiting for resources", summary.pending_resources as f64, thresholds.pending_resources),
+        pressure_box(summary.pending_priority.to_string(), "Waiting for priority", summary.pending_priority as f64, thresholds.pending_priority)
+    ]
+}
+
+/// One row of the down/drained nodes table, nodes with the same reason are grouped like in "sinfo -R"
+#[derive(Debug, Clone, PartialEq)]
+pub struct DownNodeRow {
+    pub cluster: String,
+    /// Compressed hostlist, for example "node[07-08]"
+    pub nodes: String,
+    pub reason: String,
+    pub set_by: String,
+    pub since: String
+}
+
+impl ToJson for DownNodeRow {
+    fn to_json(&self) -> Json {
+        let mut result = BTreeMap::new();
+        result.insert("cluster".to_string(), self.cluster.to_json());
+        result.insert("nodes".to_string(), self.nodes.to_json());
+        result.insert("reason".to_string(), self.reason.to_json());
+        result.insert("set_by".to_string(), self.set_by.to_json());
+        result.insert("since".to_string(), self.since.to_json());
+        Json::Object(result)
+    }
+}
+
+/// One row of the reservations table
+#[derive(Debug, Clone, PartialEq)]
+pub struct ReservationRow {
+    /// "reservation_active", "reservation_upcoming" or None
+    pub class: Option<String>,
+    pub name: String,
+    pub start_time: String,
+    pub end_time: String,
+    pub nodes: String,
+    pub users: String,
+    pub accounts: String,
+    pub flags: String
+}
+
+impl ToJson for ReservationRow {
+    fn to_json(&self) -> Json {
+        let mut result = BTreeMap::new();
+        result.insert("class".to_string(), self.class.to_json());
+        result.insert("name".to_string(), self.name.to_json());
+        result.insert("start_time".to_string(), self.start_time.to_json());
+        result.insert("end_time".to_string(), self.end_time.to_json());
+        result.insert("nodes".to_string(), self.nodes.to_json());
+        result.insert("users".to_string(), self.users.to_json());
+        result.insert("accounts".to_string(), self.accounts.to_json());
+        result.insert("flags".to_string(), self.flags.to_json());
+        Json::Object(result)
+    }
+}
+
+/// One row of the licenses table
+#[derive(Debug, Clone, PartialEq)]
+pub struct LicenseRow {
+    /// "license_exhausted" if no license is free, otherwise None
+    pub class: Option<String>,
+    pub name: String,
+    pub total: String,
+    pub used: String,
+    pub free: String,
+    pub reserved: String
+}
+
+impl ToJson for LicenseRow {
+    fn to_json(&self) -> Json {
+        let mut result = BTreeMap::new();
+        result.insert("class".to_string(), self.class.to_json());
+        result.insert("name".to_string(), self.name.to_json());
+        result.insert("total".to_string(), self.total.to_json());
+        result.insert("used".to_string(), self.used.to_json());
+        result.insert("free".to_string(), self.free.to_json());
+        result.insert("reserved".to_string(), self.reserved.to_json());
+        Json::Object(result)
+    }
+}
+
+/// One row of the QOS table, limits that the QOS doesn't set are "-"
+#[derive(Debug, Clone, PartialEq)]
+pub struct QosRow {
+    pub name: String,
+    pub priority: String,
+    pub max_wall: String,
+    /// Trackable resources per user, for example "cpu=64,gres/gpu=4"
+    pub max_tres_per_user: String,
+    pub max_jobs_per_user: String,
+    pub max_submit_per_user: String
+}
+
+impl ToJson for QosRow {
+    fn to_json(&self) -> Json {
+        let mut result = BTreeMap::new();
+        result.insert("name".to_string(), self.name.to_json());
+        result.insert("priority".to_string(), self.priority.to_json());
+        result.insert("max_wall".to_string(), self.max_wall.to_json());
+        result.insert("max_tres_per_user".to_string(), self.max_tres_per_user.to_json());
+        result.insert("max_jobs_per_user".to_string(), self.max_jobs_per_user.to_json());
+        result.insert("max_submit_per_user".to_string(), self.max_submit_per_user.to_json());
+        Json::Object(result)
+    }
+}
+
+/// One row of the partition table, the limits are "-" if scontrol doesn't know the partition
+#[derive(Debug, Clone, PartialEq)]
+pub struct PartitionRow {
+    pub cluster: String,
+    pub name: String,
+    pub available: bool,
+    pub nodes: u32,
+    /// Number of nodes per node state, for example "Allocated 1, Idle 3"
+    pub node_states: String,
+    /// Empty if the partition has no GPUs
+    pub gpus: String,
+    /// Used and total memory of the nodes, "-" if the nodes don't report their memory
+    pub memory: String,
+    /// Number of pending jobs and their min / median / 95% / max wait time, "-" if there are no pending jobs
+    pub pending_jobs: String,
+    pub wait_times: String,
+    pub max_time: String,
+    pub default_time: String,
+    pub max_nodes: String,
+    pub max_cpus_per_node: String,
+    pub state: String,
+    pub allow_groups: String
+}
+
+impl ToJson for PartitionRow {
+    fn to_json(&self) -> Json {
+        let mut result = BTreeMap::new();
+        result.insert("cluster".to_string(), self.cluster.to_json());
+        result.insert("name".to_string(), self.name.to_json());
+        result.insert("available".to_string(), self.available.to_json());
+        result.insert("nodes".to_string(), self.nodes.to_json());
+        result.insert("node_states".to_string(), self.node_states.to_json());
+        result.insert("gpus".to_string(), self.gpus.to_json());
+        result.insert("memory".to_string(), self.memory.to_json());
+        result.insert("pending_jobs".to_string(), self.pending_jobs.to_json());
+        result.insert("wait_times".to_string(), self.wait_times.to_json());
+        result.insert("max_time".to_string(), self.max_time.to_json());
+        result.insert("default_time".to_string(), self.default_time.to_json());
+        result.insert("max_nodes".to_string(), self.max_nodes.to_json());
+        result.insert("max_cpus_per_node".to_string(), self.max_cpus_per_node.to_json());
+        result.insert("state".to_string(), self.state.to_json());
+        result.insert("allow_groups".to_string(), self.allow_groups.to_json());
+        Json::Object(result)
+    }
+}
+
+/// One row of the node table
+#[derive(Debug, Clone, PartialEq)]
+pub struct NodeRow {
+    /// Fragment id, only set in the first row of a node that is in several partitions
+    pub anchor: Option<String>,
+    pub cluster: String,
+    pub partition: String,
+    /// Number of rows of the partition group, only set in the first row of the group
+    pub partition_rows: Option<usize>,
+    pub available: bool,
+    pub hostname: String,
+    pub node: String,
+    /// Fragment id of the first job on the node
+    pub job_anchor: Option<String>,
+    pub error: String,
+    /// Why the node is down or drained, shown as tooltip of the node state
+    pub reason: Option<String>,
+    pub cpu_load: String,
+    /// CPU load relative to the cores, for example "0.22 / 8 (3%)", the CPU load if the cores are not known
+    pub relative_load: String,
+    /// CSS class of an oversubscribed or an allocated but idle node
+    pub load_class: Option<String>,
+    pub state: String,
+    /// Symbols of the node flags and their descriptions, empty if there are no flags
+    pub flags: String,
+    pub flag_descriptions: String,
+    pub sockets: String,
+    pub cores: String,
+    pub threads: String,
+    /// Allocated/idle/other/total CPUs
+    pub cpus: String,
+    pub utilization: String,
+    /// The CPU load doesn't match the allocation
+    pub load_warning: bool,
+    /// Used and total memory, for example "3.9 GB / 31.2 GB (12%)", "-" for the used memory if the node doesn't report its free memory
+    pub memory: String,
+    /// Percentage of the memory in use, rounded, for the bar and the sorting of the memory column
+    pub memory_percent: Option<u32>,
+    /// More than MEMORY_WARNING_PERCENT of the memory is in use
+    pub memory_warning: bool,
+    pub gpus: String,
+    pub jobs: Vec<Link>,
+    /// Route of the admin action offered for the node (drain or resume) and its name, None for nodes of other clusters
+    pub action_route: Option<String>,
+    pub action_name: Option<String>,
+    /// Cells of the shown columns, see columns::node_columns
+    pub cells: Vec<Cell>
+}
+
+impl ToJson for NodeRow {
+    fn to_json(&self) -> Json {
+        let mut result = BTreeMap::new();
+        result.insert("anchor".to_string(), self.anchor.to_json());
+        result.insert("cluster".to_string(), self.cluster.to_json());
+        result.insert("partition".to_string(), self.partition.to_json());
+        result.insert("partition_rows".to_string(), self.partition_rows.to_json());
+        result.insert("available".to_string(), self.available.to_json());
+        result.insert("hostname".to_string(), self.hostname.to_json());
+        result.insert("node".to_string(), self.node.to_json());
+        result.insert("job_anchor".to_string(), self.job_anchor.to_json());
+        result.insert("error".to_string(), self.error.to_json());
+        result.insert("reason".to_string(), self.reason.to_json());
+        result.insert("cpu_load".to_string(), self.cpu_load.to_json());
+        result.insert("relative_load".to_string(), self.relative_load.to_json());
+        result.insert("load_class".to_string(), self.load_class.to_json());
+        result.insert("state".to_string(), self.state.to_json());
+        result.insert("flags".to_string(), self.flags.to_json());
+        result.insert("flag_descriptions".to_string(), self.flag_descriptions.to_json());
+        result.insert("sockets".to_string(), self.sockets.to_json());
+        result.insert("cores".to_string(), self.cores.to_json());
+        result.insert("threads".to_string(), self.threads.to_json());
+        result.insert("cpus".to_string(), self.cpus.to_json());
+        result.insert("utilization".to_string(), self.utilization.to_json());
+        result.insert("load_warning".to_string(), self.load_warning.to_json());
+        result.insert("memory".to_string(), self.memory.to_json());
+        result.insert("memory_percent".to_string(), self.memory_percent.to_json());
+        result.insert("memory_warning".to_string(), self.memory_warning.to_json());
+        result.insert("gpus".to_string(), self.gpus.to_json());
+        result.insert("jobs".to_string(), self.jobs.to_json());
+        result.insert("action_route".to_string(), self.action_route.to_json());
+        result.insert("action_name".to_string(), self.action_name.to_json());
+        result.insert("cells".to_string(), self.cells.to_json());
+        Json::Object(result)
+    }
+}
+
+/// One row of the job table
+#[derive(Debug, Clone, PartialEq)]
+pub struct JobRow {
+    /// Fragment id, None if the job has no ID
+    pub anchor: Option<String>,
+    /// Job steps (only with --show-job-steps) follow their job and are indented
+    pub is_step: bool,
+    /// The job belongs to the user of the request (?me= or --remote-user-header) and is highlighted
+    pub own_job: bool,
+    pub cluster: String,
+    pub executing_host: String,
+    pub minimum_cpu: String,
+    pub num_cpu: String,
+    pub num_nodes: String,
+    pub gpus: String,
+    pub job_array_id: String,
+    pub num_sockets: String,
+    pub job_id: String,
+    pub num_cores: String,
+    pub job_name: String,
+    pub num_threads: String,
+    pub job_array_index: String,
+    pub run_time: String,
+    /// Time until a running job reaches its time limit and percentage used, "∞" without limit, "-" for the other jobs
+    pub remaining: String,
+    /// CSS class of jobs that are close to their time limit
+    pub remaining_class: Option<String>,
+    /// Remaining seconds for sorting (data-sort attribute), empty if there is no remaining time
+    pub remaining_sort: String,
+    /// The nodes as compressed hostlist, each entry links to its first node
+    pub nodes: Vec<Link>,
+    /// Number of ranges that are left out, the full compressed hostlist is in hostlist
+    pub more_ranges: usize,
+    pub hostlist: String,
+    /// The priority rounded to job_sort::PRIORITY_DIGITS significant digits, the exact value of squeue is priority_raw
+    pub priority: String,
+    pub priority_raw: Option<String>,
+    /// Weighted priority factors of sprio for pending jobs (--enable-sprio), shown as tooltip of the priority
+    pub priority_factors: Option<String>,
+    pub state_reason: String,
+    /// The jobs this job depends on, each one links to its row, "singleton" is not a link
+    pub depends_on: Vec<Link>,
+    pub singleton: bool,
+    /// All dependencies as text, for example "afterok:12345,singleton", "-" if the job has none
+    pub dependencies: String,
+    /// Limits of the QOS if a pending job waits for one of them (--enable-qos), shown as tooltip of the state reason
+    pub qos_limits: Option<String>,
+    pub start_time: String,
+    /// Submit time and wait time of pending jobs, for example "2015-12-18T09:00:00 (1:00:00)", "-" for the other jobs
+    pub waiting_since: String,
+    pub job_state: String,
+    /// CSS class of job states that need attention
+    pub state_class: Option<String>,
+    pub user_name: String,
+    pub user_id: String,
+    /// Job ID for the cancel button of the admin actions, only for plain jobs of the local cluster (not array tasks or steps)
+    pub cancel_id: Option<u32>,
+    /// Cells of the shown columns, see columns::job_columns
+    pub cells: Vec<Cell>
+}
+
+impl JobRow {
+    /// CSS classes of the row: "job_step" for job steps and "own_job" for the jobs of the user of the request, None for the other rows
+    pub fn row_class(&self) -> Option<String> {
+        let mut classes = Vec::new();
+        if self.is_step {
+            classes.push("job_step");
+        }
+        if self.own_job {
+            classes.push("own_job");
+        }
+        if classes.is_empty() { None } else { Some(classes.join(" ")) }
+    }
+}
+
+impl ToJson for JobRow {
+    fn to_json(&self) -> Json {
+        let mut result = BTreeMap::new();
+        result.insert("anchor".to_string(), self.anchor.to_json());
+        result.insert("is_step".to_string(), self.is_step.to_json());
+        result.insert("own_job".to_string(), self.own_job.to_json());
+        result.insert("row_class".to_string(), self.row_class().to_json());
+        result.insert("cluster".to_string(), self.cluster.to_json());
+        result.insert("executing_host".to_string(), self.executing_host.to_json());
+        result.insert("minimum_cpu".to_string(), self.minimum_cpu.to_json());
+        result.insert("num_cpu".to_string(), self.num_cpu.to_json());
+        result.insert("num_nodes".to_string(), self.num_nodes.to_json());
+        result.insert("gpus".to_string(), self.gpus.to_json());
+        result.insert("job_array_id".to_string(), self.job_array_id.to_json());
+        result.insert("num_sockets".to_string(), self.num_sockets.to_json());
+        result.insert("job_id".to_string(), self.job_id.to_json());
+        result.insert("num_cores".to_string(), self.num_cores.to_json());
+        result.insert("job_name".to_string(), self.job_name.to_json());
+        result.insert("num_threads".to_string(), self.num_threads.to_json());
+        result.insert("job_array_index".to_string(), self.job_array_index.to_json());
+        result.insert("run_time".to_string(), self.run_time.to_json());
+        result.insert("remaining".to_string(), self.remaining.to_json());
+        result.insert("remaining_class".to_string(), self.remaining_class.to_json());
+        result.insert("remaining_sort".to_string(), self.remaining_sort.to_json());
+        result.insert("nodes".to_string(), self.nodes.to_json());
+        result.insert("more_ranges".to_string(), self.more_ranges.to_json());
+        result.insert("hostlist".to_string(), self.hostlist.to_json());
+        result.insert("priority".to_string(), self.priority.to_json());
+        result.insert("priority_raw".to_string(), self.priority_raw.to_json());
+        result.insert("priority_factors".to_string(), self.priority_factors.to_json());
+        result.insert("state_reason".to_string(), self.state_reason.to_json());
+        result.insert("depends_on".to_string(), self.depends_on.to_json());
+        result.insert("singleton".to_string(), self.singleton.to_json());
+        result.insert("dependencies".to_string(), self.dependencies.to_json());
+        result.insert("qos_limits".to_string(), self.qos_limits.to_json());
+        result.insert("start_time".to_string(), self.start_time.to_json());
+        result.insert("waiting_since".to_string(), self.waiting_since.to_json());
+        result.insert("job_state".to_string(), self.job_state.to_json());
+        result.insert("state_class".to_string(), self.state_class.to_json());
+        result.insert("user_name".to_string(), self.user_name.to_json());
+        result.insert("user_id".to_string(), self.user_id.to_json());
+        result.insert("cancel_id".to_string(), self.cancel_id.to_json());
+        result.insert("cells".to_string(), self.cells.to_json());
+        Json::Object(result)
+    }
+}
+
+/// A job array shown as one row that can be expanded to show the tasks
+#[derive(Debug, Clone, PartialEq)]
+pub struct JobArrayRow {
+    pub anchor: String,
+    pub cluster: String,
+    pub array_id: u32,
+    pub job_name: String,
+    pub user_name: String,
+    pub num_tasks: u32,
+    /// Number of tasks per job state, for example "Pending 1, Running 2"
+    pub state_counts: String,
+    /// Shortest and longest run time, for example "0:00 - 1:02:03", None if no task has a run time
+    pub run_time: Option<String>,
+    pub tasks: Vec<JobRow>
+}
+
+impl ToJson for JobArrayRow {
+    fn to_json(&self) -> Json {
+        let mut result = BTreeMap::new();
+        result.insert("anchor".to_string(), self.anchor.to_json());
+        result.insert("cluster".to_string(), self.cluster.to_json());
+        result.insert("array_id".to_string(), self.array_id.to_json());
+        result.insert("job_name".to_string(), self.job_name.to_json());
+        result.insert("user_name".to_string(), self.user_name.to_json());
+        result.insert("num_tasks".to_string(), self.num_tasks.to_json());
+        result.insert("state_counts".to_string(), self.state_counts.to_json());
+        result.insert("run_time".to_string(), self.run_time.to_json());
+        result.insert("tasks".to_string(), self.tasks.to_json());
+        Json::Object(result)
+    }
+}
+
+/// A row of the job table, the templates check for "job" or "array"
+#[derive(Debug, Clone, PartialEq)]
+pub enum JobGroupRow {
+    Single(JobRow),
+    Array(JobArrayRow)
+}
+
+impl ToJson for JobGroupRow {
+    fn to_json(&self) -> Json {
+        let mut result = BTreeMap::new();
+        match *self {
+            JobGroupRow::Single(ref row) => result.insert("job".to_string(), row.to_json()),
+            JobGroupRow::Array(ref row) => result.insert("array".to_string(), row.to_json())
+        };
+        Json::Object(result)
+    }
+}
+
+/// Length of the job queue over the last hour
+#[derive(Debug, Clone, PartialEq)]
+pub struct QueueRow {
+    pub min: u32,
+    pub max: u32,
+    /// Average with one decimal
+    pub avg: String,
+    /// Number of updates in the last hour
+    pub samples: usize
+}
+
+impl ToJson for QueueRow {
+    fn to_json(&self) -> Json {
+        let mut result = BTreeMap::new();
+        result.insert("min".to_string(), self.min.to_json());
+        result.insert("max".to_string(), self.max.to_json());
+        result.insert("avg".to_string(), self.avg.to_json());
+        result.insert("samples".to_string(), self.samples.to_json());
+        Json::Object(result)
+    }
+}
+
+/// One row of the finished jobs table
+#[derive(Debug, Clone, PartialEq)]
+pub struct FinishedJobRow {
+    pub job_id: String,
+    pub job_name: String,
+    pub user_name: String,
+    pub partition: String,
+    pub job_state: String,
+    pub state_class: Option<String>,
+    /// Exit status and signal, for example "1:0"
+    pub exit_code: String,
+    /// A guess why the job failed, for example "exit code 1"
+    pub exit_hint: Option<String>,
+    pub elapsed: String,
+    pub end_time: String
+}
+
+impl ToJson for FinishedJobRow {
+    fn to_json(&self) -> Json {
+        let mut result = BTreeMap::new();
+        result.insert("job_id".to_string(), self.job_id.to_json());
+        result.insert("job_name".to_string(), self.job_name.to_json());
+        result.insert("user_name".to_string(), self.user_name.to_json());
+        result.insert("partition".to_string(), self.partition.to_json());
+        result.insert("job_state".to_string(), self.job_state.to_json());
+        result.insert("state_class".to_string(), self.state_class.to_json());
+        result.insert("exit_code".to_string(), self.exit_code.to_json());
+        result.insert("exit_hint".to_string(), self.exit_hint.to_json());
+        result.insert("elapsed".to_string(), self.elapsed.to_json());
+        result.insert("end_time".to_string(), self.end_time.to_json());
+        Json::Object(result)
+    }
+}
+
+/// One row of the recently finished jobs, jobs that disappeared from squeue in the last minutes
+#[derive(Debug, Clone, PartialEq)]
+pub struct RecentJobRow {
+    pub job_id: String,
+    pub job_name: String,
+    pub user_name: String,
+    pub partition: String,
+    pub last_state: String,
+    /// Time of the last update that still contained the job, format: %Y.%m.%d - %H:%M
+    pub last_seen: String
+}
+
+impl ToJson for RecentJobRow {
+    fn to_json(&self) -> Json {
+        let mut result = BTreeMap::new();
+        result.insert("job_id".to_string(), self.job_id.to_json());
+        result.insert("job_name".to_string(), self.job_name.to_json());
+        result.insert("user_name".to_string(), self.user_name.to_json());
+        result.insert("partition".to_string(), self.partition.to_json());
+        result.insert("last_state".to_string(), self.last_state.to_json());
+        result.insert("last_seen".to_string(), self.last_seen.to_json());
+        Json::Object(result)
+    }
+}
+
+/// One row of the longest running or the longest waiting jobs, linked to the row of the job in the job table
+#[derive(Debug, Clone, PartialEq)]
+pub struct TopJobRow {
+    pub job_id: String,
+    pub anchor: String,
+    pub user_name: String,
+    pub partition: String,
+    /// Run or wait time, format: [days-]hours:minutes:seconds
+    pub duration: String
+}
+
+impl ToJson for TopJobRow {
+    fn to_json(&self) -> Json {
+        let mut result = BTreeMap::new();
+        result.insert("job_id".to_string(), self.job_id.to_json());
+        result.insert("anchor".to_string(), self.anchor.to_json());
+        result.insert("user_name".to_string(), self.user_name.to_json());
+        result.insert("partition".to_string(), self.partition.to_json());
+        result.insert("duration".to_string(), self.duration.to_json());
+        Json::Object(result)
+    }
+}
+
+// Private helper function that converts the longest running or waiting jobs into rows
+fn top_job_rows(top_jobs: &[TopJob]) -> Vec<TopJobRow> {
+    top_jobs.iter().map(|job| TopJobRow {
+        job_id: job.job_id.to_string(),
+        anchor: html_anchor("job", &job.cluster, &job.job_id.to_string()),
+        user_name: job.user_name.clone(),
+        partition: job.partition.clone(),
+        duration: format_duration(job.seconds)
+    }).collect()
+}
+
+/// The job counters of the scheduler statistics
+#[derive(Debug, Clone, PartialEq)]
+pub struct SchedulerRow {
+    pub jobs_submitted: String,
+    pub jobs_started: String,
+    pub jobs_completed: String,
+    pub jobs_canceled: String,
+    pub jobs_failed: String,
+    pub server_threads: String,
+    pub agent_queue_size: String,
+    pub rpc_count: String
+}
+
+impl ToJson for SchedulerRow {
+    fn to_json(&self) -> Json {
+        let mut result = BTreeMap::new();
+        result.insert("jobs_submitted".to_string(), self.jobs_submitted.to_json());
+        result.insert("jobs_started".to_string(), self.jobs_started.to_json());
+        result.insert("jobs_completed".to_string(), self.jobs_completed.to_json());
+        result.insert("jobs_canceled".to_string(), self.jobs_canceled.to_json());
+        result.insert("jobs_failed".to_string(), self.jobs_failed.to_json());
+        result.insert("server_threads".to_string(), self.server_threads.to_json());
+        result.insert("agent_queue_size".to_string(), self.agent_queue_size.to_json());
+        result.insert("rpc_count".to_string(), self.rpc_count.to_json());
+        Json::Object(result)
+    }
+}
+
+/// The cycle statistics of the main or the backfill scheduler
+#[derive(Debug, Clone, PartialEq)]
+pub struct SchedulerCycleRow {
+    /// "Main" or "Backfill"
+    pub name: String,
+    pub last_cycle: String,
+    pub mean_cycle: String,
+    pub max_cycle: String,
+    pub total_cycles: String,
+    pub depth: String,
+    pub queue_length: String
+}
+
+impl ToJson for SchedulerCycleRow {
+    fn to_json(&self) -> Json {
+        let mut result = BTreeMap::new();
+        result.insert("name".to_string(), self.name.to_json());
+        result.insert("last_cycle".to_string(), self.last_cycle.to_json());
+        result.insert("mean_cycle".to_string(), self.mean_cycle.to_json());
+        result.insert("max_cycle".to_string(), self.max_cycle.to_json());
+        result.insert("total_cycles".to_string(), self.total_cycles.to_json());
+        result.insert("depth".to_string(), self.depth.to_json());
+        result.insert("queue_length".to_string(), self.queue_length.to_json());
+        Json::Object(result)
+    }
+}
+
+/// One row of the fair-share table
+#[derive(Debug, Clone, PartialEq)]
+pub struct ShareRow {
+    pub account: String,
+    pub user: String,
+    pub raw_shares: String,
+    pub norm_shares: String,
+    pub raw_usage: String,
+    pub effective_usage: String,
+    pub fair_share: String
+}
+
+impl ToJson for ShareRow {
+    fn to_json(&self) -> Json {
+        let mut result = BTreeMap::new();
+        result.insert("account".to_string(), self.account.to_json());
+        result.insert("user".to_string(), self.user.to_json());
+        result.insert("raw_shares".to_string(), self.raw_shares.to_json());
+        result.insert("norm_shares".to_string(), self.norm_shares.to_json());
+        result.insert("raw_usage".to_string(), self.raw_usage.to_json());
+        result.insert("effective_usage".to_string(), self.effective_usage.to_json());
+        result.insert("fair_share".to_string(), self.fair_share.to_json());
+        Json::Object(result)
+    }
+}
+
+/// One row of the usage table of this month, the CPU hours have one decimal
+#[derive(Debug, Clone, PartialEq)]
+pub struct UsageRow {
+    pub account: String,
+    /// "-" for the total of the account
+    pub user: String,
+    pub cpu_minutes: u64,
+    pub cpu_hours: String
+}
+
+impl ToJson for UsageRow {
+    fn to_json(&self) -> Json {
+        let mut result = BTreeMap::new();
+        result.insert("account".to_string(), self.account.to_json());
+        result.insert("user".to_string(), self.user.to_json());
+        result.insert("cpu_minutes".to_string(), self.cpu_minutes.to_json());
+        result.insert("cpu_hours".to_string(), self.cpu_hours.to_json());
+        Json::Object(result)
+    }
+}
+
+/// Timing and parse counters of one SLURM command, the durations have one decimal
+#[derive(Debug, Clone, PartialEq)]
+pub struct MetricsRow {
+    pub command: String,
+    pub last_duration: String,
+    pub average_duration: String,
+    pub last_parsed: usize,
+    pub last_skipped: usize,
+    pub calls: u64,
+    pub failures: u64,
+    pub total_parsed: u64,
+    pub total_skipped: u64
+}
+
+impl ToJson for MetricsRow {
+    fn to_json(&self) -> Json {
+        let mut result = BTreeMap::new();
+        result.insert("command".to_string(), self.command.to_json());
+        result.insert("last_duration".to_string(), self.last_duration.to_json());
+        result.insert("average_duration".to_string(), self.average_duration.to_json());
+        result.insert("last_parsed".to_string(), self.last_parsed.to_json());
+        result.insert("last_skipped".to_string(), self.last_skipped.to_json());
+        result.insert("calls".to_string(), self.calls.to_json());
+        result.insert("failures".to_string(), self.failures.to_json());
+        result.insert("total_parsed".to_string(), self.total_parsed.to_json());
+        result.insert("total_skipped".to_string(), self.total_skipped.to_json());
+        Json::Object(result)
+    }
+}
+
+/// Everything that is shown on the status page, the context of the "status" template
+#[derive(Debug, Clone, PartialEq)]
+pub struct StatusPage {
+    /// Auto refresh interval in seconds, 0 if disabled
+    pub page_refresh: u64,
+    pub custom_css: bool,
+    /// Prepended to the links to the stylesheets and the refresh, for example "/slurm", empty without reverse proxy
+    pub url_prefix: String,
+    /// Shown in the title and the header, empty before the first update
+    pub cluster_name: String,
+    pub slurm_version: String,
+    pub last_update: String,
+    /// Text of the banner file (--banner-file), shown at the top of the page
+    pub banner: Option<String>,
+    /// Label of the time zone of the timestamps, for example "UTC" (--display-timezone)
+    pub timezone: String,
+    /// Time of the last update of the node table, empty before the first update
+    pub node_update: String,
+    /// Time of the last update of the job table, empty before the first update
+    pub job_update: String,
+    /// State names in the query string that could not be parsed
+    pub unknown_states: Vec<String>,
+    /// Note about the partitions hidden by the configuration
+    pub hidden_partitions: Option<String>,
+    /// Note about the team of --users, only their jobs are shown
+    pub team: Option<String>,
+    /// Number of running and pending jobs of the user of the request, None if the user is unknown
+    pub own_jobs: Option<String>,
+    /// Errors of the last update, the tables show older data in that case
+    pub update_errors: Vec<String>,
+    /// Note about failed updates in a row, None unless an error repeats
+    pub failures: Option<String>,
+    /// Note about the lines of sinfo and squeue that could not be parsed in the last update
+    pub skipped_lines: Option<String>,
+    /// Show the buttons of the admin actions (--enable-admin-actions)
+    pub admin_actions: bool,
+    /// Result of the admin action that redirected to the page
+    pub admin_notice: Option<AdminNotice>,
+    /// Notes about unknown column ids in the query string, with the valid ids
+    pub column_notices: Vec<String>,
+    /// Recent node state changes, newest first
+    pub node_events: Vec<EventRow>,
+    pub dashboard: Vec<DashboardBox>,
+    /// Second row of the dashboard: the scheduler pressure indicators
+    pub pressure: Vec<DashboardBox>,
+    /// Shown at the top of the page, empty if no node is down
+    pub down_nodes: Vec<DownNodeRow>,
+    pub reservations: Vec<ReservationRow>,
+    /// Empty if the licenses are not enabled (--enable-licenses)
+    pub licenses: Vec<LicenseRow>,
+    /// Empty if the QOS are not enabled (--enable-qos)
+    pub qos: Vec<QosRow>,
+    /// The cluster column is only shown if more than the local cluster is monitored
+    pub show_cluster: bool,
+    /// The limit columns of the partition table are only shown if there are limits
+    pub show_limits: bool,
+    pub partitions: Vec<PartitionRow>,
+    /// One colored cell per node above the node table, empty if it's disabled (--grid-width=0)
+    pub grid: Vec<GridPartition>,
+    /// Headers of the shown columns of the node table
+    pub node_headers: Vec<String>,
+    pub nodes: Vec<NodeRow>,
+    /// Links to the previous and next page of the job table, None if it fits on one page
+    pub job_pages: Option<PageLinks>,
+    /// Number of columns of the job table, used by the job array rows
+    pub job_colspan: usize,
+    /// Headers of the shown columns of the job table
+    pub job_headers: Vec<String>,
+    pub jobs: Vec<JobGroupRow>,
+    /// Number of finished jobs that are not shown in the job table (?all_jobs=1 shows them)
+    pub finished_hidden: usize,
+    /// Query string of the link that shows or hides the finished jobs, None if there are no finished jobs
+    pub finished_toggle: Option<String>,
+    /// The TOP_JOBS running jobs with the longest run time, shown above the job table
+    pub longest_running: Vec<TopJobRow>,
+    /// The TOP_JOBS pending jobs with the longest wait time, shown next to the longest running jobs
+    pub longest_waiting: Vec<TopJobRow>,
+    /// Jobs that disappeared from squeue, newest first, shown greyed out below the job table
+    pub recently_finished: Vec<RecentJobRow>,
+    pub queue_summary: Option<QueueRow>,
+    /// SVG chart of the pending jobs per partition over the history, None if there is nothing to show
+    pub backlog_chart: Option<String>,
+    pub users: Vec<UserUsage>,
+    pub finished_hours: u64,
+    pub finished_jobs: Vec<FinishedJobRow>,
+    pub scheduler: SchedulerRow,
+    pub scheduler_cycles: Vec<SchedulerCycleRow>,
+    pub show_shares: bool,
+    pub shares: Vec<ShareRow>,
+    pub show_usage: bool,
+    /// CPU minutes of this month, sorted by CPU minutes, with the time and the errors of the last sreport call
+    pub usage: Vec<UsageRow>,
+    pub usage_time: String,
+    pub usage_errors: Vec<String>,
+    /// Only filled after the first real update, the test mode doesn't call any SLURM command
+    pub metrics: Vec<MetricsRow>,
+    /// Configuration in effect, only with --debug-endpoints
+    pub config: Vec<ConfigEntry>,
+    /// Version, commit and uptime of slurm_inspector, shown in the footer
+    pub build_info: String
+}
+
+impl ToJson for StatusPage {
+    fn to_json(&self) -> Json {
+        let mut result = BTreeMap::new();
+        result.insert("page_refresh".to_string(), self.page_refresh.to_json());
+        result.insert("custom_css".to_string(), self.custom_css.to_json());
+        result.insert("url_prefix".to_string(), self.url_prefix.to_json());
+        result.insert("cluster_name".to_string(), self.cluster_name.to_json());
+        result.insert("slurm_version".to_string(), self.slurm_version.to_json());
+        result.insert("last_update".to_string(), self.last_update.to_json());
+        result.insert("banner".to_string(), self.banner.to_json());
+        result.insert("timezone".to_string(), self.timezone.to_json());
+        result.insert("node_update".to_string(), self.node_update.to_json());
+        result.insert("job_update".to_string(), self.job_update.to_json());
+        result.insert("unknown_states".to_string(), self.unknown_states.to_json());
+        result.insert("hidden_partitions".to_string(), self.hidden_partitions.to_json());
+        result.insert("team".to_string(), self.team.to_json());
+        result.insert("own_jobs".to_string(), self.own_jobs.to_json());
+        result.insert("update_errors".to_string(), self.update_errors.to_json());
+        result.insert("failures".to_string(), self.failures.to_json());
+        result.insert("skipped_lines".to_string(), self.skipped_lines.to_json());
+        result.insert("admin_actions".to_string(), self.admin_actions.to_json());
+        result.insert("admin_notice".to_string(), self.admin_notice.to_json());
+        result.insert("column_notices".to_string(), self.column_notices.to_json());
+        result.insert("node_events".to_string(), self.node_events.to_json());
+        result.insert("dashboard".to_string(), self.dashboard.to_json());
+        result.insert("pressure".to_string(), self.pressure.to_json());
+        result.insert("down_nodes".to_string(), self.down_nodes.to_json());
+        result.insert("reservations".to_string(), self.reservations.to_json());
+        result.insert("licenses".to_string(), self.licenses.to_json());
+        result.insert("qos".to_string(), self.qos.to_json());
+        result.insert("show_cluster".to_string(), self.show_cluster.to_json());
+        result.insert("show_limits".to_string(), self.show_limits.to_json());
+        result.insert("partitions".to_string(), self.partitions.to_json());
+        result.insert("grid".to_string(), self.grid.to_json());
+        result.insert("node_headers".to_string(), self.node_headers.to_json());
+        result.insert("nodes".to_string(), self.nodes.to_json());
+        result.insert("job_pages".to_string(), self.job_pages.to_json());
+        result.insert("job_colspan".to_string(), self.job_colspan.to_json());
+        result.insert("job_headers".to_string(), self.job_headers.to_json());
+        result.insert("jobs".to_string(), self.jobs.to_json());
+        result.insert("finished_hidden".to_string(), self.finished_hidden.to_json());
+        result.insert("finished_toggle".to_string(), self.finished_toggle.to_json());
+        result.insert("longest_running".to_string(), self.longest_running.to_json());
+        result.insert("longest_waiting".to_string(), self.longest_waiting.to_json());
+        result.insert("recently_finished".to_string(), self.recently_finished.to_json());
+        result.insert("queue_summary".to_string(), self.queue_summary.to_json());
+        result.insert("backlog_chart".to_string(), self.backlog_chart.to_json());
+        result.insert("users".to_string(), self.users.to_json());
+        result.insert("finished_hours".to_string(), self.finished_hours.to_json());
+        result.insert("finished_jobs".to_string(), self.finished_jobs.to_json());
+        result.insert("scheduler".to_string(), self.scheduler.to_json());
+        result.insert("scheduler_cycles".to_string(), self.scheduler_cycles.to_json());
+        result.insert("show_shares".to_string(), self.show_shares.to_json());
+        result.insert("shares".to_string(), self.shares.to_json());
+        result.insert("show_usage".to_string(), self.show_usage.to_json());
+        result.insert("usage".to_string(), self.usage.to_json());
+        result.insert("usage_time".to_string(), self.usage_time.to_json());
+        result.insert("usage_errors".to_string(), self.usage_errors.to_json());
+        result.insert("metrics".to_string(), self.metrics.to_json());
+        result.insert("config".to_string(), self.config.to_json());
+        result.insert("build_info".to_string(), self.build_info.to_json());
+        Json::Object(result)
+    }
+}
+
+// Private helper function to format an optional value, missing values are shown as "-"
+fn or_dash<T: Display>(value: Option<T>) -> String {
+    value.map_or("-".to_string(), |value| value.to_string())
+}
+
+/// Public helper function to format a memory size of sinfo in MB, for example "512 MB", "31.2 GB" or "1.5 TB"
+pub fn format_memory(megabytes: u64) -> String {
+    if megabytes < 1024 {
+        format!("{} MB", megabytes)
+    } else if megabytes < 1024 * 1024 {
+        format!("{:.1} GB", megabytes as f64 / 1024.0)
+    } else {
+        format!("{:.1} TB", megabytes as f64 / (1024.0 * 1024.0))
+    }
+}
+
+/// Public helper function to format the used and the total memory, the used memory is "-" if it's not known
+/// The percentage is only shown for a single node, not for the sums of the partitions
+pub fn memory_to_text(used: Option<u64>, total: Option<u64>, percent: Option<f64>) -> String {
+    match (used, total, percent) {
+        (_, None, _) => "-".to_string(),
+        (Some(used), Some(total), Some(percent)) => format!("{} / {} ({}%)", format_memory(used), format_memory(total), percent.round()),
+        (used, Some(total), _) => format!("{} / {}", or_dash(used.map(format_memory)), format_memory(total))
+    }
+}
+
+#[test]
+fn test_memory_to_text() {
+    assert_eq!(format_memory(0), "0 MB");
+    assert_eq!(format_memory(1023), "1023 MB");
+    assert_eq!(format_memory(32000), "31.2 GB");
+    assert_eq!(format_memory(1572864), "1.5 TB");
+
+    assert_eq!(memory_to_text(Some(4000), Some(32000), Some(12.5)), "3.9 GB / 31.2 GB (13%)");
+    assert_eq!(memory_to_text(Some(4000), Some(32000), None), "3.9 GB / 31.2 GB");
+    assert_eq!(memory_to_text(None, Some(32000), None), "- / 31.2 GB");
+    assert_eq!(memory_to_text(None, None, None), "-");
+}
+
+// Private helper function to join a list, an empty list is shown as "-"
+fn list_or_dash(list: &[String]) -> String {
+    if list.is_empty() { "-".to_string() } else { list.join(", ") }
+}
+
+// Private helper function to join the counts of each state, for example "Allocated 1, Idle 3"
+fn counts_to_text(counts: &BTreeMap<String, u32>) -> String {
+    let counts: Vec<String> = counts.iter().map(|(state, count)| format!("{} {}", state, count)).collect();
+    counts.join(", ")
+}
+
+/// Public helper function to build the HTML fragment id of a node or job, the cluster is only added if there is one
+pub fn html_anchor(kind: &str, cluster: &str, name: &str) -> String {
+    if cluster.is_empty() {
+        format!("{}-{}", kind, name)
+    } else {
+        format!("{}-{}-{}", kind, cluster, name)
+    }
+}
+
+/// Public helper function to get the CSS class of job states that need attention, so they stand out on the page
+pub fn job_state_class(job_state: &JobState) -> Option<String> {
+    match *job_state {
+        JobState::OutOfMemory | JobState::BootFail => Some("job_alarm".to_string()),
+        JobState::Failed | JobState::NodeFail | JobState::Timeout | JobState::Deadline => Some("job_failed".to_string()),
+        _ => None
+    }
+}
+
+#[test]
+fn test_job_state_class() {
+    assert_eq!(job_state_class(&JobState::OutOfMemory), Some("job_alarm".to_string()));
+    assert_eq!(job_state_class(&JobState::BootFail), Some("job_alarm".to_string()));
+    assert_eq!(job_state_class(&JobState::Timeout), Some("job_failed".to_string()));
+    assert_eq!(job_state_class(&JobState::Running), None);
+}
+
+/// Public helper function to show the nodes of a job as compressed hostlist, each entry links to its first node in the node table
+/// Returns the links and the number of ranges that are left out if there are too many
+pub fn node_links(cluster: &str, nodes: &[String]) -> (Vec<Link>, usize) {
+    let (entries, left_out) = compress_hostlist_entries(nodes, MAX_NODE_RANGES);
+    let links = entries.into_iter().map(|entry| {
+        let first_node = expand_hostlist(&entry).into_iter().next().unwrap_or(entry.clone());
+        Link { anchor: html_anchor("node", cluster, &first_node), text: entry, class: None }
+    }).collect();
+
+    (links, left_out)
+}
+
+#[test]
+fn test_node_links() {
+    let nodes = expand_hostlist("node[001-256],gpu7");
+    assert_eq!(node_links("", &nodes), (vec![
+        Link { anchor: "node-node001".to_string(), text: "node[001-256]".to_string(), class: None },
+        Link { anchor: "node-gpu7".to_string(), text: "gpu7".to_string(), class: None }
+    ], 0));
+    assert_eq!(node_links("", &[]), (Vec::new(), 0));
+
+    let nodes = expand_hostlist("node[001-256,258,260,262,264,266,268,270,272,274,276]");
+    assert_eq!(node_links("cluster2", &nodes), (vec![
+        Link { anchor: "node-cluster2-node001".to_string(), text: "node[001-256,258,260,262,264,266,268,270]".to_string(), class: None }
+    ], 3));
+}
+
+/// Public helper function to build the rows of the reservations table
+/// Reservations that are active at the time now or start before until are highlighted (format: %Y-%m-%dT%H:%M:%S)
+pub fn reservation_rows(reservations: &[ReservationInfo], now: &str, until: &str, display_time: &DisplayTime) -> Vec<ReservationRow> {
+    reservations.iter().map(|reservation| ReservationRow {
+        class: if reservation.is_active(now) {
+            Some("reservation_active".to_string())
+        } else if reservation.starts_within(now, until) {
+            Some("reservation_upcoming".to_string())
+        } else {
+            None
+        },
+        name: reservation.name.clone(),
+        start_time: or_dash(reservation.start_time.as_ref().map(|time| display_time.format_slurm_time(time))),
+        end_time: or_dash(reservation.end_time.as_ref().map(|time| display_time.format_slurm_time(time))),
+        nodes: compress_hostlist(&reservation.nodes),
+        users: list_or_dash(&reservation.users),
+        accounts: list_or_dash(&reservation.accounts),
+        flags: list_or_dash(&reservation.flags)
+    }).collect()
+}
+
+/// Public helper function to build the rows of the licenses table, licenses without a free one are highlighted
+pub fn license_rows(licenses: &[LicenseInfo]) -> Vec<LicenseRow> {
+    licenses.iter().map(|license| LicenseRow {
+        class: if license.is_exhausted() { Some("license_exhausted".to_string()) } else { None },
+        name: license.name.clone(),
+        total: or_dash(license.total),
+        used: or_dash(license.used),
+        free: or_dash(license.free),
+        reserved: or_dash(license.reserved)
+    }).collect()
+}
+
+#[test]
+fn test_license_rows() {
+    use scontrol_util::get_license_info_test;
+
+    let rows = license_rows(&get_license_info_test());
+    assert_eq!(rows[0], LicenseRow { class: None, name: "matlab".to_string(), total: "10".to_string(), used: "3".to_string(),
+        free: "7".to_string(), reserved: "0".to_string() });
+    assert_eq!(rows[1].class, Some("license_exhausted".to_string()));
+    assert_eq!(license_rows(&[LicenseInfo::new("unknown")])[0].free, "-");
+}
+
+/// Public helper function to build the rows of the QOS table
+pub fn qos_rows(qos_info: &[QosInfo]) -> Vec<QosRow> {
+    qos_info.iter().map(|qos| QosRow {
+        name: qos.name.clone(),
+        priority: or_dash(qos.priority),
+        max_wall: or_dash(qos.max_wall.as_ref()),
+        max_tres_per_user: if qos.max_tres_per_user.is_empty() { "-".to_string() } else { qos.tres_text() },
+        max_jobs_per_user: or_dash(qos.max_jobs_per_user),
+        max_submit_per_user: or_dash(qos.max_submit_per_user)
+    }).collect()
+}
+
+#[test]
+fn test_qos_rows() {
+    use sacctmgr_util::get_qos_info_test;
+
+    let rows = qos_rows(&get_qos_info_test());
+    assert_eq!(rows[0], QosRow { name: "normal".to_string(), priority: "50".to_string(), max_wall: "2-00:00:00".to_string(),
+        max_tres_per_user: "cpu=64,gres/gpu=4".to_string(), max_jobs_per_user: "50".to_string(), max_submit_per_user: "100".to_string() });
+    assert_eq!((&rows[2].max_tres_per_user[..], &rows[2].max_submit_per_user[..]), ("-", "-"));
+}
+
+/// Public helper function to build the rows of the down/drained nodes table, only the reasons of the given nodes are shown
+/// (the nodes that pass the filter), nodes with the same cluster, reason, user and time are grouped into one row
+pub fn down_node_rows(reasons: &[DownReason], node_info: &[&PartitionNodeInfo], display_time: &DisplayTime) -> Vec<DownNodeRow> {
+    let shown: BTreeSet<(&str, &str)> = node_info.iter().map(|node| (&node.cluster[..], &node.node[..])).collect();
+    let mut groups: Vec<(&DownReason, Vec<String>)> = Vec::new();
+
+    for reason in reasons.iter().filter(|reason| shown.contains(&(&reason.cluster[..], &reason.node[..]))) {
+        let same = |other: &DownReason| other.cluster == reason.cluster && other.reason == reason.reason && other.set_by == reason.set_by && other.since == reason.since;
+        match groups.iter().position(|&(other, _)| same(other)) {
+            Some(index) => groups[index].1.push(reason.node.clone()),
+            None => groups.push((reason, vec![reason.node.clone()]))
+        }
+    }
+
+    groups.into_iter().map(|(reason, nodes)| DownNodeRow {
+        cluster: reason.cluster.clone(),
+        nodes: compress_hostlist(&nodes),
+        reason: if reason.reason.is_empty() { "-".to_string() } else { reason.reason.clone() },
+        set_by: or_dash(reason.set_by.as_ref()),
+        since: or_dash(reason.since.as_ref().map(|time| display_time.format_slurm_time(time)))
+    }).collect()
+}
+
+#[test]
+fn test_down_node_rows() {
+    use sinfo_util::{get_partition_node_info_test, get_down_reasons_test};
+
+    let nodes = get_partition_node_info_test();
+    let mut reasons = get_down_reasons_test();
+    reasons.push(DownReason{ node: "node11".to_string(), reason: String::new(), set_by: None, since: None, .. reasons[0].clone() });
+    reasons.push(DownReason{ node: "node99".to_string(), .. reasons[0].clone() });
+
+    // node09 and node10 have the same reason, node99 is not in the node table
+    let rows = down_node_rows(&reasons, &nodes.iter().collect::<Vec<&PartitionNodeInfo>>(), &DisplayTime::default());
+    let rows: Vec<(&str, &str, &str, &str)> = rows.iter().map(|row| (&row.nodes[..], &row.reason[..], &row.set_by[..], &row.since[..])).collect();
+    assert_eq!(rows, vec![
+        ("node03", "Not responding", "slurm(202)", "2015.12.18 - 08:15"),
+        ("node07", "Bad DIMM, replaced on Monday", "root(0)", "2015.12.17 - 16:40"),
+        ("node08", "Kernel update", "root(0)", "2015.12.18 - 09:30"),
+        ("node[09-10]", "Epilog error", "slurm(202)", "-"),
+        ("node11", "-", "-", "-")
+    ]);
+
+    // Only the reasons of the nodes that pass the filter are shown
+    let filtered: Vec<&PartitionNodeInfo> = nodes.iter().filter(|node| node.node == "node10").collect();
+    assert_eq!(down_node_rows(&reasons, &filtered, &DisplayTime::default()).iter().map(|row| &row.nodes[..]).collect::<Vec<&str>>(), vec!["node10"]);
+    assert!(down_node_rows(&reasons, &[], &DisplayTime::default()).is_empty());
+}
+
+/// Public helper function to build the rows of the partition table
+pub fn partition_rows(summaries: &[PartitionSummary]) -> Vec<PartitionRow> {
+    summaries.iter().map(|summary| {
+        let limits = summary.limits.as_ref();
+        PartitionRow {
+            cluster: summary.cluster.clone(),
+            name: summary.name.clone(),
+            available: summary.availability == PartitionAvailability::Up,
+            nodes: summary.nodes,
+            node_states: counts_to_text(&summary.node_states),
+            gpus: if summary.gpus > 0 { summary.gpus.to_string() } else { String::new() },
+            memory: memory_to_text(summary.memory_used, if summary.memory_total > 0 { Some(summary.memory_total) } else { None }, None),
+            pending_jobs: or_dash(summary.wait_times.as_ref().map(|wait_times| wait_times.jobs)),
+            wait_times: or_dash(summary.wait_times.as_ref().map(|wait_times| wait_times.to_text())),
+            max_time: or_dash(limits.and_then(|limits| limits.max_time.as_ref())),
+            default_time: or_dash(limits.and_then(|limits| limits.default_time.as_ref())),
+            max_nodes: or_dash(limits.and_then(|limits| limits.max_nodes.as_ref())),
+            max_cpus_per_node: or_dash(limits.and_then(|limits| limits.max_cpus_per_node.as_ref())),
+            state: or_dash(limits.and_then(|limits| limits.state.as_ref())),
+            allow_groups: or_dash(limits.map(|limits| if limits.allow_groups.is_empty() { "ALL".to_string() } else { limits.allow_groups.join(", ") }))
+        }
+    }).collect()
+}
+
+/// Public helper function to build the rows of the node table for the nodes that pass the filter
+/// The nodes are sorted by cluster and partition, so the partition name is only set in the first row of each group
+pub fn node_rows(node_info: &[&PartitionNodeInfo], job_info: &[JobInfo]) -> Vec<NodeRow> {
+    // Cross-reference between the node table and the job table via fragment ids
+    // A node can be in several partitions, the fragment id is only set in the first row
+    let node_jobs = jobs_by_node(job_info);
+    let mut node_anchors: BTreeSet<String> = BTreeSet::new();
+    let same_partition = |a: &PartitionNodeInfo, b: &PartitionNodeInfo| a.cluster == b.cluster && a.partition == b.partition;
+
+    node_info.iter().enumerate().map(|(index, node)| {
+        let anchor = html_anchor("node", &node.cluster, &node.node);
+        let job_ids = node_jobs.get(&(node.cluster.clone(), node.node.clone()));
+        let partition_rows = if index == 0 || !same_partition(node_info[index - 1], node) {
+            Some(node_info[index..].iter().take_while(|other| same_partition(other, node)).count())
+        } else {
+            None
+        };
+        let action = node_action(node);
+
+        NodeRow {
+            anchor: if node_anchors.insert(anchor.clone()) { Some(anchor) } else { None },
+            cluster: node.cluster.clone(),
+            partition: node.partition.clone(),
+            partition_rows: partition_rows,
+            available: node.availability == PartitionAvailability::Up,
+            hostname: or_dash(node.hostname.as_ref()),
+            node: node.node.clone(),
+            job_anchor: job_ids.and_then(|job_ids| job_ids.first()).map(|job_id| html_anchor("job", &node.cluster, &job_id.to_string())),
+            error: node.error.to_string(),
+            reason: node.reason.clone(),
+            cpu_load: or_dash(node.cpu_load),
+            relative_load: match (node.cpu_load, node.core_count(), node.load_percent()) {
+                (Some(load), Some(cores), Some(percent)) => format!("{} / {} ({:.0}%)", load, cores, percent),
+                _ => or_dash(node.cpu_load)
+            },
+            load_class: match node.load_level() {
+                Some(LoadLevel::Oversubscribed) => Some("load_oversubscribed".to_string()),
+                Some(LoadLevel::IdleAllocated) => Some("load_idle".to_string()),
+                Some(LoadLevel::Normal) | None => None
+            },
+            state: node.node_state.to_string(),
+            flags: node.node_flags.iter().map(|flag| flag.symbol()).collect(),
+            flag_descriptions: node.node_flags.iter().map(|flag| flag.description()).collect::<Vec<&str>>().join(", "),
+            sockets: or_dash(node.node_sockets),
+            cores: or_dash(node.node_cores),
+            threads: or_dash(node.node_threads),
+            cpus: match (node.cpus_alloc, node.cpus_idle, node.cpus_other, node.cpus_total) {
+                (Some(alloc), Some(idle), Some(other), Some(total)) => format!("{}/{}/{}/{}", alloc, idle, other, total),
+                _ => "-".to_string()
+            },
+            utilization: or_dash(node.cpu_utilization().map(|percent| format!("{:.0}%", percent))),
+            load_warning: node.cpu_utilization().is_some() && node.has_load_mismatch(),
+            memory: memory_to_text(node.memory_used(), node.memory_total, node.memory_percent()),
+            memory_percent: node.memory_percent().map(|percent| percent.round() as u32),
+            memory_warning: node.has_memory_warning(),
+            gpus: gpus_to_text(&node.gres),
+            jobs: job_ids.map_or(Vec::new(), |job_ids| job_ids.iter()
+                .map(|job_id| Link { anchor: html_anchor("job", &node.cluster, &job_id.to_string()), text: job_id.to_string(), class: None })
+                .collect()),
+            action_route: action.as_ref().map(|action| action.route()),
+            action_name: action.map(|action| match action {
+                AdminAction::ResumeNode(_) => "Resume".to_string(),
+                _ => "Drain".to_string()
+            }),
+            cells: Vec::new()
+        }
+    }).collect()
+}
+
+#[test]
+fn test_node_rows_cpus() {
+    use sinfo_util::get_partition_node_info_test;
+
+    let nodes = get_partition_node_info_test();
+    let node = |name: &str| node_rows(&[nodes.iter().find(|node| node.node == name).unwrap()], &[]).remove(0);
+
+    assert_eq!((node("node01").cpus, node("node01").utilization, node("node01").load_warning), ("0/8/0/8".to_string(), "0%".to_string(), false));
+    assert_eq!((node("node03").cpus, node("node03").utilization, node("node03").load_warning), ("-".to_string(), "-".to_string(), false));
+    assert_eq!((node("node05").cpus, node("node05").utilization, node("node05").load_warning), ("1/0/0/1".to_string(), "100%".to_string(), true));
+    assert_eq!(node("node05").cpu_load, "0");
+}
+
+#[test]
+fn test_node_rows_relative_load() {
+    use sinfo_util::{get_partition_node_info_test, NodeState};
+
+    let nodes = get_partition_node_info_test();
+    let node = |name: &str| nodes.iter().find(|node| node.node == name).unwrap().clone();
+    let row = |node: PartitionNodeInfo| node_rows(&[&node], &[]).remove(0);
+
+    assert_eq!((row(node("node01")).relative_load, row(node("node01")).load_class), ("0.22 / 8 (3%)".to_string(), None));
+    assert_eq!((row(node("node05")).relative_load, row(node("node05")).load_class), ("0 / 1 (0%)".to_string(), Some("load_idle".to_string())));
+    let busy = PartitionNodeInfo{ cpu_load: Some(12.5), node_state: NodeState::Mixed, .. node("node01") };
+    assert_eq!((row(busy.clone()).relative_load, row(busy.clone()).load_class), ("12.5 / 8 (156%)".to_string(), Some("load_oversubscribed".to_string())));
+    // Without the cores only the load is shown
+    let no_cores = PartitionNodeInfo{ node_cores: None, .. busy };
+    assert_eq!((row(no_cores.clone()).relative_load, row(no_cores).load_class), ("12.5".to_string(), None));
+    assert_eq!(row(node("node03")).relative_load, "-");
+}
+
+/// Public helper function to build one row of the job table
+pub fn job_row(job: &JobInfo, display_time: Option<&DisplayTime>) -> JobRow {
+    let (nodes, more_ranges) = node_links(&job.cluster, &job.list_of_nodes);
+    let remaining = match (job.job_state, job.run_time.as_ref(), job.time_limit.as_ref()) {
+        (JobState::Running, Some(run_time), Some(time_limit)) => RemainingTime::new(run_time, time_limit),
+        _ => None
+    };
+    let slurm_time = |time: &String| display_time.map_or(time.clone(), |display_time| display_time.format_slurm_time(time));
+
+    JobRow {
+        anchor: job.job_id.as_ref().map(|job_id| html_anchor("job", &job.cluster, &job_id.to_string())),
+        is_step: job.job_id.as_ref().map_or(false, |job_id| job_id.is_step()),
+        own_job: false,
+        cluster: job.cluster.clone(),
+        executing_host: or_dash(job.executing_host.as_ref()),
+        minimum_cpu: or_dash(job.minimum_cpu),
+        num_cpu: or_dash(job.num_cpu),
+        num_nodes: or_dash(job.num_nodes),
+        gpus: gpus_to_text(&job.gres),
+        job_array_id: or_dash(job.job_array_id),
+        num_sockets: or_dash(job.num_sockets),
+        job_id: or_dash(job.job_id.as_ref()),
+        num_cores: or_dash(job.num_cores),
+        job_name: job.job_name.clone(),
+        num_threads: or_dash(job.num_threads),
+        job_array_index: or_dash(job.job_array_index),
+        run_time: or_dash(job.run_time.as_ref()),
+        remaining: remaining.map_or("-".to_string(), |remaining| remaining.to_text()),
+        remaining_class: remaining.and_then(|remaining| remaining.css_class()),
+        remaining_sort: remaining.map_or(String::new(), |remaining| remaining.sort_key().to_string()),
+        nodes: nodes,
+        more_ranges: more_ranges,
+        hostlist: compress_hostlist(&job.list_of_nodes),
+        priority: job.priority.map_or("-".to_string(), format_priority),
+        priority_raw: job.priority.map(|priority| priority.to_string()),
+        priority_factors: None,
+        state_reason: job.state_reason.to_string(),
+        depends_on: job.dependencies.iter().filter_map(|dependency| dependency.job_id.as_ref().map(|job_id| Link {
+            anchor: html_anchor("job", &job.cluster, &job_id.to_string()),
+            text: dependency.to_string(),
+            class: None
+        })).collect(),
+        singleton: job.dependencies.iter().any(|dependency| dependency.kind == DependencyKind::Singleton),
+        dependencies: if job.dependencies.is_empty() {
+            "-".to_string()
+        } else {
+            job.dependencies.iter().map(|dependency| dependency.to_string()).collect::<Vec<String>>().join(",")
+        },
+        qos_limits: None,
+        start_time: or_dash(job.start_time.as_ref().map(&slurm_time)),
+        waiting_since: match (job.job_state, job.submit_time.as_ref(), job.wait_time) {
+            (JobState::Pending, Some(submit_time), Some(wait_time)) => format!("{} ({})", slurm_time(submit_time), format_duration(wait_time)),
+            (JobState::Pending, Some(submit_time), None) => slurm_time(submit_time),
+            _ => "-".to_string()
+        },
+        job_state: match job.cancelled_by {
+            Some(user_id) => format!("{} by {}", job.job_state, user_id),
+            None => job.job_state.to_string()
+        },
+        state_class: job_state_class(&job.job_state),
+        user_name: job.user_name.clone(),
+        user_id: or_dash(job.user_id),
+        cancel_id: match job.job_id {
+            Some(ref job_id) if job.cluster.is_empty() && job_id.array_index.is_none() && !job_id.is_step() => Some(job_id.id),
+            _ => None
+        },
+        cells: Vec::new()
+    }
+}
+
+#[test]
+fn test_job_row_remaining() {
+    use squeue_util::get_job_info_test;
+
+    let mut job = get_job_info_test().remove(0);
+    job.run_time = Some("57:30".to_string());
+    job.time_limit = Some("1:00:00".to_string());
+    let row = job_row(&job, None);
+    assert_eq!((&row.remaining[..], row.remaining_class, &row.remaining_sort[..]), ("0:02:30 (95%)", Some("time_limit_critical".to_string()), "150"));
+
+    job.time_limit = Some("UNLIMITED".to_string());
+    let row = job_row(&job, None);
+    assert_eq!((&row.remaining[..], row.remaining_class), ("\u{221e}", None));
+
+    job.time_limit = Some("INVALID".to_string());
+    assert_eq!(job_row(&job, None).remaining, "-");
+
+    // Only running jobs have a remaining time
+    job.time_limit = Some("1:00:00".to_string());
+    job.job_state = JobState::Pending;
+    assert_eq!((job_row(&job, None).remaining, job_row(&job, None).remaining_sort), ("-".to_string(), String::new()));
+}
+
+/// Public helper function to build the row of a job array
+pub fn job_array_row(summary: &JobArraySummary, display_time: Option<&DisplayTime>) -> JobArrayRow {
+    JobArrayRow {
+        anchor: html_anchor("job", &summary.cluster, &summary.array_id.to_string()),
+        cluster: summary.cluster.clone(),
+        array_id: summary.array_id,
+        job_name: summary.job_name.clone(),
+        user_name: summary.user_name.clone(),
+        num_tasks: summary.num_tasks(),
+        state_counts: counts_to_text(&summary.state_counts),
+        run_time: match (summary.min_run_time.as_ref(), summary.max_run_time.as_ref()) {
+            (Some(min), Some(max)) => Some(format!("{} - {}", min, max)),
+            _ => None
+        },
+        tasks: summary.tasks.iter().map(|task| job_row(task, display_time)).collect()
+    }
+}
+
+/// Columns of the job table with timestamps, the time zone is shown in their header
+const TIME_COLUMNS: [&'static str; 2] = ["start_time", "waiting_since"];
+
+// Private helper function to get the shown columns of a table, the cluster column is only shown if there are several clusters
+fn shown_columns<'a, R>(columns: &'a [Column<R>], ids: &[String], show_cluster: bool) -> Vec<&'a Column<R>> {
+    selected_columns(columns, ids).into_iter().filter(|column| show_cluster || column.id != "cluster").collect()
+}
+
+// Private helper function to build the note about unknown column ids of a table, None if all ids are known
+fn column_notice<R>(table: &str, unknown: &[String], columns: &[Column<R>]) -> Option<String> {
+    if unknown.is_empty() {
+        None
+    } else {
+        Some(format!("Unknown {} columns ignored: {} (valid columns: {})", table, unknown.join(", "), column_ids(columns)))
+    }
+}
+
+/// Public helper function to build the view-model of the status page
+/// Reservations that are active at the time now or start before until are highlighted (format: %Y-%m-%dT%H:%M:%S)
+pub fn status_page(status: &SlurmStatus, options: &PageOptions, now: &str, until: &str) -> StatusPage {
+    let filter = &options.filter;
+    let summary = dashboard_summary(status, filter);
+    let dashboard_box = |number: u32, label: &str, problem: bool| DashboardBox {
+        number: number.to_string(),
+        label: label.to_string(),
+        problem: problem && number > 0,
+        level: PressureLevel::Normal
+    };
+    let mut dashboard = vec![
+        dashboard_box(summary.total_nodes, "Nodes", false),
+        dashboard_box(summary.nodes_down, "Nodes down", true),
+        dashboard_box(summary.nodes_draining, "Nodes draining", true),
+        dashboard_box(summary.running_jobs, "Running jobs", false),
+        dashboard_box(summary.pending_jobs, "Pending jobs", false),
+        dashboard_box(summary.active_users, "Active users", false),
+        dashboard_box(summary.allocated_cpus, "Allocated CPUs", false)
+    ];
+    // The GPU numbers are only shown if the cluster has GPUs
+    if summary.total_gpus > 0 {
+        dashboard.push(dashboard_box(summary.total_gpus, "GPUs", false));
+        dashboard.push(dashboard_box(summary.allocated_gpus, "Allocated GPUs", false));
+    }
+
+    let show_cluster = status.node_info.iter().any(|node| !node.cluster.is_empty()) || status.job_info.iter().any(|job| !job.cluster.is_empty());
+    let summaries = partition_summaries(status, filter);
+    let node_info: Vec<&PartitionNodeInfo> = status.node_info.iter().filter(|node| filter.node_matches(node)).collect();
+
+    // Job arrays are shown as one row, unless the flat view was requested (?expand_arrays=1)
+    // The rows are split into pages after filtering and grouping (?limit= and ?page=)
+    let mut jobs: Vec<JobInfo> = status.job_info.iter().filter(|job| filter.job_matches(job)).cloned().collect();
+    // Sorted before grouping, so a job array takes the position of its first task in the chosen order (--default-sort or ?sort=)
+    sort_jobs(&mut jobs, options.sort);
+    // Finished jobs are hidden by default (?all_jobs=1 shows them), the link toggles between both views
+    let all_jobs = filter.with_finished();
+    let finished_jobs = status.job_info.iter().filter(|job| all_jobs.job_matches(job) && job.job_state.is_finished()).count();
+    let finished_hidden = finished_jobs - status.job_info.iter().filter(|job| filter.job_matches(job) && job.job_state.is_finished()).count();
+    let finished_toggle = if finished_hidden > 0 {
+        Some(query_with_param(&options.query, "all_jobs", "1"))
+    } else if finished_jobs > 0 && filter.job_states.is_empty() {
+        Some(query_with_param(&options.query, "all_jobs", "0"))
+    } else {
+        None
+    };
+    let top_running = top_job_rows(&longest_running(&jobs, TOP_JOBS));
+    let top_waiting = top_job_rows(&longest_waiting(&jobs, TOP_JOBS));
+    let job_groups: Vec<JobGroup> = if options.expand_arrays { jobs.into_iter().map(JobGroup::Single).collect() } else { group_job_arrays(jobs) };
+
+    // Only the chosen columns are shown (--job-columns, --node-columns or ?columns=, ?node_columns=)
+    let all_job_columns = job_columns();
+    let all_node_columns = node_columns();
+    let shown_job_columns = shown_columns(&all_job_columns, &options.job_columns, show_cluster);
+    let shown_node_columns = shown_columns(&all_node_columns, &options.node_columns, show_cluster);
+    // Pending jobs that are missing in the output of sprio have no tooltip
+    let priorities = priorities_by_job(&status.priority_info);
+    let dependency_graph = DependencyGraph::new(&status.job_info);
+    let job_row_with_cells = |job: &JobInfo| {
+        let mut row = job_row(job, Some(&options.display_time));
+        row.own_job = options.me.as_ref().map_or(false, |me| job.user_name == *me);
+        row.priority_factors = job_priority(job, &priorities).map(|priority| priority.factors_text());
+        if job.job_state == JobState::Pending {
+            row.qos_limits = qos_limits_text(job.state_reason, &status.qos_info);
+        }
+        // The links are the dependencies with a job id, in the same order
+        let with_job_id = job.dependencies.iter().filter(|dependency| dependency.job_id.is_some());
+        for (link, dependency) in row.depends_on.iter_mut().zip(with_job_id) {
+            if dependency_graph.is_dependency_stuck(&job.cluster, dependency) {
+                link.class = Some("dependency_stuck".to_string());
+            }
+        }
+        row.cells = row_cells(&row, &shown_job_columns);
+        row
+    };
+    let mut nodes = node_rows(&node_info, &status.job_info);
+    for node in &mut nodes {
+        node.cells = row_cells(node, &shown_node_columns);
+    }
+
+    let cluster_jobs: Vec<JobInfo> = status.job_info.iter().filter(|job| filter.cluster_matches(&job.cluster)).cloned().collect();
+    let stats = &status.scheduler_stats;
+    let mut scheduler_cycles = Vec::new();
+    if let Some(ref main) = stats.main_schedule {
+        scheduler_cycles.push(SchedulerCycleRow {
+            name: "Main".to_string(),
+            last_cycle: or_dash(main.last_cycle),
+            mean_cycle: or_dash(main.mean_cycle),
+            max_cycle: or_dash(main.max_cycle),
+            total_cycles: or_dash(main.total_cycles),
+            depth: or_dash(main.mean_depth_cycle),
+            queue_length: or_dash(main.last_queue_length)
+        });
+    }
+    if let Some(ref backfill) = stats.backfill {
+        scheduler_cycles.push(SchedulerCycleRow {
+            name: "Backfill".to_string(),
+            last_cycle: or_dash(backfill.last_cycle),
+            mean_cycle: or_dash(backfill.mean_cycle),
+            max_cycle: or_dash(backfill.max_cycle),
+            total_cycles: or_dash(backfill.total_cycles),
+            depth: or_dash(backfill.last_depth_cycle),
+            queue_length: or_dash(backfill.last_queue_length)
+        });
+    }
+
+    StatusPage {
+        page_refresh: options.page_refresh,
+        custom_css: options.custom_css,
+        url_prefix: options.url_prefix.clone(),
+        cluster_name: status.cluster_name.clone(),
+        slurm_version: status.slurm_version.clone(),
+        last_update: status.last_update.clone(),
+        banner: status.banner.clone(),
+        timezone: options.display_time.label(),
+        node_update: status.node_update.time.clone(),
+        job_update: status.job_update.time.clone(),
+        unknown_states: filter.unknown_states.clone(),
+        hidden_partitions: if status.hidden_partitions > 0 { Some(hidden_partitions_notice(status.hidden_partitions)) } else { None },
+        team: team_notice(&filter.team),
+        own_jobs: options.me.as_ref().map(|me| own_jobs_notice(&status.job_info, me)),
+        update_errors: status.update_errors.clone(),
+        failures: failures_notice(status.consecutive_failures),
+        skipped_lines: skipped_lines_notice(status.metrics.last_skipped()),
+        admin_actions: options.admin_actions,
+        admin_notice: options.admin_notice.clone(),
+        column_notices: column_notice("job", &options.unknown_job_columns, &all_job_columns).into_iter()
+            .chain(column_notice("node", &options.unknown_node_columns, &all_node_columns))
+            .collect(),
+        node_events: status.node_events.iter().rev()
+            .map(|event| EventRow { time: event.time.clone(), text: event.to_string(), alarm: event.is_alarming() })
+            .collect(),
+        dashboard: dashboard,
+        pressure: pressure_boxes(&summary, &options.pressure_thresholds),
+        down_nodes: down_node_rows(&status.down_reasons, &node_info, &options.display_time),
+        reservations: reservation_rows(&status.reservations, now, until, &options.display_time),
+        licenses: license_rows(&status.licenses),
+        qos: qos_rows(&status.qos_info),
+        show_cluster: show_cluster,
+        show_limits: summaries.iter().any(|summary| summary.limits.is_some()),
+        partitions: partition_rows(&summaries),
+        grid: occupancy_grid(&node_info, &status.job_info, options.grid_width),
+        node_headers: shown_node_columns.iter().map(|column| column.header.to_string()).collect(),
+        nodes: nodes,
+        job_pages: page_links(&options.query, &options.pagination.range(job_groups.len())),
+        job_colspan: shown_job_columns.len() + if options.admin_actions { 1 } else { 0 },
+        job_headers: shown_job_columns.iter().map(|column| if TIME_COLUMNS.contains(&column.id) {
+            format!("{} ({})", column.header, options.display_time.label())
+        } else {
+            column.header.to_string()
+        }).collect(),
+        jobs: options.pagination.apply(&job_groups).iter().map(|job_group| match *job_group {
+            JobGroup::Single(ref job) => JobGroupRow::Single(job_row_with_cells(job)),
+            JobGroup::Array(ref summary) => {
+                let mut array = job_array_row(summary, Some(&options.display_time));
+                for task in &mut array.tasks {
+                    task.cells = row_cells(task, &shown_job_columns);
+                }
+                JobGroupRow::Array(array)
+            }
+        }).collect(),
+        finished_hidden: finished_hidden,
+        finished_toggle: finished_toggle,
+        longest_running: top_running,
+        longest_waiting: top_waiting,
+        // These jobs are gone from squeue, so they are shown even if the finished jobs are hidden
+        recently_finished: status.recently_finished.jobs.iter().rev().filter(|recent| all_jobs.job_matches(&recent.job)).map(|recent| RecentJobRow {
+            job_id: or_dash(recent.job.job_id.as_ref()),
+            job_name: recent.job.job_name.clone(),
+            user_name: recent.job.user_name.clone(),
+            partition: recent.job.partition.clone(),
+            last_state: recent.job.job_state.to_string(),
+            last_seen: options.display_time.format(recent.last_seen)
+        }).collect(),
+        queue_summary: status.history.queue_summary(3600)
+            .map(|summary| QueueRow { min: summary.min, max: summary.max, avg: format!("{:.1}", summary.avg), samples: summary.samples }),
+        // The history only contains counts, so the filter doesn't apply
+        backlog_chart: backlog_chart(&backlog_samples(&status.history), &options.display_time),
+        users: usage_by_user(&cluster_jobs).into_iter().filter(|usage| filter.user_matches(&usage.user_name)).collect(),
+        finished_hours: options.finished_hours,
+        finished_jobs: status.finished_job_info.iter().filter(|job| filter.finished_job_matches(job)).map(|job| FinishedJobRow {
+            job_id: job.job_id.clone(),
+            job_name: job.job_name.clone(),
+            user_name: job.user_name.clone(),
+            partition: job.partition.clone(),
+            job_state: job.job_state.to_string(),
+            state_class: job_state_class(&job.job_state),
+            exit_code: or_dash(job.exit_code),
+            exit_hint: job.exit_code.and_then(|exit_code| exit_code.hint()),
+            elapsed: job.elapsed.clone(),
+            end_time: options.display_time.format_slurm_time(&job.end_time)
+        }).collect(),
+        scheduler: SchedulerRow {
+            jobs_submitted: or_dash(stats.jobs_submitted),
+            jobs_started: or_dash(stats.jobs_started),
+            jobs_completed: or_dash(stats.jobs_completed),
+            jobs_canceled: or_dash(stats.jobs_canceled),
+            jobs_failed: or_dash(stats.jobs_failed),
+            server_threads: or_dash(stats.server_thread_count),
+            agent_queue_size: or_dash(stats.agent_queue_size),
+            rpc_count: or_dash(stats.rpc_count)
+        },
+        scheduler_cycles: scheduler_cycles,
+        show_shares: options.show_shares,
+        shares: status.share_info.iter().map(|share| ShareRow {
+            account: share.account.clone(),
+            user: or_dash(share.user.as_ref()),
+            raw_shares: or_dash(share.raw_shares),
+            norm_shares: or_dash(share.norm_shares),
+            raw_usage: or_dash(share.raw_usage),
+            effective_usage: or_dash(share.effective_usage),
+            fair_share: or_dash(share.fair_share)
+        }).collect(),
+        show_usage: options.show_usage,
+        usage: status.usage_info.iter().map(|record| UsageRow {
+            account: record.account.clone(),
+            user: or_dash(record.user.as_ref()),
+            cpu_minutes: record.cpu_minutes,
+            cpu_hours: format!("{:.1}", record.cpu_minutes as f64 / 60.0)
+        }).collect(),
+        usage_time: status.usage_update.time.clone(),
+        usage_errors: status.usage_update.errors.clone(),
+        metrics: if status.metrics.is_empty() { Vec::new() } else {
+            status.metrics.commands().into_iter().map(|(name, metrics)| MetricsRow {
+                command: name.to_string(),
+                last_duration: format!("{:.1}", metrics.last_duration_ms),
+                average_duration: format!("{:.1}", metrics.average_duration_ms()),
+                last_parsed: metrics.last_parsed,
+                last_skipped: metrics.last_skipped,
+                calls: metrics.calls,
+                failures: metrics.failures,
+                total_parsed: metrics.total_parsed,
+                total_skipped: metrics.total_skipped
+            }).collect()
+        },
+        config: options.config.clone(),
+        build_info: status.build_info.to_text(get_time().sec)
+    }
+}
+
+#[cfg(test)]
+fn render_partial(name: &str, context: BTreeMap<String, Json>) -> String {
+    use template::builtin_templates;
+
+    builtin_templates().render(name, &Json::Object(context)).unwrap()
+}
+
+#[test]
+fn test_partitions_template() {
+    use sinfo_util::get_partition_node_info_test;
+    use scontrol_util::get_partition_limits_test;
+    use squeue_util::get_job_info_test;
+    use status_filter::StatusFilter;
+    use wait_times::set_wait_times;
+
+    let mut status = SlurmStatus::new();
+    status.node_info = get_partition_node_info_test();
+    let mut context = BTreeMap::new();
+    context.insert("partitions".to_string(), partition_rows(&partition_summaries(&status, &StatusFilter::new())).to_json());
+
+    let table = render_partial("partitions", context.clone());
+    assert!(table.contains("<td class=\"partition_name\"><a href=\"/partition/esd\">esd</a></td><td id=\"partition_down\">Down</td><td>12</td><td>allocated 1, completing 1, drained 1, draining 1, fail 1, failing 1, idle 3, maint 1, unknown 2</td><td>4</td><td>19.6 GB / 191.4 GB</td><td>-</td><td>-</td></tr>"));
+    assert!(!table.contains("<th>Max time</th>"));
+
+    status.partition_limits = get_partition_limits_test();
+    // Job 8 is pending in esd
+    status.job_info = get_job_info_test();
+    set_wait_times(&mut status.job_info, "2000-01-01T10:00:00");
+    context.insert("partitions".to_string(), partition_rows(&partition_summaries(&status, &StatusFilter::new())).to_json());
+    context.insert("show_cluster".to_string(), Json::Boolean(true));
+    context.insert("show_limits".to_string(), Json::Boolean(true));
+    let table = render_partial("partitions", context);
+    assert!(table.contains("<th>Cluster</th>"));
+    assert!(table.contains("<th>Max time</th>"));
+    assert!(table.contains("<td>4</td><td>19.6 GB / 191.4 GB</td><td>1</td><td>2:14:48 / 2:14:48 / 2:14:48 / 2:14:48</td><td>7-00:00:00</td><td>01:00:00</td><td>UNLIMITED</td><td>UNLIMITED</td><td>UP</td><td>ALL</td></tr>"));
+}
+
+#[test]
+fn test_reservations_template() {
+    let mut active = ReservationInfo::new("course");
+    active.start_time = Some("2015-12-17T09:00:00".to_string());
+    active.end_time = Some("2015-12-18T17:00:00".to_string());
+    active.nodes = vec!["node05".to_string(), "node06".to_string()];
+    active.accounts = vec!["teaching".to_string(), "esd".to_string()];
+    let mut upcoming = ReservationInfo::new("maint");
+    upcoming.start_time = Some("2015-12-18T08:00:00".to_string());
+    upcoming.flags = vec!["MAINT".to_string(), "IGNORE_JOBS".to_string()];
+    let mut later = upcoming.clone();
+    later.name = "later".to_string();
+    later.start_time = Some("2015-12-24T08:00:00".to_string());
+
+    let mut context = BTreeMap::new();
+    context.insert("reservations".to_string(), reservation_rows(&[active, upcoming, later], "2015-12-17T10:00:00", "2015-12-18T10:00:00", &DisplayTime::default()).to_json());
+    let table = render_partial("reservations", context);
+    assert!(table.contains("<tr class=\"reservation_active\">\n<td>course</td><td>2015.12.17 - 09:00</td><td>2015.12.18 - 17:00</td><td>node[05-06]</td><td>-</td><td>teaching, esd</td><td>-</td></tr>"));
+    assert!(table.contains("<tr class=\"reservation_upcoming\">\n<td>maint</td><td>2015.12.18 - 08:00</td><td>-</td><td></td><td>-</td><td>-</td><td>MAINT, IGNORE_JOBS</td></tr>"));
+    assert!(table.contains("<tr>\n<td>later</td>"));
+}
+
+#[test]
+fn test_status_page_golden_file() {
+    use configuration::Configuration;
+    use command_runner::TestRunner;
+    use slurm_status::update_slurm_status;
+    use template::{STATUS_TEMPLATE, builtin_templates};
+    use build_info::BuildInfo;
+
+    // Test mode data with fixed times, so the page doesn't change from run to run
+    let config = Configuration{ test_mode: true, enable_sshare: true, enable_sprio: true, enable_partition_limits: true, enable_licenses: true, enable_qos: true,
+        cluster_name: "cluster1".to_string(), slurm_version: "20.11.8".to_string(), .. Configuration::default() };
+    let mut status = SlurmStatus::new();
+    update_slurm_status(&mut status, &config, &TestRunner::new(Err("must not be called".to_string())), None);
+    status.last_update = "2015.12.18 - 10:00".to_string();
+    status.node_update.time = "2015.12.18 - 09:58".to_string();
+    status.job_update.time = "2015.12.18 - 10:00".to_string();
+    status.build_info = BuildInfo{ version: "0.1.0".to_string(), commit: "abc1234".to_string(), started: None };
+    let times = [("2015-12-17T09:00:00", "2015-12-18T17:00:00"), ("2015-12-19T08:00:00", "2015-12-20T08:00:00"), ("2015-12-24T08:00:00", "2015-12-25T08:00:00")];
+    for (reservation, &(start, end)) in status.reservations.iter_mut().zip(times.iter()) {
+        reservation.start_time = Some(start.to_string());
+        reservation.end_time = Some(end.to_string());
+    }
+
+    let page = status_page(&status, &PageOptions{ show_shares: true, .. PageOptions::new(&config) }, "2015-12-18T10:00:00", "2015-12-20T10:00:00");
+    let html = builtin_templates().render(STATUS_TEMPLATE, &page.to_json()).unwrap();
+    assert_eq!(html, include_str!("../tests/fixtures/status_page.html"));
+}
+
+#[test]
+fn test_status_page_url_prefix() {
+    use template::{STATUS_TEMPLATE, builtin_templates};
+
+    let options = PageOptions{ url_prefix: "/slurm".to_string(), custom_css: true, .. PageOptions::default() };
+    let page = status_page(&SlurmStatus::new(), &options, "2015-12-18T10:00:00", "2015-12-20T10:00:00");
+    let html = builtin_templates().render(STATUS_TEMPLATE, &page.to_json()).unwrap();
+    assert!(html.contains("href=\"/slurm/static/style.css\""));
+    assert!(html.contains("href=\"/slurm/static/custom.css\""));
+    assert!(html.contains("action=\"/slurm/refresh\""));
+    assert!(!html.contains("\"/static/"));
+}
+
+#[test]
+fn test_status_page_columns() {
+    use sinfo_util::get_partition_node_info_test;
+    use squeue_util::get_job_info_test;
+    use template::{STATUS_TEMPLATE, builtin_templates};
+
+    let mut status = SlurmStatus::new();
+    status.node_info = get_partition_node_info_test();
+    status.job_info = get_job_info_test();
+    let options = PageOptions{ job_columns: vec!["user_name".to_string(), "job_id".to_string()], node_columns: vec!["node".to_string()],
+        unknown_job_columns: vec!["size".to_string()], .. PageOptions::default() };
+    let page = status_page(&status, &options, "2015-12-18T10:00:00", "2015-12-20T10:00:00");
+
+    assert_eq!(page.job_headers, vec!["User name".to_string(), "Job ID".to_string()]);
+    assert_eq!(page.job_colspan, 2);
+    assert_eq!(page.node_headers, vec!["Node".to_string()]);
+    assert_eq!(page.column_notices.len(), 1);
+    assert!(page.column_notices[0].starts_with("Unknown job columns ignored: size (valid columns: cluster, executing_host,"));
+
+    let html = builtin_templates().render(STATUS_TEMPLATE, &page.to_json()).unwrap();
+    assert!(html.contains("<tr>\n<th>User name</th><th>Job ID</th></tr>\n<tr id=\"job-1\">\n<td>user01</td><td>1</td></tr>\n"));
+    assert!(html.contains("<tr id=\"node-node03\">\n<td><a href=\"#job-6\">node03</a></td></tr>\n"));
+    assert!(html.contains("<p class=\"filter_notice\">Unknown job columns ignored: size"));
+    // The cluster column is only shown with several clusters, even if it's selected
+    let options = PageOptions{ job_columns: vec!["cluster".to_string(), "job_id".to_string()], .. PageOptions::default() };
+    assert_eq!(status_page(&status, &options, "2015-12-18T10:00:00", "2015-12-20T10:00:00").job_headers, vec!["Job ID".to_string()]);
+}
+
+#[test]
+fn test_status_page_priority_factors() {
+    use squeue_util::get_job_info_test;
+    use sprio_util::get_priority_info_test;
+    use template::{STATUS_TEMPLATE, builtin_templates};
+
+    let mut status = SlurmStatus::new();
+    status.job_info = get_job_info_test();
+    status.priority_info = get_priority_info_test();
+    // Pending, but missing in sprio
+    status.job_info[0].job_state = JobState::Pending;
+
+    let page = status_page(&status, &PageOptions::default(), "2015-12-18T10:00:00", "2015-12-20T10:00:00");
+    let factors: Vec<(String, Option<String>)> = page.jobs.iter().filter_map(|group| match *group {
+        JobGroupRow::Single(ref row) => Some((row.job_id.clone(), row.priority_factors.clone())),
+        JobGroupRow::Array(_) => None
+    }).filter(|&(_, ref factors)| factors.is_some()).collect();
+    assert_eq!(factors, vec![("8".to_string(), Some("Age: 1000, Fair-share: 500, Job size: 10, Partition: 10000, QOS: 0".to_string()))]);
+
+    let html = builtin_templates().render(STATUS_TEMPLATE, &page.to_json()).unwrap();
+    // Below the exact priority
+    assert!(html.contains("<td title=\"0.9\nAge: 1000, Fair-share: 500, Job size: 10, Partition: 10000, QOS: 0\" data-sort=\"0.9\">"));
+    assert_eq!(html.matches("\nAge:").count(), 1);
+}
+
+#[test]
+fn test_status_page_config() {
+    use configuration::Configuration;
+    use template::{STATUS_TEMPLATE, builtin_templates};
+
+    let status = SlurmStatus::new();
+    let config = Configuration{ webhook_url: Some("https://hooks.example.com/SECRET_TOKEN".to_string()), .. Configuration::default() };
+    let page = status_page(&status, &PageOptions::new(&config), "2015-12-18T10:00:00", "2015-12-20T10:00:00");
+    assert!(page.config.is_empty());
+    let html = builtin_templates().render(STATUS_TEMPLATE, &page.to_json()).unwrap();
+    assert!(!html.contains("<summary><b>Configuration</b></summary>"));
+
+    let config = Configuration{ debug_endpoints: true, .. config };
+    let page = status_page(&status, &PageOptions::new(&config), "2015-12-18T10:00:00", "2015-12-20T10:00:00");
+    let html = builtin_templates().render(STATUS_TEMPLATE, &page.to_json()).unwrap();
+    assert!(html.contains("<summary><b>Configuration</b></summary>"));
+    assert!(html.contains("<td>interval</td><td>60</td>"));
+    assert!(html.contains("<td>webhook_url</td><td>(redacted)</td>"));
+    assert!(!html.contains("SECRET_TOKEN"));
+}
+
+#[test]
+fn test_status_page_job_order() {
+    use squeue_util::get_job_info_test;
+    use job_sort::JobSort;
+
+    let mut status = SlurmStatus::new();
+    status.job_info = get_job_info_test();
+    let job_ids = |sort: JobSort| {
+        let page = status_page(&status, &PageOptions { sort: sort, .. PageOptions::default() }, "2015-12-18T10:00:00", "2015-12-20T10:00:00");
+        page.jobs.iter().filter_map(|group| match *group {
+            JobGroupRow::Single(ref row) => Some(row.job_id.clone()),
+            JobGroupRow::Array(_) => None
+        }).collect::<Vec<String>>()
+    };
+
+    // By default the running job comes first, then the pending job, then the rest in the order of squeue
+    assert_eq!(job_ids(JobSort::default()), vec!["1", "8", "2", "3", "4", "5", "6", "7", "9", "10", "11", "12"]);
+    assert_eq!(job_ids(JobSort::Squeue), vec!["1", "2", "3", "4", "5", "6", "7", "8", "9", "10", "11", "12"]);
+    assert_eq!(job_ids(JobSort::Priority), vec!["1", "2", "5", "6", "7", "8", "11", "10", "9", "4", "12", "3"]);
+}
+
+#[test]
+fn test_status_page_own_jobs() {
+    use squeue_util::{get_job_info_test, str_to_job_id};
+    use job_sort::JobSort;
+    use template::{STATUS_TEMPLATE, builtin_templates};
+
+    let mut status = SlurmStatus::new();
+    status.job_info = get_job_info_test();
+    let mut step = status.job_info[0].clone();
+    step.job_id = str_to_job_id("1.batch");
+    status.job_info.insert(1, step);
+
+    // Without a user nothing is highlighted
+    let page = status_page(&status, &PageOptions::default(), "2015-12-18T10:00:00", "2015-12-20T10:00:00");
+    assert_eq!(page.own_jobs, None);
+    let html = builtin_templates().render(STATUS_TEMPLATE, &page.to_json()).unwrap();
+    assert!(!html.contains("own_job"));
+
+    let options = PageOptions { me: Some("user01".to_string()), sort: JobSort::Squeue, .. PageOptions::default() };
+    let page = status_page(&status, &options, "2015-12-18T10:00:00", "2015-12-20T10:00:00");
+    assert_eq!(page.own_jobs, Some("Your jobs: 1 running, 0 pending".to_string()));
+    let classes: Vec<(String, Option<String>)> = page.jobs.iter().filter_map(|group| match *group {
+        JobGroupRow::Single(ref row) => Some((row.job_id.clone(), row.row_class())),
+        JobGroupRow::Array(_) => None
+    }).collect();
+    assert_eq!(&classes[..3], &[("1".to_string(), Some("own_job".to_string())), ("1.batch".to_string(), Some("job_step own_job".to_string())),
+        ("2".to_string(), None)]);
+
+    let html = builtin_templates().render(STATUS_TEMPLATE, &page.to_json()).unwrap();
+    assert!(html.contains("<tr id=\"job-1\" class=\"own_job\">"));
+    assert!(html.contains("<tr id=\"job-1.batch\" class=\"job_step own_job\">"));
+    assert!(html.contains("<tr id=\"job-2\">"));
+    assert!(html.contains("<p class=\"own_jobs\">Your jobs: 1 running, 0 pending</p>"));
+}
+
+#[test]
+fn test_status_page_dependencies() {
+    use squeue_util::{get_job_info_test, str_to_job_id};
+    use job_dependencies::parse_dependencies;
+    use template::{STATUS_TEMPLATE, builtin_templates};
+
+    let mut status = SlurmStatus::new();
+    status.job_info = get_job_info_test();
+    // Job 8 waits for the failed job 6, job 13 for job 8 and for the running job 1
+    let mut waiting = status.job_info[7].clone();
+    waiting.job_id = str_to_job_id("13");
+    waiting.dependencies = parse_dependencies("afterany:8,afterok:1,singleton");
+    status.job_info.push(waiting);
+
+    let page = status_page(&status, &PageOptions::default(), "2015-12-18T10:00:00", "2015-12-20T10:00:00");
+    let row = |job_id: &str| page.jobs.iter().filter_map(|group| match *group {
+        JobGroupRow::Single(ref row) if row.job_id == job_id => Some(row.clone()),
+        _ => None
+    }).next().unwrap();
+    assert_eq!(row("8").depends_on, vec![Link { anchor: "job-6".to_string(), text: "afterok:6".to_string(), class: Some("dependency_stuck".to_string()) }]);
+    assert_eq!(row("13").depends_on.iter().map(|link| link.class.is_some()).collect::<Vec<bool>>(), vec![true, false]);
+    assert_eq!((row("13").singleton, &row("13").dependencies[..]), (true, "afterany:8,afterok:1,singleton"));
+    assert_eq!(row("1").dependencies, "-");
+
+    let html = builtin_templates().render(STATUS_TEMPLATE, &page.to_json()).unwrap();
+    assert!(html.contains("<td><a href=\"#job-6\" class=\"dependency_stuck\">afterok:6</a></td>"));
+    assert!(html.contains("<td><a href=\"#job-8\" class=\"dependency_stuck\">afterany:8</a>,<a href=\"#job-1\">afterok:1</a>,singleton</td>"));
+}
+
+#[test]
+fn test_status_page_qos_limits() {
+    use squeue_util::{StateReason, get_job_info_test};
+    use sacctmgr_util::get_qos_info_test;
+    use template::{STATUS_TEMPLATE, builtin_templates};
+
+    let mut status = SlurmStatus::new();
+    status.job_info = get_job_info_test();
+    for job in &mut status.job_info {
+        job.state_reason = StateReason::QOSMaxCpuPerUserLimit;
+    }
+    status.qos_info = get_qos_info_test();
+
+    // Only the pending job 8 waits for the QOS
+    let page = status_page(&status, &PageOptions::default(), "2015-12-18T10:00:00", "2015-12-20T10:00:00");
+    let limits: Vec<(String, Option<String>)> = page.jobs.iter().filter_map(|group| match *group {
+        JobGroupRow::Single(ref row) => Some((row.job_id.clone(), row.qos_limits.clone())),
+        JobGroupRow::Array(_) => None
+    }).filter(|&(_, ref limits)| limits.is_some()).collect();
+    assert_eq!(limits, vec![("8".to_string(), Some("Max. CPUs per user: normal 64, long 16".to_string()))]);
+
+    let html = builtin_templates().render(STATUS_TEMPLATE, &page.to_json()).unwrap();
+    assert!(html.contains("<td title=\"Max. CPUs per user: normal 64, long 16\">QOSMaxCpuPerUserLimit</td>"));
+    assert!(html.contains("<h3>QOS limits:</h3>"));
+    assert!(html.contains("<td>normal</td><td>50</td><td>2-00:00:00</td><td>cpu=64,gres/gpu=4</td><td>50</td><td>100</td>"));
+
+    // Without --enable-qos there is neither a table nor a tooltip
+    status.qos_info.clear();
+    let html = builtin_templates().render(STATUS_TEMPLATE, &status_page(&status, &PageOptions::default(), "2015-12-18T10:00:00",
+        "2015-12-20T10:00:00").to_json()).unwrap();
+    assert!(!html.contains("QOS limits"));
+    assert!(!html.contains("Max. CPUs per user"));
+}
+
+#[test]
+fn test_status_page_finished_jobs() {
+    use squeue_util::get_job_info_test;
+    use status_filter::StatusFilter;
+    use template::{STATUS_TEMPLATE, builtin_templates};
+
+    let mut status = SlurmStatus::new();
+    status.job_info = get_job_info_test();
+    for job in &mut status.job_info {
+        job.job_state = JobState::Running;
+    }
+    status.job_info[0].job_state = JobState::Completed;
+    status.job_info[1].job_state = JobState::Failed;
+    let total = status.job_info.len();
+
+    // Hidden by default (PageOptions::new)
+    let hidden = PageOptions{ filter: StatusFilter{ hide_finished: true, .. StatusFilter::new() }, .. PageOptions::default() };
+    let page = status_page(&status, &hidden, "2015-12-18T10:00:00", "2015-12-20T10:00:00");
+    assert_eq!(page.finished_hidden, 2);
+    assert_eq!(page.finished_toggle, Some("?all_jobs=1".to_string()));
+    let html = builtin_templates().render(STATUS_TEMPLATE, &page.to_json()).unwrap();
+    assert!(html.contains("<p class=\"finished_toggle\">2 finished jobs hidden, <a href=\"?all_jobs=1\">show finished jobs</a></p>"));
+    assert!(!html.contains("<tr id=\"job-1\">"));
+
+    // Shown with ?all_jobs=1, the link hides them again
+    let options = PageOptions{ filter: StatusFilter::new(), query: "all_jobs=1&limit=50".to_string(), .. PageOptions::default() };
+    let page = status_page(&status, &options, "2015-12-18T10:00:00", "2015-12-20T10:00:00");
+    assert_eq!(page.finished_hidden, 0);
+    assert_eq!(page.finished_toggle, Some("?limit=50&all_jobs=0".to_string()));
+    assert_eq!(page.jobs.len(), total);
+    let html = builtin_templates().render(STATUS_TEMPLATE, &page.to_json()).unwrap();
+    assert!(html.contains("<a href=\"?limit=50&amp;all_jobs=0\">hide finished jobs</a>"));
+
+    // No finished jobs, no link
+    status.job_info[0].job_state = JobState::Running;
+    status.job_info[1].job_state = JobState::Pending;
+    let page = status_page(&status, &hidden, "2015-12-18T10:00:00", "2015-12-20T10:00:00");
+    assert_eq!((page.finished_hidden, page.finished_toggle), (0, None));
+}
+
+#[test]
+fn test_status_page_pressure() {
+    use squeue_util::{StateReason, get_job_info_test};
+    use scheduler_pressure::parse_pressure_thresholds;
+    use template::{STATUS_TEMPLATE, builtin_templates};
+
+    // One running job and three pending jobs of two users, the oldest waits 30 hours
+    let template = get_job_info_test().remove(0);
+    let job = |user: &str, state: JobState, reason: StateReason, wait_time: Option<u64>| JobInfo {
+        user_name: user.to_string(),
+        job_state: state,
+        state_reason: reason,
+        wait_time: wait_time,
+        .. template.clone()
+    };
+    let mut status = SlurmStatus::new();
+    status.job_info = vec![
+        job("user01", JobState::Running, StateReason::None, None),
+        job("user01", JobState::Pending, StateReason::Resources, Some(108000)),
+        job("user02", JobState::Pending, StateReason::Priority, Some(600)),
+        job("user02", JobState::Pending, StateReason::Priority, Some(60))
+    ];
+
+    let page = status_page(&status, &PageOptions::default(), "2015-12-18T10:00:00", "2015-12-20T10:00:00");
+    let boxes: Vec<(&str, &str, PressureLevel)> = page.pressure.iter().map(|pressure| (&pressure.number[..], &pressure.label[..], pressure.level)).collect();
+    assert_eq!(boxes, vec![
+        ("2", "Users waiting", PressureLevel::Normal),
+        ("1d 6h 0m", "Oldest pending job", PressureLevel::Amber),
+        ("3.0", "Pending per running job", PressureLevel::Amber),
+        ("1", "Waiting for resources", PressureLevel::Normal),
+        ("2", "Waiting for priority", PressureLevel::Normal)
+    ]);
+
+    let options = PageOptions {
+        pressure_thresholds: parse_pressure_thresholds("pending_users=1:2,oldest_pending=none,pending_priority=3:4").unwrap(),
+        .. PageOptions::default()
+    };
+    let page = status_page(&status, &options, "2015-12-18T10:00:00", "2015-12-20T10:00:00");
+    let levels: Vec<PressureLevel> = page.pressure.iter().map(|pressure| pressure.level).collect();
+    assert_eq!(levels, vec![PressureLevel::Red, PressureLevel::Normal, PressureLevel::Amber, PressureLevel::Normal, PressureLevel::Normal]);
+
+    let html = builtin_templates().render(STATUS_TEMPLATE, &page.to_json()).unwrap();
+    assert!(html.contains("<div class=\"dashboard_box dashboard_red\"><span class=\"dashboard_number\">2</span><br>Users waiting</div>"));
+    assert!(html.contains("<div class=\"dashboard_box dashboard_amber\"><span class=\"dashboard_number\">3.0</span><br>Pending per running job</div>"));
+    assert!(html.contains("<div class=\"dashboard_box\"><span class=\"dashboard_number\">1d 6h 0m</span><br>Oldest pending job</div>"));
+}
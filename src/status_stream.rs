@@ -0,0 +1,247 @@
+//! Server-Sent Events stream of status updates (/events), for example for a live wall display
+//! The SLURM status thread broadcasts every new snapshot to the registered connections,
+//! each connection has its own channel and is removed when it's closed
+
+// System modules:
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::sync::mpsc::{channel, Sender, Receiver, RecvTimeoutError};
+use std::time::Duration;
+use std::io::{self, Write};
+
+// External modules:
+use iron::response::{WriteBody, ResponseBody};
+
+// Internal modules:
+use slurm_status::{SlurmStatus, summary_to_json, status_to_json};
+use status_filter::StatusFilter;
+
+/// A comment is sent after this many seconds without update, so proxies don't close the idle connection
+pub const HEARTBEAT_INTERVAL: u64 = 15;
+
+/// Maximum number of open streams, each one occupies a thread of the web server
+pub const MAX_SUBSCRIBERS: usize = 16;
+
+/// One snapshot of the SLURM status as it is sent to the streams
+#[derive(Debug, Clone, PartialEq)]
+pub struct StatusEvent {
+    /// Time of the last update, the headline numbers and the errors, see summary_to_json
+    pub summary: String,
+    /// The complete status like /api/status, only sent to streams with ?full=1
+    pub full: String
+}
+
+impl StatusEvent {
+    /// Create the event for the given status, filter is the team of --users
+    pub fn new(status: &SlurmStatus, filter: &StatusFilter) -> StatusEvent {
+        StatusEvent {
+            summary: summary_to_json(status, filter),
+            // Already rendered after the update, an empty status (before the first update) isn't
+            full: if status.rendered.json.plain.is_empty() { status_to_json(status, filter) } else { status.rendered.json.plain.clone() }
+        }
+    }
+
+    /// The data of the event for a stream with or without the full status
+    pub fn data(&self, full: bool) -> &str {
+        if full { &self.full } else { &self.summary }
+    }
+}
+
+/// The channels of all open streams, shared between the SLURM status thread and the web handlers
+#[derive(Debug)]
+pub struct Subscribers {
+    senders: Mutex<Vec<Sender<Arc<StatusEvent>>>>
+}
+
+impl Subscribers {
+    /// Create an empty registry
+    pub fn new() -> Subscribers {
+        Subscribers {
+            senders: Mutex::new(Vec::new())
+        }
+    }
+
+    // Private helper function to lock the senders, a panic of another thread doesn't leave the list inconsistent
+    fn lock<'a>(&'a self) -> MutexGuard<'a, Vec<Sender<Arc<StatusEvent>>>> {
+        self.senders.lock().unwrap_or_else(|err| err.into_inner())
+    }
+
+    /// Register a new stream, None if there are already MAX_SUBSCRIBERS streams
+    pub fn subscribe(&self) -> Option<Receiver<Arc<StatusEvent>>> {
+        let mut senders = self.lock();
+
+        if senders.len() >= MAX_SUBSCRIBERS {
+            return None;
+        }
+        let (sender, receiver) = channel();
+        senders.push(sender);
+        Some(receiver)
+    }
+
+    /// Number of registered streams
+    pub fn len(&self) -> usize {
+        self.lock().len()
+    }
+
+    /// True if there are no registered streams, so the event doesn't have to be created
+    pub fn is_empty(&self) -> bool {
+        self.lock().is_empty()
+    }
+
+    /// Send the event to all streams, the ones that have been closed are removed
+    pub fn broadcast(&self, event: StatusEvent) {
+        let event = Arc::new(event);
+        let mut senders = self.lock();
+
+        senders.retain(|sender| sender.send(event.clone()).is_ok());
+        debug!("Status event sent to {} streams", senders.len());
+    }
+}
+
+/// Public helper function that formats one Server-Sent Event, every line of the data gets its own "data:" field
+pub fn format_event(name: &str, data: &str) -> String {
+    let mut result = format!("event: {}\n", name);
+
+    for line in data.lines() {
+        result.push_str(&format!("data: {}\n", line));
+    }
+    result.push('\n');
+    result
+}
+
+/// Public helper function that formats the heartbeat, a comment that clients ignore
+pub fn format_heartbeat() -> String {
+    ": heartbeat\n\n".to_string()
+}
+
+/// Body of the /events response, it's written until the client closes the connection
+pub struct EventStream {
+    receiver: Receiver<Arc<StatusEvent>>,
+    /// Send the full status instead of the summary
+    full: bool,
+    /// The current status, sent right after connecting
+    initial: StatusEvent,
+    heartbeat: Duration
+}
+
+impl EventStream {
+    /// Create the stream for the given channel, the current status is sent first
+    pub fn new(receiver: Receiver<Arc<StatusEvent>>, full: bool, initial: StatusEvent) -> EventStream {
+        EventStream {
+            receiver: receiver,
+            full: full,
+            initial: initial,
+            heartbeat: Duration::new(HEARTBEAT_INTERVAL, 0)
+        }
+    }
+
+    /// Write the events to the connection, a write error means that the client is gone
+    /// When the function returns the receiver is dropped, so the next broadcast removes this stream
+    pub fn write_events(&mut self, res: &mut dyn Write) -> io::Result<()> {
+        res.write_all(format_event("status", self.initial.data(self.full)).as_bytes())?;
+        res.flush()?;
+
+        loop {
+            let message = match self.receiver.recv_timeout(self.heartbeat) {
+                Ok(event) => format_event("status", event.data(self.full)),
+                Err(RecvTimeoutError::Timeout) => format_heartbeat(),
+                Err(RecvTimeoutError::Disconnected) => return Ok(())
+            };
+            res.write_all(message.as_bytes())?;
+            res.flush()?;
+        }
+    }
+}
+
+impl WriteBody for EventStream {
+    fn write_body(&mut self, res: &mut ResponseBody) -> io::Result<()> {
+        self.write_events(res)
+    }
+}
+
+#[test]
+fn test_format_event() {
+    assert_eq!(format_event("status", "{\"last_update\":\"2000.01.01 - 09:00\"}"), "event: status\ndata: {\"last_update\":\"2000.01.01 - 09:00\"}\n\n");
+    assert_eq!(format_event("status", "a\nb"), "event: status\ndata: a\ndata: b\n\n");
+    assert_eq!(format_heartbeat(), ": heartbeat\n\n");
+}
+
+#[test]
+fn test_subscribers() {
+    let subscribers = Subscribers::new();
+    let event = StatusEvent{ summary: "{}".to_string(), full: "{\"job_info\":[]}".to_string() };
+    assert!(subscribers.is_empty());
+
+    let first = subscribers.subscribe().unwrap();
+    let second = subscribers.subscribe().unwrap();
+    assert_eq!(subscribers.len(), 2);
+
+    subscribers.broadcast(event.clone());
+    assert_eq!(*first.try_recv().unwrap(), event);
+    assert_eq!(*second.try_recv().unwrap(), event);
+
+    // A closed stream is removed with the next broadcast
+    drop(first);
+    subscribers.broadcast(event.clone());
+    assert_eq!(subscribers.len(), 1);
+    assert_eq!(second.try_recv().unwrap().data(true), "{\"job_info\":[]}");
+}
+
+#[test]
+fn test_subscribers_limit() {
+    let subscribers = Subscribers::new();
+    let receivers: Vec<_> = (0..MAX_SUBSCRIBERS).map(|_| subscribers.subscribe().unwrap()).collect();
+
+    assert!(subscribers.subscribe().is_none());
+    drop(receivers);
+    subscribers.broadcast(StatusEvent{ summary: String::new(), full: String::new() });
+    assert!(subscribers.subscribe().is_some());
+}
+
+#[test]
+fn test_event_stream() {
+    let subscribers = Subscribers::new();
+    let receiver = subscribers.subscribe().unwrap();
+    let initial = StatusEvent{ summary: "{\"n\":0}".to_string(), full: "{\"full\":0}".to_string() };
+    let mut stream = EventStream{ heartbeat: Duration::from_millis(1), .. EventStream::new(receiver, false, initial) };
+
+    subscribers.broadcast(StatusEvent{ summary: "{\"n\":1}".to_string(), full: "{\"full\":1}".to_string() });
+    // Dropping the registry closes the channel, so the stream ends after the queued event
+    drop(subscribers);
+    let mut output = Vec::new();
+    stream.write_events(&mut output).unwrap();
+
+    assert_eq!(String::from_utf8(output).unwrap(), "event: status\ndata: {\"n\":0}\n\nevent: status\ndata: {\"n\":1}\n\n");
+}
+
+#[test]
+fn test_event_stream_heartbeat() {
+    // Fails on the third write, like a closed connection
+    struct ClosingWriter {
+        writes: Vec<String>
+    }
+
+    impl Write for ClosingWriter {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            if self.writes.len() == 2 {
+                return Err(io::Error::new(io::ErrorKind::BrokenPipe, "closed"));
+            }
+            self.writes.push(String::from_utf8_lossy(buf).into_owned());
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    let subscribers = Subscribers::new();
+    let initial = StatusEvent{ summary: "{}".to_string(), full: String::new() };
+    let mut stream = EventStream{ heartbeat: Duration::from_millis(1), .. EventStream::new(subscribers.subscribe().unwrap(), false, initial) };
+    let mut writer = ClosingWriter{ writes: Vec::new() };
+
+    assert!(stream.write_events(&mut writer).is_err());
+    assert_eq!(writer.writes, vec!["event: status\ndata: {}\n\n".to_string(), ": heartbeat\n\n".to_string()]);
+    drop(stream);
+    subscribers.broadcast(StatusEvent{ summary: String::new(), full: String::new() });
+    assert!(subscribers.is_empty());
+}
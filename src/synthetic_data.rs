@@ -0,0 +1,355 @@
+//! Synthetic SLURM data for the test mode, to see how the page behaves with a cluster of realistic size
+//! The generator writes the output of sinfo and squeue for the given number of nodes, partitions and jobs,
+//! which then goes through the same parsers as the real output. The same seed always gives the same data
+
+// External modules:
+use time::{Duration, strptime, strftime, at_utc};
+
+// Internal modules:
+use hostlist::compress_hostlist;
+use time_limit::{duration_to_seconds, format_duration};
+use wait_times::TIME_FORMAT;
+
+/// Default number of nodes with --test-jobs, --test-partitions or --test-seed
+pub const DEFAULT_TEST_NODES: usize = 100;
+
+/// Default number of partitions with --test-nodes, --test-jobs or --test-seed
+pub const DEFAULT_TEST_PARTITIONS: usize = 4;
+
+/// Default number of jobs with --test-nodes, --test-partitions or --test-seed
+pub const DEFAULT_TEST_JOBS: usize = 1000;
+
+/// Default seed of the random numbers
+pub const DEFAULT_TEST_SEED: u64 = 1;
+
+/// The test jobs are generated relative to this time, their wait times are computed with it as well
+pub const TEST_NOW: &'static str = "2000-01-01T10:00:00";
+
+/// Names of the first partitions, further partitions are called part07, part08, ...
+const PARTITION_NAMES: &'static [&'static str] = &["batch", "short", "long", "gpu", "bigmem", "debug"];
+
+/// Prefixes of the job names
+const JOB_NAMES: &'static [&'static str] = &["sim", "train", "md_run", "cfd", "analysis", "bench", "wrf", "gromacs", "vasp", "postproc"];
+
+/// Node states as printed by sinfo with their frequency in per mille
+const NODE_STATES: &'static [(&'static str, u32)] = &[
+    ("alloc", 450), ("mixed", 250), ("idle", 180), ("drained", 30), ("draining", 15), ("down*", 30),
+    ("completing", 10), ("reserved", 10), ("maint", 5), ("fail", 5), ("idle~", 15)
+];
+
+/// Job states as printed by squeue with their frequency in per mille, squeue shows finished jobs for a few minutes
+const JOB_STATES: &'static [(&'static str, u32)] = &[
+    ("RUNNING", 550), ("PENDING", 350), ("COMPLETING", 20), ("CONFIGURING", 15), ("SUSPENDED", 10), ("COMPLETED", 15),
+    ("CANCELLED", 10), ("FAILED", 10), ("TIMEOUT", 8), ("PREEMPTED", 6), ("NODE_FAIL", 6)
+];
+
+/// Reasons of the pending jobs with their frequency in per mille
+const PENDING_REASONS: &'static [(&'static str, u32)] = &[
+    ("Priority", 500), ("Resources", 300), ("Dependency", 100), ("QOSMaxJobsPerUserLimit", 70), ("PartitionTimeLimit", 30)
+];
+
+/// Time limits of the jobs with their frequency in per mille
+const TIME_LIMITS: &'static [(&'static str, u32)] = &[
+    ("30:00", 150), ("1:00:00", 200), ("4:00:00", 200), ("12:00:00", 150), ("1-00:00:00", 200), ("2-00:00:00", 80), ("UNLIMITED", 20)
+];
+
+/// Number of nodes of the jobs with their frequency in per mille
+const JOB_NODES: &'static [(usize, u32)] = &[(1, 700), (2, 150), (4, 100), (8, 50)];
+
+/// Size of the synthetic cluster
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SyntheticData {
+    pub nodes: usize,
+    pub partitions: usize,
+    /// Number of lines of squeue, every task of a job array counts as a job
+    pub jobs: usize,
+    pub seed: u64
+}
+
+impl Default for SyntheticData {
+    fn default() -> SyntheticData {
+        SyntheticData {
+            nodes: DEFAULT_TEST_NODES,
+            partitions: DEFAULT_TEST_PARTITIONS,
+            jobs: DEFAULT_TEST_JOBS,
+            seed: DEFAULT_TEST_SEED
+        }
+    }
+}
+
+/// Small pseudo random number generator (xorshift64*), good enough for test data and without external crate
+#[derive(Debug, Clone)]
+pub struct Random {
+    state: u64
+}
+
+impl Random {
+    /// Create a new generator, the same seed always gives the same numbers
+    pub fn new(seed: u64) -> Random {
+        // The state must not be 0, otherwise all numbers are 0
+        let state = seed.wrapping_mul(0x9e37_79b9_7f4a_7c15) ^ 0x2545_f491_4f6c_dd1d;
+        Random {
+            state: if state == 0 { 1 } else { state }
+        }
+    }
+
+    /// The next random number
+    pub fn next_u64(&mut self) -> u64 {
+        self.state ^= self.state >> 12;
+        self.state ^= self.state << 25;
+        self.state ^= self.state >> 27;
+        self.state.wrapping_mul(0x2545_f491_4f6c_dd1d)
+    }
+
+    /// A random number from 0 to limit - 1, 0 if the limit is 0
+    pub fn below(&mut self, limit: u64) -> u64 {
+        if limit == 0 { 0 } else { self.next_u64() % limit }
+    }
+
+    /// A random number from 0.0 to 1.0 (exclusive)
+    pub fn unit(&mut self) -> f64 {
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+
+    /// One of the choices, each one with the given weight
+    pub fn weighted<T: Copy>(&mut self, choices: &[(T, u32)]) -> T {
+        let total: u32 = choices.iter().map(|&(_, weight)| weight).sum();
+        let mut value = self.below(total as u64) as u32;
+
+        for &(choice, weight) in choices {
+            if value < weight {
+                return choice;
+            }
+            value -= weight;
+        }
+        choices[choices.len() - 1].0
+    }
+}
+
+/// Public helper function that returns the name of the partition with the given index
+pub fn partition_name(index: usize) -> String {
+    match PARTITION_NAMES.get(index) {
+        Some(name) => name.to_string(),
+        None => format!("part{:02}", index + 1)
+    }
+}
+
+// Private helper function that returns the range of nodes (by index) of the given partition
+// The nodes are split into blocks of about the same size, a partition without node gets the first node
+fn partition_nodes(data: &SyntheticData, partition: usize) -> (usize, usize) {
+    let partitions = data.partitions.max(1);
+    let start = partition * data.nodes / partitions;
+    let end = (partition + 1) * data.nodes / partitions;
+
+    if end > start { (start, end) } else { (0, data.nodes.min(1)) }
+}
+
+// Private helper function that returns the CPUs per node of the given partition: sockets, cores per socket and threads per core
+fn partition_cpus(partition: usize) -> (u32, u32, u32) {
+    let cores = [8, 16, 24, 32][partition % 4];
+    let threads = if partition_name(partition) == "gpu" { 2 } else { 1 };
+
+    (2, cores, threads)
+}
+
+// Private helper function that formats a time before TEST_NOW
+fn time_before(seconds: u64) -> String {
+    let now = strptime(TEST_NOW, TIME_FORMAT).unwrap().to_timespec();
+    strftime(TIME_FORMAT, &at_utc(now - Duration::seconds(seconds as i64))).unwrap()
+}
+
+/// Public helper function that returns the output of sinfo (-o "%R %a %n %N %E %O %T %X %Y %Z %C %G %m %e") for the synthetic cluster
+pub fn synthetic_sinfo_output(data: &SyntheticData) -> String {
+    let mut random = Random::new(data.seed);
+    let mut output = String::new();
+
+    for partition in 0..data.partitions {
+        let name = partition_name(partition);
+        let (sockets, cores, threads) = partition_cpus(partition);
+        let total = sockets * cores * threads;
+        let gres = if name == "gpu" { "gpu:a100:4(S:0-1)" } else { "(null)" };
+        let (start, end) = partition_nodes(data, partition);
+
+        for node in start..end {
+            let state = random.weighted(NODE_STATES);
+            let partial = 1 + random.below(total as u64 - 1) as u32;
+            let (alloc, idle, other) = match state {
+                "alloc" | "completing" => (total, 0, 0),
+                "mixed" => (partial, total - partial, 0),
+                "idle" | "idle~" | "reserved" => (0, total, 0),
+                "draining" => (partial, 0, total - partial),
+                _ => (0, 0, total)
+            };
+            let reason = match state {
+                "drained" | "draining" => &state[..],
+                "down*" | "fail" => "down",
+                _ => "none"
+            };
+            let load = match state {
+                "down*" => "-".to_string(),
+                // A few allocated nodes without load (hanging jobs) and idle nodes with load (processes outside of SLURM)
+                _ if alloc > 0 && random.below(100) < 3 => "0.00".to_string(),
+                _ if alloc > 0 => format!("{:.2}", alloc as f64 * (0.85 + random.unit() * 0.2)),
+                _ if random.below(100) < 2 => format!("{:.2}", total as f64 * 0.5),
+                _ => format!("{:.2}", random.unit() * 0.3)
+            };
+            let hostname = format!("node{:04}", node + 1);
+            // 4 GB per CPU, the used memory follows the allocation and every 7th fully allocated node is almost full
+            // No extra random numbers, so the other values stay the same for a seed
+            let memory = total as u64 * 4000;
+            let used_percent = 5 + alloc as u64 * 80 / total as u64 + if alloc == total && node % 7 == 0 { 12 } else { 0 };
+            let free = if state == "down*" { "N/A".to_string() } else { (memory - memory * used_percent / 100).to_string() };
+
+            output.push_str(&format!("{} up {} {} {} {} {} {} {} {} {}/{}/{}/{} {} {} {}\n", name, hostname, hostname, reason, load, state,
+                sockets, cores, threads, alloc, idle, other, total, gres, memory, free));
+        }
+    }
+
+    output
+}
+
+/// Public helper function that returns the output of squeue (-o "%B %c %C %D %F %H %i %I %j %J %K %M %N %p %r %S %T %u %U %P %b %V %l %d")
+/// for the synthetic cluster, a few jobs are job arrays
+pub fn synthetic_squeue_output(data: &SyntheticData) -> String {
+    // Another sequence than the nodes, so changing the number of nodes doesn't change the jobs
+    let mut random = Random::new(data.seed ^ 0x5eed);
+    let mut output = String::new();
+    let users = (data.jobs / 40).max(5).min(200) as u64;
+    let mut job_id = 100000;
+    let mut lines = 0;
+
+    while lines < data.jobs && data.partitions > 0 && data.nodes > 0 {
+        let partition = random.below(data.partitions as u64) as usize;
+        let (sockets, cores, threads) = partition_cpus(partition);
+        let (start, end) = partition_nodes(data, partition);
+        let state = random.weighted(JOB_STATES);
+        // Users with a low number submit more jobs
+        let most_users = random.below(users) + 1;
+        let user = random.below(most_users);
+        let name = format!("{}_{}", JOB_NAMES[random.below(JOB_NAMES.len() as u64) as usize], random.below(1000));
+        let time_limit = random.weighted(TIME_LIMITS);
+        let limit_seconds = duration_to_seconds(time_limit).unwrap_or(3 * 86400);
+        let num_nodes = random.weighted(JOB_NODES).min(end - start);
+        let cpus_per_node = 1 + random.below((sockets * cores * threads) as u64) as u32;
+        let gres = if partition_name(partition) == "gpu" { format!("gres:gpu:a100:{}", 1 + random.below(4)) } else { "N/A".to_string() };
+        let priority = format!("{:.4}", random.unit());
+
+        // A few running and pending jobs are job arrays: the running tasks get one line each, the pending ones a single line
+        let tasks = match state {
+            "RUNNING" | "PENDING" if random.below(100) < 3 => 2 + random.below(9) as usize,
+            _ => 1
+        };
+        let array_id = job_id;
+
+        for task in 0..tasks {
+            let id = match (tasks, state) {
+                (1, _) => job_id.to_string(),
+                (_, "PENDING") => format!("{}_[0-{}]", array_id, tasks - 1),
+                _ => format!("{}_{}", array_id, task)
+            };
+            let (array_column, task_column) = match (tasks, state) {
+                (1, _) => ("N/A".to_string(), "N/A".to_string()),
+                (_, "PENDING") => (array_id.to_string(), "N/A".to_string()),
+                _ => (array_id.to_string(), task.to_string())
+            };
+
+            let (run_time, nodes, reason, start_time, wait) = if state == "PENDING" {
+                let reason = random.weighted(PENDING_REASONS);
+                ("0:00".to_string(), Vec::new(), reason, "N/A".to_string(), random.below(2 * 86400))
+            } else {
+                let run = if state == "CONFIGURING" { 0 } else { random.below(limit_seconds + 1) };
+                let first = start + random.below((end - start) as u64) as usize;
+                let nodes: Vec<String> = (0..num_nodes).map(|i| format!("node{:04}", start + (first - start + i) % (end - start) + 1)).collect();
+                let reason = match state {
+                    "FAILED" => "NonZeroExitCode",
+                    "TIMEOUT" => "TimeLimit",
+                    "NODE_FAIL" => "NodeDown",
+                    _ => "None"
+                };
+                (format_duration(run), nodes, reason, time_before(run), run + random.below(6 * 3600))
+            };
+            let executing_host = nodes.first().cloned().unwrap_or("N/A".to_string());
+            let node_list = if nodes.is_empty() { "(null)".to_string() } else { compress_hostlist(&nodes) };
+
+            output.push_str(&format!("{} {} {} {} {} * {} * {} * {} {} {} {} {} {} {} user{:03} {} {} {} {} {} (null)\n",
+                executing_host, cpus_per_node, cpus_per_node * num_nodes.max(1) as u32, num_nodes.max(1), array_column, id, name,
+                task_column, run_time, node_list, priority, reason, start_time, state, user + 1, 1000 + user + 1, partition_name(partition),
+                gres, time_before(wait), time_limit));
+
+            lines += 1;
+            job_id += 1;
+            if state == "PENDING" || lines == data.jobs {
+                break;
+            }
+        }
+    }
+
+    output
+}
+
+#[test]
+fn test_random() {
+    let numbers = |seed| { let mut random = Random::new(seed); (0..5).map(|_| random.next_u64()).collect::<Vec<u64>>() };
+    assert_eq!(numbers(1), numbers(1));
+    assert!(numbers(1) != numbers(2));
+    assert!(numbers(0).iter().all(|&number| number != 0));
+
+    let mut random = Random::new(42);
+    assert!((0..1000).all(|_| random.below(7) < 7));
+    assert_eq!(random.below(0), 0);
+    assert!((0..1000).map(|_| random.unit()).all(|value| value >= 0.0 && value < 1.0));
+    assert!((0..1000).all(|_| random.weighted(&[("a", 0), ("b", 1)]) == "b"));
+}
+
+#[test]
+fn test_partition_name() {
+    assert_eq!(partition_name(0), "batch");
+    assert_eq!(partition_name(3), "gpu");
+    assert_eq!(partition_name(6), "part07");
+}
+
+#[test]
+fn test_synthetic_nodes() {
+    use std::collections::BTreeSet;
+    use sinfo_util::{NodeState, get_partition_node_info_synthetic};
+
+    let data = SyntheticData{ nodes: 1000, partitions: 5, .. SyntheticData::default() };
+    let nodes = get_partition_node_info_synthetic(&data);
+    assert_eq!(nodes.len(), 1000);
+    assert_eq!(nodes.iter().map(|node| &node.partition[..]).collect::<BTreeSet<&str>>().len(), 5);
+    assert!(nodes.iter().all(|node| node.cpus_total.is_some() && node.cpus_alloc.unwrap_or(0) <= node.cpus_total.unwrap()));
+    let states: Vec<NodeState> = nodes.iter().map(|node| node.node_state).collect();
+    for state in &[NodeState::Allocated, NodeState::Mixed, NodeState::Idle, NodeState::Drained, NodeState::Draining, NodeState::Down,
+        NodeState::Completing, NodeState::Reserved, NodeState::Maint, NodeState::Fail] {
+        assert!(states.contains(state), "{:?} is missing", state);
+    }
+    assert_eq!(get_partition_node_info_synthetic(&data), nodes);
+
+    // More partitions than nodes
+    let data = SyntheticData{ nodes: 2, partitions: 3, .. SyntheticData::default() };
+    assert_eq!(get_partition_node_info_synthetic(&data).len(), 3);
+}
+
+#[test]
+fn test_synthetic_jobs() {
+    use squeue_util::{JobState, get_job_info_synthetic};
+    use sinfo_util::get_partition_node_info_synthetic;
+
+    let data = SyntheticData{ jobs: 10000, .. SyntheticData::default() };
+    let jobs = get_job_info_synthetic(&data);
+    assert_eq!(jobs.len(), 10000);
+    assert!(jobs.iter().all(|job| job.job_id.is_some() && job.submit_time.is_some()));
+    assert!(jobs.iter().any(|job| job.job_id.as_ref().unwrap().array_index.is_some()));
+    let states: Vec<JobState> = jobs.iter().map(|job| job.job_state).collect();
+    for state in &[JobState::Running, JobState::Pending, JobState::Completing, JobState::Configuring, JobState::Suspended, JobState::Completed,
+        JobState::Cancelled, JobState::Failed, JobState::Timeout, JobState::Preempted, JobState::NodeFail] {
+        assert!(states.contains(state), "{:?} is missing", state);
+    }
+    // Running jobs are on the nodes of their partition
+    let nodes: Vec<String> = get_partition_node_info_synthetic(&data).into_iter().map(|node| node.node).collect();
+    assert!(jobs.iter().filter(|job| job.job_state == JobState::Running).all(|job| job.list_of_nodes.iter().all(|node| nodes.contains(node))));
+
+    assert_eq!(get_job_info_synthetic(&data), jobs);
+    assert!(get_job_info_synthetic(&SyntheticData{ seed: 2, .. data }) != jobs);
+    assert_eq!(get_job_info_synthetic(&SyntheticData{ jobs: 0, .. data }).len(), 0);
+}
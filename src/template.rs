@@ -0,0 +1,819 @@
+//! Minimal template engine for the HTML page, the syntax is a subset of Handlebars
+//! Supported: {{value}} (HTML escaped), {{{value}}} (not escaped), {{#if}}, {{#unless}}, {{#each}} and {{#with}} with {{else}},
+//! partials {{> name}}, comments {{! text}} and the paths "a.b", "this", "../a", "@root.a", "@index", "@first", "@last" and "@key"
+//! Like in Handlebars, lines that only contain a block tag, a partial or a comment are removed from the output
+
+// System modules:
+use std::borrow::Cow;
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::fs::{File, read_dir};
+use std::io::Read;
+
+// External modules:
+use rustc_serialize::json::Json;
+
+/// Name of the template of the status page, the other templates are partials used by it
+pub const STATUS_TEMPLATE: &'static str = "status";
+
+/// Name of the template of the user pages (/user/<name>), it uses the job table of the status page
+pub const USER_TEMPLATE: &'static str = "user";
+
+/// Name of the template of the partition pages (/partition/<name>), it uses the tables of the status page
+pub const PARTITION_TEMPLATE: &'static str = "partition";
+
+/// Name of the template of the confirmation page of the admin actions (GET on the route of the action)
+pub const ADMIN_CONFIRM_TEMPLATE: &'static str = "admin_confirm";
+
+/// Name of the template of the recent errors at /debug/errors (with --debug-endpoints)
+pub const RECENT_ERRORS_TEMPLATE: &'static str = "recent_errors";
+
+/// The built-in templates (name and source), they are embedded in the binary
+pub const BUILTIN_TEMPLATES: [(&'static str, &'static str); 14] = [
+    ("status", include_str!("../templates/status.hbs")),
+    ("user", include_str!("../templates/user.hbs")),
+    ("partition", include_str!("../templates/partition.hbs")),
+    ("admin_confirm", include_str!("../templates/admin_confirm.hbs")),
+    ("dashboard", include_str!("../templates/dashboard.hbs")),
+    ("reservations", include_str!("../templates/reservations.hbs")),
+    ("partitions", include_str!("../templates/partitions.hbs")),
+    ("job_header", include_str!("../templates/job_header.hbs")),
+    ("job_row", include_str!("../templates/job_row.hbs")),
+    ("job_table", include_str!("../templates/job_table.hbs")),
+    ("node_table", include_str!("../templates/node_table.hbs")),
+    ("finished_jobs", include_str!("../templates/finished_jobs.hbs")),
+    ("table_cell", include_str!("../templates/table_cell.hbs")),
+    ("recent_errors", include_str!("../templates/recent_errors.hbs"))
+];
+
+/// File extension of the templates in the template directory
+pub const TEMPLATE_EXTENSION: &'static str = "hbs";
+
+/// Maximum nesting depth of partials, protects against partials that include themselves
+const MAX_PARTIAL_DEPTH: usize = 16;
+
+/// Maximum nesting depth of blocks, deeper blocks would overflow the stack while the template is compiled or rendered
+const MAX_BLOCK_DEPTH: usize = 64;
+
+/// Path to a value in the context, for example "job.name", "../show_cluster" or "@index"
+#[derive(Debug, Clone, PartialEq)]
+struct ValuePath {
+    /// Start at the root context (@root)
+    root: bool,
+    /// Number of "../" at the beginning
+    parents: usize,
+    /// Name of a loop variable like "index", without the "@"
+    data: Option<String>,
+    /// Keys of the nested objects, empty for "this"
+    keys: Vec<String>
+}
+
+/// A tag between "{{" and "}}"
+#[derive(Debug, Clone, PartialEq)]
+enum Tag {
+    Value { path: ValuePath, escape: bool },
+    Open { helper: String, path: ValuePath },
+    Else,
+    Close(String),
+    Partial(String),
+    Comment
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Text(String),
+    /// The tag and the line it starts in, for error messages
+    Tag(Tag, usize)
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Node {
+    Text(String),
+    Value { path: ValuePath, escape: bool },
+    Block { helper: String, path: ValuePath, body: Vec<Node>, otherwise: Vec<Node> },
+    Partial(String)
+}
+
+/// A compiled template
+#[derive(Debug, Clone, PartialEq)]
+pub struct Template {
+    nodes: Vec<Node>
+}
+
+// Private helper function to parse the path of a value, for example "this", "a.b", "../a" or "@root.a"
+fn parse_path(path: &str) -> Result<ValuePath, String> {
+    let mut result = ValuePath { root: false, parents: 0, data: None, keys: Vec::new() };
+    let mut rest = path;
+
+    while rest.starts_with("../") {
+        result.parents += 1;
+        rest = &rest[3..];
+    }
+
+    if rest.starts_with("@root") {
+        result.root = true;
+        rest = rest[5..].trim_start_matches('.');
+    } else if rest.starts_with('@') {
+        match &rest[1..] {
+            "index" | "first" | "last" | "key" => result.data = Some(rest[1..].to_string()),
+            _ => return Err(format!("unknown variable '{}'", rest))
+        }
+        return Ok(result);
+    } else if rest == "this" || rest.starts_with("this.") {
+        rest = rest[4..].trim_start_matches('.');
+    }
+
+    if !rest.is_empty() {
+        for key in rest.split('.') {
+            if key.is_empty() || key.contains(|c: char| c.is_whitespace() || c == '{' || c == '}') {
+                return Err(format!("invalid path '{}'", path));
+            }
+            result.keys.push(key.to_string());
+        }
+    }
+
+    Ok(result)
+}
+
+// Private helper function to parse the content of a tag, raw is true for "{{{...}}}"
+fn parse_tag(content: &str, raw: bool) -> Result<Tag, String> {
+    if raw {
+        return Ok(Tag::Value { path: parse_path(content)?, escape: false });
+    }
+
+    if content.starts_with('!') {
+        Ok(Tag::Comment)
+    } else if content.starts_with('#') {
+        let words: Vec<&str> = content[1..].split_whitespace().collect();
+        match words.len() {
+            2 if ["if", "unless", "each", "with"].contains(&words[0]) => Ok(Tag::Open { helper: words[0].to_string(), path: parse_path(words[1])? }),
+            2 => Err(format!("unknown helper '{}'", words[0])),
+            _ => Err(format!("block helper needs exactly one argument: '{}'", content))
+        }
+    } else if content.starts_with('/') {
+        Ok(Tag::Close(content[1..].trim().to_string()))
+    } else if content.starts_with('>') {
+        let name = content[1..].trim();
+        if name.is_empty() { Err("partial without name".to_string()) } else { Ok(Tag::Partial(name.to_string())) }
+    } else if content == "else" {
+        Ok(Tag::Else)
+    } else {
+        Ok(Tag::Value { path: parse_path(content)?, escape: true })
+    }
+}
+
+// Private helper function to split the source into text and tags
+fn tokenize(source: &str) -> Result<Vec<Token>, String> {
+    let mut result = Vec::new();
+    let mut rest = source;
+    let mut line = 1;
+
+    while let Some(start) = rest.find("{{") {
+        if start > 0 {
+            result.push(Token::Text(rest[..start].to_string()));
+        }
+        line += rest[..start].matches('\n').count();
+
+        let raw = rest[start..].starts_with("{{{");
+        let (open, close) = if raw { (3, "}}}") } else { (2, "}}") };
+        let after = &rest[start + open..];
+        let end = after.find(close).ok_or(format!("unclosed tag (line {})", line))?;
+        let tag = parse_tag(after[..end].trim(), raw).map_err(|err| format!("{} (line {})", err, line))?;
+
+        result.push(Token::Tag(tag, line));
+        line += after[..end].matches('\n').count();
+        rest = &after[end + close.len()..];
+    }
+
+    if !rest.is_empty() {
+        result.push(Token::Text(rest.to_string()));
+    }
+
+    Ok(result)
+}
+
+// Private helper function to check if the character is a space or a tab
+fn is_blank(c: char) -> bool {
+    c == ' ' || c == '\t' || c == '\r'
+}
+
+// Private helper function that returns the length of the indentation at the end of the text before a tag
+// None if the tag doesn't start a line
+fn line_indentation(text: &str, at_start: bool) -> Option<usize> {
+    let (tail, has_newline) = match text.rfind('\n') {
+        Some(index) => (&text[index + 1..], true),
+        None => (text, false)
+    };
+
+    if (has_newline || at_start) && tail.chars().all(is_blank) { Some(tail.len()) } else { None }
+}
+
+// Private helper function that returns the length of the rest of the line (including the newline) in the text after a tag
+// None if there is something else on the line
+fn line_rest(text: &str, at_end: bool) -> Option<usize> {
+    match text.find('\n') {
+        Some(index) if text[..index].chars().all(is_blank) => Some(index + 1),
+        None if at_end && text.chars().all(is_blank) => Some(text.len()),
+        _ => None
+    }
+}
+
+// Private helper function to remove the lines that only contain a block tag, a partial or a comment
+fn remove_standalone_lines(tokens: &mut Vec<Token>) {
+    let count = tokens.len();
+    let mut strip_start = vec![0; count];
+    let mut strip_end = vec![0; count];
+
+    for index in 0..count {
+        match tokens[index] {
+            Token::Tag(Tag::Value { .. }, _) | Token::Text(_) => continue,
+            Token::Tag(..) => {}
+        }
+
+        let before = if index == 0 { Some(0) } else {
+            match tokens[index - 1] {
+                Token::Text(ref text) => line_indentation(text, index == 1),
+                _ => None
+            }
+        };
+        let after = if index + 1 == count { Some(0) } else {
+            match tokens[index + 1] {
+                Token::Text(ref text) => line_rest(text, index + 2 == count),
+                _ => None
+            }
+        };
+
+        if let (Some(before), Some(after)) = (before, after) {
+            if index > 0 {
+                strip_end[index - 1] = before;
+            }
+            if index + 1 < count {
+                strip_start[index + 1] = after;
+            }
+        }
+    }
+
+    for (index, token) in tokens.iter_mut().enumerate() {
+        if let Token::Text(ref mut text) = *token {
+            let start = strip_start[index];
+            let end = if start + strip_end[index] > text.len() { start } else { text.len() - strip_end[index] };
+            *text = text[start..end].to_string();
+        }
+    }
+}
+
+// Private helper function to build the nodes of a block until its closing tag
+// Returns the nodes before and after {{else}}, depth is the number of blocks around this one
+fn parse_block(tokens: &[Token], index: &mut usize, block: Option<(&str, usize)>, depth: usize) -> Result<(Vec<Node>, Vec<Node>), String> {
+    let mut body = Vec::new();
+    let mut otherwise = Vec::new();
+    let mut in_else = false;
+
+    while *index < tokens.len() {
+        let token = &tokens[*index];
+        *index += 1;
+
+        let node = match *token {
+            Token::Text(ref text) if text.is_empty() => continue,
+            Token::Text(ref text) => Node::Text(text.clone()),
+            Token::Tag(ref tag, line) => match *tag {
+                Tag::Value { ref path, escape } => Node::Value { path: path.clone(), escape: escape },
+                Tag::Comment => continue,
+                Tag::Partial(ref name) => Node::Partial(name.clone()),
+                Tag::Else if block.is_some() && !in_else => {
+                    in_else = true;
+                    continue;
+                },
+                Tag::Else => return Err(format!("unexpected {{{{else}}}} (line {})", line)),
+                Tag::Close(ref name) => match block {
+                    Some((helper, _)) if helper == name => return Ok((body, otherwise)),
+                    _ => return Err(format!("unexpected {{{{/{}}}}} (line {})", name, line))
+                },
+                Tag::Open { .. } if depth >= MAX_BLOCK_DEPTH => return Err(format!("blocks nested too deep (line {})", line)),
+                Tag::Open { ref helper, ref path } => {
+                    let (inner_body, inner_otherwise) = parse_block(tokens, index, Some((helper, line)), depth + 1)?;
+                    Node::Block { helper: helper.clone(), path: path.clone(), body: inner_body, otherwise: inner_otherwise }
+                }
+            }
+        };
+
+        if in_else {
+            otherwise.push(node);
+        } else {
+            body.push(node);
+        }
+    }
+
+    match block {
+        Some((helper, line)) => Err(format!("unclosed block {{{{#{}}}}} (line {})", helper, line)),
+        None => Ok((body, otherwise))
+    }
+}
+
+impl Template {
+    /// Compile the template source, returns an error with the line number if the syntax is wrong
+    pub fn compile(source: &str) -> Result<Template, String> {
+        let mut tokens = tokenize(source)?;
+        remove_standalone_lines(&mut tokens);
+        let (nodes, _) = parse_block(&tokens, &mut 0, None, 0)?;
+
+        Ok(Template { nodes: nodes })
+    }
+}
+
+/// Public helper function to escape text before it is put in the page
+pub fn escape_html(text: &str) -> String {
+    let mut result = String::with_capacity(text.len());
+    escape_html_into(text, &mut result);
+    result
+}
+
+/// Public helper function to escape text and append it to the result in one pass, without a temporary string
+/// The text between the special characters is copied as one slice, so text without them is a single push_str
+pub fn escape_html_into(text: &str, result: &mut String) {
+    let mut start = 0;
+
+    for (i, c) in text.char_indices() {
+        let entity = match c {
+            '&' => "&amp;",
+            '<' => "&lt;",
+            '>' => "&gt;",
+            '"' => "&quot;",
+            _ => continue
+        };
+        result.push_str(&text[start..i]);
+        result.push_str(entity);
+        start = i + 1;
+    }
+    result.push_str(&text[start..]);
+}
+
+// Private helper function to decide if a block is rendered, like in Handlebars null, false, 0, "" and [] are false
+fn is_truthy(value: &Json) -> bool {
+    match *value {
+        Json::Null => false,
+        Json::Boolean(value) => value,
+        Json::I64(value) => value != 0,
+        Json::U64(value) => value != 0,
+        Json::F64(value) => value != 0.0,
+        Json::String(ref value) => !value.is_empty(),
+        Json::Array(ref values) => !values.is_empty(),
+        Json::Object(_) => true
+    }
+}
+
+// Private helper function to append the text of a value to the page, objects and arrays give an empty text
+// Numbers and booleans never contain special characters, so only strings are escaped
+fn write_value(value: &Json, escape: bool, result: &mut String) {
+    // Writing into a String can't fail
+    match *value {
+        Json::Boolean(value) => { let _ = write!(result, "{}", value); },
+        Json::I64(value) => { let _ = write!(result, "{}", value); },
+        Json::U64(value) => { let _ = write!(result, "{}", value); },
+        Json::F64(value) => { let _ = write!(result, "{}", value); },
+        Json::String(ref value) => if escape { escape_html_into(value, result) } else { result.push_str(value) },
+        Json::Null | Json::Array(_) | Json::Object(_) => ()
+    }
+}
+
+/// One level of the context: the value and the loop variables if it's an item of {{#each}}
+struct Frame<'a> {
+    value: &'a Json,
+    index: Option<usize>,
+    last: bool,
+    key: Option<&'a str>
+}
+
+// Private helper function to find the value of a path in the context, None if it doesn't exist
+fn lookup<'a>(frames: &[Frame<'a>], path: &ValuePath) -> Option<Cow<'a, Json>> {
+    let frame_index = if path.root { 0 } else { frames.len().checked_sub(1 + path.parents)? };
+
+    if let Some(ref data) = path.data {
+        let frame = frames[..frame_index + 1].iter().rev().find(|frame| frame.index.is_some())?;
+        return match &data[..] {
+            "index" => frame.index.map(|index| Cow::Owned(Json::U64(index as u64))),
+            "first" => Some(Cow::Owned(Json::Boolean(frame.index == Some(0)))),
+            "last" => Some(Cow::Owned(Json::Boolean(frame.last))),
+            _ => frame.key.map(|key| Cow::Owned(Json::String(key.to_string())))
+        };
+    }
+
+    let mut value = frames[frame_index].value;
+    for key in &path.keys {
+        value = value.find(key)?;
+    }
+
+    Some(Cow::Borrowed(value))
+}
+
+/// A set of templates that can use each other as partials
+#[derive(Debug, Clone, PartialEq)]
+pub struct Templates {
+    templates: BTreeMap<String, Template>
+}
+
+impl Templates {
+    /// Create an empty set of templates
+    pub fn new() -> Templates {
+        Templates {
+            templates: BTreeMap::new()
+        }
+    }
+
+    /// Compile the template and add it with the given name, an existing template with that name is replaced
+    pub fn register(&mut self, name: &str, source: &str) -> Result<(), String> {
+        let template = Template::compile(source).map_err(|err| format!("template '{}': {}", name, err))?;
+        self.templates.insert(name.to_string(), template);
+        Ok(())
+    }
+
+    /// Returns true if there is a template with the given name
+    pub fn contains(&self, name: &str) -> bool {
+        self.templates.contains_key(name)
+    }
+
+    /// Render the template with the given name, returns an error if the template or one of its partials doesn't exist
+    pub fn render(&self, name: &str, context: &Json) -> Result<String, String> {
+        self.render_with_capacity(name, context, 0)
+    }
+
+    /// Like render, but the page is written into a buffer of the given size, so a large page is not copied again and again while it grows
+    pub fn render_with_capacity(&self, name: &str, context: &Json, capacity: usize) -> Result<String, String> {
+        let template = self.templates.get(name).ok_or(format!("unknown template '{}'", name))?;
+        let mut frames = vec![Frame { value: context, index: None, last: false, key: None }];
+        let mut result = String::with_capacity(capacity);
+
+        self.render_nodes(&template.nodes, &mut frames, 0, &mut result).map_err(|err| format!("template '{}': {}", name, err))?;
+
+        Ok(result)
+    }
+
+    // Private helper function to render the nodes in the given context
+    fn render_nodes<'a>(&self, nodes: &[Node], frames: &mut Vec<Frame<'a>>, depth: usize, result: &mut String) -> Result<(), String> {
+        for node in nodes {
+            match *node {
+                Node::Text(ref text) => result.push_str(text),
+                Node::Value { ref path, escape } => {
+                    if let Some(value) = lookup(frames, path) {
+                        write_value(&value, escape, result);
+                    }
+                },
+                Node::Partial(ref name) => {
+                    let template = self.templates.get(name).ok_or(format!("unknown partial '{}'", name))?;
+                    if depth >= MAX_PARTIAL_DEPTH {
+                        return Err(format!("partials nested too deep in '{}'", name));
+                    }
+                    self.render_nodes(&template.nodes, frames, depth + 1, result)?;
+                },
+                Node::Block { ref helper, ref path, ref body, ref otherwise } => {
+                    let value = lookup(frames, path);
+                    let truthy = value.as_ref().map_or(false, |value| is_truthy(value));
+
+                    match &helper[..] {
+                        "if" => self.render_nodes(if truthy { body } else { otherwise }, frames, depth, result)?,
+                        "unless" => self.render_nodes(if truthy { otherwise } else { body }, frames, depth, result)?,
+                        "with" => match value {
+                            Some(Cow::Borrowed(value)) if truthy => {
+                                frames.push(Frame { value: value, index: None, last: false, key: None });
+                                let rendered = self.render_nodes(body, frames, depth, result);
+                                frames.pop();
+                                rendered?;
+                            },
+                            _ => self.render_nodes(otherwise, frames, depth, result)?
+                        },
+                        _ => {
+                            // Each item of an array or each value of an object, objects also set @key
+                            let items: Vec<(Option<&'a str>, &'a Json)> = match value {
+                                Some(Cow::Borrowed(&Json::Array(ref items))) => items.iter().map(|item| (None, item)).collect(),
+                                Some(Cow::Borrowed(&Json::Object(ref items))) => items.iter().map(|(key, item)| (Some(&key[..]), item)).collect(),
+                                _ => Vec::new()
+                            };
+
+                            if items.is_empty() {
+                                self.render_nodes(otherwise, frames, depth, result)?;
+                            }
+                            for (index, &(key, item)) in items.iter().enumerate() {
+                                frames.push(Frame { value: item, index: Some(index), last: index + 1 == items.len(), key: key });
+                                let rendered = self.render_nodes(body, frames, depth, result);
+                                frames.pop();
+                                rendered?;
+                            }
+                        }
+                    }
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Public helper function to compile the built-in templates
+pub fn builtin_templates() -> Templates {
+    let mut result = Templates::new();
+
+    for &(name, source) in BUILTIN_TEMPLATES.iter() {
+        result.register(name, source).expect("invalid built-in template");
+    }
+
+    result
+}
+
+/// Public helper function to load the templates in the given directory (all files with the extension "hbs")
+/// Templates with the name of a built-in template replace it, the other ones can be used as partials
+pub fn load_templates(template_dir: &str) -> Result<Templates, String> {
+    let mut result = builtin_templates();
+    let entries = read_dir(template_dir).map_err(|err| format!("could not read template directory '{}': {}", template_dir, err))?;
+
+    for entry in entries {
+        let path = entry.map_err(|err| format!("could not read template directory '{}': {}", template_dir, err))?.path();
+        if path.extension().map_or(true, |extension| extension != TEMPLATE_EXTENSION) {
+            continue;
+        }
+
+        let name = path.file_stem().and_then(|name| name.to_str()).ok_or(format!("invalid template file name '{}'", path.display()))?.to_string();
+        let mut source = String::new();
+        File::open(&path).and_then(|mut file| file.read_to_string(&mut source))
+            .map_err(|err| format!("could not read template '{}': {}", path.display(), err))?;
+        result.register(&name, &source)?;
+        info!("template '{}' loaded from '{}'", name, path.display());
+    }
+
+    Ok(result)
+}
+
+/// Public helper function to get the templates of the page: the built-in ones or the ones from the template directory
+/// If the templates in the directory can't be loaded, the error is logged and the built-in templates are used
+pub fn page_templates(template_dir: &Option<String>) -> Templates {
+    match *template_dir {
+        Some(ref template_dir) => load_templates(template_dir).unwrap_or_else(|err| {
+            error!("{}, using the built-in templates", err);
+            builtin_templates()
+        }),
+        None => builtin_templates()
+    }
+}
+
+#[cfg(test)]
+fn render_source(source: &str, context: &str) -> Result<String, String> {
+    let mut templates = Templates::new();
+    templates.register("test", source)?;
+    templates.render("test", &Json::from_str(context).unwrap())
+}
+
+#[test]
+fn test_render_values() {
+    assert_eq!(render_source("Hello {{name}}!", "{\"name\": \"World\"}"), Ok("Hello World!".to_string()));
+    assert_eq!(render_source("{{a.b}} {{a.c}} {{missing}}|{{n}} {{x}} {{t}}", "{\"a\": {\"b\": 1, \"c\": 0.5}, \"n\": null, \"x\": -2, \"t\": true}"),
+        Ok("1 0.5 | -2 true".to_string()));
+    assert_eq!(render_source("{{text}} {{{text}}}", "{\"text\": \"<b>&\\\"\"}"), Ok("&lt;b&gt;&amp;&quot; <b>&\"".to_string()));
+}
+
+#[test]
+fn test_escape_html() {
+    // Same result as escaping with one replace per character, the entities themselves are escaped again
+    for text in &["", "plain text", "<b>", "a & b", "\"quoted\"", "&amp;", "<<&&>>\"\"", "Grüße <äöü> & ☃", "&<>\""] {
+        let expected = text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;");
+        assert_eq!(escape_html(text), expected);
+
+        let mut result = "prefix ".to_string();
+        escape_html_into(text, &mut result);
+        assert_eq!(result, format!("prefix {}", expected));
+    }
+}
+
+#[test]
+fn test_render_with_capacity() {
+    let mut templates = Templates::new();
+    templates.register("test", "{{#each items}}<li>{{this}}</li>{{/each}}").unwrap();
+    let context = Json::from_str("{\"items\": [\"a<b\", 2, 1.5, false]}").unwrap();
+
+    let html = templates.render_with_capacity("test", &context, 1024).unwrap();
+    assert_eq!(html, "<li>a&lt;b</li><li>2</li><li>1.5</li><li>false</li>");
+    assert!(html.capacity() >= 1024);
+    assert_eq!(templates.render("test", &context), Ok(html));
+    assert!(templates.render_with_capacity("missing", &context, 1024).is_err());
+}
+
+#[test]
+fn test_render_blocks() {
+    let source = "{{#if a}}A{{else}}not A{{/if}} {{#unless b}}not B{{/unless}} {{#with c}}{{d}}{{else}}no C{{/with}}";
+    assert_eq!(render_source(source, "{\"a\": true, \"b\": 0, \"c\": {\"d\": \"D\"}}"), Ok("A not B D".to_string()));
+    assert_eq!(render_source(source, "{\"a\": [], \"b\": \"x\", \"c\": null}"), Ok("not A  no C".to_string()));
+}
+
+#[test]
+fn test_render_each() {
+    let source = "{{#each items}}{{@index}}:{{name}}{{#if @first}}(first){{/if}}{{#unless @last}}, {{/unless}}{{else}}none{{/each}}";
+    assert_eq!(render_source(source, "{\"items\": [{\"name\": \"a\"}, {\"name\": \"b\"}]}"), Ok("0:a(first), 1:b".to_string()));
+    assert_eq!(render_source(source, "{\"items\": []}"), Ok("none".to_string()));
+    assert_eq!(render_source("{{#each map}}{{@key}}={{this}};{{/each}}", "{\"map\": {\"x\": 1, \"y\": 2}}"), Ok("x=1;y=2;".to_string()));
+    // Values of the outer context via ../ and @root
+    assert_eq!(render_source("{{#each items}}{{../sep}}{{@root.sep}}{{this}}{{/each}}", "{\"sep\": \"-\", \"items\": [1, 2]}"), Ok("--1--2".to_string()));
+}
+
+#[test]
+fn test_render_standalone_lines() {
+    let source = "<ul>\n  {{#each items}}\n  <li>{{this}}</li>\n  {{/each}}\n</ul>\n{{! comment }}\n{{#if x}}x{{/if}}\n";
+    assert_eq!(render_source(source, "{\"items\": [1, 2], \"x\": true}"), Ok("<ul>\n  <li>1</li>\n  <li>2</li>\n</ul>\nx\n".to_string()));
+}
+
+#[test]
+fn test_render_partials() {
+    let mut templates = Templates::new();
+    templates.register("page", "<table>\n{{#each rows}}\n{{> row}}\n{{/each}}\n</table>\n").unwrap();
+    templates.register("row", "<tr><td>{{this}}</td></tr>\n").unwrap();
+    templates.register("loop", "{{> loop}}").unwrap();
+    templates.register("missing", "{{> nothing}}").unwrap();
+
+    assert_eq!(templates.render("page", &Json::from_str("{\"rows\": [1, 2]}").unwrap()), Ok("<table>\n<tr><td>1</td></tr>\n<tr><td>2</td></tr>\n</table>\n".to_string()));
+    assert!(templates.render("loop", &Json::Null).unwrap_err().contains("nested too deep"));
+    assert_eq!(templates.render("missing", &Json::Null), Err("template 'missing': unknown partial 'nothing'".to_string()));
+    assert_eq!(templates.render("other", &Json::Null), Err("unknown template 'other'".to_string()));
+}
+
+#[test]
+fn test_compile_errors() {
+    assert_eq!(Template::compile("{{#if a}}\n{{#each b}}{{/if}}"), Err("unexpected {{/if}} (line 2)".to_string()));
+    assert_eq!(Template::compile("\n{{#if a}}"), Err("unclosed block {{#if}} (line 2)".to_string()));
+    assert_eq!(Template::compile("{{name"), Err("unclosed tag (line 1)".to_string()));
+    assert_eq!(Template::compile("{{else}}"), Err("unexpected {{else}} (line 1)".to_string()));
+    assert_eq!(Template::compile("{{#foo a}}{{/foo}}"), Err("unknown helper 'foo' (line 1)".to_string()));
+    assert_eq!(Template::compile("{{#if}}{{/if}}"), Err("block helper needs exactly one argument: '#if' (line 1)".to_string()));
+    assert_eq!(Template::compile("{{a..b}}"), Err("invalid path 'a..b' (line 1)".to_string()));
+    assert_eq!(Template::compile("{{@foo}}"), Err("unknown variable '@foo' (line 1)".to_string()));
+}
+
+#[test]
+fn test_compile_nested_blocks() {
+    let nested = |depth: usize| format!("{}x{}", "{{#if a}}".repeat(depth), "{{/if}}".repeat(depth));
+    assert_eq!(render_source(&nested(MAX_BLOCK_DEPTH), "{\"a\": true}"), Ok("x".to_string()));
+    assert_eq!(Template::compile(&nested(MAX_BLOCK_DEPTH + 1)), Err("blocks nested too deep (line 1)".to_string()));
+    // Without the limit this overflows the stack
+    assert_eq!(Template::compile(&nested(100000)), Err("blocks nested too deep (line 1)".to_string()));
+}
+
+// Private helper function for the randomized tests, a linear congruential generator, so every run tests the same templates
+#[cfg(test)]
+fn next_random(seed: &mut u64, range: usize) -> usize {
+    *seed = seed.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+    (*seed >> 33) as usize % range
+}
+
+#[test]
+fn test_compile_random_sources() {
+    // Random sources glued together from pieces of the syntax, most of them are broken
+    // Compiling gives a template or an error and rendering a template never panics
+    const PIECES: [&'static str; 24] = ["{{", "}}", "{{{", "}}}", "#if ", "#each ", "#with ", "#unless ", "/if", "/each", "else", "> row", "> ",
+        "!", "a", ".b", "../", "@root", "@index", "this", "\n", " ", "ä☃", "\r\n"];
+    let context = Json::from_str("{\"a\": {\"b\": \"<b>\"}, \"this\": [1, 2]}").unwrap();
+    let mut templates = Templates::new();
+    templates.register("row", "<td>{{this}}</td>\n").unwrap();
+    let mut seed = 1;
+
+    for _ in 0..5000 {
+        let mut source = String::new();
+        for _ in 0..next_random(&mut seed, 40) {
+            source.push_str(PIECES[next_random(&mut seed, PIECES.len())]);
+        }
+
+        match Template::compile(&source) {
+            Ok(_) => {
+                templates.register("test", &source).unwrap();
+                if let Err(err) = templates.render("test", &context) {
+                    assert!(err.starts_with("template 'test': "), "{:?}: {}", source, err);
+                }
+            },
+            Err(err) => assert!(err.contains("(line "), "{:?}: {}", source, err)
+        }
+    }
+}
+
+// Private helper function that generates a random template without syntax errors and the page it must give
+// The context is the one of test_render_random_templates, scope is "root", "item" (in {{#each}}) or "with" (in {{#with}})
+#[cfg(test)]
+fn random_template(seed: &mut u64, scope: &str, depth: usize) -> (String, String) {
+    let mut source = String::new();
+    let mut expected = String::new();
+
+    for _ in 0..next_random(seed, 4) {
+        let choice = if depth >= 4 { next_random(seed, 4) } else { next_random(seed, 9) };
+        let (part, output) = match choice {
+            0 => ("x<ä☃>&".to_string(), "x<ä☃>&".to_string()),
+            1 => ("{{@root.text}}".to_string(), "&lt;a&gt; &amp; &quot;b&quot;".to_string()),
+            2 => ("{{{@root.text}}}".to_string(), "<a> & \"b\"".to_string()),
+            3 => match scope {
+                "item" => ("{{this}}".to_string(), "i".to_string()),
+                "with" => ("{{name}}".to_string(), "w".to_string()),
+                _ => ("{{missing}}".to_string(), String::new())
+            },
+            4 | 5 => {
+                let (body, body_output) = random_template(seed, scope, depth + 1);
+                let (otherwise, otherwise_output) = random_template(seed, scope, depth + 1);
+                let (helper, path, truthy) = [("if", "@root.text", true), ("if", "@root.empty", false), ("unless", "@root.items", false)][next_random(seed, 3)];
+                (format!("{{{{#{} {}}}}}{}{{{{else}}}}{}{{{{/{}}}}}", helper, path, body, otherwise, helper), if truthy { body_output } else { otherwise_output })
+            },
+            6 | 7 => {
+                let (body, body_output) = random_template(seed, "item", depth + 1);
+                (format!("{{{{#each @root.items}}}}{}{{{{/each}}}}", body), body_output.repeat(3))
+            },
+            _ => {
+                let (body, body_output) = random_template(seed, "with", depth + 1);
+                (format!("{{{{#with @root.object}}}}{}{{{{/with}}}}", body), body_output)
+            }
+        };
+        source.push_str(&part);
+        expected.push_str(&output);
+    }
+
+    (source, expected)
+}
+
+#[test]
+fn test_render_random_templates() {
+    let context = "{\"text\": \"<a> & \\\"b\\\"\", \"empty\": \"\", \"items\": [\"i\", \"i\", \"i\"], \"object\": {\"name\": \"w\"}}";
+    let mut seed = 1;
+
+    for _ in 0..2000 {
+        let (source, expected) = random_template(&mut seed, "root", 0);
+        assert_eq!(render_source(&source, context), Ok(expected), "{}", source);
+
+        // A comment on its own line is removed with the line, the page stays the same
+        let commented = format!("{{{{! comment }}}}\n{}\n  {{{{! comment }}}}  \n", source);
+        assert_eq!(render_source(&commented, context), render_source(&format!("{}\n", source), context), "{}", source);
+    }
+}
+
+#[test]
+fn test_render_random_values() {
+    // Values are escaped so that they can't add tags or leave an attribute, {{{value}}} is the value itself
+    const CHARS: [char; 10] = ['<', '>', '&', '"', '\'', 'a', 'ä', '☃', ' ', ';'];
+    let mut templates = Templates::new();
+    templates.register("escaped", "<td title=\"{{value}}\">{{value}}</td>").unwrap();
+    templates.register("raw", "{{{value}}}").unwrap();
+    let mut seed = 1;
+
+    for _ in 0..2000 {
+        let value: String = (0..next_random(&mut seed, 20)).map(|_| CHARS[next_random(&mut seed, CHARS.len())]).collect();
+        let mut context = BTreeMap::new();
+        context.insert("value".to_string(), Json::String(value.clone()));
+        let context = Json::Object(context);
+
+        let html = templates.render("escaped", &context).unwrap();
+        let inner = &html["<td title=\"".len()..html.len() - "</td>".len()];
+        assert!(!inner.replace("\">", "").contains(|c| c == '<' || c == '>' || c == '"'), "{}", html);
+        assert_eq!(html, format!("<td title=\"{}\">{}</td>", escape_html(&value), escape_html(&value)));
+        assert_eq!(templates.render("raw", &context), Ok(value));
+    }
+}
+
+#[test]
+fn test_builtin_templates() {
+    let templates = builtin_templates();
+    for &(name, _) in BUILTIN_TEMPLATES.iter() {
+        assert!(templates.contains(name));
+    }
+}
+
+#[test]
+fn test_builtin_templates_without_scripts() {
+    // The Content-Security-Policy (see security) blocks inline scripts, an onclick="..." would silently do nothing
+    for &(name, source) in BUILTIN_TEMPLATES.iter() {
+        let source = source.to_lowercase();
+        for (index, _) in source.match_indices(" on") {
+            let event: String = source[index + 3..].chars().take_while(|c| c.is_ascii_alphabetic()).collect();
+            let handler = !event.is_empty() && source[index + 3 + event.len()..].trim_start().starts_with('=');
+            assert!(!handler, "the template {} has an inline event handler on{}=", name, event);
+        }
+        assert!(!source.contains("<script"), "the template {} has a script", name);
+        assert!(!source.contains("javascript:"), "the template {} has a javascript: URL", name);
+    }
+}
+
+#[test]
+fn test_load_templates() {
+    use std::env::temp_dir;
+    use std::fs::{create_dir, remove_dir_all};
+    use std::io::Write;
+    use time::precise_time_ns;
+
+    let dir = temp_dir().join(format!("slurm_inspector_templates_{}", precise_time_ns()));
+    create_dir(&dir).unwrap();
+    File::create(dir.join("job_row.hbs")).unwrap().write_all(b"<tr><td>{{job_id}}</td></tr>\n").unwrap();
+    File::create(dir.join("footer.hbs")).unwrap().write_all(b"<p>footer</p>\n").unwrap();
+    File::create(dir.join("README.txt")).unwrap().write_all(b"{{#if").unwrap();
+    let dir_name = dir.to_str().unwrap().to_string();
+
+    let templates = load_templates(&dir_name).unwrap();
+    assert!(templates.contains("footer"));
+    assert!(templates.contains(STATUS_TEMPLATE));
+    assert!(!templates.contains("README"));
+    assert_eq!(templates.render("job_row", &Json::from_str("{\"job_id\": \"42\"}").unwrap()), Ok("<tr><td>42</td></tr>\n".to_string()));
+
+    // A broken template falls back to the built-in templates
+    File::create(dir.join("status.hbs")).unwrap().write_all(b"{{#if a}}").unwrap();
+    assert_eq!(load_templates(&dir_name), Err("template 'status': unclosed block {{#if}} (line 1)".to_string()));
+    assert_eq!(page_templates(&Some(dir_name)), builtin_templates());
+    assert_eq!(page_templates(&Some("/this/directory/does/not/exist".to_string())), builtin_templates());
+    remove_dir_all(&dir).unwrap();
+}
@@ -0,0 +1,215 @@
+//! Remaining time of the running jobs until their time limit (squeue %l)
+//! SLURM kills a job when it reaches its time limit, so jobs that are close to it are highlighted
+
+// System modules:
+use std::u64;
+
+/// A running job that has used more than this percentage of its time limit is highlighted orange
+pub const WARNING_PERCENT: f64 = 80.0;
+
+/// A running job that has used more than this percentage of its time limit is highlighted red
+pub const CRITICAL_PERCENT: f64 = 95.0;
+
+/// Public helper function to convert a SLURM duration into seconds
+/// The formats are "minutes", "minutes:seconds", "hours:minutes:seconds", "days-hours", "days-hours:minutes"
+/// and "days-hours:minutes:seconds", squeue prints the run time and the time limit in the last three
+pub fn duration_to_seconds(value: &str) -> Option<u64> {
+    let (days, time) = match value.find('-') {
+        Some(index) => (Some(value[..index].parse::<u64>().ok()?), &value[index + 1..]),
+        None => (None, value)
+    };
+
+    let parts = time.split(':').map(|part| part.parse::<u64>().ok()).collect::<Option<Vec<u64>>>()?;
+    let seconds = match (days.is_some(), parts.len()) {
+        (false, 1) => parts[0] * 60,
+        (false, 2) => parts[0] * 60 + parts[1],
+        (true, 1) => parts[0] * 3600,
+        (true, 2) => parts[0] * 3600 + parts[1] * 60,
+        (_, 3) => parts[0] * 3600 + parts[1] * 60 + parts[2],
+        _ => return None
+    };
+
+    Some(days.unwrap_or(0) * 86400 + seconds)
+}
+
+/// Public helper function to format seconds like squeue formats the run time: "[days-]hours:minutes:seconds"
+pub fn format_duration(seconds: u64) -> String {
+    let (days, hours, minutes, seconds) = (seconds / 86400, (seconds % 86400) / 3600, (seconds % 3600) / 60, seconds % 60);
+
+    if days > 0 {
+        format!("{}-{:02}:{:02}:{:02}", days, hours, minutes, seconds)
+    } else {
+        format!("{}:{:02}:{:02}", hours, minutes, seconds)
+    }
+}
+
+/// Public helper function to format seconds as short human readable text like "3d 4h 12m", for example for the uptime
+/// Seconds are only shown for durations below one minute, leading zero units are left out
+pub fn format_duration_short(seconds: u64) -> String {
+    let (days, hours, minutes) = (seconds / 86400, (seconds % 86400) / 3600, (seconds % 3600) / 60);
+
+    if days > 0 {
+        format!("{}d {}h {}m", days, hours, minutes)
+    } else if hours > 0 {
+        format!("{}h {}m", hours, minutes)
+    } else if minutes > 0 {
+        format!("{}m", minutes)
+    } else {
+        format!("{}s", seconds)
+    }
+}
+
+/// Time limit of a job as printed by squeue
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TimeLimit {
+    /// Time limit in seconds
+    Limited(u64),
+    Unlimited
+}
+
+/// Public helper function to parse the time limit, None for "INVALID" (the partition has no limit and the job none either)
+/// and other values that are not a duration
+pub fn parse_time_limit(value: &str) -> Option<TimeLimit> {
+    match value {
+        "UNLIMITED" | "INFINITE" => Some(TimeLimit::Unlimited),
+        _ => duration_to_seconds(value).map(TimeLimit::Limited)
+    }
+}
+
+/// Time until a running job reaches its time limit
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum RemainingTime {
+    /// Seconds until the limit (0 if the job is already over it) and the percentage of the limit that has been used
+    Limited { remaining: u64, percent_used: f64 },
+    Unlimited
+}
+
+impl RemainingTime {
+    /// Compute the remaining time from the run time and the time limit printed by squeue
+    /// None if one of them can't be parsed or the time limit is 0
+    pub fn new(run_time: &str, time_limit: &str) -> Option<RemainingTime> {
+        match parse_time_limit(time_limit)? {
+            TimeLimit::Unlimited => Some(RemainingTime::Unlimited),
+            TimeLimit::Limited(0) => None,
+            TimeLimit::Limited(limit) => {
+                let run_time = duration_to_seconds(run_time)?;
+                Some(RemainingTime::Limited {
+                    remaining: if limit > run_time { limit - run_time } else { 0 },
+                    percent_used: run_time as f64 * 100.0 / limit as f64
+                })
+            }
+        }
+    }
+
+    /// The remaining time as text, for example "0:12:00 (90%)", or "∞" for jobs without time limit
+    pub fn to_text(&self) -> String {
+        match *self {
+            RemainingTime::Limited{ remaining, percent_used } => format!("{} ({:.0}%)", format_duration(remaining), percent_used.floor()),
+            RemainingTime::Unlimited => "\u{221e}".to_string()
+        }
+    }
+
+    /// CSS class of jobs that are close to their time limit, jobs without time limit are never highlighted
+    pub fn css_class(&self) -> Option<String> {
+        match *self {
+            RemainingTime::Limited{ percent_used, .. } if percent_used > CRITICAL_PERCENT => Some("time_limit_critical".to_string()),
+            RemainingTime::Limited{ percent_used, .. } if percent_used > WARNING_PERCENT => Some("time_limit_warning".to_string()),
+            _ => None
+        }
+    }
+
+    /// Value for sorting by remaining time, jobs without time limit come last
+    pub fn sort_key(&self) -> u64 {
+        match *self {
+            RemainingTime::Limited{ remaining, .. } => remaining,
+            RemainingTime::Unlimited => u64::MAX
+        }
+    }
+}
+
+#[test]
+fn test_duration_to_seconds() {
+    assert_eq!(duration_to_seconds("30"), Some(1800));
+    assert_eq!(duration_to_seconds("2:46"), Some(166));
+    assert_eq!(duration_to_seconds("0:00"), Some(0));
+    assert_eq!(duration_to_seconds("1:02:03"), Some(3723));
+    assert_eq!(duration_to_seconds("2-12"), Some(2 * 86400 + 12 * 3600));
+    assert_eq!(duration_to_seconds("2-12:30"), Some(2 * 86400 + 12 * 3600 + 1800));
+    assert_eq!(duration_to_seconds("1-02:03:04"), Some(93784));
+    assert_eq!(duration_to_seconds("7-00:00:00"), Some(7 * 86400));
+    // squeue doesn't limit the hours of the run time to 23
+    assert_eq!(duration_to_seconds("36:00:00"), Some(36 * 3600));
+
+    assert_eq!(duration_to_seconds(""), None);
+    assert_eq!(duration_to_seconds("-"), None);
+    assert_eq!(duration_to_seconds("1-"), None);
+    assert_eq!(duration_to_seconds("-1:00"), None);
+    assert_eq!(duration_to_seconds("1:2:3:4"), None);
+    assert_eq!(duration_to_seconds("1::00"), None);
+    assert_eq!(duration_to_seconds("1-2-03:00"), None);
+    assert_eq!(duration_to_seconds("INVALID"), None);
+    assert_eq!(duration_to_seconds("N/A"), None);
+}
+
+#[test]
+fn test_format_duration() {
+    assert_eq!(format_duration(0), "0:00:00");
+    assert_eq!(format_duration(166), "0:02:46");
+    assert_eq!(format_duration(86399), "23:59:59");
+    assert_eq!(format_duration(93784), "1-02:03:04");
+}
+
+#[test]
+fn test_format_duration_short() {
+    assert_eq!(format_duration_short(0), "0s");
+    assert_eq!(format_duration_short(59), "59s");
+    assert_eq!(format_duration_short(60), "1m");
+    assert_eq!(format_duration_short(3599), "59m");
+    assert_eq!(format_duration_short(3600), "1h 0m");
+    assert_eq!(format_duration_short(86399), "23h 59m");
+    assert_eq!(format_duration_short(3 * 86400 + 4 * 3600 + 12 * 60 + 30), "3d 4h 12m");
+    assert_eq!(format_duration_short(400 * 86400), "400d 0h 0m");
+}
+
+#[test]
+fn test_parse_time_limit() {
+    assert_eq!(parse_time_limit("UNLIMITED"), Some(TimeLimit::Unlimited));
+    assert_eq!(parse_time_limit("1-00:00:00"), Some(TimeLimit::Limited(86400)));
+    assert_eq!(parse_time_limit("30:00"), Some(TimeLimit::Limited(1800)));
+    assert_eq!(parse_time_limit("INVALID"), None);
+    assert_eq!(parse_time_limit("Partition_Limit"), None);
+}
+
+#[test]
+fn test_remaining_time() {
+    let remaining = |run_time, time_limit| RemainingTime::new(run_time, time_limit);
+
+    assert_eq!(remaining("30:00", "1:00:00"), Some(RemainingTime::Limited{ remaining: 1800, percent_used: 50.0 }));
+    assert_eq!(remaining("0:00", "1-00:00:00"), Some(RemainingTime::Limited{ remaining: 86400, percent_used: 0.0 }));
+    assert_eq!(remaining("1-00:00:00", "1-00:00:00"), Some(RemainingTime::Limited{ remaining: 0, percent_used: 100.0 }));
+    // A job with OverTimeLimit can run longer than its limit
+    assert_eq!(remaining("1:10:00", "1:00:00").map(|remaining| remaining.sort_key()), Some(0));
+    assert_eq!(remaining("5:00", "UNLIMITED"), Some(RemainingTime::Unlimited));
+    // The run time doesn't matter without limit
+    assert_eq!(remaining("INVALID", "UNLIMITED"), Some(RemainingTime::Unlimited));
+    assert_eq!(remaining("5:00", "INVALID"), None);
+    assert_eq!(remaining("N/A", "1:00:00"), None);
+    assert_eq!(remaining("5:00", "0"), None);
+}
+
+#[test]
+fn test_remaining_time_highlight() {
+    let remaining = |run_time, time_limit| RemainingTime::new(run_time, time_limit).unwrap();
+
+    assert_eq!(remaining("48:00", "1:00:00").css_class(), None);
+    assert_eq!(remaining("48:00", "1:00:00").to_text(), "0:12:00 (80%)");
+    assert_eq!(remaining("48:01", "1:00:00").css_class(), Some("time_limit_warning".to_string()));
+    assert_eq!(remaining("57:00", "1:00:00").css_class(), Some("time_limit_warning".to_string()));
+    assert_eq!(remaining("57:01", "1:00:00").css_class(), Some("time_limit_critical".to_string()));
+    assert_eq!(remaining("57:01", "1:00:00").to_text(), "0:02:59 (95%)");
+    assert_eq!(remaining("2-00:00:00", "1-00:00:00").css_class(), Some("time_limit_critical".to_string()));
+    assert_eq!(remaining("2-00:00:00", "1-00:00:00").to_text(), "0:00:00 (200%)");
+    assert_eq!(remaining("100-00:00:00", "UNLIMITED").css_class(), None);
+    assert_eq!(remaining("100-00:00:00", "UNLIMITED").to_text(), "\u{221e}");
+    assert_eq!(remaining("100-00:00:00", "UNLIMITED").sort_key(), u64::MAX);
+}
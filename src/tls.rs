@@ -0,0 +1,105 @@
+//! HTTPS with a certificate and a private key (--tls-cert, --tls-key)
+//! Iron loads the files only when the server starts and then fails with an OpenSSL error that doesn't say which file is wrong,
+//! so both files are checked before: readable, valid PEM and the key belongs to the certificate. Iron uses OpenSSL through hyper,
+//! the same library is used for the check.
+
+// System modules:
+use std::error::Error;
+use std::fmt;
+use std::fs::File;
+use std::io::Read;
+
+// External modules:
+use openssl::crypto::pkey::PKey;
+use openssl::ssl::{SslContext, SslMethod};
+use openssl::x509::X509;
+
+/// Problem with the certificate or the key, the path is always included in the message
+#[derive(Debug, Clone, PartialEq)]
+pub enum TlsError {
+    /// The file can't be read: (path, error)
+    Unreadable(String, String),
+    /// The certificate file has no PEM certificate: (path, error)
+    InvalidCertificate(String, String),
+    /// The key file has no PEM private key: (path, error)
+    InvalidKey(String, String),
+    /// The key doesn't belong to the certificate: (certificate path, key path)
+    KeyMismatch(String, String)
+}
+
+impl fmt::Display for TlsError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            TlsError::Unreadable(ref path, ref err) => write!(f, "could not read '{}': {}", path, err),
+            TlsError::InvalidCertificate(ref path, ref err) => write!(f, "'{}' is not a PEM certificate: {}", path, err),
+            TlsError::InvalidKey(ref path, ref err) => write!(f, "'{}' is not a PEM private key: {}", path, err),
+            TlsError::KeyMismatch(ref cert, ref key) => write!(f, "the private key '{}' doesn't belong to the certificate '{}'", key, cert)
+        }
+    }
+}
+
+impl Error for TlsError {
+    fn description(&self) -> &str {
+        "invalid certificate or key"
+    }
+}
+
+// Private helper function that reads the whole file
+fn read_file(path: &str) -> Result<Vec<u8>, TlsError> {
+    let mut content = Vec::new();
+    File::open(path).and_then(|mut file| file.read_to_end(&mut content))
+        .map_err(|err| TlsError::Unreadable(path.to_string(), err.to_string()))?;
+    Ok(content)
+}
+
+/// Public helper function that checks the certificate and the key before the HTTPS server is started
+/// Only the first certificate of the file is checked (and used by the server)
+pub fn check_tls_files(cert_path: &str, key_path: &str) -> Result<(), TlsError> {
+    let cert = X509::from_pem(&mut &read_file(cert_path)?[..])
+        .map_err(|err| TlsError::InvalidCertificate(cert_path.to_string(), err.to_string()))?;
+    let key = PKey::private_key_from_pem(&mut &read_file(key_path)?[..])
+        .map_err(|err| TlsError::InvalidKey(key_path.to_string(), err.to_string()))?;
+
+    let mismatch = |_| TlsError::KeyMismatch(cert_path.to_string(), key_path.to_string());
+    let mut context = SslContext::new(SslMethod::Sslv23).map_err(&mismatch)?;
+    context.set_certificate(&cert).map_err(&mismatch)?;
+    context.set_private_key(&key).map_err(&mismatch)?;
+    context.check_private_key().map_err(&mismatch)
+}
+
+#[test]
+fn test_check_tls_files() {
+    let cert = "tests/fixtures/tls_cert.pem";
+    let key = "tests/fixtures/tls_key.pem";
+    let other_key = "tests/fixtures/tls_other_key.pem";
+
+    assert_eq!(check_tls_files(cert, key), Ok(()));
+    assert_eq!(check_tls_files(cert, other_key), Err(TlsError::KeyMismatch(cert.to_string(), other_key.to_string())));
+
+    match check_tls_files("tests/fixtures/missing.pem", key) {
+        Err(TlsError::Unreadable(ref path, _)) => assert_eq!(path, "tests/fixtures/missing.pem"),
+        result => panic!("unexpected result: {:?}", result)
+    }
+    match check_tls_files(cert, "tests/fixtures/missing.pem") {
+        Err(TlsError::Unreadable(ref path, _)) => assert_eq!(path, "tests/fixtures/missing.pem"),
+        result => panic!("unexpected result: {:?}", result)
+    }
+
+    // Certificate and key swapped
+    match check_tls_files(key, cert) {
+        Err(TlsError::InvalidCertificate(ref path, _)) => assert_eq!(path, key),
+        result => panic!("unexpected result: {:?}", result)
+    }
+    match check_tls_files(cert, cert) {
+        Err(TlsError::InvalidKey(ref path, _)) => assert_eq!(path, cert),
+        result => panic!("unexpected result: {:?}", result)
+    }
+}
+
+#[test]
+fn test_tls_error_display() {
+    assert_eq!(format!("{}", TlsError::KeyMismatch("cert.pem".to_string(), "key.pem".to_string())),
+        "the private key 'key.pem' doesn't belong to the certificate 'cert.pem'");
+    assert_eq!(format!("{}", TlsError::Unreadable("cert.pem".to_string(), "Permission denied".to_string())),
+        "could not read 'cert.pem': Permission denied");
+}
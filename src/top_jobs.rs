@@ -0,0 +1,131 @@
+//! The longest running and the longest waiting jobs, shown as two small lists above the job table and in /api/summary
+//! The run time is the one printed by squeue (%M), the wait time is computed from the submit time (see wait_times).
+//! Jobs whose time can't be parsed are left out, job steps are left out since their job is already in the list
+
+// System modules:
+use std::collections::BTreeMap;
+
+// External modules:
+use rustc_serialize::json::{Json, ToJson};
+
+// Internal modules:
+use squeue_util::{JobInfo, JobState, JobId};
+use job_arrays::run_time_to_seconds;
+
+/// Number of jobs in each list
+pub const TOP_JOBS: usize = 10;
+
+/// A job of one of the lists with its run or wait time
+#[derive(Debug, Clone, PartialEq)]
+pub struct TopJob {
+    pub cluster: String,
+    pub job_id: JobId,
+    pub user_name: String,
+    pub partition: String,
+    /// Run time of a running job or wait time of a pending job in seconds
+    pub seconds: u64
+}
+
+impl ToJson for TopJob {
+    fn to_json(&self) -> Json {
+        let mut result = BTreeMap::new();
+        result.insert("cluster".to_string(), self.cluster.to_json());
+        result.insert("job_id".to_string(), self.job_id.to_json());
+        result.insert("user_name".to_string(), self.user_name.to_json());
+        result.insert("partition".to_string(), self.partition.to_json());
+        result.insert("seconds".to_string(), self.seconds.to_json());
+        Json::Object(result)
+    }
+}
+
+// Private helper function that returns the count jobs with the longest times, ties are broken by the job id (lowest first)
+fn top_jobs<F>(jobs: &[JobInfo], count: usize, state: JobState, seconds: F) -> Vec<TopJob>
+        where F: Fn(&JobInfo) -> Option<u64> {
+    let mut result: Vec<TopJob> = jobs.iter()
+        .filter(|job| job.job_state == state)
+        .filter_map(|job| match (job.job_id.as_ref(), seconds(job)) {
+            (Some(job_id), Some(seconds)) if !job_id.is_step() => Some(TopJob {
+                cluster: job.cluster.clone(),
+                job_id: job_id.clone(),
+                user_name: job.user_name.clone(),
+                partition: job.partition.clone(),
+                seconds: seconds
+            }),
+            _ => None
+        })
+        .collect();
+
+    result.sort_by(|a, b| b.seconds.cmp(&a.seconds).then_with(|| a.job_id.cmp(&b.job_id)).then_with(|| a.cluster.cmp(&b.cluster)));
+    result.truncate(count);
+    result
+}
+
+/// Public helper function that returns the running jobs with the longest run time, longest first
+pub fn longest_running(jobs: &[JobInfo], count: usize) -> Vec<TopJob> {
+    top_jobs(jobs, count, JobState::Running, |job| job.run_time.as_ref().and_then(|run_time| run_time_to_seconds(run_time)))
+}
+
+/// Public helper function that returns the pending jobs with the longest wait time, longest first
+pub fn longest_waiting(jobs: &[JobInfo], count: usize) -> Vec<TopJob> {
+    top_jobs(jobs, count, JobState::Pending, |job| job.wait_time)
+}
+
+#[cfg(test)]
+fn test_job(job_id: &str, state: JobState, run_time: Option<&str>, wait_time: Option<u64>) -> JobInfo {
+    JobInfo {
+        run_time: run_time.map(|run_time| run_time.to_string()),
+        wait_time: wait_time,
+        .. ::squeue_util::test_job(job_id, state)
+    }
+}
+
+#[test]
+fn test_longest_running() {
+    let jobs = vec![
+        test_job("12", JobState::Running, Some("1:00:00"), None),
+        test_job("10", JobState::Running, Some("2-00:00:00"), None),
+        // Same run time as 12, the lower job id comes first
+        test_job("11", JobState::Running, Some("60:00"), None),
+        // Not parseable or missing
+        test_job("13", JobState::Running, Some("INVALID"), None),
+        test_job("14", JobState::Running, None, None),
+        // Job step and not running
+        test_job("10.batch", JobState::Running, Some("3-00:00:00"), None),
+        test_job("15", JobState::Pending, Some("5-00:00:00"), Some(100)),
+        test_job("16", JobState::Running, Some("0:05"), None)
+    ];
+
+    let ids = |top: Vec<TopJob>| top.iter().map(|job| job.job_id.to_string()).collect::<Vec<String>>();
+    assert_eq!(ids(longest_running(&jobs, 10)), vec!["10", "11", "12", "16"]);
+    assert_eq!(ids(longest_running(&jobs, 2)), vec!["10", "11"]);
+    assert_eq!(longest_running(&jobs, 1)[0].seconds, 172800);
+    assert_eq!(longest_running(&[], 10), Vec::new());
+}
+
+#[test]
+fn test_longest_waiting() {
+    let jobs = vec![
+        test_job("20", JobState::Pending, None, Some(600)),
+        test_job("22", JobState::Pending, None, Some(7200)),
+        test_job("21", JobState::Pending, None, Some(7200)),
+        // No parseable submit time
+        test_job("23", JobState::Pending, None, None),
+        test_job("24", JobState::Running, Some("10-00:00:00"), Some(86400)),
+        test_job("25_[1-10]", JobState::Pending, None, Some(60))
+    ];
+
+    let top = longest_waiting(&jobs, 10);
+    assert_eq!(top.iter().map(|job| job.job_id.to_string()).collect::<Vec<String>>(), vec!["21", "22", "20", "25_[1-10]"]);
+    assert_eq!(top.iter().map(|job| job.seconds).collect::<Vec<u64>>(), vec![7200, 7200, 600, 60]);
+    assert_eq!(longest_waiting(&jobs, 0), Vec::new());
+}
+
+#[test]
+fn test_top_job_to_json() {
+    let top = longest_waiting(&[test_job("21", JobState::Pending, None, Some(7200))], 10);
+    let json = top[0].to_json();
+
+    assert_eq!(json.find("job_id").and_then(|val| val.as_string()), Some("21"));
+    assert_eq!(json.find("seconds").and_then(|val| val.as_u64()), Some(7200));
+    assert!(json.find("user_name").is_some());
+}
@@ -0,0 +1,248 @@
+//! Unix domain socket listener (--listen-socket), an alternative to the TCP port for a reverse proxy on the same host
+//! Iron only listens on TCP addresses, so the socket is wrapped into a hyper listener and each request is passed to the same
+//! handler as on the TCP port. Who can connect is controlled by the permissions of the socket file (--socket-mode).
+//! The socket file is removed when slurm_inspector is stopped with SIGTERM or SIGINT
+
+// System modules:
+use std::fs::{metadata, remove_file, set_permissions, Permissions};
+use std::io::{self, Read, Write};
+use std::net::{Shutdown, SocketAddr, SocketAddrV4, Ipv4Addr};
+use std::os::raw::c_int;
+use std::os::unix::fs::{FileTypeExt, PermissionsExt};
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::path::{Path, PathBuf};
+use std::process;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread;
+use std::time::Duration;
+
+// External modules:
+use hyper;
+use hyper::net::{NetworkListener, NetworkStream, Fresh};
+use hyper::server::{Server, Listening, Handler as HttpHandler, Request as HttpRequest, Response as HttpResponse};
+use hyper::status::StatusCode;
+use iron::{Handler, Request, Protocol, Timeouts};
+
+/// Default permissions of the socket file: the owner and its group (for example the group of nginx) can connect
+pub const DEFAULT_SOCKET_MODE: u32 = 0o660;
+
+/// How often the shutdown thread checks for a signal
+const SIGNAL_POLL: u64 = 200;
+
+/// Linux signal numbers of Ctrl-C and of "kill" / systemctl stop
+const SIGINT: c_int = 2;
+const SIGTERM: c_int = 15;
+
+extern "C" {
+    fn signal(signum: c_int, handler: extern "C" fn(c_int)) -> usize;
+}
+
+/// Set by the signal handler, the shutdown thread does the actual work (a signal handler can't do much safely)
+static SHUTDOWN: AtomicBool = AtomicBool::new(false);
+
+extern "C" fn on_signal(_signum: c_int) {
+    SHUTDOWN.store(true, Ordering::SeqCst);
+}
+
+// Private helper function that returns the address of the clients on the socket
+// A unix socket has no IP address, the clients are on the same host, so they show up as 127.0.0.1
+// in the access log and for --allowed-ips
+fn socket_client_addr() -> SocketAddr {
+    SocketAddr::V4(SocketAddrV4::new(Ipv4Addr::new(127, 0, 0, 1), 0))
+}
+
+/// The socket file, it's removed when this is dropped
+#[derive(Debug)]
+pub struct SocketFile {
+    pub path: PathBuf
+}
+
+impl Drop for SocketFile {
+    fn drop(&mut self) {
+        if let Err(err) = remove_file(&self.path) {
+            warn!("Could not remove the socket '{}': {}", self.path.display(), err);
+        }
+    }
+}
+
+/// Public helper function that creates the socket with the given permissions (for example 0o660)
+/// A socket file left over from a killed run is replaced, but not a socket that another process still listens on or any other file
+pub fn bind_socket(path: &str, mode: u32) -> io::Result<(UnixListener, SocketFile)> {
+    if let Ok(existing) = metadata(path) {
+        if !existing.file_type().is_socket() {
+            return Err(io::Error::new(io::ErrorKind::AlreadyExists, format!("'{}' exists and is not a socket", path)));
+        }
+        if UnixStream::connect(path).is_ok() {
+            return Err(io::Error::new(io::ErrorKind::AddrInUse, format!("another process listens on '{}'", path)));
+        }
+        info!("removing the socket '{}' of an earlier run", path);
+        remove_file(path)?;
+    }
+
+    let listener = UnixListener::bind(path)?;
+    let file = SocketFile { path: Path::new(path).to_path_buf() };
+    set_permissions(path, Permissions::from_mode(mode))?;
+
+    Ok((listener, file))
+}
+
+/// Public helper function that removes the socket file and exits when SIGTERM or SIGINT arrives
+/// The web server threads never return, so this is the only clean way to stop with the socket
+pub fn remove_on_signal(file: SocketFile) {
+    unsafe {
+        signal(SIGINT, on_signal);
+        signal(SIGTERM, on_signal);
+    }
+
+    thread::spawn(move || {
+        while !SHUTDOWN.load(Ordering::SeqCst) {
+            thread::sleep(Duration::from_millis(SIGNAL_POLL));
+        }
+        info!("shutting down, removing the socket '{}'", file.path.display());
+        drop(file);
+        process::exit(0);
+    });
+}
+
+/// A connection on the socket
+pub struct SocketStream(UnixStream);
+
+impl Clone for SocketStream {
+    fn clone(&self) -> SocketStream {
+        // Like the TCP streams of hyper, a failed clone (out of file descriptors) can't be handled here
+        SocketStream(self.0.try_clone().expect("could not clone the socket connection"))
+    }
+}
+
+impl Read for SocketStream {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.0.read(buf)
+    }
+}
+
+impl Write for SocketStream {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.0.flush()
+    }
+}
+
+impl NetworkStream for SocketStream {
+    fn peer_addr(&mut self) -> io::Result<SocketAddr> {
+        Ok(socket_client_addr())
+    }
+
+    fn set_read_timeout(&self, duration: Option<Duration>) -> io::Result<()> {
+        self.0.set_read_timeout(duration)
+    }
+
+    fn set_write_timeout(&self, duration: Option<Duration>) -> io::Result<()> {
+        self.0.set_write_timeout(duration)
+    }
+
+    fn close(&mut self, how: Shutdown) -> io::Result<()> {
+        match self.0.shutdown(how) {
+            // The client has closed the connection already
+            Err(ref err) if err.kind() == io::ErrorKind::NotConnected => Ok(()),
+            result => result
+        }
+    }
+}
+
+/// The socket as listener of the hyper server
+#[derive(Clone)]
+pub struct SocketListener {
+    listener: Arc<UnixListener>,
+    /// Read and write timeouts of every connection, like Iron sets them on its TCP connections
+    timeouts: Timeouts
+}
+
+impl NetworkListener for SocketListener {
+    type Stream = SocketStream;
+
+    fn accept(&mut self) -> hyper::Result<SocketStream> {
+        let (stream, _) = self.listener.accept()?;
+        stream.set_read_timeout(self.timeouts.read)?;
+        stream.set_write_timeout(self.timeouts.write)?;
+        Ok(SocketStream(stream))
+    }
+
+    fn local_addr(&mut self) -> io::Result<SocketAddr> {
+        Ok(socket_client_addr())
+    }
+}
+
+/// Passes the requests of the hyper server to the Iron handler, like Iron does for its TCP listener
+struct SocketHandler<H> {
+    handler: H
+}
+
+impl<H: Handler> HttpHandler for SocketHandler<H> {
+    fn handle(&self, http_req: HttpRequest, mut http_res: HttpResponse<Fresh>) {
+        // In case the handler panics
+        *http_res.status_mut() = StatusCode::InternalServerError;
+
+        match Request::from_http(http_req, socket_client_addr(), &Protocol::Http) {
+            Ok(mut req) => self.handler.handle(&mut req).unwrap_or_else(|err| {
+                error!("Error handling a request on the socket: {:?}", err.error);
+                err.response
+            }).write_back(http_res),
+            Err(err) => {
+                warn!("Invalid request on the socket: {}", err);
+                *http_res.status_mut() = StatusCode::BadRequest;
+                if let Ok(res) = http_res.start() {
+                    let _ = res.end();
+                }
+            }
+        }
+    }
+}
+
+/// Public helper function that serves the Iron handler on the socket with the given number of threads and the read and write timeouts
+/// Like Iron::listen_with the server runs in other threads, dropping the returned guard waits for them
+pub fn listen_socket<H: Handler>(handler: H, listener: UnixListener, threads: usize, timeouts: Timeouts) -> hyper::Result<Listening> {
+    Server::new(SocketListener { listener: Arc::new(listener), timeouts: timeouts }).handle_threads(SocketHandler { handler: handler }, threads)
+}
+
+#[cfg(test)]
+fn temp_socket_path(name: &str) -> String {
+    use std::env::temp_dir;
+
+    temp_dir().join(format!("slurm_inspector_{}_{}.sock", name, process::id())).to_string_lossy().to_string()
+}
+
+#[test]
+fn test_bind_socket() {
+    let path = temp_socket_path("bind");
+    {
+        let (_listener, _file) = bind_socket(&path, 0o600).unwrap();
+        assert_eq!(metadata(&path).unwrap().permissions().mode() & 0o777, 0o600);
+        // Somebody listens on it
+        assert_eq!(bind_socket(&path, 0o600).unwrap_err().kind(), io::ErrorKind::AddrInUse);
+    }
+    // Removed when the file is dropped
+    assert!(metadata(&path).is_err());
+}
+
+#[test]
+fn test_bind_socket_stale() {
+    use std::fs::File;
+
+    // Left over from a killed run, nobody listens on it any more
+    let path = temp_socket_path("stale");
+    let (listener, file) = bind_socket(&path, 0o600).unwrap();
+    drop(listener);
+    ::std::mem::forget(file);
+    let (_listener, _file) = bind_socket(&path, 0o660).unwrap();
+    assert_eq!(metadata(&path).unwrap().permissions().mode() & 0o777, 0o660);
+
+    // Other files are never replaced
+    let path = temp_socket_path("regular");
+    File::create(&path).unwrap();
+    assert_eq!(bind_socket(&path, 0o600).unwrap_err().kind(), io::ErrorKind::AlreadyExists);
+    remove_file(&path).unwrap();
+}
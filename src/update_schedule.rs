@@ -0,0 +1,296 @@
+//! Schedule of the SLURM status thread
+//! The node information (sinfo) and the job information (squeue) are fetched with their own interval,
+//! a single loop sleeps until the next fetch is due or a refresh is requested
+//! A part whose update failed is retried sooner with exponential backoff, for example while slurmctld restarts
+
+// System modules:
+use std::cmp::min;
+
+/// Delay of the first retry after a failed update in seconds, it's doubled after each further failure
+pub const BACKOFF_START: u64 = 5;
+
+/// Public helper function that returns the number of seconds until the next update after the given number of failed updates in a row
+/// Without failures it's the normal interval, after a failure BACKOFF_START and then twice as long after each further failure,
+/// but never longer than the ceiling (the normal interval)
+pub fn backoff_delay(failures: u32, start: u64, ceiling: u64) -> u64 {
+    if failures == 0 {
+        return ceiling;
+    }
+
+    // 2^63 seconds are more than enough, larger shifts would overflow
+    min(start.saturating_mul(1 << min(failures - 1, 63)), ceiling)
+}
+
+/// Source of the current time, so the schedule can be tested without waiting
+pub trait Clock {
+    /// Current time in seconds since the epoch
+    fn now(&self) -> i64;
+    /// Wait for the given number of seconds, returns true if the wait was interrupted by a refresh request
+    fn sleep(&self, seconds: u64) -> bool;
+}
+
+/// The parts of the SLURM status that are fetched in one update
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DueUpdates {
+    /// Partition and node information (sinfo) and partition limits
+    pub node_info: bool,
+    /// Job information (squeue), finished jobs, scheduler statistics, fair-share information and reservations
+    pub job_info: bool
+}
+
+impl DueUpdates {
+    /// Fetch everything, for example for --once or a refresh
+    pub fn all() -> DueUpdates {
+        DueUpdates{ node_info: true, job_info: true }
+    }
+
+    /// True if at least one part has to be fetched
+    pub fn any(&self) -> bool {
+        self.node_info || self.job_info
+    }
+}
+
+/// Next due times of the node and job information, both are due right after the start
+#[derive(Debug, Clone, PartialEq)]
+pub struct UpdateSchedule {
+    node_interval: u64,
+    job_interval: u64,
+    next_node_update: Option<i64>,
+    next_job_update: Option<i64>,
+    /// Start of the last update, the retries count from there
+    last_start: i64,
+    /// Number of failed updates in a row of each part
+    node_failures: u32,
+    job_failures: u32
+}
+
+// Private helper function that checks if an update with the given next due time has to run now
+fn is_due(next_update: Option<i64>, now: i64) -> bool {
+    next_update.map_or(true, |next_update| next_update <= now)
+}
+
+impl UpdateSchedule {
+    /// Create a new schedule with the update intervals (in sec.) of the node and the job information
+    pub fn new(node_interval: u64, job_interval: u64) -> UpdateSchedule {
+        UpdateSchedule {
+            node_interval: node_interval,
+            job_interval: job_interval,
+            next_node_update: None,
+            next_job_update: None,
+            last_start: 0,
+            node_failures: 0,
+            job_failures: 0
+        }
+    }
+
+    /// The parts that are due at the given time
+    pub fn due(&self, now: i64) -> DueUpdates {
+        DueUpdates {
+            node_info: is_due(self.next_node_update, now),
+            job_info: is_due(self.next_job_update, now)
+        }
+    }
+
+    /// Number of seconds until the next part is due, 0 if one is due already
+    pub fn seconds_until_due(&self, now: i64) -> u64 {
+        match (self.next_node_update, self.next_job_update) {
+            (Some(next_node_update), Some(next_job_update)) => {
+                let next_update = min(next_node_update, next_job_update);
+                if next_update > now { (next_update - now) as u64 } else { 0 }
+            },
+            _ => 0
+        }
+    }
+
+    /// Schedule the next update of the given parts, counting from the time the update started
+    /// An update that takes longer than the interval is followed by the next one right away
+    pub fn mark_started(&mut self, due: DueUpdates, now: i64) {
+        self.last_start = now;
+        if due.node_info {
+            self.next_node_update = Some(now + self.node_interval as i64);
+        }
+        if due.job_info {
+            self.next_job_update = Some(now + self.job_interval as i64);
+        }
+    }
+
+    /// Record which of the updated parts failed: a failed part is retried with backoff (see backoff_delay),
+    /// counting from the start of the update, a successful update resets it to the normal interval
+    pub fn record_result(&mut self, due: DueUpdates, failed: DueUpdates) {
+        if due.node_info {
+            self.node_failures = if failed.node_info { self.node_failures + 1 } else { 0 };
+            self.next_node_update = Some(self.last_start + backoff_delay(self.node_failures, BACKOFF_START, self.node_interval) as i64);
+        }
+        if due.job_info {
+            self.job_failures = if failed.job_info { self.job_failures + 1 } else { 0 };
+            self.next_job_update = Some(self.last_start + backoff_delay(self.job_failures, BACKOFF_START, self.job_interval) as i64);
+        }
+    }
+
+    /// Sleep until at least one part is due, schedule its next update and return the due parts
+    /// A refresh request interrupts the sleep and everything is fetched right away
+    pub fn wait(&mut self, clock: &dyn Clock) -> DueUpdates {
+        loop {
+            if clock.sleep(self.seconds_until_due(clock.now())) {
+                self.mark_started(DueUpdates::all(), clock.now());
+                return DueUpdates::all();
+            }
+            let now = clock.now();
+            let due = self.due(now);
+            if due.any() {
+                self.mark_started(due, now);
+                return due;
+            }
+        }
+    }
+}
+
+// Clock for the tests: sleeping doesn't wait, it only advances the time
+// A refresh request interrupts the next sleep right away
+#[cfg(test)]
+pub struct TestClock {
+    pub time: ::std::cell::Cell<i64>,
+    pub refresh: ::std::cell::Cell<bool>
+}
+
+#[cfg(test)]
+impl TestClock {
+    pub fn new(time: i64) -> TestClock {
+        TestClock { time: ::std::cell::Cell::new(time), refresh: ::std::cell::Cell::new(false) }
+    }
+}
+
+#[cfg(test)]
+impl Clock for TestClock {
+    fn now(&self) -> i64 {
+        self.time.get()
+    }
+
+    fn sleep(&self, seconds: u64) -> bool {
+        if self.refresh.replace(false) {
+            return true;
+        }
+        self.time.set(self.time.get() + seconds as i64);
+        false
+    }
+}
+
+#[test]
+fn test_backoff_delay() {
+    // Growth: 5, 10, 20, 40 sec.
+    let delays: Vec<u64> = (1..5).map(|failures| backoff_delay(failures, 5, 300)).collect();
+    assert_eq!(delays, vec![5, 10, 20, 40]);
+    // Ceiling: never longer than the normal interval, even after many failures
+    assert_eq!(backoff_delay(7, 5, 300), 300);
+    assert_eq!(backoff_delay(1000, 5, 300), 300);
+    assert_eq!(backoff_delay(u32::max_value(), 5, 300), 300);
+    assert_eq!(backoff_delay(1, 5, 3), 3);
+    // Reset: without failures the normal interval
+    assert_eq!(backoff_delay(0, 5, 300), 300);
+}
+
+#[test]
+fn test_schedule_backoff() {
+    let clock = TestClock::new(0);
+    let mut schedule = UpdateSchedule::new(60, 60);
+    let failed = DueUpdates::all();
+    let ok = DueUpdates{ node_info: false, job_info: false };
+    let mut times = Vec::new();
+
+    // Three failed updates, then a successful one
+    for result in &[failed, failed, failed, ok, ok] {
+        let due = schedule.wait(&clock);
+        schedule.record_result(due, *result);
+        times.push(clock.now());
+    }
+    assert_eq!(times, vec![0, 5, 15, 35, 95]);
+    assert_eq!(schedule.seconds_until_due(clock.now()), 60);
+}
+
+#[test]
+fn test_schedule_backoff_one_part() {
+    let clock = TestClock::new(0);
+    let mut schedule = UpdateSchedule::new(60, 30);
+
+    // Only squeue fails, the node information keeps its interval
+    let due = schedule.wait(&clock);
+    schedule.record_result(due, DueUpdates{ node_info: false, job_info: true });
+    assert_eq!(schedule.wait(&clock), DueUpdates{ node_info: false, job_info: true });
+    assert_eq!(clock.now(), 5);
+    schedule.record_result(DueUpdates{ node_info: false, job_info: true }, DueUpdates{ node_info: false, job_info: true });
+    assert_eq!(schedule.wait(&clock), DueUpdates{ node_info: false, job_info: true });
+    assert_eq!(clock.now(), 15);
+    schedule.record_result(DueUpdates{ node_info: false, job_info: true }, DueUpdates{ node_info: false, job_info: false });
+    assert_eq!(schedule.wait(&clock), DueUpdates{ node_info: false, job_info: true });
+    assert_eq!(clock.now(), 45);
+    assert_eq!(schedule.wait(&clock), DueUpdates{ node_info: true, job_info: false });
+    assert_eq!(clock.now(), 60);
+}
+
+#[test]
+fn test_due_updates() {
+    assert!(DueUpdates::all().any());
+    assert!(DueUpdates{ node_info: false, job_info: true }.any());
+    assert!(!DueUpdates{ node_info: false, job_info: false }.any());
+}
+
+#[test]
+fn test_schedule_same_interval() {
+    let clock = TestClock::new(1000);
+    let mut schedule = UpdateSchedule::new(60, 60);
+
+    // Both parts are due right at the start and then always together
+    for step in 0..3 {
+        assert_eq!(schedule.wait(&clock), DueUpdates::all());
+        assert_eq!(clock.now(), 1000 + step * 60);
+    }
+}
+
+#[test]
+fn test_schedule_separate_intervals() {
+    let clock = TestClock::new(0);
+    let mut schedule = UpdateSchedule::new(30, 10);
+    let mut updates = Vec::new();
+
+    for _ in 0..7 {
+        let due = schedule.wait(&clock);
+        updates.push((clock.now(), due.node_info, due.job_info));
+    }
+
+    assert_eq!(updates, vec![(0, true, true), (10, false, true), (20, false, true), (30, true, true),
+        (40, false, true), (50, false, true), (60, true, true)]);
+}
+
+#[test]
+fn test_schedule_slow_update() {
+    let clock = TestClock::new(0);
+    let mut schedule = UpdateSchedule::new(20, 10);
+
+    assert_eq!(schedule.wait(&clock), DueUpdates::all());
+    // The update takes 15 sec., longer than the job interval
+    clock.sleep(15);
+    assert_eq!(schedule.seconds_until_due(clock.now()), 0);
+    assert_eq!(schedule.wait(&clock), DueUpdates{ node_info: false, job_info: true });
+    assert_eq!(clock.now(), 15);
+    // The next job update counts from 15 sec., the node update is still due at 20 sec.
+    assert_eq!(schedule.seconds_until_due(clock.now()), 5);
+    assert_eq!(schedule.wait(&clock), DueUpdates{ node_info: true, job_info: false });
+    assert_eq!(clock.now(), 20);
+    assert_eq!(schedule.wait(&clock), DueUpdates{ node_info: false, job_info: true });
+    assert_eq!(clock.now(), 25);
+}
+
+#[test]
+fn test_schedule_refresh() {
+    let clock = TestClock::new(0);
+    let mut schedule = UpdateSchedule::new(60, 30);
+
+    assert_eq!(schedule.wait(&clock), DueUpdates::all());
+    clock.sleep(5);
+    clock.refresh.set(true);
+    assert_eq!(schedule.wait(&clock), DueUpdates::all());
+    assert_eq!(clock.now(), 5);
+    // Both intervals start again at the refresh
+    assert_eq!(schedule.wait(&clock), DueUpdates{ node_info: false, job_info: true });
+    assert_eq!(clock.now(), 35);
+}
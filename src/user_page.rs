@@ -0,0 +1,199 @@
+//! Page of a single user (/user/<name>): the running, pending and finished jobs of the user and the totals at the top
+//! The job tables are rendered like on the status page, /api/users/<name> returns the same information as JSON
+
+// System modules:
+use std::collections::BTreeMap;
+
+// External modules:
+use rustc_serialize::json::{Json, ToJson};
+
+// Internal modules:
+use slurm_status::{SlurmStatus, PageOptions, usage_by_user, render_filtered_status};
+use squeue_util::{JobInfo, JobState};
+use status_filter::StatusFilter;
+use hostlist::compress_hostlist;
+use time_limit::format_duration_short;
+use template::USER_TEMPLATE;
+
+/// The pending job of a user that waits longest
+#[derive(Debug, Clone, PartialEq)]
+pub struct PendingJob {
+    pub job_id: String,
+    /// Time since the submission in seconds
+    pub wait_time: u64
+}
+
+impl ToJson for PendingJob {
+    fn to_json(&self) -> Json {
+        let mut result = BTreeMap::new();
+        result.insert("job_id".to_string(), self.job_id.to_json());
+        result.insert("wait_time".to_string(), self.wait_time.to_json());
+        result.insert("wait".to_string(), format_duration_short(self.wait_time).to_json());
+        Json::Object(result)
+    }
+}
+
+/// Totals of the jobs of a single user, shown at the top of the user page
+#[derive(Debug, Clone, PartialEq)]
+pub struct UserSummary {
+    pub user_name: String,
+    pub running_jobs: u32,
+    pub pending_jobs: u32,
+    /// Number of jobs that finished in the last --finished-hours
+    pub finished_jobs: u32,
+    /// Sum of CPUs of all running jobs, like in the "Usage by user" table
+    pub cpus_in_use: u32,
+    /// Nodes used by the jobs of the user as hostlist, for example "node[01-04]"
+    pub nodes: String,
+    /// None if no job of the user is pending (or the submit times are not known)
+    pub longest_pending: Option<PendingJob>
+}
+
+impl ToJson for UserSummary {
+    fn to_json(&self) -> Json {
+        let mut result = BTreeMap::new();
+        result.insert("user_name".to_string(), self.user_name.to_json());
+        result.insert("running_jobs".to_string(), self.running_jobs.to_json());
+        result.insert("pending_jobs".to_string(), self.pending_jobs.to_json());
+        result.insert("finished_jobs".to_string(), self.finished_jobs.to_json());
+        result.insert("cpus_in_use".to_string(), self.cpus_in_use.to_json());
+        result.insert("nodes".to_string(), self.nodes.to_json());
+        result.insert("longest_pending".to_string(), self.longest_pending.to_json());
+        Json::Object(result)
+    }
+}
+
+/// Public helper function that computes the totals of the jobs of the given user
+/// Returns None if the user has neither current nor finished jobs
+pub fn user_summary(status: &SlurmStatus, user_name: &str) -> Option<UserSummary> {
+    let jobs: Vec<JobInfo> = status.job_info.iter().filter(|job| job.user_name == user_name).cloned().collect();
+    let finished_jobs = status.finished_job_info.iter().filter(|job| job.user_name == user_name).count() as u32;
+
+    if jobs.is_empty() && finished_jobs == 0 {
+        return None;
+    }
+
+    // usage_by_user returns one entry for the jobs of a single user, or none without jobs
+    let usage = usage_by_user(&jobs).pop();
+    let nodes: Vec<String> = jobs.iter().flat_map(|job| job.list_of_nodes.iter().cloned()).collect();
+    let longest_pending = jobs.iter()
+        .filter(|job| job.job_state == JobState::Pending)
+        .filter_map(|job| job.wait_time.map(|wait_time| (job, wait_time)))
+        .max_by_key(|&(_, wait_time)| wait_time)
+        .map(|(job, wait_time)| PendingJob {
+            job_id: job.job_id.as_ref().map_or("-".to_string(), |job_id| job_id.to_string()),
+            wait_time: wait_time
+        });
+
+    Some(UserSummary {
+        user_name: user_name.to_string(),
+        running_jobs: usage.as_ref().map_or(0, |usage| usage.running_jobs),
+        pending_jobs: usage.as_ref().map_or(0, |usage| usage.pending_jobs),
+        finished_jobs: finished_jobs,
+        cpus_in_use: usage.as_ref().map_or(0, |usage| usage.cpus_in_use),
+        nodes: compress_hostlist(&nodes),
+        longest_pending: longest_pending
+    })
+}
+
+// Private helper function that returns the filter for the jobs of the given user, the other parameters of the query are kept
+fn user_filter(filter: &StatusFilter, user_name: &str) -> StatusFilter {
+    StatusFilter { user: Some(user_name.to_string()), .. filter.clone() }
+}
+
+/// Public helper function to render the page of a user, None if the user has no jobs
+/// The job tables are rendered like on the status page, all jobs of the user are shown on one page
+pub fn user_to_html(status: &SlurmStatus, options: &PageOptions, user_name: &str) -> Option<Result<String, String>> {
+    user_summary(status, user_name)
+        .map(|summary| render_filtered_status(status, options, user_filter(&options.filter, user_name), USER_TEMPLATE, summary.to_json()))
+}
+
+/// Public helper function that returns the totals and the current and finished jobs of a user as JSON (/api/users/<name>)
+/// None if the user has no jobs
+pub fn user_to_json(status: &SlurmStatus, filter: &StatusFilter, user_name: &str) -> Option<String> {
+    let summary = user_summary(status, user_name)?;
+    let filter = user_filter(filter, user_name);
+    let job_info: Vec<Json> = status.job_info.iter().filter(|job| filter.job_matches(job)).map(|job| job.to_json()).collect();
+    let finished_job_info: Vec<Json> = status.finished_job_info.iter().filter(|job| filter.finished_job_matches(job)).map(|job| job.to_json()).collect();
+    let mut result = BTreeMap::new();
+
+    result.insert("cluster_name".to_string(), status.cluster_name.to_json());
+    result.insert("last_update".to_string(), status.last_update.to_json());
+    result.insert("summary".to_string(), summary.to_json());
+    result.insert("job_info".to_string(), Json::Array(job_info));
+    result.insert("finished_job_info".to_string(), Json::Array(finished_job_info));
+
+    Some(Json::Object(result).to_string())
+}
+
+#[cfg(test)]
+fn test_status() -> SlurmStatus {
+    use configuration::Configuration;
+    use command_runner::TestRunner;
+    use slurm_status::update_slurm_status;
+
+    let config = Configuration{ test_mode: true, .. Configuration::default() };
+    let mut status = SlurmStatus::new();
+    update_slurm_status(&mut status, &config, &TestRunner::new(Err("must not be called".to_string())), None);
+    status
+}
+
+#[test]
+fn test_user_summary() {
+    let status = test_status();
+
+    let summary = user_summary(&status, "user01").unwrap();
+    assert_eq!(summary.user_name, "user01");
+    assert_eq!((summary.running_jobs, summary.pending_jobs, summary.finished_jobs, summary.cpus_in_use), (1, 0, 2, 2));
+    assert!(!summary.nodes.is_empty());
+    assert_eq!(summary.longest_pending, None);
+
+    let summary = user_summary(&status, "user02").unwrap();
+    assert_eq!((summary.running_jobs, summary.pending_jobs), (0, 1));
+
+    assert_eq!(user_summary(&status, "nobody"), None);
+}
+
+#[test]
+fn test_user_summary_longest_pending() {
+    let mut status = test_status();
+    for (job, wait_time) in status.job_info.iter_mut().zip(0..) {
+        job.user_name = "user09".to_string();
+        job.job_state = JobState::Pending;
+        job.wait_time = Some(wait_time * 3600);
+    }
+
+    let longest = user_summary(&status, "user09").unwrap().longest_pending.unwrap();
+    assert_eq!(longest.wait_time, (status.job_info.len() as u64 - 1) * 3600);
+    assert_eq!(Some(&longest.job_id), status.job_info.last().and_then(|job| job.job_id.as_ref()).map(|job_id| job_id.to_string()).as_ref());
+}
+
+#[test]
+fn test_user_to_html() {
+    let status = test_status();
+    let options = PageOptions::default();
+
+    let html = user_to_html(&status, &options, "user01").unwrap().unwrap();
+    assert!(html.contains("<h1>User user01</h1>"));
+    assert!(html.contains("<tr><th>Running jobs</th><td>1</td></tr>"));
+    assert!(html.contains("<tr><th>Longest waiting job</th><td>-</td></tr>"));
+    // Only the jobs of the user are in the tables
+    assert!(html.contains("<td>small_test01</td><td>user01</td>"));
+    assert!(!html.contains("user02"));
+
+    assert_eq!(user_to_html(&status, &options, "nobody"), None);
+}
+
+#[test]
+fn test_user_to_json() {
+    let status = test_status();
+
+    let json = Json::from_str(&user_to_json(&status, &StatusFilter::new(), "user02").unwrap()).unwrap();
+    assert_eq!(json.find_path(&["summary", "user_name"]).and_then(|value| value.as_string()), Some("user02"));
+    assert_eq!(json.find_path(&["summary", "pending_jobs"]).and_then(|value| value.as_u64()), Some(1));
+    let jobs = json.find("job_info").and_then(|value| value.as_array()).unwrap();
+    assert!(jobs.iter().all(|job| job.find("user_name").and_then(|value| value.as_string()) == Some("user02")));
+    assert_eq!(json.find("finished_job_info").and_then(|value| value.as_array()).map(|jobs| jobs.len()), Some(1));
+
+    assert_eq!(user_to_json(&status, &StatusFilter::new(), "nobody"), None);
+}
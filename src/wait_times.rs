@@ -0,0 +1,151 @@
+//! Wait time of the pending jobs: how long do jobs wait in the queue ?
+//! The wait time is the time since the submission (squeue %V), it's computed after each update
+//! and aggregated per partition for the partition table, /api/partitions and /api/summary
+
+// System modules:
+use std::collections::BTreeMap;
+
+// External modules:
+use rustc_serialize::json::{Json, ToJson};
+use time::strptime;
+
+// Internal modules:
+use squeue_util::{JobInfo, JobState};
+use time_limit::format_duration;
+
+/// Format of the submit time and the current time, SLURM_TIME_FORMAT=standard
+pub const TIME_FORMAT: &'static str = "%Y-%m-%dT%H:%M:%S";
+
+/// Public helper function that returns the number of seconds between the submit time and now (both in TIME_FORMAT)
+/// None if one of the times can't be parsed, a submit time in the future (clock skew) is a wait of 0 seconds
+pub fn wait_seconds(submit_time: &str, now: &str) -> Option<u64> {
+    let submit_time = strptime(submit_time, TIME_FORMAT).ok()?.to_timespec();
+    let now = strptime(now, TIME_FORMAT).ok()?.to_timespec();
+
+    Some(if now > submit_time { (now - submit_time).num_seconds() as u64 } else { 0 })
+}
+
+/// Public helper function that sets the wait time of every pending job, the other jobs don't wait
+pub fn set_wait_times(jobs: &mut [JobInfo], now: &str) {
+    for job in jobs.iter_mut() {
+        job.wait_time = match (job.job_state, job.submit_time.as_ref()) {
+            (JobState::Pending, Some(submit_time)) => wait_seconds(submit_time, now),
+            _ => None
+        };
+    }
+}
+
+/// Public helper function that returns the given percentile (0 - 100) of the sorted values with the nearest-rank method
+/// The result is always one of the values, None if there are no values
+pub fn percentile(sorted: &[u64], percent: u32) -> Option<u64> {
+    if sorted.is_empty() {
+        return None;
+    }
+
+    let rank = (percent as usize * sorted.len() + 99) / 100;
+    Some(sorted[if rank == 0 { 0 } else { rank - 1 }])
+}
+
+/// Shortest, median, 95th percentile and longest wait time of the pending jobs in seconds
+#[derive(Debug, Clone, PartialEq)]
+pub struct WaitStatistics {
+    /// Number of pending jobs with a known wait time
+    pub jobs: u32,
+    pub min: u64,
+    /// The lower one of the two middle values for an even number of jobs
+    pub median: u64,
+    pub p95: u64,
+    pub max: u64
+}
+
+impl WaitStatistics {
+    /// Compute the statistics of the given wait times, None if there are no wait times
+    pub fn new(wait_times: &[u64]) -> Option<WaitStatistics> {
+        let mut sorted = wait_times.to_vec();
+        sorted.sort();
+
+        Some(WaitStatistics {
+            jobs: sorted.len() as u32,
+            min: *sorted.first()?,
+            median: percentile(&sorted, 50)?,
+            p95: percentile(&sorted, 95)?,
+            max: *sorted.last()?
+        })
+    }
+
+    /// The statistics as short text, for example "0:10:00 / 1:00:00 / 2:30:00 / 3:00:00" (min / median / 95% / max)
+    pub fn to_text(&self) -> String {
+        [self.min, self.median, self.p95, self.max].iter().map(|&seconds| format_duration(seconds)).collect::<Vec<String>>().join(" / ")
+    }
+}
+
+impl ToJson for WaitStatistics {
+    fn to_json(&self) -> Json {
+        let mut result = BTreeMap::new();
+        result.insert("jobs".to_string(), self.jobs.to_json());
+        result.insert("min".to_string(), self.min.to_json());
+        result.insert("median".to_string(), self.median.to_json());
+        result.insert("p95".to_string(), self.p95.to_json());
+        result.insert("max".to_string(), self.max.to_json());
+        Json::Object(result)
+    }
+}
+
+#[test]
+fn test_wait_seconds() {
+    assert_eq!(wait_seconds("2015-12-18T09:00:00", "2015-12-18T10:30:15"), Some(5415));
+    assert_eq!(wait_seconds("2015-12-17T23:00:00", "2015-12-18T01:00:00"), Some(7200));
+    assert_eq!(wait_seconds("2015-12-18T09:00:00", "2015-12-18T09:00:00"), Some(0));
+    // Clock skew between slurmctld and this host
+    assert_eq!(wait_seconds("2015-12-18T10:00:05", "2015-12-18T10:00:00"), Some(0));
+    assert_eq!(wait_seconds("N/A", "2015-12-18T10:00:00"), None);
+    assert_eq!(wait_seconds("Unknown", "2015-12-18T10:00:00"), None);
+    assert_eq!(wait_seconds("2015-13-18T09:00:00", "2015-12-18T10:00:00"), None);
+    assert_eq!(wait_seconds("2015-12-18", "2015-12-18T10:00:00"), None);
+    assert_eq!(wait_seconds("2015-12-18T09:00:00", ""), None);
+}
+
+#[test]
+fn test_percentile() {
+    assert_eq!(percentile(&[], 50), None);
+    assert_eq!(percentile(&[7], 0), Some(7));
+    assert_eq!(percentile(&[7], 95), Some(7));
+    assert_eq!(percentile(&[1, 2, 3, 4], 50), Some(2));
+    assert_eq!(percentile(&[1, 2, 3, 4, 5], 50), Some(3));
+    assert_eq!(percentile(&[1, 2, 3, 4], 100), Some(4));
+
+    let values: Vec<u64> = (1..101).collect();
+    assert_eq!(percentile(&values, 0), Some(1));
+    assert_eq!(percentile(&values, 50), Some(50));
+    assert_eq!(percentile(&values, 95), Some(95));
+    assert_eq!(percentile(&values[..20], 95), Some(19));
+    assert_eq!(percentile(&values[..19], 95), Some(19));
+}
+
+#[test]
+fn test_wait_statistics() {
+    assert_eq!(WaitStatistics::new(&[]), None);
+
+    let statistics = WaitStatistics::new(&[3600, 60, 600, 86400 + 3723]).unwrap();
+    assert_eq!(statistics, WaitStatistics{ jobs: 4, min: 60, median: 600, p95: 90123, max: 90123 });
+    assert_eq!(statistics.to_text(), "0:01:00 / 0:10:00 / 1-01:02:03 / 1-01:02:03");
+    assert_eq!(statistics.to_json().to_string(), "{\"jobs\":4,\"max\":90123,\"median\":600,\"min\":60,\"p95\":90123}");
+}
+
+#[test]
+fn test_set_wait_times() {
+    use squeue_util::get_job_info_test;
+
+    let mut jobs = get_job_info_test();
+    jobs[0].job_state = JobState::Pending;
+    jobs[1].job_state = JobState::Pending;
+    jobs[1].submit_time = None;
+    set_wait_times(&mut jobs, "2000-01-01T10:00:00");
+
+    let wait_times: Vec<Option<u64>> = jobs.iter().map(|job| job.wait_time).collect();
+    assert_eq!(wait_times[0], Some(7200));
+    assert_eq!(wait_times[1], None);
+    // Job 8 is the only other pending job
+    assert_eq!(wait_times[7], Some(8088));
+    assert_eq!(wait_times.iter().filter(|wait_time| wait_time.is_some()).count(), 2);
+}
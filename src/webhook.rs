@@ -0,0 +1,289 @@
+//! Webhook notifications for nodes that go down and partitions that become unavailable
+//! The notifications are sent as JSON via HTTP POST from the SLURM status thread
+
+// System modules:
+use std::collections::BTreeMap;
+use std::time::Duration;
+
+// External modules:
+use rustc_serialize::json::{Json, ToJson};
+use hyper::Client;
+use hyper::status::StatusClass;
+use iron::headers::ContentType;
+use iron::mime::{Mime, TopLevel, SubLevel};
+
+// Internal modules:
+use sinfo_util::{NodeState, PartitionAvailability};
+use node_events::{NodeEvent, NodeEventKind, PartitionEvent};
+use error_log::{ErrorCategory, record_error};
+
+/// Maximum number of notifications that are kept for retry, the oldest ones are dropped first
+pub const MAX_PENDING_NOTIFICATIONS: usize = 100;
+
+/// Sends the JSON payload to the webhook URL, can be replaced in tests
+pub trait WebhookSender {
+    fn post_json(&self, url: &str, body: &str) -> Result<(), String>;
+}
+
+/// Sends the payload via HTTP POST using hyper
+pub struct HttpSender;
+
+impl WebhookSender for HttpSender {
+    fn post_json(&self, url: &str, body: &str) -> Result<(), String> {
+        let mut client = Client::new();
+        // Don't block the SLURM status thread for too long if the receiver hangs
+        client.set_read_timeout(Some(Duration::new(10, 0)));
+        client.set_write_timeout(Some(Duration::new(10, 0)));
+
+        match client.post(url).header(ContentType(Mime(TopLevel::Application, SubLevel::Json, vec![]))).body(body).send() {
+            Result::Ok(response) => {
+                if response.status.class() == StatusClass::Success {
+                    Ok(())
+                } else {
+                    Err(format!("unexpected status: {}", response.status))
+                }
+            },
+            Result::Err(err) => Err(format!("{}", err))
+        }
+    }
+}
+
+/// What the notification is about
+#[derive(Debug, Clone, PartialEq)]
+pub enum WebhookSubject {
+    Node(String),
+    Partition(String)
+}
+
+/// A single notification
+#[derive(Debug, Clone, PartialEq)]
+pub struct WebhookPayload {
+    pub subject: WebhookSubject,
+    pub old_state: String,
+    pub new_state: String,
+    /// Time of the update that detected the change, format: %Y.%m.%d - %H:%M
+    pub timestamp: String,
+    pub cluster: String
+}
+
+impl ToJson for WebhookPayload {
+    fn to_json(&self) -> Json {
+        let mut result = BTreeMap::new();
+        match self.subject {
+            WebhookSubject::Node(ref node) => result.insert("node".to_string(), node.to_json()),
+            WebhookSubject::Partition(ref partition) => result.insert("partition".to_string(), partition.to_json())
+        };
+        result.insert("old_state".to_string(), self.old_state.to_json());
+        result.insert("new_state".to_string(), self.new_state.to_json());
+        result.insert("timestamp".to_string(), self.timestamp.to_json());
+        result.insert("cluster".to_string(), self.cluster.to_json());
+        Json::Object(result)
+    }
+}
+
+/// Collects the notifications of each update and sends them to the webhook URL
+/// The same notification (subject and new state) is only sent once within the cooldown, so flapping nodes don't spam the receiver
+pub struct WebhookNotifier {
+    url: String,
+    cluster: String,
+    /// Cooldown in seconds
+    cooldown: i64,
+    /// Time of the last notification for each subject and new state
+    last_sent: BTreeMap<(String, String), i64>,
+    /// Notifications that have not been delivered yet
+    pending: Vec<WebhookPayload>
+}
+
+impl WebhookNotifier {
+    /// Create a new notifier for the given URL, cooldown is in seconds
+    pub fn new(url: &str, cluster: &str, cooldown: i64) -> WebhookNotifier {
+        WebhookNotifier {
+            url: url.to_string(),
+            cluster: cluster.to_string(),
+            cooldown: cooldown,
+            last_sent: BTreeMap::new(),
+            pending: Vec::new()
+        }
+    }
+
+    /// Notifications that have not been delivered yet
+    pub fn pending(&self) -> &[WebhookPayload] {
+        &self.pending
+    }
+
+    /// Queue a notification for every node that went down, failed or has been drained
+    pub fn queue_node_events(&mut self, events: &[NodeEvent], now: i64) {
+        for event in events {
+            if let NodeEventKind::StateChanged { from, to, .. } = event.kind {
+                match to {
+                    NodeState::Down | NodeState::Fail | NodeState::Drained => {
+                        self.queue(WebhookSubject::Node(event.node.clone()), from.to_string(), to.to_string(), &event.time, now);
+                    },
+                    _ => {}
+                }
+            }
+        }
+    }
+
+    /// Queue a notification for every partition that became unavailable
+    pub fn queue_partition_events(&mut self, events: &[PartitionEvent], now: i64) {
+        for event in events {
+            if event.to == PartitionAvailability::Down {
+                self.queue(WebhookSubject::Partition(event.partition.clone()), event.from.to_string(), event.to.to_string(), &event.time, now);
+            }
+        }
+    }
+
+    // Private helper function to add a notification unless the same one has been queued within the cooldown
+    fn queue(&mut self, subject: WebhookSubject, old_state: String, new_state: String, timestamp: &str, now: i64) {
+        let key = (format!("{:?}", subject), new_state.clone());
+
+        if let Some(last_time) = self.last_sent.get(&key) {
+            if now - last_time < self.cooldown {
+                debug!("Skip webhook notification within cooldown: {:?}", key);
+                return;
+            }
+        }
+
+        self.last_sent.insert(key, now);
+        self.pending.push(WebhookPayload {
+            subject: subject,
+            old_state: old_state,
+            new_state: new_state,
+            timestamp: timestamp.to_string(),
+            cluster: self.cluster.clone()
+        });
+
+        if self.pending.len() > MAX_PENDING_NOTIFICATIONS {
+            warn!("Too many undelivered webhook notifications, dropping the oldest one");
+            self.pending.remove(0);
+        }
+    }
+
+    /// Send all pending notifications, the ones that fail are kept and sent again on the next call
+    pub fn send_pending(&mut self, sender: &dyn WebhookSender) {
+        let pending = ::std::mem::replace(&mut self.pending, Vec::new());
+
+        for payload in pending {
+            match sender.post_json(&self.url, &payload.to_json().to_string()) {
+                Result::Ok(_) => debug!("Webhook notification sent: {:?}", payload),
+                Result::Err(err) => {
+                    error!("Could not send webhook notification to '{}': {}", self.url, err);
+                    record_error(ErrorCategory::Webhook, &err);
+                    self.pending.push(payload);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+struct TestSender {
+    fail: bool,
+    bodies: ::std::cell::RefCell<Vec<String>>
+}
+
+#[cfg(test)]
+impl WebhookSender for TestSender {
+    fn post_json(&self, url: &str, body: &str) -> Result<(), String> {
+        assert_eq!(url, "http://localhost/hook");
+        if self.fail {
+            Err("connection refused".to_string())
+        } else {
+            self.bodies.borrow_mut().push(body.to_string());
+            Ok(())
+        }
+    }
+}
+
+#[cfg(test)]
+fn test_node_event(from: NodeState, to: NodeState) -> NodeEvent {
+    use sinfo_util::ErrorCause;
+
+    NodeEvent {
+        time: "2000.01.01 - 03:00".to_string(),
+        node: "node07".to_string(),
+        kind: NodeEventKind::StateChanged { from: from, to: to, reason: ErrorCause::None }
+    }
+}
+
+#[test]
+fn test_webhook_payload_shape() {
+    let mut notifier = WebhookNotifier::new("http://localhost/hook", "cluster1", 600);
+    let sender = TestSender { fail: false, bodies: ::std::cell::RefCell::new(Vec::new()) };
+
+    notifier.queue_node_events(&[test_node_event(NodeState::Idle, NodeState::Down)], 0);
+    notifier.send_pending(&sender);
+
+    let bodies = sender.bodies.borrow();
+    assert_eq!(bodies.len(), 1);
+    let json = Json::from_str(&bodies[0]).unwrap();
+    assert_eq!(json.find("node").and_then(|val| val.as_string()), Some("node07"));
+    assert_eq!(json.find("old_state").and_then(|val| val.as_string()), Some("idle"));
+    assert_eq!(json.find("new_state").and_then(|val| val.as_string()), Some("down"));
+    assert_eq!(json.find("timestamp").and_then(|val| val.as_string()), Some("2000.01.01 - 03:00"));
+    assert_eq!(json.find("cluster").and_then(|val| val.as_string()), Some("cluster1"));
+    assert_eq!(json.as_object().map(|object| object.len()), Some(5));
+    assert_eq!(notifier.pending().len(), 0);
+}
+
+#[test]
+fn test_webhook_ignores_harmless_states() {
+    let mut notifier = WebhookNotifier::new("http://localhost/hook", "cluster1", 600);
+
+    notifier.queue_node_events(&[test_node_event(NodeState::Idle, NodeState::Allocated), test_node_event(NodeState::Down, NodeState::Idle)], 0);
+    assert_eq!(notifier.pending().len(), 0);
+}
+
+#[test]
+fn test_webhook_cooldown() {
+    let mut notifier = WebhookNotifier::new("http://localhost/hook", "cluster1", 600);
+
+    // The node flaps between idle and down
+    notifier.queue_node_events(&[test_node_event(NodeState::Idle, NodeState::Down)], 0);
+    notifier.queue_node_events(&[test_node_event(NodeState::Down, NodeState::Idle)], 60);
+    notifier.queue_node_events(&[test_node_event(NodeState::Idle, NodeState::Down)], 120);
+    assert_eq!(notifier.pending().len(), 1);
+
+    // A different new state is a different notification
+    notifier.queue_node_events(&[test_node_event(NodeState::Down, NodeState::Drained)], 180);
+    assert_eq!(notifier.pending().len(), 2);
+
+    notifier.queue_node_events(&[test_node_event(NodeState::Idle, NodeState::Down)], 600);
+    assert_eq!(notifier.pending().len(), 3);
+}
+
+#[test]
+fn test_webhook_partition_down() {
+    let mut notifier = WebhookNotifier::new("http://localhost/hook", "cluster1", 600);
+    let event = PartitionEvent {
+        time: "t".to_string(),
+        partition: "esd".to_string(),
+        from: PartitionAvailability::Up,
+        to: PartitionAvailability::Down
+    };
+
+    notifier.queue_partition_events(&[event.clone(), PartitionEvent { from: PartitionAvailability::Down, to: PartitionAvailability::Up, .. event }], 0);
+
+    assert_eq!(notifier.pending().len(), 1);
+    assert_eq!(notifier.pending()[0].subject, WebhookSubject::Partition("esd".to_string()));
+    assert_eq!(notifier.pending()[0].to_json().find("partition").and_then(|val| val.as_string()), Some("esd"));
+}
+
+#[test]
+fn test_webhook_retry_after_failure() {
+    let mut notifier = WebhookNotifier::new("http://localhost/hook", "cluster1", 600);
+    let failing = TestSender { fail: true, bodies: ::std::cell::RefCell::new(Vec::new()) };
+    let working = TestSender { fail: false, bodies: ::std::cell::RefCell::new(Vec::new()) };
+
+    notifier.queue_node_events(&[test_node_event(NodeState::Idle, NodeState::Fail)], 0);
+    notifier.send_pending(&failing);
+    assert_eq!(notifier.pending().len(), 1);
+    // The failed delivery shows up at /debug/errors
+    assert!(::error_log::error_recorder().entries().iter()
+        .any(|entry| entry.category == ErrorCategory::Webhook && entry.message == "connection refused"));
+
+    notifier.send_pending(&working);
+    assert_eq!(notifier.pending().len(), 0);
+    assert_eq!(working.bodies.borrow().len(), 1);
+}
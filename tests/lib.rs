@@ -1,4 +1,6 @@
 extern crate slurm_util;
+extern crate iron;
+extern crate rustc_serialize;
 
 // use slurm_util::sinfo_util::*;
 
@@ -7,3 +9,159 @@ extern crate slurm_util;
 fn test_lib() {
     assert!(true);
 }
+
+#[test]
+fn test_log_rotation() {
+    use std::env::temp_dir;
+    use std::fs::{create_dir, remove_dir_all, metadata};
+    use slurm_util::logging::RotatingFile;
+
+    let dir = temp_dir().join(format!("slurm_inspector_rotation_{}", std::process::id()));
+    create_dir(&dir).unwrap();
+    let path = dir.join("slurm_inspector.log");
+    let mut file = RotatingFile::open(&path, 100, 7).unwrap();
+
+    // 15 lines of 10 bytes don't fit into 100 bytes
+    for _ in 0..15 {
+        file.write_line("log line\n\n").unwrap();
+    }
+
+    assert!(file.rotated_path(1).exists());
+    assert_eq!(metadata(file.rotated_path(1)).unwrap().len(), 100);
+    assert_eq!(metadata(&path).unwrap().len(), 50);
+    assert!(!file.rotated_path(2).exists());
+    remove_dir_all(&dir).unwrap();
+}
+
+// Talks plain HTTP over the unix socket like a reverse proxy does
+#[test]
+fn test_listen_socket() {
+    use std::env::temp_dir;
+    use std::fs::metadata;
+    use std::io::{Read, Write};
+    use std::os::unix::net::UnixStream;
+    use iron::prelude::{Request, Response};
+    use iron::status;
+    use slurm_util::unix_socket::{bind_socket, listen_socket};
+    use slurm_util::request_limit::http_timeouts;
+
+    let path = temp_dir().join(format!("slurm_inspector_listen_{}.sock", std::process::id())).to_string_lossy().to_string();
+    let (listener, socket_file) = bind_socket(&path, 0o600).unwrap();
+    let mut listening = listen_socket(|req: &mut Request| {
+        let mut res = Response::new();
+        res.status = Some(status::Ok);
+        res.body = Some(Box::new(format!("path: {}", req.url.path.join("/"))));
+        Ok(res)
+    }, listener, 2, http_timeouts(30, 30)).unwrap();
+
+    let mut stream = UnixStream::connect(&path).unwrap();
+    stream.write_all(b"GET /health HTTP/1.0\r\nHost: localhost\r\n\r\n").unwrap();
+    let mut response = String::new();
+    stream.read_to_string(&mut response).unwrap();
+    assert!(response.starts_with("HTTP/1.0 200 OK\r\n") || response.starts_with("HTTP/1.1 200 OK\r\n"), "{}", response);
+    assert!(response.ends_with("\r\n\r\npath: health"), "{}", response);
+
+    listening.close().unwrap();
+    drop(socket_file);
+    assert!(metadata(&path).is_err());
+}
+
+#[test]
+fn test_listen_socket_read_timeout() {
+    use std::env::temp_dir;
+    use std::io::{Read, Write};
+    use std::os::unix::net::UnixStream;
+    use std::time::{Duration, Instant};
+    use iron::prelude::{Request, Response};
+    use iron::status;
+    use slurm_util::unix_socket::{bind_socket, listen_socket};
+    use slurm_util::request_limit::http_timeouts;
+
+    let path = temp_dir().join(format!("slurm_inspector_timeout_{}.sock", std::process::id())).to_string_lossy().to_string();
+    let (listener, socket_file) = bind_socket(&path, 0o600).unwrap();
+    let mut listening = listen_socket(|_: &mut Request| {
+        let mut res = Response::new();
+        res.status = Some(status::Ok);
+        res.body = Some(Box::new("ok"));
+        Ok(res)
+    }, listener, 1, http_timeouts(1, 1)).unwrap();
+
+    // The idle client doesn't send anything, the server must give up on it and serve the next one
+    let start = Instant::now();
+    let idle = UnixStream::connect(&path).unwrap();
+    idle.set_read_timeout(Some(Duration::from_secs(10))).unwrap();
+    let mut stream = UnixStream::connect(&path).unwrap();
+    stream.set_read_timeout(Some(Duration::from_secs(10))).unwrap();
+    stream.write_all(b"GET /health HTTP/1.0\r\nHost: localhost\r\n\r\n").unwrap();
+    let mut response = String::new();
+    let result = stream.read_to_string(&mut response);
+    // Close the idle client and the server before the asserts, otherwise a failed test would wait for the server thread forever
+    drop(idle);
+    listening.close().unwrap();
+    drop(socket_file);
+    result.unwrap();
+    assert!(response.ends_with("\r\n\r\nok"), "{}", response);
+    assert!(start.elapsed() < Duration::from_secs(10));
+}
+
+// The HTML page of a large synthetic cluster must fit into the estimated buffer, so the buffer never grows while the page is rendered
+#[test]
+fn test_status_to_html_capacity() {
+    use slurm_util::configuration::Configuration;
+    use slurm_util::slurm_status::{SlurmStatus, PageOptions, update_slurm_status, status_to_html, estimated_page_size, create_runner};
+    use slurm_util::synthetic_data::SyntheticData;
+
+    let data = SyntheticData{ nodes: 200, partitions: 8, jobs: 1500, seed: 1 };
+    let config = Configuration{ test_mode: true, test_data: Some(data), page_limit: 0, .. Configuration::default() };
+    let mut status = SlurmStatus::new();
+    update_slurm_status(&mut status, &config, &*create_runner(&config), None);
+    let options = PageOptions::new(&config);
+    let estimate = estimated_page_size(&status);
+
+    let html = status_to_html(&status, &options).unwrap();
+    assert!(html.len() <= estimate, "{} bytes, estimate: {}", html.len(), estimate);
+    assert!(html.capacity() >= html.len());
+    assert_eq!(status_to_html(&status, &options).unwrap(), html);
+}
+
+// Benchmark of the HTML rendering with a large synthetic cluster, it's ignored by default since it takes a few seconds
+// It compares the page rendered into the preallocated buffer with the same page rendered into a buffer that grows
+// Run it with: cargo test --release --test lib -- --ignored --nocapture bench_status_to_html
+#[test]
+#[ignore]
+fn bench_status_to_html() {
+    use slurm_util::configuration::Configuration;
+    use slurm_util::slurm_status::{SlurmStatus, PageOptions, update_slurm_status, status_to_html, estimated_page_size, create_runner};
+    use slurm_util::status_page::status_page;
+    use slurm_util::scontrol_util::{reservation_time, UPCOMING_RESERVATION_HOURS};
+    use slurm_util::synthetic_data::SyntheticData;
+    use slurm_util::template::STATUS_TEMPLATE;
+    use rustc_serialize::json::ToJson;
+    use std::time::{Duration, Instant};
+
+    const RUNS: u32 = 10;
+
+    let data = SyntheticData{ nodes: 2000, partitions: 8, jobs: 15000, seed: 1 };
+    let config = Configuration{ test_mode: true, test_data: Some(data), page_limit: 0, .. Configuration::default() };
+    let mut status = SlurmStatus::new();
+    update_slurm_status(&mut status, &config, &*create_runner(&config), None);
+    let options = PageOptions::new(&config);
+    let page = status_page(&status, &options, &reservation_time(0), &reservation_time(UPCOMING_RESERVATION_HOURS * 3600)).to_json();
+    let ms = |elapsed: Duration| (elapsed.as_secs() * 1000) as f64 + elapsed.subsec_nanos() as f64 / 1e6;
+
+    let html = status_to_html(&status, &options).unwrap();
+    let start = Instant::now();
+    for _ in 0..RUNS {
+        assert_eq!(options.templates.render_with_capacity(STATUS_TEMPLATE, &page, estimated_page_size(&status)).unwrap().len(), html.len());
+    }
+    let preallocated = ms(start.elapsed());
+
+    let start = Instant::now();
+    for _ in 0..RUNS {
+        assert_eq!(options.templates.render(STATUS_TEMPLATE, &page).unwrap().len(), html.len());
+    }
+    let growing = ms(start.elapsed());
+
+    println!("status_to_html: {} jobs, {} nodes, {} bytes", status.job_info.len(), status.node_info.len(), html.len());
+    println!("preallocated buffer: {:.1} ms per page, growing buffer: {:.1} ms per page", preallocated / RUNS as f64, growing / RUNS as f64);
+}